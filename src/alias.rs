@@ -0,0 +1,139 @@
+//! `git-wiz alias install|uninstall|status`: run the tool as `git wiz`.
+//!
+//! The alias is a shell alias in the global git config (`alias.wiz = !git-wiz`), so git
+//! runs the binary with the remaining arguments. `--absolute` stores the resolved path
+//! of this binary instead, for setups where git's shell sees a different PATH. An
+//! existing `wiz` alias that runs something else is only replaced with `--force`.
+//! `GIT_CONFIG_GLOBAL` is honored, as by git itself.
+
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+use crate::read_only;
+
+const KEY: &str = "alias.wiz";
+const BIN: &str = "git-wiz";
+
+/// What `alias.wiz` is set to in the global git config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasStatus {
+    NotInstalled,
+    /// Runs git-wiz (by name or by path).
+    Installed(String),
+    /// Set to something else.
+    Other(String),
+}
+
+/// The current state of the `wiz` alias.
+pub fn status() -> Result<AliasStatus> {
+    Ok(match current()? {
+        None => AliasStatus::NotInstalled,
+        Some(value) if runs_git_wiz(&value) => AliasStatus::Installed(value),
+        Some(value) => AliasStatus::Other(value),
+    })
+}
+
+/// Set `alias.wiz` and return its new value. Replaces an unrelated alias only with `force`.
+pub fn install(absolute: bool, force: bool) -> Result<String> {
+    read_only::check("Changing the git config")?;
+    let value = alias_value(absolute)?;
+    if let AliasStatus::Other(existing) = status()? {
+        if !force {
+            bail!(
+                "`git wiz` is already an alias for `{}`. Use --force to replace it.",
+                existing
+            );
+        }
+    }
+    git_config(&["--global", KEY, &value])?;
+    Ok(value)
+}
+
+/// Remove `alias.wiz`. Returns false when there was none; an unrelated alias is only
+/// removed with `force`.
+pub fn uninstall(force: bool) -> Result<bool> {
+    read_only::check("Changing the git config")?;
+    match status()? {
+        AliasStatus::NotInstalled => return Ok(false),
+        AliasStatus::Other(existing) if !force => bail!(
+            "`git wiz` runs `{}`, not git-wiz. Use --force to remove it anyway.",
+            existing
+        ),
+        _ => {}
+    }
+    git_config(&["--global", "--unset", KEY])?;
+    Ok(true)
+}
+
+/// `argv` without a leading `wiz` argument, in case an alias passes the alias name on.
+pub fn strip_alias_arg(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    if args.get(1).is_some_and(|a| a == "wiz") {
+        args.remove(1);
+    }
+    args
+}
+
+fn current() -> Result<Option<String>> {
+    let o = Command::new("git")
+        .args(["config", "--global", "--get", KEY])
+        .output()
+        .context("Failed to run git config")?;
+    // Exit code 1: the key is not set.
+    if o.status.code() == Some(1) {
+        return Ok(None);
+    }
+    if !o.status.success() {
+        bail!(
+            "git config --global --get {} failed: {}",
+            KEY,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(Some(String::from_utf8_lossy(&o.stdout).trim().to_string()))
+}
+
+fn git_config(args: &[&str]) -> Result<()> {
+    let o = Command::new("git")
+        .arg("config")
+        .args(args)
+        .output()
+        .context("Failed to run git config")?;
+    if !o.status.success() {
+        bail!(
+            "git config {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// `!git-wiz`, or `!"/abs/path/git-wiz"` with `absolute`.
+fn alias_value(absolute: bool) -> Result<String> {
+    if !absolute {
+        return Ok(format!("!{}", BIN));
+    }
+    let exe = std::env::current_exe().context("Could not resolve the git-wiz binary path")?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    // Git runs aliases with sh, also on Windows: forward slashes, quoted for spaces.
+    let path = exe.display().to_string().replace('\\', "/");
+    Ok(format!("!\"{}\"", path))
+}
+
+/// True when a shell alias runs git-wiz, by name or by path.
+fn runs_git_wiz(value: &str) -> bool {
+    let Some(command) = value.strip_prefix('!') else {
+        return false;
+    };
+    let command = command.trim_start();
+    let program = match command.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+        None => command.split_whitespace().next().unwrap_or_default(),
+    };
+    Path::new(program)
+        .file_stem()
+        .is_some_and(|stem| stem == BIN)
+}
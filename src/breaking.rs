@@ -0,0 +1,289 @@
+//! Heuristic breaking-change detection for Rust diffs.
+//!
+//! A removed `pub` item (`fn`, `struct`, `enum`, `trait`, `type`, `const`, `static`, `mod`)
+//! counts as breaking unless the same line comes back elsewhere in the diff (a move). If
+//! an item of the same kind and name comes back with a different line, its signature
+//! changed. Restricted visibility (`pub(crate)`, `pub(super)`) and test files are ignored,
+//! and a rename shows up as the old name being removed.
+//!
+//! When something is found, the prompt asks for the `!` marker and a `BREAKING CHANGE:`
+//! footer, and the UIs warn next to the preview. `detect_breaking: false` in the config
+//! turns the check off.
+
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::config::{CommitStyle, Config};
+
+/// Changes listed in the warning before the rest are summed up.
+const LISTED: usize = 3;
+
+/// A public item the diff removes or changes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BreakingChange {
+    pub kind: String,
+    pub name: String,
+    pub path: String,
+    /// The item is still there with another signature.
+    pub changed: bool,
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = if self.changed { "changed" } else { "removed" };
+        write!(
+            f,
+            "{} `pub {} {}` ({})",
+            what, self.kind, self.name, self.path
+        )
+    }
+}
+
+/// `pub` item declarations: visibility, then kind and name.
+fn item_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"^\s*pub(\([^)]*\))?\s+(?:(?:async|unsafe|const|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|type|const|static|mod)\s+(?:mut\s+)?([A-Za-z_][A-Za-z0-9_]*)"#,
+        )
+        .expect("valid regex")
+    })
+}
+
+/// `(kind, name)` of the public item `line` declares, if any.
+fn public_item(line: &str) -> Option<(String, String)> {
+    let caps = item_regex().captures(line)?;
+    if caps.get(1).is_some() {
+        return None;
+    }
+    Some((caps[2].to_string(), caps[3].to_string()))
+}
+
+fn is_test_path(path: &str) -> bool {
+    path.starts_with("tests/")
+        || path.contains("/tests/")
+        || path.ends_with("/tests.rs")
+        || path.ends_with("_test.rs")
+        || path.ends_with("_tests.rs")
+}
+
+/// Public items `diff` removes or changes, in path order.
+pub fn detect(diff: &str) -> Vec<BreakingChange> {
+    let mut removed: Vec<(String, String, String, String)> = Vec::new();
+    let mut added: BTreeSet<String> = BTreeSet::new();
+    let mut added_items: BTreeSet<(String, String)> = BTreeSet::new();
+
+    let mut path = String::new();
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            path = rest.rsplit_once(" b/").map_or(rest, |(_, b)| b).to_string();
+            continue;
+        }
+        if !path.ends_with(".rs") || is_test_path(&path) {
+            continue;
+        }
+        if line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('-') {
+            if let Some((kind, name)) = public_item(text) {
+                removed.push((kind, name, path.clone(), text.trim().to_string()));
+            }
+        } else if let Some(text) = line.strip_prefix('+') {
+            if let Some(item) = public_item(text) {
+                added.insert(text.trim().to_string());
+                added_items.insert(item);
+            }
+        }
+    }
+
+    let mut changes: Vec<BreakingChange> = removed
+        .into_iter()
+        .filter(|(_, _, _, text)| !added.contains(text))
+        .map(|(kind, name, path, _)| BreakingChange {
+            changed: added_items.contains(&(kind.clone(), name.clone())),
+            kind,
+            name,
+            path,
+        })
+        .collect();
+    changes.sort();
+    changes.dedup();
+    changes
+}
+
+/// `detect`, unless `cfg` turned the check off.
+pub fn for_config(diff: &str, cfg: Option<&Config>) -> Vec<BreakingChange> {
+    if cfg.is_some_and(|c| !c.detect_breaking) {
+        return Vec::new();
+    }
+    detect(diff)
+}
+
+/// The prompt sentence asking the model to flag `changes` as breaking.
+pub fn hint(changes: &[BreakingChange], style: CommitStyle) -> String {
+    let how = match style {
+        CommitStyle::Conventional => {
+            "add `!` after the type (and scope) and a `BREAKING CHANGE:` footer that says what \
+             callers must change"
+        }
+        CommitStyle::Gitmoji => "use 💥 and say in the body what callers must change",
+        CommitStyle::Plain => "say in the body that it breaks the API and what callers must change",
+    };
+    format!("This is a breaking change ({}): {}.", summary(changes), how)
+}
+
+/// e.g. `This looks like a breaking change: removed `pub fn parse` (src/lib.rs).`
+pub fn warning(changes: &[BreakingChange]) -> Option<String> {
+    (!changes.is_empty())
+        .then(|| format!("This looks like a breaking change: {}.", summary(changes)))
+}
+
+fn summary(changes: &[BreakingChange]) -> String {
+    let mut listed: Vec<String> = changes
+        .iter()
+        .take(LISTED)
+        .map(ToString::to_string)
+        .collect();
+    if changes.len() > LISTED {
+        listed.push(format!("{} more", changes.len() - LISTED));
+    }
+    listed.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A one-hunk diff of `path` removing `removed` and adding `added`.
+    fn diff(path: &str, removed: &[&str], added: &[&str]) -> String {
+        let mut text =
+            format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -1,3 +1,3 @@\n");
+        for line in removed {
+            text.push_str(&format!("-{}\n", line));
+        }
+        for line in added {
+            text.push_str(&format!("+{}\n", line));
+        }
+        text
+    }
+
+    /// The prompt sentence a Conventional Commits prompt gets for `diff`, if any.
+    fn conventional_hint(diff: &str) -> Option<String> {
+        let changes = detect(diff);
+        (!changes.is_empty()).then(|| hint(&changes, CommitStyle::Conventional))
+    }
+
+    fn assert_asks_for_markers(hint: &str) {
+        assert!(hint.contains("add `!` after the type"), "{}", hint);
+        assert!(hint.contains("a `BREAKING CHANGE:` footer"), "{}", hint);
+    }
+
+    #[test]
+    fn removed_pub_item_asks_for_the_markers() {
+        let diff = diff(
+            "src/lib.rs",
+            &["pub fn parse(input: &str) -> Ast {", "pub struct Ast;"],
+            &["fn parse(input: &str) -> Ast {"],
+        );
+        let hint = conventional_hint(&diff).unwrap();
+        assert_asks_for_markers(&hint);
+        assert!(
+            hint.contains("removed `pub fn parse` (src/lib.rs)"),
+            "{}",
+            hint
+        );
+        assert!(
+            hint.contains("removed `pub struct Ast` (src/lib.rs)"),
+            "{}",
+            hint
+        );
+        assert_eq!(
+            warning(&detect(&diff)).as_deref(),
+            Some(
+                "This looks like a breaking change: removed `pub fn parse` (src/lib.rs), \
+                 removed `pub struct Ast` (src/lib.rs)."
+            )
+        );
+    }
+
+    #[test]
+    fn private_only_removals_ask_for_nothing() {
+        let private = diff(
+            "src/lib.rs",
+            &[
+                "fn helper() {}",
+                "pub(crate) fn internal() {}",
+                "    pub(super) struct Inner;",
+            ],
+            &[],
+        );
+        // Test files are not part of the API.
+        let tests = diff("tests/api.rs", &["pub fn fixture() {}"], &[]);
+        for diff in [private, tests] {
+            assert_eq!(detect(&diff), []);
+            assert_eq!(conventional_hint(&diff), None);
+            assert_eq!(warning(&detect(&diff)), None);
+        }
+    }
+
+    #[test]
+    fn renames_and_signature_changes_are_breaking_but_moves_are_not() {
+        let rename = diff("src/api.rs", &["pub fn load() {}"], &["pub fn read() {}"]);
+        assert_eq!(
+            detect(&rename),
+            [BreakingChange {
+                kind: "fn".to_string(),
+                name: "load".to_string(),
+                path: "src/api.rs".to_string(),
+                changed: false,
+            }]
+        );
+        assert_asks_for_markers(&conventional_hint(&rename).unwrap());
+
+        let signature = diff(
+            "src/api.rs",
+            &["pub async fn load(path: &Path) {}"],
+            &["pub async fn load(path: &Path, strict: bool) {}"],
+        );
+        let hint = conventional_hint(&signature).unwrap();
+        assert_asks_for_markers(&hint);
+        assert!(
+            hint.contains("changed `pub fn load` (src/api.rs)"),
+            "{}",
+            hint
+        );
+
+        let moved = diff("src/a.rs", &["pub fn load() {}"], &[])
+            + &diff("src/b.rs", &[], &["pub fn load() {}"]);
+        assert_eq!(conventional_hint(&moved), None);
+    }
+
+    #[test]
+    fn non_rust_files_are_not_checked() {
+        let diff = diff("README.md", &["pub fn parse() {}"], &[])
+            + &diff("src/app.py", &["def parse(input):"], &["def read(input):"]);
+        assert_eq!(detect(&diff), []);
+        assert_eq!(conventional_hint(&diff), None);
+        assert_eq!(warning(&detect(&diff)), None);
+    }
+
+    #[test]
+    fn other_styles_and_the_config_switch() {
+        let diff = diff("src/lib.rs", &["pub enum Mode {"], &[]);
+        let changes = detect(&diff);
+        let gitmoji = hint(&changes, CommitStyle::Gitmoji);
+        assert!(gitmoji.contains("use 💥") && !gitmoji.contains("BREAKING CHANGE:"));
+
+        let mut cfg = Config::new(
+            crate::config::Provider::Gemini,
+            String::new(),
+            String::new(),
+        );
+        assert_eq!(for_config(&diff, Some(&cfg)), changes);
+        cfg.detect_breaking = false;
+        assert_eq!(for_config(&diff, Some(&cfg)), []);
+    }
+}
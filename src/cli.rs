@@ -0,0 +1,3012 @@
+//! Classic CLI: argument parsing, subcommands, and the interactive menu.
+//!
+//! This module is built with the `cli` feature and does not depend on the TUI stack,
+//! so `cargo build --no-default-features --features cli` produces a fully working binary.
+//!
+//! Flows:
+//! - `run_generate_flow`: pick diff source, pre-flight summary, generate, review, commit
+//! - `run_stage_flow` / `run_diff_flow` / `run_push_flow`: thin wrappers over `git`
+//! - `run_release_flow`: tag-based release via the `release` module
+
+use anyhow::{bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use cliclack::{confirm, input, multiselect, outro, select};
+use colored::*;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::alias::{self, AliasStatus};
+use crate::breaking;
+use crate::completions::{self, Shell, Values};
+use crate::config::{self, AutoPush, Config, IssueKeyword};
+use crate::conflicts;
+use crate::conventional;
+use crate::doctor;
+use crate::generator::{self, Generator, ModelUnavailable};
+use crate::git::{self, DiffSource, Operation, RefKind};
+use crate::history;
+use crate::hook::{self, HookOutcome};
+use crate::message::{self, FormatOptions};
+use crate::notify;
+use crate::offline;
+use crate::pr;
+use crate::prompt::{self, PromptOptions, PromptSizes, Refinement};
+use crate::read_only;
+use crate::release;
+use crate::release_notes;
+use crate::repo_config;
+use crate::review;
+use crate::runtime;
+use crate::scope;
+use crate::serve;
+use crate::setup;
+use crate::split;
+use crate::state::{self, ReleaseChoice};
+use crate::ui;
+use crate::usage;
+
+#[derive(Parser, Debug)]
+#[command(name = "git-wiz", version, about)]
+pub struct Args {
+    /// Use the mock generator (no API calls, no credits used)
+    #[arg(long, global = true)]
+    pub mock: bool,
+
+    /// Like --mock, for when there is no network: each message says it is a placeholder
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Extra context passed to the model for the first generation
+    #[arg(long, global = true)]
+    pub hint: Option<String>,
+
+    /// Compact commit preview without the box (for narrow terminals)
+    #[arg(long, global = true)]
+    pub compact: bool,
+
+    /// Run the setup wizard before starting
+    #[arg(long)]
+    pub config: bool,
+
+    /// Disable everything that changes the repository or config (generation still works)
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Repository to work in (default: the one containing the current directory)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub repo: Option<PathBuf>,
+
+    /// Use the classic interactive menu instead of the full-screen TUI
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub menu: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Generate a commit message for the current changes and commit it
+    #[command(after_long_help = "\
+post_generate_command (config.json): a shell command run on every generated message.
+It receives the message on stdin and runs in the repository root. Exit 0 accepts the
+message; non-empty stdout replaces it. A non-zero exit rejects it and stderr is shown.
+Commands running longer than 10 seconds are killed and count as a rejection.")]
+    Generate {
+        /// Which changes to describe
+        #[arg(long, value_enum, default_value_t = SourceArg::Staged)]
+        source: SourceArg,
+
+        /// Commit the generated message without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Bump the version, commit, tag and push (patch, minor, major or X.Y.Z)
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Release {
+        #[arg(required = true)]
+        bump: Option<String>,
+
+        /// Skip the final confirmation (and the release notes step)
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Bump `[workspace.package].version` instead of the package's own version
+        #[arg(long)]
+        workspace: bool,
+
+        /// Leave out a preflight check: fmt, clippy, test, or an extra command line from
+        /// `.gitwiz.toml` (repeatable; noted in the release commit)
+        #[arg(long = "skip-preflight", value_name = "NAME")]
+        skip_preflight: Vec<String>,
+
+        #[command(subcommand)]
+        action: Option<ReleaseCommand>,
+    },
+    /// Run the setup wizard (provider, API key, model)
+    Setup,
+    /// Diagnose git, config, provider access and remote setup
+    Doctor {
+        /// Skip checks that need the network (provider, remote)
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Show local usage statistics from the usage ledger
+    Usage {
+        /// Show p50/p95 latency per model
+        #[arg(long)]
+        latency: bool,
+
+        /// Number of recent calls per model to include
+        #[arg(long, default_value_t = usage::DEFAULT_WINDOW)]
+        last: usize,
+    },
+    /// Serve line-delimited JSON-RPC for editor integrations
+    Serve {
+        /// Use stdin/stdout as the transport (currently the only transport)
+        #[arg(long)]
+        stdio: bool,
+    },
+    /// Print a shell completion script (bash, zsh or fish)
+    #[command(after_long_help = "\
+Load it from your shell's startup file, e.g.:
+  bash: source <(git-wiz completions bash)
+  zsh:  source <(git-wiz completions zsh)
+  fish: git-wiz completions fish | source")]
+    Completions { shell: Shell },
+    /// Install or remove the `git wiz` alias (global git config)
+    Alias {
+        #[command(subcommand)]
+        action: AliasCommand,
+    },
+    /// Print tags for the completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete { kind: Values },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReleaseCommand {
+    /// Draft GitHub release notes for an existing tag
+    Notes { tag: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AliasCommand {
+    /// Set `alias.wiz` so `git wiz` runs git-wiz
+    Install {
+        /// Store the absolute path of this binary (when git's shell has a different PATH)
+        #[arg(long)]
+        absolute: bool,
+
+        /// Replace an existing `wiz` alias that runs something else
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the `git wiz` alias
+    Uninstall {
+        /// Remove the `wiz` alias even if it doesn't run git-wiz
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show whether `git wiz` is set up
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceArg {
+    Staged,
+    Unstaged,
+    Both,
+}
+
+impl From<SourceArg> for DiffSource {
+    fn from(s: SourceArg) -> Self {
+        match s {
+            SourceArg::Staged => DiffSource::Staged,
+            SourceArg::Unstaged => DiffSource::Unstaged,
+            SourceArg::Both => DiffSource::Both,
+        }
+    }
+}
+
+/// Options for one run of the generate flow.
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    pub mock: bool,
+    pub hint: Option<String>,
+    pub source: DiffSource,
+    /// Non-interactive: skip pre-flight and review, commit directly.
+    pub yes: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    Generate,
+    GenerateFromStash,
+    PrDescription,
+    Stage,
+    Stash,
+    Branch,
+    Revert,
+    Fixup,
+    Diff,
+    Push,
+    Pull,
+    Release,
+    Config,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewAction {
+    Commit,
+    Edit,
+    LinkIssues,
+    Refine,
+    Regenerate,
+    RegenerateWithHint,
+    Split,
+    Amend,
+    Cancel,
+}
+
+/// CLI entrypoint: parse arguments and dispatch.
+pub fn run() -> Result<()> {
+    let mut args = Args::parse_from(alias::strip_alias_arg(std::env::args_os()));
+    ui::set_compact(args.compact);
+    ui::install_interrupt_handler();
+    git::select_repo(args.repo.as_deref())?;
+    if args.read_only {
+        read_only::enable();
+    }
+    if args.offline {
+        offline::enable();
+        args.mock = true;
+    }
+
+    if args.config {
+        setup::run_setup()?;
+    }
+
+    match &args.command {
+        Some(Command::Setup) => {
+            setup::run_setup()?;
+            Ok(())
+        }
+        Some(Command::Doctor { offline }) => doctor::run(*offline),
+        Some(Command::Completions { shell }) => {
+            print!("{}", completions::script(*shell, Args::command()));
+            Ok(())
+        }
+        Some(Command::Complete { kind }) => {
+            completions::print_values(*kind);
+            Ok(())
+        }
+        Some(Command::Usage { latency, last }) => run_usage(*latency, *last),
+        Some(Command::Alias { action }) => run_alias(action),
+        Some(Command::Serve { stdio }) => {
+            if !stdio {
+                bail!("Only the stdio transport is supported: run `git-wiz serve --stdio`.");
+            }
+            serve::run_stdio(args.mock)
+        }
+        Some(Command::Generate { source, yes }) => {
+            ui::print_banner();
+            ensure_repo()?;
+            ensure_config(args.mock)?;
+            run_generate_flow(&GenerateOptions {
+                mock: args.mock,
+                hint: args.hint.clone(),
+                source: (*source).into(),
+                yes: *yes,
+            })
+        }
+        Some(Command::Release {
+            action: Some(ReleaseCommand::Notes { tag }),
+            ..
+        }) => {
+            ui::print_banner();
+            ensure_repo()?;
+            run_release_notes(tag, args.mock)
+        }
+        Some(Command::Release {
+            bump,
+            yes,
+            workspace,
+            skip_preflight,
+            ..
+        }) => {
+            ui::print_banner();
+            ensure_repo()?;
+            let scope = workspace.then_some(release::VersionScope::Workspace);
+            run_release(
+                bump.as_deref().unwrap_or_default(),
+                scope,
+                skip_preflight,
+                *yes,
+                args.mock,
+            )
+        }
+        None => {
+            #[cfg(feature = "tui")]
+            if !args.menu {
+                return crate::tui::run_tui(args.mock);
+            }
+            run_main_menu(&args)
+        }
+    }
+}
+
+fn run_main_menu(args: &Args) -> Result<()> {
+    ui::print_banner();
+    ensure_repo()?;
+    ensure_config(args.mock)?;
+
+    let mut first_hint = args.hint.clone();
+
+    loop {
+        // One `git status` per menu round: branch, ahead/behind and change counts.
+        if let Ok(snapshot) = git::snapshot() {
+            ui::print_info(snapshot);
+        }
+
+        let action = select("What would you like to do?")
+            .item(
+                MenuAction::Generate,
+                "Generate commit message",
+                "from your changes",
+            )
+            .item(
+                MenuAction::GenerateFromStash,
+                "Generate from stash…",
+                "describe a stash entry",
+            )
+            .item(
+                MenuAction::PrDescription,
+                "Generate PR description",
+                "from the branch's commits",
+            )
+            .item(MenuAction::Stage, "Stage / unstage", "git add / restore")
+            .item(MenuAction::Stash, "Stash", "save, pop or list")
+            .item(MenuAction::Branch, "Branch", "switch or create")
+            .item(
+                MenuAction::Revert,
+                "Revert a commit…",
+                "undo it with a new commit",
+            )
+            .item(
+                MenuAction::Fixup,
+                "Create fixup commit…",
+                "staged changes, for --autosquash",
+            )
+            .item(MenuAction::Diff, "View diff", "")
+            .item(MenuAction::Push, "Push", "branch or tags")
+            .item(MenuAction::Pull, "Pull", "fast-forward only")
+            .item(MenuAction::Release, "Release", "bump, commit, tag, push")
+            .item(MenuAction::Config, "Config", "provider, API key, model")
+            .item(MenuAction::Quit, "Quit", "")
+            .interact()?;
+
+        let result = match action {
+            MenuAction::Generate => select_source().and_then(|source| {
+                run_generate_flow(&GenerateOptions {
+                    mock: args.mock,
+                    // The CLI hint only applies to the first generation of the session.
+                    hint: first_hint.take(),
+                    source,
+                    yes: false,
+                })
+            }),
+            MenuAction::GenerateFromStash => run_stash_flow(args.mock),
+            MenuAction::PrDescription => run_pr_flow(args.mock),
+            MenuAction::Stage => run_stage_flow(),
+            MenuAction::Stash => run_stash_menu_flow(),
+            MenuAction::Branch => run_branch_flow(),
+            MenuAction::Revert => run_revert_flow(args.mock),
+            MenuAction::Fixup => run_fixup_flow(),
+            MenuAction::Diff => run_diff_flow(),
+            MenuAction::Push => run_push_flow(),
+            MenuAction::Pull => run_pull_flow(),
+            MenuAction::Release => run_release_flow(args.mock),
+            MenuAction::Config => run_config_flow(),
+            MenuAction::Quit => break,
+        };
+
+        if let Err(e) = result {
+            ui::print_error(&e);
+        }
+    }
+
+    outro("Bye!")?;
+    Ok(())
+}
+
+fn ensure_repo() -> Result<()> {
+    if !git::is_repo() {
+        bail!("Not a git repository (or git is not installed).");
+    }
+    Ok(())
+}
+
+fn ensure_config(mock: bool) -> Result<()> {
+    if !mock && Config::load()?.is_none() {
+        setup::run_setup()?;
+    }
+    Ok(())
+}
+
+/// Pick the diff source, starting on the one last used in this repository. "Against a ref"
+/// then asks for the ref, offering the last one used or the remote's default branch.
+fn select_source() -> Result<DiffSource> {
+    let last = state::load().diff_source;
+    let last_ref = match &last {
+        Some(DiffSource::AgainstRef(reference)) => Some(reference.clone()),
+        _ => None,
+    };
+    // The menu item stands for any ref.
+    let against = DiffSource::AgainstRef(String::new());
+    let last = last.map(|source| match source {
+        DiffSource::AgainstRef(_) => against.clone(),
+        source => source,
+    });
+
+    let mut prompt = select("Generate from");
+    for (source, label, hint) in [
+        (DiffSource::Staged, "Staged changes", "recommended"),
+        (DiffSource::Unstaged, "Unstaged changes", ""),
+        (DiffSource::Both, "Staged + unstaged", ""),
+        (
+            against,
+            "Against a ref",
+            "what HEAD added since a branch, tag or commit",
+        ),
+    ] {
+        let is_last = last.as_ref() == Some(&source);
+        prompt = prompt.item(source, last_used(label, is_last), hint);
+    }
+    let mut source = prompt
+        .initial_value(last.unwrap_or(DiffSource::Staged))
+        .interact()?;
+    if let DiffSource::AgainstRef(reference) = &mut source {
+        let default = last_ref.or_else(git::default_base_ref).unwrap_or_default();
+        let picked: String = input("Compare HEAD with")
+            .placeholder("e.g. origin/main, v1.2.0 or a commit hash")
+            .default_input(&default)
+            .validate(|r: &String| git::verify_ref(r))
+            .interact()?;
+        *reference = picked.trim().to_string();
+    }
+    state::remember_diff_source(source.clone());
+    Ok(source)
+}
+
+/// `label`, marked when it is the remembered choice.
+fn last_used(label: &str, is_last: bool) -> String {
+    if is_last {
+        format!("{} (last used)", label)
+    } else {
+        label.to_string()
+    }
+}
+
+/// The files with the most changed lines, so a lock file or generated file taking up
+/// most of the diff shows before it is sent (`prompt_exclude` leaves it out).
+fn print_largest_files(summary: &git::DiffSummary) {
+    if summary.files_changed < 2 {
+        return;
+    }
+    let lines: Vec<String> = summary
+        .largest_files(git::LARGEST_FILES)
+        .iter()
+        .map(|f| {
+            format!(
+                "  {:>11}  {:>4}  {}",
+                f.counts(),
+                summary
+                    .share(f)
+                    .map_or_else(String::new, |p| format!("{}%", p)),
+                f.display_path()
+            )
+        })
+        .collect();
+    ui::print_info(format!("Largest files:\n{}", lines.join("\n")));
+}
+
+fn source_label(source: &DiffSource) -> String {
+    match source {
+        DiffSource::Staged => "staged".to_string(),
+        DiffSource::Unstaged => "unstaged".to_string(),
+        DiffSource::Both => "staged + unstaged".to_string(),
+        DiffSource::AgainstRef(reference) => format!("{}...HEAD", reference),
+    }
+}
+
+/// Generate a commit message for `opts.source`, let the user review it, and commit.
+pub fn run_generate_flow(opts: &GenerateOptions) -> Result<()> {
+    let repo_state = git::repo_state()?;
+    if !repo_state.conflicted.is_empty() {
+        ui::print_warning(format!(
+            "Generation is blocked: {} file(s) have unresolved merge conflicts: {}",
+            repo_state.conflicted.len(),
+            repo_state.conflicted.join(", ")
+        ));
+        if opts.yes
+            || !confirm("Resolve them now?")
+                .initial_value(true)
+                .interact()?
+            || !run_conflict_flow()?
+        {
+            bail!("Resolve the conflicts and `git add` the files, then generate again.");
+        }
+    }
+    // A merge is concluded by the commit (below); anything else gets a commit in the middle.
+    if let Some(operation) = repo_state
+        .operation
+        .filter(|operation| *operation != Operation::Merge)
+    {
+        ui::print_warning(format!(
+            "A {} is in progress. Committing now adds a new commit in the middle of it; \
+             `{} --continue` is usually what finishes it.",
+            operation,
+            operation.command()
+        ));
+        if opts.yes
+            || !confirm("Generate and commit anyway?")
+                .initial_value(false)
+                .interact()?
+        {
+            bail!(
+                "Finish the {} with `{} --continue` (or undo it with `{} --abort`), then \
+                 generate again.",
+                operation,
+                operation.command(),
+                operation.command()
+            );
+        }
+    }
+
+    if !opts.yes && repo_state.operation.is_none() {
+        offer_feature_branch()?;
+    }
+
+    let mut opts = opts.clone();
+    let branch_hint = match opts.hint {
+        Some(_) => None,
+        None => prompt::branch_hint(Config::load().ok().flatten().as_ref())?,
+    };
+    if branch_hint.is_some() {
+        opts.hint = branch_hint.clone();
+    }
+
+    // Concluding a merge: describe the merge and let `git commit` finish it.
+    if let Some(subject) = conflicts::merge_in_progress()? {
+        ui::print_info(format!(
+            "Merge in progress: generating the message for {}",
+            subject
+        ));
+        opts.source = DiffSource::Staged;
+        opts.hint = Some(conflicts::merge_hint(&subject, opts.hint.as_deref()));
+    }
+    let opts = &opts;
+
+    if opts.source == DiffSource::Staged && git::get_diff_staged_allow_empty()?.trim().is_empty() {
+        if opts.yes {
+            bail!("No staged changes found. Did you forget to 'git add'?");
+        }
+        let stage = confirm("Nothing is staged. Stage all changes (git add -A)?")
+            .initial_value(true)
+            .interact()?;
+        if !stage {
+            ui::print_info("Nothing to commit.");
+            return Ok(());
+        }
+        git::stage_all()?;
+    }
+
+    if opts.source == DiffSource::Staged && !confirm_not_duplicate(opts.yes)? {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+
+    let summary = git::diff_summary(opts.source.clone())?;
+    let diff = prompt::diff_for(opts.source.clone())?;
+    let breakdown = git::change_breakdown(&git::status_entries()?, &opts.source);
+    ui::print_info(format!(
+        "Diff ({}): {} files{}{}, +{} -{}, ~{} bytes",
+        source_label(&opts.source),
+        summary.files_changed,
+        if breakdown.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", breakdown)
+        },
+        if summary.binary_files > 0 {
+            format!(", {} binary", summary.binary_files)
+        } else {
+            String::new()
+        },
+        summary.insertions,
+        summary.deletions,
+        summary.bytes
+    ));
+    print_largest_files(&summary);
+    if let Some(note) = prompt::excluded_summary(&diff.excluded) {
+        ui::print_info(note);
+    }
+    match git::commit_template() {
+        Ok(Some(template)) => ui::print_info(format!(
+            "Filling in commit.template ({})",
+            template.path.display()
+        )),
+        Ok(None) => {}
+        Err(e) => ui::print_warning(format!("{:#}", e)),
+    }
+    if let Some(hint) = &branch_hint {
+        ui::print_info(format!("Hint from branch: {}", hint));
+    }
+    let diff = diff.text;
+
+    if !opts.yes && !confirm_preflight(&diff, opts.hint.as_deref(), opts.mock)? {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+
+    let Some((mut generator, mut provider, model)) = connect(opts.mock, !opts.yes)? else {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    };
+    ui::print_info(format!("Using {} ({})", provider, model));
+
+    // The hint in effect for regenerations and refinements; "Regenerate with hint" replaces it.
+    let mut hint = opts.hint.clone();
+    let mut message = generate_message(
+        &mut generator,
+        &mut provider,
+        &diff,
+        hint.clone(),
+        None,
+        !opts.yes,
+    )?;
+
+    let config = Config::load().ok().flatten();
+    let keyword = config
+        .as_ref()
+        .map(|c| c.issue_keyword)
+        .unwrap_or(IssueKeyword::Closes);
+    let branch = git::current_branch().ok();
+    let candidates = message::issue_candidates(branch.as_deref(), &diff);
+
+    if opts.yes {
+        ui::print_commit_preview(&message);
+        commit_message(&message, &opts.source, true)?;
+        return Ok(());
+    }
+
+    if config.as_ref().is_some_and(|c| c.prompt_link_issues) && !candidates.is_empty() {
+        message = link_issues(&message, keyword, &candidates)?;
+    }
+    let merging = conflicts::merge_in_progress()?.is_some();
+    // Splitting needs at least two files, all staged and committed on their own.
+    let splittable =
+        opts.source == DiffSource::Staged && !merging && git::snapshot()?.staged().nth(1).is_some();
+    let amendable = opts.source == DiffSource::Staged && !merging && git::head_commit().is_ok();
+
+    loop {
+        ui::print_commit_preview(&message);
+
+        let mut menu = select("What next?")
+            .item(ReviewAction::Commit, "Commit", "")
+            .item(ReviewAction::Edit, "Edit", "open in your editor")
+            .item(
+                ReviewAction::LinkIssues,
+                "Link issues",
+                "Closes/Refs footers",
+            )
+            .item(
+                ReviewAction::Refine,
+                "Refine",
+                "tell the model what to change",
+            )
+            .item(ReviewAction::Regenerate, "Regenerate", "")
+            .item(
+                ReviewAction::RegenerateWithHint,
+                "Regenerate with hint",
+                hint.as_deref()
+                    .unwrap_or("tell the model what the change is about"),
+            );
+        if splittable {
+            menu = menu.item(
+                ReviewAction::Split,
+                "Split into multiple commits",
+                "the model groups the files",
+            );
+        }
+        if amendable {
+            menu = menu.item(
+                ReviewAction::Amend,
+                "Amend last commit",
+                "fold the staged changes into it, with a new message",
+            );
+        }
+        let action = menu.item(ReviewAction::Cancel, "Cancel", "").interact()?;
+
+        match action {
+            ReviewAction::Commit => {
+                if commit_message(&message, &opts.source, false)? {
+                    return Ok(());
+                }
+            }
+            ReviewAction::Edit => match edit_message(&message) {
+                Ok(edited) if !edited.trim().is_empty() => message = edited,
+                Ok(_) => ui::print_warning("Edited message was empty; keeping the previous one."),
+                Err(e) => ui::print_error(&e),
+            },
+            ReviewAction::LinkIssues => {
+                message = link_issues(&message, keyword, &candidates)?;
+            }
+            ReviewAction::Refine => {
+                let feedback: String = input("What should change?")
+                    .placeholder("e.g. mention the config migration, drop the test details")
+                    .required(false)
+                    .interact()?;
+                if feedback.trim().is_empty() {
+                    continue;
+                }
+                let refinement = Refinement {
+                    previous: message.clone(),
+                    feedback,
+                };
+                message = generate_message(
+                    &mut generator,
+                    &mut provider,
+                    &diff,
+                    hint.clone(),
+                    Some(&refinement),
+                    true,
+                )?;
+            }
+            ReviewAction::Regenerate => {
+                message = generate_message(
+                    &mut generator,
+                    &mut provider,
+                    &diff,
+                    hint.clone(),
+                    None,
+                    true,
+                )?;
+            }
+            ReviewAction::RegenerateWithHint => {
+                let new_hint: String = input("Hint")
+                    .placeholder("e.g. fixes the login timeout; leave empty to clear")
+                    .default_input(hint.as_deref().unwrap_or_default())
+                    .required(false)
+                    .interact()?;
+                let new_hint = new_hint.trim();
+                hint = (!new_hint.is_empty()).then(|| new_hint.to_string());
+                message = generate_message(
+                    &mut generator,
+                    &mut provider,
+                    &diff,
+                    hint.clone(),
+                    None,
+                    true,
+                )?;
+            }
+            ReviewAction::Split => match run_split_flow(&generator, &provider, &diff) {
+                Ok(true) => return Ok(()),
+                Ok(false) => ui::print_info("Back to the single commit."),
+                Err(e) => {
+                    ui::print_error(&e);
+                    ui::print_warning("Falling back to a single commit.");
+                }
+            },
+            ReviewAction::Amend => match run_amend_flow(&mut generator, &mut provider, &hint) {
+                Ok(true) => return Ok(()),
+                Ok(false) => ui::print_info("Back to the new commit."),
+                Err(e) => ui::print_error(&e),
+            },
+            ReviewAction::Cancel => {
+                ui::print_info("Cancelled. Nothing was committed.");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Fold the staged changes into HEAD with a message generated for the result (HEAD's
+/// parent against the index). Returns false when the user went back without amending.
+fn run_amend_flow(
+    generator: &mut Generator,
+    provider: &mut String,
+    hint: &Option<String>,
+) -> Result<bool> {
+    let head = git::head_commit()?;
+    ui::print_info(format!(
+        "Amending {} {}",
+        head.short,
+        head.message.lines().next().unwrap_or_default()
+    ));
+    match git::remote_branches_containing_head() {
+        Ok(branches) if !branches.is_empty() => ui::print_warning(format!(
+            "{} is already pushed ({}). After amending you will have to force-push.",
+            head.short,
+            branches.join(", ")
+        )),
+        Ok(_) => {}
+        Err(e) => ui::print_warning(format!(
+            "Could not check whether {} was pushed: {:#}",
+            head.short, e
+        )),
+    }
+
+    let diff = prompt::amend_diff()?;
+    let summary = git::amend_diff_summary()?;
+    ui::print_info(format!(
+        "Diff (HEAD + staged): {} files, +{} -{}, ~{} bytes",
+        summary.files_changed, summary.insertions, summary.deletions, summary.bytes
+    ));
+    print_largest_files(&summary);
+    let mut message = generate_message(generator, provider, &diff, hint.clone(), None, true)?;
+    loop {
+        ui::print_commit_preview(&message);
+        let action = select(format!("Amend {}?", head.short))
+            .item("amend", "Amend", "rewrites the commit: it gets a new hash")
+            .item("edit", "Edit", "open in your editor")
+            .item(
+                "previous",
+                "Edit the previous message",
+                "start from what the commit says now",
+            )
+            .item("regenerate", "Regenerate", "")
+            .item("back", "Back", "keep the new commit instead")
+            .interact()?;
+        match action {
+            "amend" => {
+                let options = repo_config::commit_options()?;
+                let label = if git::commit_hooks(options).is_empty() {
+                    "Amending…"
+                } else {
+                    "Amending (running hooks)…"
+                };
+                ui::with_streaming_spinner(label, |on_token| {
+                    git::amend_commit(&message, options, &mut |line| on_token(line))
+                })?;
+                let amended = git::head_commit()?;
+                ui::print_success(format!("Amended {} -> {}.", head.short, amended.short));
+                return Ok(true);
+            }
+            "edit" | "previous" => {
+                let start = if action == "edit" {
+                    &message
+                } else {
+                    &head.message
+                };
+                match edit_message(start) {
+                    Ok(edited) if !edited.trim().is_empty() => message = edited,
+                    Ok(_) => {
+                        ui::print_warning("Edited message was empty; keeping the previous one.")
+                    }
+                    Err(e) => ui::print_error(&e),
+                }
+            }
+            "regenerate" => {
+                message = generate_message(generator, provider, &diff, hint.clone(), None, true)?;
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// Ask the model to split the staged changes, then commit each group after confirmation.
+///
+/// Returns false when nothing is left to commit separately: the model's proposal was
+/// declined, or the user stopped before the first group. Stopping later restores the
+/// staging state of the groups that are left.
+fn run_split_flow(generator: &Generator, provider: &str, diff: &str) -> Result<bool> {
+    let staged = split::StagedFiles::collect()?;
+    let groups = ui::with_spinner(&format!("Grouping with {}…", provider), || {
+        runtime::block_on(split::propose(generator, diff, &staged))
+    })?;
+    if groups.len() < 2 {
+        ui::print_info("The model sees a single logical change here.");
+        return Ok(false);
+    }
+
+    ui::print_info(format!(
+        "{} commits proposed for {} files:",
+        groups.len(),
+        staged.paths.len()
+    ));
+    for (i, group) in groups.iter().enumerate() {
+        let subject = group.message.lines().next().unwrap_or_default();
+        println!("  {}. {}  ({})", i + 1, subject, group.files.join(", "));
+    }
+    if !confirm("Commit them one by one?")
+        .initial_value(true)
+        .interact()?
+    {
+        return Ok(false);
+    }
+
+    let saved = split::SavedIndex::save()?;
+    for (i, group) in groups.iter().enumerate() {
+        ui::print_commit_preview(&group.message);
+        let go = confirm(format!(
+            "Commit {}/{} ({})?",
+            i + 1,
+            groups.len(),
+            group.files.join(", ")
+        ))
+        .initial_value(true)
+        .interact();
+        let result = match go {
+            Ok(true) => split::commit_group(&staged, group).map(|()| true),
+            Ok(false) => Ok(false),
+            Err(e) => Err(e.into()),
+        };
+        if matches!(result, Ok(true)) {
+            ui::print_success(format!("Committed {}/{}", i + 1, groups.len()));
+            continue;
+        }
+
+        saved
+            .restore()
+            .context("Could not restore the staging state")?;
+        if i == 0 {
+            result?;
+            ui::print_info("Nothing was committed; the staging state is as before.");
+            return Ok(false);
+        }
+        if let Err(e) = result {
+            ui::print_error(&e);
+        }
+        ui::print_warning(format!(
+            "{} of {} commits made; the rest is staged again.",
+            i,
+            groups.len()
+        ));
+        return Ok(true);
+    }
+    push_after_commit(false);
+    Ok(true)
+}
+
+/// Stash the working tree's changes, pop an entry, or list them.
+fn run_stash_menu_flow() -> Result<()> {
+    let choice = select("Stash")
+        .item("push", "Stash changes", "git stash push -u")
+        .item("pop", "Pop a stash…", "")
+        .item("list", "List stashes", "")
+        .interact()?;
+
+    match choice {
+        "push" => {
+            let message: String = input("Message")
+                .placeholder("optional, e.g. experiment with retries")
+                .required(false)
+                .interact()?;
+            match git::stash_push(Some(&message))? {
+                Some(entry) => {
+                    ui::print_success(format!("Stashed as {}: {}", entry.name, entry.subject))
+                }
+                None => ui::print_info("No local changes to stash."),
+            }
+        }
+        "pop" => {
+            let entries = git::stash_list()?;
+            if entries.is_empty() {
+                ui::print_info("No stash entries.");
+                return Ok(());
+            }
+            let mut picker = select("Which stash?");
+            for entry in &entries {
+                picker = picker.item(entry.name.clone(), &entry.name, &entry.subject);
+            }
+            let stash: String = picker.interact()?;
+            ui::with_spinner(&format!("Popping {}…", stash), || git::stash_pop(&stash))?;
+            ui::print_success(format!("Popped {}.", stash));
+        }
+        _ => {
+            let entries = git::stash_list()?;
+            if entries.is_empty() {
+                ui::print_info("No stash entries.");
+            }
+            for entry in entries {
+                println!("{}  {}", entry.name, entry.subject);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Generate a message for a stash entry, then print it or pop + stage + commit.
+fn run_stash_flow(mock: bool) -> Result<()> {
+    let entries = git::stash_list()?;
+    if entries.is_empty() {
+        ui::print_info("No stash entries.");
+        return Ok(());
+    }
+
+    let mut picker = select("Which stash?");
+    for entry in &entries {
+        picker = picker.item(entry.name.clone(), &entry.name, &entry.subject);
+    }
+    let stash: String = picker.interact()?;
+
+    let diff = git::stash_diff(&stash)?;
+    if diff.trim().is_empty() {
+        bail!("{} has no changes to describe.", stash);
+    }
+    if !confirm_preflight(&diff, None, mock)? {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+
+    let Some((mut generator, mut provider, model)) = connect(mock, true)? else {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    };
+    ui::print_info(format!("Using {} ({})", provider, model));
+    let message = generate_message(&mut generator, &mut provider, &diff, None, None, true)?;
+    ui::print_commit_preview(&message);
+
+    let choice = select("What next?")
+        .item("commit", "Pop, stage all and commit", "")
+        .item("print", "Just print the message", "")
+        .item("cancel", "Cancel", "")
+        .interact()?;
+    match choice {
+        "commit" => pop_stage_commit(&stash, &message),
+        "print" => {
+            println!("\n{}\n", message);
+            Ok(())
+        }
+        _ => {
+            ui::print_info("Cancelled. The stash is untouched.");
+            Ok(())
+        }
+    }
+}
+
+/// Commits offered when picking one to revert or fix up.
+const RECENT_COMMITS: usize = 20;
+
+/// Pick one of the recent commits; `None` when there are none yet.
+fn pick_recent_commit(prompt: &str) -> Result<Option<git::CommitSummary>> {
+    let commits = git::recent_commits(RECENT_COMMITS)?;
+    if commits.is_empty() {
+        ui::print_info("No commits yet.");
+        return Ok(None);
+    }
+    let mut picker = select(prompt);
+    for (i, commit) in commits.iter().enumerate() {
+        picker = picker.item(
+            i,
+            format!("{} {}", commit.short, commit.subject),
+            &commit.when,
+        );
+    }
+    let index = picker.interact()?;
+    Ok(commits.into_iter().nth(index))
+}
+
+/// Undo a recent commit with a new one, with git's `Revert "…"` message or one generated
+/// from the inverted diff.
+fn run_revert_flow(mock: bool) -> Result<()> {
+    let Some(target) = pick_recent_commit("Revert which commit?")? else {
+        return Ok(());
+    };
+    let how = select("Message")
+        .item("git", "Use git's message", "Revert \"…\"")
+        .item(
+            "generate",
+            "Generate a message",
+            "from what the revert changes",
+        )
+        .item("back", "Back", "")
+        .interact()?;
+    match how {
+        "git" => {
+            let options = repo_config::commit_options()?;
+            let outcome = ui::with_spinner(&format!("Reverting {}…", target.short), || {
+                git::revert(&target.hash, options)
+            })?;
+            if let git::RevertOutcome::Conflicts(files) = outcome {
+                bail!(revert_conflicts(&target, &files));
+            }
+            let head = git::head_commit()?;
+            ui::print_success(format!("Reverted {} in {}.", target.short, head.short));
+            Ok(())
+        }
+        "generate" => revert_with_generated_message(&target, mock),
+        _ => Ok(()),
+    }
+}
+
+fn revert_with_generated_message(target: &git::CommitSummary, mock: bool) -> Result<()> {
+    if !git::get_diff_staged_allow_empty()?.trim().is_empty() {
+        bail!("Changes are staged. Commit or unstage them first: the revert is committed from the index.");
+    }
+    let diff = git::revert_diff(&target.hash)?;
+    if diff.trim().is_empty() {
+        bail!(
+            "{} changes no files, so there is nothing to revert.",
+            target.short
+        );
+    }
+    let hint = format!(
+        "This reverts commit {} (\"{}\"); say what is undone.",
+        target.short, target.subject
+    );
+    if !confirm_preflight(&diff, Some(&hint), mock)? {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+    let Some((mut generator, mut provider, model)) = connect(mock, true)? else {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    };
+    ui::print_info(format!("Using {} ({})", provider, model));
+    let message = generate_message(&mut generator, &mut provider, &diff, Some(hint), None, true)?;
+    // The trailer git itself would add, so the revert still names what it undoes.
+    let message = format!(
+        "{}\n\nThis reverts commit {}.",
+        message.trim_end(),
+        target.hash
+    );
+    ui::print_commit_preview(&message);
+    if !confirm("Revert with this message?")
+        .initial_value(true)
+        .interact()?
+    {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+
+    let outcome = ui::with_spinner(&format!("Reverting {}…", target.short), || {
+        git::revert_no_commit(&target.hash)
+    })?;
+    if let git::RevertOutcome::Conflicts(files) = outcome {
+        println!("\n{}\n", message);
+        bail!(
+            "{} Commit with the message above once they are resolved.",
+            revert_conflicts(target, &files)
+        );
+    }
+    if !commit_message(&message, &DiffSource::Staged, false)? {
+        ui::print_warning(
+            "Not committed. The revert is staged: commit it, or undo it with `git revert --abort`.",
+        );
+    }
+    Ok(())
+}
+
+/// e.g. ``Reverting abc1234 stopped on conflicts in src/a.rs. Resolve them, …``
+fn revert_conflicts(target: &git::CommitSummary, files: &[String]) -> String {
+    format!(
+        "Reverting {} stopped on conflicts in {}. Resolve them and `git add` the files, or \
+         undo the revert with `git revert --abort`.",
+        target.short,
+        files.join(", ")
+    )
+}
+
+/// Commit the staged changes as `fixup! <subject>` of a recent commit.
+fn run_fixup_flow() -> Result<()> {
+    if git::get_diff_staged_allow_empty()?.trim().is_empty() {
+        ui::print_info("Nothing is staged. Stage the fix first (Stage / unstage).");
+        return Ok(());
+    }
+    let Some(target) = pick_recent_commit("Fix up which commit?")? else {
+        return Ok(());
+    };
+    let options = repo_config::commit_options()?;
+    ui::with_spinner("Committing fixup…", || {
+        git::commit_fixup(&target.hash, options)
+    })?;
+    ui::print_success(format!(
+        "Committed fixup! {}. Fold it in with `git rebase -i --autosquash {}~1`.",
+        target.subject, target.short
+    ));
+    Ok(())
+}
+
+/// Draft a pull request description for the branch against a base ref, then copy or
+/// save it.
+fn run_pr_flow(mock: bool) -> Result<()> {
+    let cfg = Config::load().ok().flatten();
+    let mut base_prompt = input("Base ref").placeholder("origin/main");
+    if let Some(base) = pr::default_base(cfg.as_ref()) {
+        base_prompt = base_prompt.default_input(&base);
+    }
+    let base: String = base_prompt.interact()?;
+
+    let changes = pr::collect(&base)?;
+    ui::print_info(format!(
+        "{} commit(s) since {}, ~{} bytes of diff",
+        changes.commits.len(),
+        changes.base,
+        changes.diff.len()
+    ));
+    if !confirm_preflight(&changes.prompt_text(), None, mock)? {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    }
+
+    let Some((generator, provider, model)) = connect(mock, true)? else {
+        ui::print_info("Cancelled.");
+        return Ok(());
+    };
+    let generation =
+        ui::with_spinner(&format!("Writing with {} ({})…", provider, model), || {
+            runtime::block_on(pr::generate(&generator, &changes))
+        })?;
+    warn_prompt_truncated(&generation.prompt_sizes);
+    if let Some(tokens) = generation.tokens {
+        ui::print_success(format!("Used {}", tokens));
+    }
+    let body = generation.message;
+    println!("\n{}\n", body);
+
+    loop {
+        let choice = select("PR description")
+            .item("copy", "Copy to clipboard", "")
+            .item("file", "Write PR_BODY.md", "repository root")
+            .item("done", "Done", "")
+            .interact()?;
+        let result = match choice {
+            "copy" => pr::copy(&body).map(|()| "Copied to clipboard.".to_string()),
+            "file" => pr::write_file(&body).map(|p| format!("Wrote {}", p.display())),
+            _ => return Ok(()),
+        };
+        match result {
+            Ok(done) => ui::print_success(done),
+            Err(e) => ui::print_error(&e),
+        }
+    }
+}
+
+/// Pop `stash`, stage everything and commit it with `message`.
+///
+/// Requires a clean tree so the commit holds exactly the stash. Every failure says what
+/// state the repository was left in.
+fn pop_stage_commit(stash: &str, message: &str) -> Result<()> {
+    if !git::snapshot()?.is_clean() {
+        bail!("The working tree has changes. Commit or stash them first: the stash is committed with `git add -A`.");
+    }
+
+    if let Err(e) = ui::with_spinner(&format!("Popping {}…", stash), || git::stash_pop(stash)) {
+        println!("\n{}\n", message);
+        bail!(
+            "{:#}\nIf it conflicted, the stash was kept: resolve the conflicts, `git add` them and commit with the message above, then `git stash drop {}`.",
+            e,
+            stash
+        );
+    }
+
+    let committed =
+        git::stage_all().and_then(|()| commit_message(message, &DiffSource::Staged, false));
+    match committed {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            println!("\n{}\n", message);
+            ui::print_warning("Not committed. The stash was popped and its changes are staged; the message is above.");
+            Ok(())
+        }
+        Err(e) => {
+            println!("\n{}\n", message);
+            bail!(
+                "{:#}\nThe stash was already popped: its changes are in the working tree. Commit them with the message above, or run `git stash` to put them back.",
+                e
+            );
+        }
+    }
+}
+
+/// Guard against re-committing changes that match the last git-wiz commit.
+/// Returns false if the user chose not to continue.
+fn confirm_not_duplicate(yes: bool) -> Result<bool> {
+    let staged = git::get_diff_staged_allow_empty()?;
+    let Some(last) = history::matching_last_commit(&staged) else {
+        return Ok(true);
+    };
+
+    let note = format!(
+        "These staged changes look identical to what you committed {} ({}).",
+        last.age(),
+        last.commit
+    );
+    if yes {
+        bail!("{} Re-run without --yes to continue anyway.", note);
+    }
+    ui::print_warning(note);
+    Ok(confirm("Generate a message anyway?")
+        .initial_value(false)
+        .interact()?)
+}
+
+/// Build the generator with `generator::build_generator`, once the provider's host
+/// answers. When it doesn't, offer to retry or to switch to the mock generator for the
+/// rest of the run; `None` when the user cancels. Without `prompt` (`generate --yes`)
+/// the probe error is returned.
+fn connect(mock: bool, prompt: bool) -> Result<Option<(Generator, String, String)>> {
+    if mock || offline::is_active() {
+        return generator::build_generator(true).map(Some);
+    }
+    // No config: let build_generator report it.
+    let Some(cfg) = Config::load()? else {
+        return generator::build_generator(false).map(Some);
+    };
+    loop {
+        let probe = runtime::block_on(offline::probe(&cfg));
+        let Err(e) = probe else {
+            return generator::build_generator(false).map(Some);
+        };
+        if !prompt {
+            bail!("{:#}. Use --offline to run without it.", e);
+        }
+        ui::print_warning(format!("{:#}", e));
+        let choice = select("No connection to the provider")
+            .item("retry", "Retry", "")
+            .item("mock", "Use the mock generator", "for the rest of this run")
+            .item("cancel", "Cancel", "")
+            .interact()?;
+        match choice {
+            "retry" => continue,
+            "mock" => {
+                offline::enable();
+                return generator::build_generator(true).map(Some);
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Pre-flight menu before spending tokens, under the estimated cost. Returns false if
+/// the user cancelled.
+fn confirm_preflight(diff: &str, hint: Option<&str>, mock: bool) -> Result<bool> {
+    ui::print_info(generator::estimate_cost(mock, diff, hint).to_string());
+    loop {
+        let choice = select("Send this diff to the model?")
+            .item("proceed", "Proceed", "")
+            .item("preview", "Preview diff", "")
+            .item("prompt", "Show prompt", "section sizes after the budget")
+            .item("cancel", "Cancel", "")
+            .interact()?;
+
+        match choice {
+            "proceed" => return Ok(true),
+            "preview" => print_diff(diff),
+            "prompt" => print_prompt_sizes(diff, hint),
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// Per-section prompt sizes for the configured model, flagging anything the budget cuts.
+fn print_prompt_sizes(diff: &str, hint: Option<&str>) {
+    let cfg = Config::load().ok().flatten();
+    let prompt = prompt::build(
+        cfg.as_ref().map(|c| &c.provider),
+        &PromptOptions::from_config(cfg.as_ref()),
+        diff,
+        hint,
+    );
+    for s in &prompt.sizes.sections {
+        let line = format!(
+            "{:<10} {:>9} bytes{}",
+            s.section,
+            s.kept,
+            if s.truncated() {
+                format!(" (cut from {})", s.original)
+            } else {
+                String::new()
+            }
+        );
+        if s.truncated() {
+            ui::print_warning(line);
+        } else {
+            ui::print_info(line);
+        }
+    }
+    ui::print_info(format!(
+        "~{} of {} tokens",
+        prompt.sizes.tokens(),
+        prompt.sizes.budget_tokens
+    ));
+    if !prompt.sizes.omitted_files.is_empty() {
+        ui::print_warning(format!(
+            "Headers only: {}",
+            prompt.sizes.omitted_files.join(", ")
+        ));
+    }
+}
+
+/// Tell the user the model saw a trimmed diff, so the message may miss changes.
+fn warn_prompt_truncated(sizes: &PromptSizes) {
+    if !sizes.truncated() {
+        return;
+    }
+    let mut note = format!(
+        "The diff was trimmed to fit the ~{} token prompt budget, so the message may be incomplete",
+        sizes.budget_tokens
+    );
+    if !sizes.omitted_files.is_empty() {
+        note.push_str(&format!(
+            " (headers only: {})",
+            sizes.omitted_files.join(", ")
+        ));
+    }
+    ui::print_warning(format!("{}.", note));
+}
+
+/// Generate a message for `diff`, or rewrite the previous one when `refinement` is set.
+///
+/// If the provider reports the configured model as unavailable and `interactive` is set,
+/// offer to pick another model for the same provider, then retry with the new config.
+fn generate_message(
+    generator: &mut Generator,
+    provider: &mut String,
+    diff: &str,
+    hint: Option<String>,
+    refinement: Option<&Refinement>,
+    interactive: bool,
+) -> Result<String> {
+    loop {
+        let verb = if refinement.is_some() {
+            "Refining"
+        } else {
+            "Generating"
+        };
+        let result =
+            ui::with_streaming_spinner(&format!("{} with {}…", verb, provider), |on_token| {
+                runtime::block_on(async {
+                    match refinement {
+                        Some(r) => generator.refine(diff, hint.clone(), r, on_token).await,
+                        None => {
+                            generator
+                                .generate_streaming(diff, hint.clone(), on_token)
+                                .await
+                        }
+                    }
+                })
+            });
+
+        let err = match result {
+            Ok(generation) => {
+                warn_prompt_truncated(&generation.prompt_sizes);
+                if offline::is_active() {
+                    ui::print_warning(offline::REMINDER);
+                }
+                if let Some(tokens) = generation.tokens {
+                    ui::print_success(format!("Used {}", tokens));
+                }
+                if generation.truncated {
+                    let limit = generator.output_limit();
+                    ui::print_warning(format!(
+                        "The reply hit the {}-token output limit, so the message is probably cut off (max_output_tokens in the config raises it).",
+                        limit
+                    ));
+                    if interactive
+                        && confirm(format!("Regenerate with a limit of {} tokens?", limit * 2))
+                            .initial_value(true)
+                            .interact()?
+                    {
+                        let (rebuilt, rebuilt_provider, _) =
+                            generator::build_generator_with_output_limit(false, limit * 2)?;
+                        *generator = rebuilt;
+                        *provider = rebuilt_provider;
+                        continue;
+                    }
+                }
+                let cfg = Config::load().ok().flatten();
+                let mut message = generation.message;
+                let violations = conventional::check(&message, cfg.as_ref());
+                if !violations.is_empty() && cfg.as_ref().is_some_and(|c| c.conventional_autofix) {
+                    let fixed = ui::with_spinner("Fixing the commit format…", || {
+                        runtime::block_on(conventional::fix(generator, &message, &violations))
+                    });
+                    match fixed {
+                        Ok(fixed) => message = fixed,
+                        Err(e) => ui::print_warning(format!("Could not fix the format: {:#}", e)),
+                    }
+                }
+                if let Some(fix) = scope::fix_for(&message, diff, cfg.as_ref()) {
+                    if interactive && confirm(fix.question()).initial_value(true).interact()? {
+                        message = scope::with_scope(&message, &fix.scope).unwrap_or(message);
+                    }
+                }
+                let mut message = check_generated_message(message, interactive)?;
+                if let Some(cfg) = &cfg {
+                    let branch = git::current_branch().ok();
+                    message = message::append_trailers(&message, &cfg.trailers, branch.as_deref());
+                }
+                let options = FormatOptions::from_config(cfg.as_ref());
+                if let Some(warning) = message::subject_warning(&message, &options) {
+                    ui::print_warning(warning);
+                }
+                if let Some(warning) =
+                    conventional::warning(&conventional::check(&message, cfg.as_ref()))
+                {
+                    ui::print_warning(warning);
+                }
+                if let Some(warning) = breaking::warning(&breaking::for_config(diff, cfg.as_ref()))
+                {
+                    ui::print_warning(warning);
+                }
+                if let Some(warning) = git::encoding_warning(diff) {
+                    ui::print_warning(warning);
+                }
+                return Ok(message);
+            }
+            Err(e) => e,
+        };
+        let Some(unavailable) = err.downcast_ref::<ModelUnavailable>() else {
+            return Err(err);
+        };
+        if !interactive {
+            return Err(err);
+        }
+
+        ui::print_warning(unavailable);
+        let pick = confirm(format!(
+            "Choose a different {} model now?",
+            unavailable.provider
+        ))
+        .initial_value(true)
+        .interact()?;
+        if !pick {
+            return Err(err);
+        }
+
+        setup::run_model_selection()?;
+        let (rebuilt, rebuilt_provider, _) = generator::build_generator(false)?;
+        *generator = rebuilt;
+        *provider = rebuilt_provider;
+    }
+}
+
+/// Run `post_generate_command` (if configured) on a freshly generated message.
+///
+/// On rejection, interactive runs can edit and re-check or use the message anyway.
+fn check_generated_message(mut message: String, interactive: bool) -> Result<String> {
+    let Some(command) = hook::post_generate_command() else {
+        return Ok(message);
+    };
+
+    loop {
+        let outcome = ui::with_spinner("Checking message…", || {
+            Ok(hook::run_post_generate(&command, &message))
+        })?;
+        let reason = match outcome {
+            HookOutcome::Accepted(checked) => return Ok(checked),
+            HookOutcome::Rejected(reason) => reason,
+        };
+        if !interactive {
+            bail!("post_generate_command rejected the message:\n{}", reason);
+        }
+
+        ui::print_warning(format!(
+            "post_generate_command rejected the message:\n{}",
+            reason
+        ));
+        ui::print_commit_preview(&message);
+        let choice = select("What now?")
+            .item("edit", "Edit and re-check", "")
+            .item("override", "Use it anyway", "")
+            .item("cancel", "Cancel", "")
+            .interact()?;
+        match choice {
+            "edit" => match edit_message(&message) {
+                Ok(edited) if !edited.trim().is_empty() => message = edited,
+                Ok(_) => ui::print_warning("Edited message was empty; keeping the previous one."),
+                Err(e) => ui::print_error(&e),
+            },
+            "override" => return Ok(message),
+            _ => bail!("Cancelled: the message was rejected by post_generate_command."),
+        }
+    }
+}
+
+fn link_issues(message: &str, keyword: IssueKeyword, candidates: &[u64]) -> Result<String> {
+    let prefill = candidates
+        .iter()
+        .map(|n| format!("#{}", n))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut prompt = input("Issues to close (add 'refs' before numbers to only reference)")
+        .placeholder("e.g. #12 34 refs 56")
+        .required(false);
+    if !prefill.is_empty() {
+        prompt = prompt.default_input(&prefill);
+    }
+    let value: String = prompt.interact()?;
+
+    let links = message::parse_issue_input(&value);
+    if links.is_empty() {
+        return Ok(message.to_string());
+    }
+    Ok(message::append_issue_footers(message, keyword, &links))
+}
+
+/// Stage if needed, show the pre-commit review, and commit.
+/// Returns false if the user backed out at the review step or after a hook rejected the
+/// commit; anything staged here for the commit is unstaged again.
+fn commit_message(message: &str, source: &DiffSource, yes: bool) -> Result<bool> {
+    // The index as it was before `stage_all`, to go back to when nothing is committed.
+    let mut staged_before = None;
+    match source {
+        DiffSource::Staged => {}
+        // The message describes commits that already exist; only staged work can take it.
+        DiffSource::AgainstRef(reference) => {
+            if git::get_diff_staged_allow_empty()?.trim().is_empty() {
+                ui::print_warning(format!(
+                    "Nothing is staged. The message describes the commits since {}; stage \
+                     changes (or `git reset --soft {}` to squash them) to commit it.",
+                    reference, reference
+                ));
+                return Ok(false);
+            }
+        }
+        DiffSource::Unstaged | DiffSource::Both => {
+            let stage = yes
+                || confirm("The message describes unstaged changes. Stage all before committing?")
+                    .initial_value(true)
+                    .interact()?;
+            if stage {
+                // Unmerged paths have no tree to save; staging them then sticks.
+                staged_before = git::write_index_tree().ok();
+                git::stage_all()?;
+            }
+        }
+    }
+
+    let confirm_commit = Config::load()
+        .ok()
+        .flatten()
+        .is_none_or(|c| c.confirm_commit);
+    if !yes && confirm_commit {
+        ui::print_commit_review(&review::staged_review(message)?);
+        if !confirm("Commit these changes?")
+            .initial_value(true)
+            .interact()?
+        {
+            restore_index(staged_before.as_deref());
+            return Ok(false);
+        }
+    }
+
+    let mut options = repo_config::commit_options()?;
+    loop {
+        let hooks = git::commit_hooks(options);
+        let label = if hooks.is_empty() {
+            "Committing…"
+        } else {
+            "Committing (running hooks)…"
+        };
+        let started = Instant::now();
+        let Err(err) = ui::with_streaming_spinner(label, |on_token| {
+            history::commit_and_record_with(message, options, &mut |line| on_token(line))
+        }) else {
+            if let Some(warning) = repo_config::slow_hooks_warning(&hooks, started.elapsed()) {
+                ui::print_warning(warning);
+            }
+            break;
+        };
+        let hook = err
+            .downcast_ref::<git::CommitFailed>()
+            .is_some_and(git::CommitFailed::hook_related);
+        if yes || !hook {
+            return Err(err);
+        }
+        ui::print_error(&err);
+        let choice = select("The commit was rejected. What now?")
+            .item("retry", "Retry", "after fixing what the hook reported")
+            .item(
+                "no-verify",
+                "Retry with --no-verify",
+                "skip the pre-commit and commit-msg hooks",
+            )
+            .item("abort", "Abort", "back to the message")
+            .interact()?;
+        match choice {
+            "retry" => {}
+            "no-verify" => options.no_verify = true,
+            _ => {
+                restore_index(staged_before.as_deref());
+                return Ok(false);
+            }
+        }
+    }
+    ui::print_success("Committed successfully.");
+    push_after_commit(yes);
+    Ok(true)
+}
+
+/// Put back the index `commit_message` saved before staging everything, if it did.
+fn restore_index(tree: Option<&str>) {
+    let Some(tree) = tree else {
+        return;
+    };
+    match git::read_index_tree(tree) {
+        Ok(()) => ui::print_info("Unstaged the changes staged for this commit."),
+        Err(e) => ui::print_warning(format!(
+            "Everything is still staged: restoring the index failed: {:#}",
+            e
+        )),
+    }
+}
+
+/// Apply `auto_push` after a commit. Anything that stops the push (no destination, a
+/// cancelled prompt, a failed push) is only reported: the commit already happened.
+fn push_after_commit(yes: bool) {
+    let auto_push = Config::load()
+        .ok()
+        .flatten()
+        .map(|c| c.auto_push)
+        .unwrap_or_default();
+    if auto_push == AutoPush::Never {
+        return;
+    }
+
+    let destination = match git::push_destination() {
+        Ok(destination) => destination,
+        Err(e) => {
+            ui::print_warning(format!(
+                "Committed, but not pushed: {:#}. The commit is kept; push from the Push menu.",
+                e
+            ));
+            return;
+        }
+    };
+    let push = auto_push == AutoPush::Always
+        || yes
+        || confirm(format!("Push to {}?", destination))
+            .initial_value(true)
+            .interact()
+            // A cancelled prompt (Esc, Ctrl+C) keeps the commit local.
+            .unwrap_or(false);
+    if !push {
+        ui::print_info(format!(
+            "Not pushed; the commit is local ({} is unchanged).",
+            destination
+        ));
+        return;
+    }
+
+    match push_branch(&format!("Pushing to {}…", destination), !yes) {
+        Ok(Some(pushed)) => ui::print_success(pushed),
+        Ok(None) => ui::print_info("Not pushed; the commit is local."),
+        Err(e) => {
+            ui::print_warning("Committed, but the push failed. The commit is kept; push again from the Push menu.");
+            ui::print_error(&e);
+        }
+    }
+}
+
+/// Open `message` in the editor git would use (see `git::resolve_editor`) and return the
+/// edited text.
+fn edit_message(message: &str) -> Result<String> {
+    let (program, args) = git::resolve_editor();
+    if program.is_empty() {
+        bail!("Editor command is empty");
+    }
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("git-wiz-edit-{}.txt", std::process::id()));
+    std::fs::write(&path, message)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let status = std::process::Command::new(&program)
+        .args(&args)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", program));
+
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    if !status?.success() {
+        bail!("Editor exited with an error; keeping the previous message.");
+    }
+    Ok(edited
+        .context("Failed to read edited message")?
+        .trim()
+        .to_string())
+}
+
+/// Open conflicted files one at a time and stage them once their markers are gone.
+///
+/// Returns true when no conflicts are left.
+fn run_conflict_flow() -> Result<bool> {
+    loop {
+        let files = conflicts::conflicted_files()?;
+        if files.is_empty() {
+            ui::print_success("All conflicts resolved and staged.");
+            return Ok(true);
+        }
+
+        let mut picker = select(format!(
+            "{} conflicted file(s). Open one in your editor:",
+            files.len()
+        ));
+        for path in &files {
+            let state = if conflicts::has_markers(path)? {
+                "conflict markers left"
+            } else {
+                "no markers: ready to stage"
+            };
+            picker = picker.item(path.clone(), path, state);
+        }
+        let path: String = picker
+            .item(String::new(), "Stop", "leave the rest for later")
+            .interact()?;
+        if path.is_empty() {
+            return Ok(false);
+        }
+
+        if conflicts::has_markers(&path)? {
+            if let Err(e) = conflicts::open_in_editor(&path) {
+                ui::print_error(&e);
+                continue;
+            }
+            if conflicts::has_markers(&path)? {
+                ui::print_warning(format!("{} still has conflict markers.", path));
+                continue;
+            }
+        }
+
+        if confirm(format!("{} has no conflict markers. Stage it?", path))
+            .initial_value(true)
+            .interact()?
+        {
+            conflicts::mark_resolved(&path)?;
+            ui::print_success(format!("Staged {}", path));
+        }
+    }
+}
+
+fn run_stage_flow() -> Result<()> {
+    let choice = select("Stage / unstage")
+        .item("stage_files", "Stage selected files", "pick from a list")
+        .item("stage_patch", "Stage patch", "git add -p (interactive)")
+        .item("stage_all", "Stage all", "git add -A")
+        .item(
+            "unstage_files",
+            "Unstage selected files",
+            "pick from a list",
+        )
+        .item("unstage_patch", "Unstage patch", "interactive")
+        .item("unstage_all", "Unstage all", "")
+        .interact()?;
+
+    match choice {
+        "stage_files" => {
+            let snapshot = git::snapshot()?;
+            let files = select_files(
+                "Files to stage",
+                snapshot.unstaged().chain(snapshot.untracked()),
+            )?;
+            if files.is_empty() {
+                ui::print_info("Nothing staged.");
+                return Ok(());
+            }
+            git::stage_files(&files)?;
+        }
+        "stage_patch" => git::stage_patch()?,
+        "stage_all" => git::stage_all()?,
+        "unstage_files" => {
+            let snapshot = git::snapshot()?;
+            let files = select_files("Files to unstage", snapshot.staged())?;
+            if files.is_empty() {
+                ui::print_info("Nothing unstaged.");
+                return Ok(());
+            }
+            git::unstage_files(&files)?;
+        }
+        "unstage_patch" => git::unstage_patch()?,
+        _ => git::unstage_all()?,
+    }
+    ui::print_success("Done.");
+    Ok(())
+}
+
+/// Let the user tick some of `entries`; an empty list when there are none to pick. A
+/// rename brings the path it came from along, so both sides move together.
+fn select_files<'a>(
+    prompt: &str,
+    entries: impl Iterator<Item = &'a git::StatusEntry>,
+) -> Result<Vec<String>> {
+    let mut menu = multiselect(prompt).required(false);
+    let mut any = false;
+    for entry in entries {
+        let hint = match entry.kind {
+            git::EntryKind::Untracked => "new".to_string(),
+            git::EntryKind::Renamed => format!(
+                "renamed from {}",
+                entry.orig_path.as_deref().unwrap_or_default()
+            ),
+            _ => String::new(),
+        };
+        let paths: Vec<String> = std::iter::once(&entry.path)
+            .chain(&entry.orig_path)
+            .cloned()
+            .collect();
+        menu = menu.item(paths, &entry.path, hint);
+        any = true;
+    }
+    if !any {
+        return Ok(Vec::new());
+    }
+    Ok(menu.interact()?.concat())
+}
+
+fn run_diff_flow() -> Result<()> {
+    let source = select_source()?;
+    let word_diff = Config::load().ok().flatten().is_some_and(|c| c.word_diff);
+    let diff = if word_diff {
+        git::get_word_diff_allow_empty(source)?
+    } else {
+        git::get_diff_allow_empty(source, &[])?
+    };
+    if diff.trim().is_empty() {
+        ui::print_info("No changes.");
+    } else {
+        print_diff(&diff);
+        if let Some(warning) = git::encoding_warning(&diff) {
+            ui::print_warning(warning);
+        }
+    }
+    Ok(())
+}
+
+fn print_diff(diff: &str) {
+    for line in diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", line.bold());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else if line.contains("[-") || line.contains("{+") {
+            println!("{}", color_word_diff(line));
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// A `--word-diff=plain` line with `[-removed-]` in red and `{+added+}` in green.
+fn color_word_diff(line: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\[-.*?-\]|\{\+.*?\+\}").expect("valid regex"));
+    re.replace_all(line, |caps: &regex::Captures| {
+        let change = &caps[0];
+        if change.starts_with('[') {
+            change.red().to_string()
+        } else {
+            change.green().to_string()
+        }
+    })
+    .into_owned()
+}
+
+/// Pick a local branch to switch to, or create one at HEAD.
+fn run_branch_flow() -> Result<()> {
+    let branches = git::branches()?;
+    let mut picker = select("Branch").item(None, "Create a new branch…", "at HEAD");
+    for branch in &branches {
+        let label = if branch.current {
+            format!("{} (current)", branch.name)
+        } else {
+            branch.name.clone()
+        };
+        picker = picker.item(
+            Some(branch.name.clone()),
+            label,
+            branch.upstream.as_deref().unwrap_or_default(),
+        );
+    }
+    if let Some(current) = branches.iter().find(|b| b.current) {
+        picker = picker.initial_value(Some(current.name.clone()));
+    }
+
+    match picker.interact()? {
+        None => {
+            let name: String = input("Branch name")
+                .placeholder("e.g. feat/retry-timeouts")
+                .validate(|name: &String| git::validate_ref_name(RefKind::Branch, name.trim()))
+                .interact()?;
+            let checkout = confirm("Switch to it now? (your changes come along)")
+                .initial_value(true)
+                .interact()?;
+            git::create_branch(&name, checkout)?;
+            if checkout {
+                ui::print_success(format!("Switched to a new branch {}.", name.trim()));
+            } else {
+                ui::print_success(format!("Created branch {}.", name.trim()));
+            }
+        }
+        Some(name) if branches.iter().any(|b| b.current && b.name == name) => {
+            ui::print_info(format!("Already on {}.", name));
+        }
+        Some(name) => switch_branch(&name)?,
+    }
+    Ok(())
+}
+
+/// Switch to `name`. When git refuses because of local changes, offer to stash them.
+fn switch_branch(name: &str) -> Result<()> {
+    let Err(e) = git::switch_branch(name) else {
+        ui::print_success(format!("Switched to {}.", name));
+        return Ok(());
+    };
+    if git::snapshot()?.is_clean() {
+        return Err(e);
+    }
+    ui::print_warning(format!("{:#}", e));
+    if !confirm(format!("Stash your changes and switch to {}?", name))
+        .initial_value(true)
+        .interact()?
+    {
+        bail!("Not switched: your changes are still in the working tree.");
+    }
+    let stashed = git::stash_push(Some(&format!("git-wiz: before switching to {}", name)))?;
+    let stash = stashed.map(|entry| entry.name).unwrap_or_default();
+    git::switch_branch(name).with_context(|| format!("Your changes are stashed as {}", stash))?;
+    ui::print_success(format!(
+        "Switched to {}. Your changes are stashed as {}; pop them from Stash when you're back.",
+        name, stash
+    ));
+    Ok(())
+}
+
+/// On a branch listed in `protected_branches`, offer to create a feature branch before
+/// generating. The changes come along to it.
+fn offer_feature_branch() -> Result<()> {
+    let Some(branch) = git::snapshot()?.branch else {
+        return Ok(());
+    };
+    let protected = config::protected_branches(Config::load().ok().flatten().as_ref());
+    if !protected.contains(&branch) {
+        return Ok(());
+    }
+    let choice = select(format!("You're on {}", branch))
+        .item("here", format!("Commit on {}", branch), "")
+        .item(
+            "branch",
+            "Create a branch first",
+            "git switch -c, changes come along",
+        )
+        .interact()?;
+    if choice == "here" {
+        return Ok(());
+    }
+    let name: String = input("Branch name")
+        .placeholder("e.g. feat/retry-timeouts")
+        .validate(|name: &String| git::validate_ref_name(RefKind::Branch, name.trim()))
+        .interact()?;
+    git::create_branch(&name, true)?;
+    ui::print_success(format!("Switched to a new branch {}.", name.trim()));
+    Ok(())
+}
+
+/// Offer to create a branch at the detached HEAD `commit`, so there is something to push.
+/// False when the user aborts.
+fn branch_off_detached_head(commit: &str) -> Result<bool> {
+    ui::print_warning(format!(
+        "HEAD is detached at {}: there is no branch to push.",
+        commit
+    ));
+    let choice = select("Push needs a branch")
+        .item("create", "Create a branch here", "git switch -c")
+        .item("abort", "Abort", "")
+        .interact()?;
+    if choice == "abort" {
+        return Ok(false);
+    }
+    let name: String = input("Branch name")
+        .validate(|name: &String| git::validate_ref_name(RefKind::Branch, name.trim()))
+        .interact()?;
+    git::create_branch(&name, true)?;
+    ui::print_success(format!("Created branch {} at {}.", name.trim(), commit));
+    Ok(true)
+}
+
+/// Push the current branch. When it has no upstream and origin already has a branch of
+/// the same name that the push wouldn't fast-forward, ask whether to rebase onto it,
+/// overwrite it (unless it is protected) or stop (`None`); without `interactive` that
+/// is an error.
+fn push_branch(label: &str, interactive: bool) -> Result<Option<git::BranchPush>> {
+    let pushed = ui::with_spinner(label, || {
+        git::push_branch_with_policy(git::PushPolicy::FastForwardOnly)
+    });
+    let diverged = match pushed.map_err(|e| e.downcast::<git::RemoteBranchDiverged>()) {
+        Ok(pushed) => return Ok(Some(pushed)),
+        Err(Ok(diverged)) if interactive => diverged,
+        Err(Ok(diverged)) => return Err(diverged.into()),
+        Err(Err(e)) => return Err(e),
+    };
+
+    ui::print_warning(&diverged);
+    let protected = config::protected_branches(Config::load().ok().flatten().as_ref())
+        .iter()
+        .any(|p| p.trim() == diverged.branch);
+    let mut choice = select(format!("origin/{} already exists", diverged.branch)).item(
+        "rebase",
+        format!("Rebase onto origin/{}, then push", diverged.branch),
+        "git rebase --autostash",
+    );
+    if !protected {
+        choice = choice.item(
+            "force",
+            format!("Overwrite origin/{}", diverged.branch),
+            "--force-with-lease: its own commits are lost",
+        );
+    }
+    let policy = match choice
+        .item("abort", "Abort", "nothing is pushed")
+        .interact()?
+    {
+        "rebase" => git::PushPolicy::Rebase,
+        "force" => git::PushPolicy::ForceWithLease {
+            expect: diverged.remote_commit,
+        },
+        _ => return Ok(None),
+    };
+    ui::with_spinner(label, || git::push_branch_with_policy(policy)).map(Some)
+}
+
+/// The branch is behind its upstream, so a plain push would be rejected: offer to
+/// fast-forward first. False when the user cancels.
+fn pull_before_push(tracking: &git::AheadBehind) -> Result<bool> {
+    ui::print_warning(format!(
+        "{} has {} commit(s) this branch doesn't have; a push now would be rejected.",
+        tracking.upstream, tracking.behind
+    ));
+    let choice = select("Push")
+        .item(
+            "pull",
+            "Pull (fast-forward only) then push",
+            "git pull --ff-only",
+        )
+        .item("push", "Push anyway", "")
+        .item("cancel", "Cancel", "")
+        .interact()?;
+    match choice {
+        "pull" => {
+            let pulled = ui::with_spinner("Pulling…", git::pull_ff_only)?;
+            ui::print_success(pulled);
+            Ok(true)
+        }
+        "push" => Ok(true),
+        _ => Ok(false),
+    }
+}
+
+fn run_pull_flow() -> Result<()> {
+    match ui::with_spinner("Pulling…", git::pull_ff_only) {
+        Ok(pulled) => {
+            ui::print_success(pulled);
+            Ok(())
+        }
+        Err(e) => {
+            let Some(diverged) = e.downcast_ref::<git::NotFastForward>() else {
+                return Err(e);
+            };
+            ui::print_warning(diverged);
+            if confirm("Show the diverging commits?")
+                .initial_value(true)
+                .interact()?
+            {
+                ui::print_info(format!(
+                    "< only on this branch, > only on {}:\n{}",
+                    diverged.upstream,
+                    git::divergence()?
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_push_flow() -> Result<()> {
+    if Config::load()
+        .ok()
+        .flatten()
+        .is_some_and(|c| c.fetch_before_push)
+    {
+        if let Err(e) = ui::with_spinner("Fetching…", git::fetch) {
+            ui::print_warning(format!(
+                "Could not fetch, so the counts may be out of date: {:#}",
+                e
+            ));
+        }
+    }
+    let tracking = git::ahead_behind()?;
+    let title = match &tracking {
+        Some(tracking) => format!("Push ({})", tracking),
+        None => "Push".to_string(),
+    };
+    let choice = select(title)
+        .item("branch", "Push branch", "sets upstream if missing")
+        .item(
+            "force",
+            "Force-push branch",
+            "--force-with-lease, after amend/rebase",
+        )
+        .item("tag", "Push a specific tag", "")
+        .item("tags", "Push all tags", "may trigger releases (v*)")
+        .item("list", "Tags", "list, show or delete")
+        .interact()?;
+
+    match choice {
+        "branch" => {
+            if let Some(commit) = git::detached_head()? {
+                if !branch_off_detached_head(&commit)? {
+                    ui::print_info("Not pushed.");
+                    return Ok(());
+                }
+            }
+            if let Some(tracking) = tracking.filter(|t| t.behind > 0) {
+                if !pull_before_push(&tracking)? {
+                    ui::print_info("Not pushed.");
+                    return Ok(());
+                }
+            }
+            match push_branch("Pushing branch…", true)? {
+                Some(pushed) => ui::print_success(pushed),
+                None => ui::print_info("Not pushed."),
+            }
+        }
+        "force" => {
+            let protected = config::protected_branches(Config::load().ok().flatten().as_ref());
+            let target = git::force_push_target(&protected)?;
+            ui::print_warning(format!(
+                "This replaces {} with your local '{}': commits only the remote has are lost. \
+                 --force-with-lease refuses if anyone pushed since your last fetch.",
+                target.upstream, target.branch
+            ));
+            let push = confirm(format!(
+                "Force-push '{}' to {}?",
+                target.branch, target.upstream
+            ))
+            .initial_value(false)
+            .interact()?;
+            if !push {
+                ui::print_info("Not pushed.");
+                return Ok(());
+            }
+            let target =
+                ui::with_spinner("Force-pushing…", || git::force_push_branch(&protected))?;
+            ui::print_success(format!(
+                "Force-pushed '{}' to {}.",
+                target.branch, target.upstream
+            ));
+        }
+        "tag" => {
+            let tags = git::tags(None)?;
+            if tags.is_empty() {
+                ui::print_info("No local tags to push.");
+                return Ok(());
+            }
+            let mut picker = select("Tag to push");
+            for tag in &tags {
+                picker = picker.item(tag.name.clone(), &tag.name, tag_hint(tag));
+            }
+            let tag: String = picker.interact()?;
+            ui::with_spinner(&format!("Pushing tag {}…", tag.trim()), || {
+                git::push_tag(&tag)
+            })?;
+            ui::print_success(format!("Tag pushed: {}", tag.trim()));
+        }
+        "list" => run_tags_flow()?,
+        _ => {
+            if confirm("Push ALL tags? This may trigger releases (v*).")
+                .initial_value(false)
+                .interact()?
+            {
+                ui::with_spinner("Pushing all tags…", git::push_all_tags)?;
+                ui::print_success("All tags pushed.");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// e.g. `2024-05-01 · Release v0.3.0 · on origin`
+fn tag_hint(tag: &git::TagInfo) -> String {
+    let mut hint = tag.date.clone();
+    if let Some(subject) = &tag.subject {
+        hint.push_str(&format!(" · {}", subject));
+    }
+    match tag.on_remote {
+        Some(true) => hint.push_str(" · on origin"),
+        Some(false) => hint.push_str(" · local only"),
+        None => {}
+    }
+    hint
+}
+
+/// List the local tags and whether `origin` has them; show or delete the one picked.
+fn run_tags_flow() -> Result<()> {
+    loop {
+        let tags = ui::with_spinner("Checking tags on origin…", || git::tags(Some("origin")))?;
+        if tags.is_empty() {
+            ui::print_info("No local tags.");
+            return Ok(());
+        }
+        if tags.iter().all(|tag| tag.on_remote.is_none()) {
+            ui::print_warning("Could not reach origin: whether it has each tag is unknown.");
+        }
+        let mut picker = select(format!("Tags ({})", tags.len())).item(None, "Done", "");
+        for tag in &tags {
+            picker = picker.item(Some(tag.name.clone()), &tag.name, tag_hint(tag));
+        }
+        let Some(name) = picker.interact()? else {
+            return Ok(());
+        };
+        let on_remote = tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .and_then(|tag| tag.on_remote);
+
+        println!("\n{}\n", git::show_tag(&name)?);
+        let mut actions = select(format!("Tag {}", name))
+            .item("back", "Back to the list", "")
+            .item("local", "Delete locally", "git tag --delete");
+        if on_remote != Some(false) {
+            actions = actions.item(
+                "remote",
+                "Delete locally and on origin",
+                "git push origin --delete",
+            );
+        }
+        match actions.interact()? {
+            "local" => {
+                git::delete_tag(&name, false)?;
+                ui::print_success(format!("Deleted tag {} locally.", name));
+            }
+            "remote" => {
+                if !confirm_remote_tag_delete(&name)? {
+                    ui::print_info(format!("Kept {}.", name));
+                    continue;
+                }
+                ui::with_spinner(&format!("Deleting {} on origin…", name), || {
+                    git::delete_tag(&name, true)
+                })?;
+                ui::print_success(format!("Deleted tag {} locally and on origin.", name));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Ask before deleting `tag` on origin. A release tag (`v*`) has to be typed out, since
+/// deleting it can break the release it belongs to.
+fn confirm_remote_tag_delete(tag: &str) -> Result<bool> {
+    if !git::is_release_tag(tag) {
+        return Ok(confirm(format!("Delete {} locally and on origin?", tag))
+            .initial_value(false)
+            .interact()?);
+    }
+    ui::print_warning(format!(
+        "{} is a release tag: deleting it on origin can break its GitHub release and \
+         anything that fetches that version.",
+        tag
+    ));
+    let typed: String = input(format!("Type {} to delete it on origin", tag))
+        .required(false)
+        .interact()?;
+    Ok(typed.trim() == tag)
+}
+
+fn run_release_flow(mock: bool) -> Result<()> {
+    let last = state::load().release_bump;
+    let mut prompt = select("Release");
+    for (choice, label, hint) in [
+        (ReleaseChoice::Patch, "Patch", "x.y.Z"),
+        (ReleaseChoice::Minor, "Minor", "x.Y.0"),
+        (ReleaseChoice::Major, "Major", "X.0.0"),
+        (ReleaseChoice::Custom, "Custom version", ""),
+    ] {
+        prompt = prompt.item(choice, last_used(label, last == Some(choice)), hint);
+    }
+    let choice = prompt
+        .initial_value(last.unwrap_or(ReleaseChoice::Patch))
+        .interact()?;
+
+    let bump = match choice {
+        ReleaseChoice::Patch => "patch".to_string(),
+        ReleaseChoice::Minor => "minor".to_string(),
+        ReleaseChoice::Major => "major".to_string(),
+        ReleaseChoice::Custom => input("New version")
+            .placeholder("e.g. 0.3.0")
+            .validate(|version: &String| {
+                git::validate_ref_name(RefKind::Tag, &format!("v{}", version.trim()))
+            })
+            .interact()?,
+    };
+    // Only now: cancelling the version prompt shouldn't make Custom the default.
+    state::remember_release_bump(choice);
+    run_release(&bump, None, &[], false, mock)
+}
+
+/// Plan and run a tag-based release for `bump` (patch/minor/major or an explicit version).
+/// Without a `scope`, a package in a workspace with a version of its own asks which
+/// version to bump (the package's with `yes`). `skip` names preflight checks to leave
+/// out; without `yes` they are only unticked in the list of checks.
+fn run_release(
+    bump: &str,
+    scope: Option<release::VersionScope>,
+    skip: &[String],
+    yes: bool,
+    mock: bool,
+) -> Result<()> {
+    release::ensure_no_operation_in_progress()?;
+    let manifest = release::manifest_path()?;
+    let scope = match scope {
+        Some(scope) => scope,
+        None if yes => release::VersionScope::Package,
+        None => select_version_scope(&manifest)?,
+    };
+    let plan = match bump.trim() {
+        "patch" => release::plan_bump(&manifest, release::BumpKind::Patch, scope)?,
+        "minor" => release::plan_bump(&manifest, release::BumpKind::Minor, scope)?,
+        "major" => release::plan_bump(&manifest, release::BumpKind::Major, scope)?,
+        custom => release::plan_custom(&manifest, custom.trim_start_matches('v'), scope)?,
+    };
+
+    ui::print_info(format!(
+        "Bump {} -> {} in {} and push tag {}",
+        plan.old_version, plan.new_version, plan.source, plan.tag
+    ));
+    let mut preflight = repo_config::preflight_config()?;
+    preflight.skip_checks(skip)?;
+    let tags = repo_config::tag_config()?;
+    if tags.sign {
+        ui::print_info(format!(
+            "Tag {} will be signed with {}.",
+            plan.tag,
+            tags.signing_key
+                .as_deref()
+                .map_or("user.signingkey".to_string(), |key| format!("key {}", key))
+        ));
+    }
+    if !yes {
+        preflight.skip = select_preflight_skips(&preflight)?;
+    }
+    let checks: Vec<String> = preflight
+        .checks()
+        .iter()
+        .filter(|c| !preflight.is_skipped(c))
+        .map(|c| c.to_string())
+        .collect();
+    if checks.is_empty() {
+        ui::print_info("Preflight: no checks.");
+    } else {
+        ui::print_info(format!("Preflight, before the bump: {}", checks.join(", ")));
+    }
+    if let Some(note) = preflight.skipped_note() {
+        ui::print_warning(format!("{} (recorded in the release commit)", note));
+    }
+    let guards =
+        release::ReleaseGuardrailConfig::from_config(Config::load().ok().flatten().as_ref());
+    if let Some(note) = release::upstream_note(&guards)? {
+        ui::print_warning(note);
+    }
+    let changelog = if Config::load().ok().flatten().is_some_and(|c| c.changelog) {
+        Some(changelog_section(&plan, yes, mock)?)
+    } else {
+        None
+    };
+
+    if !yes
+        && !confirm("Proceed? This triggers CI release + crates publish.")
+            .initial_value(false)
+            .interact()?
+    {
+        ui::print_info("Release cancelled.");
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let mut stashed = None;
+    let result = loop {
+        let result = release::run_tag_release(
+            &plan,
+            &format!("chore(release): {}", plan.tag),
+            changelog.as_deref(),
+            &preflight,
+            &guards,
+            &tags,
+        );
+        // The remote and clean-tree guards trip before anything is modified, so the
+        // release can simply be retried once they pass.
+        let Err(e) = result else {
+            break Ok(());
+        };
+        let recovered = match e.downcast_ref::<release::MissingRemote>() {
+            Some(missing) if !yes => add_release_remote(missing),
+            _ if !yes && stashed.is_none() && e.is::<release::DirtyWorkingTree>() => {
+                stash_for_release(&plan.tag).map(|entry| stashed = Some(entry))
+            }
+            _ => Err(e),
+        };
+        if let Err(e) = recovered {
+            break Err(e);
+        }
+    };
+    // Before the stash comes back, so the rollback only sees the release's own changes.
+    let result = result.map_err(|e| offer_release_rollback(e, yes));
+    // Popped whether the release went through or not.
+    if let Some(entry) = stashed {
+        restore_release_stash(&entry);
+    }
+    result?;
+
+    notify::task_finished(
+        started.elapsed(),
+        &format!("git-wiz: release {} pushed", plan.tag),
+    );
+    ui::print_success(format!("Release initiated: pushed tag {}", plan.tag));
+
+    // Optional last step; the release itself already succeeded.
+    if !yes
+        && confirm(format!("Draft release notes for {}?", plan.tag))
+            .initial_value(true)
+            .interact()?
+    {
+        if let Err(e) = run_release_notes(&plan.tag, mock) {
+            ui::print_error(&e);
+        }
+    }
+    Ok(())
+}
+
+/// The package's version or the workspace's, when the package has its own version but
+/// its workspace has one too.
+fn select_version_scope(manifest: &Path) -> Result<release::VersionScope> {
+    let Some(workspace) = release::workspace_alternative(manifest)? else {
+        return Ok(release::VersionScope::Package);
+    };
+    let package = release::version_source(manifest, release::VersionScope::Package)?;
+    Ok(select("Which version?")
+        .item(
+            release::VersionScope::Package,
+            "The package's",
+            package.to_string(),
+        )
+        .item(
+            release::VersionScope::Workspace,
+            "The workspace's",
+            workspace.to_string(),
+        )
+        .interact()?)
+}
+
+/// The CHANGELOG.md section for `plan`, polished by the model if the user wants, shown
+/// before the release is confirmed.
+fn changelog_section(plan: &release::ReleasePlan, yes: bool, mock: bool) -> Result<String> {
+    let section = release::generate_changelog_section(plan)?;
+    ui::print_info(format!("{} gets:", release::CHANGELOG_FILE));
+    println!("\n{}", section);
+    if yes
+        || !confirm("Polish it with the model? (sends commit subjects and bodies)")
+            .initial_value(false)
+            .interact()?
+    {
+        return Ok(section);
+    }
+    match polish_changelog(plan, &section, mock) {
+        Ok(polished) => {
+            println!("\n{}", polished);
+            Ok(polished)
+        }
+        Err(e) => {
+            ui::print_error(&e);
+            ui::print_warning("Using the unpolished section.");
+            Ok(section)
+        }
+    }
+}
+
+fn polish_changelog(plan: &release::ReleasePlan, draft: &str, mock: bool) -> Result<String> {
+    let Some((generator, provider, model)) = connect(mock, true)? else {
+        bail!("Cancelled.");
+    };
+    ui::with_spinner(
+        &format!("Polishing with {} ({})…", provider, model),
+        || runtime::block_on(release::polish_changelog_section(&generator, plan, draft)),
+    )
+}
+
+/// Draft notes for `tag`, optionally polish them, then copy / write / publish them.
+fn run_release_notes(tag: &str, mock: bool) -> Result<()> {
+    let release = release_notes::commits_for(tag)?;
+    if release.commits.is_empty() {
+        ui::print_info(format!("No commits to describe in {}.", tag));
+        return Ok(());
+    }
+    ui::print_info(format!(
+        "{} commit(s) since {}",
+        release.commits.len(),
+        release.previous.as_deref().unwrap_or("the first commit")
+    ));
+
+    let mut notes = release_notes::draft(&release);
+    if confirm("Polish with the model? (sends commit subjects and bodies)")
+        .initial_value(true)
+        .interact()?
+    {
+        match polish_release_notes(&release, &notes, mock) {
+            Ok(polished) => notes = polished,
+            Err(e) => {
+                ui::print_error(&e);
+                ui::print_warning("Using the unpolished draft.");
+            }
+        }
+    }
+    println!("\n{}\n", notes);
+
+    let gh = release_notes::gh_available();
+    loop {
+        let mut menu = select("Release notes")
+            .item("copy", "Copy to clipboard", "")
+            .item("file", "Write RELEASE_NOTES.md", "repository root");
+        if gh {
+            menu = menu.item("gh", "Set the GitHub release body", "gh release edit");
+        }
+        let choice = menu.item("done", "Done", "").interact()?;
+
+        let result = match choice {
+            "copy" => release_notes::copy(&notes).map(|()| "Copied to clipboard.".to_string()),
+            "file" => release_notes::write_file(&notes).map(|p| format!("Wrote {}", p.display())),
+            "gh" => ui::with_spinner(&format!("Waiting for the {} release…", tag), || {
+                release_notes::publish_with_gh(tag, &notes)
+            })
+            .map(|()| format!("Updated the GitHub release for {}.", tag)),
+            _ => return Ok(()),
+        };
+        match result {
+            Ok(done) => ui::print_success(done),
+            Err(e) => ui::print_error(&e),
+        }
+    }
+}
+
+fn polish_release_notes(
+    release: &release_notes::ReleaseCommits,
+    draft: &str,
+    mock: bool,
+) -> Result<String> {
+    let Some((generator, provider, model)) = connect(mock, true)? else {
+        bail!("Cancelled.");
+    };
+    ui::with_spinner(
+        &format!("Polishing with {} ({})…", provider, model),
+        || runtime::block_on(release_notes::polish(&generator, release, draft)),
+    )
+}
+
+/// Offer to stash the changes that keep the tree from being clean; bails if the user
+/// declines.
+fn stash_for_release(tag: &str) -> Result<git::StashEntry> {
+    ui::print_warning(release::DirtyWorkingTree);
+    if !confirm("Stash changes and continue? (popped after the release)")
+        .initial_value(true)
+        .interact()?
+    {
+        bail!("Release cancelled: the working tree has changes.");
+    }
+    let Some(entry) = git::stash_push(Some(&format!("git-wiz: before release {}", tag)))? else {
+        bail!("Release cancelled: nothing could be stashed, but the working tree has changes.");
+    };
+    ui::print_success(format!(
+        "Stashed your changes as {}. Continuing release…",
+        entry.name
+    ));
+    Ok(entry)
+}
+
+/// Pop the changes `stash_for_release` put away. A failed pop only warns: git keeps the
+/// entry, so nothing is lost.
+fn restore_release_stash(entry: &git::StashEntry) {
+    match git::stash_pop(&entry.name) {
+        Ok(()) => ui::print_success(format!("Restored your stashed changes ({}).", entry.name)),
+        Err(e) => ui::print_warning(format!(
+            "{:#}\nYour changes are kept in {}. If the pop conflicted, resolve the files and \
+             `git stash drop`; otherwise `git stash pop` once the tree allows it.",
+            e, entry.name
+        )),
+    }
+}
+
+/// Let the user untick preflight checks for this run; returns the names left out. Those
+/// already in `preflight.skip` start unticked.
+fn select_preflight_skips(preflight: &release::PreflightConfig) -> Result<Vec<String>> {
+    let checks = preflight.checks();
+    if checks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut menu = multiselect("Preflight checks to run (space toggles)").required(false);
+    for check in &checks {
+        let hint = if check.name == check.to_string() {
+            String::new()
+        } else {
+            check.to_string()
+        };
+        menu = menu.item(check.name.clone(), &check.name, hint);
+    }
+    let run = menu
+        .initial_values(
+            checks
+                .iter()
+                .filter(|c| !preflight.is_skipped(c))
+                .map(|c| c.name.clone())
+                .collect(),
+        )
+        .interact()?;
+    Ok(checks
+        .into_iter()
+        .map(|c| c.name)
+        .filter(|name| !run.contains(name))
+        .collect())
+}
+
+/// Offer to undo a release that failed after the bump commit, or undo it right away with
+/// `release_auto_rollback`. Hands the error back: the release failed either way.
+fn offer_release_rollback(e: anyhow::Error, yes: bool) -> anyhow::Error {
+    let Some(incomplete) = e.downcast_ref::<release::IncompleteRelease>() else {
+        return e;
+    };
+    let auto = Config::load()
+        .ok()
+        .flatten()
+        .is_some_and(|c| c.release_auto_rollback);
+    let mode = if auto {
+        Some(release::ResetMode::Soft)
+    } else if yes {
+        None
+    } else {
+        match choose_rollback(incomplete) {
+            Ok(mode) => mode,
+            Err(prompt_error) => return prompt_error,
+        }
+    };
+
+    match mode {
+        Some(mode) => match release::rollback_release(incomplete, mode) {
+            Ok(undone) => {
+                for line in undone {
+                    ui::print_success(line);
+                }
+            }
+            Err(rollback_error) => {
+                ui::print_warning(format!("Rollback failed: {:#}", rollback_error))
+            }
+        },
+        None => ui::print_info(
+            incomplete
+                .resume_hint()
+                .unwrap_or_else(|| "Nothing was rolled back.".to_string()),
+        ),
+    }
+    e
+}
+
+/// `None` keeps what the release did. The cause is printed with the error afterwards.
+fn choose_rollback(incomplete: &release::IncompleteRelease) -> Result<Option<release::ResetMode>> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Choice {
+        Soft,
+        Hard,
+        Keep,
+    }
+    let keep_hint = if incomplete.resume_hint().is_some() {
+        "push the tag yourself later"
+    } else {
+        "fix it by hand"
+    };
+    let choice = select(format!(
+        "Release {} stopped after {}. Roll it back?",
+        incomplete.tag, incomplete.step
+    ))
+    .item(
+        Choice::Soft,
+        "Roll back",
+        "delete the tag, git reset --soft, restore the files",
+    )
+    .item(
+        Choice::Hard,
+        "Roll back with git reset --hard",
+        "discards every uncommitted change",
+    )
+    .item(Choice::Keep, "Keep it", keep_hint)
+    .interact()?;
+    Ok(match choice {
+        Choice::Soft => Some(release::ResetMode::Soft),
+        Choice::Hard
+            if confirm("git reset --hard discards every uncommitted change. Continue?")
+                .initial_value(false)
+                .interact()? =>
+        {
+            Some(release::ResetMode::Hard)
+        }
+        Choice::Hard | Choice::Keep => None,
+    })
+}
+
+/// Offer to add the missing release remote; bails if the user declines.
+fn add_release_remote(missing: &release::MissingRemote) -> Result<()> {
+    ui::print_warning(missing);
+    if !confirm(format!("Add the '{}' remote now?", missing.remote))
+        .initial_value(true)
+        .interact()?
+    {
+        bail!("Release cancelled: no '{}' remote.", missing.remote);
+    }
+
+    let url: String = input(format!("URL for '{}'", missing.remote))
+        .placeholder("git@github.com:owner/repo.git")
+        .validate(|url: &String| release::validate_remote_url(url))
+        .interact()?;
+    ui::with_spinner("Checking remote…", || {
+        release::add_remote(&missing.remote, &url)
+    })?;
+    ui::print_success(format!(
+        "Added remote '{}'. Continuing release…",
+        missing.remote
+    ));
+    Ok(())
+}
+
+fn run_alias(action: &AliasCommand) -> Result<()> {
+    match action {
+        AliasCommand::Install { absolute, force } => {
+            let value = alias::install(*absolute, *force)?;
+            println!("Installed: `git wiz` runs `{}`.", value.trim_start_matches('!'));
+        }
+        AliasCommand::Uninstall { force } => {
+            if alias::uninstall(*force)? {
+                println!("Removed the `git wiz` alias.");
+            } else {
+                println!("No `git wiz` alias is set.");
+            }
+        }
+        AliasCommand::Status => match alias::status()? {
+            AliasStatus::NotInstalled => {
+                println!("Not installed. Run `git-wiz alias install` to use `git wiz`.")
+            }
+            AliasStatus::Installed(value) => {
+                println!("Installed: `git wiz` runs `{}`.", value.trim_start_matches('!'))
+            }
+            AliasStatus::Other(value) => println!(
+                "`git wiz` is set to `{}`, which is not git-wiz (`alias install --force` replaces it).",
+                value
+            ),
+        },
+    }
+    Ok(())
+}
+
+fn run_usage(latency: bool, last: usize) -> Result<()> {
+    let entries = usage::load()?;
+    if entries.is_empty() {
+        println!(
+            "No generations recorded yet ({}).",
+            usage::ledger_path()?.display()
+        );
+        return Ok(());
+    }
+
+    if latency {
+        println!(
+            "{}",
+            format!("Latency per model (last {} calls each)", last).bold()
+        );
+        println!(
+            "  {:<14} {:<28} {:>6} {:>9} {:>9}",
+            "provider", "model", "calls", "p50", "p95"
+        );
+        for s in usage::latency_stats(&entries, last.max(1)) {
+            println!(
+                "  {:<14} {:<28} {:>6} {:>9} {:>9}",
+                s.provider,
+                s.model,
+                s.calls,
+                usage::format_ms(s.p50_ms),
+                usage::format_ms(s.p95_ms)
+            );
+        }
+        return Ok(());
+    }
+
+    println!("{}", "Generations per model".bold());
+    println!(
+        "  {:<14} {:<28} {:>6} {:>12}",
+        "provider", "model", "calls", "prompt KB"
+    );
+    let mut totals: Vec<(&str, &str, usize, usize)> = Vec::new();
+    for e in &entries {
+        match totals
+            .iter_mut()
+            .find(|t| t.0 == e.provider && t.1 == e.model)
+        {
+            Some(t) => {
+                t.2 += 1;
+                t.3 += e.prompt_bytes;
+            }
+            None => totals.push((&e.provider, &e.model, 1, e.prompt_bytes)),
+        }
+    }
+    for (provider, model, calls, bytes) in totals {
+        println!(
+            "  {:<14} {:<28} {:>6} {:>12.1}",
+            provider,
+            model,
+            calls,
+            bytes as f64 / 1024.0
+        );
+    }
+    println!("\nRun `git-wiz usage --latency` for p50/p95 latency per model.");
+    Ok(())
+}
+
+fn run_config_flow() -> Result<()> {
+    let choice = select("Config")
+        .item("setup", "Run setup wizard", "provider, API key, model")
+        .item(
+            "style",
+            "Change commit style",
+            "conventional, gitmoji, plain",
+        )
+        .item("language", "Change message language", "e.g. German")
+        .item("prompt", "Custom system prompt", "prompt.txt")
+        .item("show", "Show current config", "")
+        .item("clear", "Clear config", "deletes the local config file")
+        .interact()?;
+
+    match choice {
+        "setup" => {
+            setup::run_setup()?;
+        }
+        "style" => {
+            setup::run_style_selection()?;
+        }
+        "language" => {
+            setup::run_language_selection()?;
+        }
+        "prompt" => {
+            setup::run_prompt_selection()?;
+        }
+        "show" => match Config::load()? {
+            Some(cfg) => ui::print_info(format!(
+                "Provider: {}\nModel:    {}\nStyle:    {}\nLanguage: {}\nPrompt:   {}\nFile:     {}",
+                cfg.provider,
+                cfg.model,
+                cfg.commit_style,
+                setup::language_label(&cfg),
+                prompt::template_label(&cfg),
+                Config::get_path()?.display()
+            )),
+            None => ui::print_info("Not configured."),
+        },
+        _ => {
+            if confirm("Clear config? This will delete the local config file.")
+                .initial_value(false)
+                .interact()?
+            {
+                Config::delete()?;
+                ui::print_success("Config cleared.");
+            }
+        }
+    }
+    Ok(())
+}
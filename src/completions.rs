@@ -0,0 +1,366 @@
+//! Shell completion scripts (`git-wiz completions <shell>`) and the hidden
+//! `git-wiz __complete <kind>` helper they call for values that live in the repository.
+//!
+//! Subcommands, flags and fixed values are read from the clap definition, so the scripts
+//! follow the CLI without edits. Tags are looked up at completion time; `__complete`
+//! prints nothing and exits 0 outside a repository so a `<TAB>` never writes errors into
+//! the command line.
+
+use clap::{Command, ValueEnum};
+
+use crate::git;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Repository values printed by `git-wiz __complete <kind>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Values {
+    Tags,
+}
+
+impl Values {
+    fn name(self) -> &'static str {
+        match self {
+            Values::Tags => "tags",
+        }
+    }
+}
+
+/// Positional arguments completed from the repository, by subcommand path.
+const DYNAMIC: &[(&str, Values)] = &[("release notes", Values::Tags)];
+
+/// Print one value per line; silent on any error (e.g. not a repository).
+pub fn print_values(kind: Values) {
+    let values = match kind {
+        Values::Tags => git::tag_names(),
+    };
+    for value in values.unwrap_or_default() {
+        println!("{}", value);
+    }
+}
+
+/// The completion script for `shell`, generated from `cmd` (the `git-wiz` clap command).
+pub fn script(shell: Shell, mut cmd: Command) -> String {
+    // Propagates global flags (--mock, --hint…) into every subcommand.
+    cmd.build();
+    let bin = cmd.get_name().to_string();
+    let mut spec = Spec::default();
+    collect(&cmd, "", &mut spec);
+    match shell {
+        Shell::Bash => bash(&bin, &spec),
+        Shell::Zsh => zsh(&bin, &spec),
+        Shell::Fish => fish(&bin, &spec),
+    }
+}
+
+/// What can be completed after each subcommand path (`""`, `"release"`, `"release notes"`).
+#[derive(Default)]
+struct Spec {
+    nodes: Vec<Node>,
+    /// Flags that take a value, with the fixed values if any (empty: free text).
+    value_flags: Vec<(String, Vec<String>)>,
+}
+
+struct Node {
+    path: String,
+    subcommands: Vec<String>,
+    /// Long and short flags, e.g. `--yes`, `-y`.
+    flags: Vec<String>,
+    /// Fixed values of the positional argument (e.g. shells for `completions`).
+    values: Vec<String>,
+    dynamic: Option<Values>,
+}
+
+impl Node {
+    fn words(&self) -> String {
+        [&self.subcommands, &self.values, &self.flags]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn collect(cmd: &Command, path: &str, spec: &mut Spec) {
+    let subcommands: Vec<&Command> = cmd
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set() && s.get_name() != "help")
+        .collect();
+
+    let mut flags = Vec::new();
+    let mut values = Vec::new();
+    for arg in cmd.get_arguments().filter(|a| !a.is_hide_set()) {
+        let possible: Vec<String> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        if arg.is_positional() {
+            values.extend(possible);
+            continue;
+        }
+        let names: Vec<String> = arg
+            .get_long()
+            .map(|l| format!("--{}", l))
+            .into_iter()
+            .chain(arg.get_short().map(|s| format!("-{}", s)))
+            .collect();
+        if arg.get_action().takes_values() {
+            for name in &names {
+                if !spec.value_flags.iter().any(|(n, _)| n == name) {
+                    spec.value_flags.push((name.clone(), possible.clone()));
+                }
+            }
+        }
+        flags.extend(names);
+    }
+
+    spec.nodes.push(Node {
+        path: path.to_string(),
+        subcommands: subcommands
+            .iter()
+            .map(|s| s.get_name().to_string())
+            .collect(),
+        flags,
+        values,
+        dynamic: DYNAMIC
+            .iter()
+            .find(|(p, _)| *p == path)
+            .map(|(_, kind)| *kind),
+    });
+
+    for sub in subcommands {
+        let sub_path = if path.is_empty() {
+            sub.get_name().to_string()
+        } else {
+            format!("{} {}", path, sub.get_name())
+        };
+        collect(sub, &sub_path, spec);
+    }
+}
+
+/// `case` patterns (`"release>notes"`) that extend the subcommand path by one word.
+fn transitions(spec: &Spec) -> String {
+    spec.nodes
+        .iter()
+        .flat_map(|n| {
+            n.subcommands
+                .iter()
+                .map(move |s| format!("\"{}>{}\"", n.path, s))
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn bash(bin: &str, spec: &Spec) -> String {
+    let func = format!("_{}", bin.replace('-', "_"));
+    let mut out = format!(
+        r#"{func}() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}} prev=${{COMP_WORDS[COMP_CWORD-1]}} cmd_path="" w i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        w=${{COMP_WORDS[i]}}
+        case "$cmd_path>$w" in
+            {transitions}) cmd_path="${{cmd_path:+$cmd_path }}$w" ;;
+        esac
+    done
+    case "$prev" in
+"#,
+        func = func,
+        transitions = transitions(spec)
+    );
+    for (flag, values) in &spec.value_flags {
+        out.push_str(&format!(
+            "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;\n",
+            flag,
+            values.join(" ")
+        ));
+    }
+    out.push_str("    esac\n    case \"$cmd_path\" in\n");
+    for node in &spec.nodes {
+        let mut words = format!("\"{}\"", node.words());
+        if let Some(kind) = node.dynamic {
+            words = format!(
+                "\"$({} __complete {} 2>/dev/null) {}\"",
+                bin,
+                kind.name(),
+                node.words()
+            );
+        }
+        out.push_str(&format!(
+            "        \"{}\") COMPREPLY=($(compgen -W {} -- \"$cur\")) ;;\n",
+            node.path, words
+        ));
+    }
+    out.push_str(&format!("    esac\n}}\ncomplete -F {} {}\n", func, bin));
+    out
+}
+
+fn zsh(bin: &str, spec: &Spec) -> String {
+    // `path` is special in zsh (tied to $PATH), hence `cmd_path`.
+    let func = format!("_{}", bin.replace('-', "_"));
+    let mut out = format!(
+        r#"#compdef {bin}
+
+{func}() {{
+    local cmd_path="" w i
+    for ((i = 2; i < CURRENT; i++)); do
+        w=${{words[i]}}
+        case "$cmd_path>$w" in
+            {transitions}) cmd_path="${{cmd_path:+$cmd_path }}$w" ;;
+        esac
+    done
+    case "${{words[CURRENT-1]}}" in
+"#,
+        bin = bin,
+        func = func,
+        transitions = transitions(spec)
+    );
+    for (flag, values) in &spec.value_flags {
+        out.push_str(&format!(
+            "        {}) compadd -- {}; return ;;\n",
+            flag,
+            values.join(" ")
+        ));
+    }
+    out.push_str("    esac\n    case \"$cmd_path\" in\n");
+    for node in &spec.nodes {
+        let dynamic = node.dynamic.map_or(String::new(), |kind| {
+            format!(
+                "compadd -- ${{(f)\"$({} __complete {} 2>/dev/null)\"}}; ",
+                bin,
+                kind.name()
+            )
+        });
+        out.push_str(&format!(
+            "        \"{}\") {}compadd -- {} ;;\n",
+            node.path,
+            dynamic,
+            node.words()
+        ));
+    }
+    out.push_str(&format!(
+        r#"    esac
+}}
+
+if [ "$funcstack[1]" = "{func}" ]; then
+    {func} "$@"
+else
+    compdef {func} {bin}
+fi
+"#,
+        func = func,
+        bin = bin
+    ));
+    out
+}
+
+fn fish(bin: &str, spec: &Spec) -> String {
+    let func = format!("__{}", bin.replace('-', "_"));
+    let mut out = format!(
+        r#"function {func}_path
+    set -l cmd_path ""
+    for w in (commandline -opc)[2..-1]
+        switch "$cmd_path>$w"
+            case {cases}
+                set cmd_path (string trim -- "$cmd_path $w")
+        end
+    end
+    echo $cmd_path
+end
+
+function {func}_at
+    set -l cmd_path ({func}_path)
+    test "$cmd_path" = "$argv[1]"
+end
+
+complete -c {bin} -f
+"#,
+        func = func,
+        bin = bin,
+        cases = transitions(spec).replace('|', " ")
+    );
+    for node in &spec.nodes {
+        let cond = format!("{}_at '{}'", func, node.path);
+        let mut words = [&node.subcommands, &node.values]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(kind) = node.dynamic {
+            words = format!("({} __complete {} 2>/dev/null) {}", bin, kind.name(), words);
+        }
+        if !words.trim().is_empty() {
+            out.push_str(&format!(
+                "complete -c {} -n \"{}\" -a \"{}\"\n",
+                bin,
+                cond,
+                words.trim()
+            ));
+        }
+        for flag in &node.flags {
+            let (opt, name) = match flag.strip_prefix("--") {
+                Some(long) => ("-l", long),
+                None => ("-s", flag.trim_start_matches('-')),
+            };
+            let values = spec
+                .value_flags
+                .iter()
+                .find(|(n, _)| n == flag)
+                .map(|(_, values)| format!(" -x -a \"{}\"", values.join(" ")))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "complete -c {} -n \"{}\" {} {}{}\n",
+                bin, cond, opt, name, values
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRepo;
+    use clap::CommandFactory;
+
+    #[test]
+    fn tag_values_match_git_tag_list() {
+        let repo = TempRepo::new();
+        repo.write("a.txt", "a\n");
+        repo.commit_all("init");
+        for tag in ["v0.1.0", "v0.2.0", "nightly"] {
+            repo.git(&["tag", tag]);
+        }
+        assert_eq!(
+            git::tag_names().unwrap().join("\n"),
+            repo.git(&["tag", "--list"])
+        );
+    }
+
+    #[test]
+    fn scripts_complete_release_notes_with_tags() {
+        let cmd = crate::cli::Args::command();
+        for (shell, expected) in [
+            (
+                Shell::Bash,
+                "\"release notes\") COMPREPLY=($(compgen -W \"$(git-wiz __complete tags",
+            ),
+            (
+                Shell::Zsh,
+                "\"release notes\") compadd -- ${(f)\"$(git-wiz __complete tags",
+            ),
+            (Shell::Fish, "-a \"(git-wiz __complete tags 2>/dev/null)"),
+        ] {
+            let script = script(shell, cmd.clone());
+            assert!(script.contains(expected), "{shell:?}:\n{script}");
+        }
+    }
+}
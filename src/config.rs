@@ -1,13 +1,21 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::git::CommitOptions;
+use crate::read_only;
+use crate::release::TagConfig;
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum Provider {
     OpenAI,
     Anthropic,
     Gemini,
+    OpenRouter,
+    Groq,
+    Mistral,
 }
 
 impl std::fmt::Display for Provider {
@@ -16,18 +24,329 @@ impl std::fmt::Display for Provider {
             Provider::OpenAI => write!(f, "OpenAI"),
             Provider::Anthropic => write!(f, "Anthropic"),
             Provider::Gemini => write!(f, "Google Gemini"),
+            Provider::OpenRouter => write!(f, "OpenRouter"),
+            Provider::Groq => write!(f, "Groq"),
+            Provider::Mistral => write!(f, "Mistral"),
+        }
+    }
+}
+
+/// Keyword used for issue footers that should auto-close the referenced issue.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IssueKeyword {
+    #[default]
+    Closes,
+    Fixes,
+    Resolves,
+}
+
+impl std::fmt::Display for IssueKeyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IssueKeyword::Closes => write!(f, "Closes"),
+            IssueKeyword::Fixes => write!(f, "Fixes"),
+            IssueKeyword::Resolves => write!(f, "Resolves"),
+        }
+    }
+}
+
+/// Whether to push the branch after a successful commit.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoPush {
+    #[default]
+    Never,
+    /// Confirm with one keypress after each commit.
+    Ask,
+    Always,
+}
+
+impl std::fmt::Display for AutoPush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutoPush::Never => write!(f, "never"),
+            AutoPush::Ask => write!(f, "ask"),
+            AutoPush::Always => write!(f, "always"),
         }
     }
 }
 
+/// Shape of generated commit messages.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitStyle {
+    /// `type(scope): subject` (Conventional Commits).
+    #[default]
+    Conventional,
+    /// Emoji prefix and a short subject (gitmoji.dev).
+    Gitmoji,
+    /// Imperative subject and a wrapped body, no prefix.
+    Plain,
+}
+
+impl CommitStyle {
+    pub const ALL: [CommitStyle; 3] = [
+        CommitStyle::Conventional,
+        CommitStyle::Gitmoji,
+        CommitStyle::Plain,
+    ];
+}
+
+impl std::fmt::Display for CommitStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommitStyle::Conventional => write!(f, "Conventional Commits"),
+            CommitStyle::Gitmoji => write!(f, "Gitmoji"),
+            CommitStyle::Plain => write!(f, "Plain"),
+        }
+    }
+}
+
+/// Model price in USD per million tokens (see `generator::price_for`).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// Trailers appended to every generated message (see `message::append_trailers`).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Trailers {
+    /// One `Co-authored-by:` trailer each, e.g. `Jane Doe <jane@example.com>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub co_authors: Vec<String>,
+    /// Add `Refs: #N` for an issue number in the branch name (`123-fix`, `issue-45`).
+    #[serde(default)]
+    pub refs_from_branch: bool,
+}
+
+impl Trailers {
+    pub fn is_empty(&self) -> bool {
+        self.co_authors.is_empty() && !self.refs_from_branch
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub provider: Provider,
     pub api_key: String,
     pub model: String,
+    /// Message style the prompts ask for.
+    #[serde(default)]
+    pub commit_style: CommitStyle,
+    /// Language messages are written in, as a name or ISO code (`German`, `de`); `None`
+    /// leaves it to the model (English in practice).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Keyword for auto-close issue footers (`Closes #N`).
+    #[serde(default)]
+    pub issue_keyword: IssueKeyword,
+    /// Open the "Link issues" step automatically after generation when candidates are found.
+    #[serde(default)]
+    pub prompt_link_issues: bool,
+    /// Show the "what will be committed" review and ask before every commit.
+    #[serde(default = "default_true")]
+    pub confirm_commit: bool,
+    /// Push the branch after each commit: never (default), ask, or always.
+    #[serde(default)]
+    pub auto_push: AutoPush,
+    /// `git fetch` before pushing the branch, so the ahead/behind check sees what others
+    /// pushed meanwhile. Off by default: it needs the network.
+    #[serde(default)]
+    pub fetch_before_push: bool,
+    /// Branches that are never force-pushed, even with `--force-with-lease`.
+    #[serde(default = "default_protected_branches")]
+    pub protected_branches: Vec<String>,
+    /// Shell command that checks each generated message (see `hook`): it gets the message
+    /// on stdin, exit 0 accepts it and non-empty stdout replaces it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_generate_command: Option<String>,
+    /// Notify (OSC 9/777 or bell) when a task that took 10s or more finishes.
+    #[serde(default)]
+    pub notify: bool,
+    /// OpenAI-compatible API root (LM Studio, vLLM, LiteLLM…), e.g. `http://localhost:1234/v1`.
+    /// Only used with the OpenAI provider; `None` means api.openai.com.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Proxy for provider requests, e.g. `http://proxy.corp:3128`; replaces `HTTPS_PROXY` /
+    /// `HTTP_PROXY` from the environment (`NO_PROXY` still applies).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// PEM file with extra CA certificates to trust, for proxies that inspect TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_ca_bundle: Option<String>,
+    /// Longest `Retry-After` wait of a rate-limited request before giving up; `None` means
+    /// `http::DEFAULT_MAX_RETRY_WAIT_SECS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retry_wait_secs: Option<u64>,
+    /// Prompt budget in (estimated) tokens, at least `MIN_PROMPT_TOKENS`; `None` uses the
+    /// model's context window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<usize>,
+    /// Reply limit in tokens for every provider; `None` uses the built-in default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    /// Extra globs (git pathspec syntax, e.g. `docs/generated/**`) whose changes are left
+    /// out of the prompt, on top of the built-in lockfile list (`prompt::DEFAULT_EXCLUDES`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompt_exclude: Vec<String>,
+    /// Context lines around each change in the diff sent to the model (`-U<n>`); `None`
+    /// keeps git's. Viewed diffs are not affected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_context_lines: Option<u32>,
+    /// Send the whole function around each change (`-W`).
+    #[serde(default)]
+    pub diff_function_context: bool,
+    /// Show viewed diffs word by word (`--word-diff=plain`), for prose; the TUI Diff tab
+    /// toggles it with `w`.
+    #[serde(default)]
+    pub word_diff: bool,
+    /// System prompt replacing the built-in one (placeholders: `{{hint}}`, `{{branch}}`).
+    /// Takes precedence over `prompt.txt` next to this file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+    /// Wrap generated message bodies at 72 columns (see `message::format_commit_message`).
+    #[serde(default = "default_true")]
+    pub wrap_body: bool,
+    /// Warn when a generated subject is longer than this; defaults to 72 (50 when strict).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_limit: Option<usize>,
+    /// Use the strict 50-character subject limit.
+    #[serde(default)]
+    pub strict_subject: bool,
+    /// When a generated message isn't a valid Conventional Commit, ask the model once to
+    /// fix the format (see `conventional::fix`).
+    #[serde(default)]
+    pub conventional_autofix: bool,
+    /// When no hint is given, derive one from the branch name (see `prompt::branch_hint`).
+    #[serde(default)]
+    pub hint_from_branch: bool,
+    /// Regex for `hint_from_branch`, with `ticket` and `slug` groups; defaults to
+    /// `prompt::DEFAULT_BRANCH_HINT_PATTERN`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_hint_pattern: Option<String>,
+    /// Path prefix to Conventional Commits scope, e.g. `"src/tui/**": "tui"` (see `scope`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scope_map: BTreeMap<String, String>,
+    /// Subjects of this many recent commits go into the prompt as examples of the
+    /// repository's style; 0 sends none.
+    #[serde(default)]
+    pub style_examples: usize,
+    /// Ask for the breaking-change markers and warn when the diff removes or changes
+    /// public items (see `breaking`).
+    #[serde(default = "default_true")]
+    pub detect_breaking: bool,
+    /// Co-author and issue-reference trailers added after generation.
+    #[serde(default, skip_serializing_if = "Trailers::is_empty")]
+    pub trailers: Trailers,
+    /// Prices by model ID, replacing or extending the built-in table for cost estimates.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prices: BTreeMap<String, ModelPrice>,
+    /// Base ref offered for PR descriptions (default: `origin/HEAD`, then main / master).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr_base: Option<String>,
+    /// Add a section for the commits since the previous `v*` tag to `CHANGELOG.md` in
+    /// the release commit (see `release::generate_changelog_section`).
+    #[serde(default)]
+    pub changelog: bool,
+    /// Roll a release back without asking when it fails after the bump commit (most
+    /// often the tag push); see `release::rollback_release`.
+    #[serde(default)]
+    pub release_auto_rollback: bool,
+    /// Refuse to release from a branch that has no upstream yet, instead of creating it
+    /// on the next push (`release::ReleaseGuardrailConfig::require_upstream`).
+    #[serde(default)]
+    pub release_require_upstream: bool,
+    /// Sign-off, GPG signing and hook flags for every commit; `.gitwiz.toml` in the
+    /// repository overrides them (see `repo_config`).
+    #[serde(default, skip_serializing_if = "CommitOptions::is_default")]
+    pub commit: CommitOptions,
+    /// Signing for release tags; `.gitwiz.toml` can require it (see `repo_config`).
+    #[serde(default, skip_serializing_if = "TagConfig::is_default")]
+    pub tag: TagConfig,
+}
+
+/// Smallest `max_prompt_tokens` accepted: below it the system template alone would crowd
+/// out the diff.
+pub const MIN_PROMPT_TOKENS: usize = 1024;
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn default_protected_branches() -> Vec<String> {
+    vec!["master".to_string(), "main".to_string()]
+}
+
+/// `protected_branches` of `cfg`, or the default list without a config.
+pub fn protected_branches(cfg: Option<&Config>) -> Vec<String> {
+    cfg.map_or_else(default_protected_branches, |c| c.protected_branches.clone())
+}
+
+/// Check a user-supplied API base URL and drop any trailing `/`.
+pub fn normalize_base_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let Some((scheme, rest)) = url.split_once("://") else {
+        bail!("Base URL must include a scheme, e.g. http://localhost:1234/v1");
+    };
+    if scheme != "http" && scheme != "https" {
+        bail!("Base URL scheme must be http or https, got '{}'", scheme);
+    }
+    if rest.is_empty() {
+        bail!("Base URL has no host: {}", url);
+    }
+    Ok(url.to_string())
 }
 
 impl Config {
+    /// Build a config for the given provider/model with all optional settings defaulted.
+    pub fn new(provider: Provider, api_key: String, model: String) -> Self {
+        Self {
+            provider,
+            api_key,
+            model,
+            commit_style: CommitStyle::default(),
+            language: None,
+            issue_keyword: IssueKeyword::default(),
+            prompt_link_issues: false,
+            confirm_commit: true,
+            auto_push: AutoPush::Never,
+            fetch_before_push: false,
+            protected_branches: default_protected_branches(),
+            post_generate_command: None,
+            notify: false,
+            base_url: None,
+            proxy_url: None,
+            extra_ca_bundle: None,
+            max_retry_wait_secs: None,
+            max_prompt_tokens: None,
+            max_output_tokens: None,
+            prompt_exclude: Vec::new(),
+            diff_context_lines: None,
+            diff_function_context: false,
+            word_diff: false,
+            prompt_template: None,
+            wrap_body: true,
+            subject_limit: None,
+            strict_subject: false,
+            conventional_autofix: false,
+            hint_from_branch: false,
+            branch_hint_pattern: None,
+            scope_map: BTreeMap::new(),
+            style_examples: 0,
+            detect_breaking: true,
+            trailers: Trailers::default(),
+            prices: BTreeMap::new(),
+            pr_base: None,
+            changelog: false,
+            release_auto_rollback: false,
+            release_require_upstream: false,
+            commit: CommitOptions::default(),
+            tag: TagConfig::default(),
+        }
+    }
+
     pub fn get_path() -> Result<PathBuf> {
         let mut path = dirs::config_dir().context("Could not determine config directory")?;
         path.push("git-wiz");
@@ -45,17 +364,67 @@ impl Config {
             return Ok(None);
         }
 
-        let content = fs::read_to_string(&path).context("Failed to read config file")?;
-        let config: Config =
-            serde_json::from_str(&content).context("Failed to parse config file")?;
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Config = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid settings in {}", path.display()))?;
 
         Ok(Some(config))
     }
 
+    /// Reject values that parse but can't work.
+    fn validate(&self) -> Result<()> {
+        if let Some(max) = self.max_prompt_tokens {
+            if max < MIN_PROMPT_TOKENS {
+                bail!(
+                    "max_prompt_tokens is {}, but must be at least {}",
+                    max,
+                    MIN_PROMPT_TOKENS
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
+        read_only::check("Changing the config")?;
         let path = Self::get_path()?;
         let content = serde_json::to_string_pretty(self).context("Failed to serialize config")?;
         fs::write(&path, content).context("Failed to write config file")?;
         Ok(())
     }
+
+    /// Delete the config file, if there is one.
+    pub fn delete() -> Result<()> {
+        read_only::check("Changing the config")?;
+        let path = Self::get_path()?;
+        if path.exists() {
+            fs::remove_file(&path).context("Failed to remove config file")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_prompt_tokens_has_a_floor() {
+        let mut cfg = Config::new(Provider::Gemini, "key".to_string(), "gemini".to_string());
+        assert!(cfg.validate().is_ok());
+
+        cfg.max_prompt_tokens = Some(MIN_PROMPT_TOKENS);
+        assert!(cfg.validate().is_ok());
+
+        cfg.max_prompt_tokens = Some(100);
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "max_prompt_tokens is 100, but must be at least 1024");
+
+        cfg.max_prompt_tokens = Some(0);
+        assert!(cfg.validate().is_err());
+    }
 }
@@ -0,0 +1,99 @@
+//! Resolving merge conflicts before generation: list the conflicted files, open them in
+//! the user's editor, and stage the ones that no longer contain conflict markers.
+//!
+//! Once every conflict is staged, generation runs in merge mode: the merge subject git
+//! prepared (`MERGE_MSG`) is passed to the model as a hint, and committing concludes the
+//! merge.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+
+use crate::git;
+use crate::read_only;
+
+/// Paths (relative to the repository root) that still have unmerged index entries.
+pub fn conflicted_files() -> Result<Vec<String>> {
+    Ok(git::snapshot()?
+        .unmerged()
+        .map(|e| e.path.clone())
+        .collect())
+}
+
+/// True if the work tree file still contains `<<<<<<<` / `>>>>>>>` markers.
+///
+/// A deleted file (delete/modify conflicts) counts as resolved.
+pub fn has_markers(path: &str) -> Result<bool> {
+    let full = git::repo_root()?.join(path);
+    if !full.exists() {
+        return Ok(false);
+    }
+    let bytes = fs::read(&full).with_context(|| format!("Failed to read {}", full.display()))?;
+    Ok(String::from_utf8_lossy(&bytes)
+        .lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> ")))
+}
+
+/// Open `path` in the editor git would use and wait for it to close.
+///
+/// Interactive: TUI callers run this with the TUI suspended.
+pub fn open_in_editor(path: &str) -> Result<()> {
+    read_only::check("Editing files")?;
+    git::edit_file(&git::repo_root()?.join(path))
+}
+
+/// `git add` (or `git rm` for a deleted file) to mark `path` as resolved.
+pub fn mark_resolved(path: &str) -> Result<()> {
+    read_only::check("Staging")?;
+    let root = git::repo_root()?;
+    let verb = if root.join(path).exists() {
+        "add"
+    } else {
+        "rm"
+    };
+    let o = git::command()
+        .current_dir(&root)
+        .args([verb, "--", path])
+        .output()
+        .with_context(|| format!("Failed to run git {} {}", verb, path))?;
+    if !o.status.success() {
+        bail!(
+            "git {} {} failed: {}",
+            verb,
+            path,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// The subject git prepared for the merge being concluded (e.g. `Merge branch 'x'`),
+/// or `None` when no merge is in progress.
+pub fn merge_in_progress() -> Result<Option<String>> {
+    let o = git::command()
+        .args(["rev-parse", "-q", "--verify", "MERGE_HEAD"])
+        .output()
+        .context("Failed to run git rev-parse MERGE_HEAD")?;
+    if !o.status.success() {
+        return Ok(None);
+    }
+
+    let path = git::git_path("MERGE_MSG")?;
+    let subject = fs::read_to_string(&path)
+        .ok()
+        .and_then(|msg| msg.lines().next().map(str::to_string))
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| "Merge".to_string());
+    Ok(Some(subject))
+}
+
+/// Generation hint for a merge commit, combined with the user's own hint.
+pub fn merge_hint(subject: &str, hint: Option<&str>) -> String {
+    let base = format!(
+        "This is a merge commit ({}). Describe what the merge brings in and how conflicts were resolved.",
+        subject
+    );
+    match hint {
+        Some(h) if !h.trim().is_empty() => format!("{} {}", base, h.trim()),
+        _ => base,
+    }
+}
@@ -0,0 +1,301 @@
+//! Conventional Commits check for generated messages: `type(scope)!: subject`, a blank
+//! line before the body, and `BREAKING CHANGE:` footers.
+//!
+//! Only applied when the configured style is Conventional Commits and no custom prompt
+//! template is in use. Violations are shown as a warning; with `conventional_autofix` the
+//! model gets one follow-up call to fix the format.
+
+use anyhow::{bail, Result};
+use std::fmt;
+
+use crate::config::{CommitStyle, Config};
+use crate::generator::Generator;
+use crate::message::{self, FormatOptions};
+use crate::prompt;
+
+/// Types accepted in the header (the Angular convention the spec builds on).
+pub const TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+const FIX_PROMPT: &str = "You fix the format of git commit messages. The user sends a \
+    commit message that breaks the Conventional Commits specification for the reasons \
+    listed below. Rewrite it as `<type>(<optional scope>): <subject>`, a blank line, then \
+    the body and footers, keeping the meaning and wording as far as possible. Only output \
+    the commit message itself, no wrapper text or markdown code blocks.";
+
+/// The parsed header of a valid message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    /// `!` in the header or a `BREAKING CHANGE:` footer.
+    pub breaking: bool,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The message is empty.
+    Empty,
+    /// No `type: ` prefix at all.
+    MissingType,
+    UnknownType(String),
+    /// `type(scope` without the closing parenthesis.
+    UnclosedScope,
+    EmptyScope,
+    /// The prefix is not followed by `: `.
+    MissingSeparator,
+    EmptySubject,
+    /// The body starts right under the header.
+    MissingBlankLine,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Empty => write!(f, "the message is empty"),
+            Violation::MissingType => write!(f, "the subject has no `type:` prefix"),
+            Violation::UnknownType(kind) => {
+                write!(f, "`{}` is not a known type ({})", kind, TYPES.join(", "))
+            }
+            Violation::UnclosedScope => write!(f, "the scope is missing its `)`"),
+            Violation::EmptyScope => write!(f, "the scope is empty"),
+            Violation::MissingSeparator => write!(f, "the type must be followed by `: `"),
+            Violation::EmptySubject => write!(f, "the subject is empty"),
+            Violation::MissingBlankLine => {
+                write!(
+                    f,
+                    "the body must be separated from the subject by a blank line"
+                )
+            }
+        }
+    }
+}
+
+/// Parse `message`, or list everything that keeps it from being a Conventional Commit.
+pub fn parse(message: &str) -> std::result::Result<ConventionalCommit, Vec<Violation>> {
+    let mut lines = message.trim().lines();
+    let Some(header) = lines.next().map(str::trim_end) else {
+        return Err(vec![Violation::Empty]);
+    };
+
+    let mut violations = Vec::new();
+    if lines.next().is_some_and(|l| !l.trim().is_empty()) {
+        violations.push(Violation::MissingBlankLine);
+    }
+
+    let Some(split) = header.find([':', '(', '!', ' ']) else {
+        violations.insert(0, Violation::MissingType);
+        return Err(violations);
+    };
+    let kind = &header[..split];
+    if kind.is_empty() || header[split..].starts_with(' ') {
+        violations.insert(0, Violation::MissingType);
+        return Err(violations);
+    }
+    if !TYPES.contains(&kind) {
+        violations.push(Violation::UnknownType(kind.to_string()));
+    }
+
+    let mut rest = &header[split..];
+    let mut scope = None;
+    if let Some(after) = rest.strip_prefix('(') {
+        match after.split_once(')') {
+            // Scopes may contain slashes, dots and dashes (`api/v2`, `deps-dev`).
+            Some((inner, after)) => {
+                if inner.trim().is_empty() {
+                    violations.push(Violation::EmptyScope);
+                } else {
+                    scope = Some(inner.to_string());
+                }
+                rest = after;
+            }
+            None => {
+                violations.push(Violation::UnclosedScope);
+                return Err(violations);
+            }
+        }
+    }
+    let bang = rest.starts_with('!');
+    if bang {
+        rest = &rest[1..];
+    }
+    let Some(subject) = rest.strip_prefix(": ") else {
+        violations.push(Violation::MissingSeparator);
+        return Err(violations);
+    };
+    if subject.trim().is_empty() {
+        violations.push(Violation::EmptySubject);
+    }
+
+    if !violations.is_empty() {
+        return Err(violations);
+    }
+    Ok(ConventionalCommit {
+        kind: kind.to_string(),
+        scope,
+        breaking: bang || has_breaking_footer(message),
+        subject: subject.trim().to_string(),
+    })
+}
+
+/// True when a footer line starts with `BREAKING CHANGE:` (or `BREAKING-CHANGE:`).
+pub fn has_breaking_footer(message: &str) -> bool {
+    message
+        .lines()
+        .skip(1)
+        .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"))
+}
+
+/// Whether messages generated with `cfg` are meant to be Conventional Commits.
+pub fn applies(cfg: Option<&Config>) -> bool {
+    cfg.is_none_or(|c| c.commit_style == CommitStyle::Conventional)
+        && prompt::custom_template(cfg).is_none()
+}
+
+/// Violations of `message`, or none when the check doesn't apply to `cfg`.
+pub fn check(message: &str, cfg: Option<&Config>) -> Vec<Violation> {
+    if !applies(cfg) {
+        return Vec::new();
+    }
+    parse(message).err().unwrap_or_default()
+}
+
+/// One follow-up call asking the model to fix the format of `message`.
+pub async fn fix(generator: &Generator, message: &str, violations: &[Violation]) -> Result<String> {
+    let reasons: Vec<String> = violations.iter().map(|v| format!("- {}", v)).collect();
+    let system = format!("{}\n\nProblems:\n{}", FIX_PROMPT, reasons.join("\n"));
+    let fixed = generator.complete(&system, message).await?.message;
+    if fixed.trim().is_empty() {
+        bail!("The model returned an empty message");
+    }
+    let cfg = Config::load().ok().flatten();
+    Ok(message::format_commit_message(
+        &fixed,
+        &FormatOptions::from_config(cfg.as_ref()),
+    ))
+}
+
+/// e.g. `Not a Conventional Commit: the subject has no `type:` prefix.`
+pub fn warning(violations: &[Violation]) -> Option<String> {
+    if violations.is_empty() {
+        return None;
+    }
+    let reasons: Vec<String> = violations.iter().map(ToString::to_string).collect();
+    Some(format!(
+        "Not a Conventional Commit: {}.",
+        reasons.join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Provider;
+
+    #[test]
+    fn scopes_may_contain_slashes_dots_and_dashes() {
+        for (message, scope) in [
+            ("feat(api/v2): add pagination", "api/v2"),
+            ("build(deps-dev): bump vitest", "deps-dev"),
+            ("fix(ui.preview): keep the cursor", "ui.preview"),
+        ] {
+            let commit = parse(message).unwrap();
+            assert_eq!(commit.scope.as_deref(), Some(scope), "{}", message);
+            assert!(!commit.breaking);
+        }
+        assert_eq!(
+            parse("docs: fix a typo").unwrap(),
+            ConventionalCommit {
+                kind: "docs".to_string(),
+                scope: None,
+                breaking: false,
+                subject: "fix a typo".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn breaking_changes_come_from_the_bang_or_a_footer() {
+        let bang = parse("feat(config)!: drop the legacy keys").unwrap();
+        assert!(bang.breaking);
+        assert_eq!(bang.subject, "drop the legacy keys");
+        assert!(parse("refactor!: rename the crate").unwrap().breaking);
+
+        for footer in ["BREAKING CHANGE", "BREAKING-CHANGE"] {
+            let message = format!(
+                "feat: new flag\n\nSome body.\n\n{}: `--old` is gone",
+                footer
+            );
+            assert!(parse(&message).unwrap().breaking, "{}", footer);
+        }
+        // Only footers count, not the words in the subject.
+        assert!(
+            !parse("docs: explain BREAKING CHANGE: footers")
+                .unwrap()
+                .breaking
+        );
+    }
+
+    #[test]
+    fn multi_paragraph_bodies_are_accepted() {
+        let message = "fix(git): quote paths\n\nPaths with spaces broke the diff.\n\nThey are now passed after `--`.\n\nRefs: #42";
+        let commit = parse(message).unwrap();
+        assert_eq!(commit.kind, "fix");
+        assert_eq!(commit.subject, "quote paths");
+
+        assert_eq!(
+            parse("fix: quote paths\nPaths with spaces broke the diff."),
+            Err(vec![Violation::MissingBlankLine])
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        for (message, expected) in [
+            ("", vec![Violation::Empty]),
+            ("Add pagination", vec![Violation::MissingType]),
+            ("add pagination to the api", vec![Violation::MissingType]),
+            (
+                "feature: add pagination",
+                vec![Violation::UnknownType("feature".to_string())],
+            ),
+            ("feat(api: add pagination", vec![Violation::UnclosedScope]),
+            ("feat(): add pagination", vec![Violation::EmptyScope]),
+            (
+                "feat(api) add pagination",
+                vec![Violation::MissingSeparator],
+            ),
+            ("feat:add pagination", vec![Violation::MissingSeparator]),
+            ("feat:  ", vec![Violation::MissingSeparator]),
+            ("feat: ", vec![Violation::MissingSeparator]),
+            (
+                "Feat(): x\nbody",
+                vec![
+                    Violation::MissingBlankLine,
+                    Violation::UnknownType("Feat".to_string()),
+                    Violation::EmptyScope,
+                ],
+            ),
+        ] {
+            assert_eq!(parse(message), Err(expected), "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn warning_lists_the_reasons() {
+        assert_eq!(warning(&[]), None);
+        assert_eq!(
+            warning(&[Violation::MissingBlankLine, Violation::EmptyScope]).as_deref(),
+            Some(
+                "Not a Conventional Commit: the body must be separated from the subject \
+                 by a blank line; the scope is empty."
+            )
+        );
+
+        let mut cfg = Config::new(Provider::Gemini, String::new(), String::new());
+        cfg.commit_style = CommitStyle::Gitmoji;
+        assert!(check("✨ Add pagination", Some(&cfg)).is_empty());
+    }
+}
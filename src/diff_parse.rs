@@ -0,0 +1,367 @@
+//! Unified diffs split into files and hunks, and patches rebuilt from single hunks, for
+//! staging hunk by hunk in the TUI.
+//!
+//! A file's header (`diff --git` up to its first `@@`, including any `GIT binary patch`)
+//! goes into every patch built from it. Each hunk is applied on its own, so its positions
+//! are rebased on the hunks of the same file that are already in the index: a hunk still
+//! applies after the ones before it were skipped or failed.
+
+/// What a file diff does to the file as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Modified,
+    Added,
+    Deleted,
+}
+
+/// One file of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: String,
+    pub change: FileChange,
+    /// `(old, new)` file mode when the diff changes it.
+    pub mode_change: Option<(String, String)>,
+    pub binary: bool,
+    header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+/// One `@@` hunk of a file diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Text after the closing `@@`, usually the enclosing function.
+    pub section: String,
+    /// The body: context, `-` and `+` lines, and `\ No newline at end of file`.
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    /// e.g. `@@ -12,7 +12,9 @@ fn main()`
+    pub fn header(&self) -> String {
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_lines, self.new_start, self.new_lines
+        );
+        if self.section.is_empty() {
+            header
+        } else {
+            format!("{} {}", header, self.section)
+        }
+    }
+
+    /// `(added, removed)` lines.
+    pub fn counts(&self) -> (usize, usize) {
+        let added = self.lines.iter().filter(|l| l.starts_with('+')).count();
+        let removed = self.lines.iter().filter(|l| l.starts_with('-')).count();
+        (added, removed)
+    }
+
+    /// How many lines the hunk adds to the file (negative when it removes more).
+    fn delta(&self) -> isize {
+        self.new_lines as isize - self.old_lines as isize
+    }
+}
+
+impl FileDiff {
+    /// Header and every hunk, as in the diff; for files without hunks (mode changes,
+    /// empty or binary files) this is the only patch there is.
+    pub fn whole_patch(&self) -> String {
+        let mut lines = self.header.clone();
+        for hunk in &self.hunks {
+            lines.push(hunk.header());
+            lines.extend(hunk.lines.iter().cloned());
+        }
+        patch_text(lines)
+    }
+
+    /// A patch of hunk `index` alone, positioned for a target that already has the hunks
+    /// in `present` (earlier hunks of this file; later ones don't move it). The mode change,
+    /// if any, comes along only `with_mode`, so it is applied once.
+    pub fn hunk_patch(&self, index: usize, present: &[usize], with_mode: bool) -> String {
+        let hunk = &self.hunks[index];
+        let before: isize = self.hunks[..index].iter().map(Hunk::delta).sum();
+        let applied: isize = present
+            .iter()
+            .filter(|&&i| i < index)
+            .map(|&i| self.hunks[i].delta())
+            .sum();
+        let rebased = Hunk {
+            old_start: (hunk.old_start as isize + applied).max(0) as usize,
+            new_start: (hunk.new_start as isize - before + applied).max(0) as usize,
+            ..hunk.clone()
+        };
+
+        let mut lines: Vec<String> = self
+            .header
+            .iter()
+            .filter(|l| with_mode || !(l.starts_with("old mode ") || l.starts_with("new mode ")))
+            .cloned()
+            .collect();
+        lines.push(rebased.header());
+        lines.extend(rebased.lines);
+        patch_text(lines)
+    }
+}
+
+fn patch_text(lines: Vec<String>) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Split a `git diff` into its files and hunks. Anything outside `diff --git` sections
+/// (combined diffs of unmerged files, `* Unmerged path` notes) is left out. Lines keep a
+/// trailing `\r`, so CRLF files still apply.
+pub fn parse(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut in_file = false;
+    let diff = diff.strip_suffix('\n').unwrap_or(diff);
+    for line in diff.split('\n') {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            in_file = true;
+            files.push(FileDiff {
+                path: header_path(rest),
+                change: FileChange::Modified,
+                mode_change: None,
+                binary: false,
+                header: vec![line.to_string()],
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        if line.starts_with("diff ") || line.starts_with("* ") {
+            in_file = false;
+        }
+        let Some(file) = files.last_mut().filter(|_| in_file) else {
+            continue;
+        };
+        if let Some(hunk) = parse_hunk_header(line) {
+            file.hunks.push(hunk);
+            continue;
+        }
+        if let Some(hunk) = file.hunks.last_mut() {
+            if line.starts_with([' ', '+', '-', '\\']) {
+                hunk.lines.push(line.to_string());
+            }
+            continue;
+        }
+
+        if line.starts_with("new file mode ") {
+            file.change = FileChange::Added;
+        } else if line.starts_with("deleted file mode ") {
+            file.change = FileChange::Deleted;
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            file.mode_change = Some((mode.to_string(), String::new()));
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            if let Some((_, new)) = file.mode_change.as_mut() {
+                *new = mode.to_string();
+            }
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if path != "/dev/null" {
+                file.path = unquote(path);
+            }
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            if file.change == FileChange::Deleted {
+                file.path = unquote(path);
+            }
+        }
+        file.header.push(line.to_string());
+    }
+    files
+}
+
+/// `a/x b/x` → `x`. Only a fallback: `+++`/`---` give the path when there are hunks.
+fn header_path(rest: &str) -> String {
+    let b = rest.rsplit_once(" b/").map_or(rest, |(_, b)| b);
+    unquote(b)
+}
+
+/// `b/x` → `x`. Paths git quoted for special characters (`"b/x y"`) lose the quotes; the
+/// escapes inside stay, this is only for display.
+fn unquote(path: &str) -> String {
+    let path = path.trim_end_matches('\t').trim_matches('"');
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// `@@ -a[,b] +c[,d] @@ section`; counts default to 1, as in the diff format.
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |r: &str| -> Option<(usize, usize)> {
+        match r.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(old)?;
+    let (new_start, new_lines) = range(new)?;
+    Some(Hunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        section: section.trim().to_string(),
+        lines: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git;
+    use crate::test_support::TempRepo;
+
+    const TWO_HUNKS: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn one() {}
++fn one_and_a_half() {}
+ fn two() {}
+ fn three() {}
+@@ -10,4 +11,3 @@ fn nine() {}
+ fn ten() {}
+-fn eleven() {}
+ fn twelve() {}
+-fn thirteen() {}
++fn thirteen() {}
+\\ No newline at end of file
+";
+
+    #[test]
+    fn parses_every_hunk_of_a_file() {
+        let files = parse(TWO_HUNKS);
+        assert_eq!(files.len(), 1);
+        let file = &files[0];
+        assert_eq!(file.path, "src/lib.rs");
+        assert_eq!(file.change, FileChange::Modified);
+        assert_eq!(file.hunks.len(), 2);
+
+        let first = &file.hunks[0];
+        assert_eq!(first.header(), "@@ -1,3 +1,4 @@");
+        assert_eq!(first.counts(), (1, 0));
+        let second = &file.hunks[1];
+        assert_eq!(
+            (
+                second.old_start,
+                second.old_lines,
+                second.new_start,
+                second.new_lines
+            ),
+            (10, 4, 11, 3)
+        );
+        assert_eq!(second.section, "fn nine() {}");
+        assert_eq!(second.counts(), (1, 2));
+        assert_eq!(second.lines.last().unwrap(), "\\ No newline at end of file");
+        assert_eq!(file.whole_patch(), TWO_HUNKS);
+    }
+
+    #[test]
+    fn reads_added_and_deleted_files() {
+        let diff = "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3b18e51
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+diff --git a/old.txt b/old.txt
+deleted file mode 100755
+index 3b18e51..0000000
+--- a/old.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-hello
+-world
+diff --git a/mode.sh b/mode.sh
+old mode 100644
+new mode 100755
+";
+        let files = parse(diff);
+        let summary: Vec<_> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.change, f.hunks.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("new.txt", FileChange::Added, 1),
+                ("old.txt", FileChange::Deleted, 1),
+                ("mode.sh", FileChange::Modified, 0),
+            ]
+        );
+        assert_eq!(files[0].hunks[0].header(), "@@ -0,0 +1,1 @@");
+        assert_eq!(
+            files[2].mode_change,
+            Some(("100644".to_string(), "100755".to_string()))
+        );
+    }
+
+    #[test]
+    fn hunk_patch_rebases_on_the_hunks_present() {
+        let file = &parse(TWO_HUNKS)[0];
+        let header = |patch: String| {
+            patch
+                .lines()
+                .find(|l| l.starts_with("@@"))
+                .unwrap()
+                .to_string()
+        };
+        // The second hunk alone: the first one's added line isn't in the target.
+        assert_eq!(
+            header(file.hunk_patch(1, &[], true)),
+            "@@ -10,4 +10,3 @@ fn nine() {}"
+        );
+        assert_eq!(
+            header(file.hunk_patch(1, &[0], true)),
+            "@@ -11,4 +11,3 @@ fn nine() {}"
+        );
+        // Later hunks never move an earlier one.
+        assert_eq!(header(file.hunk_patch(0, &[1], true)), "@@ -1,3 +1,4 @@");
+    }
+
+    #[test]
+    fn stages_a_subset_of_hunks() {
+        let repo = TempRepo::new();
+        let lines: Vec<String> = (1..=20).map(|n| format!("line {n}")).collect();
+        repo.write("file.txt", &(lines.join("\n") + "\n"));
+        repo.commit_all("init");
+
+        let mut changed = lines.clone();
+        changed.insert(2, "added near the top".to_string());
+        changed.remove(10);
+        changed[18] = "changed at the end".to_string();
+        repo.write("file.txt", &(changed.join("\n") + "\n"));
+
+        let files = git::hunks(false).unwrap();
+        assert_eq!(files[0].hunks.len(), 3);
+        // Skip the first hunk: the others must still apply.
+        assert!(git::apply_hunks(&files[0], &[1, 2], false)
+            .unwrap()
+            .is_empty());
+        let staged = git::hunks(true).unwrap();
+        let staged_lines: Vec<_> = staged[0].hunks.iter().flat_map(|h| &h.lines).collect();
+        assert!(staged_lines.contains(&&"-line 10".to_string()));
+        assert!(staged_lines.contains(&&"+changed at the end".to_string()));
+        assert!(!staged_lines.contains(&&"+added near the top".to_string()));
+
+        // And the skipped one applies on top of them.
+        let rest = git::hunks(false).unwrap();
+        assert_eq!(rest[0].hunks.len(), 1);
+        assert!(git::apply_hunks(&rest[0], &[0], false).unwrap().is_empty());
+        assert!(git::hunks(false).unwrap().is_empty());
+    }
+}
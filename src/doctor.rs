@@ -0,0 +1,269 @@
+//! `git-wiz doctor`: diagnose the environment one check at a time.
+//!
+//! Hard checks (git, repository, config, provider access) make the command fail;
+//! soft checks (git version, remote, hooksPath) only warn. Every problem comes with
+//! the command that usually fixes it.
+
+use anyhow::{bail, Result};
+use colored::*;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::generator::{self, InvalidApiKey, ModelUnavailable};
+use crate::git;
+use crate::runtime;
+
+/// Oldest git with `git restore` (used for unstaging).
+const MIN_GIT_VERSION: (u32, u32) = (2, 23);
+
+enum Outcome {
+    Pass(String),
+    Warn(String, Option<String>),
+    Fail(String, Option<String>),
+    Skip(String),
+}
+
+/// Run all checks, printing each result as it completes.
+///
+/// `offline` skips the checks that need the network (provider and remote).
+pub fn run(offline: bool) -> Result<()> {
+    println!("{}", "git-wiz doctor".bold());
+
+    let mut failures = 0;
+    let mut report = |name: &str, outcome: Outcome| {
+        let (mark, detail, fix) = match outcome {
+            Outcome::Pass(d) => ("✓".green(), d, None),
+            Outcome::Warn(d, fix) => ("!".yellow(), d, fix),
+            Outcome::Fail(d, fix) => {
+                failures += 1;
+                ("✗".red(), d, fix)
+            }
+            Outcome::Skip(d) => ("-".dimmed(), d, None),
+        };
+        println!("  {} {:<16} {}", mark, name, detail);
+        if let Some(fix) = fix {
+            println!("    {} {}", "fix:".dimmed(), fix.cyan());
+        }
+    };
+
+    let git_ok = match git_version() {
+        Some(version) => {
+            report("git", check_git_version(&version));
+            true
+        }
+        None => {
+            report(
+                "git",
+                Outcome::Fail(
+                    "git was not found on PATH".to_string(),
+                    Some("install git from https://git-scm.com/downloads".to_string()),
+                ),
+            );
+            false
+        }
+    };
+
+    let repo_ok = git_ok && git::is_repo();
+    report(
+        "repository",
+        if repo_ok {
+            Outcome::Pass(git::repo_root()?.display().to_string())
+        } else {
+            Outcome::Fail(
+                "not inside a git work tree".to_string(),
+                Some("cd into your project, pass `--repo <path>`, or run `git init`".to_string()),
+            )
+        },
+    );
+
+    let config = check_config();
+    let cfg = match config {
+        Ok((outcome, cfg)) => {
+            report("config", outcome);
+            cfg
+        }
+        Err(outcome) => {
+            report("config", outcome);
+            None
+        }
+    };
+
+    report(
+        "provider",
+        match (&cfg, offline) {
+            (_, true) => Outcome::Skip("skipped (--offline)".to_string()),
+            (None, false) => Outcome::Skip("skipped (no config)".to_string()),
+            (Some(cfg), false) => check_provider(cfg),
+        },
+    );
+
+    report(
+        "remote",
+        match (repo_ok, offline) {
+            (_, true) => Outcome::Skip("skipped (--offline)".to_string()),
+            (false, false) => Outcome::Skip("skipped (not a repository)".to_string()),
+            (true, false) => check_remote(),
+        },
+    );
+
+    report(
+        "hooksPath",
+        if repo_ok {
+            check_hooks_path()
+        } else {
+            Outcome::Skip("skipped (not a repository)".to_string())
+        },
+    );
+
+    if failures > 0 {
+        bail!(
+            "{} check{} failed.",
+            failures,
+            if failures == 1 { "" } else { "s" }
+        );
+    }
+    println!("\n{}", "All required checks passed.".green());
+    Ok(())
+}
+
+fn git_version() -> Option<String> {
+    let output = git::command().arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Parse `git version 2.43.0` / `git version 2.43.0.windows.1` into (major, minor).
+fn parse_git_version(text: &str) -> Option<(u32, u32)> {
+    let version = text.split_whitespace().nth(2)?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn check_git_version(text: &str) -> Outcome {
+    match parse_git_version(text) {
+        Some(v) if v >= MIN_GIT_VERSION => Outcome::Pass(text.to_string()),
+        Some(_) => Outcome::Warn(
+            format!(
+                "{} (older than {}.{}; some commands use slower fallbacks)",
+                text, MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+            ),
+            Some("upgrade git".to_string()),
+        ),
+        None => Outcome::Warn(format!("unrecognized version: {}", text), None),
+    }
+}
+
+fn check_config() -> std::result::Result<(Outcome, Option<Config>), Outcome> {
+    let path = Config::get_path().map_err(|e| Outcome::Fail(format!("{:#}", e), None))?;
+    match Config::load() {
+        Ok(Some(cfg)) => match generator::endpoint(&cfg) {
+            Ok(endpoint) => Ok((
+                Outcome::Pass(format!(
+                    "{} ({}, {}, {})",
+                    path.display(),
+                    cfg.provider,
+                    cfg.model,
+                    endpoint
+                )),
+                Some(cfg),
+            )),
+            Err(e) => Err(Outcome::Fail(
+                format!("{:#}", e),
+                Some("git-wiz setup (enter the base URL with http:// or https://)".to_string()),
+            )),
+        },
+        Ok(None) => Err(Outcome::Fail(
+            format!("no config at {}", path.display()),
+            Some("git-wiz setup".to_string()),
+        )),
+        Err(e) => Err(Outcome::Fail(
+            format!("{:#}", e),
+            Some("git-wiz setup (rewrites the file)".to_string()),
+        )),
+    }
+}
+
+fn check_provider(cfg: &Config) -> Outcome {
+    match runtime::block_on(generator::check_access(cfg)) {
+        Ok(latency) => Outcome::Pass(format!(
+            "{} reachable, key accepted, model `{}` available ({} ms)",
+            cfg.provider,
+            cfg.model,
+            latency.as_millis()
+        )),
+        Err(e) if e.downcast_ref::<InvalidApiKey>().is_some() => Outcome::Fail(
+            e.to_string(),
+            Some("git-wiz setup (enter a new API key)".to_string()),
+        ),
+        Err(e) if e.downcast_ref::<ModelUnavailable>().is_some() => Outcome::Fail(
+            e.to_string(),
+            Some("git-wiz setup (choose another model)".to_string()),
+        ),
+        Err(e) => Outcome::Fail(
+            format!("{:#}", e),
+            Some("check your network/proxy settings".to_string()),
+        ),
+    }
+}
+
+fn check_remote() -> Outcome {
+    let output = git::command()
+        .args(["ls-remote", "--heads", "origin"])
+        // Never block on a credential prompt.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output();
+
+    match output {
+        Ok(o) if o.status.success() => Outcome::Pass("origin is reachable".to_string()),
+        Ok(o) => {
+            let stderr = String::from_utf8_lossy(&o.stderr);
+            let first = stderr.lines().next().unwrap_or_default().trim().to_string();
+            if first.contains("'origin' does not appear to be a git repository") {
+                Outcome::Warn(
+                    "no `origin` remote (push and release need one)".to_string(),
+                    Some("git remote add origin <url>".to_string()),
+                )
+            } else {
+                Outcome::Warn(
+                    format!("origin is not reachable: {}", first),
+                    Some("check the remote URL and your credentials (git remote -v)".to_string()),
+                )
+            }
+        }
+        Err(e) => Outcome::Warn(format!("failed to run git ls-remote: {}", e), None),
+    }
+}
+
+fn check_hooks_path() -> Outcome {
+    let output = git::command()
+        .args(["config", "--get", "core.hooksPath"])
+        .output();
+    let value = match output {
+        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).trim().to_string(),
+        _ => return Outcome::Pass("default (.git/hooks)".to_string()),
+    };
+
+    // Relative hooksPath values are resolved against the work tree root.
+    let mut path = PathBuf::from(&value);
+    if path.is_relative() {
+        if let Ok(o) = git::command()
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+        {
+            path = PathBuf::from(String::from_utf8_lossy(&o.stdout).trim()).join(path);
+        }
+    }
+
+    if path.is_dir() {
+        Outcome::Pass(value)
+    } else {
+        Outcome::Warn(
+            format!("core.hooksPath points to a missing directory: {}", value),
+            Some("git config --unset core.hooksPath".to_string()),
+        )
+    }
+}
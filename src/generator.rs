@@ -1,102 +1,614 @@
 use anyhow::{bail, Context, Result};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde_json::json;
-use std::time::Duration;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
-pub struct MockGenerator;
+use crate::config::{self, CommitStyle, Config, ModelPrice, Provider};
+use crate::conventional;
+use crate::git;
+use crate::http;
+use crate::message::{self, FormatOptions};
+use crate::prompt::{self, PromptOptions, PromptSizes, Refinement};
+use crate::usage::{self, UsageEntry};
+
+/// Sampling settings shared by all providers so output length/style stays comparable.
+const TEMPERATURE: f64 = 0.7;
+/// Reply limit when `max_output_tokens` isn't set; Anthropic and Gemini require one.
+pub const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 1024;
+
+/// API root used for OpenAI when the config has no `base_url`.
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// OpenRouter's OpenAI-compatible API root.
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// Groq's OpenAI-compatible API root.
+pub const GROQ_BASE_URL: &str = "https://api.groq.com/openai/v1";
+
+/// Mistral's OpenAI-compatible API root.
+pub const MISTRAL_BASE_URL: &str = "https://api.mistral.ai/v1";
+
+/// Attribution headers OpenRouter asks apps to send (shown on its app rankings).
+const OPENROUTER_HEADERS: &[(&str, &str)] = &[
+    ("HTTP-Referer", "https://github.com/AyoubAchour/Git-Wiz"),
+    ("X-Title", "Git Wiz"),
+];
+
+/// A boxed future, so `CommitMessageGenerator` can be used as a trait object.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Called with the reply so far, each time more of it arrives.
+pub type OnToken<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+/// One way of turning a diff into a commit message: a provider's API or the mock.
+///
+/// A provider implements `complete` (the HTTP call) and gets an arm in `from_config`;
+/// usage recording and message formatting are added by `Generator` for all of them.
+/// Providers that can stream the reply also implement `complete_streaming`.
+pub trait CommitMessageGenerator: Send + Sync {
+    /// `None` for the mock generator, whose calls are not recorded.
+    fn provider(&self) -> Option<Provider>;
+
+    fn model(&self) -> &str;
+
+    fn prompt_options(&self) -> &PromptOptions;
+
+    /// Send one system + user prompt pair and return the cleaned reply.
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>>;
+
+    /// `complete`, passing the reply to `on_token` as it arrives. Without streaming support
+    /// the whole reply is passed once.
+    fn complete_streaming<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(async move {
+            let generation = self.complete(system_prompt, user_prompt).await?;
+            on_token(&generation.message);
+            Ok(generation)
+        })
+    }
+
+    /// Build the commit-message prompt for `diff` (within the budget) and send it.
+    fn generate<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(async move {
+            let provider = self.provider();
+            let prompt = prompt::build(
+                provider.as_ref(),
+                self.prompt_options(),
+                diff,
+                hint.as_deref(),
+            );
+            let mut generation = self.complete(&prompt.system, &prompt.user).await?;
+            generation.prompt_sizes = prompt.sizes;
+            Ok(generation)
+        })
+    }
+
+    /// Rewrite `refinement.previous` for `diff` per the feedback, streaming the reply.
+    fn refine<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+        refinement: &'a Refinement,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(async move {
+            let provider = self.provider();
+            let prompt = prompt::build_refinement(
+                provider.as_ref(),
+                self.prompt_options(),
+                diff,
+                hint.as_deref(),
+                refinement,
+            );
+            let mut generation = self
+                .complete_streaming(&prompt.system, &prompt.user, on_token)
+                .await?;
+            generation.prompt_sizes = prompt.sizes;
+            Ok(generation)
+        })
+    }
+
+    /// `generate`, streaming the reply through `on_token`.
+    fn generate_streaming<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(async move {
+            let provider = self.provider();
+            let prompt = prompt::build(
+                provider.as_ref(),
+                self.prompt_options(),
+                diff,
+                hint.as_deref(),
+            );
+            let mut generation = self
+                .complete_streaming(&prompt.system, &prompt.user, on_token)
+                .await?;
+            generation.prompt_sizes = prompt.sizes;
+            Ok(generation)
+        })
+    }
+}
+
+/// The mock generator's "thinking" time, unless `MOCK_DELAY_MS` says otherwise.
+const MOCK_DELAY: Duration = Duration::from_millis(1000);
+
+/// Pause between streamed words (never longer than `MOCK_DELAY_MS`).
+const MOCK_WORD_DELAY: Duration = Duration::from_millis(40);
+
+/// Files the mock message lists before summing up the rest.
+const MOCK_LISTED_FILES: usize = 3;
+
+pub struct MockGenerator {
+    prompt: PromptOptions,
+}
 
 impl MockGenerator {
-    pub fn new() -> Self {
-        Self
+    pub fn new(prompt: PromptOptions) -> Self {
+        Self { prompt }
     }
 
-    pub async fn generate(&self, _diff: &str, hint: Option<String>) -> Result<String> {
+    /// A message built from `diff` alone: the same diff and hint always give the same text.
+    async fn fake_generate(&self, diff: &str, hint: Option<String>) -> Result<Generation> {
+        // Sized like a real prompt so the budget reporting can be tried without credits.
+        let prompt = prompt::build(None, &self.prompt, diff, hint.as_deref());
+
         // Simulate network latency/thinking time
-        sleep(Duration::from_millis(1000)).await;
+        let started = Instant::now();
+        sleep(mock_delay()).await;
+        let latency = started.elapsed();
 
-        let subject = if let Some(h) = hint {
-            format!("feat: {}", h)
-        } else {
-            "feat(core): initialize project structure".to_string()
-        };
+        let mut message = mock_message(self.prompt.style, diff, hint);
+        // Shows the `language` setting reached the prompt.
+        if let Some(language) = &self.prompt.language {
+            message.push_str(&format!(
+                "\n- Written in {}",
+                prompt::language_name(language)
+            ));
+        }
+
+        Ok(Generation {
+            message,
+            latency,
+            prompt_bytes: prompt.system.len() + prompt.user.len(),
+            prompt_sizes: prompt.sizes,
+            tokens: Some(TokenUsage::default()),
+            truncated: false,
+        })
+    }
+
+    /// `fake_generate`, handing out the message a word at a time like a real stream.
+    async fn fake_stream(
+        &self,
+        diff: &str,
+        hint: Option<String>,
+        on_token: OnToken<'_>,
+    ) -> Result<Generation> {
+        let generation = self.fake_generate(diff, hint).await?;
+        stream_words(&generation.message, on_token).await;
+        Ok(generation)
+    }
+
+    /// The previous message with the feedback added as a bullet.
+    async fn fake_refine(
+        &self,
+        diff: &str,
+        hint: Option<String>,
+        refinement: &Refinement,
+        on_token: OnToken<'_>,
+    ) -> Result<Generation> {
+        let prompt =
+            prompt::build_refinement(None, &self.prompt, diff, hint.as_deref(), refinement);
+        let started = Instant::now();
+        sleep(mock_delay()).await;
+        let latency = started.elapsed();
+
+        let message = format!(
+            "{}\n- {}",
+            refinement.previous.trim_end(),
+            refinement.feedback.trim()
+        );
+        stream_words(&message, on_token).await;
+        Ok(Generation {
+            message,
+            latency,
+            prompt_bytes: prompt.system.len() + prompt.user.len(),
+            prompt_sizes: prompt.sizes,
+            tokens: Some(TokenUsage::default()),
+            truncated: false,
+        })
+    }
 
-        Ok(format!(
-            "{}\n\n- Added git diff capture\n- Implemented mock AI generator\n- Set up basic CLI flow",
-            subject
-        ))
+    /// Echoes the user prompt back, so callers see their own input unchanged.
+    async fn echo(&self, system_prompt: &str, user_prompt: &str) -> Result<Generation> {
+        let started = Instant::now();
+        sleep(mock_delay()).await;
+        Ok(Generation {
+            message: user_prompt.trim().to_string(),
+            latency: started.elapsed(),
+            prompt_bytes: system_prompt.len() + user_prompt.len(),
+            prompt_sizes: PromptSizes::default(),
+            tokens: Some(TokenUsage::default()),
+            truncated: false,
+        })
     }
 }
 
+impl CommitMessageGenerator for MockGenerator {
+    fn provider(&self) -> Option<Provider> {
+        None
+    }
+
+    fn model(&self) -> &str {
+        "-"
+    }
+
+    fn prompt_options(&self) -> &PromptOptions {
+        &self.prompt
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.echo(system_prompt, user_prompt))
+    }
+
+    fn generate<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.fake_generate(diff, hint))
+    }
+
+    fn generate_streaming<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.fake_stream(diff, hint, on_token))
+    }
+
+    fn refine<'a>(
+        &'a self,
+        diff: &'a str,
+        hint: Option<String>,
+        refinement: &'a Refinement,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.fake_refine(diff, hint, refinement, on_token))
+    }
+}
+
+/// Hand `message` to `on_token` a word at a time, like a real stream.
+async fn stream_words(message: &str, on_token: OnToken<'_>) {
+    let pause = MOCK_WORD_DELAY.min(mock_delay());
+    let mut so_far = String::new();
+    for word in message.split_inclusive(' ') {
+        so_far.push_str(word);
+        on_token(&so_far);
+        sleep(pause).await;
+    }
+}
+
+/// How long the mock generator "thinks": `MOCK_DELAY_MS`, else a second.
+fn mock_delay() -> Duration {
+    std::env::var("MOCK_DELAY_MS")
+        .ok()
+        .and_then(|ms| ms.trim().parse().ok())
+        .map_or(MOCK_DELAY, Duration::from_millis)
+}
+
+/// The mock's message for `diff`: the hint (or the changed files) as the subject, and
+/// one line per file with its counts.
+fn mock_message(style: CommitStyle, diff: &str, hint: Option<String>) -> String {
+    let stats = git::numstat_from_diff(diff);
+    let names: Vec<&str> = stats
+        .iter()
+        .map(|s| s.path.rsplit('/').next().unwrap_or(&s.path))
+        .collect();
+    let files = match names.as_slice() {
+        [] => "files".to_string(),
+        [one] => one.to_string(),
+        [first, second] => format!("{} and {}", first, second),
+        [first, second, third] => format!("{}, {} and {}", first, second, third),
+        [first, second, rest @ ..] => {
+            format!("{}, {} and {} more files", first, second, rest.len())
+        }
+    };
+
+    // Same shape as the configured style asks the real models for.
+    let subject = match (style, hint) {
+        (CommitStyle::Conventional, Some(h)) => format!("feat: {}", h),
+        (CommitStyle::Conventional, None) => format!("chore: update {}", files),
+        (CommitStyle::Gitmoji, Some(h)) => format!("✨ {}", h),
+        (CommitStyle::Gitmoji, None) => format!("✨ Update {}", files),
+        (CommitStyle::Plain, Some(h)) => h,
+        (CommitStyle::Plain, None) => format!("Update {}", files),
+    };
+
+    let mut lines: Vec<String> = stats
+        .iter()
+        .take(MOCK_LISTED_FILES)
+        .map(|s| match (s.insertions, s.deletions) {
+            (Some(ins), Some(del)) => format!("- {}: +{} -{}", s.path, ins, del),
+            _ => format!("- {}: binary", s.path),
+        })
+        .collect();
+    if stats.len() > MOCK_LISTED_FILES {
+        lines.push(format!("- {} more files", stats.len() - MOCK_LISTED_FILES));
+    }
+    if lines.is_empty() {
+        return subject;
+    }
+    format!("{}\n\n{}", subject, lines.join("\n"))
+}
+
 pub struct OpenAIGenerator {
     client: Client,
     api_key: String,
     model: String,
+    /// API root without trailing `/`; requests go to `{base_url}/chat/completions`.
+    base_url: String,
+    prompt: PromptOptions,
+    /// Who serves the API (errors and the prompt template depend on it).
+    provider: Provider,
+    /// Sent with every request on top of the key.
+    headers: &'static [(&'static str, &'static str)],
 }
 
 impl OpenAIGenerator {
-    pub fn new(api_key: String, model: String) -> Self {
+    pub fn new(
+        client: Client,
+        api_key: String,
+        model: String,
+        base_url: String,
+        prompt: PromptOptions,
+    ) -> Self {
         Self {
-            client: Client::new(),
+            client,
             api_key,
             model,
+            base_url,
+            prompt,
+            provider: Provider::OpenAI,
+            headers: &[],
         }
     }
 
-    pub async fn generate(&self, diff: &str, hint: Option<String>) -> Result<String> {
-        let system_prompt = "You are a senior developer. \
-            Write a commit message following the Conventional Commits specification. \
-            The format should be:\n\
-            <type>(<scope>): <subject>\n\n\
-            <body>\n\n\
-            <footer>\n\
-            Only output the commit message itself, no wrapper text or markdown code blocks.";
-
-        let user_prompt = format!(
-            "Here is the git diff:\n\n{}\n\n{}",
-            diff,
-            if let Some(h) = hint {
-                format!("Focus on this context: {}", h)
-            } else {
-                String::new()
-            }
-        );
+    /// A hosted OpenAI-compatible API (Groq, Mistral): the same requests, with `provider`
+    /// named in errors and the usage ledger.
+    fn hosted(
+        provider: Provider,
+        base_url: &str,
+        client: Client,
+        api_key: String,
+        model: String,
+        prompt: PromptOptions,
+    ) -> Self {
+        Self {
+            provider,
+            ..Self::new(client, api_key, model, base_url.to_string(), prompt)
+        }
+    }
 
-        let request_body = json!({
+    /// One chat completion; streamed (SSE) when `on_token` is given.
+    async fn request(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        on_token: Option<OnToken<'_>>,
+    ) -> Result<Generation> {
+        let mut request_body = json!({
             "model": self.model,
             "messages": [
                 {"role": "system", "content": system_prompt},
                 {"role": "user", "content": user_prompt}
             ],
-            "temperature": 0.7
+            "temperature": TEMPERATURE
         });
+        // Only when configured: these servers don't need a limit, and their defaults vary.
+        if let Some(limit) = self.prompt.max_output_tokens {
+            // api.openai.com rejects `max_tokens` for reasoning models.
+            let param = if self.base_url == OPENAI_BASE_URL {
+                "max_completion_tokens"
+            } else {
+                "max_tokens"
+            };
+            request_body[param] = json!(limit);
+        }
+        if on_token.is_some() {
+            request_body["stream"] = json!(true);
+            // Token counts arrive in a last chunk, where the server supports it.
+            if self.base_url == OPENAI_BASE_URL || self.provider == Provider::OpenRouter {
+                request_body["stream_options"] = json!({"include_usage": true});
+            }
+        }
 
-        let response = self
+        let prompt_bytes = system_prompt.len() + user_prompt.len();
+        let started = Instant::now();
+        let mut request = self
             .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request_body)
-            .send()
-            .await
-            .context("Failed to send request to OpenAI")?;
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        for (name, value) in self.headers {
+            request = request.header(*name, *value);
+        }
+        let response = http::send(request.json(&request_body), &self.provider, &self.model).await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            bail!("OpenAI API error: {}", error_text);
+            return Err(api_error(
+                self.provider.clone(),
+                &self.model,
+                status,
+                &error_text,
+            ));
+        }
+
+        if let Some(on_token) = on_token {
+            let mut content = String::new();
+            let mut tokens = None;
+            let mut truncated = false;
+            read_sse(response, |data| {
+                let event: serde_json::Value = serde_json::from_str(data)
+                    .with_context(|| format!("Failed to parse {} stream", self.provider))?;
+                if let Some(error) = event["error"]["message"].as_str() {
+                    bail!("{} stopped the reply: {}", self.provider, error);
+                }
+                let choice = &event["choices"][0];
+                if let Some(delta) = choice["delta"]["content"].as_str() {
+                    content.push_str(delta);
+                    on_token(&content);
+                }
+                truncated |= choice["finish_reason"] == "length";
+                let usage = &event["usage"];
+                tokens = TokenUsage::parse(&usage["prompt_tokens"], &usage["completion_tokens"])
+                    .or(tokens);
+                Ok(())
+            })
+            .await?;
+            if content.trim().is_empty() {
+                bail!("Invalid response format from {}", self.provider);
+            }
+            return Ok(Generation {
+                message: clean_response(content.trim().to_string()),
+                latency: started.elapsed(),
+                prompt_bytes,
+                prompt_sizes: PromptSizes::default(),
+                tokens,
+                truncated,
+            });
         }
 
         let response_json: serde_json::Value = response
             .json()
             .await
-            .context("Failed to parse OpenAI response")?;
+            .with_context(|| format!("Failed to parse {} response", self.provider))?;
 
         let content = response_json["choices"][0]["message"]["content"]
             .as_str()
-            .context("Invalid response format from OpenAI")?
+            .with_context(|| format!("Invalid response format from {}", self.provider))?
             .trim()
             .to_string();
 
-        Ok(clean_response(content))
+        Ok(Generation {
+            message: clean_response(content),
+            latency: started.elapsed(),
+            prompt_bytes,
+            prompt_sizes: PromptSizes::default(),
+            tokens: TokenUsage::parse(
+                &response_json["usage"]["prompt_tokens"],
+                &response_json["usage"]["completion_tokens"],
+            ),
+            truncated: response_json["choices"][0]["finish_reason"] == "length",
+        })
+    }
+}
+
+impl CommitMessageGenerator for OpenAIGenerator {
+    fn provider(&self) -> Option<Provider> {
+        Some(self.provider.clone())
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt_options(&self) -> &PromptOptions {
+        &self.prompt
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.request(system_prompt, user_prompt, None))
+    }
+
+    fn complete_streaming<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.request(system_prompt, user_prompt, Some(on_token)))
+    }
+}
+
+/// OpenRouter: one key for many providers' models, addressed by slug
+/// (`anthropic/claude-3.5-sonnet`). The API is OpenAI's chat completions.
+pub struct OpenRouterGenerator(OpenAIGenerator);
+
+impl OpenRouterGenerator {
+    pub fn new(client: Client, api_key: String, model: String, prompt: PromptOptions) -> Self {
+        Self(OpenAIGenerator {
+            provider: Provider::OpenRouter,
+            headers: OPENROUTER_HEADERS,
+            ..OpenAIGenerator::hosted(
+                Provider::OpenRouter,
+                OPENROUTER_BASE_URL,
+                client,
+                api_key,
+                model,
+                prompt,
+            )
+        })
+    }
+}
+
+impl CommitMessageGenerator for OpenRouterGenerator {
+    fn provider(&self) -> Option<Provider> {
+        self.0.provider()
+    }
+
+    fn model(&self) -> &str {
+        self.0.model()
+    }
+
+    fn prompt_options(&self) -> &PromptOptions {
+        self.0.prompt_options()
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        self.0.complete(system_prompt, user_prompt)
+    }
+
+    fn complete_streaming<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        self.0
+            .complete_streaming(system_prompt, user_prompt, on_token)
     }
 }
 
@@ -104,55 +616,101 @@ pub struct AnthropicGenerator {
     client: Client,
     api_key: String,
     model: String,
+    prompt: PromptOptions,
 }
 
 impl AnthropicGenerator {
-    pub fn new(api_key: String, model: String) -> Self {
+    pub fn new(client: Client, api_key: String, model: String, prompt: PromptOptions) -> Self {
         Self {
-            client: Client::new(),
+            client,
             api_key,
             model,
+            prompt,
         }
     }
 
-    pub async fn generate(&self, diff: &str, hint: Option<String>) -> Result<String> {
-        let system_prompt = "You are a senior developer. \
-            Write a commit message following the Conventional Commits specification. \
-            Only output the commit message itself, no wrapper text or markdown code blocks.";
-
-        let user_prompt = format!(
-            "Here is the git diff:\n\n{}\n\n{}",
-            diff,
-            if let Some(h) = hint {
-                format!("Focus on this context: {}", h)
-            } else {
-                String::new()
-            }
-        );
-
+    /// One message; streamed (SSE) when `on_token` is given.
+    async fn request(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        on_token: Option<OnToken<'_>>,
+    ) -> Result<Generation> {
         let request_body = json!({
             "model": self.model,
-            "max_tokens": 1024,
+            "max_tokens": output_limit(&self.prompt),
             "system": system_prompt,
             "messages": [
                 {"role": "user", "content": user_prompt}
-            ]
+            ],
+            "stream": on_token.is_some()
         });
 
-        let response = self
+        let prompt_bytes = system_prompt.len() + user_prompt.len();
+        let started = Instant::now();
+        let request = self
             .client
             .post("https://api.anthropic.com/v1/messages")
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&request_body)
-            .send()
-            .await
-            .context("Failed to send request to Anthropic")?;
+            .json(&request_body);
+        let response = http::send(request, Provider::Anthropic, &self.model).await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            bail!("Anthropic API error: {}", error_text);
+            return Err(api_error(
+                Provider::Anthropic,
+                &self.model,
+                status,
+                &error_text,
+            ));
+        }
+
+        if let Some(on_token) = on_token {
+            let mut content = String::new();
+            let (mut input_tokens, mut output_tokens) = (json!(null), json!(null));
+            let mut truncated = false;
+            read_sse(response, |data| {
+                let event: serde_json::Value =
+                    serde_json::from_str(data).context("Failed to parse Anthropic stream")?;
+                match event["type"].as_str() {
+                    Some("message_start") => {
+                        input_tokens = event["message"]["usage"]["input_tokens"].clone();
+                    }
+                    Some("content_block_delta") => {
+                        if let Some(text) = event["delta"]["text"].as_str() {
+                            content.push_str(text);
+                            on_token(&content);
+                        }
+                    }
+                    Some("message_delta") => {
+                        output_tokens = event["usage"]["output_tokens"].clone();
+                        truncated = event["delta"]["stop_reason"] == "max_tokens";
+                    }
+                    Some("error") => bail!(
+                        "Anthropic stopped the reply: {}",
+                        event["error"]["message"]
+                            .as_str()
+                            .unwrap_or("unknown error")
+                    ),
+                    _ => {}
+                }
+                Ok(())
+            })
+            .await?;
+            if content.trim().is_empty() {
+                bail!("Invalid response format from Anthropic");
+            }
+            return Ok(Generation {
+                message: clean_response(content.trim().to_string()),
+                latency: started.elapsed(),
+                prompt_bytes,
+                prompt_sizes: PromptSizes::default(),
+                tokens: TokenUsage::parse(&input_tokens, &output_tokens),
+                truncated,
+            });
         }
 
         let response_json: serde_json::Value = response
@@ -166,7 +724,48 @@ impl AnthropicGenerator {
             .trim()
             .to_string();
 
-        Ok(clean_response(content))
+        Ok(Generation {
+            message: clean_response(content),
+            latency: started.elapsed(),
+            prompt_bytes,
+            prompt_sizes: PromptSizes::default(),
+            tokens: TokenUsage::parse(
+                &response_json["usage"]["input_tokens"],
+                &response_json["usage"]["output_tokens"],
+            ),
+            truncated: response_json["stop_reason"] == "max_tokens",
+        })
+    }
+}
+
+impl CommitMessageGenerator for AnthropicGenerator {
+    fn provider(&self) -> Option<Provider> {
+        Some(Provider::Anthropic)
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt_options(&self) -> &PromptOptions {
+        &self.prompt
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.request(system_prompt, user_prompt, None))
+    }
+
+    fn complete_streaming<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+        on_token: OnToken<'a>,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.request(system_prompt, user_prompt, Some(on_token)))
     }
 }
 
@@ -174,35 +773,25 @@ pub struct GeminiGenerator {
     client: Client,
     api_key: String,
     model: String,
+    prompt: PromptOptions,
 }
 
 impl GeminiGenerator {
-    pub fn new(api_key: String, model: String) -> Self {
+    pub fn new(client: Client, api_key: String, model: String, prompt: PromptOptions) -> Self {
         Self {
-            client: Client::new(),
+            client,
             api_key,
             model,
+            prompt,
         }
     }
 
-    pub async fn generate(&self, diff: &str, hint: Option<String>) -> Result<String> {
-        let system_prompt = "You are a senior developer. \
-            Write a commit message following the Conventional Commits specification. \
-            Only output the commit message itself, no wrapper text or markdown code blocks.";
-
-        let user_prompt = format!(
-            "Here is the git diff:\n\n{}\n\n{}",
-            diff,
-            if let Some(h) = hint {
-                format!("Focus on this context: {}", h)
-            } else {
-                String::new()
-            }
-        );
-
+    /// The `generateContent` request. The key goes in a header, never the URL, so it can't
+    /// leak into error messages or proxy logs.
+    fn build_request(&self, system_prompt: &str, user_prompt: &str) -> RequestBuilder {
         let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            self.model, self.api_key
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            self.model
         );
 
         let request_body = json!({
@@ -213,60 +802,1274 @@ impl GeminiGenerator {
                 {
                     "parts": [ {"text": user_prompt} ]
                 }
-            ]
+            ],
+            "generationConfig": {
+                "temperature": TEMPERATURE,
+                "maxOutputTokens": output_limit(&self.prompt)
+            }
         });
 
-        let response = self
-            .client
+        self.client
             .post(&url)
+            .header("x-goog-api-key", &self.api_key)
             .json(&request_body)
-            .send()
-            .await
-            .context("Failed to send request to Gemini")?;
+    }
 
-        if !response.status().is_success() {
+    async fn request(&self, system_prompt: &str, user_prompt: &str) -> Result<Generation> {
+        let prompt_bytes = system_prompt.len() + user_prompt.len();
+        let started = Instant::now();
+        let request = self.build_request(system_prompt, user_prompt);
+        let response = http::send(request, Provider::Gemini, &self.model).await?;
+
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            bail!("Gemini API error: {}", error_text);
+            return Err(api_error(
+                Provider::Gemini,
+                &self.model,
+                status,
+                &error_text,
+            ));
         }
 
         let response_json: serde_json::Value = response
             .json()
             .await
             .context("Failed to parse Gemini response")?;
+        let (content, truncated) = gemini_reply(&response_json, output_limit(&self.prompt))?;
+
+        Ok(Generation {
+            message: clean_response(content),
+            latency: started.elapsed(),
+            prompt_bytes,
+            prompt_sizes: PromptSizes::default(),
+            tokens: TokenUsage::parse(
+                &response_json["usageMetadata"]["promptTokenCount"],
+                &response_json["usageMetadata"]["candidatesTokenCount"],
+            ),
+            truncated,
+        })
+    }
+}
+
+/// The message in a Gemini reply, and whether it stopped at `limit` output tokens.
+/// Blocked prompts and safety stops are errors.
+fn gemini_reply(response_json: &serde_json::Value, limit: u32) -> Result<(String, bool)> {
+    if let Some(reason) = response_json["promptFeedback"]["blockReason"].as_str() {
+        bail!("Gemini blocked the request ({}).", reason);
+    }
+
+    let Some(candidate) = response_json["candidates"]
+        .as_array()
+        .and_then(|candidates| candidates.first())
+    else {
+        bail!("Gemini returned no candidates; the response was probably withheld by its safety filters.");
+    };
+    let truncated = candidate["finishReason"] == "MAX_TOKENS";
+    match candidate["finishReason"].as_str() {
+        None | Some("STOP" | "MAX_TOKENS") => {}
+        Some(reason @ ("SAFETY" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII")) => bail!(
+            "Gemini withheld the message ({}): its safety filters flagged the diff.",
+            reason
+        ),
+        Some(reason) => bail!("Gemini stopped without a usable message ({}).", reason),
+    }
+
+    let Some(content) = candidate["content"]["parts"][0]["text"].as_str() else {
+        if truncated {
+            // Thinking models can spend the whole limit before writing anything.
+            bail!(
+                "Gemini used up maxOutputTokens ({}) before writing a message. Raise max_output_tokens in the config.",
+                limit
+            );
+        }
+        bail!("Invalid response format from Gemini");
+    };
+    Ok((content.trim().to_string(), truncated))
+}
+
+impl CommitMessageGenerator for GeminiGenerator {
+    fn provider(&self) -> Option<Provider> {
+        Some(Provider::Gemini)
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt_options(&self) -> &PromptOptions {
+        &self.prompt
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        user_prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Generation>> {
+        Box::pin(self.request(system_prompt, user_prompt))
+    }
+}
+
+/// The typed error for a failed provider call: `ModelUnavailable` when the model is
+/// unknown (callers offer to pick another), else a classified `ApiError`.
+fn api_error(provider: Provider, model: &str, status: StatusCode, body: &str) -> anyhow::Error {
+    match ModelUnavailable::detect(provider.clone(), model, status, body) {
+        Some(unavailable) => unavailable.into(),
+        None => ApiError::classify(provider, status, body).into(),
+    }
+}
+
+/// What went wrong with a provider call, as far as the user is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    InvalidKey,
+    QuotaExceeded,
+    RateLimited,
+    ModelMissing,
+    ContentTooLarge,
+    ServerError,
+    Other,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCategory::InvalidKey => write!(f, "API key rejected"),
+            ErrorCategory::QuotaExceeded => write!(f, "quota exceeded"),
+            ErrorCategory::RateLimited => write!(f, "rate limited"),
+            ErrorCategory::ModelMissing => write!(f, "model not found"),
+            ErrorCategory::ContentTooLarge => write!(f, "request too large"),
+            ErrorCategory::ServerError => write!(f, "provider error"),
+            ErrorCategory::Other => write!(f, "request failed"),
+        }
+    }
+}
 
-        let content = response_json["candidates"][0]["content"]["parts"][0]["text"]
+/// A provider call failed: category and guidance first, the raw response as the source.
+///
+/// `ui::print_error` shows the source as a dimmed `caused by:` line; the TUI logs it.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub provider: Provider,
+    pub category: ErrorCategory,
+    pub response: RawResponse,
+}
+
+/// Status and (shortened) body of a failed provider response.
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Longest raw body kept in an `ApiError`.
+const RAW_BODY_MAX: usize = 500;
+
+impl ApiError {
+    /// Map the status and the provider's error `type` / `code` / `status` fields to a
+    /// category. Error shapes:
+    /// - OpenAI: `{"error": {"type": "...", "code": "invalid_api_key", "message": "..."}}`
+    /// - Anthropic: `{"type": "error", "error": {"type": "authentication_error", "message": "..."}}`
+    /// - Gemini: `{"error": {"code": 400, "status": "INVALID_ARGUMENT", "message": "...",
+    ///   "details": [{"reason": "API_KEY_INVALID"}]}}`
+    /// - Mistral: `{"object": "error", "type": "invalid_model", "message": "..."}` (no wrapper)
+    pub fn classify(provider: Provider, status: StatusCode, body: &str) -> Self {
+        let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let error = if json["error"].is_object() {
+            &json["error"]
+        } else {
+            &json
+        };
+        let message = error["message"]
             .as_str()
-            .context("Invalid response format from Gemini")?
-            .trim()
-            .to_string();
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        // OpenAI puts it in `code`, Anthropic in `type`, Gemini in `status` / `details`.
+        let kind = [&error["code"], &error["type"], &error["status"]]
+            .into_iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let reason_is = |reason: &str| {
+            error["details"]
+                .as_array()
+                .is_some_and(|d| d.iter().any(|d| d["reason"].as_str() == Some(reason)))
+        };
+
+        let category = if kind.contains("invalid_api_key")
+            || kind.contains("authentication_error")
+            || kind.contains("permission_error")
+            || reason_is("API_KEY_INVALID")
+            || status == StatusCode::UNAUTHORIZED
+            || status == StatusCode::FORBIDDEN
+        {
+            ErrorCategory::InvalidKey
+        } else if kind.contains("insufficient_quota")
+            || message.contains("credit balance")
+            || message.contains("billing")
+        {
+            // OpenAI sends `insufficient_quota` with a 429, so it goes before the rate limits.
+            ErrorCategory::QuotaExceeded
+        } else if kind.contains("rate_limit")
+            || kind.contains("RESOURCE_EXHAUSTED")
+            || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            // Gemini's per-minute limits say "quota" too, but they pass.
+            ErrorCategory::RateLimited
+        } else if kind.contains("context_length_exceeded")
+            || kind.contains("request_too_large")
+            || message.contains("too long")
+            || message.contains("exceeds the maximum")
+            || message.contains("maximum context length")
+            || status == StatusCode::PAYLOAD_TOO_LARGE
+        {
+            ErrorCategory::ContentTooLarge
+        } else if kind.contains("model_not_found")
+            || kind.contains("not_found_error")
+            || kind.contains("invalid_model")
+            || status == StatusCode::NOT_FOUND
+        {
+            ErrorCategory::ModelMissing
+        } else if kind.contains("overloaded_error")
+            || kind.contains("api_error")
+            || kind.contains("server_error")
+            || status.is_server_error()
+            || status.as_u16() == 529
+        {
+            ErrorCategory::ServerError
+        } else {
+            ErrorCategory::Other
+        };
+
+        let mut body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+        if body.len() > RAW_BODY_MAX {
+            let mut end = RAW_BODY_MAX;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+            body.push('…');
+        }
 
-        Ok(clean_response(content))
+        Self {
+            provider,
+            category,
+            response: RawResponse {
+                status: status.as_u16(),
+                body,
+            },
+        }
+    }
+
+    /// What to do about it, specific to the provider where that helps.
+    pub fn guidance(&self) -> String {
+        let setup = "and run the setup wizard again (`git-wiz --config`, or Config → Run setup wizard in the TUI)";
+        match (self.category, &self.provider) {
+            (ErrorCategory::InvalidKey, Provider::OpenAI) => format!(
+                "Your OpenAI key was rejected — create a new one at platform.openai.com/api-keys {}.",
+                setup
+            ),
+            (ErrorCategory::InvalidKey, Provider::Anthropic) => format!(
+                "Your Anthropic key was rejected — regenerate it at console.anthropic.com {}.",
+                setup
+            ),
+            (ErrorCategory::InvalidKey, Provider::Gemini) => format!(
+                "Your Gemini key was rejected — create a new one at aistudio.google.com/apikey {}.",
+                setup
+            ),
+            (ErrorCategory::InvalidKey, Provider::OpenRouter) => format!(
+                "Your OpenRouter key was rejected — create a new one at openrouter.ai/settings/keys {}.",
+                setup
+            ),
+            (ErrorCategory::InvalidKey, Provider::Groq) => format!(
+                "Your Groq key was rejected — create a new one at console.groq.com/keys {}.",
+                setup
+            ),
+            (ErrorCategory::InvalidKey, Provider::Mistral) => format!(
+                "Your Mistral key was rejected — create a new one at console.mistral.ai/api-keys {}.",
+                setup
+            ),
+            (ErrorCategory::QuotaExceeded, Provider::OpenAI) => {
+                "Your OpenAI account is out of credits — add some at platform.openai.com/settings/organization/billing.".to_string()
+            }
+            (ErrorCategory::QuotaExceeded, Provider::Anthropic) => {
+                "Your Anthropic credit balance is too low — add credits at console.anthropic.com/settings/billing.".to_string()
+            }
+            (ErrorCategory::QuotaExceeded, Provider::Gemini) => {
+                "Your Gemini quota is used up — wait for it to reset or enable billing for the project at aistudio.google.com.".to_string()
+            }
+            (ErrorCategory::QuotaExceeded, Provider::OpenRouter) => {
+                "Your OpenRouter credits are used up — add some at openrouter.ai/settings/credits.".to_string()
+            }
+            (ErrorCategory::QuotaExceeded, Provider::Groq) => {
+                "Your Groq usage limit is reached — check your plan at console.groq.com/settings/billing.".to_string()
+            }
+            (ErrorCategory::QuotaExceeded, Provider::Mistral) => {
+                "Your Mistral usage limit is reached — check your plan at console.mistral.ai/billing.".to_string()
+            }
+            (ErrorCategory::RateLimited, provider) => format!(
+                "{} is limiting requests — wait a minute and generate again.",
+                provider
+            ),
+            (ErrorCategory::ModelMissing, _) => {
+                "The model (or, with `base_url`, the endpoint) was not found — pick another with Config → Change model.".to_string()
+            }
+            (ErrorCategory::ContentTooLarge, _) => {
+                "The prompt is too big for the model — stage fewer files, add globs to `prompt_exclude`, or lower `max_prompt_tokens`.".to_string()
+            }
+            (ErrorCategory::ServerError, provider) => format!(
+                "{} is having trouble — try again in a moment.",
+                provider
+            ),
+            (ErrorCategory::Other, _) => "See the response below.".to_string(),
+        }
     }
 }
 
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} API error ({}): {}",
+            self.provider,
+            self.category,
+            self.guidance()
+        )
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.response)
+    }
+}
+
+impl fmt::Display for RawResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for RawResponse {}
+
+/// The configured model was rejected by the provider as unknown or retired.
+///
+/// Surfaced as a typed error so callers can `downcast_ref` it and offer to pick
+/// another model instead of showing the raw API error body.
+#[derive(Debug, Clone)]
+pub struct ModelUnavailable {
+    pub provider: Provider,
+    pub model: String,
+}
+
+impl ModelUnavailable {
+    /// Recognize the provider-specific "model not found / deprecated" error shapes.
+    fn detect(provider: Provider, model: &str, status: StatusCode, body: &str) -> Option<Self> {
+        let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let error = &json["error"];
+
+        let unavailable = match provider {
+            // {"error": {"code": "model_not_found", ...}}
+            Provider::OpenAI | Provider::Groq => error["code"].as_str() == Some("model_not_found"),
+            // {"object": "error", "type": "invalid_model", "message": "Invalid model: x"}
+            Provider::Mistral => json["type"].as_str() == Some("invalid_model"),
+            // {"type": "error", "error": {"type": "not_found_error", "message": "model: ..."}}
+            Provider::Anthropic => {
+                error["type"].as_str() == Some("not_found_error")
+                    && error["message"]
+                        .as_str()
+                        .is_some_and(|m| m.to_ascii_lowercase().contains("model"))
+            }
+            // The model is part of the URL path, so a 404 means the model is unknown.
+            Provider::Gemini => status == StatusCode::NOT_FOUND,
+            // {"error": {"message": "x is not a valid model ID", "code": 400}}
+            Provider::OpenRouter => error["message"].as_str().is_some_and(|m| {
+                m.contains("not a valid model") || m.contains("No endpoints found")
+            }),
+        };
+
+        unavailable.then(|| Self {
+            provider,
+            model: model.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ModelUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The configured {} model `{}` is not available (it may have been retired).",
+            self.provider, self.model
+        )
+    }
+}
+
+impl std::error::Error for ModelUnavailable {}
+
+/// The provider rejected the configured API key (HTTP 401/403).
+#[derive(Debug, Clone)]
+pub struct InvalidApiKey {
+    pub provider: Provider,
+}
+
+impl fmt::Display for InvalidApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The {} API key was rejected.", self.provider)
+    }
+}
+
+impl std::error::Error for InvalidApiKey {}
+
+/// Check that the provider is reachable, the API key is accepted, and the configured
+/// model exists, by fetching the model's metadata (no tokens are spent).
+///
+/// Returns the request latency. Failures use the typed errors above where possible.
+/// Used by `git-wiz doctor` and by the setup wizard before it saves a new key.
+pub async fn check_access(cfg: &Config) -> Result<Duration> {
+    let client = http::builder()?
+        .timeout(Duration::from_secs(15))
+        .build()
+        .context("Failed to build HTTP client")?;
+
+    let request = match cfg.provider {
+        Provider::OpenAI => client
+            .get(format!("{}/models/{}", openai_base_url(cfg)?, cfg.model))
+            .bearer_auth(&cfg.api_key),
+        Provider::Anthropic => client
+            .get(format!("https://api.anthropic.com/v1/models/{}", cfg.model))
+            .header("x-api-key", &cfg.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        Provider::Gemini => client
+            .get(format!(
+                "https://generativelanguage.googleapis.com/v1beta/models/{}",
+                cfg.model
+            ))
+            .header("x-goog-api-key", &cfg.api_key),
+        Provider::Groq => client
+            .get(format!("{}/models/{}", GROQ_BASE_URL, cfg.model))
+            .bearer_auth(&cfg.api_key),
+        Provider::Mistral => client
+            .get(format!("{}/models/{}", MISTRAL_BASE_URL, cfg.model))
+            .bearer_auth(&cfg.api_key),
+        // Public endpoint: unknown slugs 404 here, the key is checked below.
+        Provider::OpenRouter => client
+            .get(format!(
+                "{}/models/{}/endpoints",
+                OPENROUTER_BASE_URL, cfg.model
+            ))
+            .bearer_auth(&cfg.api_key),
+    };
+
+    let started = std::time::Instant::now();
+    let response = request
+        .send()
+        .await
+        .map_err(|e| http::send_error(e, &cfg.provider))?;
+    let latency = started.elapsed();
+
+    // OpenRouter's model endpoints answer without a key; its key endpoint does not.
+    if cfg.provider == Provider::OpenRouter && response.status().is_success() {
+        let key = client
+            .get(format!("{}/key", OPENROUTER_BASE_URL))
+            .bearer_auth(&cfg.api_key)
+            .send()
+            .await
+            .map_err(|e| http::send_error(e, &cfg.provider))?;
+        if matches!(
+            key.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(InvalidApiKey {
+                provider: cfg.provider.clone(),
+            }
+            .into());
+        }
+    }
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(latency);
+    }
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(InvalidApiKey {
+            provider: cfg.provider.clone(),
+        }
+        .into());
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(ModelUnavailable {
+            provider: cfg.provider.clone(),
+            model: cfg.model.clone(),
+        }
+        .into());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(ApiError::classify(cfg.provider.clone(), status, &body).into())
+}
+
+/// Feed the `data:` payload of each server-sent event in `response` to `on_data` as it
+/// arrives, up to OpenAI's `[DONE]` marker or the end of the body.
+async fn read_sse(
+    mut response: reqwest::Response,
+    mut on_data: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("The reply stream broke off")?
+    {
+        buffer.extend_from_slice(&chunk);
+        // Whole lines only: a chunk can end in the middle of a line (or a UTF-8 character).
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim_start();
+            if data == "[DONE]" {
+                return Ok(());
+            }
+            on_data(data)?;
+        }
+    }
+    Ok(())
+}
+
+/// The configured OpenAI-compatible API root, or api.openai.com.
+fn openai_base_url(cfg: &Config) -> Result<String> {
+    match &cfg.base_url {
+        Some(url) => config::normalize_base_url(url).context("Invalid base_url in config"),
+        None => Ok(OPENAI_BASE_URL.to_string()),
+    }
+}
+
+/// URL that generation requests for `cfg` are sent to (shown so users know where the diff goes).
+pub fn endpoint(cfg: &Config) -> Result<String> {
+    Ok(match cfg.provider {
+        Provider::OpenAI => format!("{}/chat/completions", openai_base_url(cfg)?),
+        Provider::Anthropic => "https://api.anthropic.com/v1/messages".to_string(),
+        Provider::Gemini => format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            cfg.model
+        ),
+        Provider::OpenRouter => format!("{}/chat/completions", OPENROUTER_BASE_URL),
+        Provider::Groq => format!("{}/chat/completions", GROQ_BASE_URL),
+        Provider::Mistral => format!("{}/chat/completions", MISTRAL_BASE_URL),
+    })
+}
+
+/// The reply without the wrapper noise models add around a message: a conversational
+/// lead-in ("Here is the commit message:"), a fence around the whole reply (``` or ~~~,
+/// any language tag) and quotes around the whole reply. Fences and quotes inside the
+/// message are left alone.
 fn clean_response(content: String) -> String {
-    content
-        .replace("```git commit", "")
-        .replace("```commit", "")
-        .replace("```", "")
-        .trim()
-        .to_string()
+    let text = strip_lead_in(content.trim());
+    let text = strip_fence(text);
+    strip_quotes(text).trim().to_string()
+}
+
+/// `text` without its first line when that line is chatter: the line is not a subject
+/// itself, a blank line follows, and then comes a Conventional Commits subject or a fence.
+fn strip_lead_in(text: &str) -> &str {
+    let mut lines = text.splitn(3, '\n');
+    let (Some(first), Some(blank), Some(rest)) = (lines.next(), lines.next(), lines.next()) else {
+        return text;
+    };
+    let third = rest.lines().next().unwrap_or_default();
+    let is_subject = |line: &str| conventional::parse(line).is_ok();
+    if is_subject(first) || !blank.trim().is_empty() {
+        return text;
+    }
+    if is_subject(third) || fence_marker(third).is_some() {
+        rest.trim_start()
+    } else {
+        text
+    }
+}
+
+/// `text` without a fence around all of it. A missing closing fence (a cut-off reply)
+/// still drops the opening one.
+fn strip_fence(text: &str) -> &str {
+    let Some((first, rest)) = text.split_once('\n') else {
+        return text;
+    };
+    let Some(open) = fence_marker(first) else {
+        return text;
+    };
+    let rest = rest.trim_end();
+    let (body, last) = rest.rsplit_once('\n').unwrap_or(("", rest));
+    let last = last.trim();
+    let closes = last.len() >= open.len() && last.chars().all(|c| open.starts_with(c));
+    if closes {
+        body
+    } else {
+        rest
+    }
+}
+
+/// The run of three or more backticks or tildes opening `line`, if any.
+fn fence_marker(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let ch = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then(|| &line[..len])
+}
+
+/// `text` without straight or curly quotes around all of it.
+fn strip_quotes(text: &str) -> &str {
+    for (open, close) in [('"', '"'), ('\'', '\''), ('“', '”')] {
+        if let Some(inner) = text.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+            if !inner.trim().is_empty() && !inner.starts_with(char::is_whitespace) {
+                return inner;
+            }
+        }
+    }
+    text
+}
+
+/// Reply length assumed for cost estimates, in tokens (a subject and a short body).
+const REPLY_TOKENS_ESTIMATE: usize = 200;
+
+/// List prices (USD per million input / output tokens) when this table was written.
+/// `prices` in the config overrides or extends it; unknown models get no cost estimate.
+const PRICES: &[(Provider, &str, ModelPrice)] = &[
+    (Provider::OpenAI, "gpt-5", price(1.25, 10.0)),
+    (Provider::OpenAI, "gpt-5-mini", price(0.25, 2.0)),
+    (Provider::OpenAI, "gpt-5-nano", price(0.05, 0.4)),
+    (Provider::OpenAI, "gpt-4.1", price(2.0, 8.0)),
+    (Provider::OpenAI, "gpt-4.1-mini", price(0.4, 1.6)),
+    (Provider::OpenAI, "gpt-4.1-nano", price(0.1, 0.4)),
+    (Provider::OpenAI, "gpt-4o", price(2.5, 10.0)),
+    (Provider::OpenAI, "gpt-4o-mini", price(0.15, 0.6)),
+    (Provider::Anthropic, "claude-opus-4-5", price(5.0, 25.0)),
+    (Provider::Anthropic, "claude-opus-4-1", price(15.0, 75.0)),
+    (Provider::Anthropic, "claude-opus-4", price(15.0, 75.0)),
+    (Provider::Anthropic, "claude-sonnet-4-5", price(3.0, 15.0)),
+    (Provider::Anthropic, "claude-sonnet-4", price(3.0, 15.0)),
+    (Provider::Anthropic, "claude-haiku-4-5", price(1.0, 5.0)),
+    (Provider::Anthropic, "claude-3-5-haiku", price(0.8, 4.0)),
+    (Provider::Gemini, "gemini-2.5-pro", price(1.25, 10.0)),
+    (Provider::Gemini, "gemini-2.5-flash", price(0.3, 2.5)),
+    (Provider::Gemini, "gemini-2.5-flash-lite", price(0.1, 0.4)),
+    (Provider::Groq, "llama-3.3-70b-versatile", price(0.59, 0.79)),
+    (Provider::Groq, "llama-3.1-8b-instant", price(0.05, 0.08)),
+    (Provider::Mistral, "mistral-large-latest", price(2.0, 6.0)),
+    (Provider::Mistral, "mistral-small-latest", price(0.1, 0.3)),
+];
+
+const fn price(input: f64, output: f64) -> ModelPrice {
+    ModelPrice { input, output }
 }
 
-pub enum Generator {
-    Mock(MockGenerator),
-    OpenAI(OpenAIGenerator),
-    Anthropic(AnthropicGenerator),
-    Gemini(GeminiGenerator),
+/// True when `key` names `model` itself or a dated snapshot of it (`gpt-4o-2024-08-06`).
+fn price_key_matches(model: &str, key: &str) -> bool {
+    model == key
+        || model
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with('-'))
 }
 
+/// Price of the configured model: `prices` in the config first, then the built-in table
+/// (skipped for a custom `base_url`, where the model is usually local). The longest
+/// matching model ID wins, so `gpt-4o-mini` is not priced as `gpt-4o`.
+pub fn price_for(cfg: &Config) -> Option<ModelPrice> {
+    let model = cfg.model.as_str();
+    let configured = cfg
+        .prices
+        .iter()
+        .filter(|(key, _)| price_key_matches(model, key))
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, price)| *price);
+    if configured.is_some() || cfg.base_url.is_some() {
+        return configured;
+    }
+    PRICES
+        .iter()
+        .filter(|(provider, key, _)| *provider == cfg.provider && price_key_matches(model, key))
+        .max_by_key(|(_, key, _)| key.len())
+        .map(|(_, _, price)| *price)
+}
+
+/// Estimated size and cost of one generation, shown before the call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    /// Prompt tokens as sent, after the budget.
+    pub tokens: usize,
+    /// USD, including `REPLY_TOKENS_ESTIMATE` output tokens; `None` when the price is
+    /// unknown (or for the mock generator).
+    pub cost: Option<f64>,
+    pub model: String,
+}
+
+impl CostEstimate {
+    /// Estimate for a prompt of `sizes`; `cfg` is `None` for the mock generator.
+    fn new(cfg: Option<&Config>, sizes: &PromptSizes) -> Self {
+        let tokens = sizes.tokens();
+        let cost = cfg.and_then(price_for).map(|price| {
+            (tokens as f64 * price.input + REPLY_TOKENS_ESTIMATE as f64 * price.output)
+                / 1_000_000.0
+        });
+        Self {
+            tokens,
+            cost,
+            model: cfg.map_or_else(|| "mock".to_string(), |c| c.model.clone()),
+        }
+    }
+
+    /// `~3,200 tokens, est. $0.004`, without the model.
+    pub fn short(&self) -> String {
+        let tokens = format!("~{} tokens", thousands(self.tokens as u64));
+        match self.cost {
+            Some(cost) if cost < 0.001 => format!("{}, est. <$0.001", tokens),
+            Some(cost) if cost < 1.0 => format!("{}, est. ${:.3}", tokens, cost),
+            Some(cost) => format!("{}, est. ${:.2}", tokens, cost),
+            None => tokens,
+        }
+    }
+}
+
+/// e.g. `~3,200 tokens, est. $0.004 with gpt-4o`; tokens only when the price is unknown.
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cost {
+            Some(_) => write!(f, "{} with {}", self.short(), self.model),
+            None => write!(f, "{}", self.short()),
+        }
+    }
+}
+
+/// What sending `diff` with the configured model would cost, built the way the generator
+/// builds it (same budget and template).
+pub fn estimate_cost(mock_mode: bool, diff: &str, hint: Option<&str>) -> CostEstimate {
+    let cfg = if mock_mode {
+        None
+    } else {
+        Config::load().ok().flatten()
+    };
+    let prompt = prompt::build(
+        cfg.as_ref().map(|c| &c.provider),
+        &PromptOptions::from_config(cfg.as_ref()),
+        diff,
+        hint,
+    );
+    CostEstimate::new(cfg.as_ref(), &prompt.sizes)
+}
+
+/// `3200` -> `3,200`.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Tokens one or more calls consumed, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+}
+
+impl TokenUsage {
+    /// Usage from the provider's counters; `None` unless the input count is present.
+    fn parse(input: &serde_json::Value, output: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            input: input.as_u64()?,
+            output: output.as_u64().unwrap_or_default(),
+        })
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input += other.input;
+        self.output += other.output;
+    }
+}
+
+/// e.g. `2,913 in / 187 out tokens`.
+impl fmt::Display for TokenUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in / {} out tokens",
+            thousands(self.input),
+            thousands(self.output)
+        )
+    }
+}
+
+/// A generated message plus measurements for the usage ledger.
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub message: String,
+    /// Wall-clock time of the provider HTTP call (request through parsed response).
+    pub latency: Duration,
+    /// Size of the system + user prompt sent.
+    pub prompt_bytes: usize,
+    /// Per-section sizes after the prompt budget (empty for free-form completions).
+    pub prompt_sizes: PromptSizes,
+    /// As reported by the provider (zeros for the mock generator); `None` when the reply
+    /// had no usage, as with some OpenAI-compatible servers.
+    pub tokens: Option<TokenUsage>,
+    /// The reply stopped at the output token limit, so the message is probably cut off.
+    pub truncated: bool,
+}
+
+/// The generator in use, behind the trait so callers don't care which provider it is.
+pub struct Generator(Box<dyn CommitMessageGenerator>);
+
 impl Generator {
-    pub async fn generate(&self, diff: &str, hint: Option<String>) -> Result<String> {
-        match self {
-            Generator::Mock(g) => g.generate(diff, hint).await,
-            Generator::OpenAI(g) => g.generate(diff, hint).await,
-            Generator::Anthropic(g) => g.generate(diff, hint).await,
-            Generator::Gemini(g) => g.generate(diff, hint).await,
+    /// Generate a message; real provider calls are recorded in the usage ledger.
+    pub async fn generate(&self, diff: &str, hint: Option<String>) -> Result<Generation> {
+        let generation = self.0.generate(diff, hint).await?;
+        self.record_usage(&generation);
+        Ok(formatted(generation))
+    }
+
+    /// `generate`, passing the reply so far to `on_token` as it arrives (unformatted).
+    pub async fn generate_streaming(
+        &self,
+        diff: &str,
+        hint: Option<String>,
+        on_token: OnToken<'_>,
+    ) -> Result<Generation> {
+        let generation = self.0.generate_streaming(diff, hint, on_token).await?;
+        self.record_usage(&generation);
+        Ok(formatted(generation))
+    }
+
+    /// Rewrite a generated message per the user's feedback, streamed like
+    /// `generate_streaming`.
+    pub async fn refine(
+        &self,
+        diff: &str,
+        hint: Option<String>,
+        refinement: &Refinement,
+        on_token: OnToken<'_>,
+    ) -> Result<Generation> {
+        let generation = self.0.refine(diff, hint, refinement, on_token).await?;
+        self.record_usage(&generation);
+        Ok(formatted(generation))
+    }
+
+    /// Free-form completion with a caller-supplied system prompt (e.g. release notes).
+    pub async fn complete(&self, system_prompt: &str, user_prompt: &str) -> Result<Generation> {
+        let generation = self.0.complete(system_prompt, user_prompt).await?;
+        self.record_usage(&generation);
+        Ok(generation)
+    }
+
+    /// Reply limit the provider is asked for (see `output_limit`).
+    pub fn output_limit(&self) -> u32 {
+        output_limit(self.0.prompt_options())
+    }
+
+    fn record_usage(&self, generation: &Generation) {
+        let Some(provider) = self.0.provider() else {
+            return;
+        };
+        let _ = usage::record(&UsageEntry::new(
+            provider.to_string(),
+            self.0.model().to_string(),
+            generation.latency.as_millis() as u64,
+            generation.prompt_bytes,
+        ));
+    }
+}
+
+/// `max_output_tokens` from the config, or the default limit.
+fn output_limit(options: &PromptOptions) -> u32 {
+    options
+        .max_output_tokens
+        .unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS)
+}
+
+/// `generation` with its message laid out per the config (`message::format_commit_message`).
+fn formatted(mut generation: Generation) -> Generation {
+    let options = FormatOptions::from_config(Config::load().ok().flatten().as_ref());
+    generation.message = message::format_commit_message(&generation.message, &options);
+    generation
+}
+
+/// The generator for `cfg`, or the mock generator. A new provider gets its arm here.
+pub fn from_config(cfg: Option<&Config>, mock_mode: bool) -> Result<Generator> {
+    let prompt = PromptOptions::from_config(cfg);
+    if mock_mode {
+        return Ok(Generator(Box::new(MockGenerator::new(prompt))));
+    }
+    let Some(cfg) = cfg else {
+        bail!("No config found. Run `git-wiz setup` or use the Config tab.");
+    };
+    let (api_key, model) = (cfg.api_key.clone(), cfg.model.clone());
+    let client = http::client()?;
+    let inner: Box<dyn CommitMessageGenerator> = match cfg.provider {
+        Provider::OpenAI => Box::new(OpenAIGenerator::new(
+            client,
+            api_key,
+            model,
+            openai_base_url(cfg)?,
+            prompt,
+        )),
+        Provider::Anthropic => Box::new(AnthropicGenerator::new(client, api_key, model, prompt)),
+        Provider::Gemini => Box::new(GeminiGenerator::new(client, api_key, model, prompt)),
+        Provider::OpenRouter => Box::new(OpenRouterGenerator::new(client, api_key, model, prompt)),
+        Provider::Groq => Box::new(OpenAIGenerator::hosted(
+            Provider::Groq,
+            GROQ_BASE_URL,
+            client,
+            api_key,
+            model,
+            prompt,
+        )),
+        Provider::Mistral => Box::new(OpenAIGenerator::hosted(
+            Provider::Mistral,
+            MISTRAL_BASE_URL,
+            client,
+            api_key,
+            model,
+            prompt,
+        )),
+    };
+    Ok(Generator(inner))
+}
+
+/// Build the generator for the current config (or the mock generator).
+///
+/// Returns `(generator, provider_label, model_label)` so callers can show what is in use.
+/// Shared by the CLI flows and the TUI background tasks.
+pub fn build_generator(mock_mode: bool) -> Result<(Generator, String, String)> {
+    // The mock generator only reads the prompt settings, so a broken config is fine.
+    let cfg = if mock_mode {
+        Config::load().ok().flatten()
+    } else {
+        Config::load()?
+    };
+    Ok(labelled(from_config(cfg.as_ref(), mock_mode)?))
+}
+
+/// `build_generator` with the reply limit raised to `max_output_tokens` for this run,
+/// after a reply was cut off. The config file is left as it is.
+pub fn build_generator_with_output_limit(
+    mock_mode: bool,
+    max_output_tokens: u32,
+) -> Result<(Generator, String, String)> {
+    let mut cfg = if mock_mode {
+        Config::load().ok().flatten()
+    } else {
+        Config::load()?
+    };
+    if let Some(cfg) = cfg.as_mut() {
+        cfg.max_output_tokens = Some(max_output_tokens);
+    }
+    Ok(labelled(from_config(cfg.as_ref(), mock_mode)?))
+}
+
+/// `generator` with its provider and model labels.
+fn labelled(generator: Generator) -> (Generator, String, String) {
+    let provider_label = generator
+        .0
+        .provider()
+        .map_or_else(|| "Mock".to_string(), |p| p.to_string());
+    let model_label = generator.0.model().to_string();
+    (generator, provider_label, model_label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_reads_each_providers_error_shape() {
+        let cases = [
+            (
+                Provider::OpenAI,
+                401,
+                r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error","code":"invalid_api_key"}}"#,
+                ErrorCategory::InvalidKey,
+            ),
+            (
+                Provider::OpenAI,
+                429,
+                r#"{"error":{"message":"You exceeded your current quota, please check your plan and billing details.","type":"insufficient_quota","code":"insufficient_quota"}}"#,
+                ErrorCategory::QuotaExceeded,
+            ),
+            (
+                Provider::OpenAI,
+                429,
+                r#"{"error":{"message":"Rate limit reached for gpt-4o-mini on requests per min (RPM)","type":"requests","code":"rate_limit_exceeded"}}"#,
+                ErrorCategory::RateLimited,
+            ),
+            (
+                Provider::OpenAI,
+                400,
+                r#"{"error":{"message":"This model's maximum context length is 128000 tokens.","type":"invalid_request_error","code":"context_length_exceeded"}}"#,
+                ErrorCategory::ContentTooLarge,
+            ),
+            (
+                Provider::Anthropic,
+                400,
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"Your credit balance is too low to access the Anthropic API."}}"#,
+                ErrorCategory::QuotaExceeded,
+            ),
+            (
+                Provider::Anthropic,
+                429,
+                r#"{"type":"error","error":{"type":"rate_limit_error","message":"Number of request tokens has exceeded your per-minute rate limit"}}"#,
+                ErrorCategory::RateLimited,
+            ),
+            (
+                Provider::Anthropic,
+                529,
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+                ErrorCategory::ServerError,
+            ),
+            (
+                Provider::Anthropic,
+                404,
+                r#"{"type":"error","error":{"type":"not_found_error","message":"model: claude-nope"}}"#,
+                ErrorCategory::ModelMissing,
+            ),
+            (
+                Provider::Gemini,
+                429,
+                r#"{"error":{"code":429,"message":"Quota exceeded for quota metric 'Generate Content API requests per minute' and limit 'GenerateContent request limit per minute for a region' of service 'generativelanguage.googleapis.com'.","status":"RESOURCE_EXHAUSTED","details":[{"@type":"type.googleapis.com/google.rpc.ErrorInfo","reason":"RATE_LIMIT_EXCEEDED"}]}}"#,
+                ErrorCategory::RateLimited,
+            ),
+            (
+                Provider::Gemini,
+                400,
+                r#"{"error":{"code":400,"message":"API key not valid. Please pass a valid API key.","status":"INVALID_ARGUMENT","details":[{"@type":"type.googleapis.com/google.rpc.ErrorInfo","reason":"API_KEY_INVALID"}]}}"#,
+                ErrorCategory::InvalidKey,
+            ),
+            (
+                Provider::Groq,
+                502,
+                "<html>Bad Gateway</html>",
+                ErrorCategory::ServerError,
+            ),
+            (Provider::Mistral, 418, "", ErrorCategory::Other),
+        ];
+        for (provider, status, body, expected) in cases {
+            let status = StatusCode::from_u16(status).unwrap();
+            let error = ApiError::classify(provider.clone(), status, body);
+            assert_eq!(error.category, expected, "{provider} {status}: {body}");
+        }
+    }
+    #[test]
+    fn clean_response_strips_only_the_wrapper() {
+        let cases = [
+            ("feat: add retry", "feat: add retry"),
+            ("  fix: trim input\n\n", "fix: trim input"),
+            ("```\nfeat: add retry\n```", "feat: add retry"),
+            (
+                "```text\nfeat: add retry\n\nBody.\n```",
+                "feat: add retry\n\nBody.",
+            ),
+            ("~~~~\nfix: x\n~~~~", "fix: x"),
+            // A cut-off reply loses the opening fence anyway.
+            ("```\nfix: x\n\nStill writing", "fix: x\n\nStill writing"),
+            (
+                "Here is the commit message:\n\nfeat(cli): add --yes",
+                "feat(cli): add --yes",
+            ),
+            (
+                "Sure! Here you go:\n\n```\nfix: handle empty diff\n```",
+                "fix: handle empty diff",
+            ),
+            // Not a lead-in: no subject or fence after the blank line.
+            (
+                "Update the parser\n\nIt now handles tabs.",
+                "Update the parser\n\nIt now handles tabs.",
+            ),
+            ("\"fix: quote the path\"", "fix: quote the path"),
+            ("'docs: fix typo'", "docs: fix typo"),
+            ("“chore: bump deps”", "chore: bump deps"),
+            // Quotes and fences inside the message stay.
+            (
+                "fix: escape \"quotes\"\n\nUse ```rust blocks``` in docs.",
+                "fix: escape \"quotes\"\n\nUse ```rust blocks``` in docs.",
+            ),
+            ("\" spaced\"", "\" spaced\""),
+        ];
+        for (reply, expected) in cases {
+            assert_eq!(clean_response(reply.to_string()), expected, "{reply:?}");
+        }
+    }
+
+    fn gemini() -> GeminiGenerator {
+        let prompt = PromptOptions {
+            max_output_tokens: Some(512),
+            ..PromptOptions::from_config(None)
+        };
+        GeminiGenerator::new(
+            Client::new(),
+            "secret-gemini-key".to_string(),
+            "gemini-2.0-flash".to_string(),
+            prompt,
+        )
+    }
+
+    #[test]
+    fn gemini_key_goes_in_a_header_with_the_generation_config() {
+        let request = gemini().build_request("system", "user").build().unwrap();
+
+        assert!(!request.url().as_str().contains("secret-gemini-key"));
+        assert_eq!(request.url().query(), None);
+        assert_eq!(request.headers()["x-goog-api-key"], "secret-gemini-key");
+
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "system");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "user");
+        assert_eq!(body["generationConfig"]["temperature"], TEMPERATURE);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 512);
+    }
+
+    #[test]
+    fn gemini_errors_never_show_the_key() {
+        let request = gemini().build_request("system", "user").build().unwrap();
+        let body = r#"{"error": {"code": 400, "message": "API key not valid.", "status": "INVALID_ARGUMENT"}}"#;
+        let err = api_error(
+            Provider::Gemini,
+            "gemini-2.0-flash",
+            StatusCode::BAD_REQUEST,
+            body,
+        )
+        .context(format!("POST {}", request.url()));
+        assert!(!format!("{:#}", err).contains("secret-gemini-key"));
+        assert!(!format!("{:?}", err).contains("secret-gemini-key"));
+    }
+
+    #[test]
+    fn gemini_reply_reads_the_finish_reason() {
+        let reply = |finish: &str, text: Option<&str>| {
+            let mut candidate = json!({ "finishReason": finish });
+            if let Some(text) = text {
+                candidate["content"] = json!({ "parts": [{ "text": text }] });
+            }
+            json!({ "candidates": [candidate] })
+        };
+
+        assert_eq!(
+            gemini_reply(&reply("STOP", Some(" feat: add b \n")), 512).unwrap(),
+            ("feat: add b".to_string(), false)
+        );
+        assert_eq!(
+            gemini_reply(&reply("MAX_TOKENS", Some("feat: add")), 512).unwrap(),
+            ("feat: add".to_string(), true)
+        );
+
+        let error = |reply: serde_json::Value| gemini_reply(&reply, 512).unwrap_err().to_string();
+        assert!(error(reply("MAX_TOKENS", None)).contains("maxOutputTokens (512)"));
+        assert!(error(reply("SAFETY", None)).contains("safety filters flagged"));
+        assert!(error(reply("RECITATION", Some("x"))).contains("(RECITATION)"));
+        assert!(error(json!({ "candidates": [] })).contains("no candidates"));
+        assert!(
+            error(json!({ "promptFeedback": { "blockReason": "OTHER" } }))
+                .contains("blocked the request (OTHER)")
+        );
+    }
+
+    const MOCK_DIFF: &str = "\
+diff --git a/src/git.rs b/src/git.rs
+index 1111111..2222222 100644
+--- a/src/git.rs
++++ b/src/git.rs
+@@ -1,4 +1,5 @@
+ use std::fs;
+-use std::path::Path;
++use std::path::{Path, PathBuf};
++use std::process::Command;
+ 
+ fn main() {}
+diff --git a/README.md b/README.md
+index 3333333..4444444 100644
+--- a/README.md
++++ b/README.md
+@@ -10,3 +10,2 @@
+ ## Usage
+--- a list item
+ text
+diff --git a/docs/logo.png b/docs/logo.png
+new file mode 100644
+index 0000000..5555555
+Binary files /dev/null and b/docs/logo.png differ
+diff --git a/src/tui/view.rs b/src/tui/view.rs
+index 6666666..7777777 100644
+--- a/src/tui/view.rs
++++ b/src/tui/view.rs
+@@ -1 +1 @@
+-old
++new
+";
+
+    #[test]
+    fn mock_message_describes_the_diff() {
+        assert_eq!(
+            mock_message(CommitStyle::Conventional, MOCK_DIFF, None),
+            "chore: update git.rs, README.md and 2 more files\n\n\
+             - src/git.rs: +2 -1\n\
+             - README.md: +0 -1\n\
+             - docs/logo.png: binary\n\
+             - 1 more files"
+        );
+
+        let two_files = MOCK_DIFF.split("diff --git a/docs").next().unwrap();
+        assert_eq!(
+            mock_message(CommitStyle::Plain, two_files, None),
+            "Update git.rs and README.md\n\n- src/git.rs: +2 -1\n- README.md: +0 -1"
+        );
+        assert_eq!(
+            mock_message(CommitStyle::Gitmoji, "", None),
+            "✨ Update files"
+        );
+    }
+
+    #[test]
+    fn mock_message_uses_the_hint_as_subject() {
+        for (style, subject) in [
+            (CommitStyle::Conventional, "feat: quote paths"),
+            (CommitStyle::Gitmoji, "✨ quote paths"),
+            (CommitStyle::Plain, "quote paths"),
+        ] {
+            let message = mock_message(style, MOCK_DIFF, Some("quote paths".to_string()));
+            assert_eq!(message.lines().next(), Some(subject));
+            assert_eq!(message.lines().nth(2), Some("- src/git.rs: +2 -1"));
         }
     }
+
+    #[tokio::test]
+    async fn mock_generator_is_deterministic() {
+        std::env::set_var("MOCK_DELAY_MS", "0");
+        let generator = MockGenerator::new(PromptOptions {
+            style: CommitStyle::Conventional,
+            budget_tokens: 8192,
+            template: None,
+            max_output_tokens: None,
+            language: Some("de".to_string()),
+            style_examples: Vec::new(),
+            scope_map: Default::default(),
+            detect_breaking: false,
+            commit_template: None,
+        });
+
+        let first = generator.generate(MOCK_DIFF, None).await.unwrap();
+        let second = generator.generate(MOCK_DIFF, None).await.unwrap();
+        assert_eq!(first.message, second.message);
+        assert!(first
+            .message
+            .starts_with("chore: update git.rs, README.md and 2 more files\n\n"));
+        assert!(first.message.ends_with("\n- Written in German"));
+        assert!(first.prompt_bytes > MOCK_DIFF.len());
+        assert!(first.latency < MOCK_DELAY);
+    }
 }
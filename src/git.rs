@@ -1,41 +1,171 @@
 use anyhow::{bail, Context, Result};
-use std::fs;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg(feature = "tui")]
+use crate::diff_parse::{self, FileDiff};
+use crate::platform;
+use crate::read_only;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DiffSource {
     Staged,
     Unstaged,
     Both,
+    /// What HEAD changed since it forked from a branch, tag or commit (`git diff <ref>...HEAD`).
+    #[serde(rename = "ref")]
+    AgainstRef(String),
+}
+
+/// Kind of ref a user-typed name is for (see `validate_ref_name`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    Tag,
+    Branch,
+}
+
+impl RefKind {
+    fn namespace(self) -> &'static str {
+        match self {
+            RefKind::Tag => "refs/tags/",
+            RefKind::Branch => "refs/heads/",
+        }
+    }
+}
+
+impl std::fmt::Display for RefKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefKind::Tag => write!(f, "tag"),
+            RefKind::Branch => write!(f, "branch"),
+        }
+    }
 }
 
+/// Content of each untracked file shown in unstaged diffs.
+const UNTRACKED_MAX_BYTES: usize = 16 * 1024;
+
+/// Files the pre-flight summaries list by size (`DiffSummary::largest_files`).
+pub const LARGEST_FILES: usize = 5;
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct DiffSummary {
     pub files_changed: usize,
     pub insertions: usize,
     pub deletions: usize,
+    /// Files without line counts (included in `files_changed`).
+    pub binary_files: usize,
     pub bytes: usize,
+    /// Each file's counts, in diff order.
+    pub files: Vec<FileStat>,
+}
+
+/// Work tree root every git command runs in (`git -C`), chosen once at startup.
+static REPO: OnceLock<PathBuf> = OnceLock::new();
+
+/// Run git in the work tree containing `path` (`--repo`), or the current directory, for
+/// the rest of the process. Outside a repository only an explicit `path` is an error;
+/// otherwise git keeps running where it was started and `ensure_repo` reports it.
+pub fn select_repo(path: Option<&Path>) -> Result<()> {
+    let dir = path.unwrap_or(Path::new("."));
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output();
+    match output {
+        Ok(o) if o.status.success() => {
+            let root = String::from_utf8_lossy(&o.stdout).trim().to_string();
+            let _ = REPO.set(PathBuf::from(root));
+            IN_WORK_TREE.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        _ if path.is_some() => bail!("Not a git repository: {}", dir.display()),
+        _ => Ok(()),
+    }
+}
+
+/// `command` run in the selected work tree (`-C <root>`).
+fn in_repo(mut command: Command) -> Command {
+    #[cfg(test)]
+    if let Some(root) = crate::test_support::current_repo() {
+        command.arg("-C").arg(root);
+        return command;
+    }
+    if let Some(root) = REPO.get() {
+        command.arg("-C").arg(root);
+    }
+    command
+}
+
+/// A `git` command for the selected repository (see `select_repo`).
+pub fn command() -> Command {
+    in_repo(Command::new("git"))
+}
+
+/// `name` inside the git directory (`git rev-parse --git-path`), e.g. `MERGE_MSG`.
+pub fn git_path(name: &str) -> Result<PathBuf> {
+    let output = run_git(&["rev-parse", "--git-path", name])?;
+    if !output.status.success() {
+        bail!(
+            "git rev-parse --git-path failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(in_git_dir(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// A path `git rev-parse --git-path` printed, which is relative to the directory git ran
+/// in, not to ours.
+fn in_git_dir(printed: &str) -> PathBuf {
+    let path = PathBuf::from(printed);
+    #[cfg(test)]
+    if let Some(root) = crate::test_support::current_repo() {
+        return root.join(path);
+    }
+    match REPO.get() {
+        Some(root) => root.join(path),
+        None => path,
+    }
 }
 
+/// Set once git has confirmed the work tree. It stays one for the rest of the process
+/// (short of `.git` being deleted, which git's own errors then report), so every helper
+/// can `ensure_repo` without spawning `git rev-parse` again.
+static IN_WORK_TREE: AtomicBool = AtomicBool::new(false);
+
 pub fn is_repo() -> bool {
-    Command::new("git")
+    if IN_WORK_TREE.load(Ordering::Relaxed) {
+        return true;
+    }
+    let inside = command()
         .arg("rev-parse")
         .arg("--is-inside-work-tree")
         .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+        .is_ok_and(|o| o.status.success());
+    if inside {
+        IN_WORK_TREE.store(true, Ordering::Relaxed);
+    }
+    inside
 }
 
 fn run_git(args: &[&str]) -> Result<std::process::Output> {
-    Command::new("git")
+    command()
         .args(args)
         .output()
         .with_context(|| format!("Failed to run git {}", args.join(" ")))
 }
 
+/// Run an interactive git command with inherited stdio (see `platform::interactive_git`).
 fn run_git_status(args: &[&str]) -> Result<std::process::ExitStatus> {
-    Command::new("git")
+    in_repo(platform::interactive_git())
         .args(args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -51,33 +181,548 @@ fn ensure_repo() -> Result<()> {
     Ok(())
 }
 
-pub fn get_diff(source: DiffSource) -> Result<String> {
+/// The editor git itself would use, as program + arguments (e.g. `code --wait`):
+/// `$GIT_EDITOR`, then `core.editor`, then `$VISUAL`, `$EDITOR`, then the platform default.
+pub fn resolve_editor() -> (String, Vec<String>) {
+    let core_editor = run_git(&["config", "--get", "core.editor"])
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string());
+    let editor = platform::pick_editor([
+        std::env::var("GIT_EDITOR").ok(),
+        core_editor,
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+    ]);
+    platform::split_command(&editor)
+}
+
+/// Open `path` in the editor from `resolve_editor` and wait for it to close.
+pub fn edit_file(path: &Path) -> Result<()> {
+    let (program, args) = resolve_editor();
+    if program.is_empty() {
+        bail!("Editor command is empty");
+    }
+    let status = Command::new(&program)
+        .args(&args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", program))?;
+    if !status.success() {
+        bail!("Editor exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Absolute path of the work tree root.
+pub fn repo_root() -> Result<PathBuf> {
+    ensure_repo()?;
+    let output = run_git(&["rev-parse", "--show-toplevel"])?;
+    if !output.status.success() {
+        bail!(
+            "git rev-parse --show-toplevel failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Current branch name (`HEAD` when detached).
+pub fn current_branch() -> Result<String> {
+    ensure_repo()?;
+    let output = run_git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+    if !output.status.success() {
+        bail!(
+            "git rev-parse --abbrev-ref HEAD failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Short hash of HEAD when it is detached (`git symbolic-ref -q HEAD` fails), e.g. after
+/// `git checkout <commit>` or mid-rebase; `None` on a branch, including an unborn one.
+pub fn detached_head() -> Result<Option<String>> {
+    ensure_repo()?;
+    let output = run_git(&["symbolic-ref", "-q", "HEAD"])?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    // 1 is "not a symbolic ref"; anything else is a real failure.
+    if output.status.code() != Some(1) {
+        bail!(
+            "git symbolic-ref HEAD failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(Some(head_short_hash()?))
+}
+
+fn head_short_hash() -> Result<String> {
+    let output = run_git(&["rev-parse", "--short", "HEAD"])?;
+    if !output.status.success() {
+        bail!(
+            "git rev-parse --short HEAD failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returned by the push when HEAD is detached: there is no branch to push or to track
+/// an upstream. `create_branch` fixes it.
+#[derive(Debug, Clone)]
+pub struct DetachedHead {
+    /// Short hash of HEAD.
+    pub commit: String,
+}
+
+impl std::fmt::Display for DetachedHead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HEAD is detached at {}, so there is no branch to push. Create a branch here \
+             first (`git switch -c <name>`).",
+            self.commit
+        )
+    }
+}
+
+impl std::error::Error for DetachedHead {}
+
+/// A local branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    pub name: String,
+    /// Checked out.
+    pub current: bool,
+    /// e.g. `origin/main`
+    pub upstream: Option<String>,
+}
+
+/// Local branches, most recently committed to first.
+pub fn branches() -> Result<Vec<Branch>> {
+    ensure_repo()?;
+    let o = run_git(&[
+        "for-each-ref",
+        "--sort=-committerdate",
+        "--format=%(HEAD)%00%(refname:short)%00%(upstream:short)",
+        "refs/heads",
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git for-each-ref refs/heads failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let current = fields.next()? == "*";
+            let name = fields.next()?.to_string();
+            let upstream = fields.next().filter(|u| !u.is_empty()).map(str::to_string);
+            Some(Branch {
+                name,
+                current,
+                upstream,
+            })
+        })
+        .collect())
+}
+
+/// `git switch <switch_args>`, or `git checkout <checkout_args>` on git before 2.23,
+/// which has no `switch`.
+fn switch_or_checkout(switch_args: &[&str], checkout_args: &[&str]) -> Result<()> {
+    let mut args = vec!["switch"];
+    args.extend(switch_args);
+    let mut output = run_git(&args)?;
+    if !output.status.success()
+        && String::from_utf8_lossy(&output.stderr).contains("is not a git command")
+    {
+        args = vec!["checkout"];
+        args.extend(checkout_args);
+        output = run_git(&args)?;
+    }
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// A new branch at HEAD, checked out (`git switch -c`) when `checkout`. Uncommitted
+/// changes come along.
+pub fn create_branch(name: &str, checkout: bool) -> Result<()> {
+    read_only::check("Creating branches")?;
+    ensure_repo()?;
+    let name = name.trim();
+    validate_ref_name(RefKind::Branch, name)?;
+    if checkout {
+        return switch_or_checkout(&["-c", name], &["-b", name]);
+    }
+    let output = run_git(&["branch", name])?;
+    if !output.status.success() {
+        bail!(
+            "git branch {} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// `git switch <name>`. Local changes that the switch would overwrite make git refuse;
+/// its message is the error.
+pub fn switch_branch(name: &str) -> Result<()> {
+    read_only::check("Switching branches")?;
+    ensure_repo()?;
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Branch name cannot be empty.");
+    }
+    switch_or_checkout(&[name], &[name])
+}
+
+/// Check `name` against git's ref rules before anything is created or pushed.
+///
+/// The common mistakes get a plain explanation ("tag names cannot contain spaces");
+/// `git check-ref-format` has the final word on the rest. Works outside a repository.
+pub fn validate_ref_name(kind: RefKind, name: &str) -> Result<()> {
+    let problem = if name.trim().is_empty() {
+        Some("cannot be empty".to_string())
+    } else if name.chars().any(char::is_whitespace) {
+        Some("cannot contain spaces".to_string())
+    } else if name.starts_with('-') {
+        Some("cannot start with '-'".to_string())
+    } else if name.contains("..") {
+        Some("cannot contain '..'".to_string())
+    } else if name.contains("@{") {
+        Some("cannot contain '@{'".to_string())
+    } else if let Some(c) = name
+        .chars()
+        .find(|c| c.is_control() || "~^:?*[\\".contains(*c))
+    {
+        Some(format!("cannot contain '{}'", c.escape_default()))
+    } else if name.ends_with(".lock") {
+        Some("cannot end with '.lock'".to_string())
+    } else if name.ends_with('.') || name.ends_with('/') {
+        Some(format!("cannot end with '{}'", &name[name.len() - 1..]))
+    } else if name.split('/').any(|c| c.is_empty() || c.starts_with('.')) {
+        Some("cannot have empty parts or parts starting with '.'".to_string())
+    } else if name == "@" {
+        Some("cannot be '@'".to_string())
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        bail!(
+            "Invalid {} name '{}': {} names {}.",
+            kind,
+            name,
+            kind,
+            problem
+        );
+    }
+
+    let full = format!("{}{}", kind.namespace(), name);
+    let output = run_git(&["check-ref-format", &full])?;
+    if !output.status.success() {
+        bail!(
+            "Invalid {} name '{}': not a valid git ref (see `git help check-ref-format`).",
+            kind,
+            name
+        );
+    }
+    Ok(())
+}
+
+/// The diff of `source`, limited to `paths` (relative to the repository root; all
+/// changes when empty). Fails when there is nothing to show.
+pub fn get_diff(source: DiffSource, paths: &[&Path]) -> Result<String> {
     ensure_repo()?;
+    if !paths.is_empty() {
+        let diff = get_diff_allow_empty(source, paths)?;
+        if diff.trim().is_empty() {
+            bail!("No changes found in the selected files.");
+        }
+        return Ok(diff);
+    }
 
     match source {
         DiffSource::Staged => get_diff_staged(),
         DiffSource::Unstaged => get_diff_unstaged(),
         DiffSource::Both => {
-            let staged = get_diff_staged_allow_empty()?;
-            let unstaged = get_diff_unstaged_allow_empty()?;
+            let (staged, unstaged) =
+                concurrently(get_diff_staged_allow_empty, get_diff_unstaged_allow_empty)?;
 
             if staged.trim().is_empty() && unstaged.trim().is_empty() {
                 bail!("No staged or unstaged changes found.");
             }
 
-            let combined = match (staged.trim().is_empty(), unstaged.trim().is_empty()) {
-                (false, true) => staged,
-                (true, false) => unstaged,
-                (false, false) => format!(
-                    "--- STAGED ---\n{}\n\n--- UNSTAGED ---\n{}",
-                    staged, unstaged
-                ),
-                (true, true) => unreachable!(),
-            };
+            Ok(combine_both(staged, unstaged))
+        }
+        DiffSource::AgainstRef(reference) => {
+            let diff = get_diff_against_ref_allow_empty(&reference)?;
+            if diff.trim().is_empty() {
+                bail!("No changes on HEAD since {}.", reference);
+            }
+            Ok(diff)
+        }
+    }
+}
+
+/// `a()` and `b()` at the same time, e.g. the staged and unstaged side of a diff: on a
+/// large work tree each git call takes a while, and they don't depend on each other.
+fn concurrently<A: Send, B>(
+    a: impl FnOnce() -> Result<A> + Send,
+    b: impl FnOnce() -> Result<B>,
+) -> Result<(A, B)> {
+    // A test's repository is per thread; hand it to the one spawned here.
+    #[cfg(test)]
+    let a = {
+        let repo = crate::test_support::current_repo();
+        move || {
+            crate::test_support::set_current_repo(repo);
+            a()
+        }
+    };
+    std::thread::scope(|s| {
+        let a = s.spawn(a);
+        let b = b();
+        let a = a.join().unwrap_or_else(|e| std::panic::resume_unwind(e));
+        Ok((a?, b?))
+    })
+}
+
+/// Staged and unstaged diffs as one text; at least one of them must be non-empty.
+fn combine_both(staged: String, unstaged: String) -> String {
+    match (staged.trim().is_empty(), unstaged.trim().is_empty()) {
+        (false, true) => staged,
+        (true, false) => unstaged,
+        (false, false) => format!(
+            "--- STAGED ---\n{}\n\n--- UNSTAGED ---\n{}",
+            staged, unstaged
+        ),
+        (true, true) => unreachable!(),
+    }
+}
+
+/// Diff text for the model, with some changed files left out.
+#[derive(Debug, Clone)]
+pub struct FilteredDiff {
+    pub text: String,
+    /// Changed files (repository-relative) that are not in `text`.
+    pub excluded: Vec<String>,
+}
+
+/// Context lines around each change in diffs for the model; the default is git's own
+/// (`diff.context`, else 3 lines). `diff_context_lines` and `diff_function_context` in the
+/// config, overridden per repository by `.gitwiz.toml` (see `repo_config::diff_context`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffContext {
+    /// `-U<n>`.
+    pub lines: Option<u32>,
+    /// `-W`: the whole function around each change.
+    pub function: bool,
+}
+
+impl DiffContext {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = self.lines.iter().map(|n| format!("-U{}", n)).collect();
+        if self.function {
+            args.push("-W".to_string());
+        }
+        args
+    }
+}
+
+/// `get_diff` without the files matching any of `excludes` (git pathspec globs relative to
+/// the repository root, e.g. `**/Cargo.lock`), with `context` around each change.
+///
+/// Only the text for the model is filtered; commits still take the whole index. When every
+/// changed file is excluded the full diff is returned, so such changes still get a message.
+pub fn get_diff_filtered(
+    source: DiffSource,
+    excludes: &[String],
+    context: DiffContext,
+) -> Result<FilteredDiff> {
+    let full = get_diff(source.clone(), &[])?;
+    let mut pathspecs = vec![":/".to_string()];
+    let mut excluded = Vec::new();
+    if !excludes.is_empty() {
+        let mut kept_specs = pathspecs.clone();
+        kept_specs.extend(excludes.iter().map(|g| format!(":(top,exclude,glob){}", g)));
+        let kept = changed_paths(&source, &kept_specs)?;
+        excluded = changed_paths(&source, &[])?
+            .into_iter()
+            .filter(|p| !kept.contains(p))
+            .collect();
+        if kept.is_empty() {
+            excluded.clear();
+        } else if !excluded.is_empty() {
+            pathspecs = kept_specs;
+        }
+    }
+    if excluded.is_empty() && context.is_default() {
+        return Ok(FilteredDiff {
+            text: full,
+            excluded,
+        });
+    }
+
+    let text = diff_with_pathspecs(&source, &pathspecs, context)?;
+    Ok(FilteredDiff { text, excluded })
+}
+
+/// The diff of `source` limited to `pathspecs`, with `context`; empty when nothing matches.
+fn diff_with_pathspecs(
+    source: &DiffSource,
+    pathspecs: &[String],
+    context: DiffContext,
+) -> Result<String> {
+    let context = context.args();
+    let context: Vec<&str> = context.iter().map(String::as_str).collect();
+    diff_with_args(source, pathspecs, &context)
+}
+
+/// The diff of `source` limited to `pathspecs`, with `extra` diff options; empty when
+/// nothing matches.
+fn diff_with_args(source: &DiffSource, pathspecs: &[String], extra: &[&str]) -> Result<String> {
+    let staged = || diff_pathspecs(&[&["diff", "--cached"], extra].concat(), pathspecs);
+    let unstaged = || -> Result<String> {
+        Ok(diff_pathspecs(&[&["diff"], extra].concat(), pathspecs)? + &untracked_diff(pathspecs)?)
+    };
+    Ok(match source {
+        DiffSource::Staged => staged()?,
+        DiffSource::Unstaged => unstaged()?,
+        DiffSource::Both => {
+            let (staged, unstaged) = concurrently(staged, unstaged)?;
+            if staged.trim().is_empty() && unstaged.trim().is_empty() {
+                String::new()
+            } else {
+                combine_both(staged, unstaged)
+            }
+        }
+        DiffSource::AgainstRef(reference) => {
+            let range = ref_range(reference)?;
+            diff_pathspecs(&[&["diff", &range], extra].concat(), pathspecs)?
+        }
+    })
+}
+
+/// Root-relative `paths` as literal pathspecs, so `*` or `:` in a name are just characters.
+fn literal_pathspecs(paths: &[&Path]) -> Result<Vec<String>> {
+    paths
+        .iter()
+        .map(|p| {
+            p.to_str()
+                .map(|p| format!(":(top,literal){}", p))
+                .with_context(|| format!("Path is not valid UTF-8: {}", p.display()))
+        })
+        .collect()
+}
+
+/// Paths changed in `source`, limited to `pathspecs` (all paths when empty).
+fn changed_paths(source: &DiffSource, pathspecs: &[String]) -> Result<Vec<String>> {
+    let names = |base: &[&str]| -> Result<Vec<String>> {
+        let text = diff_pathspecs(&[base, &["--name-only"]].concat(), pathspecs)?;
+        Ok(text.lines().map(str::to_string).collect())
+    };
+    let mut paths = match source {
+        DiffSource::Staged => names(&["diff", "--cached"])?,
+        DiffSource::Unstaged => [names(&["diff"])?, untracked_paths(pathspecs)?].concat(),
+        DiffSource::Both => [
+            names(&["diff", "--cached"])?,
+            names(&["diff"])?,
+            untracked_paths(pathspecs)?,
+        ]
+        .concat(),
+        DiffSource::AgainstRef(reference) => names(&["diff", &ref_range(reference)?])?,
+    };
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// `git <args> -- <pathspecs>` stdout.
+fn diff_pathspecs(args: &[&str], pathspecs: &[String]) -> Result<String> {
+    let mut full: Vec<&str> = args.to_vec();
+    if !pathspecs.is_empty() {
+        full.push("--");
+        full.extend(pathspecs.iter().map(String::as_str));
+    }
+    let o = run_git(&full)?;
+    if !o.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(diff_text(o.stdout))
+}
+
+/// Diff output as text. Bytes that aren't UTF-8 (Latin-1 files, mixed encodings) become
+/// U+FFFD instead of failing the whole diff; `encoding_warning` reports them.
+fn diff_text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
 
-            Ok(combined)
+/// e.g. `The diff has 3 invalid UTF-8 sequences (shown as �) in notes.txt; …`, when
+/// `diff_text` replaced any. A file that really contains U+FFFD counts too.
+pub fn encoding_warning(diff: &str) -> Option<String> {
+    const LISTED: usize = 3;
+    let mut replaced = 0;
+    let mut files: Vec<&str> = Vec::new();
+    let mut path = "";
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            path = rest.rsplit_once(" b/").map_or(rest, |(_, b)| b);
+            continue;
+        }
+        let count = line.matches(char::REPLACEMENT_CHARACTER).count();
+        if count > 0 {
+            replaced += count;
+            if !path.is_empty() && files.last() != Some(&path) {
+                files.push(path);
+            }
         }
     }
+    (replaced > 0).then(|| {
+        let mut listed: Vec<String> = files.iter().take(LISTED).map(|f| f.to_string()).collect();
+        if files.len() > LISTED {
+            listed.push(format!("{} more", files.len() - LISTED));
+        }
+        let location = if listed.is_empty() {
+            String::new()
+        } else {
+            format!(" in {}", listed.join(", "))
+        };
+        format!(
+            "The diff has {} invalid UTF-8 {} (shown as {}){}; lines in other encodings may \
+             be misread.",
+            replaced,
+            if replaced == 1 {
+                "sequence"
+            } else {
+                "sequences"
+            },
+            char::REPLACEMENT_CHARACTER,
+            location
+        )
+    })
 }
 
 pub fn get_diff_staged() -> Result<String> {
@@ -113,9 +758,37 @@ pub fn get_diff_staged_allow_empty() -> Result<String> {
         );
     }
 
-    String::from_utf8(output.stdout).context("git diff --cached output was not valid UTF-8")
+    Ok(diff_text(output.stdout))
 }
 
+/// `git diff <reference>...HEAD`: what HEAD changed since it forked from `reference`.
+pub fn get_diff_against_ref_allow_empty(reference: &str) -> Result<String> {
+    ensure_repo()?;
+    diff_pathspecs(&["diff", &ref_range(reference)?], &[])
+}
+
+/// Fails with a readable "unknown revision" error unless `reference` names a commit.
+pub fn verify_ref(reference: &str) -> Result<()> {
+    let reference = reference.trim();
+    if reference.is_empty() {
+        bail!("No ref given to compare against.");
+    }
+    if reference.starts_with('-') || !ref_exists(reference) {
+        bail!(
+            "Unknown revision '{}': not a branch, tag or commit in this repository.",
+            reference
+        );
+    }
+    Ok(())
+}
+
+/// `<reference>...HEAD`, once `verify_ref` accepts `reference`.
+fn ref_range(reference: &str) -> Result<String> {
+    verify_ref(reference)?;
+    Ok(format!("{}...HEAD", reference.trim()))
+}
+
+/// `git diff`, followed by the untracked files as new-file diffs (see `untracked_diff`).
 pub fn get_diff_unstaged_allow_empty() -> Result<String> {
     ensure_repo()?;
     let output = run_git(&["diff"])?;
@@ -127,39 +800,120 @@ pub fn get_diff_unstaged_allow_empty() -> Result<String> {
         );
     }
 
-    String::from_utf8(output.stdout).context("git diff output was not valid UTF-8")
+    Ok(diff_text(output.stdout) + &untracked_diff(&[])?)
+}
+
+/// Untracked, non-ignored files (repository-relative), limited to `pathspecs` (all when
+/// empty).
+fn untracked_paths(pathspecs: &[String]) -> Result<Vec<String>> {
+    let o = command()
+        .current_dir(repo_root()?)
+        .args(["ls-files", "-z", "--others", "--exclude-standard", "--"])
+        .args(pathspecs)
+        .output()
+        .context("Failed to run git ls-files")?;
+    if !o.status.success() {
+        bail!(
+            "git ls-files failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The untracked files in `pathspecs` as diffs against `/dev/null`, so new files show up
+/// in unstaged diffs like `git add -N` would show them. Text beyond `UNTRACKED_MAX_BYTES`
+/// per file is left out with a note; binary files (with a NUL byte) get the name only.
+fn untracked_diff(pathspecs: &[String]) -> Result<String> {
+    let root = repo_root()?;
+    let mut diff = String::new();
+    for path in untracked_paths(pathspecs)? {
+        diff.push_str(&format!(
+            "diff --git a/{path} b/{path}\nnew file mode 100644\n",
+            path = path
+        ));
+        // Directories (nested repositories) and unreadable files: the name is all there is.
+        let Ok(bytes) = std::fs::read(root.join(&path)) else {
+            continue;
+        };
+        if bytes.contains(&0) {
+            diff.push_str(&format!("Binary files /dev/null and b/{} differ\n", path));
+            continue;
+        }
+        // Text in another encoding than UTF-8 is still text, like in tracked diffs.
+        let text = String::from_utf8_lossy(&bytes);
+        if text.is_empty() {
+            continue;
+        }
+        let mut end = text.len().min(UNTRACKED_MAX_BYTES);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Whole lines only.
+        let shown = match text[..end].rfind('\n') {
+            Some(i) if end < text.len() => &text[..=i],
+            _ => &text[..end],
+        };
+        let lines: Vec<&str> = shown.lines().collect();
+        diff.push_str(&format!(
+            "--- /dev/null\n+++ b/{}\n@@ -0,0 +1,{} @@\n",
+            path,
+            lines.len()
+        ));
+        for line in &lines {
+            diff.push('+');
+            diff.push_str(line);
+            diff.push('\n');
+        }
+        if shown.len() < text.len() {
+            diff.push_str(&format!(
+                "\\ [… {} more bytes not shown]\n",
+                text.len() - shown.len()
+            ));
+        } else if !text.ends_with('\n') {
+            diff.push_str("\\ No newline at end of file\n");
+        }
+    }
+    Ok(diff)
+}
+
+/// `get_diff_allow_empty` as a word diff (`--word-diff=plain`: `[-old-]{+new+}` inline),
+/// for reading changes to prose. Untracked files still show as added lines.
+pub fn get_word_diff_allow_empty(source: DiffSource) -> Result<String> {
+    ensure_repo()?;
+    diff_with_args(&source, &[], &["--word-diff=plain"])
 }
 
-pub fn get_diff_allow_empty(source: DiffSource) -> Result<String> {
+/// `get_diff`, but empty instead of an error when there is nothing to show.
+pub fn get_diff_allow_empty(source: DiffSource, paths: &[&Path]) -> Result<String> {
     ensure_repo()?;
+    if !paths.is_empty() {
+        return diff_with_pathspecs(&source, &literal_pathspecs(paths)?, DiffContext::default());
+    }
 
     match source {
         DiffSource::Staged => get_diff_staged_allow_empty(),
         DiffSource::Unstaged => get_diff_unstaged_allow_empty(),
         DiffSource::Both => {
-            let staged = get_diff_staged_allow_empty()?;
-            let unstaged = get_diff_unstaged_allow_empty()?;
+            let (staged, unstaged) =
+                concurrently(get_diff_staged_allow_empty, get_diff_unstaged_allow_empty)?;
 
             if staged.trim().is_empty() && unstaged.trim().is_empty() {
                 return Ok(String::new());
             }
 
-            let combined = match (staged.trim().is_empty(), unstaged.trim().is_empty()) {
-                (false, true) => staged,
-                (true, false) => unstaged,
-                (false, false) => format!(
-                    "--- STAGED ---\n{}\n\n--- UNSTAGED ---\n{}",
-                    staged, unstaged
-                ),
-                (true, true) => unreachable!(),
-            };
-
-            Ok(combined)
+            Ok(combine_both(staged, unstaged))
         }
+        DiffSource::AgainstRef(reference) => get_diff_against_ref_allow_empty(&reference),
     }
 }
 
 pub fn stage_patch() -> Result<()> {
+    read_only::check("Staging")?;
     ensure_repo()?;
     let status = run_git_status(&["add", "-p"])?;
     if !status.success() {
@@ -169,6 +923,7 @@ pub fn stage_patch() -> Result<()> {
 }
 
 pub fn stage_all() -> Result<()> {
+    read_only::check("Staging")?;
     ensure_repo()?;
     let output = run_git(&["add", "-A"])?;
     if !output.status.success() {
@@ -181,10 +936,11 @@ pub fn stage_all() -> Result<()> {
 }
 
 pub fn unstage_patch() -> Result<()> {
+    read_only::check("Unstaging")?;
     ensure_repo()?;
 
     // Prefer `git restore --staged -p` (newer), fallback to `git reset -p`.
-    let status = Command::new("git")
+    let status = in_repo(platform::interactive_git())
         .args(["restore", "--staged", "-p", "."])
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -204,12 +960,11 @@ pub fn unstage_patch() -> Result<()> {
 }
 
 pub fn unstage_all() -> Result<()> {
+    read_only::check("Unstaging")?;
     ensure_repo()?;
 
     // Prefer `git restore --staged .`, fallback to `git reset`.
-    let output = Command::new("git")
-        .args(["restore", "--staged", "."])
-        .output();
+    let output = command().args(["restore", "--staged", "."]).output();
     match output {
         Ok(o) if o.status.success() => Ok(()),
         Ok(_) | Err(_) => {
@@ -225,111 +980,3097 @@ pub fn unstage_all() -> Result<()> {
     }
 }
 
-pub fn diff_summary(source: DiffSource) -> Result<DiffSummary> {
+/// The unstaged diff of tracked files, or with `staged` the staged one, split into files
+/// and hunks to stage or unstage one at a time. Prefixes, renames, textconv and external
+/// diff tools are pinned so the hunks apply back with `git apply`.
+#[cfg(feature = "tui")]
+pub fn hunks(staged: bool) -> Result<Vec<FileDiff>> {
     ensure_repo()?;
+    let mut args = vec![
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-textconv",
+        "--no-renames",
+        "--binary",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+    ];
+    if staged {
+        args.push("--cached");
+    }
+    let o = run_git(&args)?;
+    if !o.status.success() {
+        bail!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(diff_parse::parse(&diff_text(o.stdout)))
+}
 
-    let bytes = match source {
-        DiffSource::Staged => get_diff_staged_allow_empty()?.len(),
-        DiffSource::Unstaged => get_diff_unstaged_allow_empty()?.len(),
-        DiffSource::Both => {
-            let a = get_diff_staged_allow_empty()?.len();
-            let b = get_diff_unstaged_allow_empty()?.len();
-            a + b
-        }
-    };
+/// A hunk the index didn't take, e.g. because the file changed since it was listed.
+#[cfg(feature = "tui")]
+#[derive(Debug, Clone)]
+pub struct HunkFailure {
+    pub path: String,
+    /// The hunk's body, to find it again in a fresh listing; `None` for a whole-file patch.
+    pub lines: Option<Vec<String>>,
+    pub message: String,
+}
 
-    // Use numstat for insertions/deletions and file count.
-    // For Both, combine cached + working-tree.
-    let mut summary = DiffSummary {
-        files_changed: 0,
-        insertions: 0,
-        deletions: 0,
-        bytes,
+/// Stage the `chosen` hunks of `file` (from `hunks(false)`), or with `unstage` take them
+/// out of the index (from `hunks(true)`). Each hunk is its own `git apply --cached`, so one
+/// that no longer applies fails alone; the failures are returned. A file without hunks
+/// (mode change, empty or binary file) goes as a whole.
+#[cfg(feature = "tui")]
+pub fn apply_hunks(file: &FileDiff, chosen: &[usize], unstage: bool) -> Result<Vec<HunkFailure>> {
+    read_only::check(if unstage { "Unstaging" } else { "Staging" })?;
+    ensure_repo()?;
+    let failure = |lines: Option<&Vec<String>>, e: anyhow::Error| HunkFailure {
+        path: file.path.clone(),
+        lines: lines.cloned(),
+        message: format!("{:#}", e),
     };
+    if file.hunks.is_empty() {
+        return Ok(apply_cached(&file.whole_patch(), unstage)
+            .err()
+            .map(|e| failure(None, e))
+            .into_iter()
+            .collect());
+    }
 
-    let mut accumulate_numstat = |args: &[&str]| -> Result<()> {
-        let o = run_git(args)?;
-        if !o.status.success() {
-            bail!(
-                "git {} failed: {}",
-                args.join(" "),
-                String::from_utf8_lossy(&o.stderr)
-            );
-        }
-        let text = String::from_utf8(o.stdout).context("git numstat output was not valid UTF-8")?;
-        for line in text.lines() {
-            // Format: <insertions>\t<deletions>\t<path>
-            // Binary files can show '-' for counts.
-            let mut parts = line.split('\t');
-            let ins = parts.next().unwrap_or("").trim();
-            let del = parts.next().unwrap_or("").trim();
-            let path = parts.next().unwrap_or("").trim();
-
-            if path.is_empty() {
-                continue;
-            }
-            summary.files_changed += 1;
-
-            if let Ok(n) = ins.parse::<usize>() {
-                summary.insertions += n;
-            }
-            if let Ok(n) = del.parse::<usize>() {
-                summary.deletions += n;
+    // Hunks of this file the index has, which move the later ones: none yet when staging,
+    // all of them when unstaging.
+    let mut present: Vec<usize> = if unstage {
+        (0..file.hunks.len()).collect()
+    } else {
+        Vec::new()
+    };
+    let mut mode_applied = false;
+    let mut failed = Vec::new();
+    let mut chosen = chosen.to_vec();
+    chosen.sort_unstable();
+    chosen.dedup();
+    for index in chosen {
+        let patch = file.hunk_patch(index, &present, !mode_applied);
+        match apply_cached(&patch, unstage) {
+            Ok(()) => {
+                mode_applied = true;
+                if unstage {
+                    present.retain(|&i| i != index);
+                } else {
+                    present.push(index);
+                }
             }
+            Err(e) => failed.push(failure(Some(&file.hunks[index].lines), e)),
         }
-        Ok(())
+    }
+    Ok(failed)
+}
+
+/// `git apply --cached [--reverse]` of `patch`, from the repository root: git ignores the
+/// paths outside the directory it runs in.
+#[cfg(feature = "tui")]
+fn apply_cached(patch: &str, reverse: bool) -> Result<()> {
+    let mut child = command()
+        .current_dir(repo_root()?)
+        .args(["apply", "--cached", "--whitespace=nowarn"])
+        .args(reverse.then_some("--reverse"))
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git apply")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(patch.as_bytes())
+            .context("Failed to pass the patch to git apply")?;
+    }
+    let o = child
+        .wait_with_output()
+        .context("Failed to wait for git apply")?;
+    if !o.status.success() {
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        let reason: Vec<&str> = stderr
+            .lines()
+            .map(|l| l.trim().trim_start_matches("error: "))
+            .filter(|l| !l.is_empty())
+            .collect();
+        bail!("{}", reason.join("; "));
+    }
+    Ok(())
+}
+
+/// `git add -A` for just `paths` (relative to the repository root), so deletions and
+/// renames are staged too. Each path is its own argument and matched literally.
+#[cfg(feature = "cli")]
+pub fn stage_files<P: AsRef<Path>>(paths: &[P]) -> Result<()> {
+    read_only::check("Staging")?;
+    ensure_repo()?;
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let o = command()
+        .current_dir(repo_root()?)
+        .args(["--literal-pathspecs", "add", "-A", "--"])
+        .args(paths.iter().map(|p| p.as_ref()))
+        .output()
+        .context("Failed to run git add")?;
+    if !o.status.success() {
+        bail!(
+            "git add failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Unstage `paths` (relative to the repository root), leaving the work tree alone. Each
+/// path is its own argument and matched literally.
+#[cfg(feature = "cli")]
+pub fn unstage_files<P: AsRef<Path>>(paths: &[P]) -> Result<()> {
+    read_only::check("Unstaging")?;
+    ensure_repo()?;
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let root = repo_root()?;
+    let git = |args: &[&str]| {
+        command()
+            .current_dir(&root)
+            .arg("--literal-pathspecs")
+            .args(args)
+            .arg("--")
+            .args(paths.iter().map(|p| p.as_ref()))
+            .output()
+    };
+
+    // Same fallbacks as `unstage_all`. Before the first commit there is nothing to reset
+    // to, so the files leave the index instead.
+    let has_head =
+        run_git(&["rev-parse", "--verify", "-q", "HEAD"]).is_ok_and(|o| o.status.success());
+    let attempts: &[&[&str]] = if has_head {
+        &[&["restore", "--staged"], &["reset", "-q"]]
+    } else {
+        &[&["rm", "--cached", "-r", "-q"]]
+    };
+    let mut error = String::new();
+    for args in attempts {
+        match git(args) {
+            Ok(o) if o.status.success() => return Ok(()),
+            Ok(o) => error = String::from_utf8_lossy(&o.stderr).trim().to_string(),
+            Err(e) => error = e.to_string(),
+        }
+    }
+    bail!("Failed to unstage the selected files: {}", error)
+}
+
+/// Save the index as a tree object and return its ID (`git write-tree`).
+#[cfg(feature = "cli")]
+pub fn write_index_tree() -> Result<String> {
+    ensure_repo()?;
+    let o = run_git(&["write-tree"])?;
+    if !o.status.success() {
+        bail!(
+            "git write-tree failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+/// Replace the index with a tree saved by `write_index_tree`; the work tree is untouched.
+#[cfg(feature = "cli")]
+pub fn read_index_tree(tree: &str) -> Result<()> {
+    read_only::check("Staging")?;
+    let o = run_git(&["read-tree", tree])?;
+    if !o.status.success() {
+        bail!(
+            "git read-tree {} failed: {}",
+            tree,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+pub fn diff_summary(source: DiffSource) -> Result<DiffSummary> {
+    ensure_repo()?;
+
+    let mut summary = match &source {
+        DiffSource::Staged => summarize_diff(&["diff", "--cached"])?,
+        DiffSource::Unstaged => summarize_diff(&["diff"])?,
+        DiffSource::Both => {
+            let (staged, unstaged) = concurrently(
+                || summarize_diff(&["diff", "--cached"]),
+                || summarize_diff(&["diff"]),
+            )?;
+            DiffSummary {
+                files_changed: staged.files_changed + unstaged.files_changed,
+                insertions: staged.insertions + unstaged.insertions,
+                deletions: staged.deletions + unstaged.deletions,
+                binary_files: staged.binary_files + unstaged.binary_files,
+                bytes: staged.bytes + unstaged.bytes,
+                files: [staged.files, unstaged.files].concat(),
+            }
+        }
+        DiffSource::AgainstRef(reference) => summarize_diff(&["diff", &ref_range(reference)?])?,
+    };
+    // numstat leaves out untracked files; count their shown lines.
+    if matches!(source, DiffSource::Unstaged | DiffSource::Both) {
+        let untracked = untracked_diff(&[])?;
+        summary.bytes += untracked.len();
+        for stat in numstat_from_diff(&untracked) {
+            summary.add(&stat);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Summary of `git <args>` (a `git diff` command): counts from `--numstat`, and the size
+/// of the diff text, both at once.
+fn summarize_diff(args: &[&str]) -> Result<DiffSummary> {
+    let numstat_args = [args, &["--numstat", "-z"]].concat();
+    let (bytes, stats) = concurrently(|| diff_bytes(args), || numstat(&numstat_args))?;
+    let mut summary = DiffSummary {
+        bytes,
+        ..DiffSummary::default()
+    };
+    for stat in stats {
+        summary.add(&stat);
+    }
+    Ok(summary)
+}
+
+/// Size of the `git <args>` output, counted as it streams by rather than read into memory.
+fn diff_bytes(args: &[&str]) -> Result<usize> {
+    let mut child = command()
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+    let mut stdout = child.stdout.take().context("git stdout was not captured")?;
+    let bytes = std::io::copy(&mut stdout, &mut std::io::sink())
+        .with_context(|| format!("Failed to read git {} output", args.join(" ")))?;
+    let o = child.wait_with_output()?;
+    if !o.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(bytes as usize)
+}
+
+impl DiffSummary {
+    fn add(&mut self, stat: &FileStat) {
+        self.files_changed += 1;
+        match (stat.insertions, stat.deletions) {
+            (Some(insertions), Some(deletions)) => {
+                self.insertions += insertions;
+                self.deletions += deletions;
+            }
+            _ => self.binary_files += 1,
+        }
+        self.files.push(stat.clone());
+    }
+
+    /// The `limit` files with the most changed lines, most first and ties in path order.
+    /// A file in both the staged and the unstaged diff is listed once with both counts;
+    /// binary files have no lines, so they come last.
+    pub fn largest_files(&self, limit: usize) -> Vec<FileStat> {
+        let mut files: Vec<FileStat> = Vec::new();
+        for stat in &self.files {
+            match files
+                .iter_mut()
+                .find(|f| f.path == stat.path && f.orig_path == stat.orig_path)
+            {
+                Some(file) => {
+                    file.insertions = file.insertions.zip(stat.insertions).map(|(a, b)| a + b);
+                    file.deletions = file.deletions.zip(stat.deletions).map(|(a, b)| a + b);
+                }
+                None => files.push(stat.clone()),
+            }
+        }
+        files.sort_by(|a, b| {
+            b.changed_lines()
+                .cmp(&a.changed_lines())
+                .then_with(|| a.display_path().cmp(&b.display_path()))
+        });
+        files.truncate(limit);
+        files
+    }
+
+    /// `stat`'s share of the changed lines, in percent; none for a binary file.
+    pub fn share(&self, stat: &FileStat) -> Option<usize> {
+        stat.insertions?;
+        let total = (self.insertions + self.deletions).max(1);
+        Some((stat.changed_lines() * 100 + total / 2) / total)
+    }
+}
+
+/// `git <args>` (ending in `--numstat -z`), parsed.
+fn numstat(args: &[&str]) -> Result<Vec<FileStat>> {
+    let o = run_git(args)?;
+    if !o.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(parse_numstat_z(&String::from_utf8_lossy(&o.stdout)))
+}
+
+/// Parse `--numstat -z` output: `<ins>\t<del>\t<path>\0` per file, or for a rename or
+/// copy `<ins>\t<del>\t\0<old path>\0<new path>\0`. Binary files have `-` counts.
+pub fn parse_numstat_z(text: &str) -> Vec<FileStat> {
+    let mut stats = Vec::new();
+    let mut records = text.split('\0');
+    while let Some(record) = records.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(ins), Some(del), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let (path, orig_path) = if path.is_empty() {
+            let (Some(old), Some(new)) = (records.next(), records.next()) else {
+                break;
+            };
+            (new, Some(old.to_string()))
+        } else {
+            (path, None)
+        };
+        stats.push(FileStat {
+            path: path.to_string(),
+            orig_path,
+            insertions: ins.parse().ok(),
+            deletions: del.parse().ok(),
+        });
+    }
+    stats
+}
+
+/// Per-file `--numstat` entry. Counts are `None` for binary files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub path: String,
+    /// Where a renamed or copied file came from.
+    pub orig_path: Option<String>,
+    pub insertions: Option<usize>,
+    pub deletions: Option<usize>,
+}
+
+impl FileStat {
+    /// Insertions plus deletions; none for a binary file.
+    pub fn changed_lines(&self) -> usize {
+        self.insertions.unwrap_or_default() + self.deletions.unwrap_or_default()
+    }
+
+    /// `path`, or `old → new` for a renamed or copied file.
+    pub fn display_path(&self) -> String {
+        match &self.orig_path {
+            Some(orig) => format!("{} → {}", orig, self.path),
+            None => self.path.clone(),
+        }
+    }
+
+    /// `+12 -3`, or `binary`.
+    pub fn counts(&self) -> String {
+        match (self.insertions, self.deletions) {
+            (Some(i), Some(d)) => format!("+{} -{}", i, d),
+            _ => "binary".to_string(),
+        }
+    }
+}
+
+/// Staged files with their insertion/deletion counts.
+pub fn staged_file_stats() -> Result<Vec<FileStat>> {
+    ensure_repo()?;
+    numstat(&["diff", "--cached", "--numstat", "-z"])
+}
+
+/// `--numstat` entries counted from the text of a unified diff, in diff order.
+pub fn numstat_from_diff(diff: &str) -> Vec<FileStat> {
+    let mut stats: Vec<FileStat> = Vec::new();
+    let mut in_hunk = false;
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            stats.push(FileStat {
+                path: rest.rsplit_once(" b/").map_or(rest, |(_, b)| b).to_string(),
+                orig_path: None,
+                insertions: Some(0),
+                deletions: Some(0),
+            });
+            in_hunk = false;
+            continue;
+        }
+        let Some(stat) = stats.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if !in_hunk && line.starts_with("Binary files ") {
+            stat.insertions = None;
+            stat.deletions = None;
+        } else if in_hunk && line.starts_with('+') {
+            stat.insertions = stat.insertions.map(|n| n + 1);
+        } else if in_hunk && line.starts_with('-') {
+            stat.deletions = stat.deletions.map(|n| n + 1);
+        }
+    }
+    stats
+}
+
+/// Size in bytes of the staged (index) version of `path`, if it exists.
+pub fn staged_blob_size(path: &str) -> Option<u64> {
+    let o = run_git(&["cat-file", "-s", &format!(":{}", path)]).ok()?;
+    if !o.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&o.stdout).trim().parse().ok()
+}
+
+/// One `git status --porcelain=v2 --branch` call: branch, upstream and every changed path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoSnapshot {
+    /// `None` when HEAD is detached.
+    pub branch: Option<String>,
+    /// `None` before the first commit.
+    pub oid: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub entries: Vec<StatusEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Ordinary change (`1` records): modified, added, deleted, type change.
+    Changed,
+    /// Rename or copy (`2` records); `orig_path` is set.
+    Renamed,
+    /// Merge conflict (`u` records).
+    Unmerged,
+    Untracked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    pub kind: EntryKind,
+    /// Index status (`M`, `A`, `D`, `R`, `C`, `T`, …); `.` when unchanged.
+    pub index: char,
+    /// Work tree status, same letters; `.` when unchanged.
+    pub worktree: char,
+    pub path: String,
+    pub orig_path: Option<String>,
+    /// Object name of the index version (empty for untracked and unmerged entries).
+    pub index_oid: String,
+}
+
+impl StatusEntry {
+    pub fn is_staged(&self) -> bool {
+        matches!(self.kind, EntryKind::Changed | EntryKind::Renamed) && self.index != '.'
+    }
+
+    pub fn is_unstaged(&self) -> bool {
+        matches!(self.kind, EntryKind::Changed | EntryKind::Renamed) && self.worktree != '.'
+    }
+
+    pub fn is_untracked(&self) -> bool {
+        self.kind == EntryKind::Untracked
+    }
+
+    #[cfg(feature = "cli")]
+    /// What `source` shows of this path (`modified`, `added`, `renamed`, …), if anything.
+    /// Untracked files count as added; with `Both` the staged change wins. The work tree
+    /// says nothing about `AgainstRef`.
+    pub fn change(&self, source: &DiffSource) -> Option<&'static str> {
+        if self.kind == EntryKind::Unmerged {
+            return Some("conflicted");
+        }
+        if self.is_untracked() {
+            return matches!(source, DiffSource::Unstaged | DiffSource::Both).then_some("added");
+        }
+        let status = match source {
+            DiffSource::Staged => self.index,
+            DiffSource::Unstaged => self.worktree,
+            DiffSource::Both if self.index != '.' => self.index,
+            DiffSource::Both => self.worktree,
+            DiffSource::AgainstRef(_) => return None,
+        };
+        match status {
+            'M' => Some("modified"),
+            'A' => Some("added"),
+            'D' => Some("deleted"),
+            'R' => Some("renamed"),
+            'C' => Some("copied"),
+            'T' => Some("type changed"),
+            _ => None,
+        }
+    }
+}
+
+/// e.g. `3 modified, 1 added, 1 renamed`: what `source` changes, by kind. Empty when
+/// nothing matches.
+#[cfg(feature = "cli")]
+pub fn change_breakdown(entries: &[StatusEntry], source: &DiffSource) -> String {
+    const ORDER: &[&str] = &[
+        "modified",
+        "added",
+        "deleted",
+        "renamed",
+        "copied",
+        "type changed",
+        "conflicted",
+    ];
+    let changes: Vec<&str> = entries.iter().filter_map(|e| e.change(source)).collect();
+    ORDER
+        .iter()
+        .map(|kind| (changes.iter().filter(|c| *c == kind).count(), kind))
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, kind)| format!("{} {}", count, kind))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl RepoSnapshot {
+    pub fn staged(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.entries.iter().filter(|e| e.is_staged())
+    }
+
+    pub fn unstaged(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.entries.iter().filter(|e| e.is_unstaged())
+    }
+
+    pub fn untracked(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.entries.iter().filter(|e| e.is_untracked())
+    }
+
+    pub fn unmerged(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.kind == EntryKind::Unmerged)
+    }
+
+    /// No staged, unstaged, untracked or conflicted paths.
+    pub fn is_clean(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[cfg(feature = "tui")]
+    /// Changes whenever the staged content changes (paths and index object names).
+    pub fn staged_fingerprint(&self) -> u64 {
+        let mut text = String::new();
+        for e in self.staged() {
+            text.push_str(&e.path);
+            text.push('\0');
+            text.push_str(&e.index_oid);
+            text.push('\0');
+        }
+        crate::hash::fnv1a(text.as_bytes())
+    }
+}
+
+/// `master ↑1 ↓2 · 3 staged · 1 unstaged`: branch (or `detached @ abc1234`), ahead/behind
+/// and non-zero counts.
+impl std::fmt::Display for RepoSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.branch, &self.oid) {
+            (Some(branch), _) => write!(f, "{}", branch)?,
+            (None, Some(oid)) => write!(f, "detached @ {}", &oid[..oid.len().min(7)])?,
+            (None, None) => write!(f, "(detached)")?,
+        }
+        if self.upstream.is_some() && (self.ahead > 0 || self.behind > 0) {
+            write!(f, " ↑{} ↓{}", self.ahead, self.behind)?;
+        }
+        for (count, label) in [
+            (self.staged().count(), "staged"),
+            (self.unstaged().count(), "unstaged"),
+            (self.untracked().count(), "untracked"),
+            (self.unmerged().count(), "conflicted"),
+        ] {
+            if count > 0 {
+                write!(f, " · {} {}", count, label)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `git status --porcelain=v2 --branch -z` once and parse it.
+pub fn snapshot() -> Result<RepoSnapshot> {
+    ensure_repo()?;
+    let o = run_git(&["status", "--porcelain=v2", "--branch", "-z"])?;
+    if !o.status.success() {
+        bail!("git status failed: {}", String::from_utf8_lossy(&o.stderr));
+    }
+    Ok(parse_porcelain_v2(&String::from_utf8_lossy(&o.stdout)))
+}
+
+/// Every changed path from `git status --porcelain=v2 -z`, untracked files included.
+#[cfg(feature = "cli")]
+pub fn status_entries() -> Result<Vec<StatusEntry>> {
+    ensure_repo()?;
+    let o = run_git(&["status", "--porcelain=v2", "-z"])?;
+    if !o.status.success() {
+        bail!("git status failed: {}", String::from_utf8_lossy(&o.stderr));
+    }
+    Ok(parse_porcelain_v2(&String::from_utf8_lossy(&o.stdout)).entries)
+}
+
+/// A multi-step git command that stopped to let the user step in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+}
+
+impl Operation {
+    /// e.g. `git rebase`.
+    pub fn command(self) -> &'static str {
+        match self {
+            Operation::Merge => "git merge",
+            Operation::Rebase => "git rebase",
+            Operation::CherryPick => "git cherry-pick",
+            Operation::Revert => "git revert",
+        }
+    }
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operation::Merge => "merge",
+            Operation::Rebase => "rebase",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+        })
+    }
+}
+
+/// What stands between the work tree and an ordinary commit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoState {
+    pub operation: Option<Operation>,
+    /// Paths (repository-relative) with unresolved conflicts.
+    pub conflicted: Vec<String>,
+}
+
+impl RepoState {
+    /// e.g. ``a rebase is in progress (finish it with `git rebase --continue` or undo it
+    /// with `git rebase --abort`); 2 files have conflicts: a.rs, b.rs``
+    pub fn describe(&self) -> Option<String> {
+        let mut problems = Vec::new();
+        if let Some(operation) = self.operation {
+            problems.push(format!(
+                "a {} is in progress (finish it with `{} --continue` or undo it with `{} \
+                 --abort`)",
+                operation,
+                operation.command(),
+                operation.command()
+            ));
+        }
+        if !self.conflicted.is_empty() {
+            problems.push(format!(
+                "{} {} conflicts: {}",
+                self.conflicted.len(),
+                if self.conflicted.len() == 1 {
+                    "file has"
+                } else {
+                    "files have"
+                },
+                self.conflicted.join(", ")
+            ));
+        }
+        (!problems.is_empty()).then(|| problems.join("; "))
+    }
+}
+
+/// The operation in progress, from the markers git leaves in the git directory
+/// (`rebase-merge`/`rebase-apply`, `MERGE_HEAD`, `CHERRY_PICK_HEAD`, `REVERT_HEAD`), and
+/// the conflicted paths from `git status`.
+pub fn repo_state() -> Result<RepoState> {
+    // A rebase that stopped on a conflict can leave the other markers too.
+    const MARKERS: [(&str, Operation); 5] = [
+        ("rebase-merge", Operation::Rebase),
+        ("rebase-apply", Operation::Rebase),
+        ("MERGE_HEAD", Operation::Merge),
+        ("CHERRY_PICK_HEAD", Operation::CherryPick),
+        ("REVERT_HEAD", Operation::Revert),
+    ];
+    let conflicted = snapshot()?.unmerged().map(|e| e.path.clone()).collect();
+
+    let mut args = vec!["rev-parse"];
+    for (name, _) in MARKERS {
+        args.extend(["--git-path", name]);
+    }
+    let o = run_git(&args)?;
+    if !o.status.success() {
+        bail!(
+            "git rev-parse --git-path failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&o.stdout);
+    let operation = MARKERS
+        .iter()
+        .zip(stdout.lines())
+        .find(|(_, path)| in_git_dir(path).exists())
+        .map(|((_, operation), _)| *operation);
+
+    Ok(RepoState {
+        operation,
+        conflicted,
+    })
+}
+
+/// Parse NUL-separated porcelain v2 output (`--branch` headers optional). Unknown records
+/// are skipped.
+pub fn parse_porcelain_v2(text: &str) -> RepoSnapshot {
+    let mut snap = RepoSnapshot::default();
+    let mut records = text.split('\0').filter(|r| !r.is_empty());
+
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => snap.oid = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => snap.branch = Some(value.to_string()),
+                "branch.upstream" => snap.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for part in value.split_whitespace() {
+                        if let Some(n) = part.strip_prefix('+') {
+                            snap.ahead = n.parse().unwrap_or(0);
+                        } else if let Some(n) = part.strip_prefix('-') {
+                            snap.behind = n.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        // Field counts before the path; paths may contain spaces.
+        let (kind, fields) = match record.as_bytes().first() {
+            Some(b'1') => (EntryKind::Changed, 8),
+            // Renames are followed by a separate record holding the original path.
+            Some(b'2') => (EntryKind::Renamed, 9),
+            Some(b'u') => (EntryKind::Unmerged, 10),
+            Some(b'?') => (EntryKind::Untracked, 1),
+            _ => continue,
+        };
+        let parts: Vec<&str> = record.splitn(fields + 1, ' ').collect();
+        let Some(path) = parts.get(fields) else {
+            continue;
+        };
+        let mut xy = parts
+            .get(1)
+            .filter(|_| kind != EntryKind::Untracked)
+            .map(|xy| xy.chars())
+            .into_iter()
+            .flatten();
+        let index = xy.next().unwrap_or('?');
+        let worktree = xy.next().unwrap_or('?');
+        let index_oid = match kind {
+            EntryKind::Changed | EntryKind::Renamed => parts.get(7).copied().unwrap_or_default(),
+            _ => "",
+        };
+        let orig_path = if kind == EntryKind::Renamed {
+            records.next().map(str::to_string)
+        } else {
+            None
+        };
+
+        snap.entries.push(StatusEntry {
+            kind,
+            index,
+            worktree,
+            path: path.to_string(),
+            orig_path,
+            index_oid: index_oid.to_string(),
+        });
+    }
+
+    snap
+}
+
+/// The current branch's upstream (e.g. `origin/master`), or `None` if none is configured.
+///
+/// Unlike a plain success check, unexpected git failures are reported as errors
+/// instead of being mistaken for "no upstream".
+pub fn upstream() -> Result<Option<String>> {
+    ensure_repo()?;
+    let o = run_git(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])?;
+    if o.status.success() {
+        return Ok(Some(String::from_utf8_lossy(&o.stdout).trim().to_string()));
+    }
+
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    if stderr.contains("no upstream configured") || stderr.contains("does not point to a branch") {
+        return Ok(None);
+    }
+    bail!("Failed to resolve upstream: {}", stderr.trim());
+}
+
+/// How `push_branch_with_policy` pushed the branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchPush {
+    /// Plain `git push` to the existing upstream.
+    Existing { upstream: String },
+    /// `git push -u origin <branch>`: the upstream was created.
+    CreatedUpstream { branch: String },
+    /// `origin/<branch>` already existed; the branch was rebased onto it, then pushed.
+    Rebased { branch: String },
+    /// `origin/<branch>` already existed and was overwritten with `--force-with-lease`.
+    Overwrote { branch: String },
+}
+
+impl std::fmt::Display for BranchPush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BranchPush::Existing { upstream } => write!(f, "Pushed to {}.", upstream),
+            BranchPush::CreatedUpstream { branch } => write!(
+                f,
+                "Pushed and set upstream to origin/{} (git push -u origin {}).",
+                branch, branch
+            ),
+            BranchPush::Rebased { branch } => write!(
+                f,
+                "Rebased onto origin/{}, pushed and set it as upstream.",
+                branch
+            ),
+            BranchPush::Overwrote { branch } => write!(
+                f,
+                "Force-pushed over origin/{} (--force-with-lease) and set it as upstream.",
+                branch
+            ),
+        }
+    }
+}
+
+/// How the current branch compares with its upstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AheadBehind {
+    pub upstream: String,
+    /// Commits on the branch that the upstream doesn't have.
+    pub ahead: usize,
+    /// Commits on the upstream that the branch doesn't have: a push would be rejected.
+    pub behind: usize,
+}
+
+/// `2 ahead, 1 behind origin/master`, or `up to date with origin/master`.
+impl std::fmt::Display for AheadBehind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ahead == 0 && self.behind == 0 {
+            write!(f, "up to date with {}", self.upstream)
+        } else {
+            write!(
+                f,
+                "{} ahead, {} behind {}",
+                self.ahead, self.behind, self.upstream
+            )
+        }
+    }
+}
+
+impl RepoSnapshot {
+    /// `None` without an upstream.
+    pub fn ahead_behind(&self) -> Option<AheadBehind> {
+        Some(AheadBehind {
+            upstream: self.upstream.clone()?,
+            ahead: self.ahead,
+            behind: self.behind,
+        })
+    }
+}
+
+/// The current branch against its upstream as of the last fetch (`git status --branch`
+/// counts the same commits as `git rev-list --left-right --count @{u}...HEAD`).
+pub fn ahead_behind() -> Result<Option<AheadBehind>> {
+    Ok(snapshot()?.ahead_behind())
+}
+
+/// `git fetch` from the current branch's remote (`origin` without an upstream), so
+/// `ahead_behind` is current. Needs the network.
+pub fn fetch() -> Result<()> {
+    read_only::check("Fetching")?;
+    ensure_repo()?;
+    let o = run_git(&["fetch", "--quiet"])?;
+    if !o.status.success() {
+        bail!(
+            "git fetch failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// What `pull_ff_only` did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullResult {
+    UpToDate {
+        upstream: String,
+    },
+    FastForwarded {
+        upstream: String,
+        commits: usize,
+        /// `abc1234..def5678`
+        range: String,
+    },
+}
+
+impl std::fmt::Display for PullResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PullResult::UpToDate { upstream } => {
+                write!(f, "Already up to date with {}.", upstream)
+            }
+            PullResult::FastForwarded {
+                upstream,
+                commits,
+                range,
+            } => write!(
+                f,
+                "Pulled {} commit(s) from {} ({}).",
+                commits, upstream, range
+            ),
+        }
+    }
+}
+
+/// Returned by `pull_ff_only` when the branch and its upstream both have commits the
+/// other lacks. Nothing was merged; `divergence` lists the commits.
+#[derive(Debug, Clone)]
+pub struct NotFastForward {
+    pub upstream: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl std::fmt::Display for NotFastForward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Can't fast-forward: this branch has {} commit(s) {} doesn't have, and it has {} \
+             this branch doesn't. Nothing was merged; rebase onto {} (or merge it) to \
+             combine them.",
+            self.ahead, self.upstream, self.behind, self.upstream
+        )
+    }
+}
+
+impl std::error::Error for NotFastForward {}
+
+/// `git pull --ff-only` into the current branch: take the upstream's new commits, never
+/// a merge commit. A diverged branch fails with `NotFastForward`.
+pub fn pull_ff_only() -> Result<PullResult> {
+    read_only::check("Pulling")?;
+    if let Some(commit) = detached_head()? {
+        bail!(
+            "HEAD is detached at {}: there is no branch to pull into.",
+            commit
+        );
+    }
+    let Some(upstream) = upstream()? else {
+        bail!("'{}' has no upstream to pull from.", current_branch()?);
+    };
+    let before = head_short_hash().ok();
+    let o = run_git(&["pull", "--ff-only", "--quiet"])?;
+    if !o.status.success() {
+        // `git pull` fetched first, so the counts are current.
+        if let Some(tracking) = ahead_behind()?.filter(|t| t.ahead > 0 && t.behind > 0) {
+            return Err(NotFastForward {
+                upstream: tracking.upstream,
+                ahead: tracking.ahead,
+                behind: tracking.behind,
+            }
+            .into());
+        }
+        bail!(
+            "git pull --ff-only failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+
+    let after = head_short_hash()?;
+    let Some(before) = before.filter(|before| *before != after) else {
+        return Ok(PullResult::UpToDate { upstream });
+    };
+    let range = format!("{}..{}", before, after);
+    let count = run_git(&["rev-list", "--count", &range])?;
+    Ok(PullResult::FastForwarded {
+        upstream,
+        commits: String::from_utf8_lossy(&count.stdout)
+            .trim()
+            .parse()
+            .unwrap_or_default(),
+        range,
+    })
+}
+
+/// `git log --left-right` between HEAD and its upstream: `<` marks commits only on the
+/// branch, `>` commits only on the upstream.
+pub fn divergence() -> Result<String> {
+    ensure_repo()?;
+    let o = run_git(&["log", "--oneline", "--left-right", "HEAD...@{u}"])?;
+    if !o.status.success() {
+        bail!(
+            "git log HEAD...@{{u}} failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout).trim_end().to_string())
+}
+
+/// The branch `force_push_branch` would overwrite and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForcePushTarget {
+    pub branch: String,
+    /// e.g. `origin/main`.
+    pub upstream: String,
+    /// The upstream's remote and branch name on it (`branch.<name>.remote` / `.merge`).
+    pub remote: String,
+    pub remote_branch: String,
+    /// Where the remote-tracking branch is: the lease.
+    pub expect: String,
+}
+
+/// Returned by `force_push_branch` when the lease failed: the remote branch moved since
+/// the last fetch, so forcing would have dropped commits nobody here has seen.
+#[derive(Debug, Clone)]
+pub struct LeaseRejected {
+    pub upstream: String,
+}
+
+impl std::fmt::Display for LeaseRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Not pushed: {} changed since your last fetch (someone else pushed to it), so \
+             --force-with-lease refused to overwrite it. Fetch, look at the new commits and \
+             rebase onto them, then force-push again.",
+            self.upstream
+        )
+    }
+}
+
+impl std::error::Error for LeaseRejected {}
+
+/// What a force push would overwrite. Fails on a detached HEAD, without an upstream, or
+/// when the branch or the upstream branch it pushes to is in `protected`.
+pub fn force_push_target(protected: &[String]) -> Result<ForcePushTarget> {
+    if let Some(commit) = detached_head()? {
+        return Err(DetachedHead { commit }.into());
+    }
+    let branch = current_branch()?;
+    let Some(upstream) = upstream()? else {
+        bail!(
+            "'{}' has no upstream, so there is nothing to overwrite: use Push branch.",
+            branch
+        );
+    };
+    let config = |key: &str| -> Result<String> {
+        let key = format!("branch.{}.{}", branch, key);
+        let o = run_git(&["config", "--get", &key])?;
+        if !o.status.success() {
+            bail!(
+                "'{}' has no `{}` set, so it can't be force-pushed.",
+                branch,
+                key
+            );
+        }
+        Ok(String::from_utf8_lossy(&o.stdout).trim().to_string())
+    };
+    let remote = config("remote")?;
+    let merge = config("merge")?;
+    let Some(remote_branch) = merge.strip_prefix("refs/heads/").map(str::to_string) else {
+        bail!(
+            "'{}' tracks {}, which is not a branch; refusing to force-push.",
+            branch,
+            merge
+        );
+    };
+    for name in [&branch, &remote_branch] {
+        if protected.iter().any(|p| p.trim() == name) {
+            bail!(
+                "Refusing to force-push to '{}': it is a protected branch \
+                 (`protected_branches` in the config).",
+                name
+            );
+        }
+    }
+    let o = run_git(&["rev-parse", "--verify", "-q", "@{u}"])?;
+    if !o.status.success() {
+        bail!(
+            "{} has not been fetched yet, so there is no lease to check: fetch first.",
+            upstream
+        );
+    }
+    let expect = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    Ok(ForcePushTarget {
+        branch,
+        upstream,
+        remote,
+        remote_branch,
+        expect,
+    })
+}
+
+/// `git push --force-with-lease` of this branch only, to its upstream branch (never
+/// `--force`), once `force_push_target` allows it. The lease is the remote-tracking
+/// branch, so a remote that moved since the last fetch fails with `LeaseRejected`.
+pub fn force_push_branch(protected: &[String]) -> Result<ForcePushTarget> {
+    read_only::check("Pushing")?;
+    let target = force_push_target(protected)?;
+    let lease = format!(
+        "--force-with-lease=refs/heads/{}:{}",
+        target.remote_branch, target.expect
+    );
+    let refspec = format!(
+        "refs/heads/{}:refs/heads/{}",
+        target.branch, target.remote_branch
+    );
+    let o = run_git(&["push", &lease, &target.remote, &refspec])?;
+    if !o.status.success() {
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        if stderr.contains("stale info") {
+            return Err(LeaseRejected {
+                upstream: target.upstream,
+            }
+            .into());
+        }
+        bail!("git push --force-with-lease failed: {}", stderr.trim());
+    }
+    Ok(target)
+}
+
+/// Where `push_branch_with_policy` would push: the upstream, or `origin/<branch>` when
+/// there is none yet.
+pub fn push_destination() -> Result<String> {
+    match upstream()? {
+        Some(upstream) => Ok(upstream),
+        None => Ok(format!("origin/{}", current_branch()?)),
+    }
+}
+
+/// What `push_branch_with_policy` does when the branch has no upstream yet and origin
+/// already has a branch of the same name that the push wouldn't fast-forward.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushPolicy {
+    /// Don't push: fail with `RemoteBranchDiverged` so the user can pick.
+    FastForwardOnly,
+    /// `git rebase --autostash origin/<branch>`, then push.
+    Rebase,
+    /// `git push -u --force-with-lease`, expecting origin's branch at `expect` (the
+    /// `remote_commit` the user was shown), so commits pushed since are never dropped.
+    ForceWithLease { expect: String },
+}
+
+/// Returned by `push_branch_with_policy(PushPolicy::FastForwardOnly)` when the branch
+/// has no upstream but `origin/<branch>` exists with commits it doesn't have. Nothing
+/// was pushed; the remote branch was fetched, so `git log` can show both sides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteBranchDiverged {
+    pub branch: String,
+    /// Where origin's branch was, for `PushPolicy::ForceWithLease`.
+    pub remote_commit: String,
+    /// Commits on the local branch that origin's doesn't have.
+    pub ahead: usize,
+    /// Commits on origin's branch that the local one doesn't have.
+    pub behind: usize,
+}
+
+impl std::fmt::Display for RemoteBranchDiverged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "origin already has a branch '{}' with {} commit(s) this branch doesn't have",
+            self.branch, self.behind
+        )?;
+        if self.ahead > 0 {
+            write!(f, " (and this branch has {} it doesn't)", self.ahead)?;
+        }
+        write!(
+            f,
+            ", so pushing would be rejected. Nothing was pushed: rebase onto origin/{} \
+             first, overwrite it with --force-with-lease, or push under another name.",
+            self.branch
+        )
+    }
+}
+
+impl std::error::Error for RemoteBranchDiverged {}
+
+/// Push the current branch. With an upstream that is a plain `git push`. Without one it
+/// sets `origin/<branch>` as upstream, after checking `git ls-remote` for a branch of the
+/// same name on origin: one the push fast-forwards is simply taken over, any other is
+/// fetched and handled by `policy`.
+pub fn push_branch_with_policy(policy: PushPolicy) -> Result<BranchPush> {
+    read_only::check("Pushing")?;
+    if let Some(commit) = detached_head()? {
+        return Err(DetachedHead { commit }.into());
+    }
+
+    // If upstream exists, `git push` is enough. Otherwise set upstream.
+    if let Some(upstream) = upstream()? {
+        let o = run_git(&["push"])?;
+        if !o.status.success() {
+            bail!("git push failed: {}", String::from_utf8_lossy(&o.stderr));
+        }
+        return Ok(BranchPush::Existing { upstream });
+    }
+
+    let branch = current_branch()?;
+    let mut pushed = BranchPush::CreatedUpstream {
+        branch: branch.clone(),
     };
+    let mut lease = None;
+    if let Some(diverged) = remote_branch_divergence(&branch)? {
+        match policy {
+            PushPolicy::FastForwardOnly => return Err(diverged.into()),
+            PushPolicy::Rebase => {
+                rebase_onto(&format!("origin/{}", branch))?;
+                pushed = BranchPush::Rebased {
+                    branch: branch.clone(),
+                };
+            }
+            PushPolicy::ForceWithLease { expect } => {
+                lease = Some(format!(
+                    "--force-with-lease=refs/heads/{}:{}",
+                    branch, expect
+                ));
+                pushed = BranchPush::Overwrote {
+                    branch: branch.clone(),
+                };
+            }
+        }
+    }
+
+    let mut args = vec!["push", "-u"];
+    args.extend(lease.as_deref());
+    args.extend(["origin", branch.as_str()]);
+    let o = run_git(&args)?;
+    if !o.status.success() {
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        if lease.is_some() && stderr.contains("stale info") {
+            return Err(LeaseRejected {
+                upstream: format!("origin/{}", branch),
+            }
+            .into());
+        }
+        bail!("git {} failed: {}", args.join(" "), stderr);
+    }
+    Ok(pushed)
+}
+
+/// `origin/<branch>` when origin has that branch and pushing HEAD to it wouldn't be a
+/// fast-forward. The branch is fetched first, so the counts are current.
+fn remote_branch_divergence(branch: &str) -> Result<Option<RemoteBranchDiverged>> {
+    let head = format!("refs/heads/{}", branch);
+    let o = run_git(&["ls-remote", "--heads", "origin", &head])?;
+    if !o.status.success() {
+        bail!(
+            "git ls-remote origin {} failed: {}",
+            head,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let listing = String::from_utf8_lossy(&o.stdout);
+    let Some(remote_commit) = listing
+        .lines()
+        .filter_map(|l| l.split_once('\t'))
+        .find(|(_, name)| *name == head)
+        .map(|(hash, _)| hash.to_string())
+    else {
+        return Ok(None);
+    };
+
+    let tracking = format!("refs/remotes/origin/{}", branch);
+    let o = run_git(&[
+        "fetch",
+        "--quiet",
+        "origin",
+        &format!("+{}:{}", head, tracking),
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git fetch origin {} failed: {}",
+            branch,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let o = run_git(&[
+        "rev-list",
+        "--left-right",
+        "--count",
+        &format!("{}...HEAD", remote_commit),
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git rev-list {}...HEAD failed: {}",
+            remote_commit,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let counts = String::from_utf8_lossy(&o.stdout);
+    let mut counts = counts
+        .split_whitespace()
+        .map(|n| n.parse::<usize>().unwrap_or_default());
+    let (behind, ahead) = (
+        counts.next().unwrap_or_default(),
+        counts.next().unwrap_or_default(),
+    );
+    if behind == 0 {
+        return Ok(None);
+    }
+    Ok(Some(RemoteBranchDiverged {
+        branch: branch.to_string(),
+        remote_commit,
+        ahead,
+        behind,
+    }))
+}
+
+/// `git rebase --autostash <onto>`. Conflicts leave the rebase in progress for the user.
+fn rebase_onto(onto: &str) -> Result<()> {
+    let o = run_git(&["rebase", "--autostash", onto])?;
+    if o.status.success() {
+        return Ok(());
+    }
+    let conflicted = repo_state().map(|s| s.conflicted).unwrap_or_default();
+    if !conflicted.is_empty() {
+        bail!(
+            "Rebasing onto {} stopped on conflicts in {}. Nothing was pushed: resolve them, \
+             `git add` the files and run `git rebase --continue`, then push again (or undo \
+             it with `git rebase --abort`).",
+            onto,
+            conflicted.join(", ")
+        );
+    }
+    bail!(
+        "git rebase {} failed: {}",
+        onto,
+        String::from_utf8_lossy(&o.stderr).trim()
+    );
+}
+
+/// Push a single tag to `origin`.
+pub fn push_tag(tag: &str) -> Result<()> {
+    read_only::check("Pushing tags")?;
+    ensure_repo()?;
+    let t = tag.trim();
+    validate_ref_name(RefKind::Tag, t)?;
+
+    let o = run_git(&["push", "origin", t])?;
+    if !o.status.success() {
+        bail!(
+            "git push origin {} failed: {}",
+            t,
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// A local tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    pub name: String,
+    /// `YYYY-MM-DD`: when an annotated tag was made, the commit date otherwise.
+    pub date: String,
+    /// First line of the annotation; `None` for a lightweight tag.
+    pub subject: Option<String>,
+    /// Whether the remote has it; `None` when it wasn't asked or couldn't be reached.
+    pub on_remote: Option<bool>,
+}
+
+/// `v*` tags trigger the release workflow: pushing or deleting one on the remote is not
+/// to be done lightly.
+pub fn is_release_tag(name: &str) -> bool {
+    name.starts_with('v')
+}
+
+/// Local tags, newest first. With `remote`, each says whether the remote has it too
+/// (`git ls-remote`, so this needs the network).
+pub fn tags(remote: Option<&str>) -> Result<Vec<TagInfo>> {
+    ensure_repo()?;
+    let o = run_git(&[
+        "for-each-ref",
+        "--sort=-creatordate",
+        "--format=%(refname:short)%00%(creatordate:short)%00%(objecttype)%00%(contents:subject)",
+        "refs/tags",
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git for-each-ref refs/tags failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let remote_tags = remote.and_then(|remote| remote_tag_names(remote).ok());
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?.to_string();
+            let date = fields.next()?.to_string();
+            let annotated = fields.next()? == "tag";
+            let subject = fields.next().filter(|_| annotated).map(str::to_string);
+            Some(TagInfo {
+                on_remote: remote_tags.as_ref().map(|tags| tags.contains(&name)),
+                name,
+                date,
+                subject,
+            })
+        })
+        .collect())
+}
+
+/// Tag names on `remote`.
+fn remote_tag_names(remote: &str) -> Result<BTreeSet<String>> {
+    let o = run_git(&["ls-remote", "--tags", "--refs", remote])?;
+    if !o.status.success() {
+        bail!(
+            "git ls-remote --tags {} failed: {}",
+            remote,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("refs/tags/"))
+        .map(|(_, name)| name.trim().to_string())
+        .collect())
+}
+
+/// `git show --no-patch <name>`: the annotation, if any, and the commit it points to.
+pub fn show_tag(name: &str) -> Result<String> {
+    ensure_repo()?;
+    let o = run_git(&[
+        "show",
+        "--no-patch",
+        "--no-color",
+        &format!("refs/tags/{}", name),
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git show {} failed: {}",
+            name,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout).trim_end().to_string())
+}
+
+/// Delete the local tag `name` and, with `also_remote`, the one on `origin`. The remote
+/// goes first, so when that fails the local tag is still there.
+pub fn delete_tag(name: &str, also_remote: bool) -> Result<()> {
+    read_only::check("Deleting tags")?;
+    ensure_repo()?;
+    let name = name.trim();
+    validate_ref_name(RefKind::Tag, name)?;
+    let tag_ref = format!("refs/tags/{}", name);
+    if also_remote {
+        let o = run_git(&["push", "origin", "--delete", &tag_ref])?;
+        if !o.status.success() {
+            bail!(
+                "git push origin --delete {} failed: {}",
+                tag_ref,
+                String::from_utf8_lossy(&o.stderr).trim()
+            );
+        }
+    }
+    let o = run_git(&["tag", "--delete", name])?;
+    if !o.status.success() {
+        bail!(
+            "git tag --delete {} failed: {}",
+            name,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Push all local tags to the default remote.
+pub fn push_all_tags() -> Result<()> {
+    read_only::check("Pushing tags")?;
+    ensure_repo()?;
+    let o = run_git(&["push", "--tags"])?;
+    if !o.status.success() {
+        bail!(
+            "git push --tags failed: {}",
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Flags every git-wiz commit is made with: `commit` in the config, overridden per
+/// repository by `.gitwiz.toml` (see `repo_config::commit_options`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitOptions {
+    /// `-s`: add a `Signed-off-by:` trailer (DCO).
+    pub signoff: bool,
+    /// `-S`: GPG-sign the commit with `user.signingkey`.
+    pub gpg_sign: bool,
+    /// `--no-verify`: skip the pre-commit and commit-msg hooks.
+    pub no_verify: bool,
+}
+
+impl CommitOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn args(&self) -> Vec<&'static str> {
+        [
+            (self.signoff, "-s"),
+            (self.gpg_sign, "-S"),
+            (self.no_verify, "--no-verify"),
+        ]
+        .into_iter()
+        .filter_map(|(on, arg)| on.then_some(arg))
+        .collect()
+    }
+}
+
+/// Commit the staged changes. Each line git and its hooks print goes to `on_output` as it
+/// comes, so a slow hook shows what it is doing.
+pub fn commit_changes(
+    message: &str,
+    options: CommitOptions,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    run_commit(&[], message, options, on_output)
+}
+
+/// One entry of `git stash list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    /// `stash@{N}`
+    pub name: String,
+    /// e.g. `WIP on master: 1a2b3c4 subject`
+    pub subject: String,
+}
+
+#[cfg(feature = "cli")]
+pub fn stash_list() -> Result<Vec<StashEntry>> {
+    ensure_repo()?;
+    let o = run_git(&["stash", "list", "--format=%gd%x00%gs"])?;
+    if !o.status.success() {
+        bail!(
+            "git stash list failed: {}",
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, subject) = line.split_once('\0')?;
+            Some(StashEntry {
+                name: name.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(feature = "cli")]
+/// Patch for a stash entry, including its untracked files when git supports it (2.32+).
+pub fn stash_diff(name: &str) -> Result<String> {
+    ensure_repo()?;
+    let mut o = run_git(&["stash", "show", "-p", "--include-untracked", name])?;
+    if !o.status.success() {
+        // Older git: no --include-untracked for `stash show`.
+        o = run_git(&["stash", "show", "-p", name])?;
+    }
+    if !o.status.success() {
+        bail!(
+            "git stash show {} failed: {}",
+            name,
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(diff_text(o.stdout))
+}
+
+/// `git stash push --include-untracked`, leaving a clean tree. `None` when there was
+/// nothing to stash.
+pub fn stash_push(message: Option<&str>) -> Result<Option<StashEntry>> {
+    read_only::check("Stashing")?;
+    ensure_repo()?;
+    let before = stash_top()?;
+    let mut args = vec!["stash", "push", "--include-untracked"];
+    if let Some(message) = message.map(str::trim).filter(|m| !m.is_empty()) {
+        args.extend(["--message", message]);
+    }
+    let o = run_git(&args)?;
+    if !o.status.success() {
+        bail!(
+            "git stash push failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    // "No local changes to save" still exits 0; only a new entry means something was saved.
+    let after = stash_top()?;
+    if after.is_none() || after == before {
+        return Ok(None);
+    }
+    let o = run_git(&["stash", "list", "-1", "--format=%gs"])?;
+    Ok(Some(StashEntry {
+        name: "stash@{0}".to_string(),
+        subject: String::from_utf8_lossy(&o.stdout).trim().to_string(),
+    }))
+}
+
+/// Commit of `stash@{0}`, if there is a stash.
+fn stash_top() -> Result<Option<String>> {
+    let o = run_git(&["rev-parse", "--verify", "--quiet", "refs/stash"])?;
+    Ok(o.status
+        .success()
+        .then(|| String::from_utf8_lossy(&o.stdout).trim().to_string()))
+}
+
+/// `git stash pop <name>`. On conflicts git keeps the stash entry and leaves markers.
+pub fn stash_pop(name: &str) -> Result<()> {
+    read_only::check("Popping a stash")?;
+    ensure_repo()?;
+    let o = run_git(&["stash", "pop", name])?;
+    if !o.status.success() {
+        let stdout = String::from_utf8_lossy(&o.stdout);
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        bail!(
+            "git stash pop {} failed: {}",
+            name,
+            format!("{}\n{}", stdout.trim(), stderr.trim()).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+/// Tag names, as listed by `git tag --list`.
+pub fn tag_names() -> Result<Vec<String>> {
+    output_lines(&["tag", "--list"])
+}
+
+#[cfg(feature = "cli")]
+/// Non-empty stdout lines of a git command that must succeed inside a repository.
+fn output_lines(args: &[&str]) -> Result<Vec<String>> {
+    ensure_repo()?;
+    let o = run_git(args)?;
+    if !o.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// What HEAD holds once amended with the staged changes: the index against HEAD's parent,
+/// without the files matching `excludes` unless that would leave nothing. Fails when the
+/// two add up to no change. `context` is as in `get_diff_filtered`.
+pub fn get_diff_amend(excludes: &[String], context: DiffContext) -> Result<String> {
+    ensure_repo()?;
+    let base = amend_base()?;
+    let mut args = vec!["diff".to_string(), "--cached".to_string(), base];
+    args.extend(context.args());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut pathspecs = vec![":/".to_string()];
+    pathspecs.extend(excludes.iter().map(|g| format!(":(top,exclude,glob){}", g)));
+    let mut diff = diff_pathspecs(&args, &pathspecs)?;
+    if diff.trim().is_empty() {
+        diff = diff_pathspecs(&args, &[])?;
+    }
+    if diff.trim().is_empty() {
+        bail!(
+            "Nothing to amend with: the last commit plus the staged changes add up to no change."
+        );
+    }
+    Ok(diff)
+}
+
+/// `diff_summary` for `get_diff_amend`.
+pub fn amend_diff_summary() -> Result<DiffSummary> {
+    ensure_repo()?;
+    summarize_diff(&["diff", "--cached", &amend_base()?])
+}
+
+/// HEAD's parent, or the empty tree when HEAD is the first commit.
+fn amend_base() -> Result<String> {
+    if !ref_exists("HEAD") {
+        bail!("No commit to amend yet.");
+    }
+    if ref_exists("HEAD~1") {
+        return Ok("HEAD~1".to_string());
+    }
+    let o = run_git(&["hash-object", "-t", "tree", "--stdin"])?;
+    Ok(String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+/// Replace HEAD with a commit using `message` plus anything staged (`git commit --amend`).
+///
+/// With nothing staged this only rewrites the message. Output goes to `on_output` as in
+/// `commit_changes`.
+pub fn amend_commit(
+    message: &str,
+    options: CommitOptions,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    run_commit(&["--amend"], message, options, on_output)
+}
+
+fn run_commit(
+    extra_args: &[&str],
+    message: &str,
+    options: CommitOptions,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    read_only::check("Committing")?;
+    ensure_repo()?;
+
+    // Feed the message through stdin (`git commit -F -`) to reliably preserve multi-line
+    // messages without temp-file paths (which can break on Windows `%TEMP%` with spaces).
+    let mut child = command()
+        .arg("commit")
+        .args(extra_args)
+        .args(options.args())
+        .args(["-F", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git commit")?;
+
+    // Read both pipes line by line on helper threads, so hook output shows up while the
+    // hook runs and neither pipe can fill up and stall git. They start before the message
+    // is written: git runs pre-commit before it reads stdin, and a chatty hook must not
+    // block it while a long message waits in the pipe.
+    let (tx, rx) = mpsc::channel::<(bool, String)>();
+    if let Some(pipe) = child.stdout.take() {
+        forward_lines(pipe, false, tx.clone());
+    }
+    if let Some(pipe) = child.stderr.take() {
+        forward_lines(pipe, true, tx.clone());
+    }
+    drop(tx);
+    // Dropping stdin closes it, ending the message. When git exits before reading it (bad
+    // options, a rejecting hook), its own error below says more than the broken pipe.
+    let written = child
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(message.as_bytes()));
+    let (mut stdout, mut stderr) = (String::new(), String::new());
+    for (is_stderr, line) in rx {
+        on_output(&line);
+        let buffer = if is_stderr { &mut stderr } else { &mut stdout };
+        buffer.push_str(&line);
+        buffer.push('\n');
+    }
+    let status = child.wait().context("Failed to wait for git commit")?;
+
+    if !status.success() {
+        let stdout = stdout.trim_end().to_string();
+        let stderr = stderr.trim_end().to_string();
+        if is_signing_failure(&stderr) {
+            bail!(
+                "Signing the commit failed, so nothing was committed. git said:\n\n{}\n\n\
+                 Check that `user.signingkey` names a key you have and that gpg-agent can \
+                 unlock it (`echo test | gpg --clearsign` shows the same error), or turn \
+                 off `gpg_sign`.",
+                stderr.trim()
+            );
+        }
+        let hooks = if refused_by_git(&stdout, &stderr) {
+            Vec::new()
+        } else {
+            commit_hooks(options)
+        };
+        return Err(CommitFailed {
+            stdout,
+            stderr,
+            hooks,
+        }
+        .into());
+    }
+    if let Some(Err(e)) = written {
+        return Err(e).context("Failed to pass commit message to git");
+    }
+
+    Ok(())
+}
+
+/// `git commit` exited with an error. Its stdout and stderr are kept apart so a hook's
+/// output reads the way the hook printed it.
+///
+/// Typed so the UIs can `downcast_ref` it and, when a hook may be the cause, offer to
+/// retry with `--no-verify`.
+#[derive(Debug, Clone)]
+pub struct CommitFailed {
+    pub stdout: String,
+    pub stderr: String,
+    /// Installed hooks that `--no-verify` skips (`pre-commit`, `commit-msg`); empty when
+    /// git itself refused the commit or the hooks were already skipped.
+    pub hooks: Vec<&'static str>,
+}
+
+impl CommitFailed {
+    pub fn hook_related(&self) -> bool {
+        !self.hooks.is_empty()
+    }
+
+    /// e.g. `git commit failed; the pre-commit hook may have rejected it:`
+    pub fn summary(&self) -> String {
+        if self.hook_related() {
+            format!(
+                "git commit failed; the {} hook may have rejected it:",
+                self.hooks.join(" or ")
+            )
+        } else {
+            "git commit failed:".to_string()
+        }
+    }
+
+    /// Everything the commit printed, stderr first.
+    pub fn output(&self) -> String {
+        [self.stderr.as_str(), self.stdout.as_str()]
+            .into_iter()
+            .filter(|s| !s.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
-    match source {
-        DiffSource::Staged => accumulate_numstat(&["diff", "--cached", "--numstat"])?,
-        DiffSource::Unstaged => accumulate_numstat(&["diff", "--numstat"])?,
-        DiffSource::Both => {
-            accumulate_numstat(&["diff", "--cached", "--numstat"])?;
-            accumulate_numstat(&["diff", "--numstat"])?;
+impl std::fmt::Display for CommitFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.summary())?;
+        let output = self.output();
+        if output.is_empty() {
+            return write!(f, " (no output)");
+        }
+        for line in output.lines() {
+            write!(f, "\n    {}", line)?;
         }
+        Ok(())
     }
+}
 
-    Ok(summary)
+impl std::error::Error for CommitFailed {}
+
+/// Failures git reports itself, before or without any hook (identity, nothing staged…).
+fn refused_by_git(stdout: &str, stderr: &str) -> bool {
+    stderr.lines().any(|l| l.starts_with("fatal: "))
+        || [
+            "nothing to commit",
+            "nothing added to commit",
+            "no changes added to commit",
+        ]
+        .iter()
+        .any(|m| stdout.contains(m))
 }
 
-pub fn commit_changes(message: &str) -> Result<()> {
-    ensure_repo()?;
+/// Send each line of `pipe` with `is_stderr` over `tx`, from a thread of its own.
+fn forward_lines(
+    pipe: impl Read + Send + 'static,
+    is_stderr: bool,
+    tx: mpsc::Sender<(bool, String)>,
+) {
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).split(b'\n').map_while(|l| l.ok()) {
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            if tx.send((is_stderr, line)).is_err() {
+                break;
+            }
+        }
+    });
+}
 
-    // Use a temp file + `git commit -F` to reliably preserve multi-line messages.
-    let mut path: PathBuf = std::env::temp_dir();
-    let unique = format!(
-        "git-wiz-commit-{}-{}.txt",
-        std::process::id(),
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis()
-    );
-    path.push(unique);
+/// The file `commit.template` names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitTemplate {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+#[cfg(feature = "tui")]
+impl CommitTemplate {
+    /// `message` without the template's `#` lines it still contains, so a message
+    /// started from the template doesn't commit its instructions.
+    pub fn strip_comments(&self, message: &str) -> String {
+        let comments: Vec<&str> = self
+            .text
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| l.starts_with('#'))
+            .collect();
+        message
+            .lines()
+            .filter(|l| !comments.contains(&l.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
-    fs::write(&path, message).with_context(|| {
+/// `git config commit.template`, read; a relative path is taken from the work tree root.
+/// `None` when it isn't set or the file is blank. A file that can't be read is an error,
+/// which callers show as a warning and go on without it.
+pub fn commit_template() -> Result<Option<CommitTemplate>> {
+    let o = run_git(&["config", "--path", "--get", "commit.template"])?;
+    let configured = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    if !o.status.success() || configured.is_empty() {
+        return Ok(None);
+    }
+    let mut path = PathBuf::from(&configured);
+    if path.is_relative() {
+        path = repo_root()?.join(path);
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| {
         format!(
-            "Failed to write temp commit message file: {}",
+            "commit.template is set to {}, which can't be read; generating without it",
             path.display()
         )
     })?;
+    Ok((!text.trim().is_empty()).then_some(CommitTemplate { path, text }))
+}
 
-    let output = Command::new("git")
-        .arg("commit")
-        .arg("-F")
-        .arg(&path)
-        .output()
-        .context("Failed to execute git commit")?;
+/// Where git looks for hooks: `core.hooksPath` (relative to the work tree root), or
+/// `hooks` in the git directory.
+pub fn hooks_dir() -> Result<PathBuf> {
+    let o = run_git(&["config", "--path", "--get", "core.hooksPath"])?;
+    let configured = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    if !o.status.success() || configured.is_empty() {
+        return git_path("hooks");
+    }
+    let path = PathBuf::from(configured);
+    if path.is_relative() {
+        return Ok(repo_root()?.join(path));
+    }
+    Ok(path)
+}
 
-    // Best-effort cleanup (ignore errors)
-    let _ = fs::remove_file(&path);
+/// Installed hooks a commit with `options` runs that `--no-verify` would skip
+/// (`pre-commit`, `commit-msg`); none when it skips them already.
+pub fn commit_hooks(options: CommitOptions) -> Vec<&'static str> {
+    if options.no_verify {
+        return Vec::new();
+    }
+    let Ok(dir) = hooks_dir() else {
+        return Vec::new();
+    };
+    ["pre-commit", "commit-msg"]
+        .into_iter()
+        .filter(|hook| is_executable(&dir.join(hook)))
+        .collect()
+}
 
-    if !output.status.success() {
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// Git for Windows runs hooks without an executable bit.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// `git commit` / `git tag -s` stderr for an object that couldn't be signed (no key,
+/// locked agent…).
+pub fn is_signing_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_ascii_lowercase();
+    [
+        "gpg failed to sign",
+        "failed to sign the data",
+        "signing failed",
+    ]
+    .iter()
+    .any(|m| stderr.contains(m))
+}
+
+/// A commit in a picker of recent commits.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub hash: String,
+    pub short: String,
+    pub subject: String,
+    /// Committer date, relative (`2 days ago`).
+    pub when: String,
+}
+
+/// The last `limit` commits of HEAD, newest first; empty before the first commit.
+#[cfg(feature = "cli")]
+pub fn recent_commits(limit: usize) -> Result<Vec<CommitSummary>> {
+    ensure_repo()?;
+    if !ref_exists("HEAD") {
+        return Ok(Vec::new());
+    }
+    let count = format!("-{}", limit);
+    let o = run_git(&["log", &count, "--format=%H%x1f%h%x1f%s%x1f%cr"])?;
+    if !o.status.success() {
         bail!(
-            "git commit failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+            "git log failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\x1f');
+            Some(CommitSummary {
+                hash: fields.next()?.to_string(),
+                short: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+                when: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Subjects of the last `limit` non-merge commits of HEAD, newest first; empty before
+/// the first commit.
+pub fn recent_subjects(limit: usize) -> Result<Vec<String>> {
+    ensure_repo()?;
+    if !ref_exists("HEAD") {
+        return Ok(Vec::new());
+    }
+    let count = format!("-{}", limit);
+    let o = run_git(&["log", &count, "--no-merges", "--format=%s"])?;
+    if !o.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
         );
     }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// What `revert` or `revert_no_commit` did.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertOutcome {
+    /// Reverted: committed by `revert`, applied and staged by `revert_no_commit`.
+    Done,
+    /// Stopped on conflicts in these paths; the revert is still in progress.
+    Conflicts(Vec<String>),
+}
+
+/// `git revert --no-edit <hash>`: a new commit undoing `hash`, with git's message and the
+/// sign-off and signing of `options`.
+#[cfg(feature = "cli")]
+pub fn revert(hash: &str, options: CommitOptions) -> Result<RevertOutcome> {
+    let mut args = vec!["revert", "--no-edit"];
+    args.extend(
+        options
+            .args()
+            .into_iter()
+            .filter(|arg| *arg != "--no-verify"),
+    );
+    args.push(hash);
+    run_revert(&args)
+}
+
+/// `git revert --no-commit <hash>`: the undo applied to the index and work tree, for a
+/// commit with a message of one's own. The revert stays in progress until then.
+#[cfg(feature = "cli")]
+pub fn revert_no_commit(hash: &str) -> Result<RevertOutcome> {
+    run_revert(&["revert", "--no-commit", hash])
+}
+
+#[cfg(feature = "cli")]
+fn run_revert(args: &[&str]) -> Result<RevertOutcome> {
+    read_only::check("Reverting")?;
+    ensure_repo()?;
+    let o = run_git(args)?;
+    if o.status.success() {
+        return Ok(RevertOutcome::Done);
+    }
+    // git exits non-zero on conflicts too; tell them apart by what it left unmerged.
+    let conflicted = repo_state()?.conflicted;
+    if !conflicted.is_empty() {
+        return Ok(RevertOutcome::Conflicts(conflicted));
+    }
+    bail!(
+        "git revert failed: {}",
+        String::from_utf8_lossy(&o.stderr).trim()
+    );
+}
 
+/// `git commit --fixup=<hash>` of the staged changes, for `git rebase --autosquash` to
+/// fold into `hash`.
+#[cfg(feature = "cli")]
+pub fn commit_fixup(hash: &str, options: CommitOptions) -> Result<()> {
+    read_only::check("Committing")?;
+    ensure_repo()?;
+    if get_diff_staged_allow_empty()?.trim().is_empty() {
+        bail!("Nothing is staged: a fixup commit takes the staged changes.");
+    }
+    let fixup = format!("--fixup={}", hash);
+    let mut args = vec!["commit", fixup.as_str()];
+    args.extend(options.args());
+    let o = run_git(&args)?;
+    if !o.status.success() {
+        return Err(CommitFailed {
+            stdout: String::from_utf8_lossy(&o.stdout).trim_end().to_string(),
+            stderr: String::from_utf8_lossy(&o.stderr).trim_end().to_string(),
+            hooks: commit_hooks(options),
+        }
+        .into());
+    }
     Ok(())
 }
+
+/// `hash` inverted (`git show -R`): the diff a revert of it commits. `-R` swaps the
+/// prefixes too, so they are given swapped for the usual `a/x b/x` headers.
+#[cfg(feature = "cli")]
+pub fn revert_diff(hash: &str) -> Result<String> {
+    ensure_repo()?;
+    let o = run_git(&[
+        "show",
+        "-R",
+        "--format=",
+        "--no-color",
+        "--src-prefix=b/",
+        "--dst-prefix=a/",
+        hash,
+    ])?;
+    if !o.status.success() {
+        bail!(
+            "git show {} failed: {}",
+            hash,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(diff_text(o.stdout))
+}
+
+/// The commit at HEAD, as shown when amending it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadCommit {
+    pub hash: String,
+    pub short: String,
+    pub message: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+pub fn head_commit() -> Result<HeadCommit> {
+    ensure_repo()?;
+    let o = run_git(&["log", "-1", "--format=%H%n%h%n%B"])?;
+    if !o.status.success() {
+        bail!(
+            "No commit to amend: {}",
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    let text = String::from_utf8_lossy(&o.stdout).to_string();
+    let mut lines = text.splitn(3, '\n');
+    let hash = lines.next().unwrap_or_default().trim().to_string();
+    let short = lines.next().unwrap_or_default().trim().to_string();
+    let message = lines.next().unwrap_or_default().trim().to_string();
+
+    let mut head = HeadCommit {
+        hash,
+        short,
+        message,
+        files_changed: 0,
+        insertions: 0,
+        deletions: 0,
+    };
+
+    let o = run_git(&["show", "--numstat", "--format=", "HEAD"])?;
+    if o.status.success() {
+        for line in String::from_utf8_lossy(&o.stdout).lines() {
+            let mut parts = line.splitn(3, '\t');
+            let (Some(ins), Some(del), Some(_path)) = (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            head.files_changed += 1;
+            head.insertions += ins.parse::<usize>().unwrap_or(0);
+            head.deletions += del.parse::<usize>().unwrap_or(0);
+        }
+    }
+
+    Ok(head)
+}
+
+/// Remote-tracking branches that already contain HEAD (empty if HEAD was never pushed).
+pub fn remote_branches_containing_head() -> Result<Vec<String>> {
+    ensure_repo()?;
+    let o = run_git(&["branch", "-r", "--contains", "HEAD"])?;
+    if !o.status.success() {
+        bail!(
+            "git branch -r --contains HEAD failed: {}",
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.contains(" -> "))
+        .map(str::to_string)
+        .collect())
+}
+
+/// The branch pull requests most likely target: `origin/HEAD`, else the first of
+/// `origin/main`, `origin/master`, `main` and `master` that exists.
+pub fn default_base_ref() -> Option<String> {
+    let o = run_git(&[
+        "symbolic-ref",
+        "--quiet",
+        "--short",
+        "refs/remotes/origin/HEAD",
+    ])
+    .ok()?;
+    let head = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    if o.status.success() && !head.is_empty() {
+        return Some(head);
+    }
+    ["origin/main", "origin/master", "main", "master"]
+        .into_iter()
+        .find(|name| ref_exists(name))
+        .map(str::to_string)
+}
+
+fn ref_exists(name: &str) -> bool {
+    run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", name),
+    ])
+    .is_ok_and(|o| o.status.success())
+}
+
+/// Commits on HEAD that `base` doesn't have, oldest first, as `subject` or
+/// `subject\n\nbody`.
+pub fn log_between(base: &str) -> Result<Vec<String>> {
+    ensure_repo()?;
+    if !ref_exists(base) {
+        bail!("Unknown base ref: {}", base);
+    }
+    let range = format!("{}..HEAD", base);
+    let o = run_git(&["log", "--reverse", "--format=%s%n%n%b%x1e", &range])?;
+    if !o.status.success() {
+        bail!(
+            "git log {} failed: {}",
+            range,
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .split('\x1e')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// `git diff base...HEAD` (what HEAD changed since it forked from `base`), without the
+/// files matching `excludes` unless that would leave nothing.
+pub fn diff_between(base: &str, excludes: &[String]) -> Result<String> {
+    ensure_repo()?;
+    let range = format!("{}...HEAD", base);
+    let mut pathspecs = vec![":/".to_string()];
+    pathspecs.extend(excludes.iter().map(|g| format!(":(top,exclude,glob){}", g)));
+    let filtered = diff_pathspecs(&["diff", &range], &pathspecs)?;
+    if !filtered.trim().is_empty() {
+        return Ok(filtered);
+    }
+    diff_pathspecs(&["diff", &range], &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRepo;
+
+    fn stat(path: &str, orig: Option<&str>, ins: Option<usize>, del: Option<usize>) -> FileStat {
+        FileStat {
+            path: path.to_string(),
+            orig_path: orig.map(str::to_string),
+            insertions: ins,
+            deletions: del,
+        }
+    }
+
+    #[test]
+    fn parse_numstat_z_reads_renames_binaries_and_odd_paths() {
+        let text = "3\t1\tsrc/main.rs\0\
+                    0\t0\t\0old name.rs\0new\tname.rs\0\
+                    -\t-\tlogo.png\0\
+                    2\t0\tline\nbreak.txt\0";
+        assert_eq!(
+            parse_numstat_z(text),
+            [
+                stat("src/main.rs", None, Some(3), Some(1)),
+                stat("new\tname.rs", Some("old name.rs"), Some(0), Some(0)),
+                stat("logo.png", None, None, None),
+                stat("line\nbreak.txt", None, Some(2), Some(0)),
+            ]
+        );
+        assert_eq!(parse_numstat_z(""), []);
+        // A rename cut off before its paths is dropped, not misread.
+        assert_eq!(parse_numstat_z("1\t1\t\0old.rs"), []);
+    }
+
+    #[test]
+    fn staged_file_stats_matches_git() {
+        let repo = TempRepo::new();
+        repo.write("old.txt", "one\ntwo\nthree\nfour\n");
+        repo.write("logo.bin", "\0\u{1}\u{2}");
+        repo.commit_all("init");
+        repo.git(&["mv", "old.txt", "tab\there.txt"]);
+        repo.write("logo.bin", "\0\u{3}");
+        repo.write("new\nline.txt", "a\nb\n");
+        repo.git(&["add", "-A"]);
+
+        let mut stats = staged_file_stats().unwrap();
+        stats.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            stats,
+            [
+                stat("logo.bin", None, None, None),
+                stat("new\nline.txt", None, Some(2), Some(0)),
+                stat("tab\there.txt", Some("old.txt"), Some(0), Some(0)),
+            ]
+        );
+        assert_eq!(stats[2].display_path(), "old.txt → tab\there.txt");
+    }
+    /// `master` with one commit, and `feature` two commits ahead of it (one touching a
+    /// lockfile), checked out.
+    fn feature_branch() -> TempRepo {
+        let repo = TempRepo::new();
+        repo.write("src/lib.rs", "fn one() {}\n");
+        repo.commit_all("init");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.write("src/lib.rs", "fn one() {}\nfn two() {}\n");
+        repo.commit_all("feat: add two\n\nTwo is the new one.");
+        repo.write("Cargo.lock", "# generated\n");
+        repo.commit_all("chore: lock");
+        repo
+    }
+
+    #[test]
+    fn log_between_lists_the_branch_commits_oldest_first() {
+        let _repo = feature_branch();
+        assert_eq!(
+            log_between("master").unwrap(),
+            ["feat: add two\n\nTwo is the new one.", "chore: lock"]
+        );
+        assert_eq!(log_between("feature").unwrap(), Vec::<String>::new());
+        let err = log_between("no-such-base").unwrap_err();
+        assert!(err.to_string().contains("Unknown base ref"), "{err}");
+    }
+
+    #[test]
+    fn diff_between_leaves_out_excluded_files_unless_nothing_is_left() {
+        let repo = feature_branch();
+        // A commit on master after the fork is not part of the branch's diff.
+        repo.git(&["checkout", "-q", "master"]);
+        repo.write("README.md", "later\n");
+        repo.commit_all("docs: readme");
+        repo.git(&["checkout", "-q", "feature"]);
+
+        let excludes = ["Cargo.lock".to_string()];
+        let diff = diff_between("master", &excludes).unwrap();
+        assert!(diff.contains("+fn two() {}"), "{diff}");
+        assert!(!diff.contains("Cargo.lock"), "{diff}");
+        assert!(!diff.contains("README.md"), "{diff}");
+        assert!(diff_between("master", &[]).unwrap().contains("Cargo.lock"));
+
+        let everything = ["**/*".to_string(), "*".to_string()];
+        let diff = diff_between("master", &everything).unwrap();
+        assert!(
+            diff.contains("Cargo.lock") && diff.contains("src/lib.rs"),
+            "{diff}"
+        );
+    }
+
+    /// Sets environment variables for the test and restores them on drop. Only the
+    /// editor test touches these, so parallel tests don't see them change.
+    struct ScopedEnv(Vec<(&'static str, Option<std::ffi::OsString>)>);
+
+    impl ScopedEnv {
+        fn new(names: &[&'static str]) -> Self {
+            Self(names.iter().map(|&n| (n, std::env::var_os(n))).collect())
+        }
+
+        fn set(&self, name: &str, value: Option<&str>) {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+
+    impl Drop for ScopedEnv {
+        fn drop(&mut self) {
+            for (name, value) in &self.0 {
+                match value {
+                    Some(value) => std::env::set_var(name, value),
+                    None => std::env::remove_var(name),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn editor_is_resolved_in_gits_order() {
+        let repo = TempRepo::new();
+        let env = ScopedEnv::new(&["GIT_EDITOR", "VISUAL", "EDITOR", "GIT_CONFIG_GLOBAL"]);
+        // Keep the user's own `core.editor` out of it.
+        env.set("GIT_CONFIG_GLOBAL", Some("/dev/null"));
+        let editor = |program: &str, args: &[&str]| {
+            (
+                program.to_string(),
+                args.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            )
+        };
+
+        env.set("GIT_EDITOR", Some("nano -w"));
+        env.set("VISUAL", Some("code --wait"));
+        env.set("EDITOR", Some("vim"));
+        repo.git(&["config", "core.editor", "emacs -nw"]);
+        assert_eq!(resolve_editor(), editor("nano", &["-w"]));
+
+        env.set("GIT_EDITOR", None);
+        assert_eq!(resolve_editor(), editor("emacs", &["-nw"]));
+
+        repo.git(&["config", "--unset", "core.editor"]);
+        assert_eq!(resolve_editor(), editor("code", &["--wait"]));
+
+        env.set("VISUAL", Some(" "));
+        assert_eq!(resolve_editor(), editor("vim", &[]));
+
+        env.set("VISUAL", None);
+        env.set("EDITOR", None);
+        let default = if cfg!(windows) { "notepad.exe" } else { "vi" };
+        assert_eq!(resolve_editor(), editor(default, &[]));
+
+        // An editor that fails leaves the file as it was and says so.
+        env.set("GIT_EDITOR", Some("false"));
+        repo.write("MSG", "keep me");
+        let err = edit_file(&repo.path("MSG")).unwrap_err();
+        assert!(err.to_string().starts_with("Editor exited with"), "{}", err);
+        assert_eq!(
+            std::fs::read_to_string(repo.path("MSG")).unwrap(),
+            "keep me"
+        );
+    }
+
+    const H1: &str = "1111111111111111111111111111111111111111";
+    const H2: &str = "2222222222222222222222222222222222222222";
+
+    /// `git status --porcelain=v2 --branch -z` for a branch with an upstream, one record
+    /// of each kind.
+    fn porcelain() -> String {
+        [
+            "# branch.oid 3333333333333333333333333333333333333333".to_string(),
+            "# branch.head feat/x".to_string(),
+            "# branch.upstream origin/feat/x".to_string(),
+            "# branch.ab +2 -1".to_string(),
+            format!("1 M. N... 100644 100644 100644 {H1} {H2} src/main.rs"),
+            format!("1 .M N... 100644 100644 100644 {H1} {H1} docs/read me.md"),
+            format!("1 T. N... 100644 120000 120000 {H1} {H2} link"),
+            format!("1 MM N... 100644 100644 100644 {H1} {H2} both.rs"),
+            format!("2 R. N... 100644 100644 100644 {H1} {H1} R100 src/new name.rs"),
+            "src/old name.rs".to_string(),
+            format!("u UU N... 100644 100644 100644 100644 {H1} {H2} {H1} conflict.rs"),
+            "? notes/todo list.txt".to_string(),
+            String::new(),
+        ]
+        .join("\0")
+    }
+
+    #[test]
+    fn porcelain_v2_records_are_parsed_by_kind() {
+        let snap = parse_porcelain_v2(&porcelain());
+
+        assert_eq!(snap.branch.as_deref(), Some("feat/x"));
+        assert_eq!(snap.upstream.as_deref(), Some("origin/feat/x"));
+        assert_eq!((snap.ahead, snap.behind), (2, 1));
+
+        let paths = |entries: Vec<&StatusEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.path.clone()).collect()
+        };
+        assert_eq!(
+            paths(snap.staged().collect()),
+            ["src/main.rs", "link", "both.rs", "src/new name.rs"]
+        );
+        assert_eq!(
+            paths(snap.unstaged().collect()),
+            ["docs/read me.md", "both.rs"]
+        );
+        assert_eq!(paths(snap.untracked().collect()), ["notes/todo list.txt"]);
+        assert_eq!(paths(snap.unmerged().collect()), ["conflict.rs"]);
+
+        let rename = &snap.entries[4];
+        assert_eq!(rename.kind, EntryKind::Renamed);
+        assert_eq!(rename.orig_path.as_deref(), Some("src/old name.rs"));
+        assert_eq!(rename.index, 'R');
+        let typechange = &snap.entries[2];
+        assert_eq!((typechange.index, typechange.worktree), ('T', '.'));
+        assert_eq!(typechange.index_oid, H2);
+        let conflict = &snap.entries[5];
+        assert_eq!((conflict.index, conflict.worktree), ('U', 'U'));
+        assert!(!conflict.is_staged() && !conflict.is_unstaged());
+        assert_eq!(conflict.index_oid, "");
+
+        assert_eq!(
+            snap.to_string(),
+            "feat/x ↑2 ↓1 · 4 staged · 2 unstaged · 1 untracked · 1 conflicted"
+        );
+    }
+
+    #[test]
+    fn porcelain_v2_headers_for_detached_and_unborn_heads() {
+        let detached =
+            parse_porcelain_v2(&format!("# branch.oid {H1}\0# branch.head (detached)\0"));
+        assert_eq!(detached.branch, None);
+        assert_eq!(detached.to_string(), "detached @ 1111111");
+        assert!(detached.is_clean());
+
+        let unborn = parse_porcelain_v2("# branch.oid (initial)\0# branch.head master\0? a\0");
+        assert_eq!(unborn.oid, None);
+        assert_eq!(unborn.to_string(), "master · 1 untracked");
+
+        // Without `--branch`, and with a record type this parser doesn't know.
+        let bare = parse_porcelain_v2(
+            &[
+                "! ignored.log".to_string(),
+                format!("1 A. N... 000000 100644 100644 {H1} {H2} new.rs"),
+            ]
+            .join("\0"),
+        );
+        assert_eq!(bare.branch, None);
+        assert_eq!(bare.entries.len(), 1);
+        assert_eq!(bare.entries[0].index, 'A');
+    }
+
+    #[test]
+    fn snapshot_of_a_real_repository() {
+        let repo = TempRepo::new();
+        repo.write("a.txt", "a\n");
+        repo.write("old.txt", "same content for the rename\n");
+        repo.write("c.txt", "base\n");
+        repo.commit_all("init");
+
+        repo.git(&["checkout", "-q", "-b", "other"]);
+        repo.write("c.txt", "other\n");
+        repo.commit_all("other");
+        repo.git(&["checkout", "-q", "master"]);
+        repo.write("c.txt", "master\n");
+        repo.commit_all("master");
+        let merge = Command::new("git")
+            .arg("-C")
+            .arg(&repo.root)
+            .args(["merge", "-q", "other"])
+            .output()
+            .unwrap();
+        assert!(!merge.status.success());
+
+        repo.git(&["mv", "old.txt", "new.txt"]);
+        repo.write("a.txt", "changed\n");
+        repo.write("untracked file.txt", "u\n");
+
+        let snap = snapshot().unwrap();
+        assert_eq!(snap.branch.as_deref(), Some("master"));
+        assert_eq!(snap.upstream, None);
+        let rename = snap.staged().next().unwrap();
+        assert_eq!(rename.path, "new.txt");
+        assert_eq!(rename.orig_path.as_deref(), Some("old.txt"));
+        assert_eq!(snap.unstaged().next().unwrap().path, "a.txt");
+        assert_eq!(snap.unmerged().next().unwrap().path, "c.txt");
+        assert_eq!(snap.untracked().next().unwrap().path, "untracked file.txt");
+    }
+
+    #[test]
+    fn ref_names_get_a_plain_explanation() {
+        let cases = [
+            ("v1.0 beta", "tag names cannot contain spaces"),
+            ("-v1.0", "tag names cannot start with '-'"),
+            ("v1..0", "tag names cannot contain '..'"),
+            ("v1.0.lock", "tag names cannot end with '.lock'"),
+            ("v1.0.", "tag names cannot end with '.'"),
+            ("v1@{0}", "tag names cannot contain '@{'"),
+            ("v1~1", "tag names cannot contain '~'"),
+            ("v1:0", "tag names cannot contain ':'"),
+            ("v1\t0", "tag names cannot contain spaces"),
+            (
+                "rel//v1",
+                "tag names cannot have empty parts or parts starting with '.'",
+            ),
+            (
+                "rel/.v1",
+                "tag names cannot have empty parts or parts starting with '.'",
+            ),
+            ("@", "tag names cannot be '@'"),
+            ("  ", "tag names cannot be empty"),
+        ];
+        for (name, problem) in cases {
+            let err = validate_ref_name(RefKind::Tag, name)
+                .unwrap_err()
+                .to_string();
+            assert!(
+                err.ends_with(&format!("{}.", problem)),
+                "{:?}: {}",
+                name,
+                err
+            );
+            assert!(err.starts_with(&format!("Invalid tag name '{}'", name)));
+        }
+
+        let err = validate_ref_name(RefKind::Branch, "feat x").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("branch names cannot contain spaces"));
+
+        for name in ["v1.0.0", "v1.0.0-beta.1", "release/v2", "feat/JIRA-12_x"] {
+            assert!(validate_ref_name(RefKind::Tag, name).is_ok(), "{}", name);
+        }
+    }
+
+    /// Paths in the index that differ from HEAD (or all of them before the first commit).
+    #[cfg(feature = "cli")]
+    fn staged_names(repo: &TempRepo) -> Vec<String> {
+        let mut names: Vec<String> = repo
+            .git(&["diff", "--cached", "--name-only", "-z", "--root"])
+            .split('\0')
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn files_are_staged_and_unstaged_by_literal_path() {
+        let repo = TempRepo::new();
+        let names = ["a file.txt", "ünïcode.txt", "[ab].txt", "a.txt"];
+        for name in names {
+            repo.write(name, "one\n");
+        }
+        repo.commit_all("init");
+        for name in names {
+            repo.write(name, "two\n");
+        }
+
+        // `[ab].txt` is not a glob for `a.txt`.
+        stage_files(&["a file.txt", "ünïcode.txt", "[ab].txt"]).unwrap();
+        assert_eq!(
+            staged_names(&repo),
+            ["[ab].txt", "a file.txt", "ünïcode.txt"]
+        );
+
+        let diff = get_diff(DiffSource::Staged, &[Path::new("ünïcode.txt")]).unwrap();
+        assert_eq!(diff.matches("diff --git").count(), 1);
+        assert!(diff.contains("-one\n+two"));
+        let unstaged = get_diff(DiffSource::Unstaged, &[Path::new("a.txt")]).unwrap();
+        assert!(unstaged.starts_with("diff --git a/a.txt b/a.txt"));
+        let err = get_diff(DiffSource::Staged, &[Path::new("a.txt")]).unwrap_err();
+        assert_eq!(err.to_string(), "No changes found in the selected files.");
+        assert_eq!(
+            get_diff_allow_empty(DiffSource::Staged, &[Path::new("a.txt")]).unwrap(),
+            ""
+        );
+
+        unstage_files(&[Path::new("a file.txt"), Path::new("[ab].txt")]).unwrap();
+        assert_eq!(staged_names(&repo), ["ünïcode.txt"]);
+        assert_eq!(
+            std::fs::read_to_string(repo.path("a file.txt")).unwrap(),
+            "two\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn unstaging_before_the_first_commit_keeps_the_files() {
+        let repo = TempRepo::new();
+        repo.write("keep.txt", "k\n");
+        repo.write("drop me.txt", "d\n");
+        stage_files(&["keep.txt", "drop me.txt"]).unwrap();
+        assert_eq!(staged_names(&repo), ["drop me.txt", "keep.txt"]);
+
+        unstage_files(&["drop me.txt"]).unwrap();
+        assert_eq!(staged_names(&repo), ["keep.txt"]);
+        assert!(repo.path("drop me.txt").exists());
+
+        stage_files::<&str>(&[]).unwrap();
+        unstage_files::<&str>(&[]).unwrap();
+        assert_eq!(staged_names(&repo), ["keep.txt"]);
+    }
+
+    /// `git status --porcelain=v2 -z` with copies, both kinds of conflict, and paths that
+    /// contain newlines.
+    #[cfg(feature = "cli")]
+    fn status_fixture() -> String {
+        [
+            format!("1 M. N... 100644 100644 100644 {H1} {H2} src/main.rs"),
+            format!("1 .D N... 100644 100644 000000 {H1} {H1} gone.rs"),
+            format!("1 A. N... 000000 100644 100644 {H1} {H2} new.rs"),
+            format!("1 .M N... 100644 100644 100644 {H1} {H1} line\nbreak.rs"),
+            format!("2 C. N... 100644 100644 100644 {H1} {H1} C75 copy.rs"),
+            "orig.rs".to_string(),
+            format!("2 RM N... 100644 100644 100644 {H1} {H2} R90 moved.rs"),
+            "was\nhere.rs".to_string(),
+            format!("1 T. N... 100644 120000 120000 {H1} {H2} link"),
+            format!("u AA N... 000000 100644 100644 100644 {H1} {H2} {H1} both added.rs"),
+            format!("u DU N... 100644 000000 100644 100644 {H1} {H2} {H1} deleted by us.rs"),
+            "? new\nfile.txt".to_string(),
+            String::new(),
+        ]
+        .join("\0")
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn status_entries_keep_copies_conflicts_and_newlines() {
+        let entries = parse_porcelain_v2(&status_fixture()).entries;
+        assert_eq!(entries.len(), 10);
+
+        let copy = &entries[4];
+        assert_eq!((copy.kind, copy.index), (EntryKind::Renamed, 'C'));
+        assert_eq!(copy.path, "copy.rs");
+        assert_eq!(copy.orig_path.as_deref(), Some("orig.rs"));
+        let moved = &entries[5];
+        assert_eq!(moved.orig_path.as_deref(), Some("was\nhere.rs"));
+        assert!(moved.is_staged() && moved.is_unstaged());
+        assert_eq!(entries[3].path, "line\nbreak.rs");
+        assert_eq!(entries[9].path, "new\nfile.txt");
+        assert!(entries[9].is_untracked());
+        for conflict in &entries[7..9] {
+            assert_eq!(conflict.kind, EntryKind::Unmerged);
+            assert_eq!(conflict.change(&DiffSource::Staged), Some("conflicted"));
+        }
+        assert_eq!((entries[8].index, entries[8].worktree), ('D', 'U'));
+        assert_eq!(entries[8].path, "deleted by us.rs");
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn change_breakdown_counts_what_each_source_shows() {
+        let entries = parse_porcelain_v2(&status_fixture()).entries;
+        let cases = [
+            (
+                DiffSource::Staged,
+                "1 modified, 1 added, 1 renamed, 1 copied, 1 type changed, 2 conflicted",
+            ),
+            (
+                DiffSource::Unstaged,
+                "2 modified, 1 added, 1 deleted, 2 conflicted",
+            ),
+            (
+                DiffSource::Both,
+                "2 modified, 2 added, 1 deleted, 1 renamed, 1 copied, 1 type changed, \
+                 2 conflicted",
+            ),
+            (DiffSource::AgainstRef("main".to_string()), "2 conflicted"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                change_breakdown(&entries, &source),
+                expected,
+                "{:?}",
+                source
+            );
+        }
+        assert_eq!(change_breakdown(&[], &DiffSource::Both), "");
+    }
+
+    #[test]
+    #[cfg(all(feature = "cli", unix))]
+    fn status_entries_match_a_real_repository() {
+        let repo = TempRepo::new();
+        repo.write("old.txt", "content that survives the rename\n");
+        repo.write("edit.txt", "a\n");
+        repo.commit_all("init");
+        repo.git(&["mv", "old.txt", "new\nname.txt"]);
+        repo.write("edit.txt", "b\n");
+        repo.write("fresh.txt", "f\n");
+
+        let entries = status_entries().unwrap();
+        let summary: Vec<(char, char, &str, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e.index, e.worktree, e.path.as_str(), e.orig_path.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ('.', 'M', "edit.txt", None),
+                ('R', '.', "new\nname.txt", Some("old.txt")),
+                ('?', '?', "fresh.txt", None),
+            ]
+        );
+        assert_eq!(
+            change_breakdown(&entries, &DiffSource::Both),
+            "1 modified, 1 added, 1 renamed"
+        );
+    }
+
+    #[test]
+    fn diff_summary_counts_a_large_diff_without_reading_it() {
+        const LINES: usize = 40_000;
+        let text = |word: &str| -> String {
+            (0..LINES)
+                .map(|n| format!("{} line {} of the synthetic diff\n", word, n))
+                .collect()
+        };
+        let repo = TempRepo::new();
+        repo.write("big.txt", &text("old"));
+        repo.write("small.txt", "a\n");
+        repo.write("logo.bin", "\0\u{1}");
+        repo.commit_all("init");
+        repo.write("big.txt", &text("new"));
+        repo.git(&["add", "big.txt"]);
+        repo.write("small.txt", "b\n");
+        repo.write("logo.bin", "\0\u{2}");
+        repo.write("fresh.txt", "1\n2\n3\n");
+
+        let started = std::time::Instant::now();
+        let staged = diff_summary(DiffSource::Staged).unwrap();
+        let unstaged = diff_summary(DiffSource::Unstaged).unwrap();
+        let both = diff_summary(DiffSource::Both).unwrap();
+        // Generous: only a regression to something far slower than git itself trips it.
+        assert!(started.elapsed() < std::time::Duration::from_secs(20));
+
+        assert_eq!(
+            (staged.files_changed, staged.insertions, staged.deletions),
+            (1, LINES, LINES)
+        );
+        assert_eq!(staged.bytes, get_diff_staged_allow_empty().unwrap().len());
+        assert!(staged.bytes > 2 * text("new").len());
+
+        assert_eq!(
+            (
+                unstaged.files_changed,
+                unstaged.insertions,
+                unstaged.deletions,
+                unstaged.binary_files
+            ),
+            (3, 4, 1, 1)
+        );
+        assert_eq!(
+            unstaged.bytes,
+            get_diff_unstaged_allow_empty().unwrap().len()
+        );
+
+        assert_eq!(both.files_changed, 4);
+        assert_eq!(both.insertions, LINES + 4);
+        assert_eq!(both.bytes, staged.bytes + unstaged.bytes);
+        assert_eq!(both.files, [staged.files, unstaged.files].concat());
+    }
+
+    /// HEAD's message exactly as stored, without the trimming `TempRepo::git` does.
+    fn head_message(repo: &TempRepo) -> String {
+        let o = Command::new("git")
+            .arg("-C")
+            .arg(&repo.root)
+            .args(["log", "-1", "--format=%B"])
+            .output()
+            .unwrap();
+        // `%B` is followed by one newline of its own.
+        let text = String::from_utf8(o.stdout).unwrap();
+        text.strip_suffix('\n').unwrap().to_string()
+    }
+
+    #[test]
+    fn commit_messages_survive_the_stdin_pipe() {
+        let repo = TempRepo::new();
+        repo.write("a.txt", "a\n");
+        repo.git(&["add", "-A"]);
+
+        let message = "feat: ünïcode subject\n\nBody with `code`.\n    indented line\n# not a comment here\n\n\n";
+        commit_changes(message, CommitOptions::default(), &mut |_| {}).unwrap();
+        // git drops the trailing blank lines, nothing else.
+        assert_eq!(head_message(&repo), message.trim_end().to_string() + "\n");
+
+        // CRLF line ends lose their `\r` like any trailing whitespace.
+        amend_commit(
+            "fix: from Windows\r\n\r\nline one\r\nline two\r\n",
+            CommitOptions::default(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(
+            head_message(&repo),
+            "fix: from Windows\n\nline one\nline two\n"
+        );
+        assert_eq!(repo.git(&["rev-list", "--count", "HEAD"]), "1");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn chatty_and_rejecting_hooks_dont_block_the_message() {
+        let repo = TempRepo::new();
+        repo.write("a.txt", "a\n");
+        repo.git(&["add", "-A"]);
+        let hook = repo.path(".git/hooks/pre-commit");
+        // Far more output than a pipe buffer holds, before git reads the message.
+        std::fs::write(&hook, "#!/bin/sh\nseq 1 50000\n").unwrap();
+        crate::test_support::make_executable(&hook);
+
+        let long = format!("chore: long\n\n{}\n", "x".repeat(70).repeat(2000));
+        let mut hook_lines = 0;
+        commit_changes(&long, CommitOptions::default(), &mut |line| {
+            hook_lines += usize::from(line.parse::<u32>().is_ok())
+        })
+        .unwrap();
+        assert_eq!(hook_lines, 50000);
+        assert_eq!(head_message(&repo), long);
+
+        // A hook that refuses: git's own error, not the broken pipe of the unread message.
+        std::fs::write(&hook, "#!/bin/sh\necho 'lint failed' >&2\nexit 1\n").unwrap();
+        repo.write("a.txt", "b\n");
+        repo.git(&["add", "-A"]);
+        let err = commit_changes(&long, CommitOptions::default(), &mut |_| {}).unwrap_err();
+        let failed = err.downcast_ref::<CommitFailed>().expect("CommitFailed");
+        assert_eq!(failed.stderr, "lint failed");
+        assert_eq!(failed.hooks, ["pre-commit"]);
+    }
+
+    #[test]
+    fn largest_files_sorts_merges_and_breaks_ties_by_path() {
+        let mut summary = DiffSummary::default();
+        for stat in [
+            stat("b.rs", None, Some(12), Some(0)),
+            stat("a.png", None, None, None),
+            stat("a.rs", None, Some(5), Some(5)),
+            stat("Cargo.lock", None, Some(300), Some(200)),
+            stat("src/new.rs", Some("src/old.rs"), Some(1), Some(1)),
+            stat("z.txt", None, Some(1), Some(0)),
+            // The unstaged side of a file that also has staged changes.
+            stat("a.rs", None, Some(2), Some(0)),
+        ] {
+            summary.add(&stat);
+        }
+
+        let listed: Vec<(String, String)> = summary
+            .largest_files(LARGEST_FILES)
+            .iter()
+            .map(|f| (f.display_path(), f.counts()))
+            .collect();
+        let expected = [
+            ("Cargo.lock", "+300 -200"),
+            ("a.rs", "+7 -5"),
+            ("b.rs", "+12 -0"),
+            ("src/old.rs → src/new.rs", "+1 -1"),
+            ("z.txt", "+1 -0"),
+        ];
+        assert_eq!(
+            listed,
+            expected.map(|(path, counts)| (path.to_string(), counts.to_string()))
+        );
+        assert_eq!(summary.largest_files(10).last().unwrap().counts(), "binary");
+        assert_eq!(summary.largest_files(0), []);
+
+        let lock = &summary.largest_files(1)[0];
+        assert_eq!(summary.share(lock), Some(95));
+        assert_eq!(summary.share(&stat("a.png", None, None, None)), None);
+        assert_eq!(DiffSummary::default().largest_files(5), []);
+    }
+
+    #[test]
+    fn latin1_files_are_diffed_with_a_warning_naming_them() {
+        let repo = TempRepo::new();
+        // "café" and "naïve" in Latin-1: 0xE9 and 0xEF are not valid UTF-8 on their own.
+        std::fs::write(repo.path("notes latin1.txt"), b"caf\xe9\n").unwrap();
+        repo.write("plain.txt", "a\n");
+        repo.commit_all("init");
+        std::fs::write(repo.path("notes latin1.txt"), b"caf\xe9\nna\xefve\n").unwrap();
+        repo.write("plain.txt", "b\n");
+        repo.git(&["add", "-A"]);
+
+        let diff = get_diff(DiffSource::Staged, &[]).unwrap();
+        assert!(diff.contains("+na\u{fffd}ve"), "{}", diff);
+        assert!(diff.contains("+b"));
+        assert_eq!(
+            encoding_warning(&diff).as_deref(),
+            Some(
+                "The diff has 2 invalid UTF-8 sequences (shown as \u{fffd}) in notes latin1.txt; \
+                 lines in other encodings may be misread."
+            )
+        );
+        let summary = diff_summary(DiffSource::Staged).unwrap();
+        assert_eq!((summary.files_changed, summary.insertions), (2, 2));
+
+        assert_eq!(encoding_warning("diff --git a/x b/x\n+fine\n"), None);
+        let many: String = (1..=5)
+            .map(|n| format!("diff --git a/f{n} b/f{n}\n+\u{fffd}\n"))
+            .collect();
+        assert!(encoding_warning(&many)
+            .unwrap()
+            .contains("(shown as \u{fffd}) in f1, f2, f3, 2 more;"));
+    }
+
+    #[test]
+    fn detached_head_is_reported_and_refused_by_push() {
+        let repo = TempRepo::new().with_origin();
+        assert_eq!(detached_head().unwrap(), None, "unborn branch");
+        repo.write("a.txt", "a\n");
+        repo.commit_all("init");
+        assert_eq!(detached_head().unwrap(), None);
+
+        repo.git(&["checkout", "-q", "--detach", "HEAD"]);
+        let short = repo.git(&["rev-parse", "--short", "HEAD"]);
+        assert_eq!(detached_head().unwrap().as_deref(), Some(short.as_str()));
+        assert_eq!(
+            snapshot().unwrap().to_string(),
+            format!("detached @ {}", short)
+        );
+
+        let err = push_branch_with_policy(PushPolicy::FastForwardOnly).unwrap_err();
+        let detached = err.downcast_ref::<DetachedHead>().expect("DetachedHead");
+        assert_eq!(detached.commit, short);
+        assert!(err.to_string().contains("git switch -c"), "{}", err);
+        assert!(force_push_target(&[]).unwrap_err().is::<DetachedHead>());
+        assert!(pull_ff_only()
+            .unwrap_err()
+            .to_string()
+            .starts_with(&format!("HEAD is detached at {}", short)));
+        let guardrails = crate::release::ReleaseGuardrailConfig {
+            remote: "origin".to_string(),
+            expected_branch: Some("master".to_string()),
+            require_upstream: false,
+        };
+        let refused = crate::release::assert_release_guardrails(&guardrails).unwrap_err();
+        assert!(refused.to_string().starts_with(&format!(
+            "Refusing to release: HEAD is detached at {}",
+            short
+        )));
+        // Nothing reached origin.
+        assert_eq!(repo.git(&["ls-remote", "--heads", "origin"]), "");
+
+        // The way out the push offers: a branch at HEAD.
+        create_branch("rescue", true).unwrap();
+        assert_eq!(detached_head().unwrap(), None);
+        assert!(matches!(
+            push_branch_with_policy(PushPolicy::FastForwardOnly).unwrap(),
+            BranchPush::CreatedUpstream { branch } if branch == "rescue"
+        ));
+    }
+}
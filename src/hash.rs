@@ -0,0 +1,19 @@
+//! Stable content hashing for diffs.
+//!
+//! Hashes here are persisted (history, caches), so they must not depend on the
+//! Rust version or process: no `DefaultHasher`, just 64-bit FNV-1a.
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Hex fingerprint of a diff, ignoring trailing whitespace at the end of the text.
+pub fn diff_hash(diff: &str) -> String {
+    format!("{:016x}", fnv1a(diff.trim_end().as_bytes()))
+}
@@ -0,0 +1,140 @@
+//! Per-repository record of commits made through git-wiz.
+//!
+//! Stored as JSON under the repository's git dir (`.git/git-wiz/history.json`,
+//! resolved with `git rev-parse --git-path` so worktrees get their own file).
+//! Recording is best-effort: a failure to write history never fails a commit.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git::{self, CommitOptions};
+use crate::hash;
+use crate::repo_config;
+
+/// Keep the file small; only recent entries are useful.
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitRecord {
+    /// Short commit hash.
+    pub commit: String,
+    /// First line of the commit message.
+    pub subject: String,
+    /// `hash::diff_hash` of the staged diff that was committed.
+    pub diff_hash: String,
+    /// Unix timestamp (seconds).
+    pub timestamp: u64,
+}
+
+impl CommitRecord {
+    /// Human-friendly age, e.g. "3 minutes ago".
+    pub fn age(&self) -> String {
+        format_age(now().saturating_sub(self.timestamp))
+    }
+}
+
+fn history_path() -> Result<PathBuf> {
+    git::git_path("git-wiz/history.json")
+}
+
+/// Load all records for the current repository (oldest first).
+pub fn load() -> Result<Vec<CommitRecord>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn save(records: &[CommitRecord]) -> Result<()> {
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(records)?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The most recent git-wiz commit in this repository, if any.
+pub fn last_commit() -> Option<CommitRecord> {
+    load().ok().and_then(|mut records| records.pop())
+}
+
+/// If `staged_diff` hashes the same as the last git-wiz commit, return that record.
+pub fn matching_last_commit(staged_diff: &str) -> Option<CommitRecord> {
+    if staged_diff.trim().is_empty() {
+        return None;
+    }
+    let hash = hash::diff_hash(staged_diff);
+    last_commit().filter(|r| r.diff_hash == hash)
+}
+
+/// Commit the staged changes with `message` and record the commit in history.
+pub fn commit_and_record(message: &str) -> Result<()> {
+    commit_and_record_with(message, repo_config::commit_options()?, &mut |_| {})
+}
+
+/// `commit_and_record` with explicit flags, e.g. to retry with `--no-verify`, passing
+/// what git and its hooks print to `on_output` line by line.
+pub fn commit_and_record_with(
+    message: &str,
+    options: CommitOptions,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    let staged = git::get_diff_staged_allow_empty().unwrap_or_default();
+    git::commit_changes(message, options, on_output)?;
+    let _ = record_head(&hash::diff_hash(&staged));
+    Ok(())
+}
+
+fn record_head(diff_hash: &str) -> Result<()> {
+    let output = git::command()
+        .args(["log", "-1", "--format=%h%n%s"])
+        .output()
+        .context("Failed to run git log")?;
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines();
+    let commit = lines.next().unwrap_or_default().trim().to_string();
+    let subject = lines.next().unwrap_or_default().trim().to_string();
+
+    let mut records = load().unwrap_or_default();
+    records.push(CommitRecord {
+        commit,
+        subject,
+        diff_hash: diff_hash.to_string(),
+        timestamp: now(),
+    });
+    if records.len() > MAX_ENTRIES {
+        let excess = records.len() - MAX_ENTRIES;
+        records.drain(..excess);
+    }
+    save(&records)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn format_age(secs: u64) -> String {
+    let (n, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
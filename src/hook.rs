@@ -0,0 +1,184 @@
+//! `post_generate_command`: a user command that checks (and may rewrite) generated messages.
+//!
+//! Contract: the message is written to the command's stdin; the command runs through the
+//! shell with the repository root as working directory. Exit 0 accepts the message, and
+//! non-empty stdout replaces it. Any other exit rejects it, with stderr as the reason.
+//! Commands that run longer than `TIMEOUT` are killed and count as a rejection.
+
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::git;
+
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// The message to use: the original, or the command's rewrite.
+    Accepted(String),
+    /// Why the command rejected the message (its stderr, or what went wrong running it).
+    Rejected(String),
+}
+
+/// The configured `post_generate_command`, if any.
+pub fn post_generate_command() -> Option<String> {
+    Config::load()
+        .ok()
+        .flatten()
+        .and_then(|c| c.post_generate_command)
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// Run `command` on `message`. Failing to run the command at all is also a rejection.
+pub fn run_post_generate(command: &str, message: &str) -> HookOutcome {
+    run(command, message).unwrap_or_else(|e| HookOutcome::Rejected(format!("{:#}", e)))
+}
+
+fn run(command: &str, message: &str) -> Result<HookOutcome> {
+    let mut child = shell(command)
+        .current_dir(git::repo_root()?)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run post_generate_command `{}`", command))?;
+
+    // Feed stdin and drain both pipes on helper threads so a chatty command can't deadlock.
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = message.to_string();
+        // A command that ignores its input may close stdin early; that's fine.
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
+    let stdout = read_to_string_async(child.stdout.take());
+    let stderr = read_to_string_async(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child, command)?;
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let reason = match stderr.trim() {
+            "" => format!("`{}` exited with {}", command, status),
+            reason => reason.to_string(),
+        };
+        return Ok(HookOutcome::Rejected(reason));
+    }
+
+    Ok(HookOutcome::Accepted(match stdout.trim() {
+        "" => message.to_string(),
+        rewritten => rewritten.to_string(),
+    }))
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+fn read_to_string_async(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut out = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut out);
+        }
+        out
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, command: &str) -> Result<std::process::ExitStatus> {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "post_generate_command `{}` timed out after {}s",
+                command,
+                TIMEOUT.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_executable, TempRepo};
+
+    /// A policy linter: rejects WIP messages, rewrites `fixed` to `fix`, accepts the rest
+    /// silently. It reads `.policy` from its working directory, which must be the repo root.
+    const LINTER: &str = r#"#!/bin/sh
+test -f .policy || { echo "not run from the repository root" >&2; exit 2; }
+message=$(cat)
+case "$message" in
+  *WIP*) echo "WIP commits are not allowed" >&2; exit 1 ;;
+  fixed*) printf '%s\n' "$message" | sed '1s/^fixed/fix/' ;;
+esac
+"#;
+
+    fn repo_with_linter() -> (TempRepo, String) {
+        let repo = TempRepo::new();
+        repo.write(".policy", "");
+        repo.write("tools/lint-message", LINTER);
+        make_executable(&repo.path("tools/lint-message"));
+        (repo, "tools/lint-message".to_string())
+    }
+
+    // The linter is named relative to the repository root, which is also where it looks
+    // for `.policy`: the tests below run from the crate directory, so both only work
+    // because the command runs in the root.
+    #[test]
+    fn accepts_the_message_unchanged_when_the_command_prints_nothing() {
+        let (_repo, linter) = repo_with_linter();
+        assert_eq!(
+            run_post_generate(&linter, "feat: add retry\n\nBody."),
+            HookOutcome::Accepted("feat: add retry\n\nBody.".to_string())
+        );
+    }
+
+    #[test]
+    fn uses_the_rewritten_message() {
+        let (_repo, linter) = repo_with_linter();
+        assert_eq!(
+            run_post_generate(&linter, "fixed: handle empty input\n\nBody."),
+            HookOutcome::Accepted("fix: handle empty input\n\nBody.".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_with_the_commands_stderr() {
+        let (_repo, linter) = repo_with_linter();
+        assert_eq!(
+            run_post_generate(&linter, "WIP: retry"),
+            HookOutcome::Rejected("WIP commits are not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn a_silent_failure_names_the_command_and_its_status() {
+        let (_repo, _) = repo_with_linter();
+        let HookOutcome::Rejected(reason) = run_post_generate("exit 3", "feat: a") else {
+            panic!("accepted");
+        };
+        assert!(reason.contains("`exit 3` exited with"), "{}", reason);
+        assert!(reason.contains('3'), "{}", reason);
+    }
+}
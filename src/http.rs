@@ -0,0 +1,187 @@
+//! HTTP clients for the provider APIs, with the network settings from the config.
+//!
+//! reqwest already honors `HTTPS_PROXY` / `HTTP_PROXY` / `ALL_PROXY` and `NO_PROXY`.
+//! `proxy_url` replaces the proxy from the environment (`NO_PROXY` still applies), and
+//! `extra_ca_bundle` adds the certificates of a PEM file to the trusted roots, for
+//! networks that inspect TLS with a private CA. Both are read from the saved config, so
+//! the setup wizard's checks go through the same proxy as generations.
+//!
+//! Generation requests go through `send`: a 429 with `Retry-After` (or OpenAI's
+//! `retry-after-ms`) is retried after exactly that long, counting down through the
+//! reporter the UI installed with `with_wait_reporter`. Waits longer than
+//! `max_retry_wait_secs` (90 s by default) fail instead. An HTTP-date `Retry-After`, or
+//! none at all, leaves the 429 to the caller's error handling, and so does a 429 after
+//! `MAX_RATE_LIMIT_RETRIES` retries. Every retry waits at least a second, whatever the
+//! header says.
+
+use anyhow::{bail, Context, Result};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy, RequestBuilder, Response, StatusCode};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Shown with errors caused by an untrusted server certificate.
+pub const CA_HINT: &str = "The server's TLS certificate is not trusted. Behind a proxy with \
+    its own CA, set `extra_ca_bundle` in the config to the CA's PEM file.";
+
+/// Start of each countdown message, e.g. `Rate limited by OpenAI — retrying in 12s`.
+pub const RATE_LIMITED: &str = "Rate limited";
+
+/// Default for `max_retry_wait_secs`.
+pub const DEFAULT_MAX_RETRY_WAIT_SECS: u64 = 90;
+
+/// Retries of a rate-limited request before its 429 goes to the caller.
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// Shortest wait before a retry, so `Retry-After: 0` can't spin.
+const MIN_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Receives `send`'s countdown messages.
+type WaitReporter = Rc<dyn Fn(&str)>;
+
+thread_local! {
+    /// Where `send` reports its countdown on this thread.
+    static ON_WAIT: RefCell<Option<WaitReporter>> = const { RefCell::new(None) };
+}
+
+/// A client builder with `proxy_url` and `extra_ca_bundle` applied.
+pub fn builder() -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+    let Some(cfg) = Config::load().ok().flatten() else {
+        return Ok(builder);
+    };
+    if let Some(url) = cfg.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        let proxy = Proxy::all(url.trim())
+            .with_context(|| format!("Invalid proxy_url in config: {}", url))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = cfg
+        .extra_ca_bundle
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+    {
+        let pem = std::fs::read(path.trim())
+            .with_context(|| format!("Failed to read extra_ca_bundle {}", path))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("extra_ca_bundle {} is not a PEM file", path))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    Ok(builder)
+}
+
+/// A client without a timeout, for generations.
+pub fn client() -> Result<reqwest::Client> {
+    builder()?.build().context("Failed to build HTTP client")
+}
+
+/// True when `e` failed because the server's certificate wasn't trusted.
+pub fn is_tls_error(e: &reqwest::Error) -> bool {
+    let mut cause: Option<&dyn Error> = Some(e);
+    while let Some(error) = cause {
+        let text = error.to_string().to_ascii_lowercase();
+        if ["certificate", "unknownissuer", "self signed", "self-signed"]
+            .iter()
+            .any(|m| text.contains(m))
+        {
+            return true;
+        }
+        cause = error.source();
+    }
+    false
+}
+
+/// A failed send to `target`, with `CA_HINT` when TLS verification was the cause.
+pub fn send_error(e: reqwest::Error, target: impl Display) -> anyhow::Error {
+    let tls = is_tls_error(&e);
+    let error = anyhow::Error::new(e).context(format!("Failed to send request to {}", target));
+    if tls {
+        error.context(CA_HINT)
+    } else {
+        error
+    }
+}
+
+/// Run `f` with `report` getting the countdown of every rate-limit wait `send` does on
+/// this thread (the futures `runtime::block_on` drives run on the calling thread).
+pub fn with_wait_reporter<T>(report: impl Fn(&str) + 'static, f: impl FnOnce() -> T) -> T {
+    let previous = ON_WAIT.with(|w| w.replace(Some(Rc::new(report))));
+    let result = f();
+    ON_WAIT.with(|w| *w.borrow_mut() = previous);
+    result
+}
+
+fn report_wait(text: &str) {
+    if let Some(report) = ON_WAIT.with(|w| w.borrow().clone()) {
+        report(text);
+    }
+}
+
+/// Send a request to `target` for `model`, waiting out and retrying 429s that say how long
+/// to wait.
+pub async fn send(request: RequestBuilder, target: impl Display, model: &str) -> Result<Response> {
+    let cap = Duration::from_secs(
+        Config::load()
+            .ok()
+            .flatten()
+            .and_then(|c| c.max_retry_wait_secs)
+            .unwrap_or(DEFAULT_MAX_RETRY_WAIT_SECS),
+    );
+    let mut request = request;
+    let mut waited = Duration::ZERO;
+    let mut retries = 0;
+    loop {
+        let retry = request.try_clone();
+        let response = request.send().await.map_err(|e| send_error(e, &target))?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(response);
+        }
+        let (Some(wait), Some(retry)) = (retry_after(&response), retry) else {
+            return Ok(response);
+        };
+        if retries == MAX_RATE_LIMIT_RETRIES {
+            return Ok(response);
+        }
+        retries += 1;
+        let wait = wait.max(MIN_RETRY_WAIT);
+        if waited + wait > cap {
+            bail!(
+                "{} is rate limiting {} for this API key and asks to wait {} s, more than the \
+                 {} s limit (`max_retry_wait_secs`). Try again later, or switch to another \
+                 model or key.",
+                target,
+                model,
+                wait.as_secs_f64().ceil(),
+                cap.as_secs()
+            );
+        }
+        for left in (1..=wait.as_secs_f64().ceil() as u64).rev() {
+            report_wait(&format!(
+                "{} by {} — retrying in {}s",
+                RATE_LIMITED, target, left
+            ));
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        report_wait(&format!("{} by {} — retrying now", RATE_LIMITED, target));
+        waited += wait;
+        request = retry;
+    }
+}
+
+/// The wait a 429 asks for, from `retry-after-ms` or a `Retry-After` in seconds. Values
+/// a `Duration` can't hold count as no header.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let header = |name, scale: f64| {
+        let value = response.headers().get(name)?.to_str().ok()?;
+        let secs = value.trim().parse::<f64>().ok()? / scale;
+        Duration::try_from_secs_f64(secs).ok()
+    };
+    header("retry-after-ms", 1000.0).or_else(|| header(RETRY_AFTER.as_str(), 1.0))
+}
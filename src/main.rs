@@ -1,17 +1,71 @@
 use anyhow::Result;
 
+#[cfg(not(any(feature = "cli", feature = "tui")))]
+compile_error!("git-wiz needs at least one of the `cli` or `tui` features enabled.");
+
+#[cfg(feature = "cli")]
+mod alias;
+mod breaking;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+mod completions;
 mod config;
+mod conflicts;
+mod conventional;
+#[cfg(feature = "tui")]
+mod diff_parse;
+#[cfg(feature = "cli")]
+mod doctor;
 mod generator;
 mod git;
+mod hash;
+mod history;
+mod hook;
+mod http;
+mod message;
+mod models;
+mod notify;
+mod offline;
+mod platform;
+mod pr;
+mod prompt;
+mod read_only;
 mod release;
+mod release_notes;
+mod repo_config;
+mod review;
+mod runtime;
+mod scope;
+#[cfg(feature = "cli")]
+mod serve;
 mod setup;
+#[cfg(feature = "cli")]
+mod split;
+mod state;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "tui")]
 mod tui;
+#[cfg(feature = "cli")]
+mod ui;
+mod usage;
 
 fn main() -> Result<()> {
     // Ensure terminal colors are enabled on Windows (useful for any non-TUI fallback/logging)
     #[cfg(windows)]
     let _ = colored::control::set_virtual_terminal(true);
 
-    // Full-screen TUI is the entrypoint.
-    tui::run_tui()
+    // The CLI parses arguments and launches the TUI by default when it is compiled in.
+    #[cfg(feature = "cli")]
+    {
+        cli::run()
+    }
+
+    // TUI-only builds: the full-screen TUI is the entrypoint.
+    #[cfg(not(feature = "cli"))]
+    {
+        git::select_repo(None)?;
+        tui::run_tui(false)
+    }
 }
@@ -0,0 +1,603 @@
+//! Commit message post-processing helpers (layout, footers/trailers).
+//!
+//! These functions are pure string transformations so both the CLI and the TUI
+//! can apply them to a generated (or hand-edited) message before committing.
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{Config, IssueKeyword, Trailers};
+
+/// Column the body is wrapped at, as `git log` and most tooling expect.
+pub const BODY_WIDTH: usize = 72;
+/// Subject length above which a warning is shown.
+pub const SUBJECT_LIMIT: usize = 72;
+/// Subject limit with `strict_subject`.
+pub const STRICT_SUBJECT_LIMIT: usize = 50;
+
+/// How generated messages are laid out (see `format_commit_message`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Hard-wrap body lines longer than `BODY_WIDTH`.
+    pub wrap_body: bool,
+    /// Warn when the subject is longer than this many characters.
+    pub subject_limit: usize,
+}
+
+impl FormatOptions {
+    /// Options for `cfg` (defaults without a config).
+    pub fn from_config(cfg: Option<&Config>) -> Self {
+        let strict = cfg.is_some_and(|c| c.strict_subject);
+        Self {
+            wrap_body: cfg.is_none_or(|c| c.wrap_body),
+            subject_limit: cfg.and_then(|c| c.subject_limit).unwrap_or(if strict {
+                STRICT_SUBJECT_LIMIT
+            } else {
+                SUBJECT_LIMIT
+            }),
+        }
+    }
+}
+
+/// Tidy a generated message: trailing whitespace and surplus blank lines go, the subject
+/// is separated from the body by one blank line, and (with `wrap_body`) long body lines
+/// are wrapped at `BODY_WIDTH` columns without breaking words.
+///
+/// Trailers, indented lines and fenced code are never wrapped; list items keep a hanging
+/// indent. The subject is left alone: see `subject_warning`.
+pub fn format_commit_message(message: &str, options: &FormatOptions) -> String {
+    let mut lines = message.lines().map(str::trim_end);
+    let Some(subject) = lines.by_ref().find(|l| !l.trim().is_empty()) else {
+        return String::new();
+    };
+
+    let trailers = trailer_lines(message);
+    let mut body: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in lines {
+        if line.is_empty() {
+            // One blank line between paragraphs.
+            if body.last().is_some_and(|l| !l.is_empty()) {
+                body.push(String::new());
+            }
+            continue;
+        }
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            body.push(line.to_string());
+            continue;
+        }
+        let keep = !options.wrap_body
+            || in_fence
+            || line.width() <= BODY_WIDTH
+            || line.starts_with([' ', '\t'])
+            || trailers.contains(&line);
+        if keep {
+            body.push(line.to_string());
+        } else {
+            body.extend(wrap_line(line, BODY_WIDTH));
+        }
+    }
+    while body.last().is_some_and(|l| l.is_empty()) {
+        body.pop();
+    }
+
+    let mut out = subject.to_string();
+    if !body.is_empty() {
+        out.push_str("\n\n");
+        out.push_str(&body.join("\n"));
+    }
+    out
+}
+
+/// e.g. `Subject is 84 characters; keep it under 72.` when the subject is over the limit.
+pub fn subject_warning(message: &str, options: &FormatOptions) -> Option<String> {
+    let len = message.lines().next()?.trim_end().chars().count();
+    (len > options.subject_limit).then(|| {
+        format!(
+            "Subject is {} characters; keep it under {}.",
+            len, options.subject_limit
+        )
+    })
+}
+
+/// `line` split at spaces into lines of at most `width` columns (wide characters such as
+/// CJK take two, as in a terminal). A word longer than `width` (e.g. a URL) gets a line of
+/// its own. List items (`- `, `* `, `1. `) continue under their text.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let indent = " ".repeat(list_marker_len(line));
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ').filter(|w| !w.is_empty()) {
+        let prefix = if out.is_empty() { "" } else { indent.as_str() };
+        if !current.is_empty() && prefix.len() + current.width() + 1 + word.width() > width {
+            out.push(format!("{}{}", prefix, current));
+            current.clear();
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        let prefix = if out.is_empty() { "" } else { indent.as_str() };
+        out.push(format!("{}{}", prefix, current));
+    }
+    out
+}
+
+/// Width of a leading `- `, `* ` or `12. ` list marker (0 if there is none).
+fn list_marker_len(line: &str) -> usize {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return 2;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        0
+    }
+}
+
+/// Issue references to append as footers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IssueLinks {
+    /// Issues closed by this commit (`Closes #N`).
+    pub closes: Vec<u64>,
+    /// Issues merely referenced (`Refs #N`).
+    pub refs: Vec<u64>,
+}
+
+impl IssueLinks {
+    pub fn is_empty(&self) -> bool {
+        self.closes.is_empty() && self.refs.is_empty()
+    }
+}
+
+/// Collect candidate issue numbers from the branch name and `#NNN` references
+/// found in added diff lines. Branch candidates come first; duplicates are removed.
+pub fn issue_candidates(branch: Option<&str>, diff: &str) -> Vec<u64> {
+    let mut out: Vec<u64> = Vec::new();
+
+    if let Some(branch) = branch {
+        for n in branch_issue_numbers(branch) {
+            push_unique(&mut out, n);
+        }
+    }
+
+    for line in diff.lines() {
+        if !line.starts_with('+') || line.starts_with("+++") {
+            continue;
+        }
+        for n in hash_references(line) {
+            push_unique(&mut out, n);
+        }
+    }
+
+    out
+}
+
+/// Parse user input such as `12, #34 refs 56` into issue links.
+///
+/// Numbers are "closing" by default; after the word `refs` they become plain
+/// references until `closes`/`fixes`/`resolves` switches back.
+pub fn parse_issue_input(input: &str) -> IssueLinks {
+    let mut links = IssueLinks::default();
+    let mut closing = true;
+
+    for token in input
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|t| !t.is_empty())
+    {
+        let lower = token.trim_end_matches(':').to_ascii_lowercase();
+        match lower.as_str() {
+            "refs" | "ref" => {
+                closing = false;
+                continue;
+            }
+            "closes" | "close" | "fixes" | "fix" | "resolves" | "resolve" => {
+                closing = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Ok(n) = token.trim_start_matches('#').parse::<u64>() {
+            if closing {
+                push_unique(&mut links.closes, n);
+            } else {
+                push_unique(&mut links.refs, n);
+            }
+        }
+    }
+
+    links.refs.retain(|n| !links.closes.contains(n));
+    links
+}
+
+/// Append `Closes #N` / `Refs #N` footers to `message`.
+///
+/// Issues already present in an existing footer are skipped. New footers are
+/// placed after any existing trailer block, separated from the body by a blank line.
+pub fn append_issue_footers(message: &str, keyword: IssueKeyword, links: &IssueLinks) -> String {
+    let existing = footer_issue_numbers(message);
+
+    let mut footers: Vec<String> = Vec::new();
+    for n in &links.closes {
+        if !existing.contains(n) {
+            footers.push(format!("{} #{}", keyword, n));
+        }
+    }
+    for n in &links.refs {
+        if !existing.contains(n) && !links.closes.contains(n) {
+            footers.push(format!("Refs #{}", n));
+        }
+    }
+
+    append_footer_lines(message, &footers)
+}
+
+/// Append the configured trailers: `Co-authored-by:` for each co-author and, with
+/// `refs_from_branch`, `Refs: #N` for the first issue number in `branch`.
+///
+/// Trailers the message already has (the model may emit them) are not repeated:
+/// co-authors are compared case-insensitively, issues by number in any footer.
+pub fn append_trailers(message: &str, trailers: &Trailers, branch: Option<&str>) -> String {
+    let mut seen: Vec<String> = trailer_lines(message)
+        .into_iter()
+        .map(normalize_trailer)
+        .collect();
+
+    let mut footers: Vec<String> = Vec::new();
+    for author in trailers.co_authors.iter().map(|a| a.trim()) {
+        let line = format!("Co-authored-by: {}", author);
+        let key = normalize_trailer(&line);
+        if !author.is_empty() && !seen.contains(&key) {
+            seen.push(key);
+            footers.push(line);
+        }
+    }
+    if trailers.refs_from_branch {
+        let issue = branch.and_then(|b| branch_issue_numbers(b).first().copied());
+        if let Some(n) = issue.filter(|n| !footer_issue_numbers(message).contains(n)) {
+            footers.push(format!("Refs: #{}", n));
+        }
+    }
+
+    append_footer_lines(message, &footers)
+}
+
+/// Lowercased trailer with single spaces, so `Co-Authored-By:  Jane` matches.
+fn normalize_trailer(line: &str) -> String {
+    line.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Index of the first line of the trailer block in `lines` (a message without its
+/// subject), or `lines.len()` when there is none. As in git, only the final paragraph
+/// counts.
+pub fn trailer_block_start(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .rposition(|l| l.trim().is_empty())
+        .map(|i| i + 1)
+        .filter(|&i| i < lines.len() && lines[i..].iter().all(|l| is_trailer_line(l)))
+        .unwrap_or(lines.len())
+}
+
+/// The trailer lines of `message` (trimmed at the end): its final paragraph after the
+/// subject, when every line of it is a trailer.
+fn trailer_lines(message: &str) -> Vec<&str> {
+    let lines: Vec<&str> = message
+        .trim_end()
+        .lines()
+        .skip(1)
+        .map(str::trim_end)
+        .collect();
+    lines[trailer_block_start(&lines)..].to_vec()
+}
+
+/// Returns true if `line` has the form of a git trailer / conventional-commit footer,
+/// e.g. `Signed-off-by: Name <mail>`, `BREAKING CHANGE: ...`, or `Closes #12`: a token of
+/// letters, digits and `-` (no spaces, not starting with `-`), then `: ` and a value, or
+/// ` #` and an issue number. Whether it is one depends on where it is: see
+/// `trailer_block_start`.
+pub fn is_trailer_line(line: &str) -> bool {
+    let line = line.trim_end();
+    if line.starts_with("BREAKING CHANGE: ") || line.starts_with("BREAKING-CHANGE: ") {
+        return true;
+    }
+
+    let (token, has_value) = match (line.split_once(": "), line.split_once(" #")) {
+        (Some((token, value)), _) if !token.contains(' ') => (token, !value.trim().is_empty()),
+        (_, Some((token, number))) => (token, number.starts_with(|c: char| c.is_ascii_digit())),
+        _ => return false,
+    };
+    has_value
+        && token.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Append footer lines after the existing trailer block (or after a blank line).
+pub fn append_footer_lines(message: &str, footers: &[String]) -> String {
+    let trimmed = message.trim_end();
+    if footers.is_empty() {
+        return trimmed.to_string();
+    }
+    if trimmed.is_empty() {
+        return footers.join("\n");
+    }
+
+    let mut out = trimmed.to_string();
+    if ends_with_trailer_block(trimmed) {
+        out.push('\n');
+    } else {
+        out.push_str("\n\n");
+    }
+    out.push_str(&footers.join("\n"));
+    out
+}
+
+/// True when the last paragraph of a multi-paragraph message consists only of trailers.
+fn ends_with_trailer_block(message: &str) -> bool {
+    let Some((_, last)) = message.rsplit_once("\n\n") else {
+        return false;
+    };
+    let last = last.trim();
+    !last.is_empty() && last.lines().all(is_trailer_line)
+}
+
+fn footer_issue_numbers(message: &str) -> Vec<u64> {
+    let mut out = Vec::new();
+    for line in trailer_lines(message) {
+        for n in hash_references(line) {
+            push_unique(&mut out, n);
+        }
+    }
+    out
+}
+
+/// Issue numbers embedded in a branch name: `123-fix`, `feat/123-x`, `issue-45`, `gh-7`, `#9`.
+fn branch_issue_numbers(branch: &str) -> Vec<u64> {
+    let mut out = Vec::new();
+    for segment in branch.split('/') {
+        let lower = segment.to_ascii_lowercase();
+        let rest = ["issue-", "issues-", "gh-", "#"]
+            .iter()
+            .find_map(|p| lower.strip_prefix(p))
+            .unwrap_or(&lower);
+
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let boundary = !rest[digits.len()..].starts_with(|c: char| c.is_ascii_alphanumeric());
+        if !digits.is_empty() && boundary {
+            if let Ok(n) = digits.parse::<u64>() {
+                push_unique(&mut out, n);
+            }
+        }
+    }
+    out
+}
+
+/// `#NNN` references in a line of text (ignores `#` followed by non-digits).
+fn hash_references(text: &str) -> Vec<u64> {
+    let mut out = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'#' && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric()) {
+            let start = i + 1;
+            let mut end = start;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+            let boundary = end >= bytes.len() || !bytes[end].is_ascii_alphanumeric();
+            if end > start && boundary {
+                if let Ok(n) = text[start..end].parse::<u64>() {
+                    push_unique(&mut out, n);
+                }
+            }
+            i = end.max(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+    out
+}
+
+fn push_unique(v: &mut Vec<u64>, n: u64) {
+    if n > 0 && !v.contains(&n) {
+        v.push(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailer_lines_follow_the_trailer_grammar() {
+        for line in [
+            "Signed-off-by: Jane Doe <jane@example.com>",
+            "Co-authored-by: Jane Doe <jane@example.com>",
+            "BREAKING CHANGE: the config moved",
+            "Refs: #12",
+            "Closes #12",
+            "Fixes #12, #13",
+            "Reviewed-by: Sam",
+        ] {
+            assert!(is_trailer_line(line), "{:?}", line);
+        }
+        for line in [
+            "Note that: the old flag is gone",
+            "See the docs: https://example.com",
+            "-x: y",
+            "Fixes #bug in the parser",
+            "Acked-by: ",
+            "Refs issue #12",
+            "plain text",
+        ] {
+            assert!(!is_trailer_line(line), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn only_the_final_paragraph_holds_trailers() {
+        let message = "fix: handle empty input\n\nNote: empty input used to panic.\n\nRefs: #7\nSigned-off-by: Jane <j@example.com>";
+        assert_eq!(
+            trailer_lines(message),
+            ["Refs: #7", "Signed-off-by: Jane <j@example.com>"]
+        );
+        assert!(trailer_lines("fix: a\n\nNote: it works\nmostly").is_empty());
+        assert!(trailer_lines("Refs: #7").is_empty());
+    }
+
+    #[test]
+    fn body_lines_that_look_like_trailers_are_wrapped() {
+        let note = format!("Note: {}", "word ".repeat(20).trim_end());
+        let trailer = format!("Co-authored-by: {} <a@example.com>", "Name".repeat(20));
+        let message = format!("fix: a\n\n{}\n\n{}", note, trailer);
+        let options = FormatOptions {
+            wrap_body: true,
+            subject_limit: 72,
+        };
+
+        let formatted = format_commit_message(&message, &options);
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert!(lines[2].starts_with("Note: ") && lines[2].width() <= BODY_WIDTH);
+        assert!(lines[3].starts_with("word"));
+        assert_eq!(lines.last(), Some(&trailer.as_str()));
+    }
+
+    #[test]
+    fn issues_mentioned_in_the_body_still_get_a_footer() {
+        let message = "fix: a\n\nSee: #12 for the report.\n\nRefs #13";
+        let links = parse_issue_input("12 13");
+        assert_eq!(
+            append_issue_footers(message, IssueKeyword::default(), &links),
+            format!("{}\nCloses #12", message)
+        );
+
+        let trailers = Trailers {
+            co_authors: Vec::new(),
+            refs_from_branch: true,
+        };
+        let message = "fix: a\n\nSee: #12 for the report.\n\nEmpty input no longer panics.";
+        assert_eq!(
+            append_trailers(message, &trailers, Some("12-fix")),
+            format!("{}\n\nRefs: #12", message)
+        );
+    }
+
+    const WRAP: FormatOptions = FormatOptions {
+        wrap_body: true,
+        subject_limit: SUBJECT_LIMIT,
+    };
+
+    #[test]
+    fn body_is_wrapped_at_72_columns_without_breaking_words() {
+        let paragraph = "The retry loop now waits for the Retry-After header instead of a fixed second, which keeps us under the provider's rate limit during bursts.";
+        let formatted = format_commit_message(
+            &format!("fix: respect Retry-After   \n\n\n{}\n", paragraph),
+            &WRAP,
+        );
+
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(lines[0], "fix: respect Retry-After");
+        assert_eq!(lines[1], "");
+        assert!(lines[2..].iter().all(|l| l.width() <= BODY_WIDTH));
+        assert_eq!(lines[2..].join(" "), paragraph);
+    }
+
+    #[test]
+    fn wrapping_keeps_lists_code_and_long_words_intact() {
+        let url = format!("https://example.com/{}", "x".repeat(80));
+        let code = format!("    {}", "let long_indented_line = 1; ".repeat(4));
+        let message = format!(
+            "docs: a\n\n- {} and some more words to push the item past the limit\n12. numbered item that is also long enough to be wrapped onto a second line\n{}\n```\n{}\n```\n{}",
+            "item",
+            url,
+            "fenced ".repeat(15),
+            code
+        );
+        let formatted = format_commit_message(&message, &WRAP);
+        let lines: Vec<&str> = formatted.lines().collect();
+
+        assert_eq!(
+            &lines[2..6],
+            [
+                "- item and some more words to push the item past the limit",
+                "12. numbered item that is also long enough to be wrapped onto a second",
+                "    line",
+                url.as_str(),
+            ]
+        );
+        assert_eq!(lines[7], "fenced ".repeat(15).trim_end());
+        assert_eq!(lines.last(), Some(&code.trim_end()));
+    }
+
+    #[test]
+    fn wide_characters_count_double() {
+        let body = "変更".repeat(30);
+        let formatted = format_commit_message(&format!("feat: a\n\n{}", body), &WRAP);
+        // One word with no spaces: left whole rather than cut mid-character.
+        assert_eq!(formatted.lines().nth(2), Some(body.as_str()));
+
+        let words = ["変更点"; 20].join(" ");
+        let formatted = format_commit_message(&format!("feat: a\n\n{}", words), &WRAP);
+        assert!(formatted.lines().skip(2).all(|l| l.width() <= BODY_WIDTH));
+        assert!(formatted.lines().count() > 3);
+    }
+
+    #[test]
+    fn raw_output_is_only_tidied() {
+        let long = "word ".repeat(30);
+        let raw = FormatOptions {
+            wrap_body: false,
+            ..WRAP
+        };
+        assert_eq!(
+            format_commit_message(&format!("\n\nfeat: a  \n\n\n\n{}\n\n", long), &raw),
+            format!("feat: a\n\n{}", long.trim_end())
+        );
+        assert_eq!(format_commit_message(" \n\n", &raw), "");
+    }
+
+    #[test]
+    fn long_subjects_are_flagged_against_the_configured_limit() {
+        let subject = format!("feat: {}", "a".repeat(60));
+        assert_eq!(subject_warning(&subject, &WRAP), None);
+        assert_eq!(
+            subject_warning(
+                &format!("{}\n\nbody", subject),
+                &FormatOptions {
+                    subject_limit: STRICT_SUBJECT_LIMIT,
+                    ..WRAP
+                }
+            ),
+            Some("Subject is 66 characters; keep it under 50.".to_string())
+        );
+
+        let mut cfg = Config::new(
+            crate::config::Provider::Gemini,
+            String::new(),
+            String::new(),
+        );
+        assert_eq!(FormatOptions::from_config(Some(&cfg)), WRAP);
+        cfg.strict_subject = true;
+        assert_eq!(
+            FormatOptions::from_config(Some(&cfg)).subject_limit,
+            STRICT_SUBJECT_LIMIT
+        );
+        cfg.subject_limit = Some(60);
+        cfg.wrap_body = false;
+        assert_eq!(
+            FormatOptions::from_config(Some(&cfg)),
+            FormatOptions {
+                wrap_body: false,
+                subject_limit: 60
+            }
+        );
+        assert_eq!(FormatOptions::from_config(None), WRAP);
+    }
+}
@@ -0,0 +1,284 @@
+//! Live model lists for the setup wizard, from the provider's models endpoint.
+//!
+//! Lists are filtered to chat models, newest first where the provider says, and cached in
+//! `models.json` next to `config.json` for a day so repeated setups don't re-hit the API.
+//! Best-effort like the usage ledger: when the fetch fails or the list comes back empty,
+//! the wizard shows its built-in menu instead.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{self, Provider};
+use crate::generator::{GROQ_BASE_URL, MISTRAL_BASE_URL, OPENAI_BASE_URL, OPENROUTER_BASE_URL};
+use crate::http;
+use crate::read_only;
+
+/// How long a fetched list is reused.
+const CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// OpenAI model ids that aren't chat-completion models.
+const OPENAI_EXCLUDED: &[&str] = &[
+    "audio",
+    "realtime",
+    "transcribe",
+    "tts",
+    "image",
+    "search",
+    "embedding",
+    "instruct",
+    "codex",
+];
+
+/// Groq also serves speech and moderation models.
+const GROQ_EXCLUDED: &[&str] = &["whisper", "tts", "guard"];
+
+/// Gemini models that can `generateContent` but don't write text replies.
+const GEMINI_EXCLUDED: &[&str] = &["tts", "image", "embedding", "live", "audio"];
+
+/// One entry of a provider's model list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    /// Display name, when the provider has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedList {
+    /// Unix timestamp (seconds).
+    fetched_at: u64,
+    models: Vec<Model>,
+}
+
+/// `models.json`: one list per provider (and per OpenAI-compatible endpoint).
+type Cache = BTreeMap<String, CachedList>;
+
+fn cache_path() -> Result<PathBuf> {
+    let mut path = dirs::config_dir().context("Could not determine config directory")?;
+    path.push("git-wiz");
+    path.push("models.json");
+    Ok(path)
+}
+
+fn cache_key(provider: &Provider, base_url: Option<&str>) -> String {
+    match (provider, base_url) {
+        (Provider::OpenAI, Some(url)) => format!("{} {}", provider, url),
+        _ => provider.to_string(),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn load_cache() -> Cache {
+    cache_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The cached list for `provider`, unless it is older than a day.
+pub fn cached(provider: &Provider, base_url: Option<&str>) -> Option<Vec<Model>> {
+    let list = load_cache().remove(&cache_key(provider, base_url))?;
+    (now().saturating_sub(list.fetched_at) < CACHE_TTL_SECS && !list.models.is_empty())
+        .then_some(list.models)
+}
+
+/// Remember `models` as the current list for `provider`.
+pub fn store(provider: &Provider, base_url: Option<&str>, models: &[Model]) -> Result<()> {
+    read_only::check("Caching the model list")?;
+    let path = cache_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut cache = load_cache();
+    cache.insert(
+        cache_key(provider, base_url),
+        CachedList {
+            fetched_at: now(),
+            models: models.to_vec(),
+        },
+    );
+    fs::write(&path, serde_json::to_string_pretty(&cache)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Fetch the chat models `api_key` can use from the provider.
+pub async fn fetch(
+    provider: &Provider,
+    api_key: &str,
+    base_url: Option<&str>,
+) -> Result<Vec<Model>> {
+    let client = http::builder()?
+        .timeout(Duration::from_secs(10))
+        .build()
+        .context("Failed to build HTTP client")?;
+
+    let base = match base_url.filter(|_| *provider == Provider::OpenAI) {
+        Some(url) => config::normalize_base_url(url).context("Invalid base_url")?,
+        None => OPENAI_BASE_URL.to_string(),
+    };
+    let request = match provider {
+        Provider::OpenAI => client.get(format!("{}/models", base)).bearer_auth(api_key),
+        Provider::Anthropic => client
+            .get("https://api.anthropic.com/v1/models?limit=1000")
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01"),
+        Provider::Gemini => client
+            .get("https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000")
+            .header("x-goog-api-key", api_key),
+        Provider::OpenRouter => client.get(format!("{}/models", OPENROUTER_BASE_URL)),
+        Provider::Groq => client
+            .get(format!("{}/models", GROQ_BASE_URL))
+            .bearer_auth(api_key),
+        Provider::Mistral => client
+            .get(format!("{}/models", MISTRAL_BASE_URL))
+            .bearer_auth(api_key),
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| http::send_error(e, provider))?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!("{} returned {} for the model list", provider, status);
+    }
+    let json: Value = response
+        .json()
+        .await
+        .context("Failed to parse the model list")?;
+
+    Ok(match provider {
+        Provider::OpenAI => openai_models(&json, base == OPENAI_BASE_URL),
+        Provider::Anthropic => anthropic_models(&json),
+        Provider::Gemini => gemini_models(&json),
+        Provider::OpenRouter => openrouter_models(&json),
+        Provider::Groq => groq_models(&json),
+        Provider::Mistral => mistral_models(&json),
+    })
+}
+
+fn entries<'a>(json: &'a Value, field: &str) -> impl Iterator<Item = &'a Value> {
+    json[field].as_array().into_iter().flatten()
+}
+
+/// Newest first. Other OpenAI-compatible servers list whatever they serve, so only
+/// api.openai.com is filtered.
+fn openai_models(json: &Value, official: bool) -> Vec<Model> {
+    let mut models: Vec<(i64, Model)> = entries(json, "data")
+        .filter_map(|m| {
+            let id = m["id"].as_str()?;
+            let chat = ["gpt-", "chatgpt-", "o1", "o3", "o4"]
+                .iter()
+                .any(|p| id.starts_with(p))
+                && !OPENAI_EXCLUDED.iter().any(|x| id.contains(x));
+            (!official || chat).then(|| {
+                let model = Model {
+                    id: id.to_string(),
+                    name: None,
+                };
+                (m["created"].as_i64().unwrap_or_default(), model)
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+    models.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Anthropic already lists newest first.
+fn anthropic_models(json: &Value) -> Vec<Model> {
+    entries(json, "data")
+        .filter_map(|m| {
+            Some(Model {
+                id: m["id"].as_str()?.to_string(),
+                name: m["display_name"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Gemini has no dates; descending ids put the newest generation first.
+fn gemini_models(json: &Value) -> Vec<Model> {
+    let mut models: Vec<Model> = entries(json, "models")
+        .filter(|m| entries(m, "supportedGenerationMethods").any(|g| g == "generateContent"))
+        .filter_map(|m| {
+            let id = m["name"].as_str()?.trim_start_matches("models/");
+            (id.starts_with("gemini-") && !GEMINI_EXCLUDED.iter().any(|x| id.contains(x))).then(
+                || Model {
+                    id: id.to_string(),
+                    name: m["displayName"].as_str().map(str::to_string),
+                },
+            )
+        })
+        .collect();
+    models.sort_by(|a, b| b.id.cmp(&a.id));
+    models
+}
+
+/// Newest first; models that can't reply with text are left out.
+fn openrouter_models(json: &Value) -> Vec<Model> {
+    let mut models: Vec<(i64, Model)> = entries(json, "data")
+        .filter(|m| {
+            m["architecture"]["output_modalities"]
+                .as_array()
+                .is_none_or(|outputs| outputs.iter().any(|o| o == "text"))
+        })
+        .filter_map(|m| {
+            let model = Model {
+                id: m["id"].as_str()?.to_string(),
+                name: m["name"].as_str().map(str::to_string),
+            };
+            Some((m["created"].as_i64().unwrap_or_default(), model))
+        })
+        .collect();
+    models.sort_by_key(|(created, _)| Reverse(*created));
+    models.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Newest first, without the speech and guard models.
+fn groq_models(json: &Value) -> Vec<Model> {
+    let mut models: Vec<(i64, Model)> = entries(json, "data")
+        .filter(|m| m["active"].as_bool() != Some(false))
+        .filter_map(|m| {
+            let id = m["id"].as_str()?;
+            (!GROQ_EXCLUDED.iter().any(|x| id.contains(x))).then(|| {
+                let model = Model {
+                    id: id.to_string(),
+                    name: None,
+                };
+                (m["created"].as_i64().unwrap_or_default(), model)
+            })
+        })
+        .collect();
+    models.sort_by_key(|(created, _)| Reverse(*created));
+    models.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Chat-capable models by id; Mistral lists each alias (`-latest`) next to its version.
+fn mistral_models(json: &Value) -> Vec<Model> {
+    let mut models: Vec<Model> = entries(json, "data")
+        .filter(|m| m["capabilities"]["completion_chat"].as_bool() == Some(true))
+        .filter_map(|m| {
+            Some(Model {
+                id: m["id"].as_str()?.to_string(),
+                name: None,
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models.dedup_by(|a, b| a.id == b.id);
+    models
+}
@@ -0,0 +1,79 @@
+//! Optional "done" notifications for long tasks (`notify` in config.json).
+//!
+//! Terminals that understand OSC 9 (iTerm2, Windows Terminal, WezTerm, Ghostty, ConEmu)
+//! or OSC 777 (VTE-based terminals, urxvt, foot) get a desktop notification; everything
+//! else gets a plain bell. Nothing is written unless stdout is a terminal. The TUI calls
+//! this from its event loop between frames, so sequences never land mid-render.
+
+use std::env;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Only tasks that ran at least this long are worth a notification.
+pub const THRESHOLD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Osc9,
+    Osc777,
+    Bell,
+}
+
+/// Notify that a task finished, if enabled and it took at least `THRESHOLD`.
+pub fn task_finished(elapsed: Duration, message: &str) {
+    if elapsed < THRESHOLD {
+        return;
+    }
+    if !Config::load().ok().flatten().is_some_and(|c| c.notify) {
+        return;
+    }
+    notify(message);
+}
+
+fn notify(message: &str) {
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return;
+    }
+
+    let kind = kind_from_env(
+        env::var("TERM_PROGRAM").ok().as_deref(),
+        env::var("TERM").ok().as_deref(),
+        env::var_os("WT_SESSION").is_some() || env::var_os("ConEmuPID").is_some(),
+        env::var_os("VTE_VERSION").is_some(),
+        env::var_os("TMUX").is_some(),
+    );
+    // Control characters would end the sequence early.
+    let message: String = message.chars().filter(|c| !c.is_control()).collect();
+    let sequence = match kind {
+        Kind::Osc9 => format!("\x1b]9;{}\x07", message),
+        Kind::Osc777 => format!("\x1b]777;notify;git-wiz;{}\x07", message),
+        Kind::Bell => "\x07".to_string(),
+    };
+    let _ = stdout.write_all(sequence.as_bytes());
+    let _ = stdout.flush();
+}
+
+/// Detection logic, separated from the environment lookups.
+///
+/// tmux swallows OSC notifications unless passthrough is configured, so it gets the bell.
+fn kind_from_env(
+    term_program: Option<&str>,
+    term: Option<&str>,
+    windows_terminal: bool,
+    vte: bool,
+    tmux: bool,
+) -> Kind {
+    if tmux {
+        return Kind::Bell;
+    }
+    if windows_terminal || matches!(term_program, Some("iTerm.app" | "WezTerm" | "ghostty")) {
+        return Kind::Osc9;
+    }
+    if vte || term.is_some_and(|t| t.starts_with("rxvt") || t.starts_with("foot")) {
+        return Kind::Osc777;
+    }
+    Kind::Bell
+}
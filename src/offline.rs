@@ -0,0 +1,93 @@
+//! Offline mode (`--offline`, or picked when the provider can't be reached): the mock
+//! generator stands in for the provider for the rest of the run, and each generated
+//! message comes with a reminder that it is a placeholder.
+//!
+//! Before a real generation the UIs `probe` the provider's host: a HEAD request with a
+//! 2 s timeout. Only a failed connection counts as offline; any HTTP status means the
+//! host is up, and real API errors are left to the generation itself.
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::config::{Config, Provider};
+use crate::generator;
+use crate::http;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Shown with each message generated while offline.
+pub const REMINDER: &str =
+    "Offline: this is a mock message, not generated from your diff. Review it before committing.";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Use the mock generator for the rest of the process.
+pub fn enable() {
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// The provider's host did not answer the probe.
+///
+/// Typed so the TUI can `downcast_ref` it and offer the mock generator instead.
+#[derive(Debug, Clone)]
+pub struct Unreachable {
+    pub provider: Provider,
+    pub host: String,
+    /// The innermost connection error, e.g. a DNS failure.
+    pub reason: String,
+}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is unreachable ({}): {}",
+            self.provider, self.host, self.reason
+        )
+    }
+}
+
+impl std::error::Error for Unreachable {}
+
+/// Check that the host `cfg` sends generations to answers at all.
+pub async fn probe(cfg: &Config) -> Result<()> {
+    let mut url = Url::parse(&generator::endpoint(cfg)?).context("Invalid provider URL")?;
+    url.set_path("/");
+    url.set_query(None);
+    let host = url.host_str().unwrap_or_default().to_string();
+
+    let client = http::builder()?
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .context("Failed to build HTTP client")?;
+    match client.head(url).send().await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let mut cause: &dyn Error = &e;
+            while let Some(source) = cause.source() {
+                cause = source;
+            }
+            let reason = if e.is_timeout() {
+                format!("no answer within {} s", PROBE_TIMEOUT.as_secs())
+            } else if http::is_tls_error(&e) {
+                format!("{} {}", cause, http::CA_HINT)
+            } else {
+                cause.to_string()
+            };
+            Err(Unreachable {
+                provider: cfg.provider.clone(),
+                host,
+                reason,
+            }
+            .into())
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Platform-specific terminal and editor behavior (mostly Windows consoles).
+//!
+//! Under mintty (Git Bash's default terminal, Cygwin/MSYS2) console programs such as
+//! `git add -p` don't see a real Windows console, so interactive prompts hang or
+//! garble. `winpty` bridges that gap when it's installed.
+
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// True when running in a mintty/Cygwin/MSYS terminal on Windows.
+pub fn is_mintty() -> bool {
+    cfg!(windows)
+        && mintty_from_env(
+            env::var("TERM_PROGRAM").ok().as_deref(),
+            env::var("MSYSTEM").ok().as_deref(),
+            env::var("WT_SESSION").ok().as_deref(),
+        )
+}
+
+/// Detection logic, separated from the environment lookups.
+///
+/// Windows Terminal (and other ConPTY hosts such as VS Code, which set `TERM_PROGRAM`)
+/// provide a real console even when running Git Bash, so only mintty needs help.
+fn mintty_from_env(
+    term_program: Option<&str>,
+    msystem: Option<&str>,
+    wt_session: Option<&str>,
+) -> bool {
+    if wt_session.is_some() {
+        return false;
+    }
+    match term_program {
+        Some(p) => p.eq_ignore_ascii_case("mintty"),
+        None => msystem.is_some(),
+    }
+}
+
+/// A `git` command suitable for interactive use with inherited stdio.
+///
+/// Under mintty this goes through `winpty` when available; otherwise it warns that
+/// the prompt may misbehave and runs git directly.
+pub fn interactive_git() -> Command {
+    if is_mintty() {
+        if on_path("winpty") {
+            let mut cmd = Command::new("winpty");
+            cmd.arg("git");
+            return cmd;
+        }
+        eprintln!(
+            "{}",
+            "Warning: interactive git may not work in mintty (Git Bash) without winpty. \
+             Install winpty or use Windows Terminal."
+                .yellow()
+        );
+    }
+    Command::new("git")
+}
+
+/// The first non-empty editor command from `candidates` (in precedence order),
+/// else the platform default (`notepad.exe` on Windows, `vi` elsewhere).
+pub fn pick_editor<const N: usize>(candidates: [Option<String>; N]) -> String {
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|e| e.trim().to_string())
+        .find(|e| !e.is_empty())
+        .unwrap_or_else(|| default_editor().to_string())
+}
+
+/// Split an editor command into program and arguments.
+///
+/// Handles `code --wait`, a quoted program (`"C:\Program Files\...\notepad++.exe" -multiInst`),
+/// and an unquoted path to an existing file that contains spaces.
+pub fn split_command(command: &str) -> (String, Vec<String>) {
+    let command = command.trim();
+    if std::path::Path::new(command).is_file() {
+        return (command.to_string(), Vec::new());
+    }
+    if let Some(rest) = command.strip_prefix('"') {
+        if let Some((program, args)) = rest.split_once('"') {
+            return (
+                program.to_string(),
+                args.split_whitespace().map(str::to_string).collect(),
+            );
+        }
+    }
+    let mut parts = command.split_whitespace().map(str::to_string);
+    let program = parts.next().unwrap_or_default();
+    (program, parts.collect())
+}
+
+fn default_editor() -> &'static str {
+    if cfg!(windows) {
+        "notepad.exe"
+    } else {
+        "vi"
+    }
+}
+
+/// Copy `text` to the system clipboard via the platform's clipboard command
+/// (`clip`, `pbcopy`, `wl-copy`, `xclip` or `xsel`).
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let candidates: &[(&str, &[&str])] = if cfg!(windows) {
+        &[("clip", &[])]
+    } else if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    let Some((program, args)) = candidates.iter().find(|(p, _)| on_path(p)) else {
+        bail!("No clipboard command found (install wl-copy, xclip or xsel)");
+    };
+
+    let mut child = Command::new(program)
+        .args(*args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Failed to pass text to {}", program))?;
+    }
+    let status = child
+        .wait()
+        .with_context(|| format!("Failed to wait for {}", program))?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+pub fn on_path(program: &str) -> bool {
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&paths)
+        .any(|dir| dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_mintty_needs_help() {
+        let cases = [
+            // (TERM_PROGRAM, MSYSTEM, WT_SESSION, mintty)
+            (Some("mintty"), Some("MINGW64"), None, true),
+            (Some("MinTTY"), None, None, true),
+            (None, Some("MINGW64"), None, true),
+            (Some("mintty"), Some("MINGW64"), Some("a1b2"), false),
+            (None, Some("MSYS"), Some("a1b2"), false),
+            (Some("vscode"), Some("MINGW64"), None, false),
+            (None, None, None, false),
+        ];
+        for (term_program, msystem, wt_session, expected) in cases {
+            assert_eq!(
+                mintty_from_env(term_program, msystem, wt_session),
+                expected,
+                "{:?} {:?} {:?}",
+                term_program,
+                msystem,
+                wt_session
+            );
+        }
+    }
+
+    #[test]
+    fn first_set_editor_wins() {
+        assert_eq!(
+            pick_editor([
+                None,
+                Some("code --wait".to_string()),
+                Some("vim".to_string())
+            ]),
+            "code --wait"
+        );
+        assert_eq!(
+            pick_editor([Some("  ".to_string()), Some(" nano ".to_string())]),
+            "nano"
+        );
+        assert_eq!(pick_editor([None, Some(String::new())]), default_editor());
+    }
+
+    #[test]
+    fn editor_commands_split_into_program_and_arguments() {
+        let cases = [
+            ("vim", ("vim", vec![])),
+            ("code --wait", ("code", vec!["--wait"])),
+            (
+                r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst -nosession"#,
+                (
+                    r"C:\Program Files\Notepad++\notepad++.exe",
+                    vec!["-multiInst", "-nosession"],
+                ),
+            ),
+            ("  emacs  -nw  ", ("emacs", vec!["-nw"])),
+        ];
+        for (command, (program, args)) in cases {
+            let expected = (
+                program.to_string(),
+                args.into_iter().map(str::to_string).collect::<Vec<_>>(),
+            );
+            assert_eq!(split_command(command), expected, "{:?}", command);
+        }
+    }
+
+    #[test]
+    fn an_existing_path_with_spaces_is_one_program() {
+        let dir = std::env::temp_dir().join(format!("git-wiz editor {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let editor = dir.join("my editor");
+        std::fs::write(&editor, "").unwrap();
+
+        let path = editor.to_string_lossy().to_string();
+        assert_eq!(split_command(&path), (path.clone(), Vec::new()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Pull request descriptions: the commits and diff of the current branch against a base
+//! ref, written up by the generator as a PR body, then copied or saved to `PR_BODY.md`.
+//!
+//! The commit list goes ahead of the diff in the prompt, so the budget cuts the diff
+//! (largest files first, as for commits) and never the commits. Shared by the CLI menu
+//! and the TUI Generate tab.
+
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::generator::{Generation, Generator};
+use crate::git;
+use crate::platform;
+use crate::prompt::{self, PromptOptions};
+use crate::read_only;
+
+/// File written by the "write to file" option, relative to the repository root.
+pub const BODY_FILE: &str = "PR_BODY.md";
+
+const PR_PROMPT: &str = "You are a senior developer writing a GitHub pull request \
+    description. You get the commits on the branch and its combined diff. Write Markdown: \
+    a short paragraph on what the change does and why, then `## Changes` with the notable \
+    changes as a list, and `## Testing` only if the commits say how it was tested. Group \
+    related commits instead of listing each one, and don't invent motivation or testing. \
+    No title line. Only output the description, no preamble and no code fences.";
+
+/// What the current branch adds on top of `base`.
+#[derive(Debug, Clone)]
+pub struct BranchChanges {
+    pub base: String,
+    /// `subject` or `subject\n\nbody`, oldest first.
+    pub commits: Vec<String>,
+    pub diff: String,
+}
+
+impl BranchChanges {
+    /// The commit list followed by the diff, as sent to the model.
+    pub fn prompt_text(&self) -> String {
+        let commits: Vec<String> = self
+            .commits
+            .iter()
+            .map(|c| format!("- {}", c.replace('\n', "\n  ")))
+            .collect();
+        format!(
+            "Commits since {}:\n\n{}\n\n{}",
+            self.base,
+            commits.join("\n"),
+            self.diff
+        )
+    }
+}
+
+/// Base offered by default: `pr_base` from the config, else the remote's default branch.
+pub fn default_base(cfg: Option<&Config>) -> Option<String> {
+    cfg.and_then(|c| c.pr_base.clone())
+        .filter(|b| !b.trim().is_empty())
+        .or_else(git::default_base_ref)
+}
+
+/// Commits and diff of HEAD since it forked from `base`.
+pub fn collect(base: &str) -> Result<BranchChanges> {
+    let base = base.trim();
+    if base.is_empty() {
+        bail!("No base ref given.");
+    }
+    let commits = git::log_between(base)?;
+    if commits.is_empty() {
+        bail!("No commits on this branch since {}.", base);
+    }
+    let diff = git::diff_between(base, &prompt::excludes())?;
+    Ok(BranchChanges {
+        base: base.to_string(),
+        commits,
+        diff,
+    })
+}
+
+/// Ask the model for the description. `prompt_sizes` on the result says what the
+/// budget cut.
+pub async fn generate(generator: &Generator, changes: &BranchChanges) -> Result<Generation> {
+    let cfg = Config::load().ok().flatten();
+    let options = PromptOptions {
+        template: Some(PR_PROMPT.to_string()),
+        commit_template: None,
+        // Commit subjects say nothing about how to write a PR description.
+        style_examples: Vec::new(),
+        ..PromptOptions::from_config(cfg.as_ref())
+    };
+    let prompt = prompt::build(None, &options, &changes.prompt_text(), None);
+    let mut generation = generator.complete(&prompt.system, &prompt.user).await?;
+    if generation.message.trim().is_empty() {
+        bail!("The model returned an empty description");
+    }
+    generation.prompt_sizes = prompt.sizes;
+    Ok(generation)
+}
+
+/// Write `body` to `PR_BODY.md` in the repository root and return its path.
+pub fn write_file(body: &str) -> Result<PathBuf> {
+    read_only::check("Writing the PR description")?;
+    let path = git::repo_root()?.join(BODY_FILE);
+    std::fs::write(&path, format!("{}\n", body.trim_end()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Copy `body` to the system clipboard.
+pub fn copy(body: &str) -> Result<()> {
+    platform::copy_to_clipboard(body)
+}
@@ -0,0 +1,1094 @@
+//! Commit-message prompt assembly with an explicit size budget.
+//!
+//! The prompt has five sections: the system template, the style examples (recent commit
+//! subjects, with `style_examples`), the suggested scope, the user's hint, and the diff.
+//! The budget is counted in tokens, estimated as bytes / 4, and comes from the model's
+//! context window unless the config sets `max_prompt_tokens`. Every section but the diff
+//! has a fixed ceiling; the diff gets whatever is left. When the total would not fit,
+//! sections are cut in this order:
+//!
+//! 1. the template, examples, scope and hint are cut to their ceilings,
+//! 2. while that leaves the diff less than `MIN_DIFF`, the examples, then the hint, then
+//!    the scope are dropped,
+//! 3. if it still does, the template is cut to make room for `MIN_DIFF`,
+//! 4. and the diff is fitted to the remaining room (see below).
+//!
+//! The diff is fitted file by file: starting with the largest, files are reduced to their
+//! file and hunk headers until the rest fits, and a note at the end names them. Only if
+//! the headers alone are still too big is the diff cut mid-file.
+//!
+//! Extra context from `diff_context_lines` / `diff_function_context` is part of the diff
+//! when it is fitted: files are cut to their headers as above, never back to less context.
+//!
+//! Every cut leaves a `[… N bytes truncated]` marker, and the final sizes are reported
+//! per section so the CLI and TUI can show what got cut.
+//!
+//! Before any of that, lockfiles and other generated files are left out of the diff
+//! (`diff_for`): they cost many tokens and say little about the change.
+//!
+//! The system template is built in per commit style unless the user supplies their own:
+//! `prompt_template` in the config, else `prompt.txt` next to `config.json`. A custom
+//! template may use `{{hint}}` and `{{branch}}`, filled in before sending; when it uses
+//! `{{hint}}` the hint is not repeated in the user message. With `language` set, either
+//! template ends with an instruction to write in that language. A `commit.template` set
+//! in git comes last, for the model to fill in.
+//!
+//! The diff also adds to the prompt: the scope its paths suggest is a section of its own,
+//! and when it removes or changes public items (`breaking::detect`) the hint asks for the
+//! breaking-change markers.
+//!
+//! A refinement (`build_refinement`) sends the same prompt followed by the previous
+//! message and the user's feedback on it. That section is sized first, up to the
+//! template and hint ceilings, and the others share what is left of the budget.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::breaking;
+use crate::config::{CommitStyle, Config, Provider};
+use crate::git::{self, DiffSource, FilteredDiff};
+use crate::repo_config;
+use crate::scope;
+
+/// Largest system template kept.
+const SYSTEM_CEILING: usize = 4 * 1024;
+/// Largest block of style examples kept.
+const EXAMPLES_CEILING: usize = 1024;
+/// Largest scope suggestion kept.
+const SCOPE_CEILING: usize = 512;
+/// Largest hint kept.
+const HINT_CEILING: usize = 2 * 1024;
+/// Smallest diff worth sending; the other sections give way to keep at least this.
+const MIN_DIFF: usize = 16 * 1024;
+/// Token estimate used for budgeting: coarse, but providers don't share a tokenizer.
+const BYTES_PER_TOKEN: usize = 4;
+/// Context window assumed for the mock generator.
+const MOCK_CONTEXT_TOKENS: usize = 128_000;
+
+/// Files left out of the prompt diff unless the diff has nothing else: lockfiles and
+/// minified or generated assets. `prompt_exclude` in the config adds to this list.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "**/Cargo.lock",
+    "**/package-lock.json",
+    "**/npm-shrinkwrap.json",
+    "**/yarn.lock",
+    "**/pnpm-lock.yaml",
+    "**/bun.lockb",
+    "**/composer.lock",
+    "**/Gemfile.lock",
+    "**/poetry.lock",
+    "**/Pipfile.lock",
+    "**/uv.lock",
+    "**/go.sum",
+    "**/flake.lock",
+    "**/*.min.js",
+    "**/*.min.css",
+    "**/*.js.map",
+];
+
+const SYSTEM_OPENAI: &str = "You are a senior developer. \
+    Write a commit message following the Conventional Commits specification. \
+    The format should be:\n\
+    <type>(<scope>): <subject>\n\n\
+    <body>\n\n\
+    <footer>\n\
+    Only output the commit message itself, no wrapper text or markdown code blocks.";
+
+const SYSTEM_DEFAULT: &str = "You are a senior developer. \
+    Write a commit message following the Conventional Commits specification. \
+    Only output the commit message itself, no wrapper text or markdown code blocks.";
+
+const SYSTEM_GITMOJI: &str = "You are a senior developer. \
+    Write a commit message in the gitmoji style: start the subject with the one gitmoji \
+    that fits the change best (e.g. ✨ feature, 🐛 bug fix, ♻️ refactor, 📝 docs, ✅ tests, \
+    🔧 configuration, ⬆️ dependencies, 🔥 removal), then a short imperative subject of at \
+    most 50 characters. Add a body after a blank line only when the change needs it. \
+    Only output the commit message itself, no wrapper text or markdown code blocks.";
+
+const SYSTEM_PLAIN: &str = "You are a senior developer. \
+    Write a commit message with a short imperative subject (at most 50 characters, no \
+    type prefix, no trailing period), a blank line, and a body wrapped at 72 characters \
+    that explains what changed and why. \
+    Only output the commit message itself, no wrapper text or markdown code blocks.";
+
+/// Custom template file, next to `config.json`.
+pub const TEMPLATE_FILE: &str = "prompt.txt";
+
+const COMMIT_TEMPLATE_INTRO: &str = "This repository has a commit message template. Fill it \
+    in: keep its sections and headings in order, follow the instructions on its lines that \
+    start with `#`, and leave those lines out of the message.\n\n";
+const DIFF_INTRO: &str = "Here is the git diff:\n\n";
+const EXAMPLES_INTRO: &str = "Recent commit subjects in this repository; match their style:\n";
+const HINT_INTRO: &str = "Focus on this context: ";
+const PREVIOUS_INTRO: &str = "You wrote this commit message for the diff:\n\n";
+const FEEDBACK_INTRO: &str =
+    "Rewrite it with this feedback, keeping whatever the feedback doesn't ask to change: ";
+
+/// A follow-up request for a message already generated: what to change about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refinement {
+    pub previous: String,
+    pub feedback: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    System,
+    Examples,
+    Scope,
+    Hint,
+    Diff,
+    Refinement,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Section::System => write!(f, "system"),
+            Section::Examples => write!(f, "examples"),
+            Section::Scope => write!(f, "scope"),
+            Section::Hint => write!(f, "hint"),
+            Section::Diff => write!(f, "diff"),
+            Section::Refinement => write!(f, "refinement"),
+        }
+    }
+}
+
+/// Bytes of one section before and after the budget was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionSize {
+    pub section: Section,
+    pub original: usize,
+    pub kept: usize,
+}
+
+impl SectionSize {
+    pub fn truncated(&self) -> bool {
+        self.kept < self.original
+    }
+}
+
+/// Per-section sizes of the prompt that was (or would be) sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptSizes {
+    pub sections: Vec<SectionSize>,
+    /// Files reduced to their headers to fit the budget, largest first.
+    pub omitted_files: Vec<String>,
+    /// Budget the prompt was fitted to, in estimated tokens.
+    pub budget_tokens: usize,
+}
+
+impl PromptSizes {
+    pub fn truncated(&self) -> bool {
+        self.sections.iter().any(SectionSize::truncated)
+    }
+
+    /// Estimated tokens of the prompt as sent.
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(self.sections.iter().map(|s| s.kept).sum())
+    }
+}
+
+/// e.g. `system 312 B · hint 0 B · diff 340.0 KB (cut from 1.2 MB) · ~85k of 96k tokens`
+impl fmt::Display for PromptSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, s) in self.sections.iter().enumerate() {
+            if i > 0 {
+                write!(f, " · ")?;
+            }
+            write!(f, "{} {}", s.section, format_bytes(s.kept))?;
+            if s.truncated() {
+                write!(f, " (cut from {})", format_bytes(s.original))?;
+            }
+        }
+        if self.budget_tokens > 0 {
+            write!(
+                f,
+                " · ~{} of {} tokens",
+                format_tokens(self.tokens()),
+                format_tokens(self.budget_tokens)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The assembled prompt plus what the budget did to each section.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub system: String,
+    pub user: String,
+    pub sizes: PromptSizes,
+}
+
+/// The diff to send for `source`: the built-in and configured excludes are left out, and
+/// each change has the configured context (`repo_config::diff_context`).
+pub fn diff_for(source: DiffSource) -> Result<FilteredDiff> {
+    git::get_diff_filtered(source, &excludes(), repo_config::diff_context()?)
+}
+
+/// `git::get_diff_amend` for the prompt, like `diff_for`.
+pub fn amend_diff() -> Result<String> {
+    git::get_diff_amend(&excludes(), repo_config::diff_context()?)
+}
+
+/// Globs left out of prompt diffs: `DEFAULT_EXCLUDES` plus `prompt_exclude`.
+pub fn excludes() -> Vec<String> {
+    let cfg = Config::load().ok().flatten();
+    DEFAULT_EXCLUDES
+        .iter()
+        .map(|g| g.to_string())
+        .chain(cfg.into_iter().flat_map(|c| c.prompt_exclude))
+        .collect()
+}
+
+/// e.g. `2 excluded from the prompt: Cargo.lock, web/yarn.lock`; `None` if nothing was.
+pub fn excluded_summary(excluded: &[String]) -> Option<String> {
+    if excluded.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{} excluded from the prompt: {}",
+        excluded.len(),
+        excluded.join(", ")
+    ))
+}
+
+/// Config-derived settings every commit-message prompt is built with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptOptions {
+    pub style: CommitStyle,
+    /// See `token_budget`.
+    pub budget_tokens: usize,
+    /// Custom system template (see `custom_template`); `None` uses the built-in one.
+    pub template: Option<String>,
+    /// Reply limit (`max_output_tokens`); `None` uses `generator::DEFAULT_MAX_OUTPUT_TOKENS`
+    /// where the API requires a limit and the server's own default elsewhere.
+    pub max_output_tokens: Option<u32>,
+    /// Language to write in (see `language_name`); `None` adds no instruction.
+    pub language: Option<String>,
+    /// Recent commit subjects shown as examples of the style (`style_examples`).
+    pub style_examples: Vec<String>,
+    /// `scope_map`, for the scope suggested to Conventional Commits prompts.
+    pub scope_map: BTreeMap<String, String>,
+    /// `detect_breaking`: ask for the breaking-change markers when `breaking::detect` fires.
+    pub detect_breaking: bool,
+    /// Text of git's `commit.template`, for the model to fill in; `None` without one.
+    pub commit_template: Option<String>,
+}
+
+impl PromptOptions {
+    /// Options for `cfg` (`None` for the mock generator without a config).
+    pub fn from_config(cfg: Option<&Config>) -> Self {
+        Self {
+            style: cfg.map(|c| c.commit_style).unwrap_or_default(),
+            budget_tokens: token_budget(cfg),
+            template: custom_template(cfg).map(|(_, template)| template),
+            max_output_tokens: cfg.and_then(|c| c.max_output_tokens),
+            language: cfg
+                .and_then(|c| c.language.as_deref())
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string),
+            style_examples: match cfg.map_or(0, |c| c.style_examples) {
+                0 => Vec::new(),
+                n => git::recent_subjects(n).unwrap_or_default(),
+            },
+            scope_map: cfg.map(|c| c.scope_map.clone()).unwrap_or_default(),
+            detect_breaking: cfg.is_none_or(|c| c.detect_breaking),
+            commit_template: git::commit_template().ok().flatten().map(|t| t.text),
+        }
+    }
+}
+
+/// Where a custom system template was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// `prompt_template` in the config.
+    Config,
+    /// `prompt.txt` next to the config.
+    File(PathBuf),
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::Config => write!(f, "custom (prompt_template)"),
+            TemplateSource::File(_) => write!(f, "custom ({})", TEMPLATE_FILE),
+        }
+    }
+}
+
+/// Path of `prompt.txt`, whether or not it exists.
+pub fn template_path() -> Result<PathBuf> {
+    Ok(Config::get_path()?.with_file_name(TEMPLATE_FILE))
+}
+
+/// The user's system template: `prompt_template` wins over `prompt.txt`. Blank ones
+/// don't count.
+pub fn custom_template(cfg: Option<&Config>) -> Option<(TemplateSource, String)> {
+    if let Some(template) = cfg.and_then(|c| c.prompt_template.as_ref()) {
+        if !template.trim().is_empty() {
+            return Some((TemplateSource::Config, template.clone()));
+        }
+    }
+    let path = template_path().ok()?;
+    let template = fs::read_to_string(&path).ok()?;
+    (!template.trim().is_empty()).then_some((TemplateSource::File(path), template))
+}
+
+/// Config tab / `config show` label: `built-in (gitmoji)` or `custom (prompt.txt)`.
+pub fn template_label(cfg: &Config) -> String {
+    match custom_template(Some(cfg)) {
+        Some((source, _)) => source.to_string(),
+        None => format!("built-in ({})", cfg.commit_style),
+    }
+}
+
+/// The built-in system template for `style`, used as the starting point of `prompt.txt`.
+pub fn builtin_template(style: CommitStyle, provider: Option<&Provider>) -> &'static str {
+    match (style, provider) {
+        (CommitStyle::Conventional, Some(Provider::OpenAI)) => SYSTEM_OPENAI,
+        (CommitStyle::Conventional, _) => SYSTEM_DEFAULT,
+        (CommitStyle::Gitmoji, _) => SYSTEM_GITMOJI,
+        (CommitStyle::Plain, _) => SYSTEM_PLAIN,
+    }
+}
+
+/// Branches that never carry a hint (`HEAD` is a detached checkout).
+const PLAIN_BRANCHES: &[&str] = &["main", "master", "HEAD"];
+
+/// Default `branch_hint_pattern`: an optional `type/` prefix, an optional ticket ID and
+/// the slug, e.g. `feat/JIRA-1234-add-retry`.
+pub const DEFAULT_BRANCH_HINT_PATTERN: &str =
+    r"^(?:[\w.-]+/)?(?:(?P<ticket>[A-Z][A-Z0-9]+-\d+)[-_]?)?(?P<slug>.*)$";
+
+/// With `hint_from_branch`, a hint derived from the current branch name: the `ticket`
+/// and `slug` groups of the pattern (all groups, for a pattern without them), with `-`
+/// and `_` in the slug read as spaces. `None` when disabled, on `main`/`master`, on a
+/// detached HEAD, or when the pattern doesn't match.
+pub fn branch_hint(cfg: Option<&Config>) -> Result<Option<String>> {
+    let Some(cfg) = cfg.filter(|c| c.hint_from_branch) else {
+        return Ok(None);
+    };
+    let Ok(branch) = git::current_branch() else {
+        return Ok(None);
+    };
+    if PLAIN_BRANCHES.contains(&branch.as_str()) {
+        return Ok(None);
+    }
+    let pattern = cfg
+        .branch_hint_pattern
+        .as_deref()
+        .unwrap_or(DEFAULT_BRANCH_HINT_PATTERN);
+    let re = Regex::new(pattern).context("Invalid branch_hint_pattern in the config")?;
+    let Some(caps) = re.captures(&branch) else {
+        return Ok(None);
+    };
+
+    let group = |name: &str| caps.name(name).map(|m| m.as_str().trim());
+    let parts: Vec<String> = match (group("ticket"), group("slug")) {
+        (None, None) => caps
+            .iter()
+            .skip(1)
+            .flatten()
+            .map(|m| m.as_str().to_string())
+            .collect(),
+        (ticket, slug) => {
+            let mut parts = Vec::new();
+            if let Some(ticket) = ticket.filter(|t| !t.is_empty()) {
+                parts.push(format!("Ticket {}.", ticket));
+            }
+            let slug = slug.unwrap_or_default().replace(['-', '_', '/'], " ");
+            if !slug.trim().is_empty() {
+                parts.push(format!("Branch topic: {}.", slug.trim()));
+            }
+            parts
+        }
+    };
+    let hint = parts.join(" ");
+    Ok((!hint.trim().is_empty()).then_some(hint))
+}
+
+/// Build the commit-message prompt for `provider` (`None` for the mock generator).
+pub fn build(
+    provider: Option<&Provider>,
+    options: &PromptOptions,
+    diff: &str,
+    hint: Option<&str>,
+) -> Prompt {
+    // The breaking-change markers go with the hint.
+    let extended;
+    let hint = match breaking_hint(options, diff) {
+        Some(extra) => {
+            let user = hint.map(str::trim).filter(|h| !h.is_empty());
+            extended = user
+                .into_iter()
+                .chain([extra.as_str()])
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(extended.as_str())
+        }
+        None => hint,
+    };
+    let (mut system, hint) = match &options.template {
+        Some(template) => fill_template(template, hint),
+        None => (builtin_template(options.style, provider).to_string(), hint),
+    };
+    if let Some(language) = &options.language {
+        system = format!(
+            "{}\n\n{}",
+            system.trim_end(),
+            language_instruction(language, options.style)
+        );
+    }
+    if let Some(template) = &options.commit_template {
+        system = format!(
+            "{}\n\n{}{}",
+            system.trim_end(),
+            COMMIT_TEMPLATE_INTRO,
+            template.trim_end()
+        );
+    }
+    let examples: Vec<String> = options
+        .style_examples
+        .iter()
+        .map(|subject| format!("- {}", subject.trim()))
+        .collect();
+    let scope = scope_hint(options, diff).unwrap_or_default();
+    let parts = Parts {
+        system: &system,
+        examples: &examples.join("\n"),
+        scope: &scope,
+        hint: hint.unwrap_or_default(),
+        diff,
+    };
+    let budget_tokens = options.budget_tokens;
+    let mut prompt = allocate(budget_tokens * BYTES_PER_TOKEN, &parts);
+    prompt.sizes.budget_tokens = budget_tokens;
+    prompt
+}
+
+/// ISO 639-1 codes accepted for `language`, with the name the prompt uses.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("hu", "Hungarian"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// The language's name for an ISO code (`de`, also `de-AT`), else `language` as given.
+pub fn language_name(language: &str) -> String {
+    let language = language.trim();
+    let code = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or_else(|| language.to_string(), |(_, name)| name.to_string())
+}
+
+/// e.g. `Write in German; keep the Conventional Commits type and scope in English.`
+fn language_instruction(language: &str, style: CommitStyle) -> String {
+    let name = language_name(language);
+    match style {
+        CommitStyle::Conventional => format!(
+            "Write in {}; keep the Conventional Commits type and scope in English.",
+            name
+        ),
+        CommitStyle::Gitmoji | CommitStyle::Plain => format!("Write in {}.", name),
+    }
+}
+
+/// The scope `diff`'s paths suggest, as a sentence for the prompt (Conventional Commits
+/// with the built-in template only).
+fn scope_hint(options: &PromptOptions, diff: &str) -> Option<String> {
+    if options.style != CommitStyle::Conventional || options.template.is_some() {
+        return None;
+    }
+    scope::suggest(diff, &options.scope_map).map(|s| scope::hint(&s))
+}
+
+/// The breaking-change markers to ask for, when `diff` removes or changes public items.
+fn breaking_hint(options: &PromptOptions, diff: &str) -> Option<String> {
+    if !options.detect_breaking {
+        return None;
+    }
+    let changes = breaking::detect(diff);
+    (!changes.is_empty()).then(|| breaking::hint(&changes, options.style))
+}
+
+/// `build`, followed by `refinement`: the previous message and the feedback on it.
+pub fn build_refinement(
+    provider: Option<&Provider>,
+    options: &PromptOptions,
+    diff: &str,
+    hint: Option<&str>,
+    refinement: &Refinement,
+) -> Prompt {
+    let previous = refinement.previous.trim();
+    let feedback = refinement.feedback.trim();
+    let previous_kept = cut(previous, SYSTEM_CEILING);
+    let feedback_kept = cut(feedback, HINT_CEILING);
+    let section = format!(
+        "\n\n{}{}\n\n{}{}",
+        PREVIOUS_INTRO, previous_kept, FEEDBACK_INTRO, feedback_kept
+    );
+
+    let rest = PromptOptions {
+        budget_tokens: options
+            .budget_tokens
+            .saturating_sub(estimate_tokens(section.len())),
+        ..options.clone()
+    };
+    let mut prompt = build(provider, &rest, diff, hint);
+    prompt.user = format!("{}{}", prompt.user.trim_end(), section);
+    prompt.sizes.sections.push(SectionSize {
+        section: Section::Refinement,
+        original: previous.len() + feedback.len(),
+        kept: previous_kept.len() + feedback_kept.len(),
+    });
+    prompt.sizes.budget_tokens = options.budget_tokens;
+    prompt
+}
+
+/// `template` with its placeholders filled, and the hint left for the user message
+/// (`None` once `{{hint}}` has taken it).
+fn fill_template<'a>(template: &str, hint: Option<&'a str>) -> (String, Option<&'a str>) {
+    let mut system = template.trim().to_string();
+    if system.contains("{{branch}}") {
+        let branch = git::current_branch().unwrap_or_default();
+        system = system.replace("{{branch}}", &branch);
+    }
+    if !system.contains("{{hint}}") {
+        return (system, hint);
+    }
+    let system = system.replace("{{hint}}", hint.map(str::trim).unwrap_or_default());
+    (system, None)
+}
+
+/// Prompt budget in estimated tokens for `cfg` (`None` for the mock generator).
+///
+/// `max_prompt_tokens` wins when set; otherwise three quarters of the model's context
+/// window, leaving the rest for the reply and for the estimate running low on dense code.
+fn token_budget(cfg: Option<&Config>) -> usize {
+    if let Some(max) = cfg.and_then(|c| c.max_prompt_tokens) {
+        return max;
+    }
+    let window = cfg.map_or(MOCK_CONTEXT_TOKENS, context_window);
+    window - window / 4
+}
+
+/// Context window in tokens of the configured model, by model family.
+fn context_window(cfg: &Config) -> usize {
+    let model = cfg.model.as_str();
+    match cfg.provider {
+        // Local OpenAI-compatible servers often run small-context models; set
+        // `max_prompt_tokens` to use more.
+        Provider::OpenAI if cfg.base_url.is_some() => 8_192,
+        Provider::OpenAI => match model {
+            m if m.starts_with("gpt-4.1") => 1_047_576,
+            m if m.starts_with("gpt-5") => 400_000,
+            m if ["o1", "o3", "o4"].iter().any(|p| m.starts_with(p)) => 200_000,
+            m if m.starts_with("gpt-4o") || m.starts_with("gpt-4-turbo") => 128_000,
+            m if m.starts_with("gpt-4") => 8_192,
+            m if m.starts_with("gpt-3.5") => 16_385,
+            _ => 128_000,
+        },
+        Provider::Anthropic => 200_000,
+        Provider::Gemini => match model {
+            "gemini-pro" | "gemini-1.0-pro" => 32_760,
+            _ => 1_048_576,
+        },
+        // Slugs cover many model families; most current ones take at least this much.
+        Provider::OpenRouter => 128_000,
+        Provider::Groq => 131_072,
+        Provider::Mistral => 128_000,
+    }
+}
+
+/// Estimated tokens for `bytes` of prompt text.
+pub fn estimate_tokens(bytes: usize) -> usize {
+    bytes.div_ceil(BYTES_PER_TOKEN)
+}
+
+/// The text of each section before the budget is applied.
+#[derive(Debug, Clone, Copy, Default)]
+struct Parts<'a> {
+    system: &'a str,
+    examples: &'a str,
+    scope: &'a str,
+    hint: &'a str,
+    diff: &'a str,
+}
+
+/// A section of the user message that gives way to the diff.
+struct Extra<'a> {
+    section: Section,
+    intro: &'static str,
+    original: &'a str,
+    kept: String,
+}
+
+impl Extra<'_> {
+    /// Bytes it adds to the user message: intro, text and the blank line before it.
+    fn len(&self) -> usize {
+        if self.kept.is_empty() {
+            0
+        } else {
+            self.intro.len() + self.kept.len() + 2
+        }
+    }
+}
+
+/// Apply the budget (see the module docs for the order) and assemble the prompt.
+fn allocate(budget: usize, parts: &Parts) -> Prompt {
+    let diff = parts.diff;
+    // A tiny `max_prompt_tokens` still leaves half the budget to the other sections.
+    let min_diff = MIN_DIFF.min(diff.len()).min(budget / 2);
+
+    let mut system_kept = cut(parts.system, SYSTEM_CEILING);
+    // In the order they are dropped.
+    let mut extras: Vec<Extra> = [
+        (
+            Section::Examples,
+            EXAMPLES_INTRO,
+            parts.examples,
+            EXAMPLES_CEILING,
+        ),
+        (Section::Hint, HINT_INTRO, parts.hint, HINT_CEILING),
+        (Section::Scope, "", parts.scope, SCOPE_CEILING),
+    ]
+    .into_iter()
+    .map(|(section, intro, text, ceiling)| {
+        let original = text.trim();
+        Extra {
+            section,
+            intro,
+            original,
+            kept: cut(original, ceiling),
+        }
+    })
+    .collect();
+    let framing = DIFF_INTRO.len() + 2;
+    let room = |system: &str, extras: &[Extra]| {
+        let others: usize = extras.iter().map(Extra::len).sum();
+        budget.saturating_sub(framing + system.len() + others)
+    };
+
+    for i in 0..extras.len() {
+        if room(&system_kept, &extras) < min_diff {
+            extras[i].kept.clear();
+        }
+    }
+    if room(&system_kept, &extras) < min_diff {
+        system_kept = cut(parts.system, budget.saturating_sub(min_diff + framing));
+    }
+    let (diff_kept, omitted_files) = fit_diff(diff, room(&system_kept, &extras));
+
+    // Examples and scope come before the hint, which ends the message as it always has.
+    let by_position = |section| extras.iter().find(|e| e.section == section);
+    let extras: Vec<&Extra> = [Section::Examples, Section::Scope, Section::Hint]
+        .into_iter()
+        .filter_map(by_position)
+        .collect();
+
+    let mut user = format!("{}{}", DIFF_INTRO, diff_kept);
+    for extra in extras.iter().filter(|e| !e.kept.is_empty()) {
+        user.push_str("\n\n");
+        user.push_str(extra.intro);
+        user.push_str(&extra.kept);
+    }
+    if extras.iter().all(|e| e.kept.is_empty()) {
+        user.push_str("\n\n");
+    }
+
+    let mut sections = vec![SectionSize {
+        section: Section::System,
+        original: parts.system.len(),
+        kept: system_kept.len(),
+    }];
+    // The hint is always listed; examples and scope only when there are any.
+    sections.extend(
+        extras
+            .iter()
+            .filter(|e| e.section == Section::Hint || !e.original.is_empty())
+            .map(|e| SectionSize {
+                section: e.section,
+                original: e.original.len(),
+                kept: e.kept.len(),
+            }),
+    );
+    sections.push(SectionSize {
+        section: Section::Diff,
+        original: diff.len(),
+        kept: diff_kept.len(),
+    });
+
+    Prompt {
+        system: system_kept,
+        user,
+        sizes: PromptSizes {
+            sections,
+            omitted_files,
+            budget_tokens: 0,
+        },
+    }
+}
+
+/// One file's part of a `git diff`.
+struct FileDiff {
+    /// Empty for any text before the first `diff --git` line.
+    path: String,
+    text: String,
+}
+
+/// `diff` fitted into `max` bytes, plus the files that were reduced to their headers.
+///
+/// The largest files lose their hunk bodies first; file and hunk headers are kept so the
+/// model still sees what changed where. Falls back to `cut` when that is not enough.
+fn fit_diff(diff: &str, max: usize) -> (String, Vec<String>) {
+    if diff.len() <= max {
+        return (diff.to_string(), Vec::new());
+    }
+
+    let mut files = split_files(diff);
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| Reverse(files[i].text.len()));
+
+    let mut len = diff.len();
+    let mut omitted = Vec::new();
+    for i in order {
+        if len + omitted_note(&omitted).len() <= max {
+            break;
+        }
+        let file = &mut files[i];
+        if file.path.is_empty() {
+            continue;
+        }
+        let headers = headers_only(&file.text);
+        if headers.len() == file.text.len() {
+            // Binary files, renames and mode changes have nothing to drop.
+            continue;
+        }
+        len -= file.text.len() - headers.len();
+        file.text = headers;
+        omitted.push(file.path.clone());
+    }
+
+    let mut fitted: String = files.iter().map(|f| f.text.as_str()).collect();
+    fitted.push_str(&omitted_note(&omitted));
+    (cut(&fitted, max), omitted)
+}
+
+/// Split a diff at its `diff --git` lines.
+fn split_files(diff: &str) -> Vec<FileDiff> {
+    let mut files = vec![FileDiff {
+        path: String::new(),
+        text: String::new(),
+    }];
+    for line in diff.split_inclusive('\n') {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let rest = rest.trim_end();
+            let path = rest.rsplit_once(" b/").map_or(rest, |(_, b)| b);
+            files.push(FileDiff {
+                path: path.to_string(),
+                text: String::new(),
+            });
+        }
+        if let Some(file) = files.last_mut() {
+            file.text.push_str(line);
+        }
+    }
+    files
+}
+
+/// A file's diff without hunk bodies: everything before the first hunk, plus `@@` lines.
+fn headers_only(text: &str) -> String {
+    let mut in_hunks = false;
+    text.split_inclusive('\n')
+        .filter(|line| {
+            in_hunks |= line.starts_with("@@");
+            !in_hunks || line.starts_with("@@")
+        })
+        .collect()
+}
+
+/// Note appended to a diff whose files were reduced to headers.
+fn omitted_note(omitted: &[String]) -> String {
+    if omitted.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n[Changes omitted to fit the prompt budget, headers only: {}]",
+        omitted.join(", ")
+    )
+}
+
+/// `text` cut to at most `max` bytes (on a line or char boundary) with a marker.
+fn cut(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    // Reserve room for the marker; its digits never exceed those of `text.len()`.
+    let marker_len = format!("\n[… {} bytes truncated]", text.len()).len();
+    let Some(mut end) = max.checked_sub(marker_len) else {
+        return String::new();
+    };
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Prefer ending on a whole line when one is close.
+    if let Some(nl) = text[..end].rfind('\n') {
+        if nl >= end - end / 4 {
+            end = nl;
+        }
+    }
+    format!("{}\n[… {} bytes truncated]", &text[..end], text.len() - end)
+}
+
+/// `950`, `12k`, `1.0M`.
+fn format_tokens(tokens: usize) -> String {
+    match tokens {
+        t if t < 1000 => t.to_string(),
+        t if t < 1_000_000 => format!("{}k", t / 1000),
+        t => format!("{:.1}M", t as f64 / 1_000_000.0),
+    }
+}
+
+/// `312 B`, `12.1 KB`, `1.2 MB`.
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A diff of `files` files with `lines` changed lines each.
+    fn diff(files: usize, lines: usize) -> String {
+        (0..files)
+            .map(|f| {
+                let body: String = (0..lines)
+                    .map(|l| format!("+line {} of file {}\n", l, f))
+                    .collect();
+                format!(
+                    "diff --git a/src/f{f}.rs b/src/f{f}.rs\n--- a/src/f{f}.rs\n+++ b/src/f{f}.rs\n@@ -0,0 +1,{lines} @@\n{body}",
+                )
+            })
+            .collect()
+    }
+
+    fn kept(prompt: &Prompt, section: Section) -> Option<usize> {
+        prompt
+            .sizes
+            .sections
+            .iter()
+            .find(|s| s.section == section)
+            .map(|s| s.kept)
+    }
+
+    #[test]
+    fn every_section_is_cut_to_its_ceiling() {
+        let system = "s".repeat(3 * SYSTEM_CEILING);
+        let examples = "- feat: add a thing\n".repeat(EXAMPLES_CEILING);
+        let scope = "x".repeat(3 * SCOPE_CEILING);
+        let hint = "h".repeat(3 * HINT_CEILING);
+        let diff = diff(3, 50);
+        let parts = Parts {
+            system: &system,
+            examples: &examples,
+            scope: &scope,
+            hint: &hint,
+            diff: &diff,
+        };
+        let prompt = allocate(1 << 20, &parts);
+
+        let ceilings = [
+            (Section::System, SYSTEM_CEILING),
+            (Section::Examples, EXAMPLES_CEILING),
+            (Section::Scope, SCOPE_CEILING),
+            (Section::Hint, HINT_CEILING),
+        ];
+        for (section, ceiling) in ceilings {
+            let size = prompt
+                .sizes
+                .sections
+                .iter()
+                .find(|s| s.section == section)
+                .unwrap();
+            assert!(size.truncated(), "{} was not cut", section);
+            assert!(size.kept <= ceiling, "{} kept {}", section, size.kept);
+            assert!(size.kept > ceiling / 2, "{} kept {}", section, size.kept);
+        }
+        // The diff fits in what is left and is sent whole.
+        assert_eq!(kept(&prompt, Section::Diff), Some(diff.len()));
+        assert!(prompt.sizes.omitted_files.is_empty());
+        assert!(prompt.user.contains("bytes truncated]"));
+        assert_eq!(
+            prompt
+                .sizes
+                .sections
+                .iter()
+                .map(|s| s.section)
+                .collect::<Vec<_>>(),
+            [
+                Section::System,
+                Section::Examples,
+                Section::Scope,
+                Section::Hint,
+                Section::Diff
+            ]
+        );
+    }
+
+    #[test]
+    fn sections_give_way_to_the_diff_in_priority_order() {
+        let system = "s".repeat(2000);
+        let examples = "- fix: handle the empty case\n".repeat(30);
+        let scope = scope::hint("tui");
+        let hint = "h".repeat(1500);
+        let diff = diff(40, 40);
+        assert!(diff.len() > MIN_DIFF);
+        let parts = Parts {
+            system: &system,
+            examples: &examples,
+            scope: &scope,
+            hint: &hint,
+            diff: &diff,
+        };
+
+        // (examples, hint, scope, whole template) kept, as the budget shrinks.
+        let mut states = Vec::new();
+        for budget in (16..256).rev().map(|b| b * 64) {
+            let prompt = allocate(budget, &parts);
+            assert!(
+                prompt.system.len() + prompt.user.len() <= budget,
+                "over budget at {}",
+                budget
+            );
+            assert_eq!(
+                prompt.sizes.sections.len(),
+                5,
+                "sections with content are always listed"
+            );
+            let state = (
+                kept(&prompt, Section::Examples) != Some(0),
+                kept(&prompt, Section::Hint) != Some(0),
+                kept(&prompt, Section::Scope) != Some(0),
+                kept(&prompt, Section::System) == Some(system.len()),
+            );
+            if states.last() != Some(&state) {
+                states.push(state);
+            }
+            // The same inputs always give the same prompt.
+            assert_eq!(allocate(budget, &parts).user, prompt.user);
+        }
+        assert_eq!(
+            states,
+            [
+                (true, true, true, true),
+                (false, true, true, true),
+                (false, false, true, true),
+                (false, false, false, true),
+                (false, false, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_diff_loses_its_largest_files_first() {
+        let big = diff(1, 2000);
+        let small = diff(3, 10)
+            .replace("f0", "s0")
+            .replace("f1", "s1")
+            .replace("f2", "s2");
+        let all = format!("{}{}", small, big);
+        let parts = Parts {
+            system: "system",
+            hint: "hint",
+            diff: &all,
+            ..Parts::default()
+        };
+        let prompt = allocate(MIN_DIFF * 2, &parts);
+
+        assert_eq!(prompt.sizes.omitted_files, ["src/f0.rs"]);
+        assert!(prompt.user.contains(&small));
+        assert!(prompt.user.contains("@@ -0,0 +1,2000 @@"));
+        assert!(!prompt.user.contains("+line 1999 of file 0"));
+        assert!(prompt.user.contains("headers only: src/f0.rs]"));
+        // The small sections are untouched.
+        assert_eq!(kept(&prompt, Section::Hint), Some(4));
+        assert_eq!(kept(&prompt, Section::System), Some(6));
+        assert_eq!(kept(&prompt, Section::Examples), None);
+        assert_eq!(kept(&prompt, Section::Scope), None);
+    }
+
+    #[test]
+    fn examples_and_scope_are_placed_before_the_hint() {
+        let options = PromptOptions {
+            style: CommitStyle::Conventional,
+            budget_tokens: 32_000,
+            template: None,
+            max_output_tokens: None,
+            language: None,
+            style_examples: vec!["feat(tui): add a log pane".to_string()],
+            scope_map: BTreeMap::from([("src".to_string(), "core".to_string())]),
+            detect_breaking: false,
+            commit_template: None,
+        };
+        let prompt = build(None, &options, &diff(2, 3), Some("retry on 429"));
+
+        let examples = prompt.user.find("- feat(tui): add a log pane").unwrap();
+        let scope = prompt.user.find("as the commit scope").unwrap();
+        let hint = prompt
+            .user
+            .find("Focus on this context: retry on 429")
+            .unwrap();
+        assert!(examples < scope && scope < hint, "{}", prompt.user);
+        assert_eq!(
+            prompt
+                .sizes
+                .to_string()
+                .split(" · ")
+                .take(4)
+                .collect::<Vec<_>>(),
+            ["system 180 B", "examples 27 B", "scope 65 B", "hint 12 B"]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Read-only mode (`--read-only`), for demos and pairing on someone else's machine.
+//!
+//! Enforced centrally: every helper that stages, commits, tags, pushes, edits files or
+//! writes the config calls `check` first and fails with a typed `ReadOnly` error. The
+//! menus keep showing those actions; picking one explains why it is blocked. Generation
+//! and diff viewing are unaffected. Best-effort bookkeeping (history, remembered
+//! choices, usage ledger) is skipped instead.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Turn read-only mode on for the rest of the process.
+#[cfg(feature = "cli")]
+pub fn enable() {
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// A mutating action attempted in read-only mode.
+///
+/// Typed so the UIs can `downcast_ref` it and show it as a warning rather than a failure.
+#[derive(Debug, Clone)]
+pub struct ReadOnly {
+    /// What was blocked, e.g. "Committing".
+    pub action: &'static str,
+}
+
+impl fmt::Display for ReadOnly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is disabled: read-only mode is active (--read-only).",
+            self.action
+        )
+    }
+}
+
+impl std::error::Error for ReadOnly {}
+
+/// Fail with `ReadOnly` for `action` when read-only mode is active.
+pub fn check(action: &'static str) -> anyhow::Result<()> {
+    if is_active() {
+        return Err(ReadOnly { action }.into());
+    }
+    Ok(())
+}
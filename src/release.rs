@@ -1,7 +1,30 @@
 use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml_edit::{DocumentMut, Item, Value};
+
+#[cfg(feature = "cli")]
+use crate::generator::Generator;
+use crate::read_only;
+use crate::release_notes;
+
+/// Manifest whose version the release bumps, at the work tree root.
+pub const MANIFEST_FILE: &str = "Cargo.toml";
+
+/// Changelog updated by the release commit when `changelog` is enabled in the config.
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+#[cfg(feature = "cli")]
+const CHANGELOG_PROMPT: &str = "You are a senior developer maintaining a CHANGELOG.md. \
+    You get a draft section grouped by commit type. Rewrite it as concise Markdown: keep the \
+    first `## [version] - date` line exactly as given, then `### Features`, `### Fixes` and \
+    `### Other` (omit empty ones). Merge duplicates, drop version-bump chores, and keep every \
+    user-visible change. Only output the section, no preamble and no code fences.";
 
 /// Release orchestration helpers for a tag-based CI pipeline.
 ///
@@ -24,6 +47,116 @@ pub enum BumpKind {
 
 impl BumpKind {}
 
+/// Guardrail error: the release remote (usually `origin`) is not configured.
+///
+/// Callers can downcast to this to offer adding the remote instead of aborting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRemote {
+    pub remote: String,
+}
+
+impl std::fmt::Display for MissingRemote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No '{}' remote found. Add it first (git remote add {} <url>).",
+            self.remote, self.remote
+        )
+    }
+}
+
+impl std::error::Error for MissingRemote {}
+
+/// Guardrail error: the working tree has changes the release commit would pick up.
+///
+/// Callers can downcast to this to offer stashing them for the length of the release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyWorkingTree;
+
+impl std::fmt::Display for DirtyWorkingTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Working tree is not clean. Commit or stash your changes before releasing."
+        )
+    }
+}
+
+impl std::error::Error for DirtyWorkingTree {}
+
+/// Steps of `run_tag_release` that change the repository, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReleaseStep {
+    /// The version (and changelog) files were written.
+    Bumped,
+    /// The release commit was made.
+    Committed,
+    /// The annotated tag was created locally.
+    Tagged,
+}
+
+impl fmt::Display for ReleaseStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseStep::Bumped => write!(f, "bumping the version"),
+            ReleaseStep::Committed => write!(f, "committing the bump"),
+            ReleaseStep::Tagged => write!(f, "tagging locally"),
+        }
+    }
+}
+
+/// Release error: `run_tag_release` failed after it changed the repository (most often
+/// the tag push: expired credentials, no network).
+///
+/// Callers can downcast to this to offer `rollback_release`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompleteRelease {
+    pub tag: String,
+    pub remote: String,
+    /// The last step that went through.
+    pub step: ReleaseStep,
+    /// Why the next one failed.
+    pub cause: String,
+    /// HEAD before the release commit (`None` on an unborn branch).
+    head_before: Option<String>,
+    /// The files the bump and changelog write, as they were before (`None`: created).
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl std::fmt::Display for IncompleteRelease {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Release {} stopped after {}: {}",
+            self.tag, self.step, self.cause
+        )
+    }
+}
+
+impl std::error::Error for IncompleteRelease {}
+
+impl IncompleteRelease {
+    /// How to finish the release by hand instead of rolling it back, when that is one
+    /// command away.
+    pub fn resume_hint(&self) -> Option<String> {
+        (self.step == ReleaseStep::Tagged).then(|| {
+            format!(
+                "The release commit and tag are in place: `git push {} {}` finishes the release.",
+                self.remote, self.tag
+            )
+        })
+    }
+}
+
+/// How `rollback_release` drops the release commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    /// `git reset --soft`: only the release's own files are put back.
+    Soft,
+    /// `git reset --hard`: every uncommitted change is discarded too.
+    Hard,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PreflightConfig {
     /// Run `cargo fmt --check`
@@ -32,6 +165,12 @@ pub struct PreflightConfig {
     pub clippy_deny_warnings: bool,
     /// Run `cargo test --locked`
     pub test_locked: bool,
+    /// Commands (program and arguments) run after those, in order; `[preflight]` in
+    /// `.gitwiz.toml` (see `repo_config::preflight_config`).
+    pub extra_commands: Vec<Vec<String>>,
+    /// Names of checks left out of this run (see `PreflightCheck::name`); the release
+    /// commit body records them.
+    pub skip: Vec<String>,
 }
 
 impl Default for PreflightConfig {
@@ -40,21 +179,171 @@ impl Default for PreflightConfig {
             fmt_check: true,
             clippy_deny_warnings: true,
             test_locked: true,
+            extra_commands: Vec::new(),
+            skip: Vec::new(),
         }
     }
 }
 
+impl PreflightConfig {
+    /// The enabled checks in the order `run_preflight` runs them, skipped ones included.
+    pub fn checks(&self) -> Vec<PreflightCheck> {
+        let builtin = [
+            (self.fmt_check, "fmt", &["cargo", "fmt", "--check"][..]),
+            (
+                self.clippy_deny_warnings,
+                "clippy",
+                &["cargo", "clippy", "--", "-D", "warnings"][..],
+            ),
+            (self.test_locked, "test", &["cargo", "test", "--locked"][..]),
+        ];
+        let mut checks: Vec<PreflightCheck> = builtin
+            .into_iter()
+            .filter(|(enabled, _, _)| *enabled)
+            .map(|(_, name, command)| PreflightCheck {
+                name: name.to_string(),
+                command: command.iter().map(|arg| arg.to_string()).collect(),
+            })
+            .collect();
+        checks.extend(self.extra_commands.iter().map(|command| PreflightCheck {
+            name: command.join(" "),
+            command: command.clone(),
+        }));
+        checks
+    }
+
+    /// Leave out the checks called `names` for this run.
+    pub fn skip_checks(&mut self, names: &[String]) -> Result<()> {
+        let checks = self.checks();
+        for name in names {
+            let name = name.trim();
+            if !checks.iter().any(|check| check.name == name) {
+                let known: Vec<&str> = checks.iter().map(|check| check.name.as_str()).collect();
+                bail!(
+                    "Unknown preflight check `{}` (this repository runs: {}).",
+                    name,
+                    known.join(", ")
+                );
+            }
+            if !self.skip.iter().any(|skipped| skipped == name) {
+                self.skip.push(name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_skipped(&self, check: &PreflightCheck) -> bool {
+        self.skip.contains(&check.name)
+    }
+
+    /// `preflight: skipped clippy, test` for the release commit body; `None` when every
+    /// check runs.
+    pub fn skipped_note(&self) -> Option<String> {
+        let skipped: Vec<String> = self
+            .checks()
+            .into_iter()
+            .filter(|check| self.is_skipped(check))
+            .map(|check| check.name)
+            .collect();
+        (!skipped.is_empty()).then(|| format!("preflight: skipped {}", skipped.join(", ")))
+    }
+}
+
+/// One preflight command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightCheck {
+    /// `fmt`, `clippy` and `test` for the built-in checks, the command line for extra ones.
+    pub name: String,
+    /// Program and arguments.
+    pub command: Vec<String>,
+}
+
+/// The command line, e.g. `cargo clippy -- -D warnings`.
+impl fmt::Display for PreflightCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.command.join(" "))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ReleasePlan {
     pub old_version: String,
     pub new_version: String,
     pub tag: String, // "vX.Y.Z"
+    /// Where `old_version` was read and `new_version` goes.
+    pub source: VersionSource,
+}
+
+/// Which version a release bumps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionScope {
+    /// The package's own version; the workspace's when the package inherits it
+    /// (`version.workspace = true`) or the manifest is a virtual workspace root.
+    #[default]
+    Package,
+    /// `[workspace.package].version` of the workspace the package belongs to.
+    Workspace,
+}
+
+/// The table a version lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionTable {
+    Package,
+    WorkspacePackage,
+}
+
+impl fmt::Display for VersionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionTable::Package => write!(f, "[package]"),
+            VersionTable::WorkspacePackage => write!(f, "[workspace.package]"),
+        }
+    }
+}
+
+/// The manifest and table a release reads the version from and bumps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSource {
+    pub path: PathBuf,
+    pub table: VersionTable,
+}
+
+/// e.g. `Cargo.toml [workspace.package]`, the path relative to the repository root.
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root = crate::git::repo_root().ok();
+        let path = root
+            .as_deref()
+            .and_then(|root| self.path.strip_prefix(root).ok())
+            .unwrap_or(&self.path);
+        write!(f, "{} {}", path.display(), self.table)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ReleaseGuardrailConfig {
     pub remote: String,                  // usually "origin"
     pub expected_branch: Option<String>, // e.g. Some("master".into())
+    /// Refuse to release when the current branch has no upstream yet.
+    pub require_upstream: bool,
+}
+
+/// How release tags are made; `tag` in the config, `[tag]` in `.gitwiz.toml` (see
+/// `repo_config::tag_config`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagConfig {
+    /// `git tag -s` instead of `-a`, verified with `git tag -v` afterwards.
+    pub sign: bool,
+    /// Key for signed tags (`-u`); `None` uses git's `user.signingkey`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+}
+
+impl TagConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 impl Default for ReleaseGuardrailConfig {
@@ -62,29 +351,58 @@ impl Default for ReleaseGuardrailConfig {
         Self {
             remote: "origin".to_string(),
             expected_branch: Some("master".to_string()),
+            require_upstream: false,
+        }
+    }
+}
+
+impl ReleaseGuardrailConfig {
+    /// The defaults, with `require_upstream` from `release_require_upstream` in the config.
+    pub fn from_config(config: Option<&crate::config::Config>) -> Self {
+        Self {
+            require_upstream: config.is_some_and(|c| c.release_require_upstream),
+            ..Self::default()
         }
     }
 }
 
 /// Compute a release plan by reading `Cargo.toml` and applying a semver bump.
-pub fn plan_bump(cargo_toml_path: impl AsRef<Path>, bump: BumpKind) -> Result<ReleasePlan> {
-    let old_version = read_cargo_package_version(cargo_toml_path.as_ref())?;
+/// `MANIFEST_FILE` of the selected repository, wherever git-wiz was started.
+pub fn manifest_path() -> Result<PathBuf> {
+    Ok(crate::git::repo_root()?.join(MANIFEST_FILE))
+}
+
+pub fn plan_bump(
+    cargo_toml_path: impl AsRef<Path>,
+    bump: BumpKind,
+    scope: VersionScope,
+) -> Result<ReleasePlan> {
+    let source = version_source(cargo_toml_path.as_ref(), scope)?;
+    let old_version = read_version(&source)?;
     let new_version = bump_semver(&old_version, bump)?;
     Ok(ReleasePlan {
         old_version,
         tag: format!("v{}", new_version),
         new_version,
+        source,
     })
 }
 
 /// Compute a release plan using a custom version string.
 /// Validates that it looks like `x.y.z` and differs from current.
-pub fn plan_custom(cargo_toml_path: impl AsRef<Path>, new_version: &str) -> Result<ReleasePlan> {
+pub fn plan_custom(
+    cargo_toml_path: impl AsRef<Path>,
+    new_version: &str,
+    scope: VersionScope,
+) -> Result<ReleasePlan> {
     let new_version = new_version.trim();
     if new_version.is_empty() {
         bail!("New version cannot be empty.");
     }
-    let old_version = read_cargo_package_version(cargo_toml_path.as_ref())?;
+    let tag = format!("v{}", new_version);
+    crate::git::validate_ref_name(crate::git::RefKind::Tag, &tag)?;
+    let source = version_source(cargo_toml_path.as_ref(), scope)?;
+    let old_version = read_version(&source)?;
     validate_semver_3(new_version).context("Invalid custom version")?;
     if old_version == new_version {
         bail!("New version matches current version: {}", new_version);
@@ -92,23 +410,121 @@ pub fn plan_custom(cargo_toml_path: impl AsRef<Path>, new_version: &str) -> Resu
     Ok(ReleasePlan {
         old_version,
         new_version: new_version.to_string(),
-        tag: format!("v{}", new_version),
+        tag,
+        source,
     })
 }
 
-/// Run preflight checks before modifying repository state.
-pub fn run_preflight(cfg: &PreflightConfig) -> Result<()> {
-    if cfg.fmt_check {
-        run_cmd_inherit("cargo", &["fmt", "--check"])
-            .context("Release preflight failed: cargo fmt --check")?;
+/// Where `scope`'s version lives for the package (or virtual workspace) at `manifest`.
+pub fn version_source(manifest: &Path, scope: VersionScope) -> Result<VersionSource> {
+    let doc = read_manifest(manifest)?;
+    let version = doc.get("package").and_then(|p| p.get("version"));
+    match scope {
+        VersionScope::Package if version.is_some_and(|v| v.as_str().is_some()) => {
+            Ok(VersionSource {
+                path: manifest.to_path_buf(),
+                table: VersionTable::Package,
+            })
+        }
+        VersionScope::Package if version.is_some_and(inherits_workspace) => {
+            workspace_version_source(manifest, &doc)
+        }
+        VersionScope::Package if doc.get("package").is_some() => bail!(
+            "{} has no version under [package] to bump.",
+            manifest.display()
+        ),
+        _ => workspace_version_source(manifest, &doc),
     }
-    if cfg.clippy_deny_warnings {
-        run_cmd_inherit("cargo", &["clippy", "--", "-D", "warnings"])
-            .context("Release preflight failed: cargo clippy -- -D warnings")?;
+}
+
+/// The workspace's version when the package at `manifest` has a version of its own: what
+/// `VersionScope::Workspace` would bump instead. None when there is no choice to make.
+pub fn workspace_alternative(manifest: &Path) -> Result<Option<VersionSource>> {
+    if version_source(manifest, VersionScope::Package)?.table != VersionTable::Package {
+        return Ok(None);
     }
-    if cfg.test_locked {
-        run_cmd_inherit("cargo", &["test", "--locked"])
-            .context("Release preflight failed: cargo test --locked")?;
+    Ok(version_source(manifest, VersionScope::Workspace).ok())
+}
+
+/// `[workspace.package]` of the workspace `doc` (read from `manifest`) belongs to: the
+/// manifest itself when it has `[workspace]`, else `package.workspace` or the nearest
+/// parent directory in the repository with one.
+fn workspace_version_source(manifest: &Path, doc: &DocumentMut) -> Result<VersionSource> {
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let root = if doc.get("workspace").is_some() {
+        Some((manifest.to_path_buf(), doc.clone()))
+    } else if let Some(path) = doc
+        .get("package")
+        .and_then(|p| p.get("workspace"))
+        .and_then(Item::as_str)
+    {
+        let root = dir.join(path).join(MANIFEST_FILE);
+        Some((root.clone(), read_manifest(&root)?))
+    } else {
+        let repo = crate::git::repo_root().ok();
+        let mut found = None;
+        for parent in dir.ancestors().skip(1) {
+            if repo
+                .as_deref()
+                .is_some_and(|repo| !parent.starts_with(repo))
+            {
+                break;
+            }
+            let candidate = parent.join(MANIFEST_FILE);
+            if !candidate.is_file() {
+                continue;
+            }
+            let doc = read_manifest(&candidate)?;
+            if doc.get("workspace").is_some() {
+                found = Some((candidate, doc));
+                break;
+            }
+        }
+        found
+    };
+    let Some((path, doc)) = root else {
+        bail!(
+            "{} is not part of a workspace, so there is no workspace version to bump.",
+            manifest.display()
+        );
+    };
+    if doc
+        .get("workspace")
+        .and_then(|w| w.get("package"))
+        .and_then(|p| p.get("version"))
+        .and_then(Item::as_str)
+        .is_none()
+    {
+        bail!(
+            "{} has no version under [workspace.package] to bump.",
+            path.display()
+        );
+    }
+    Ok(VersionSource {
+        path,
+        table: VersionTable::WorkspacePackage,
+    })
+}
+
+/// `version.workspace = true`
+fn inherits_workspace(version: &Item) -> bool {
+    version
+        .get("workspace")
+        .and_then(Item::as_bool)
+        .unwrap_or_default()
+}
+
+/// Run preflight checks before modifying repository state.
+pub fn run_preflight(cfg: &PreflightConfig) -> Result<()> {
+    for check in cfg.checks() {
+        if cfg.is_skipped(&check) {
+            continue;
+        }
+        let Some((program, args)) = check.command.split_first() else {
+            continue;
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_cmd_inherit(program, &args).context("Release preflight failed")?;
     }
     Ok(())
 }
@@ -117,8 +533,19 @@ pub fn run_preflight(cfg: &PreflightConfig) -> Result<()> {
 pub fn assert_release_guardrails(cfg: &ReleaseGuardrailConfig) -> Result<()> {
     ensure_git_repo()?;
     ensure_remote_exists(&cfg.remote)?;
+    ensure_no_operation_in_progress()?;
     ensure_clean_working_tree()?;
 
+    if let Some(commit) = crate::git::detached_head()? {
+        bail!(
+            "Refusing to release: HEAD is detached at {} (not on a branch), so the release \
+             commit would belong to no branch. Check out {} first.",
+            commit,
+            cfg.expected_branch
+                .as_deref()
+                .unwrap_or("the release branch")
+        );
+    }
     if let Some(expected) = &cfg.expected_branch {
         let branch = current_branch()?;
         if branch != *expected {
@@ -130,25 +557,128 @@ pub fn assert_release_guardrails(cfg: &ReleaseGuardrailConfig) -> Result<()> {
         }
     }
 
+    if cfg.require_upstream && crate::git::snapshot()?.upstream.is_none() {
+        let branch = current_branch()?;
+        bail!(
+            "Refusing to release: branch '{}' has no upstream. Run `git push -u {} {}` first.",
+            branch,
+            cfg.remote,
+            branch
+        );
+    }
+
+    Ok(())
+}
+
+/// Preview note for a branch without upstream (`None` when an upstream exists).
+///
+/// The release itself only pushes the tag; the upstream is created by the next branch push.
+pub fn upstream_note(cfg: &ReleaseGuardrailConfig) -> Result<Option<String>> {
+    let snapshot = crate::git::snapshot()?;
+    if snapshot.upstream.is_some() {
+        return Ok(None);
+    }
+    let branch = snapshot.branch.unwrap_or_else(|| "HEAD".to_string());
+    if cfg.require_upstream {
+        return Ok(Some(format!(
+            "Branch '{}' has no upstream and `release_require_upstream` is set — the release \
+             will be refused. Run `git push -u {} {}` first.",
+            branch, cfg.remote, branch
+        )));
+    }
+    Ok(Some(format!(
+        "Branch '{}' has no upstream — it will be created as {}/{} on push.",
+        branch, cfg.remote, branch
+    )))
+}
+
+/// Check the rough shape of a git remote URL: `https://host/path`, `ssh://…`, `git://…`
+/// or scp-like `[user@]host:path`.
+pub fn validate_remote_url(url: &str) -> Result<()> {
+    let url = url.trim();
+    if url.is_empty() {
+        bail!("Remote URL cannot be empty.");
+    }
+    if url.chars().any(char::is_whitespace) {
+        bail!("Remote URL cannot contain spaces.");
+    }
+
+    let looks_valid = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            matches!(scheme, "https" | "http" | "ssh" | "git")
+                && rest
+                    .split_once('/')
+                    .is_some_and(|(host, path)| !host.is_empty() && !path.is_empty())
+        }
+        None => url.split_once(':').is_some_and(|(host, path)| {
+            !host.is_empty() && !host.contains('/') && !path.is_empty()
+        }),
+    };
+    if !looks_valid {
+        bail!(
+            "'{}' does not look like a git URL (e.g. https://github.com/owner/repo.git or git@github.com:owner/repo.git).",
+            url
+        );
+    }
+    Ok(())
+}
+
+/// Add `remote` pointing at `url`, then check it answers `git ls-remote`.
+///
+/// If the remote is not reachable it is removed again, leaving the repository unchanged.
+pub fn add_remote(remote: &str, url: &str) -> Result<()> {
+    read_only::check("Adding a remote")?;
+    validate_remote_url(url)?;
+
+    let out = run_git_output(&["remote", "add", remote, url.trim()])?;
+    if !out.status.success() {
+        bail!(
+            "git remote add failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+
+    let out = crate::git::command()
+        .args(["ls-remote", "--heads", remote])
+        // Never block on a credential prompt.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git ls-remote")?;
+    if !out.status.success() {
+        let _ = run_git_output(&["remote", "remove", remote]);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        bail!(
+            "Remote '{}' is not reachable, so it was not added: {}",
+            remote,
+            stderr.lines().next().unwrap_or_default().trim()
+        );
+    }
     Ok(())
 }
 
-/// Apply the version bump to `Cargo.toml` and refresh lockfile (best-effort).
+/// Apply the version bump to `source` and refresh the lockfile.
 ///
 /// This only updates files; it does not commit, tag, or push.
 pub fn apply_version_bump(
-    cargo_toml_path: impl AsRef<Path>,
+    source: &VersionSource,
     old_version: &str,
     new_version: &str,
 ) -> Result<()> {
-    update_cargo_version_in_toml(cargo_toml_path.as_ref(), old_version, new_version)?;
+    read_only::check("Bumping the version")?;
+    write_version(source, old_version, new_version)?;
     // Avoid `cargo update` during releases; just ensure lockfile exists.
-    let _ = run_cmd_inherit("cargo", &["generate-lockfile"]);
+    run_cmd_inherit("cargo", &["generate-lockfile"]).with_context(|| {
+        format!(
+            "Bumped {} to {}, but the lockfile could not be updated",
+            source, new_version
+        )
+    })?;
     Ok(())
 }
 
 /// Stage all changes (intended for staging the release bump).
 pub fn stage_all() -> Result<()> {
+    read_only::check("Staging")?;
     ensure_git_repo()?;
     let out = run_git_output(&["add", "-A"])?;
     if !out.status.success() {
@@ -160,42 +690,124 @@ pub fn stage_all() -> Result<()> {
     Ok(())
 }
 
-/// Commit with a multi-line message reliably using `git commit -F <tempfile>`.
+/// Commit with a multi-line message (`git commit -F -`), signed like every other commit
+/// (see `repo_config::commit_options`).
 pub fn commit_with_message(message: &str) -> Result<()> {
     ensure_git_repo()?;
-    crate::git::commit_changes(message)
+    crate::git::commit_changes(message, crate::repo_config::commit_options()?, &mut |_| {})
 }
 
-/// Create an annotated tag `tag` with message `tag_message`.
-pub fn create_annotated_tag(tag: &str, tag_message: &str) -> Result<()> {
+/// Create an annotated tag `tag` with message `tag_message`, GPG-signed and verified with
+/// `options.sign`. gpg may ask for a passphrase on the terminal, so the TUI calls this
+/// with the TUI suspended.
+pub fn create_annotated_tag(tag: &str, tag_message: &str, options: &TagConfig) -> Result<()> {
+    read_only::check("Tagging")?;
     ensure_git_repo()?;
     let tag = tag.trim();
-    if tag.is_empty() {
-        bail!("Tag cannot be empty.");
+    crate::git::validate_ref_name(crate::git::RefKind::Tag, tag)?;
+
+    if !options.sign {
+        let out = run_git_output(&["tag", "-a", tag, "-m", tag_message])?;
+        if !out.status.success() {
+            bail!(
+                "git tag -a {} failed: {}",
+                tag,
+                String::from_utf8_lossy(&out.stderr)
+            );
+        }
+        return Ok(());
+    }
+
+    let mut args = vec!["tag", "-s"];
+    if let Some(key) = options
+        .signing_key
+        .as_deref()
+        .filter(|k| !k.trim().is_empty())
+    {
+        args.extend(["-u", key.trim()]);
+    }
+    args.extend([tag, "-m", tag_message]);
+    let out = crate::git::command()
+        .args(&args)
+        .stdin(Stdio::inherit())
+        .output()
+        .context("Failed to run git tag -s")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if crate::git::is_signing_failure(&stderr) {
+            bail!(
+                "Signing tag {} failed, so it was not created. git said:\n\n{}\n\n{}",
+                tag,
+                stderr.trim(),
+                tag_signing_help(&stderr, options)
+            );
+        }
+        bail!("git tag -s {} failed: {}", tag, stderr.trim());
     }
 
-    let out = run_git_output(&["tag", "-a", tag, "-m", tag_message])?;
+    let out = run_git_output(&["tag", "-v", tag])?;
     if !out.status.success() {
+        // An unverifiable signature is no better than none: leave no tag behind.
+        let _ = run_git_output(&["tag", "-d", tag]);
         bail!(
-            "git tag -a {} failed: {}",
+            "Tag {} was signed but `git tag -v` could not verify it, so it was deleted. \
+             git said:\n\n{}\n\nImport the public half of the signing key \
+             (`gpg --import`) and check that it isn't expired.",
             tag,
-            String::from_utf8_lossy(&out.stderr)
+            String::from_utf8_lossy(&out.stderr).trim()
         );
     }
     Ok(())
 }
 
+/// What to do about a signature gpg refused, from git's stderr.
+fn tag_signing_help(stderr: &str, options: &TagConfig) -> String {
+    let stderr = stderr.to_ascii_lowercase();
+    let key = match &options.signing_key {
+        Some(key) => format!("`{}` (tag.signing_key)", key),
+        None => "`user.signingkey`".to_string(),
+    };
+    if ["no secret key", "secret key not available", "skipped"]
+        .iter()
+        .any(|m| stderr.contains(m))
+    {
+        format!(
+            "gpg has no secret key for {}: `gpg --list-secret-keys --keyid-format long` lists \
+             the ones you have.",
+            key
+        )
+    } else if [
+        "inappropriate ioctl",
+        "pinentry",
+        "agent",
+        "cancel",
+        "timeout",
+    ]
+    .iter()
+    .any(|m| stderr.contains(m))
+    {
+        "gpg could not ask for the passphrase. Unlock the key first \
+         (`echo test | gpg --clearsign` prompts for it) or `export GPG_TTY=$(tty)`, then retry."
+            .to_string()
+    } else {
+        format!(
+            "Check that {} names a key you have and that gpg-agent can unlock it \
+             (`echo test | gpg --clearsign` shows the same error), or turn off `tag.sign`.",
+            key
+        )
+    }
+}
+
 /// Push a single tag to the remote (e.g., `origin vX.Y.Z`) to trigger CI.
 pub fn push_tag(remote: &str, tag: &str) -> Result<()> {
+    read_only::check("Pushing tags")?;
     ensure_git_repo()?;
     let remote = remote.trim();
     let tag = tag.trim();
     if remote.is_empty() {
         bail!("Remote cannot be empty.");
     }
-    if tag.is_empty() {
-        bail!("Tag cannot be empty.");
-    }
+    crate::git::validate_ref_name(crate::git::RefKind::Tag, tag)?;
 
     let out = run_git_output(&["push", remote, tag])?;
     if !out.status.success() {
@@ -247,37 +859,145 @@ pub fn tag_exists_remote(remote: &str, tag: &str) -> Result<bool> {
     Ok(!String::from_utf8_lossy(&out.stdout).trim().is_empty())
 }
 
+/// CHANGELOG.md section for `plan`: the commits since the previous `v*` tag, grouped by
+/// Conventional Commit type under a `## [x.y.z] - YYYY-MM-DD` heading.
+pub fn generate_changelog_section(plan: &ReleasePlan) -> Result<String> {
+    let release = release_notes::unreleased_commits(&plan.tag)?;
+    let mut out = format!("## [{}] - {}\n", plan.new_version, today());
+    let groups = release_notes::grouped(&release);
+    if groups.is_empty() {
+        out.push_str("\n- No notable changes.\n");
+    }
+    for (title, entries) in groups {
+        out.push_str(&format!("\n### {}\n\n", title));
+        for entry in entries {
+            out.push_str(&format!("- {}\n", entry));
+        }
+    }
+    Ok(out)
+}
+
+/// Ask the model to rewrite the draft section; the commit bodies are passed along.
+#[cfg(feature = "cli")]
+pub async fn polish_changelog_section(
+    generator: &Generator,
+    plan: &ReleasePlan,
+    draft: &str,
+) -> Result<String> {
+    let release = release_notes::unreleased_commits(&plan.tag)?;
+    let prompt = release_notes::polish_input(&release, draft);
+    let polished = generator.complete(CHANGELOG_PROMPT, &prompt).await?.message;
+    let polished = polished.trim();
+    if polished.is_empty() {
+        bail!("The model returned an empty changelog section");
+    }
+    // The heading is ours, whatever the model did with it.
+    let heading = draft.lines().next().unwrap_or_default();
+    let body = match polished.split_once('\n') {
+        Some((first, rest)) if first.starts_with("## ") => rest.trim(),
+        _ => polished,
+    };
+    Ok(format!("{}\n\n{}\n", heading, body))
+}
+
+/// Insert `section` above the newest entry of CHANGELOG.md in the repository root,
+/// creating the file with a `# Changelog` title if needed. Returns its path.
+pub fn insert_changelog_section(section: &str) -> Result<PathBuf> {
+    read_only::check("Writing the changelog")?;
+    let path = crate::git::repo_root()?.join(CHANGELOG_FILE);
+    let existing = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "# Changelog\n".to_string(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    let section = format!("{}\n\n", section.trim_end());
+    // Sections are `## ...` headings; anything above the first one is the preamble.
+    let at = existing
+        .match_indices("## ")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || existing[..i].ends_with('\n'));
+    let updated = match at {
+        Some(i) => format!("{}{}{}", &existing[..i], section, &existing[i..]),
+        None => format!("{}\n\n{}", existing.trim_end(), section.trim_end()) + "\n",
+    };
+    fs::write(&path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
 /// Run the complete tag-based release pipeline locally (safe defaults).
 ///
 /// Steps:
 /// 1) guardrails (repo, remote, clean tree, expected branch)
 /// 2) preflight checks
-/// 3) update Cargo.toml + generate lockfile
+/// 3) update Cargo.toml + generate lockfile, and CHANGELOG.md when `changelog` is given
 /// 4) stage + commit
 /// 5) collision checks
-/// 6) create annotated tag + push tag
+/// 6) create annotated tag (signed with `tags.sign`) + push tag
 ///
 /// This is intended to trigger GitHub Actions which builds releases and publishes to crates.io.
 ///
-/// `commit_message` should be a full multi-line commit message.
+/// `commit_message` should be a full multi-line commit message; skipped preflight checks
+/// are noted at the end of its body. `changelog` is the section to insert into
+/// CHANGELOG.md (see `generate_changelog_section`).
+///
+/// A failure after the bump is returned as `IncompleteRelease`, which `rollback_release`
+/// can undo.
 pub fn run_tag_release(
-    cargo_toml_path: impl AsRef<Path>,
     plan: &ReleasePlan,
     commit_message: &str,
+    changelog: Option<&str>,
     preflight: &PreflightConfig,
     guards: &ReleaseGuardrailConfig,
+    tags: &TagConfig,
 ) -> Result<()> {
+    read_only::check("Releasing")?;
     assert_release_guardrails(guards)?;
     run_preflight(preflight)?;
 
-    // Apply bump + stage + commit
-    apply_version_bump(
-        cargo_toml_path.as_ref(),
-        &plan.old_version,
-        &plan.new_version,
-    )?;
+    let commit_message = match preflight.skipped_note() {
+        Some(note) => format!("{}\n\n{}", commit_message.trim_end(), note),
+        None => commit_message.to_string(),
+    };
+    let head_before = head_commit()?;
+    let files = release_files(&plan.source, changelog.is_some())?;
+    let mut done = None;
+    release_steps(plan, &commit_message, changelog, guards, tags, &mut done).map_err(|e| match done
+    {
+        Some(step) => IncompleteRelease {
+            tag: plan.tag.clone(),
+            remote: guards.remote.clone(),
+            step,
+            cause: format!("{:#}", e),
+            head_before,
+            files,
+        }
+        .into(),
+        None => e,
+    })
+}
+
+/// The steps of `run_tag_release` that change the repository; `done` is the last one
+/// that went through.
+fn release_steps(
+    plan: &ReleasePlan,
+    commit_message: &str,
+    changelog: Option<&str>,
+    guards: &ReleaseGuardrailConfig,
+    tags: &TagConfig,
+    done: &mut Option<ReleaseStep>,
+) -> Result<()> {
+    // Apply bump + stage + commit. Marked first: a lockfile that fails to update after
+    // the manifest was written still leaves files to roll back.
+    *done = Some(ReleaseStep::Bumped);
+    apply_version_bump(&plan.source, &plan.old_version, &plan.new_version)?;
+    if let Some(section) = changelog {
+        insert_changelog_section(section)?;
+    }
     stage_all()?;
     commit_with_message(commit_message)?;
+    *done = Some(ReleaseStep::Committed);
 
     // Tag collision checks
     if tag_exists_local(&plan.tag)? {
@@ -291,14 +1011,124 @@ pub fn run_tag_release(
         );
     }
 
-    create_annotated_tag(&plan.tag, &format!("Release {}", plan.tag))?;
+    create_annotated_tag(&plan.tag, &format!("Release {}", plan.tag), tags)?;
+    *done = Some(ReleaseStep::Tagged);
     push_tag(&guards.remote, &plan.tag)?;
 
     Ok(())
 }
 
+/// Undo what an incomplete release did, newest step first: delete the local tag, drop
+/// the release commit (`git reset --soft`, or `--hard`), and put the manifests, lockfile
+/// and changelog back as they were. Returns one line per step undone.
+///
+/// Refuses to touch files changed since the release commit, and a HEAD that moved past it.
+pub fn rollback_release(release: &IncompleteRelease, mode: ResetMode) -> Result<Vec<String>> {
+    read_only::check("Rolling back the release")?;
+    ensure_git_repo()?;
+    let paths: Vec<&Path> = release.files.iter().map(|(p, _)| p.as_path()).collect();
+
+    let head_before = if release.step >= ReleaseStep::Committed {
+        let Some(head_before) = &release.head_before else {
+            bail!("The release commit is the branch's first commit; remove it by hand.");
+        };
+        let parent = run_git_output(&["rev-parse", "--verify", "-q", "HEAD~1"])?;
+        if String::from_utf8_lossy(&parent.stdout).trim() != head_before {
+            bail!("HEAD moved past the release commit; roll back by hand.");
+        }
+        let mut args = vec!["diff", "--quiet", "--"];
+        args.extend(paths.iter().filter_map(|p| p.to_str()));
+        if !run_git_output(&args)?.status.success() {
+            bail!("The release files changed since the release commit; roll back by hand.");
+        }
+        Some(head_before.as_str())
+    } else {
+        None
+    };
+
+    let mut undone = Vec::new();
+    if release.step >= ReleaseStep::Tagged {
+        let out = run_git_output(&["tag", "-d", &release.tag])?;
+        if !out.status.success() {
+            bail!(
+                "git tag -d {} failed: {}",
+                release.tag,
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        undone.push(format!("Deleted the local tag {}.", release.tag));
+    }
+    if let Some(head_before) = head_before {
+        let flag = match mode {
+            ResetMode::Soft => "--soft",
+            ResetMode::Hard => "--hard",
+        };
+        let out = run_git_output(&["reset", "-q", flag, head_before])?;
+        if !out.status.success() {
+            bail!(
+                "git reset {} failed: {}",
+                flag,
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        undone.push(format!("Dropped the release commit (git reset {}).", flag));
+    }
+
+    // Unstage the bump, then write the files back (or remove the ones it created).
+    let mut args = vec!["reset", "-q", head_before.unwrap_or("HEAD"), "--"];
+    args.extend(paths.iter().filter_map(|p| p.to_str()));
+    if release.head_before.is_some() {
+        let out = run_git_output(&args)?;
+        if !out.status.success() {
+            bail!(
+                "Could not unstage the release files: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+    }
+    let root = crate::git::repo_root()?;
+    let mut restored = Vec::new();
+    for (path, content) in &release.files {
+        match content {
+            Some(content) => fs::write(path, content),
+            None => match fs::remove_file(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            },
+        }
+        .with_context(|| format!("Failed to restore {}", path.display()))?;
+        restored.push(
+            path.strip_prefix(&root)
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+        );
+    }
+    undone.push(format!("Restored {}.", restored.join(", ")));
+    Ok(undone)
+}
+
 /* ----------------------------- helpers ----------------------------- */
 
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 fn ensure_git_repo() -> Result<()> {
     if crate::git::is_repo() {
         Ok(())
@@ -317,23 +1147,65 @@ fn ensure_remote_exists(remote: &str) -> Result<()> {
     if out.status.success() {
         Ok(())
     } else {
-        bail!("No '{}' remote found. Add it first.", remote);
+        Err(MissingRemote {
+            remote: remote.to_string(),
+        }
+        .into())
     }
 }
 
-fn ensure_clean_working_tree() -> Result<()> {
-    let out = run_git_output(&["status", "--porcelain"])?;
-    if !out.status.success() {
-        bail!(
-            "git status failed: {}",
-            String::from_utf8_lossy(&out.stderr)
-        );
+/// Refuse during a merge, rebase, cherry-pick or revert, or with conflicts: the release
+/// commit would land in the middle of it. Checked up front by the UIs, before the prompts.
+pub fn ensure_no_operation_in_progress() -> Result<()> {
+    match crate::git::repo_state()?.describe() {
+        Some(problem) => bail!("Refusing to release: {}.", problem),
+        None => Ok(()),
     }
-    if out.stdout.is_empty() {
+}
+
+fn ensure_clean_working_tree() -> Result<()> {
+    if crate::git::snapshot()?.is_clean() {
         Ok(())
     } else {
-        bail!("Working tree is not clean. Commit or stash your changes before releasing.");
+        Err(DirtyWorkingTree.into())
+    }
+}
+
+/// HEAD's commit, `None` on an unborn branch.
+fn head_commit() -> Result<Option<String>> {
+    let out = run_git_output(&["rev-parse", "--verify", "-q", "HEAD"])?;
+    Ok(out
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string()))
+}
+
+/// The files a release writes (the version's manifest, the lockfile, the changelog when
+/// there is one) with their current content, `None` for those that don't exist yet.
+fn release_files(
+    source: &VersionSource,
+    changelog: bool,
+) -> Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+    let root = crate::git::repo_root()?;
+    let mut paths = vec![source.path.clone(), root.join("Cargo.lock")];
+    if let Some(dir) = source.path.parent() {
+        paths.push(dir.join("Cargo.lock"));
+    }
+    if changelog {
+        paths.push(root.join(CHANGELOG_FILE));
+    }
+    paths.dedup();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let content = match fs::read(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        files.push((path, content));
     }
+    Ok(files)
 }
 
 fn current_branch() -> Result<String> {
@@ -348,107 +1220,82 @@ fn current_branch() -> Result<String> {
 }
 
 fn run_git_output(args: &[&str]) -> Result<Output> {
-    Command::new("git")
+    crate::git::command()
         .args(args)
         .output()
         .with_context(|| format!("Failed to run git {}", args.join(" ")))
 }
 
+/// Run `cmd` at the work tree root, so cargo picks the manifest the release bumps.
 fn run_cmd_inherit(cmd: &str, args: &[&str]) -> Result<ExitStatus> {
+    let line = [&[cmd], args].concat().join(" ");
     Command::new(cmd)
+        .current_dir(crate::git::repo_root()?)
         .args(args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .status()
-        .with_context(|| format!("Failed to run {} {}", cmd, args.join(" ")))
+        .with_context(|| format!("Failed to run {}", line))
         .and_then(|s| {
             if s.success() {
                 Ok(s)
             } else {
-                bail!("Command failed: {} {}", cmd, args.join(" "));
+                bail!("`{}` exited with {}", line, s);
             }
         })
 }
 
-fn read_cargo_package_version(path: &Path) -> Result<String> {
+fn read_manifest(path: &Path) -> Result<DocumentMut> {
     let content =
         fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
-
-    // naive but reliable for common Cargo.toml layouts:
-    // [package]
-    // version = "x.y.z"
-    //
-    // NOTE: This does not parse TOML properly; it's a deliberate minimal dependency.
-    // If you later want correctness for workspaces and non-standard formatting,
-    // replace with `toml_edit`.
-    let mut in_package = false;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            in_package = trimmed == "[package]";
-            continue;
-        }
-
-        if in_package
-            && trimmed.starts_with("version")
-            && trimmed.contains('=')
-            && trimmed.contains('"')
-        {
-            if let Some(start) = trimmed.find('"') {
-                if let Some(end) = trimmed[start + 1..].find('"') {
-                    return Ok(trimmed[start + 1..start + 1 + end].to_string());
-                }
-            }
-        }
-    }
-
-    bail!("Failed to locate [package] version in {}", path.display())
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))
 }
 
-fn update_cargo_version_in_toml(path: &Path, old: &str, new: &str) -> Result<()> {
-    let content =
-        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
-    let mut out = String::new();
-    let mut in_package = false;
-    let mut replaced = false;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            in_package = trimmed == "[package]";
-            out.push_str(line);
-            out.push('\n');
-            continue;
-        }
-
-        if in_package
-            && !replaced
-            && trimmed.starts_with("version")
-            && line.contains(&format!("\"{}\"", old))
-        {
-            out.push_str(&line.replace(&format!("\"{}\"", old), &format!("\"{}\"", new)));
-            out.push('\n');
-            replaced = true;
-            continue;
-        }
+/// The `version` item of `table` in `doc`.
+fn version_item(doc: &mut DocumentMut, table: VersionTable) -> Option<&mut Item> {
+    let package = match table {
+        VersionTable::Package => doc.get_mut("package")?,
+        VersionTable::WorkspacePackage => doc.get_mut("workspace")?.get_mut("package")?,
+    };
+    package.get_mut("version")
+}
 
-        out.push_str(line);
-        out.push('\n');
+fn read_version(source: &VersionSource) -> Result<String> {
+    let mut doc = read_manifest(&source.path)?;
+    match version_item(&mut doc, source.table).and_then(|v| v.as_str()) {
+        Some(version) => Ok(version.to_string()),
+        None => bail!(
+            "Failed to locate {} version in {}",
+            source.table,
+            source.path.display()
+        ),
     }
+}
 
-    if !replaced {
+/// Replace `old` with `new` in place, so comments and layout around it stay as they are.
+fn write_version(source: &VersionSource, old: &str, new: &str) -> Result<()> {
+    let path = &source.path;
+    let mut doc = read_manifest(path)?;
+    let Some(value) = version_item(&mut doc, source.table)
+        .and_then(Item::as_value_mut)
+        .filter(|v| v.as_str() == Some(old))
+    else {
         bail!(
-            "Failed to update version in {} (did not find version = \"{}\" under [package])",
+            "Failed to update version in {} (did not find version = \"{}\" under {})",
             path.display(),
-            old
+            old,
+            source.table
         );
-    }
+    };
+    let decor = value.decor().clone();
+    *value = Value::from(new);
+    *value.decor_mut() = decor;
 
-    fs::write(path, out).with_context(|| format!("Failed to write {}", path.display()))?;
+    fs::write(path, doc.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
     Ok(())
 }
 
@@ -491,3 +1338,230 @@ fn bump_semver(current: &str, bump: BumpKind) -> Result<String> {
 
     Ok(format!("{}.{}.{}", major, minor, patch))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRepo;
+
+    #[test]
+    fn release_without_upstream_is_allowed_unless_required() {
+        let repo = TempRepo::new().with_origin();
+        repo.write("README.md", "hello\n");
+        repo.commit_all("init");
+
+        let permissive = ReleaseGuardrailConfig::default();
+        assert_release_guardrails(&permissive).unwrap();
+        let note = upstream_note(&permissive).unwrap().unwrap();
+        assert!(note.contains("created as origin/master"), "{note}");
+
+        let strict = ReleaseGuardrailConfig {
+            require_upstream: true,
+            ..permissive
+        };
+        let err = assert_release_guardrails(&strict).unwrap_err();
+        assert!(err.to_string().contains("has no upstream"), "{err}");
+        let note = upstream_note(&strict).unwrap().unwrap();
+        assert!(note.contains("will be refused"), "{note}");
+
+        repo.git(&["push", "-q", "-u", "origin", "master"]);
+        assert_release_guardrails(&strict).unwrap();
+        assert_eq!(upstream_note(&strict).unwrap(), None);
+    }
+    const WORKSPACE_ROOT: &str = "\
+# The workspace.
+[workspace]
+members = [\"crates/app\", \"crates/lib\"]
+
+[workspace.package]
+version = \"1.2.3\" # shared by the members
+edition = \"2021\"
+";
+
+    /// A workspace whose `app` inherits the version and whose `lib` has its own.
+    fn workspace_repo() -> TempRepo {
+        let repo = TempRepo::new();
+        repo.write("Cargo.toml", WORKSPACE_ROOT);
+        repo.write(
+            "crates/app/Cargo.toml",
+            "[package]\nname = \"app\"\nversion.workspace = true\n",
+        );
+        repo.write(
+            "crates/lib/Cargo.toml",
+            "[package]\nname = \"lib\"\nversion = \"0.4.0\"  # its own\n",
+        );
+        repo.commit_all("init");
+        repo
+    }
+
+    #[test]
+    fn inherited_version_bumps_the_workspace() {
+        let repo = workspace_repo();
+        let app = repo.path("crates/app/Cargo.toml");
+        assert_eq!(workspace_alternative(&app).unwrap(), None);
+
+        let plan = plan_bump(&app, BumpKind::Minor, VersionScope::Package).unwrap();
+        assert_eq!(plan.source.path, repo.path("Cargo.toml"));
+        assert_eq!(plan.source.table, VersionTable::WorkspacePackage);
+        assert_eq!(
+            (plan.old_version.as_str(), plan.new_version.as_str()),
+            ("1.2.3", "1.3.0")
+        );
+
+        write_version(&plan.source, &plan.old_version, &plan.new_version).unwrap();
+        assert_eq!(
+            fs::read_to_string(repo.path("Cargo.toml")).unwrap(),
+            WORKSPACE_ROOT.replace("1.2.3", "1.3.0")
+        );
+        assert_eq!(repo.git(&["status", "--porcelain"]), "M Cargo.toml");
+    }
+
+    #[test]
+    fn own_version_bumps_the_package_unless_the_workspace_is_chosen() {
+        let repo = workspace_repo();
+        let lib = repo.path("crates/lib/Cargo.toml");
+        let workspace = VersionSource {
+            path: repo.path("Cargo.toml"),
+            table: VersionTable::WorkspacePackage,
+        };
+        assert_eq!(
+            workspace_alternative(&lib).unwrap(),
+            Some(workspace.clone())
+        );
+
+        let plan = plan_bump(&lib, BumpKind::Patch, VersionScope::Package).unwrap();
+        assert_eq!(plan.source.path, lib);
+        assert_eq!(plan.new_version, "0.4.1");
+        write_version(&plan.source, &plan.old_version, &plan.new_version).unwrap();
+        assert_eq!(
+            fs::read_to_string(&lib).unwrap(),
+            "[package]\nname = \"lib\"\nversion = \"0.4.1\"  # its own\n"
+        );
+
+        let plan = plan_bump(&lib, BumpKind::Major, VersionScope::Workspace).unwrap();
+        assert_eq!(plan.source, workspace);
+        assert_eq!(plan.new_version, "2.0.0");
+    }
+
+    #[test]
+    fn virtual_root_bumps_its_workspace_package() {
+        let repo = workspace_repo();
+        let plan = plan_bump(
+            repo.path("Cargo.toml"),
+            BumpKind::Patch,
+            VersionScope::Package,
+        )
+        .unwrap();
+        assert_eq!(plan.source.table, VersionTable::WorkspacePackage);
+        assert_eq!(plan.new_version, "1.2.4");
+    }
+    /// A released-before package on `master`, pushed to `origin`, and a plan for 0.1.1.
+    fn release_repo() -> (TempRepo, ReleasePlan) {
+        let repo = TempRepo::new().with_origin();
+        repo.write(
+            "Cargo.toml",
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        );
+        repo.write("src/lib.rs", "");
+        repo.write(".gitignore", "target/\n");
+        repo.commit_all("init");
+        repo.git(&["push", "-q", "-u", "origin", "master"]);
+        let plan = plan_bump(
+            repo.path("Cargo.toml"),
+            BumpKind::Patch,
+            VersionScope::Package,
+        )
+        .unwrap();
+        (repo, plan)
+    }
+
+    /// `run_tag_release` without preflight checks; it is expected to stop partway.
+    fn failed_release(plan: &ReleasePlan) -> IncompleteRelease {
+        let preflight = PreflightConfig {
+            fmt_check: false,
+            clippy_deny_warnings: false,
+            test_locked: false,
+            ..PreflightConfig::default()
+        };
+        let err = run_tag_release(
+            plan,
+            "chore(release): v0.1.1",
+            None,
+            &preflight,
+            &ReleaseGuardrailConfig::default(),
+            &TagConfig::default(),
+        )
+        .unwrap_err();
+        err.downcast::<IncompleteRelease>().unwrap()
+    }
+
+    #[test]
+    fn rollback_after_the_commit_keeps_the_bump_unstaged() {
+        let (repo, plan) = release_repo();
+        let head = repo.git(&["rev-parse", "HEAD"]);
+        // A leftover tag stops the release between the commit and the tag.
+        repo.git(&["tag", "v0.1.1"]);
+
+        let incomplete = failed_release(&plan);
+        assert_eq!(incomplete.step, ReleaseStep::Committed);
+        assert!(incomplete.cause.contains("already exists locally"));
+        assert_ne!(repo.git(&["rev-parse", "HEAD"]), head);
+
+        let undone = rollback_release(&incomplete, ResetMode::Soft).unwrap();
+        assert_eq!(undone.len(), 2, "{undone:?}");
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "");
+        // The tag was there before the release, so it stays.
+        assert_eq!(repo.git(&["tag", "--list"]), "v0.1.1");
+    }
+
+    #[test]
+    fn rollback_after_the_tag_deletes_it() {
+        let (repo, plan) = release_repo();
+        let head = repo.git(&["rev-parse", "HEAD"]);
+        let hook = repo.origin().join("hooks/pre-receive");
+        fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        crate::test_support::make_executable(&hook);
+
+        let incomplete = failed_release(&plan);
+        assert_eq!(incomplete.step, ReleaseStep::Tagged);
+        assert_eq!(repo.git(&["tag", "--list"]), "v0.1.1");
+
+        rollback_release(&incomplete, ResetMode::Hard).unwrap();
+        assert_eq!(repo.git(&["tag", "--list"]), "");
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "");
+        assert!(fs::read_to_string(repo.path("Cargo.toml"))
+            .unwrap()
+            .contains("version = \"0.1.0\""));
+    }
+
+    #[test]
+    fn rollback_refuses_once_head_moved_on() {
+        let (repo, plan) = release_repo();
+        repo.git(&["tag", "v0.1.1"]);
+        let incomplete = failed_release(&plan);
+        repo.write("later.txt", "more work\n");
+        let later = repo.commit_all("later");
+
+        let err = rollback_release(&incomplete, ResetMode::Soft).unwrap_err();
+        assert!(err.to_string().contains("HEAD moved"), "{err}");
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), later);
+    }
+
+    #[test]
+    fn invalid_custom_tags_are_refused_before_any_change() {
+        let (repo, _) = release_repo();
+        let manifest = repo.path("Cargo.toml");
+        let before = std::fs::read_to_string(&manifest).unwrap();
+        let head = repo.git(&["rev-parse", "HEAD"]);
+
+        let err = plan_custom(&manifest, "1.0 beta", VersionScope::Package).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid tag name 'v1.0 beta': tag names cannot contain spaces."
+        );
+        assert_eq!(std::fs::read_to_string(&manifest).unwrap(), before);
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+    }
+}
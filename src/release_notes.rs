@@ -0,0 +1,289 @@
+//! Release notes for a tag: gather the commits since the previous tag, group them by
+//! Conventional Commit type, optionally polish them with the generator, then hand the
+//! result to the clipboard, `RELEASE_NOTES.md` or `gh release edit`.
+//!
+//! Shared by the CLI (`git-wiz release notes <tag>`, end of `git-wiz release`) and the
+//! TUI Release tab.
+
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::generator::Generator;
+use crate::git;
+use crate::platform;
+use crate::read_only;
+
+/// File written by the "write to file" option, relative to the repository root.
+pub const NOTES_FILE: &str = "RELEASE_NOTES.md";
+
+/// How long to wait for CI to create the GitHub release before giving up on `gh`.
+const GH_RELEASE_WAIT: Duration = Duration::from_secs(60);
+const GH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const POLISH_PROMPT: &str = "You are a senior developer writing GitHub release notes. \
+    You get a draft grouped by commit type. Rewrite it as concise Markdown with the sections \
+    `## Features`, `## Fixes` and `## Other` (omit empty sections). Merge duplicates, drop \
+    release/version-bump chores, and keep every user-visible change. \
+    Only output the release notes, no preamble and no code fences.";
+
+/// One commit between the previous tag and the release tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteCommit {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Commits that make up a release, oldest first.
+#[derive(Debug, Clone)]
+pub struct ReleaseCommits {
+    pub tag: String,
+    /// `None` for the first release (everything up to `tag`).
+    pub previous: Option<String>,
+    pub commits: Vec<NoteCommit>,
+}
+
+/// Collect the commits in `previous_tag..tag`, skipping the release commit itself.
+pub fn commits_for(tag: &str) -> Result<ReleaseCommits> {
+    let tag = tag.trim();
+    let o = git_output(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", tag),
+    ])?;
+    if !o.status.success() {
+        bail!("Unknown tag: {}", tag);
+    }
+
+    let previous = previous_tag(tag)?;
+    let range = match &previous {
+        Some(prev) => format!("{}..{}", prev, tag),
+        None => tag.to_string(),
+    };
+    Ok(ReleaseCommits {
+        tag: tag.to_string(),
+        commits: log_commits(&range)?,
+        previous,
+    })
+}
+
+/// Collect the commits since the latest `v*` tag reachable from HEAD, for a release
+/// that will be tagged `tag` but isn't yet.
+pub fn unreleased_commits(tag: &str) -> Result<ReleaseCommits> {
+    let o = git_output(&["describe", "--tags", "--abbrev=0", "--match", "v*", "HEAD"])?;
+    let previous = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    let previous = (o.status.success() && !previous.is_empty()).then_some(previous);
+    let range = match &previous {
+        Some(prev) => format!("{}..HEAD", prev),
+        None => "HEAD".to_string(),
+    };
+    Ok(ReleaseCommits {
+        tag: tag.to_string(),
+        commits: log_commits(&range)?,
+        previous,
+    })
+}
+
+/// Commits in `range`, oldest first, without release commits.
+fn log_commits(range: &str) -> Result<Vec<NoteCommit>> {
+    let o = git_output(&["log", "--reverse", "--format=%s%x00%b%x1e", range])?;
+    if !o.status.success() {
+        bail!(
+            "git log {} failed: {}",
+            range,
+            String::from_utf8_lossy(&o.stderr)
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&o.stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let (subject, body) = record.trim_start_matches('\n').split_once('\0')?;
+            Some(NoteCommit {
+                subject: subject.trim().to_string(),
+                body: body.trim().to_string(),
+            })
+        })
+        .filter(|c| !c.subject.is_empty() && !c.subject.starts_with("chore(release)"))
+        .collect())
+}
+
+/// The closest tag reachable from `tag`'s parent.
+fn previous_tag(tag: &str) -> Result<Option<String>> {
+    let o = git_output(&["describe", "--tags", "--abbrev=0", &format!("{}^", tag)])?;
+    if !o.status.success() {
+        // No earlier tag (or `tag` is the root commit).
+        return Ok(None);
+    }
+    let prev = String::from_utf8_lossy(&o.stdout).trim().to_string();
+    Ok((!prev.is_empty()).then_some(prev))
+}
+
+/// Markdown notes grouped into Features / Fixes / Other, without any model involved.
+pub fn draft(release: &ReleaseCommits) -> String {
+    let mut out = match &release.previous {
+        Some(prev) => format!("Changes since {}.\n", prev),
+        None => "First release.\n".to_string(),
+    };
+    for (title, entries) in grouped(release) {
+        out.push_str(&format!("\n## {}\n\n", title));
+        for entry in entries {
+            out.push_str(&format!("- {}\n", entry));
+        }
+    }
+    out
+}
+
+/// The non-empty Features / Fixes / Other groups, each a list of subjects without their
+/// type prefix (`feat(cli): add x` -> `**cli:** add x`).
+pub fn grouped(release: &ReleaseCommits) -> Vec<(&'static str, Vec<String>)> {
+    let mut features = Vec::new();
+    let mut fixes = Vec::new();
+    let mut other = Vec::new();
+    for commit in &release.commits {
+        let entry = strip_type(&commit.subject);
+        match conventional_type(&commit.subject) {
+            Some("feat") => features.push(entry),
+            Some("fix") => fixes.push(entry),
+            _ => other.push(entry),
+        }
+    }
+    [("Features", features), ("Fixes", fixes), ("Other", other)]
+        .into_iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .collect()
+}
+
+/// `feat(cli)!: x` -> `feat`. Only recognizes lowercase Conventional Commit prefixes.
+fn conventional_type(subject: &str) -> Option<&str> {
+    let (prefix, _) = subject.split_once(':')?;
+    let ty = prefix.split('(').next()?.trim_end_matches('!');
+    (!ty.is_empty() && ty.chars().all(|c| c.is_ascii_lowercase())).then_some(ty)
+}
+
+/// `feat(cli): add x` -> `**cli:** add x`; other subjects are kept as written.
+fn strip_type(subject: &str) -> String {
+    if conventional_type(subject).is_none() {
+        return subject.to_string();
+    }
+    let (prefix, rest) = subject.split_once(':').unwrap_or_default();
+    let rest = rest.trim();
+    match prefix
+        .split_once('(')
+        .and_then(|(_, scope)| scope.split_once(')'))
+    {
+        Some((scope, _)) if !scope.is_empty() => format!("**{}:** {}", scope, rest),
+        _ => rest.to_string(),
+    }
+}
+
+/// Ask the model to rewrite the draft; the commit bodies are passed along for context.
+pub async fn polish(
+    generator: &Generator,
+    release: &ReleaseCommits,
+    draft: &str,
+) -> Result<String> {
+    let prompt = polish_input(release, draft);
+    let notes = generator.complete(POLISH_PROMPT, &prompt).await?.message;
+    if notes.trim().is_empty() {
+        bail!("The model returned empty release notes");
+    }
+    Ok(notes)
+}
+
+/// `draft` for `release`, followed by the bodies of the commits that have one.
+pub fn polish_input(release: &ReleaseCommits, draft: &str) -> String {
+    let mut prompt = format!("Release {}\n\n{}", release.tag, draft);
+    let details: Vec<String> = release
+        .commits
+        .iter()
+        .filter(|c| !c.body.is_empty())
+        .map(|c| format!("{}\n{}", c.subject, c.body))
+        .collect();
+    if !details.is_empty() {
+        prompt.push_str("\n\nCommit details:\n\n");
+        prompt.push_str(&details.join("\n\n"));
+    }
+    prompt
+}
+
+/// Write `notes` to `RELEASE_NOTES.md` in the repository root and return its path.
+pub fn write_file(notes: &str) -> Result<PathBuf> {
+    read_only::check("Writing release notes")?;
+    let path = git::repo_root()?.join(NOTES_FILE);
+    std::fs::write(&path, format!("{}\n", notes.trim_end()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Copy `notes` to the system clipboard.
+pub fn copy(notes: &str) -> Result<()> {
+    platform::copy_to_clipboard(notes)
+}
+
+/// True when the GitHub CLI is installed.
+pub fn gh_available() -> bool {
+    platform::on_path("gh")
+}
+
+/// Set the body of the GitHub release for `tag` with `gh release edit`.
+///
+/// CI creates the release after the tag push, so this polls `gh release view` for up to a
+/// minute before giving up.
+pub fn publish_with_gh(tag: &str, notes: &str) -> Result<()> {
+    read_only::check("Publishing release notes")?;
+    let started = Instant::now();
+    loop {
+        let o = Command::new("gh")
+            .args(["release", "view", tag, "--json", "tagName"])
+            .output()
+            .context("Failed to run gh release view")?;
+        if o.status.success() {
+            break;
+        }
+        if started.elapsed() >= GH_RELEASE_WAIT {
+            bail!(
+                "No GitHub release for {} after {}s: {}",
+                tag,
+                GH_RELEASE_WAIT.as_secs(),
+                String::from_utf8_lossy(&o.stderr).trim()
+            );
+        }
+        thread::sleep(GH_POLL_INTERVAL);
+    }
+
+    let mut child = Command::new("gh")
+        .args(["release", "edit", tag, "--notes-file", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run gh release edit")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(notes.as_bytes())
+            .context("Failed to pass release notes to gh")?;
+    }
+    let o = child
+        .wait_with_output()
+        .context("Failed to wait for gh release edit")?;
+    if !o.status.success() {
+        bail!(
+            "gh release edit {} failed: {}",
+            tag,
+            String::from_utf8_lossy(&o.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn git_output(args: &[&str]) -> Result<Output> {
+    git::command()
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))
+}
@@ -0,0 +1,167 @@
+//! Per-repository settings from `.gitwiz.toml` at the work tree root, committed with the
+//! project so every contributor commits the same way:
+//!
+//! ```toml
+//! [commit]
+//! signoff = true    # -s, for projects that require a DCO sign-off
+//! gpg_sign = true   # -S
+//! no_verify = false # --no-verify
+//!
+//! [diff]
+//! context_lines = 10      # -U10 in the diff sent to the model
+//! function_context = true # -W: the whole function around each change
+//!
+//! [hooks]
+//! warn_after_secs = 30 # warn when pre-commit / commit-msg hooks take longer
+//!
+//! [tag]
+//! sign = true # release tags with git tag -s (the key stays in each user's config)
+//!
+//! [preflight]
+//! # run before a release bumps the version, after fmt / clippy / test
+//! extra_commands = [["cargo", "deny", "check"], ["./scripts/verify.sh"]]
+//! ```
+//!
+//! Each `commit` key overrides the same one under `commit` in the user config, and each
+//! `diff` key `diff_context_lines` / `diff_function_context`; missing keys keep them. No file changes nothing, but a malformed one fails the commit instead of
+//! quietly dropping a sign-off the project requires.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::git::{self, CommitOptions, DiffContext};
+use crate::release::{PreflightConfig, TagConfig};
+
+pub const FILE_NAME: &str = ".gitwiz.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RepoConfig {
+    commit: CommitOverrides,
+    diff: DiffOverrides,
+    hooks: HookSettings,
+    preflight: PreflightSettings,
+    tag: TagOverrides,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommitOverrides {
+    signoff: Option<bool>,
+    gpg_sign: Option<bool>,
+    no_verify: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiffOverrides {
+    context_lines: Option<u32>,
+    function_context: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HookSettings {
+    warn_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TagOverrides {
+    sign: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PreflightSettings {
+    extra_commands: Vec<Vec<String>>,
+}
+
+fn load() -> Result<RepoConfig> {
+    let path = git::repo_root()?.join(FILE_NAME);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(RepoConfig::default()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    toml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// The flags for commits in the current repository: the config's, then `.gitwiz.toml`'s.
+pub fn commit_options() -> Result<CommitOptions> {
+    let mut options = Config::load()
+        .ok()
+        .flatten()
+        .map(|c| c.commit)
+        .unwrap_or_default();
+    let overrides = load()?.commit;
+    options.signoff = overrides.signoff.unwrap_or(options.signoff);
+    options.gpg_sign = overrides.gpg_sign.unwrap_or(options.gpg_sign);
+    options.no_verify = overrides.no_verify.unwrap_or(options.no_verify);
+    Ok(options)
+}
+
+/// How release tags are made: the config's `tag`, then `.gitwiz.toml`'s `[tag]`.
+pub fn tag_config() -> Result<TagConfig> {
+    let mut config = Config::load()
+        .ok()
+        .flatten()
+        .map(|c| c.tag)
+        .unwrap_or_default();
+    config.sign = load()?.tag.sign.unwrap_or(config.sign);
+    Ok(config)
+}
+
+/// Context for diffs sent to the model: the config's, then `.gitwiz.toml`'s.
+pub fn diff_context() -> Result<DiffContext> {
+    let cfg = Config::load().ok().flatten();
+    let overrides = load()?.diff;
+    Ok(DiffContext {
+        lines: overrides
+            .context_lines
+            .or(cfg.as_ref().and_then(|c| c.diff_context_lines)),
+        function: overrides
+            .function_context
+            .unwrap_or(cfg.is_some_and(|c| c.diff_function_context)),
+    })
+}
+
+/// A warning when a commit that ran `hooks` took longer than `warn_after_secs`; none
+/// without hooks, without the setting, or when `.gitwiz.toml` can't be read.
+pub fn slow_hooks_warning(hooks: &[&str], took: Duration) -> Option<String> {
+    let limit = load().ok()?.hooks.warn_after_secs?;
+    if hooks.is_empty() || took.as_secs() < limit {
+        return None;
+    }
+    Some(format!(
+        "Commit hooks ({}) took {} s, more than the {} s set in {} (`hooks.warn_after_secs`).",
+        hooks.join(", "),
+        took.as_secs(),
+        limit,
+        FILE_NAME
+    ))
+}
+
+/// The release preflight checks: fmt, clippy and test, then `.gitwiz.toml`'s extra
+/// commands.
+pub fn preflight_config() -> Result<PreflightConfig> {
+    let extra_commands = load()?.preflight.extra_commands;
+    if extra_commands.iter().any(|command| {
+        command
+            .first()
+            .is_none_or(|program| program.trim().is_empty())
+    }) {
+        bail!(
+            "Invalid {}: `preflight.extra_commands` has an empty command.",
+            FILE_NAME
+        );
+    }
+    Ok(PreflightConfig {
+        extra_commands,
+        ..PreflightConfig::default()
+    })
+}
@@ -0,0 +1,128 @@
+//! Pre-commit review: what exactly is about to be committed.
+//!
+//! Builds a compact, UI-agnostic summary (subject, staged files with +/- counts, and
+//! warning flags) that the CLI prints and the TUI shows in its confirm dialog.
+
+use anyhow::Result;
+
+use crate::git::{self, FileStat};
+
+/// Staged files larger than this are flagged.
+const LARGE_FILE_BYTES: u64 = 1024 * 1024;
+/// Don't stat more blobs than this on huge commits.
+const MAX_SIZE_CHECKS: usize = 200;
+
+#[derive(Debug, Clone, Default)]
+pub struct CommitReview {
+    pub subject: String,
+    pub files: Vec<FileStat>,
+    /// Human-readable warnings (large files, possible secrets, conflict markers).
+    pub flags: Vec<String>,
+}
+
+impl CommitReview {
+    /// Plain-text rendering shared by both UIs.
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = vec![format!("Subject: {}", self.subject), String::new()];
+
+        out.push(format!("Staged files ({}):", self.files.len()));
+        for f in &self.files {
+            out.push(format!("  {:>11}  {}", f.counts(), f.display_path()));
+        }
+
+        if !self.flags.is_empty() {
+            out.push(String::new());
+            out.push("Check before committing:".to_string());
+            for flag in &self.flags {
+                out.push(format!("  ! {}", flag));
+            }
+        }
+        out
+    }
+}
+
+/// Collect the review for `message` against the current index.
+pub fn staged_review(message: &str) -> Result<CommitReview> {
+    let files = git::staged_file_stats()?;
+    let diff = git::get_diff_staged_allow_empty()?;
+
+    let mut flags = Vec::new();
+    for f in files.iter().take(MAX_SIZE_CHECKS) {
+        if let Some(size) = git::staged_blob_size(&f.path) {
+            if size > LARGE_FILE_BYTES {
+                flags.push(format!(
+                    "large file: {} ({:.1} MB)",
+                    f.path,
+                    size as f64 / (1024.0 * 1024.0)
+                ));
+            }
+        }
+    }
+    flags.extend(scan_added_lines(&diff));
+
+    Ok(CommitReview {
+        subject: message
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        files,
+        flags,
+    })
+}
+
+/// Flag conflict markers and likely secrets in added lines (at most one flag per kind per file).
+fn scan_added_lines(diff: &str) -> Vec<String> {
+    let mut flags: Vec<String> = Vec::new();
+    let mut file = "";
+
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").unwrap_or(path);
+            continue;
+        }
+        let Some(added) = line.strip_prefix('+') else {
+            continue;
+        };
+
+        let flag = if is_conflict_marker(added) {
+            Some(format!("conflict marker in {}", file))
+        } else if looks_like_secret(added) {
+            Some(format!("possible secret in {}", file))
+        } else {
+            None
+        };
+        if let Some(flag) = flag {
+            if !flags.contains(&flag) {
+                flags.push(flag);
+            }
+        }
+    }
+    flags
+}
+
+fn is_conflict_marker(line: &str) -> bool {
+    line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> ") || line == "======="
+}
+
+fn looks_like_secret(line: &str) -> bool {
+    if line.contains("-----BEGIN") && line.contains("PRIVATE KEY") {
+        return true;
+    }
+    let token_prefixes = [
+        "AKIA",
+        "ghp_",
+        "github_pat_",
+        "xoxb-",
+        "xoxp-",
+        "sk-",
+        "AIza",
+    ];
+    line.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .any(|word| {
+            token_prefixes
+                .iter()
+                .any(|p| word.starts_with(p) && word.len() >= p.len() + 16)
+        })
+}
@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+
+/// Run an async operation to completion from synchronous code (CLI flows, setup, TUI tasks).
+///
+/// If we're already inside a tokio runtime (common in tests / other runtimes), use it.
+/// Otherwise create a small runtime for this one-off call.
+pub fn block_on<F, T>(fut: F) -> Result<T>
+where
+    F: std::future::Future<Output = Result<T>>,
+{
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle.block_on(fut),
+        Err(_) => {
+            let rt = tokio::runtime::Runtime::new().context("Failed to create tokio runtime")?;
+            rt.block_on(fut)
+        }
+    }
+}
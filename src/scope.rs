@@ -0,0 +1,263 @@
+//! Deterministic Conventional Commits scope from the changed paths.
+//!
+//! `scope_map` in the config maps path prefixes to scopes (`"src/tui/**": "tui"`); the
+//! longest prefix covering a path wins. When no prefix covers any of the changed files,
+//! the scope is the deepest directory they all share, skipping generic names like `src`
+//! (`src/tui/app.rs` and `src/tui/view.rs` give `tui`). Files that map to different
+//! scopes, or only partly map, get no suggestion.
+//!
+//! The suggestion goes into the prompt as a hint, and the UIs offer to swap it in when
+//! the model picks a different scope anyway.
+
+use std::collections::BTreeMap;
+
+use crate::config::Config;
+use crate::conventional;
+
+/// Directory names that say where code lives rather than what it is.
+const GENERIC_DIRS: &[&str] = &[
+    "src", "lib", "source", "sources", "app", "apps", "pkg", "internal", "crates", "packages",
+    "include", "main",
+];
+
+/// Scope for the files changed in `diff`, or `None` when they don't share one.
+pub fn suggest(diff: &str, map: &BTreeMap<String, String>) -> Option<String> {
+    let paths: Vec<&str> = diff
+        .lines()
+        .filter_map(|line| line.strip_prefix("diff --git "))
+        .map(|rest| rest.rsplit_once(" b/").map_or(rest, |(_, b)| b))
+        .collect();
+    if paths.is_empty() {
+        return None;
+    }
+
+    let mapped: Vec<Option<&str>> = paths.iter().map(|p| mapped_scope(p, map)).collect();
+    if mapped.iter().all(Option::is_none) {
+        return common_dir_scope(&paths);
+    }
+    let first = mapped[0]?;
+    mapped
+        .iter()
+        .all(|s| *s == Some(first))
+        .then(|| first.to_string())
+}
+
+/// `suggest` for messages generated with `cfg`; `None` unless they are Conventional Commits.
+pub fn for_config(diff: &str, cfg: Option<&Config>) -> Option<String> {
+    if !conventional::applies(cfg) {
+        return None;
+    }
+    suggest(diff, &cfg.map(|c| c.scope_map.clone()).unwrap_or_default())
+}
+
+/// The scope of the longest `map` prefix covering `path`.
+fn mapped_scope<'a>(path: &str, map: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    map.iter()
+        .filter_map(|(pattern, scope)| {
+            let prefix = pattern
+                .trim()
+                .trim_end_matches("**")
+                .trim_end_matches('*')
+                .trim_end_matches('/');
+            let covers = prefix.is_empty()
+                || path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('/'));
+            covers.then_some((prefix.len(), scope.trim()))
+        })
+        .filter(|(_, scope)| !scope.is_empty())
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, scope)| scope)
+}
+
+/// The deepest non-generic directory that contains every path.
+fn common_dir_scope(paths: &[&str]) -> Option<String> {
+    let mut common: Vec<&str> = parent_dirs(paths[0]);
+    for path in &paths[1..] {
+        let dirs = parent_dirs(path);
+        let shared = common.iter().zip(&dirs).take_while(|(a, b)| a == b).count();
+        common.truncate(shared);
+    }
+    common
+        .iter()
+        .rev()
+        .find(|dir| !GENERIC_DIRS.contains(&dir.to_ascii_lowercase().as_str()))
+        .map(|dir| dir.to_string())
+}
+
+fn parent_dirs(path: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    parts.pop();
+    parts
+}
+
+/// A generated message whose scope differs from the suggested one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeFix {
+    pub scope: String,
+    /// The model's scope, if it used one.
+    pub current: Option<String>,
+}
+
+impl ScopeFix {
+    /// e.g. ``The changed files all belong to `tui`; use it instead of `core`?``
+    pub fn question(&self) -> String {
+        match &self.current {
+            Some(current) => format!(
+                "The changed files all belong to `{}`; use it instead of `{}`?",
+                self.scope, current
+            ),
+            None => format!(
+                "The changed files all belong to `{}`; add it as the scope?",
+                self.scope
+            ),
+        }
+    }
+}
+
+/// The quick-fix for `message`, generated for `diff` with `cfg`, when the model's scope
+/// disagrees with the suggestion.
+pub fn fix_for(message: &str, diff: &str, cfg: Option<&Config>) -> Option<ScopeFix> {
+    let scope = for_config(diff, cfg)?;
+    let current = conventional::parse(message).ok()?.scope;
+    (current.as_deref() != Some(scope.as_str())).then_some(ScopeFix { scope, current })
+}
+
+/// `message` with `scope` in its header, if it is a Conventional Commit with another scope.
+pub fn with_scope(message: &str, scope: &str) -> Option<String> {
+    let commit = conventional::parse(message).ok()?;
+    if commit.scope.as_deref() == Some(scope) {
+        return None;
+    }
+    let (old, rest) = match message.split_once('\n') {
+        Some((old, rest)) => (old, Some(rest)),
+        None => (message, None),
+    };
+    let bang = if old[..old.find(": ")?].ends_with('!') {
+        "!"
+    } else {
+        ""
+    };
+    let header = format!("{}({}){}: {}", commit.kind, scope, bang, commit.subject);
+    Some(match rest {
+        Some(rest) => format!("{}\n{}", header, rest),
+        None => header,
+    })
+}
+
+/// The prompt sentence asking for `scope`.
+pub fn hint(scope: &str) -> String {
+    format!(
+        "Use `{}` as the commit scope: every changed file belongs to it.",
+        scope
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff_of(paths: &[&str]) -> String {
+        paths
+            .iter()
+            .map(|p| format!("diff --git a/{p} b/{p}\n@@ -1 +1 @@\n-a\n+b\n"))
+            .collect()
+    }
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn scope_falls_back_to_the_deepest_shared_directory() {
+        let cases: &[(&[&str], Option<&str>)] = &[
+            // A single file.
+            (&["src/tui/app.rs"], Some("tui")),
+            (&["src/main.rs"], None),
+            (&["README.md"], None),
+            // Files sharing a directory.
+            (&["src/tui/app.rs", "src/tui/view.rs"], Some("tui")),
+            (&["src/tui/app.rs", "src/tui/widgets/list.rs"], Some("tui")),
+            (
+                &["crates/git/src/diff.rs", "crates/git/src/status.rs"],
+                Some("git"),
+            ),
+            (&["docs/guide/a.md", "docs/api.md"], Some("docs")),
+            // No common prefix, or only a generic one.
+            (&["src/tui/app.rs", "docs/usage.md"], None),
+            (&["src/tui/app.rs", "src/git.rs"], None),
+            (&[], None),
+        ];
+        for (paths, expected) in cases {
+            assert_eq!(
+                suggest(&diff_of(paths), &BTreeMap::new()).as_deref(),
+                *expected,
+                "{:?}",
+                paths
+            );
+        }
+    }
+
+    #[test]
+    fn configured_map_overrides_the_prefix() {
+        let scopes = map(&[
+            ("src/tui/**", "ui"),
+            ("src/tui/widgets/*", "widgets"),
+            ("docs", "docs"),
+            ("src/git.rs", "git"),
+        ]);
+        let cases: &[(&[&str], Option<&str>)] = &[
+            (&["src/tui/app.rs", "src/tui/view.rs"], Some("ui")),
+            // The longest prefix wins.
+            (&["src/tui/widgets/list.rs"], Some("widgets")),
+            (&["src/git.rs"], Some("git")),
+            (&["docs/a.md", "docs/b/c.md"], Some("docs")),
+            // `src/tui` does not cover `src/tuition`.
+            (&["src/tuition/a.rs", "src/tuition/b.rs"], Some("tuition")),
+            // Different scopes, or only some files mapped.
+            (&["src/tui/app.rs", "docs/a.md"], None),
+            (&["src/tui/app.rs", "src/other/x.rs"], None),
+        ];
+        for (paths, expected) in cases {
+            assert_eq!(
+                suggest(&diff_of(paths), &scopes).as_deref(),
+                *expected,
+                "{:?}",
+                paths
+            );
+        }
+        // A catch-all entry covers everything; blank scopes are ignored.
+        let catch_all = map(&[("**", "repo"), ("src/tui", "  ")]);
+        assert_eq!(
+            suggest(&diff_of(&["src/tui/app.rs"]), &catch_all).as_deref(),
+            Some("repo")
+        );
+    }
+
+    #[test]
+    fn with_scope_swaps_only_the_header_scope() {
+        assert_eq!(
+            with_scope("feat(core)!: split the app\n\nBody.", "tui").as_deref(),
+            Some("feat(tui)!: split the app\n\nBody.")
+        );
+        assert_eq!(
+            with_scope("fix: quote paths", "git").as_deref(),
+            Some("fix(git): quote paths")
+        );
+        assert_eq!(with_scope("fix(git): quote paths", "git"), None);
+        assert_eq!(with_scope("Quote paths", "git"), None);
+
+        let fix = ScopeFix {
+            scope: "tui".to_string(),
+            current: Some("core".to_string()),
+        };
+        assert_eq!(
+            fix.question(),
+            "The changed files all belong to `tui`; use it instead of `core`?"
+        );
+    }
+}
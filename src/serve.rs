@@ -0,0 +1,476 @@
+//! Long-running, line-delimited JSON-RPC 2.0 server over stdio (`git-wiz serve --stdio`).
+//!
+//! One JSON object per line in, one per line out. Requests are handled one at a time;
+//! while a `generate` is in flight the only request that does anything is the
+//! `$/cancel` notification (`{"method":"$/cancel","params":{"id":<request id>}}`),
+//! other requests get a "busy" error. Nothing here ever prompts: every failure is
+//! returned as a JSON-RPC error.
+//!
+//! Methods:
+//! - `status` -> `{ version, repo, branch, provider, model, mock }`
+//! - `diffSummary { source? }` -> `{ filesChanged, insertions, deletions, binaryFiles, bytes }`
+//! - `generate { source?, hint? }` -> `{ message, provider, model }`
+//! - `commit { message }` -> `{ committed: true }`
+//! - `shutdown` -> `null`, then the server exits
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+
+use crate::config::Config;
+use crate::generator::{self, ApiError, ModelUnavailable};
+use crate::git::{self, DiffSource};
+use crate::history;
+use crate::message;
+use crate::prompt;
+use crate::runtime;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+const MODEL_UNAVAILABLE: i64 = -32001;
+const SERVER_BUSY: i64 = -32002;
+const REQUEST_CANCELLED: i64 = -32800;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// Absent for notifications.
+    id: Option<Value>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast_ref::<ModelUnavailable>() {
+            Some(unavailable) => Self {
+                code: MODEL_UNAVAILABLE,
+                message: unavailable.to_string(),
+                data: Some(json!({
+                    "provider": unavailable.provider.to_string(),
+                    "model": unavailable.model,
+                })),
+            },
+            None => match e.downcast_ref::<ApiError>() {
+                Some(api) => Self {
+                    code: SERVER_ERROR,
+                    message: api.to_string(),
+                    data: Some(json!({
+                        "provider": api.provider.to_string(),
+                        "category": api.category.to_string(),
+                        "status": api.response.status,
+                        "response": api.response.body,
+                    })),
+                },
+                None => Self::new(SERVER_ERROR, format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+/// Serve JSON-RPC on stdin/stdout until `shutdown` or EOF.
+pub fn run_stdio(mock_mode: bool) -> Result<()> {
+    let input = BufReader::new(tokio::io::stdin());
+    runtime::block_on(serve(mock_mode, input, &mut tokio::io::stdout()))
+}
+
+/// Serve requests read from `input`, writing responses to `out`.
+async fn serve<R, W>(mock_mode: bool, input: R, out: &mut W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = input.lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let request = match parse_request(&line) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(err) => {
+                write_response(out, &Value::Null, Err(err)).await?;
+                continue;
+            }
+        };
+
+        // Notifications (including a late `$/cancel`) need no reply.
+        let Some(id) = request.id else {
+            continue;
+        };
+
+        let result = match request.method.as_str() {
+            "shutdown" => {
+                write_response(out, &id, Ok(Value::Null)).await?;
+                return Ok(());
+            }
+            "generate" => {
+                match generate(&id, &request.params, mock_mode, &mut lines, out).await? {
+                    Some(result) => result,
+                    // stdin closed mid-generation: nobody is left to answer.
+                    None => return Ok(()),
+                }
+            }
+            "status" => status(mock_mode),
+            "diffSummary" => diff_summary(&request.params),
+            "commit" => commit(&request.params),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", other),
+            )),
+        };
+
+        write_response(out, &id, result).await?;
+    }
+
+    Ok(())
+}
+
+/// Parse one input line. Blank lines yield `Ok(None)`.
+fn parse_request(line: &str) -> std::result::Result<Option<Request>, RpcError> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)))?;
+    serde_json::from_value(value)
+        .map(Some)
+        .map_err(|e| RpcError::new(INVALID_REQUEST, format!("Invalid request: {}", e)))
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    out: &mut W,
+    id: &Value,
+    result: RpcResult,
+) -> Result<()> {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => {
+            let mut error = json!({ "code": err.code, "message": err.message });
+            if let Some(data) = err.data {
+                error["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    };
+
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+    out.write_all(line.as_bytes()).await?;
+    out.flush().await?;
+    Ok(())
+}
+
+fn parse_source(params: &Value) -> std::result::Result<DiffSource, RpcError> {
+    match params.get("source").and_then(Value::as_str) {
+        None | Some("staged") => Ok(DiffSource::Staged),
+        Some("unstaged") => Ok(DiffSource::Unstaged),
+        Some("both") => Ok(DiffSource::Both),
+        Some(other) => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!(
+                "Invalid source '{}': expected staged, unstaged or both",
+                other
+            ),
+        )),
+    }
+}
+
+fn status(mock_mode: bool) -> RpcResult {
+    let repo = git::is_repo();
+    let branch = if repo {
+        git::current_branch().ok()
+    } else {
+        None
+    };
+    let (provider, model) = if mock_mode {
+        (Some("Mock".to_string()), None)
+    } else {
+        match Config::load()? {
+            Some(cfg) => (Some(cfg.provider.to_string()), Some(cfg.model)),
+            None => (None, None),
+        }
+    };
+
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "repo": repo,
+        "branch": branch,
+        "provider": provider,
+        "model": model,
+        "mock": mock_mode,
+    }))
+}
+
+fn diff_summary(params: &Value) -> RpcResult {
+    let source = parse_source(params)?;
+    let summary = git::diff_summary(source)?;
+    Ok(json!({
+        "filesChanged": summary.files_changed,
+        "insertions": summary.insertions,
+        "deletions": summary.deletions,
+        "binaryFiles": summary.binary_files,
+        "bytes": summary.bytes,
+    }))
+}
+
+fn commit(params: &Value) -> RpcResult {
+    let message = params
+        .get("message")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default();
+    if message.is_empty() {
+        return Err(RpcError::new(INVALID_PARAMS, "Missing commit message"));
+    }
+    history::commit_and_record(message)?;
+    Ok(json!({ "committed": true }))
+}
+
+/// Run a generation while still reading stdin so `$/cancel` can abort it.
+///
+/// Returns `Ok(None)` if stdin closed before the generation finished.
+async fn generate<R, W>(
+    id: &Value,
+    params: &Value,
+    mock_mode: bool,
+    lines: &mut Lines<R>,
+    out: &mut W,
+) -> Result<Option<RpcResult>>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let prepared = (|| -> std::result::Result<_, RpcError> {
+        let source = parse_source(params)?;
+        let hint = params
+            .get("hint")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .map(str::to_string);
+        let cfg = Config::load().ok().flatten();
+        let hint = match hint {
+            Some(hint) => Some(hint),
+            None => prompt::branch_hint(cfg.as_ref())?,
+        };
+        let trailers = cfg.map(|c| c.trailers).unwrap_or_default();
+        let diff = prompt::diff_for(source)?.text;
+        let (generator, provider, model) = generator::build_generator(mock_mode)?;
+        Ok((diff, hint, trailers, generator, provider, model))
+    })();
+    let (diff, hint, trailers, generator, provider, model) = match prepared {
+        Ok(prepared) => prepared,
+        Err(err) => return Ok(Some(Err(err))),
+    };
+
+    let mut task = tokio::spawn(async move { generator.generate(&diff, hint).await });
+
+    loop {
+        tokio::select! {
+            joined = &mut task => {
+                let result = match joined {
+                    Ok(Ok(generation)) => Ok(json!({
+                        "message": message::append_trailers(
+                            &generation.message,
+                            &trailers,
+                            git::current_branch().ok().as_deref(),
+                        ),
+                        "provider": provider,
+                        "model": model,
+                    })),
+                    Ok(Err(e)) => Err(RpcError::from(e)),
+                    Err(e) => Err(RpcError::new(SERVER_ERROR, format!("Generation failed: {}", e))),
+                };
+                return Ok(Some(result));
+            }
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    task.abort();
+                    return Ok(None);
+                };
+                let request = match parse_request(&line) {
+                    Ok(Some(request)) => request,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        write_response(out, &Value::Null, Err(err)).await?;
+                        continue;
+                    }
+                };
+
+                if request.method == "$/cancel" && request.params.get("id") == Some(id) {
+                    task.abort();
+                    return Ok(Some(Err(RpcError::new(REQUEST_CANCELLED, "Request cancelled"))));
+                }
+                if let Some(other) = request.id {
+                    let busy = RpcError::new(SERVER_BUSY, "Busy: a generate request is in progress");
+                    write_response(out, &other, Err(busy)).await?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRepo;
+    use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+    /// A repository with one commit and a staged change to `lib.rs`.
+    fn staged_repo() -> TempRepo {
+        let repo = TempRepo::new();
+        repo.write("lib.rs", "fn a() {}\n");
+        repo.commit_all("init");
+        repo.write("lib.rs", "fn a() {}\nfn b() {}\n");
+        repo.git(&["add", "lib.rs"]);
+        repo
+    }
+
+    /// The editor's end of the pipe.
+    struct Client {
+        requests: WriteHalf<DuplexStream>,
+        replies: Lines<BufReader<ReadHalf<DuplexStream>>>,
+    }
+
+    impl Client {
+        async fn call(&mut self, request: Value) -> Value {
+            let line = format!("{}\n", request);
+            self.requests.write_all(line.as_bytes()).await.unwrap();
+            let reply = self.replies.next_line().await.unwrap().unwrap();
+            serde_json::from_str(&reply).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn drives_a_commit_over_a_pipe() {
+        let repo = staged_repo();
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (server_in, mut server_out) = tokio::io::split(server);
+        let server =
+            tokio::spawn(
+                async move { serve(true, BufReader::new(server_in), &mut server_out).await },
+            );
+        let (replies, requests) = tokio::io::split(client);
+        let mut client = Client {
+            requests,
+            replies: BufReader::new(replies).lines(),
+        };
+
+        let status = client
+            .call(json!({"jsonrpc": "2.0", "id": 1, "method": "status"}))
+            .await;
+        assert_eq!(status["id"], 1);
+        assert_eq!(status["result"]["repo"], true);
+        assert_eq!(status["result"]["branch"], "master");
+        assert_eq!(status["result"]["mock"], true);
+
+        let summary = client
+            .call(json!({"jsonrpc": "2.0", "id": 2, "method": "diffSummary"}))
+            .await;
+        assert_eq!(summary["result"]["filesChanged"], 1, "{}", summary);
+        assert_eq!(summary["result"]["insertions"], 1);
+
+        let generated = client
+            .call(json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "generate",
+                "params": {"hint": "add b"},
+            }))
+            .await;
+        let message = generated["result"]["message"].as_str().unwrap().to_string();
+        assert!(message.contains("add b"), "{}", message);
+        assert_eq!(generated["result"]["provider"], "Mock");
+
+        let committed = client
+            .call(json!({
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "commit",
+                "params": {"message": message},
+            }))
+            .await;
+        assert_eq!(committed["result"]["committed"], true);
+        assert_eq!(repo.git(&["log", "-1", "--format=%B"]), message.trim());
+
+        let shutdown = client
+            .call(json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}))
+            .await;
+        assert_eq!(shutdown["result"], Value::Null);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancels_a_generation_and_reports_errors() {
+        let _repo = staged_repo();
+        let input = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "generate"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "status"}),
+            json!({"jsonrpc": "2.0", "method": "$/cancel", "params": {"id": 1}}),
+        ]
+        .iter()
+        .map(|request| format!("{}\n", request))
+        .chain([
+            "not json\n".to_string(),
+            "\n".to_string(),
+            format!("{}\n", json!({"jsonrpc": "2.0", "id": 3, "method": "rebase"})),
+            format!(
+                "{}\n",
+                json!({"jsonrpc": "2.0", "id": 4, "method": "diffSummary", "params": {"source": "stash"}})
+            ),
+            format!(
+                "{}\n",
+                json!({"jsonrpc": "2.0", "id": 5, "method": "commit", "params": {"message": " "}})
+            ),
+        ])
+        .collect::<String>();
+
+        let mut out = Vec::new();
+        serve(true, input.as_bytes(), &mut out).await.unwrap();
+
+        let replies: Vec<(Value, i64)> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .map(|reply| {
+                (
+                    reply["id"].clone(),
+                    reply["error"]["code"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            replies,
+            [
+                (json!(2), SERVER_BUSY),
+                (json!(1), REQUEST_CANCELLED),
+                (Value::Null, PARSE_ERROR),
+                (json!(3), METHOD_NOT_FOUND),
+                (json!(4), INVALID_PARAMS),
+                (json!(5), INVALID_PARAMS),
+            ]
+        );
+    }
+}
@@ -1,9 +1,17 @@
-use crate::config::{Config, Provider};
-use anyhow::Result;
-use cliclack::{input, log, note, password, select};
+use crate::config::{self, CommitStyle, Config, Provider};
+use crate::generator::{self, InvalidApiKey, ModelUnavailable, OPENAI_BASE_URL};
+use crate::git;
+use crate::models::{self, Model};
+use crate::prompt;
+use crate::read_only;
+use crate::runtime;
+use anyhow::{bail, Context, Result};
+use cliclack::{confirm, input, log, note, password, select, spinner};
 use colored::*;
+use std::fs;
 
 pub fn run_setup() -> Result<Config> {
+    read_only::check("Changing the config")?;
     log::info("Welcome! It looks like this is your first time running the tool.")?;
     log::info("Let's get you set up with a few simple questions.\n")?;
 
@@ -20,27 +28,64 @@ pub fn run_setup() -> Result<Config> {
             "Claude 4.5 (Sonnet / Opus)",
         )
         .item(Provider::OpenAI, "OpenAI", "GPT-5.2")
+        .item(
+            Provider::OpenRouter,
+            "OpenRouter",
+            "One key for Claude, Llama, GPT and more",
+        )
+        .item(Provider::Groq, "Groq", "Llama, very fast")
+        .item(Provider::Mistral, "Mistral", "Mistral Large / Small")
         .interact()?;
 
     // 2. Input API Key
-    let api_key = password(format!("Enter your {} API Key", provider))
-        .mask('•')
-        .interact()?;
+    let api_key = input_api_key(&provider)?;
+
+    let existing = Config::load().ok().flatten();
 
-    // 3. Select Model
-    let model = match provider {
-        Provider::Gemini => select_model_gemini()?,
-        Provider::Anthropic => select_model_anthropic()?,
-        Provider::OpenAI => select_model_openai()?,
+    // 3. Endpoint (OpenAI-compatible servers only; the model list comes from it)
+    let base_url = match provider {
+        Provider::OpenAI => {
+            let current = existing.as_ref().and_then(|c| c.base_url.clone());
+            input_base_url(current.as_deref())?
+        }
+        _ => existing.as_ref().and_then(|c| c.base_url.clone()),
     };
 
-    let config = Config {
-        provider,
-        api_key,
-        model,
+    // 4. Select Model
+    let model = select_model(&provider, &api_key, base_url.as_deref())?;
+
+    // 5. Verify credentials (re-asks the key or model until they work, or the user skips)
+    let (api_key, model) = verify_credentials(&provider, api_key, model, base_url.as_deref())?;
+
+    // 6. Commit style
+    let commit_style = select_commit_style(existing.as_ref().map(|c| c.commit_style))?;
+
+    // 7. Message language
+    let language = input_language(existing.as_ref().and_then(|c| c.language.as_deref()))?;
+
+    // Setup only owns provider/key/model/endpoint/style/language; keep any other settings from an existing config.
+    let config = match existing {
+        Some(existing) => Config {
+            provider,
+            api_key,
+            model,
+            base_url,
+            commit_style,
+            language,
+            ..existing
+        },
+        None => Config {
+            base_url,
+            commit_style,
+            language,
+            ..Config::new(provider, api_key, model)
+        },
     };
 
-    // 4. Save
+    // 8. Custom system prompt (prompt.txt, saved on its own)
+    select_prompt_template(&config)?;
+
+    // 9. Save
     config.save()?;
 
     log::success("Setup Complete! You are ready to go.")?;
@@ -58,6 +103,323 @@ pub fn run_setup() -> Result<Config> {
     Ok(config)
 }
 
+/// Re-run only the model-selection step for the configured provider and save the result.
+///
+/// Used to recover when the provider has retired the configured model.
+pub fn run_model_selection() -> Result<Config> {
+    read_only::check("Changing the config")?;
+    let Some(existing) = Config::load()? else {
+        bail!("No config found. Run `git-wiz setup` first.");
+    };
+
+    log::info(format!(
+        "Current model: {} ({})",
+        existing.model.cyan(),
+        existing.provider
+    ))?;
+
+    let model = select_model(
+        &existing.provider,
+        &existing.api_key,
+        existing.base_url.as_deref(),
+    )?;
+    let config = Config { model, ..existing };
+    config.save()?;
+
+    log::success(format!("Model set to {}.", config.model))?;
+    Ok(config)
+}
+
+/// Re-run only the commit-style step and save the result.
+pub fn run_style_selection() -> Result<Config> {
+    read_only::check("Changing the config")?;
+    let Some(existing) = Config::load()? else {
+        bail!("No config found. Run `git-wiz setup` first.");
+    };
+
+    let commit_style = select_commit_style(Some(existing.commit_style))?;
+    let config = Config {
+        commit_style,
+        ..existing
+    };
+    config.save()?;
+
+    log::success(format!("Commit style set to {}.", config.commit_style))?;
+    Ok(config)
+}
+
+/// Re-run only the message-language step and save the result.
+#[cfg(feature = "cli")]
+pub fn run_language_selection() -> Result<Config> {
+    read_only::check("Changing the config")?;
+    let Some(existing) = Config::load()? else {
+        bail!("No config found. Run `git-wiz setup` first.");
+    };
+
+    let language = input_language(existing.language.as_deref())?;
+    let config = Config {
+        language,
+        ..existing
+    };
+    config.save()?;
+
+    log::success(format!(
+        "Messages will be written in {}.",
+        language_label(&config)
+    ))?;
+    Ok(config)
+}
+
+/// `language` for display: its name, or the default.
+pub fn language_label(cfg: &Config) -> String {
+    match cfg.language.as_deref() {
+        Some(language) => prompt::language_name(language),
+        None => "English (default)".to_string(),
+    }
+}
+
+/// Re-run only the custom system prompt step.
+#[cfg(feature = "cli")]
+pub fn run_prompt_selection() -> Result<()> {
+    let Some(existing) = Config::load()? else {
+        bail!("No config found. Run `git-wiz setup` first.");
+    };
+    select_prompt_template(&existing)
+}
+
+/// Offer to create, edit or remove `prompt.txt`, which replaces the built-in system prompt.
+fn select_prompt_template(cfg: &Config) -> Result<()> {
+    let path = prompt::template_path()?;
+    let choice = if path.exists() {
+        select("Custom system prompt")
+            .item("keep", format!("Keep {}", prompt::TEMPLATE_FILE), "")
+            .item(
+                "edit",
+                format!("Edit {}", prompt::TEMPLATE_FILE),
+                "opens your editor",
+            )
+            .item(
+                "remove",
+                format!("Remove {}", prompt::TEMPLATE_FILE),
+                "use the built-in prompt",
+            )
+            .interact()?
+    } else {
+        select("Custom system prompt")
+            .item("keep", "Use the built-in prompt", "recommended")
+            .item(
+                "edit",
+                format!("Create {}", prompt::TEMPLATE_FILE),
+                "starts from the built-in prompt",
+            )
+            .interact()?
+    };
+
+    if choice != "keep" {
+        read_only::check("Changing the config")?;
+    }
+    match choice {
+        "edit" => {
+            if !path.exists() {
+                let template = prompt::builtin_template(cfg.commit_style, Some(&cfg.provider));
+                fs::write(&path, format!("{}\n", template))
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            git::edit_file(&path)?;
+            log::info(format!(
+                "Custom prompt: {}. Use {} and {} for the hint and the current branch.",
+                path.display(),
+                "{{hint}}".cyan(),
+                "{{branch}}".cyan()
+            ))?;
+        }
+        "remove" => {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            log::info("Using the built-in prompt.")?;
+        }
+        _ => {}
+    }
+
+    if cfg.prompt_template.is_some() {
+        log::warning(format!(
+            "prompt_template in config.json takes precedence over {}.",
+            prompt::TEMPLATE_FILE
+        ))?;
+    }
+    Ok(())
+}
+
+fn input_api_key(provider: &Provider) -> Result<String> {
+    Ok(password(format!("Enter your {} API Key", provider))
+        .mask('•')
+        .interact()?)
+}
+
+/// Offer a test call with the new key and model before anything is saved. A rejected key
+/// goes back to the key prompt, an unknown model back to the model list; other failures
+/// (network, outage) can be retried or skipped.
+fn verify_credentials(
+    provider: &Provider,
+    mut api_key: String,
+    mut model: String,
+    base_url: Option<&str>,
+) -> Result<(String, String)> {
+    if !confirm("Verify the API key now? (one request, no tokens spent)")
+        .initial_value(true)
+        .interact()?
+    {
+        return Ok((api_key, model));
+    }
+
+    loop {
+        let cfg = Config {
+            base_url: base_url.map(str::to_string),
+            ..Config::new(provider.clone(), api_key.clone(), model.clone())
+        };
+        let progress = spinner();
+        progress.start(format!("Checking your {} credentials...", provider));
+        let result = runtime::block_on(generator::check_access(&cfg));
+        match result {
+            Ok(_) => {
+                progress.stop(format!(
+                    "{} accepted the key; {} is available.",
+                    provider, model
+                ));
+                return Ok((api_key, model));
+            }
+            Err(e) if e.downcast_ref::<InvalidApiKey>().is_some() => {
+                progress.error(e.to_string());
+                api_key = input_api_key(provider)?;
+            }
+            Err(e) if e.downcast_ref::<ModelUnavailable>().is_some() => {
+                progress.error(e.to_string());
+                model = select_model(provider, &api_key, base_url)?;
+            }
+            Err(e) => {
+                progress.error(format!("{:#}", e));
+                let retry = select("The credentials could not be checked")
+                    .item(true, "Try again", "")
+                    .item(false, "Skip the check", "keep the key and model as entered")
+                    .interact()?;
+                if !retry {
+                    return Ok((api_key, model));
+                }
+            }
+        }
+    }
+}
+
+fn select_commit_style(current: Option<CommitStyle>) -> Result<CommitStyle> {
+    let mut prompt = select("Select the commit message style");
+    for style in CommitStyle::ALL {
+        let example = match style {
+            CommitStyle::Conventional => "feat(auth): add token refresh",
+            CommitStyle::Gitmoji => "✨ Add token refresh",
+            CommitStyle::Plain => "Add token refresh",
+        };
+        prompt = prompt.item(style, style.to_string(), example);
+    }
+    Ok(prompt
+        .initial_value(current.unwrap_or_default())
+        .interact()?)
+}
+
+/// Ask for the language messages are written in; blank leaves it to the model (English).
+fn input_language(current: Option<&str>) -> Result<Option<String>> {
+    let mut prompt = input("Commit message language (leave empty for English)")
+        .placeholder("e.g. German or de")
+        .required(false);
+    if let Some(current) = current {
+        prompt = prompt.default_input(current);
+    }
+    let value: String = prompt.interact()?;
+    let value = value.trim();
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+/// Ask for an OpenAI-compatible base URL; blank keeps api.openai.com.
+fn input_base_url(current: Option<&str>) -> Result<Option<String>> {
+    let mut prompt = input("OpenAI-compatible base URL (leave empty for api.openai.com)")
+        .placeholder(OPENAI_BASE_URL)
+        .required(false)
+        .validate(|value: &String| {
+            if value.trim().is_empty() {
+                return Ok(());
+            }
+            config::normalize_base_url(value)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+    if let Some(current) = current {
+        prompt = prompt.default_input(current);
+    }
+    let value: String = prompt.interact()?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(config::normalize_base_url(&value)?))
+}
+
+/// Pick from the provider's live model list (cached for a day), or from the built-in
+/// menu when the list can't be fetched.
+fn select_model(provider: &Provider, api_key: &str, base_url: Option<&str>) -> Result<String> {
+    let live = models::cached(provider, base_url)
+        .unwrap_or_else(|| fetch_models(provider, api_key, base_url));
+    if !live.is_empty() {
+        return select_live_model(provider, &live);
+    }
+    match provider {
+        Provider::Gemini => select_model_gemini(),
+        Provider::Anthropic => select_model_anthropic(),
+        Provider::OpenAI => select_model_openai(),
+        Provider::OpenRouter => select_model_openrouter(),
+        Provider::Groq => select_model_groq(),
+        Provider::Mistral => select_model_mistral(),
+    }
+}
+
+fn fetch_models(provider: &Provider, api_key: &str, base_url: Option<&str>) -> Vec<Model> {
+    let progress = spinner();
+    progress.start(format!("Fetching the {} model list...", provider));
+    match runtime::block_on(models::fetch(provider, api_key, base_url)) {
+        Ok(list) if !list.is_empty() => {
+            progress.stop(format!("{} models available.", list.len()));
+            // Best-effort: without the cache the next setup just fetches again.
+            let _ = models::store(provider, base_url, &list);
+            list
+        }
+        Ok(_) => {
+            progress.error("No chat models listed; showing the built-in menu.");
+            Vec::new()
+        }
+        Err(e) => {
+            progress.error(format!("{:#}; showing the built-in menu.", e));
+            Vec::new()
+        }
+    }
+}
+
+fn select_live_model(provider: &Provider, live: &[Model]) -> Result<String> {
+    let mut menu = select(format!("Select {} Model", provider));
+    for model in live {
+        menu = match &model.name {
+            Some(name) => menu.item(Some(model.id.clone()), name, &model.id),
+            None => menu.item(Some(model.id.clone()), &model.id, ""),
+        };
+    }
+    menu = menu.item(None, "Other...", "Enter a custom model name");
+    // Long lists (OpenRouter has hundreds) scroll and can be filtered by typing.
+    if live.len() > 10 {
+        menu = menu.filter_mode().max_rows(10);
+    }
+
+    match menu.interact()? {
+        Some(id) => Ok(id),
+        None => Ok(input("Enter custom model name").interact()?),
+    }
+}
+
 fn select_model_gemini() -> Result<String> {
     let selection = select("Select Gemini Model")
         .item(
@@ -126,3 +488,85 @@ fn select_model_openai() -> Result<String> {
         Ok(selection.to_string())
     }
 }
+
+fn select_model_openrouter() -> Result<String> {
+    let selection = select("Select OpenRouter Model")
+        .item(
+            "anthropic/claude-3.5-sonnet",
+            "Claude 3.5 Sonnet",
+            "Recommended default",
+        )
+        .item("openai/gpt-4o", "GPT-4o", "OpenAI via OpenRouter")
+        .item(
+            "google/gemini-2.5-flash",
+            "Gemini 2.5 Flash",
+            "Fast and inexpensive",
+        )
+        .item(
+            "meta-llama/llama-3.1-70b-instruct",
+            "Llama 3.1 70B Instruct",
+            "Open weights",
+        )
+        .item(
+            "custom",
+            "Other...",
+            "Enter a model slug from openrouter.ai/models",
+        )
+        .interact()?;
+
+    if selection == "custom" {
+        Ok(input("Enter model slug")
+            .placeholder("e.g. mistralai/mistral-large")
+            .interact()?)
+    } else {
+        Ok(selection.to_string())
+    }
+}
+
+fn select_model_groq() -> Result<String> {
+    let selection = select("Select Groq Model")
+        .item(
+            "llama-3.3-70b-versatile",
+            "Llama 3.3 70B Versatile",
+            "Recommended default",
+        )
+        .item(
+            "llama-3.1-8b-instant",
+            "Llama 3.1 8B Instant",
+            "Fastest, for small diffs",
+        )
+        .item("custom", "Other...", "Enter a custom model name")
+        .interact()?;
+
+    if selection == "custom" {
+        Ok(input("Enter custom model name")
+            .placeholder("e.g. llama-3.3-70b-versatile")
+            .interact()?)
+    } else {
+        Ok(selection.to_string())
+    }
+}
+
+fn select_model_mistral() -> Result<String> {
+    let selection = select("Select Mistral Model")
+        .item(
+            "mistral-large-latest",
+            "Mistral Large",
+            "Recommended default",
+        )
+        .item(
+            "mistral-small-latest",
+            "Mistral Small",
+            "Fast and inexpensive",
+        )
+        .item("custom", "Other...", "Enter a custom model name")
+        .interact()?;
+
+    if selection == "custom" {
+        Ok(input("Enter custom model name")
+            .placeholder("e.g. mistral-medium-latest")
+            .interact()?)
+    } else {
+        Ok(selection.to_string())
+    }
+}
@@ -0,0 +1,168 @@
+//! Splitting the staged changes into several commits: the model groups the staged files
+//! into logical commits (JSON: files + message per commit), then each group is staged on
+//! its own and committed after confirmation.
+//!
+//! The index is saved as a tree first, so aborting half way restores the original staging
+//! state (minus what was already committed). A reply that doesn't cover every staged file
+//! exactly once is rejected and the caller falls back to a single commit.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::generator::Generator;
+use crate::git;
+use crate::history;
+use crate::message::{self, FormatOptions};
+use crate::prompt::{self, PromptOptions};
+
+const SPLIT_PROMPT: &str = "Instead of a single commit message, split the diff into \
+    logical commits: group the changed files so that each group is one self-contained \
+    change. Unrelated changes go into separate groups, related ones stay together, and \
+    every file is in exactly one group. Write each group's message as described above. \
+    Reply with JSON only, no code fences, in the order the commits should be made: \
+    {\"commits\": [{\"files\": [\"path\", ...], \"message\": \"subject\\n\\nbody\"}]}";
+
+/// One proposed commit: the staged paths it takes and its message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CommitGroup {
+    pub files: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    commits: Vec<CommitGroup>,
+}
+
+/// The index as it was before splitting, restorable with `restore`.
+#[derive(Debug, Clone)]
+pub struct SavedIndex(String);
+
+impl SavedIndex {
+    pub fn save() -> Result<Self> {
+        git::write_index_tree().map(SavedIndex)
+    }
+
+    /// Put the saved staging state back. Changes committed since are no longer staged,
+    /// since they now match HEAD.
+    pub fn restore(&self) -> Result<()> {
+        git::read_index_tree(&self.0)
+    }
+}
+
+/// The staged files, as offered to the model.
+#[derive(Debug, Clone, Default)]
+pub struct StagedFiles {
+    pub paths: Vec<String>,
+    /// Original path of each renamed file; staged along with the new one.
+    origins: BTreeMap<String, String>,
+}
+
+impl StagedFiles {
+    /// The staged files. Fails on partially staged files, whose unstaged changes would
+    /// be committed along with them.
+    pub fn collect() -> Result<Self> {
+        let snapshot = git::snapshot()?;
+        let partial: Vec<&str> = snapshot
+            .staged()
+            .filter(|e| e.is_unstaged())
+            .map(|e| e.path.as_str())
+            .collect();
+        if !partial.is_empty() {
+            bail!(
+                "Partially staged files can't be split: {}",
+                partial.join(", ")
+            );
+        }
+        let mut staged = StagedFiles::default();
+        for entry in snapshot.staged() {
+            staged.paths.push(entry.path.clone());
+            if let Some(orig) = &entry.orig_path {
+                staged.origins.insert(entry.path.clone(), orig.clone());
+            }
+        }
+        Ok(staged)
+    }
+
+    /// Paths to stage for `group`: its files, plus where renamed ones came from.
+    fn pathspecs(&self, group: &CommitGroup) -> Vec<PathBuf> {
+        group
+            .files
+            .iter()
+            .flat_map(|f| std::iter::once(f).chain(self.origins.get(f)))
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+/// Ask the model how to split `diff`, the staged diff of `staged`.
+pub async fn propose(
+    generator: &Generator,
+    diff: &str,
+    staged: &StagedFiles,
+) -> Result<Vec<CommitGroup>> {
+    let cfg = Config::load().ok().flatten();
+    let options = PromptOptions::from_config(cfg.as_ref());
+    // The regular prompt keeps the message style; the split instructions follow it.
+    let style = prompt::build(None, &options, "", None).system;
+    let options = PromptOptions {
+        template: Some(format!("{}\n\n{}", style, SPLIT_PROMPT)),
+        // Already part of `style`.
+        commit_template: None,
+        ..options
+    };
+    let prompt = prompt::build(None, &options, diff, None);
+    let reply = generator.complete(&prompt.system, &prompt.user).await?;
+    let format = FormatOptions::from_config(cfg.as_ref());
+    Ok(parse_groups(&reply.message, &staged.paths)?
+        .into_iter()
+        .map(|group| CommitGroup {
+            message: message::format_commit_message(&group.message, &format),
+            ..group
+        })
+        .collect())
+}
+
+/// Parse the model's JSON and check that the groups cover each of `files` exactly once.
+pub fn parse_groups(reply: &str, files: &[String]) -> Result<Vec<CommitGroup>> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => bail!("The model did not reply with JSON"),
+    };
+    let reply: Reply =
+        serde_json::from_str(json).context("The model's JSON has the wrong shape")?;
+    let groups: Vec<CommitGroup> = reply
+        .commits
+        .into_iter()
+        .filter(|g| !g.files.is_empty())
+        .collect();
+    if groups.iter().any(|g| g.message.trim().is_empty()) {
+        bail!("The model left a commit message empty");
+    }
+
+    let expected: BTreeSet<&str> = files.iter().map(String::as_str).collect();
+    let mut seen = BTreeSet::new();
+    for path in groups.iter().flat_map(|g| &g.files) {
+        if !expected.contains(path.as_str()) {
+            bail!("The model listed a file that isn't staged: {}", path);
+        }
+        if !seen.insert(path.as_str()) {
+            bail!("The model put {} into more than one commit", path);
+        }
+    }
+    let missing: Vec<&str> = expected.difference(&seen).copied().collect();
+    if !missing.is_empty() {
+        bail!("The model left out: {}", missing.join(", "));
+    }
+    Ok(groups)
+}
+
+/// Stage exactly `group`'s files and commit them with its message.
+pub fn commit_group(staged: &StagedFiles, group: &CommitGroup) -> Result<()> {
+    git::unstage_all()?;
+    git::stage_files(&staged.pathspecs(group))?;
+    history::commit_and_record(&group.message)
+}
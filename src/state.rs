@@ -0,0 +1,161 @@
+//! Per-repository UI state remembered between runs (last release bump, last diff source).
+//!
+//! Stored as JSON next to the history (`.git/git-wiz/state.json`, resolved with
+//! `git rev-parse --git-path`). Like history, it is best-effort: a missing or unreadable
+//! file means "nothing remembered", and a failed write never fails the action.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::git::{self, DiffSource};
+use crate::read_only;
+
+/// Release menu choice, in the order both UIs list them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChoice {
+    Patch,
+    Minor,
+    Major,
+    Custom,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_bump: Option<ReleaseChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_source: Option<DiffSource>,
+}
+
+fn state_path() -> Result<PathBuf> {
+    git::git_path("git-wiz/state.json")
+}
+
+/// The remembered state for the current repository (default when there is none).
+pub fn load() -> RepoState {
+    state_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(state: &RepoState) -> Result<()> {
+    read_only::check("Remembering choices")?;
+    let path = state_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(state)?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Remember `choice` as the last release bump. Best-effort.
+pub fn remember_release_bump(choice: ReleaseChoice) {
+    let mut state = load();
+    state.release_bump = Some(choice);
+    let _ = save(&state);
+}
+
+/// Remember `source` as the last diff source picked for generation. Best-effort.
+#[cfg(feature = "cli")]
+pub fn remember_diff_source(source: DiffSource) {
+    let mut state = load();
+    state.diff_source = Some(source);
+    let _ = save(&state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRepo;
+
+    #[test]
+    fn state_round_trips_through_the_git_dir() {
+        let repo = TempRepo::new();
+        assert_eq!(load(), RepoState::default());
+
+        let state = RepoState {
+            release_bump: Some(ReleaseChoice::Custom),
+            diff_source: Some(DiffSource::AgainstRef("origin/main".to_string())),
+        };
+        save(&state).unwrap();
+        assert!(repo.path(".git/git-wiz/state.json").is_file());
+        assert_eq!(load(), state);
+
+        remember_release_bump(ReleaseChoice::Minor);
+        assert_eq!(
+            load(),
+            RepoState {
+                release_bump: Some(ReleaseChoice::Minor),
+                ..state
+            }
+        );
+    }
+
+    #[test]
+    fn missing_or_corrupt_state_is_the_default() {
+        let repo = TempRepo::new();
+        assert!(!repo.path(".git/git-wiz").exists());
+        assert_eq!(load(), RepoState::default());
+
+        repo.write(".git/git-wiz/state.json", "{\"release_bump\": \"huge\"");
+        assert_eq!(load(), RepoState::default());
+        repo.write(".git/git-wiz/state.json", "not json at all");
+        assert_eq!(load(), RepoState::default());
+
+        // Remembering replaces the corrupt file instead of failing.
+        remember_release_bump(ReleaseChoice::Patch);
+        assert_eq!(load().release_bump, Some(ReleaseChoice::Patch));
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn diff_source_is_remembered() {
+        let _repo = TempRepo::new();
+        remember_diff_source(DiffSource::Both);
+        assert_eq!(load().diff_source, Some(DiffSource::Both));
+        assert_eq!(load().release_bump, None);
+    }
+
+    #[test]
+    #[cfg(feature = "tui")]
+    fn cancelled_custom_release_is_not_remembered() {
+        use crate::tui::app::{App, Tab};
+        use crate::tui::tasks::TaskRunner;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let _repo = TempRepo::new();
+        remember_release_bump(ReleaseChoice::Minor);
+        let tasks = TaskRunner::new();
+        let mut app = App::new();
+        app.active_tab = Tab::Release;
+        app.action_index = 3;
+        let key = |app: &mut App, code| {
+            app.handle_global_key(&tasks, &KeyEvent::new(code, KeyModifiers::NONE));
+        };
+
+        // Escape out of the version prompt.
+        assert!(app.activate_selected_action(&tasks));
+        assert_eq!(app.modal.title, "Release Version");
+        key(&mut app, KeyCode::Esc);
+        assert_eq!(load().release_bump, Some(ReleaseChoice::Minor));
+
+        // A version that is refused doesn't count either.
+        app.activate_selected_action(&tasks);
+        for c in "1.0 beta".chars() {
+            key(&mut app, KeyCode::Char(c));
+        }
+        key(&mut app, KeyCode::Enter);
+        let status = app
+            .status
+            .as_ref()
+            .map(|s| s.message.as_str())
+            .unwrap_or_default();
+        assert!(status.contains("cannot contain spaces"), "{}", status);
+        assert_eq!(load().release_bump, Some(ReleaseChoice::Minor));
+    }
+}
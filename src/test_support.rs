@@ -0,0 +1,131 @@
+//! Throwaway git repositories for tests.
+//!
+//! `TempRepo::new` runs `git init` in a fresh directory under the system temp dir and
+//! points `git::command` at it for the current test thread (tests run one per thread),
+//! so the code under test needs no `--repo`. The directory is removed on drop.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    static CURRENT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// The repository `git::command` runs in on this thread, if a test made one.
+pub fn current_repo() -> Option<PathBuf> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Point `git::command` at `repo` on this thread, e.g. one spawned by the code under test.
+pub fn set_current_repo(repo: Option<PathBuf>) {
+    CURRENT.with(|current| *current.borrow_mut() = repo);
+}
+
+pub struct TempRepo {
+    pub root: PathBuf,
+}
+
+impl TempRepo {
+    /// An empty repository on `master`, with an identity and signing off.
+    pub fn new() -> Self {
+        let root = temp_dir("repo");
+        let repo = Self { root };
+        repo.git(&["init", "-q", "-b", "master"]);
+        repo.git(&["config", "user.name", "Test"]);
+        repo.git(&["config", "user.email", "test@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo.git(&["config", "tag.gpgsign", "false"]);
+        set_current_repo(Some(repo.root.clone()));
+        repo
+    }
+
+    /// A bare repository next to this one, added as `origin`.
+    pub fn with_origin(self) -> Self {
+        let origin = self.origin();
+        run(Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&origin));
+        self.git(&["remote", "add", "origin", &origin.to_string_lossy()]);
+        self
+    }
+
+    /// Where `with_origin` puts the bare repository.
+    pub fn origin(&self) -> PathBuf {
+        self.root.with_extension("origin.git")
+    }
+
+    /// Run git in the repository and return its trimmed stdout; panics when it fails.
+    pub fn git(&self, args: &[&str]) -> String {
+        run(Command::new("git").arg("-C").arg(&self.root).args(args))
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    pub fn write(&self, relative: &str, contents: &str) {
+        let path = self.path(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Stage everything and commit it; returns the new commit's hash.
+    pub fn commit_all(&self, message: &str) -> String {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().take());
+        let _ = fs::remove_dir_all(&self.root);
+        let _ = fs::remove_dir_all(self.origin());
+    }
+}
+
+/// A new, unique directory under the system temp dir.
+fn temp_dir(kind: &str) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let dir = std::env::temp_dir().join(format!(
+        "git-wiz-test-{}-{}-{}-{}",
+        kind,
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed),
+        nanos
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(command: &mut Command) -> String {
+    let output = command.output().expect("git runs");
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// `chmod +x`, for hooks.
+pub fn make_executable(path: &std::path::Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
@@ -1,22 +1,47 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui_textarea::{Input, TextArea};
-
-use crate::config::{Config, Provider};
-use crate::generator::{
-    AnthropicGenerator, GeminiGenerator, Generator, MockGenerator, OpenAIGenerator,
-};
+use std::collections::BTreeSet;
+use std::sync::mpsc::Sender;
+use std::time::Instant;
+
+use crate::breaking;
+use crate::config::{self, AutoPush, Config, IssueKeyword};
+use crate::conflicts;
+use crate::conventional;
+use crate::diff_parse::FileDiff;
+use crate::generator::{self, Generator, ModelUnavailable, TokenUsage};
 use crate::git;
+use crate::history;
+use crate::hook::{self, HookOutcome};
+use crate::http;
+use crate::message;
+use crate::notify;
+use crate::offline;
+use crate::pr;
+use crate::prompt::{self, Refinement};
+use crate::read_only;
 use crate::release;
+use crate::release_notes;
+use crate::repo_config;
+use crate::review;
+use crate::scope::{self, ScopeFix};
 use crate::setup;
+use crate::state::{self, ReleaseChoice};
 use crate::tui::runtime;
-use crate::tui::tasks::{TaskEvent, TaskKind, TaskResult, TaskRunner};
+use crate::tui::tasks::{FollowUp, TaskEvent, TaskKind, TaskResult, TaskRunner};
+use crate::usage;
+
+/// Lines of a rejected commit's output shown in the modal; the log keeps all of them.
+const COMMIT_OUTPUT_LINES: usize = 12;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ModalKind {
     None,
     Confirm,
     TextInput,
+    // Lists `App::branch_picker`; Enter switches, `n` creates a branch
+    BranchPicker,
 }
 
 
@@ -25,16 +50,80 @@ pub enum ConfirmPurpose {
     ClearConfig,
     PushAllTags,
 
+    // Generate tab: staged diff matches the last git-wiz commit
+    GenerateDuplicateStaged,
+    // Generate tab: a rebase, cherry-pick or revert is in progress
+    GenerateDuringOperation,
+    // Generate tab: pre-commit review ("what will be committed")
+    CommitReview,
+    // Generate tab: post_generate_command rejected the generated / edited message
+    HookRejectedGenerated,
+    HookRejectedCommit,
+    // Generate tab: the reply hit the output token limit
+    RaiseOutputLimit,
+    // Generate tab: the provider can't be reached
+    UseMockGenerator,
+    // Generate tab: the changed paths suggest another scope
+    UseSuggestedScope,
+    // Generate tab, amend mode: rewrite HEAD
+    AmendCommit,
+    // Generate tab: a pre-commit / commit-msg hook rejected the commit
+    CommitWithoutHooks,
+    // Generate tab: generation blocked by merge conflicts / a resolved file can be staged
+    ResolveConflicts,
+    StageResolvedConflict,
+
+    // Generate tab: push after committing (`auto_push: ask`)
+    PushAfterCommit,
+    // Push tab: the branch is behind its upstream; pull --ff-only, then push
+    PullThenPush,
+    // Push tab: git push --force-with-lease
+    ForcePushBranch,
+    // Push: origin has `pending_remote_branch`; rebase onto it and push (`f` overwrites it)
+    PushOverRemoteBranch,
+    // Push tab: a pull couldn't fast-forward; log the diverging commits
+    ShowDivergence,
+    // Branch picker: local changes blocked the switch; stash them, then switch
+    StashAndSwitch,
+    // Push tab, Tags list: delete `pending_tag` locally / locally and on origin
+    DeleteTag,
+    DeleteRemoteTag,
+
     // Release flow confirmations
     ReleaseTrigger,
+    // Release: the tree has changes; stash them, release, then pop them
+    StashAndRelease,
+    // Release: it failed after the bump commit; undo `pending_rollback` (`h`: --hard)
+    RollBackRelease,
+    DraftReleaseNotes,
+    // PR description: send `pending_pr` to the model
+    SendPrDescription,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextInputPurpose {
-    PushSpecificTag,
+    // Push tab: a release tag's name, typed out to delete it on origin
+    DeleteReleaseTag,
+    // Push tab: branch to create at a detached HEAD before pushing
+    PushNewBranch,
+    // Branch picker: branch to create at HEAD and switch to
+    NewBranch,
+
+    // Generate tab inputs
+    GenerateHint,
+    LinkIssues,
+    PrBase,
+    Refine,
+
+    // Diff tab inputs
+    DiffRef,
+
+    // Config tab inputs
+    Language,
 
     // Release flow inputs
     ReleaseCustomVersion,
+    ReleaseRemoteUrl,
 }
 
 #[derive(Debug, Clone)]
@@ -64,29 +153,83 @@ impl ModalState {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DiffViewSource {
     Staged,
     Unstaged,
     Both,
+    /// `git diff <ref>...HEAD`.
+    AgainstRef(String),
 }
 
 impl DiffViewSource {
-    pub fn label(self) -> &'static str {
+    pub fn label(&self) -> String {
         match self {
-            DiffViewSource::Staged => "Staged",
-            DiffViewSource::Unstaged => "Unstaged",
-            DiffViewSource::Both => "Both",
+            DiffViewSource::Staged => "Staged".to_string(),
+            DiffViewSource::Unstaged => "Unstaged".to_string(),
+            DiffViewSource::Both => "Both".to_string(),
+            DiffViewSource::AgainstRef(reference) => format!("{}...HEAD", reference),
         }
     }
 
-    pub fn to_git_source(self) -> git::DiffSource {
+    pub fn to_git_source(&self) -> git::DiffSource {
         match self {
             DiffViewSource::Staged => git::DiffSource::Staged,
             DiffViewSource::Unstaged => git::DiffSource::Unstaged,
             DiffViewSource::Both => git::DiffSource::Both,
+            DiffViewSource::AgainstRef(reference) => git::DiffSource::AgainstRef(reference.clone()),
+        }
+    }
+}
+
+/// Stage tab: the hunks of the unstaged (or staged) diff, picked to stage (or unstage).
+#[derive(Debug, Clone)]
+pub struct HunkView {
+    /// Picking from the staged diff, to take hunks out of the index.
+    pub unstage: bool,
+    pub files: Vec<FileDiff>,
+    /// `(file, hunk)` per row; `None` for a file that only goes whole (no hunks).
+    pub rows: Vec<(usize, Option<usize>)>,
+    pub index: usize,
+    pub picked: BTreeSet<usize>,
+    /// What the last apply couldn't take.
+    pub failures: Vec<git::HunkFailure>,
+}
+
+impl HunkView {
+    fn new(unstage: bool, files: Vec<FileDiff>, failures: Vec<git::HunkFailure>) -> Self {
+        let rows = files
+            .iter()
+            .enumerate()
+            .flat_map(|(f, file)| {
+                let hunks: Vec<Option<usize>> = if file.hunks.is_empty() {
+                    vec![None]
+                } else {
+                    (0..file.hunks.len()).map(Some).collect()
+                };
+                hunks.into_iter().map(move |h| (f, h))
+            })
+            .collect();
+        Self {
+            unstage,
+            files,
+            rows,
+            index: 0,
+            picked: BTreeSet::new(),
+            failures,
         }
     }
+
+    /// Why the last apply left `row` out, if it did.
+    pub fn failure(&self, row: usize) -> Option<&str> {
+        let (f, hunk) = *self.rows.get(row)?;
+        let file = &self.files[f];
+        let lines = hunk.map(|h| &file.hunks[h].lines);
+        self.failures
+            .iter()
+            .find(|failure| failure.path == file.path && failure.lines.as_ref() == lines)
+            .map(|failure| failure.message.as_str())
+    }
 }
 
 /// Per-tab selectable action menu items (v1).
@@ -94,12 +237,33 @@ impl DiffViewSource {
 pub enum ActionItem {
     // Generate tab
     GenerateFromStaged,
+    GenerateWithHint,
     Commit,
+    RefineMessage,
+    LinkIssues,
+    LoadTemplate,
     ClearMessage,
+    AmendLast,
+    GeneratePrDescription,
+    // Generate tab, once a PR description was generated
+    CopyPrDescription,
+    WritePrDescription,
+    DiscardPrDescription,
+
+    // Generate tab, amend mode
+    AmendCommit,
+    GenerateAmend,
+    ExitAmend,
+
+    // Generate tab, resolving merge conflicts
+    OpenConflict,
+    ExitConflicts,
 
     // Stage tab (wired)
+    StageHunks,
     StagePatch,
     StageAll,
+    UnstageHunks,
     UnstagePatch,
     UnstageAll,
 
@@ -107,9 +271,13 @@ pub enum ActionItem {
     ViewStaged,
     ViewUnstaged,
     ViewBoth,
+    ViewAgainstRef,
 
     // Push tab (wired)
     PushBranch,
+    PushForceWithLease,
+    Pull,
+    SwitchBranch,
     PushSpecificTag,
     PushAllTags,
 
@@ -118,9 +286,17 @@ pub enum ActionItem {
     ReleaseMinor,
     ReleaseMajor,
     ReleaseCustom,
+    // Release tab, once release notes were drafted
+    CopyReleaseNotes,
+    WriteReleaseNotes,
+    PublishReleaseNotes,
+    DiscardReleaseNotes,
 
     // Config tab (wired)
     RunSetupWizard,
+    ChangeModel,
+    ChangeCommitStyle,
+    ChangeLanguage,
     ReloadConfig,
     ClearConfig,
 }
@@ -129,19 +305,40 @@ impl ActionItem {
     pub fn label(self) -> &'static str {
         match self {
             ActionItem::GenerateFromStaged => "Generate (staged)",
+            ActionItem::GenerateWithHint => "Generate with hint…",
             ActionItem::Commit => "Commit",
+            ActionItem::RefineMessage => "Refine message…",
+            ActionItem::LinkIssues => "Link issues (footers)",
+            ActionItem::LoadTemplate => "Load commit template",
             ActionItem::ClearMessage => "Clear message",
-
+            ActionItem::AmendLast => "Amend last commit…",
+            ActionItem::GeneratePrDescription => "PR description…",
+            ActionItem::CopyPrDescription => "Copy PR description",
+            ActionItem::WritePrDescription => "Write PR_BODY.md",
+            ActionItem::DiscardPrDescription => "Done with PR description",
+
+            ActionItem::AmendCommit => "Amend HEAD (rewrites history)",
+            ActionItem::GenerateAmend => "Generate message (HEAD + staged)",
+            ActionItem::ExitAmend => "Exit amend mode",
+            ActionItem::OpenConflict => "Open next conflicted file",
+            ActionItem::ExitConflicts => "Stop resolving",
+
+            ActionItem::StageHunks => "Stage hunks…",
             ActionItem::StagePatch => "Stage patch (git add -p)",
             ActionItem::StageAll => "Stage all (git add -A)",
+            ActionItem::UnstageHunks => "Unstage hunks…",
             ActionItem::UnstagePatch => "Unstage patch (interactive)",
             ActionItem::UnstageAll => "Unstage all",
 
             ActionItem::ViewStaged => "View staged diff",
             ActionItem::ViewUnstaged => "View unstaged diff",
             ActionItem::ViewBoth => "View both diffs",
+            ActionItem::ViewAgainstRef => "View diff against ref…",
 
             ActionItem::PushBranch => "Push branch",
+            ActionItem::PushForceWithLease => "Force-push branch (with lease)",
+            ActionItem::Pull => "Pull (fast-forward only)",
+            ActionItem::SwitchBranch => "Switch / create branch…",
             ActionItem::PushSpecificTag => "Push specific tag",
             ActionItem::PushAllTags => "Push all tags",
 
@@ -149,12 +346,52 @@ impl ActionItem {
             ActionItem::ReleaseMinor => "Release (minor): bump, commit, tag, push",
             ActionItem::ReleaseMajor => "Release (major): bump, commit, tag, push",
             ActionItem::ReleaseCustom => "Release (custom): bump, commit, tag, push",
+            ActionItem::CopyReleaseNotes => "Copy release notes",
+            ActionItem::WriteReleaseNotes => "Write RELEASE_NOTES.md",
+            ActionItem::PublishReleaseNotes => "Set GitHub release body (gh)",
+            ActionItem::DiscardReleaseNotes => "Done with release notes",
 
             ActionItem::RunSetupWizard => "Run setup wizard",
+            ActionItem::ChangeModel => "Change model",
+            ActionItem::ChangeCommitStyle => "Change commit style",
+            ActionItem::ChangeLanguage => "Change message language",
             ActionItem::ReloadConfig => "Reload config",
             ActionItem::ClearConfig => "Clear config",
         }
     }
+
+    /// What the action changes, as named when read-only mode blocks it; `None` for
+    /// actions that only read.
+    pub fn mutation(self) -> Option<&'static str> {
+        match self {
+            ActionItem::Commit => Some("Committing"),
+            ActionItem::AmendLast | ActionItem::AmendCommit => Some("Amending"),
+            ActionItem::OpenConflict => Some("Editing files"),
+            ActionItem::StageHunks | ActionItem::StagePatch | ActionItem::StageAll => {
+                Some("Staging")
+            }
+            ActionItem::UnstageHunks | ActionItem::UnstagePatch | ActionItem::UnstageAll => {
+                Some("Unstaging")
+            }
+            ActionItem::PushBranch | ActionItem::PushForceWithLease => Some("Pushing"),
+            ActionItem::Pull => Some("Pulling"),
+            ActionItem::SwitchBranch => Some("Switching branches"),
+            ActionItem::PushSpecificTag | ActionItem::PushAllTags => Some("Pushing tags"),
+            ActionItem::ReleasePatch
+            | ActionItem::ReleaseMinor
+            | ActionItem::ReleaseMajor
+            | ActionItem::ReleaseCustom => Some("Releasing"),
+            ActionItem::WritePrDescription => Some("Writing the PR description"),
+            ActionItem::WriteReleaseNotes => Some("Writing release notes"),
+            ActionItem::PublishReleaseNotes => Some("Publishing release notes"),
+            ActionItem::RunSetupWizard
+            | ActionItem::ChangeModel
+            | ActionItem::ChangeCommitStyle
+            | ActionItem::ChangeLanguage
+            | ActionItem::ClearConfig => Some("Changing the config"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -233,19 +470,104 @@ pub struct App {
     pub running_task: Option<RunningTaskSnapshot>,
 
     // Generate tab state
+    pub diff_source: git::DiffSource,
     pub diff_source_label: String,
+    /// The diff was cut to fit the prompt budget, so the message may miss changes.
+    pub diff_trimmed: bool,
     pub diff_summary: String,
+    /// The staged file with the most changed lines and its share, e.g. `Cargo.lock (90%)`.
+    pub largest_file: String,
+    /// Prompt tokens and cost of generating from the staged diff, e.g. `~3,200 tokens, est. $0.004`.
+    pub cost_estimate: String,
+    /// Tokens the last generation used, and all generations this session.
+    pub last_tokens: Option<TokenUsage>,
+    pub session_tokens: TokenUsage,
+    /// Reply limit raised after a cut-off reply, for the rest of the session; and the
+    /// limit offered by the open "Reply cut off" modal.
+    pub output_limit: Option<u32>,
+    pub pending_output_limit: Option<u32>,
+    /// Scope offered by the open "Scope" modal.
+    pub pending_scope: Option<String>,
+    /// Set while the generation under way rewrites the editor's message with feedback.
+    pub refinement: Option<Refinement>,
+    /// Hint sent with every generation from the staged diff (`h`), like `--hint`.
+    pub generate_hint: Option<String>,
     pub provider_label: String,
     pub model_label: String,
+    /// Where generation requests go (Config tab), e.g. a local OpenAI-compatible server.
+    pub endpoint_label: String,
+    pub commit_style_label: String,
+    pub language_label: String,
+    /// System prompt in use: built-in or custom (`prompt.txt` / `prompt_template`).
+    pub prompt_label: String,
     pub mock_mode: bool,
+    pub issue_candidates: Vec<u64>,
+    // Ask the background refresher to update `snapshot` / `diff_summary` as soon as it can.
+    pub summary_refresh_requested: bool,
+    // Latest repository snapshot (branch, upstream, changed paths) from the refresher.
+    pub snapshot: Option<git::RepoSnapshot>,
+    // post_generate_command rejected the editor's message and it was not overridden yet.
+    pub hook_rejected: bool,
+    // The editor holds part of a reply that is still streaming in.
+    pub streaming: bool,
+    // Amend mode: the commit being amended. Enter amends instead of committing.
+    pub amending: Option<git::HeadCommit>,
+    // Conflict flow: Enter opens conflicted files in the editor (generation was blocked).
+    pub resolving_conflicts: bool,
+    // Conflicted file that lost its markers and is waiting for "stage it?" confirmation.
+    pub conflict_path: Option<String>,
+    // Branch picker: local branches and the selected row.
+    pub branch_picker: Vec<git::Branch>,
+    pub branch_picker_index: usize,
+    // Branch a switch was headed for when local changes blocked it.
+    pub pending_switch: Option<String>,
+    // Push: origin's branch of the same name that a push without upstream stopped on.
+    pub pending_remote_branch: Option<git::RemoteBranchDiverged>,
+    // Stage tab: hunks being picked, instead of the file list.
+    pub hunk_view: Option<HunkView>,
+    // Push tab: local tags (newest first), the selected row, and the one being deleted.
+    pub tags: Vec<git::TagInfo>,
+    pub tag_index: usize,
+    pub pending_tag: Option<String>,
 
     // Diff tab state
     pub diff_view_source: DiffViewSource,
     pub diff_scroll: usize,
     pub diff_text: String,
+    // Show the viewer's diff word by word (`word_diff`, toggled with `w`).
+    pub diff_words: bool,
+
+    // Base the PR description was generated against.
+    pub pr_base: Option<String>,
+    // Commits and diff collected for a PR description, waiting for the size confirmation.
+    pub pending_pr: Option<pr::BranchChanges>,
+    // Generated PR description; shown instead of the editor, with copy/write actions.
+    pub pr_description: Option<String>,
 
     // Release tab state
     pub pending_release_version: Option<String>,
+    // Bump behind `pending_release_version`, to plan it again for another scope (None: custom).
+    pub pending_release_bump: Option<release::BumpKind>,
+    // Which version releases bump; `w` in the final confirmation switches it when the
+    // package and its workspace both have one.
+    pub release_scope: release::VersionScope,
+    // CHANGELOG.md section previewed in the final confirmation (`changelog` config).
+    pub pending_changelog: Option<String>,
+    // Preflight checks for the Flow panel, read when the Release tab opens.
+    pub preflight_checks: Vec<release::PreflightCheck>,
+    // Checks left out of the pending release; toggled with 1-9 in the final confirmation.
+    pub preflight_skip: Vec<String>,
+    // Release that failed after the bump commit, waiting for "roll it back?".
+    pub pending_rollback: Option<release::IncompleteRelease>,
+    // Tag whose release notes are being drafted / shown.
+    pub release_notes_tag: Option<String>,
+    // Drafted release notes; switches the Release tab actions to copy/write/publish.
+    pub release_notes: Option<String>,
+    // Release action last used in this repository; preselected when the tab opens.
+    pub last_release_action: Option<ActionItem>,
+
+    // Config tab state
+    pub latency_stats: Vec<usage::LatencyStats>,
 
     // Editor
     pub commit_editor: TextArea<'static>,
@@ -276,17 +598,64 @@ impl App {
 
             running_task: None,
 
-            diff_source_label: "Staged (recommended)".to_string(),
+            diff_source: git::DiffSource::Staged,
+            diff_source_label: diff_source_label(&git::DiffSource::Staged),
+            diff_trimmed: false,
             diff_summary: "No diff loaded".to_string(),
+            largest_file: "-".to_string(),
+            cost_estimate: "-".to_string(),
+            last_tokens: None,
+            session_tokens: TokenUsage::default(),
+            output_limit: None,
+            pending_output_limit: None,
+            pending_scope: None,
+            refinement: None,
+            generate_hint: None,
             provider_label: "Not configured".to_string(),
             model_label: "-".to_string(),
+            endpoint_label: "-".to_string(),
+            commit_style_label: "-".to_string(),
+            language_label: "-".to_string(),
+            prompt_label: "-".to_string(),
             mock_mode: false,
+            issue_candidates: vec![],
+            summary_refresh_requested: true,
+            snapshot: None,
+            hook_rejected: false,
+            streaming: false,
+            amending: None,
+            resolving_conflicts: false,
+            conflict_path: None,
+            branch_picker: Vec::new(),
+            branch_picker_index: 0,
+            pending_switch: None,
+            pending_remote_branch: None,
+            hunk_view: None,
+            tags: Vec::new(),
+            tag_index: 0,
+            pending_tag: None,
 
             diff_view_source: DiffViewSource::Staged,
             diff_scroll: 0,
             diff_text: String::new(),
+            diff_words: Config::load().ok().flatten().is_some_and(|c| c.word_diff),
+
+            pr_base: None,
+            pending_pr: None,
+            pr_description: None,
 
             pending_release_version: None,
+            pending_release_bump: None,
+            release_scope: release::VersionScope::default(),
+            pending_changelog: None,
+            preflight_checks: Vec::new(),
+            preflight_skip: Vec::new(),
+            pending_rollback: None,
+            release_notes_tag: None,
+            release_notes: None,
+            last_release_action: state::load().release_bump.map(release_action),
+
+            latency_stats: load_latency_stats(),
 
             commit_editor: editor,
 
@@ -301,6 +670,41 @@ impl App {
         }
     }
 
+    /// Record which diff the current message was generated from, and whether files were
+    /// left out of it (`filtered`) or it was cut to fit the prompt (`trimmed`).
+    pub fn set_diff_source(&mut self, source: git::DiffSource, filtered: bool, trimmed: bool) {
+        let mut label = diff_source_label(&source);
+        if filtered {
+            label.push_str(", filtered");
+        }
+        if trimmed {
+            label.push_str(", trimmed to fit");
+        }
+        self.diff_source_label = label;
+        self.diff_source = source;
+        self.diff_trimmed = trimmed;
+    }
+
+    /// Refresh the staged summary on the next idle tick (coalesced).
+    pub fn request_summary_refresh(&mut self) {
+        self.summary_refresh_requested = true;
+    }
+
+    /// Add a generation's tokens to the session total and log both.
+    pub fn record_tokens(&mut self, tokens: TokenUsage) {
+        self.last_tokens = Some(tokens);
+        self.session_tokens += tokens;
+        self.log(format!(
+            "Used {} (session: {}).",
+            tokens, self.session_tokens
+        ));
+    }
+
+    /// Re-read the usage ledger for the Config tab's Performance block.
+    pub fn refresh_latency_stats(&mut self) {
+        self.latency_stats = load_latency_stats();
+    }
+
     pub fn set_status(&mut self, level: StatusLevel, message: impl Into<String>) {
         self.status = Some(StatusLine {
             level,
@@ -310,14 +714,37 @@ impl App {
 
     pub fn actions_for_active_tab(&self) -> &'static [ActionItem] {
         match self.active_tab {
+            Tab::Generate if self.resolving_conflicts => {
+                &[ActionItem::OpenConflict, ActionItem::ExitConflicts]
+            }
+            Tab::Generate if self.amending.is_some() => &[
+                ActionItem::AmendCommit,
+                ActionItem::GenerateAmend,
+                ActionItem::StagePatch,
+                ActionItem::StageAll,
+                ActionItem::ExitAmend,
+            ],
+            Tab::Generate if self.pr_description.is_some() => &[
+                ActionItem::CopyPrDescription,
+                ActionItem::WritePrDescription,
+                ActionItem::DiscardPrDescription,
+            ],
             Tab::Generate => &[
                 ActionItem::GenerateFromStaged,
+                ActionItem::GenerateWithHint,
                 ActionItem::Commit,
+                ActionItem::RefineMessage,
+                ActionItem::LinkIssues,
+                ActionItem::LoadTemplate,
                 ActionItem::ClearMessage,
+                ActionItem::AmendLast,
+                ActionItem::GeneratePrDescription,
             ],
             Tab::Stage => &[
+                ActionItem::StageHunks,
                 ActionItem::StagePatch,
                 ActionItem::StageAll,
+                ActionItem::UnstageHunks,
                 ActionItem::UnstagePatch,
                 ActionItem::UnstageAll,
             ],
@@ -325,12 +752,22 @@ impl App {
                 ActionItem::ViewStaged,
                 ActionItem::ViewUnstaged,
                 ActionItem::ViewBoth,
+                ActionItem::ViewAgainstRef,
             ],
             Tab::Push => &[
                 ActionItem::PushBranch,
+                ActionItem::PushForceWithLease,
+                ActionItem::Pull,
+                ActionItem::SwitchBranch,
                 ActionItem::PushSpecificTag,
                 ActionItem::PushAllTags,
             ],
+            Tab::Release if self.release_notes.is_some() => &[
+                ActionItem::CopyReleaseNotes,
+                ActionItem::WriteReleaseNotes,
+                ActionItem::PublishReleaseNotes,
+                ActionItem::DiscardReleaseNotes,
+            ],
             Tab::Release => &[
                 ActionItem::ReleasePatch,
                 ActionItem::ReleaseMinor,
@@ -339,12 +776,35 @@ impl App {
             ],
             Tab::Config => &[
                 ActionItem::RunSetupWizard,
+                ActionItem::ChangeModel,
+                ActionItem::ChangeCommitStyle,
+                ActionItem::ChangeLanguage,
                 ActionItem::ReloadConfig,
                 ActionItem::ClearConfig,
             ],
         }
     }
 
+    /// Action selected when the active tab opens: the last used release kind on the
+    /// Release tab, the first action elsewhere.
+    fn initial_action_index(&self) -> usize {
+        self.actions_for_active_tab()
+            .iter()
+            .position(|a| Some(*a) == self.last_release_action)
+            .unwrap_or(0)
+    }
+
+    fn remember_release_action(&mut self, item: ActionItem) {
+        let choice = match item {
+            ActionItem::ReleasePatch => ReleaseChoice::Patch,
+            ActionItem::ReleaseMinor => ReleaseChoice::Minor,
+            ActionItem::ReleaseMajor => ReleaseChoice::Major,
+            _ => ReleaseChoice::Custom,
+        };
+        state::remember_release_bump(choice);
+        self.last_release_action = Some(item);
+    }
+
     pub fn clamp_action_index(&mut self) {
         let len = self.actions_for_active_tab().len();
         if len == 0 {
@@ -383,6 +843,12 @@ impl App {
         let Some(action) = self.selected_action() else {
             return false;
         };
+        // The helpers refuse too; checking here explains it before any modal opens.
+        if let Err(e) = action.mutation().map_or(Ok(()), read_only::check) {
+            self.set_status(StatusLevel::Info, e.to_string());
+            self.log(format!("{} blocked: read-only mode.", action.label()));
+            return true;
+        }
 
         match action {
             // Generate tab
@@ -390,22 +856,109 @@ impl App {
                 let _started = self.start_generate_from_staged(tasks);
                 true
             }
+            ActionItem::GenerateWithHint => {
+                self.open_hint_modal();
+                true
+            }
             ActionItem::Commit => {
                 let _started = self.start_commit_from_editor(tasks);
                 true
             }
+            ActionItem::RefineMessage => {
+                self.open_refine_modal();
+                true
+            }
+            ActionItem::LinkIssues => {
+                self.open_link_issues_modal();
+                true
+            }
+            ActionItem::LoadTemplate => {
+                self.load_commit_template();
+                true
+            }
             ActionItem::ClearMessage => {
                 self.clear_editor();
                 true
             }
+            ActionItem::AmendLast => {
+                self.enter_amend_mode();
+                true
+            }
+            ActionItem::GeneratePrDescription => {
+                self.open_pr_base_modal();
+                true
+            }
+            ActionItem::CopyPrDescription => {
+                let body = self.pr_description.clone().unwrap_or_default();
+                match pr::copy(&body) {
+                    Ok(()) => {
+                        self.set_status(StatusLevel::Success, "PR description copied.");
+                        self.log("Copied the PR description to the clipboard.");
+                    }
+                    Err(e) => self.set_status(StatusLevel::Error, format!("{:#}", e)),
+                }
+                true
+            }
+            ActionItem::WritePrDescription => {
+                let body = self.pr_description.clone().unwrap_or_default();
+                match pr::write_file(&body) {
+                    Ok(path) => {
+                        self.set_status(StatusLevel::Success, format!("Wrote {}", path.display()));
+                        self.log(format!("Wrote {}", path.display()));
+                    }
+                    Err(e) => self.set_status(StatusLevel::Error, format!("{:#}", e)),
+                }
+                true
+            }
+            ActionItem::DiscardPrDescription => {
+                self.pr_description = None;
+                self.pr_base = None;
+                self.action_index = 0;
+                true
+            }
+            ActionItem::AmendCommit => {
+                self.confirm_amend(tasks);
+                true
+            }
+            ActionItem::GenerateAmend => {
+                let _started = self.start_generate_for_amend(tasks);
+                true
+            }
+            ActionItem::ExitAmend => {
+                self.exit_amend_mode();
+                self.set_status(StatusLevel::Info, "Left amend mode.");
+                true
+            }
+            // Suspended by the input layer: the editor runs in the terminal.
+            ActionItem::OpenConflict => {
+                if let Err(e) = self.open_next_conflict(tasks) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                }
+                true
+            }
+            ActionItem::ExitConflicts => {
+                self.resolving_conflicts = false;
+                self.action_index = 0;
+                self.set_status(StatusLevel::Info, "Conflicts left for later.");
+                true
+            }
 
             // Stage tab (interactive patch ops are suspended by the input layer)
+            ActionItem::StageHunks => {
+                self.open_hunk_view(false);
+                true
+            }
+            ActionItem::UnstageHunks => {
+                self.open_hunk_view(true);
+                true
+            }
             ActionItem::StagePatch => {
+                self.hunk_view = None;
                 self.set_status(StatusLevel::Info, "Switching to terminal for interactive staging…");
                 self.log("Switching to terminal: git add -p (interactive)");
                 if let Err(e) = self.stage_patch() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Stage patch failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Stage patch failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Staging complete.");
                     self.log("Staged changes interactively.");
@@ -413,18 +966,20 @@ impl App {
                 true
             }
             ActionItem::StageAll => {
+                self.hunk_view = None;
                 let _started = self.start_stage_all(tasks);
                 true
             }
             ActionItem::UnstagePatch => {
+                self.hunk_view = None;
                 self.set_status(
                     StatusLevel::Info,
                     "Switching to terminal for interactive unstaging…",
                 );
                 self.log("Switching to terminal: unstage interactively");
                 if let Err(e) = self.unstage_patch() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Unstage patch failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Unstage patch failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Unstaging complete.");
                     self.log("Unstaged changes interactively.");
@@ -432,9 +987,10 @@ impl App {
                 true
             }
             ActionItem::UnstageAll => {
+                self.hunk_view = None;
                 if let Err(e) = self.unstage_all() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Unstage all failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Unstage all failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Unstaged all changes.");
                     self.log("Unstaged all changes.");
@@ -455,21 +1011,37 @@ impl App {
                 let _started = self.start_load_diff(tasks, DiffViewSource::Both);
                 true
             }
+            ActionItem::ViewAgainstRef => {
+                self.open_diff_ref_modal();
+                true
+            }
 
             // Push tab (wired)
             ActionItem::PushBranch => {
                 let _started = self.start_push_branch(tasks);
                 true
             }
+            ActionItem::PushForceWithLease => {
+                self.open_force_push_modal();
+                true
+            }
+            ActionItem::Pull => {
+                let _started = self.start_pull(tasks);
+                true
+            }
+            ActionItem::SwitchBranch => {
+                self.open_branch_picker();
+                true
+            }
             ActionItem::PushSpecificTag => {
-                self.modal = ModalState {
-                    kind: ModalKind::TextInput,
-                    title: "Push Tag".to_string(),
-                    message: "Enter a tag to push (e.g. v0.2.3)".to_string(),
-                    confirm_purpose: None,
-                    input_purpose: Some(TextInputPurpose::PushSpecificTag),
-                    input_value: String::new(),
-                };
+                // Pick it from the Tags list instead of typing it.
+                self.refresh_tags();
+                if self.tags.is_empty() {
+                    self.set_status(StatusLevel::Info, "No local tags to push.");
+                } else {
+                    self.focus = Focus::RightPane;
+                    self.set_status(StatusLevel::Info, "Pick a tag with ↑/↓, then p pushes it.");
+                }
                 true
             }
             ActionItem::PushAllTags => {
@@ -485,10 +1057,23 @@ impl App {
             }
 
             // Release tab (v1)
-            ActionItem::ReleasePatch => self.start_release_bump("patch"),
-            ActionItem::ReleaseMinor => self.start_release_bump("minor"),
-            ActionItem::ReleaseMajor => self.start_release_bump("major"),
+            ActionItem::ReleasePatch => {
+                self.remember_release_action(action);
+                self.start_release_bump("patch")
+            }
+            ActionItem::ReleaseMinor => {
+                self.remember_release_action(action);
+                self.start_release_bump("minor")
+            }
+            ActionItem::ReleaseMajor => {
+                self.remember_release_action(action);
+                self.start_release_bump("major")
+            }
             ActionItem::ReleaseCustom => {
+                // Remembered once a version is entered, not when the prompt is cancelled.
+                if self.release_blocked() {
+                    return true;
+                }
                 self.modal = ModalState {
                     kind: ModalKind::TextInput,
                     title: "Release Version".to_string(),
@@ -499,22 +1084,85 @@ impl App {
                 };
                 true
             }
+            ActionItem::CopyReleaseNotes => {
+                let notes = self.release_notes.clone().unwrap_or_default();
+                match release_notes::copy(&notes) {
+                    Ok(()) => {
+                        self.set_status(StatusLevel::Success, "Release notes copied.");
+                        self.log("Copied release notes to the clipboard.");
+                    }
+                    Err(e) => self.set_status(StatusLevel::Error, format!("{:#}", e)),
+                }
+                true
+            }
+            ActionItem::WriteReleaseNotes => {
+                let notes = self.release_notes.clone().unwrap_or_default();
+                match release_notes::write_file(&notes) {
+                    Ok(path) => {
+                        self.set_status(StatusLevel::Success, format!("Wrote {}", path.display()));
+                        self.log(format!("Wrote {}", path.display()));
+                    }
+                    Err(e) => self.set_status(StatusLevel::Error, format!("{:#}", e)),
+                }
+                true
+            }
+            ActionItem::PublishReleaseNotes => self.start_publish_release_notes(tasks),
+            ActionItem::DiscardReleaseNotes => {
+                self.release_notes = None;
+                self.release_notes_tag = None;
+                self.action_index = self.initial_action_index();
+                true
+            }
 
             // Config tab
             ActionItem::RunSetupWizard => {
                 if let Err(e) = self.run_setup_wizard() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Setup failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Setup failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Setup complete.");
                     self.log("Setup complete.");
                 }
                 true
             }
+            ActionItem::ChangeModel => {
+                if let Err(e) = self.run_model_selection() {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Change model failed: {e:#}"));
+                } else {
+                    self.set_status(
+                        StatusLevel::Success,
+                        format!("Model set to {}.", self.model_label),
+                    );
+                    self.log(format!("Model changed to {}.", self.model_label));
+                }
+                true
+            }
+            ActionItem::ChangeLanguage => {
+                self.open_language_modal();
+                true
+            }
+            ActionItem::ChangeCommitStyle => {
+                if let Err(e) = self.run_style_selection() {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Change commit style failed: {e:#}"));
+                } else {
+                    self.set_status(
+                        StatusLevel::Success,
+                        format!("Commit style set to {}.", self.commit_style_label),
+                    );
+                    self.log(format!(
+                        "Commit style changed to {}.",
+                        self.commit_style_label
+                    ));
+                }
+                true
+            }
             ActionItem::ReloadConfig => {
+                self.refresh_latency_stats();
                 if let Err(e) = self.reload_config_labels() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Reload config failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Reload config failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Config reloaded.");
                     self.log("Config reloaded.");
@@ -548,7 +1196,19 @@ impl App {
             .position(|t| *t == self.active_tab)
             .unwrap_or(0);
         self.active_tab = Tab::ALL[(idx + 1) % Tab::ALL.len()];
-        self.action_index = 0;
+        self.action_index = self.initial_action_index();
+        if matches!(self.active_tab, Tab::Generate | Tab::Stage) {
+            self.request_summary_refresh();
+        }
+        if self.active_tab == Tab::Stage {
+            self.reload_hunk_view();
+        }
+        if self.active_tab == Tab::Push {
+            self.refresh_tags();
+        }
+        if self.active_tab == Tab::Release {
+            self.refresh_preflight_checks();
+        }
         self.set_status(
             StatusLevel::Info,
             format!("Tab: {}", self.active_tab.title()),
@@ -566,7 +1226,19 @@ impl App {
             idx - 1
         };
         self.active_tab = Tab::ALL[next];
-        self.action_index = 0;
+        self.action_index = self.initial_action_index();
+        if matches!(self.active_tab, Tab::Generate | Tab::Stage) {
+            self.request_summary_refresh();
+        }
+        if self.active_tab == Tab::Stage {
+            self.reload_hunk_view();
+        }
+        if self.active_tab == Tab::Push {
+            self.refresh_tags();
+        }
+        if self.active_tab == Tab::Release {
+            self.refresh_preflight_checks();
+        }
         self.set_status(
             StatusLevel::Info,
             format!("Tab: {}", self.active_tab.title()),
@@ -595,6 +1267,29 @@ impl App {
         self.log("Cleared commit message.");
     }
 
+    /// Replace the message with git's `commit.template` as written, to fill in by hand or
+    /// next to a generated message.
+    fn load_commit_template(&mut self) {
+        match git::commit_template() {
+            Ok(Some(template)) => {
+                self.set_commit_message_text(&template.text);
+                self.set_status(StatusLevel::Success, "Loaded the commit template.");
+                self.log(format!(
+                    "Loaded commit.template ({}). Its # lines are left out when committing.",
+                    template.path.display()
+                ));
+            }
+            Ok(None) => {
+                self.set_status(StatusLevel::Info, "No commit template is set.");
+                self.log("No commit.template is set (git config commit.template <file>).");
+            }
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("{:#}", e));
+            }
+        }
+    }
+
     pub fn handle_global_key(&mut self, tasks: &TaskRunner, key: &KeyEvent) -> bool {
         // If an app modal is open, it captures keys (except Ctrl+C).
         if self.modal.kind != ModalKind::None {
@@ -605,8 +1300,25 @@ impl App {
                 }
                 // Close modal on Esc
                 (KeyCode::Esc, _) => {
+                    let purpose = self.modal.confirm_purpose;
                     self.modal = ModalState::none();
-                    self.set_status(StatusLevel::Info, "Closed dialog.");
+                    if purpose == Some(ConfirmPurpose::PushAfterCommit) {
+                        self.set_status(StatusLevel::Info, "Committed; not pushed.");
+                        self.log("Commit kept local (push declined).");
+                    } else if purpose == Some(ConfirmPurpose::PushOverRemoteBranch) {
+                        self.pending_remote_branch = None;
+                        self.set_status(StatusLevel::Info, "Not pushed.");
+                        self.log("Push aborted: origin's branch left as it is.");
+                    } else if purpose == Some(ConfirmPurpose::RollBackRelease) {
+                        let hint = self
+                            .pending_rollback
+                            .take()
+                            .and_then(|incomplete| incomplete.resume_hint());
+                        self.set_status(StatusLevel::Info, "Release not rolled back.");
+                        self.log(hint.unwrap_or_else(|| "Release not rolled back.".to_string()));
+                    } else {
+                        self.set_status(StatusLevel::Info, "Closed dialog.");
+                    }
                     return true;
                 }
                 // Confirm modal: Enter = confirm, Backspace/Delete ignored
@@ -618,6 +1330,95 @@ impl App {
                     }
                     return true;
                 }
+                // Branch picker: ↑/↓ select, Enter switches, n creates a branch
+                (KeyCode::Up, KeyModifiers::NONE) if self.modal.kind == ModalKind::BranchPicker => {
+                    self.branch_picker_index = self.branch_picker_index.saturating_sub(1);
+                    return true;
+                }
+                (KeyCode::Down, KeyModifiers::NONE)
+                    if self.modal.kind == ModalKind::BranchPicker =>
+                {
+                    if self.branch_picker_index + 1 < self.branch_picker.len() {
+                        self.branch_picker_index += 1;
+                    }
+                    return true;
+                }
+                (KeyCode::Enter, KeyModifiers::NONE)
+                    if self.modal.kind == ModalKind::BranchPicker =>
+                {
+                    self.modal = ModalState::none();
+                    let selected = self.branch_picker.get(self.branch_picker_index).cloned();
+                    if let Some(branch) = selected {
+                        self.switch_to_branch(&branch);
+                    }
+                    return true;
+                }
+                (KeyCode::Char('f'), KeyModifiers::NONE)
+                    if self.modal.confirm_purpose == Some(ConfirmPurpose::PushOverRemoteBranch)
+                        && self
+                            .pending_remote_branch
+                            .as_ref()
+                            .is_some_and(|d| !self.is_protected(&d.branch)) =>
+                {
+                    self.modal = ModalState::none();
+                    if let Some(diverged) = self.pending_remote_branch.take() {
+                        let policy = git::PushPolicy::ForceWithLease {
+                            expect: diverged.remote_commit,
+                        };
+                        let _started = self.spawn_push_with_policy(tasks, policy);
+                    }
+                    return true;
+                }
+                (KeyCode::Char('h'), KeyModifiers::NONE)
+                    if self.modal.confirm_purpose == Some(ConfirmPurpose::RollBackRelease) =>
+                {
+                    self.modal = ModalState::none();
+                    if let Some(incomplete) = self.pending_rollback.take() {
+                        self.roll_back_release(&incomplete, release::ResetMode::Hard);
+                    }
+                    return true;
+                }
+                (KeyCode::Char('w'), KeyModifiers::NONE)
+                    if self.modal.confirm_purpose == Some(ConfirmPurpose::ReleaseTrigger)
+                        && self.other_version_source().is_some() =>
+                {
+                    self.modal = ModalState::none();
+                    self.release_scope = match self.release_scope {
+                        release::VersionScope::Package => release::VersionScope::Workspace,
+                        release::VersionScope::Workspace => release::VersionScope::Package,
+                    };
+                    self.plan_release();
+                    return true;
+                }
+                (KeyCode::Char(c @ '1'..='9'), KeyModifiers::NONE)
+                    if self.modal.confirm_purpose == Some(ConfirmPurpose::ReleaseTrigger) =>
+                {
+                    let index = c as usize - '1' as usize;
+                    if let Some(check) = self.preflight_checks.get(index) {
+                        let name = check.name.clone();
+                        if self.preflight_skip.contains(&name) {
+                            self.preflight_skip.retain(|skipped| *skipped != name);
+                        } else {
+                            self.preflight_skip.push(name);
+                        }
+                        self.modal = ModalState::none();
+                        self.plan_release();
+                    }
+                    return true;
+                }
+                (KeyCode::Char('n'), KeyModifiers::NONE)
+                    if self.modal.kind == ModalKind::BranchPicker =>
+                {
+                    self.modal = ModalState {
+                        kind: ModalKind::TextInput,
+                        title: "New branch".to_string(),
+                        message: "Name a branch to create at HEAD and switch to (your changes come along):".to_string(),
+                        confirm_purpose: None,
+                        input_purpose: Some(TextInputPurpose::NewBranch),
+                        input_value: String::new(),
+                    };
+                    return true;
+                }
                 // Text input modal: type, backspace, enter to accept
                 (KeyCode::Backspace, KeyModifiers::NONE)
                     if self.modal.kind == ModalKind::TextInput =>
@@ -725,10 +1526,30 @@ impl App {
     pub fn handle_generate_key(&mut self, tasks: &TaskRunner, key: &KeyEvent) -> bool {
         // Actions that should work regardless of focus.
         match (key.code, key.modifiers) {
+            (KeyCode::Char('g'), KeyModifiers::NONE) if self.amending.is_some() => {
+                let _started = self.start_generate_for_amend(tasks);
+                return true;
+            }
             (KeyCode::Char('g'), KeyModifiers::NONE) => {
                 let _started = self.start_generate_from_staged(tasks);
                 return true;
             }
+            (KeyCode::Char('r'), KeyModifiers::NONE)
+                if self.focus != Focus::CommitEditor && self.amending.is_none() =>
+            {
+                self.open_refine_modal();
+                return true;
+            }
+            (KeyCode::Char('h'), KeyModifiers::NONE)
+                if self.focus != Focus::CommitEditor && self.amending.is_none() =>
+            {
+                self.open_hint_modal();
+                return true;
+            }
+            (KeyCode::Enter, KeyModifiers::NONE) if self.amending.is_some() => {
+                self.confirm_amend(tasks);
+                return true;
+            }
             (KeyCode::Enter, KeyModifiers::NONE) => {
                 let _started = self.start_commit_from_editor(tasks);
                 return true;
@@ -737,6 +1558,15 @@ impl App {
                 self.clear_editor();
                 return true;
             }
+            // Don't steal `i` while typing in the editor.
+            (KeyCode::Char('i'), KeyModifiers::NONE) if self.focus != Focus::CommitEditor => {
+                self.open_link_issues_modal();
+                return true;
+            }
+            (KeyCode::Char('b'), KeyModifiers::NONE) if self.focus != Focus::CommitEditor => {
+                self.open_branch_picker();
+                return true;
+            }
             _ => {}
         }
 
@@ -762,73 +1592,107 @@ impl App {
             anyhow::bail!("Commit message is empty.");
         }
 
-        git::commit_changes(&msg)?;
+        history::commit_and_record(&msg)?;
         self.set_status(StatusLevel::Success, "Committed successfully.");
         self.log("Committed changes.");
         Ok(())
     }
 
     fn build_generator(&mut self) -> Result<Generator> {
-        if self.mock_mode {
-            self.provider_label = "Mock".to_string();
-            self.model_label = "-".to_string();
-            return Ok(Generator::Mock(MockGenerator::new()));
-        }
-
-        match Config::load()? {
-            Some(cfg) => {
-                self.provider_label = cfg.provider.to_string();
-                self.model_label = cfg.model.clone();
-
-                Ok(match cfg.provider {
-                    Provider::OpenAI => {
-                        Generator::OpenAI(OpenAIGenerator::new(cfg.api_key, cfg.model))
-                    }
-                    Provider::Anthropic => {
-                        Generator::Anthropic(AnthropicGenerator::new(cfg.api_key, cfg.model))
-                    }
-                    Provider::Gemini => {
-                        Generator::Gemini(GeminiGenerator::new(cfg.api_key, cfg.model))
-                    }
-                })
+        match generator::build_generator(self.mock_mode) {
+            Ok((gen, provider, model)) => {
+                self.provider_label = provider;
+                self.model_label = model;
+                Ok(gen)
             }
-            None => {
+            Err(e) => {
                 self.provider_label = "Not configured".to_string();
                 self.model_label = "-".to_string();
-                anyhow::bail!("No config found. Use the Config tab or run setup.")
+                Err(e)
             }
         }
     }
 
-    fn reload_config_labels(&mut self) -> Result<()> {
+    pub fn reload_config_labels(&mut self) -> Result<()> {
         match Config::load()? {
-            Some(cfg) => {
-                self.provider_label = cfg.provider.to_string();
-                self.model_label = cfg.model;
-            }
+            Some(cfg) => self.set_config_labels(&cfg),
             None => {
                 self.provider_label = "Not configured".to_string();
                 self.model_label = "-".to_string();
+                self.endpoint_label = "-".to_string();
+                self.commit_style_label = "-".to_string();
+                self.language_label = "-".to_string();
+                self.prompt_label = "-".to_string();
             }
         }
         Ok(())
     }
 
+    fn set_config_labels(&mut self, cfg: &Config) {
+        self.provider_label = cfg.provider.to_string();
+        self.model_label = cfg.model.clone();
+        self.endpoint_label =
+            generator::endpoint(cfg).unwrap_or_else(|e| format!("invalid ({:#})", e));
+        self.commit_style_label = cfg.commit_style.to_string();
+        self.language_label = setup::language_label(cfg);
+        self.prompt_label = prompt::template_label(cfg);
+    }
+
     fn run_setup_wizard(&mut self) -> Result<()> {
         // NOTE: The TUI runtime suspends raw mode + alt screen when running this.
         let cfg = setup::run_setup()?;
-        self.provider_label = cfg.provider.to_string();
-        self.model_label = cfg.model;
+        self.set_config_labels(&cfg);
+        Ok(())
+    }
+
+    fn run_model_selection(&mut self) -> Result<()> {
+        // NOTE: Like the setup wizard, this runs with the TUI suspended.
+        let cfg = setup::run_model_selection()?;
+        self.set_config_labels(&cfg);
+        Ok(())
+    }
+
+    fn run_style_selection(&mut self) -> Result<()> {
+        // NOTE: Like the setup wizard, this runs with the TUI suspended.
+        let cfg = setup::run_style_selection()?;
+        self.set_config_labels(&cfg);
+        Ok(())
+    }
+
+    fn open_language_modal(&mut self) {
+        let current = Config::load().ok().flatten().and_then(|c| c.language);
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "Message language".to_string(),
+            message: "Language for generated messages, as a name or ISO code (e.g. German or de). Leave empty for English.".to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::Language),
+            input_value: current.unwrap_or_default(),
+        };
+    }
+
+    fn save_language(&mut self, value: &str) -> Result<()> {
+        let Some(cfg) = Config::load()? else {
+            anyhow::bail!("No config found. Run the setup wizard first.");
+        };
+        let value = value.trim();
+        let cfg = Config {
+            language: (!value.is_empty()).then(|| value.to_string()),
+            ..cfg
+        };
+        cfg.save()?;
+        self.set_config_labels(&cfg);
         Ok(())
     }
 
     fn clear_config_file(&mut self) -> Result<()> {
-        let path = Config::get_path()?;
-        if path.exists() {
-            std::fs::remove_file(&path)?;
-        }
+        Config::delete()?;
         self.provider_label = "Not configured".to_string();
         self.model_label = "-".to_string();
+        self.endpoint_label = "-".to_string();
+        self.commit_style_label = "-".to_string();
+        self.language_label = "-".to_string();
+        self.prompt_label = "-".to_string();
         Ok(())
     }
 
@@ -836,8 +1700,8 @@ impl App {
         match purpose {
             ConfirmPurpose::ClearConfig => {
                 if let Err(e) = self.clear_config_file() {
-                    self.set_status(StatusLevel::Error, e.to_string());
-                    self.log(format!("Clear config failed: {e}"));
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Clear config failed: {e:#}"));
                 } else {
                     self.set_status(StatusLevel::Success, "Config cleared.");
                     self.log("Config cleared.");
@@ -846,58 +1710,243 @@ impl App {
             ConfirmPurpose::PushAllTags => {
                 let _started = self.start_push_all_tags(tasks);
             }
-            ConfirmPurpose::ReleaseTrigger => {
-                if let Some(v) = self.pending_release_version.clone() {
-                    // Suspend the TUI for the whole release execution so cargo/clippy/test output
-                    // does not corrupt the terminal UI. The release pipeline intentionally streams
-                    // output to stdout/stderr for transparency.
-                    let result = runtime::with_tui_suspended(|| self.perform_release(&v));
-
-                    match result {
-                        Ok(_) => {
-                            let tag = format!("v{}", v);
-                            self.set_status(
-                                StatusLevel::Success,
-                                format!("Release initiated: pushed tag {}", tag),
-                            );
-                            self.log(format!("Release initiated: {}", tag));
-
-                            if let Some(repo_https) = origin_https_repo_url().ok().flatten() {
-                                self.log(format!(
-                                    "Track progress (Actions): {}/actions?query=workflow%3ARelease",
-                                    repo_https
-                                ));
-                                self.log(format!(
-                                    "Release page: {}/releases/tag/{}",
-                                    repo_https, tag
-                                ));
-                            }
-                        }
-                        Err(e) => {
-                            self.set_status(StatusLevel::Error, e.to_string());
-                            self.log(format!("Release failed: {}", e));
-                        }
-                    }
+            ConfirmPurpose::GenerateDuplicateStaged => {
+                let _started = self.spawn_generate_from_staged(tasks);
+            }
+            ConfirmPurpose::GenerateDuringOperation => {
+                let _started = self.confirm_duplicate_then_generate(tasks);
+            }
+            ConfirmPurpose::CommitReview => {
+                let _started = self.spawn_commit_from_editor(tasks, false);
+            }
+            ConfirmPurpose::HookRejectedGenerated => {
+                self.hook_rejected = false;
+                self.set_status(StatusLevel::Info, "Keeping the message despite the check.");
+                self.log("post_generate_command overridden.");
+            }
+            ConfirmPurpose::AmendCommit => {
+                let _started = self.spawn_amend_commit(tasks, false);
+            }
+            ConfirmPurpose::CommitWithoutHooks => {
+                self.log("Retrying with --no-verify.");
+                let _started = if self.amending.is_some() {
+                    self.spawn_amend_commit(tasks, true)
                 } else {
-                    self.set_status(StatusLevel::Error, "No pending release version.");
-                    self.log("Release failed: missing pending version.");
-                }
+                    self.spawn_commit_from_editor(tasks, true)
+                };
             }
-        }
-    }
-
-    fn handle_text_input(&mut self, tasks: &TaskRunner, purpose: TextInputPurpose, value: String) {
-        match purpose {
-            TextInputPurpose::PushSpecificTag => {
-                let v = value.trim();
-                if v.is_empty() {
-                    self.set_status(StatusLevel::Error, "Tag cannot be empty.");
-                    self.log("Push tag failed: empty tag.");
-                    return;
+            ConfirmPurpose::HookRejectedCommit => {
+                self.hook_rejected = false;
+                self.log("post_generate_command overridden.");
+                let _started = self.review_or_commit(tasks);
+            }
+            ConfirmPurpose::ResolveConflicts => {
+                self.resolving_conflicts = true;
+                self.action_index = 0;
+                self.set_status(
+                    StatusLevel::Info,
+                    "Enter opens the next conflicted file in your editor.",
+                );
+            }
+            ConfirmPurpose::StageResolvedConflict => {
+                if let Some(path) = self.conflict_path.take() {
+                    if let Err(e) = self.stage_resolved_conflict(tasks, &path) {
+                        self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    }
                 }
-
-                let _started = self.start_push_tag(tasks, v.to_string());
             }
+            ConfirmPurpose::PushAfterCommit => {
+                let _started = self.start_push_after_commit(tasks);
+            }
+            ConfirmPurpose::PullThenPush => {
+                let _started = self.spawn_push_branch(tasks, true);
+            }
+            ConfirmPurpose::ForcePushBranch => {
+                let _started = self.start_force_push(tasks);
+            }
+            ConfirmPurpose::PushOverRemoteBranch => {
+                self.pending_remote_branch = None;
+                let _started = self.spawn_push_with_policy(tasks, git::PushPolicy::Rebase);
+            }
+            ConfirmPurpose::DeleteTag | ConfirmPurpose::DeleteRemoteTag => {
+                if let Some(tag) = self.pending_tag.take() {
+                    let also_remote = purpose == ConfirmPurpose::DeleteRemoteTag;
+                    let _started = self.start_delete_tag(tasks, tag, also_remote);
+                }
+            }
+            ConfirmPurpose::StashAndSwitch => {
+                if let Some(branch) = self.pending_switch.take() {
+                    self.stash_and_switch(&branch);
+                }
+            }
+            ConfirmPurpose::ShowDivergence => match git::divergence() {
+                Ok(commits) => {
+                    self.log("Diverging commits (< only on this branch, > only upstream):");
+                    for line in commits.lines() {
+                        self.log(line.to_string());
+                    }
+                    self.set_status(StatusLevel::Info, "Diverging commits are in the log.");
+                }
+                Err(e) => self.set_status(StatusLevel::Error, format!("{:#}", e)),
+            },
+            ConfirmPurpose::RaiseOutputLimit => {
+                self.output_limit = self.pending_output_limit.take();
+                let _started = self.spawn_generate_from_staged(tasks);
+            }
+            ConfirmPurpose::UseSuggestedScope => {
+                let Some(scope) = self.pending_scope.take() else {
+                    return;
+                };
+                let current = self.commit_editor.lines().join("\n");
+                if let Some(rescoped) = scope::with_scope(&current, &scope) {
+                    self.set_commit_message_text(&rescoped);
+                    self.set_status(StatusLevel::Success, format!("Scope set to `{}`.", scope));
+                    self.log(format!("Scope changed to `{}`.", scope));
+                }
+            }
+            ConfirmPurpose::UseMockGenerator => {
+                offline::enable();
+                self.mock_mode = true;
+                self.log("Offline: using the mock generator for the rest of the session.");
+                let _started = self.spawn_generate_from_staged(tasks);
+            }
+            ConfirmPurpose::DraftReleaseNotes => {
+                if let Some(tag) = self.release_notes_tag.clone() {
+                    let _started = self.start_draft_release_notes(tasks, tag);
+                }
+            }
+            ConfirmPurpose::SendPrDescription => {
+                if let Some(changes) = self.pending_pr.take() {
+                    let _started = self.start_generate_pr_description(tasks, changes);
+                }
+            }
+            ConfirmPurpose::ReleaseTrigger => self.trigger_release(false),
+            ConfirmPurpose::StashAndRelease => self.trigger_release(true),
+            ConfirmPurpose::RollBackRelease => {
+                if let Some(incomplete) = self.pending_rollback.take() {
+                    self.roll_back_release(&incomplete, release::ResetMode::Soft);
+                }
+            }
+        }
+    }
+
+    fn handle_text_input(&mut self, tasks: &TaskRunner, purpose: TextInputPurpose, value: String) {
+        match purpose {
+            TextInputPurpose::DeleteReleaseTag => {
+                let Some(tag) = self.pending_tag.take() else {
+                    return;
+                };
+                if value.trim() != tag {
+                    self.set_status(
+                        StatusLevel::Info,
+                        format!("Kept {}: the name didn't match.", tag),
+                    );
+                    self.log(format!("Kept tag {}: the typed name didn't match.", tag));
+                    return;
+                }
+                let _started = self.start_delete_tag(tasks, tag, true);
+            }
+            TextInputPurpose::PushNewBranch => {
+                let name = value.trim();
+                if let Err(e) = git::create_branch(name, true) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Create branch failed: {:#}", e));
+                    return;
+                }
+                self.log(format!("Created branch {}.", name));
+                let _started = self.start_push_branch(tasks);
+            }
+            TextInputPurpose::NewBranch => {
+                let name = value.trim();
+                if let Err(e) = git::create_branch(name, true) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Create branch failed: {:#}", e));
+                    return;
+                }
+                self.set_status(
+                    StatusLevel::Success,
+                    format!("Switched to a new branch {}.", name),
+                );
+                self.log(format!("Created and switched to branch {}.", name));
+                self.request_summary_refresh();
+            }
+            TextInputPurpose::GenerateHint => {
+                let hint = value.trim();
+                self.generate_hint = (!hint.is_empty()).then(|| hint.to_string());
+                match &self.generate_hint {
+                    Some(hint) => self.log(format!("Hint: {}", hint)),
+                    None => self.log("Hint cleared."),
+                }
+                let _started = self.start_generate_from_staged(tasks);
+            }
+            TextInputPurpose::LinkIssues => {
+                let links = message::parse_issue_input(&value);
+                if links.is_empty() {
+                    self.set_status(StatusLevel::Info, "No issues linked.");
+                    return;
+                }
+
+                let keyword = Config::load()
+                    .ok()
+                    .flatten()
+                    .map(|c| c.issue_keyword)
+                    .unwrap_or(IssueKeyword::Closes);
+                let current = self.commit_editor.lines().join("\n");
+                let updated = message::append_issue_footers(&current, keyword, &links);
+                self.set_commit_message_text(&updated);
+
+                let count = links.closes.len() + links.refs.len();
+                self.set_status(StatusLevel::Success, format!("Linked {} issue(s).", count));
+                self.log(format!("Linked issues: {}", value.trim()));
+            }
+            TextInputPurpose::Refine => {
+                let feedback = value.trim();
+                if feedback.is_empty() {
+                    self.set_status(StatusLevel::Info, "No feedback; the message is unchanged.");
+                    return;
+                }
+                if tasks.is_busy() {
+                    self.set_status(StatusLevel::Info, "Busy: another task is running.");
+                    return;
+                }
+                self.refinement = Some(Refinement {
+                    previous: self.commit_editor.lines().join("\n"),
+                    feedback: feedback.to_string(),
+                });
+                self.log(format!("Refining: {}", feedback));
+                let _started = self.spawn_generate_from_staged(tasks);
+            }
+            TextInputPurpose::PrBase => {
+                let base = value.trim();
+                if base.is_empty() {
+                    self.set_status(StatusLevel::Error, "Base ref cannot be empty.");
+                    return;
+                }
+                self.open_pr_preflight_modal(base);
+            }
+            TextInputPurpose::DiffRef => {
+                let reference = value.trim();
+                if let Err(e) = git::verify_ref(reference) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Load diff failed: {:#}", e));
+                    return;
+                }
+                let _started =
+                    self.start_load_diff(tasks, DiffViewSource::AgainstRef(reference.to_string()));
+            }
+            TextInputPurpose::Language => match self.save_language(&value) {
+                Ok(()) => {
+                    self.set_status(
+                        StatusLevel::Success,
+                        format!("Messages will be written in {}.", self.language_label),
+                    );
+                    self.log(format!("Language changed to {}.", self.language_label));
+                }
+                Err(e) => {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Change language failed: {e:#}"));
+                }
+            },
             TextInputPurpose::ReleaseCustomVersion => {
                 let v = value.trim();
                 if v.is_empty() {
@@ -905,283 +1954,1810 @@ impl App {
                     self.log("Release failed: empty version.");
                     return;
                 }
+                if let Err(e) = git::validate_ref_name(git::RefKind::Tag, &format!("v{}", v)) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Release failed: {:#}", e));
+                    return;
+                }
+                self.remember_release_action(ActionItem::ReleaseCustom);
+                self.pending_release_bump = None;
                 self.pending_release_version = Some(v.to_string());
-                self.modal = ModalState {
-                    kind: ModalKind::Confirm,
-                    title: "Final confirmation".to_string(),
-                    message: format!(
-                        "Create and push tag v{}? This triggers CI release + crates publish.",
-                        v
-                    ),
-                    confirm_purpose: Some(ConfirmPurpose::ReleaseTrigger),
-                    input_purpose: None,
-                    input_value: String::new(),
-                };
+                self.preflight_skip.clear();
+                self.plan_release();
+            }
+            TextInputPurpose::ReleaseRemoteUrl => {
+                let remote = release::ReleaseGuardrailConfig::default().remote;
+                if let Err(e) = release::validate_remote_url(&value) {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.open_release_remote_modal(&remote, value);
+                    return;
+                }
+                let _started =
+                    self.start_add_release_remote(tasks, remote, value.trim().to_string());
+            }
+        }
+    }
+
+    fn open_release_remote_modal(&mut self, remote: &str, prefill: String) {
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: format!("Add '{}' remote", remote),
+            message: format!(
+                "No '{}' remote. Enter its URL (https://… or git@host:owner/repo.git) to add it and continue the release.",
+                remote
+            ),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::ReleaseRemoteUrl),
+            input_value: prefill,
+        };
+    }
+
+    fn start_add_release_remote(
+        &mut self,
+        tasks: &TaskRunner,
+        remote: String,
+        url: String,
+    ) -> bool {
+        let label = format!("Adding remote '{}'…", remote);
+        let started = tasks.start(TaskKind::AddRemote, label, move |_tx| {
+            release::add_remote(&remote, &url)?;
+            Ok(TaskResult::ReleaseRemoteAdded { remote })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Add remote ignored: task runner was busy.");
+        }
+        started
+    }
+
+    /// Open the next conflicted file (markers first) in the editor; runs with the TUI
+    /// suspended. A file without markers afterwards gets a "stage it?" confirmation.
+    fn open_next_conflict(&mut self, tasks: &TaskRunner) -> Result<()> {
+        let files = conflicts::conflicted_files()?;
+        let Some(path) = files
+            .iter()
+            .find(|p| conflicts::has_markers(p).unwrap_or(true))
+            .or(files.first())
+            .cloned()
+        else {
+            self.finish_conflicts(tasks);
+            return Ok(());
+        };
+
+        if conflicts::has_markers(&path)? {
+            self.log(format!("Opening {} in the editor…", path));
+            conflicts::open_in_editor(&path)?;
+            self.snapshot = git::snapshot().ok();
+            if conflicts::has_markers(&path)? {
+                self.set_status(
+                    StatusLevel::Info,
+                    format!("{} still has conflict markers.", path),
+                );
+                return Ok(());
+            }
+        }
+
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Stage resolved file".to_string(),
+            message: format!("{} has no conflict markers. Stage it (git add)?", path),
+            confirm_purpose: Some(ConfirmPurpose::StageResolvedConflict),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.conflict_path = Some(path);
+        Ok(())
+    }
+
+    fn stage_resolved_conflict(&mut self, tasks: &TaskRunner, path: &str) -> Result<()> {
+        conflicts::mark_resolved(path)?;
+        self.snapshot = git::snapshot().ok();
+        self.log(format!("Staged resolved file {}.", path));
+
+        let left = conflicts::conflicted_files()?.len();
+        if left == 0 {
+            self.finish_conflicts(tasks);
+        } else {
+            self.set_status(
+                StatusLevel::Success,
+                format!("Staged {}; {} conflicted file(s) left.", path, left),
+            );
+        }
+        Ok(())
+    }
+
+    /// Every conflict is staged: leave the flow and generate the merge commit message.
+    fn finish_conflicts(&mut self, tasks: &TaskRunner) {
+        self.resolving_conflicts = false;
+        self.refinement = None;
+        self.action_index = 0;
+        self.log("All conflicts resolved.");
+        self.request_summary_refresh();
+        let _started = self.spawn_generate_from_staged(tasks);
+    }
+
+    /// Offer release notes as the last (optional) step after a tag push.
+    fn open_release_notes_modal(&mut self, tag: String) {
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Release notes".to_string(),
+            message: format!(
+                "Draft release notes for {}? Commit subjects and bodies since the previous tag are sent to the model.",
+                tag
+            ),
+            confirm_purpose: Some(ConfirmPurpose::DraftReleaseNotes),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.release_notes_tag = Some(tag);
+    }
+
+    fn start_draft_release_notes(&mut self, tasks: &TaskRunner, tag: String) -> bool {
+        let mock_mode = self.mock_mode;
+        let label = format!("Drafting release notes for {}…", tag);
+        let started = tasks.start(TaskKind::DraftReleaseNotes, label, move |tx| {
+            let release = release_notes::commits_for(&tag)?;
+            let draft = release_notes::draft(&release);
+            if release.commits.is_empty() {
+                return Ok(TaskResult::ReleaseNotes {
+                    tag,
+                    notes: draft,
+                    warning: Some("No commits since the previous tag.".to_string()),
+                });
+            }
+
+            let _ = tx.send(TaskEvent::Progress {
+                message: "Polishing with the model…".to_string(),
+            });
+            let polished = generator::build_generator(mock_mode).and_then(|(generator, _, _)| {
+                runtime::tui_block_on(release_notes::polish(&generator, &release, &draft))
+            });
+            Ok(match polished {
+                Ok(notes) => TaskResult::ReleaseNotes {
+                    tag,
+                    notes,
+                    warning: None,
+                },
+                Err(e) => TaskResult::ReleaseNotes {
+                    tag,
+                    notes: draft,
+                    warning: Some(format!("Using the unpolished draft: {:#}", e)),
+                },
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Release notes ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn start_publish_release_notes(&mut self, tasks: &TaskRunner) -> bool {
+        let (Some(tag), Some(notes)) = (self.release_notes_tag.clone(), self.release_notes.clone())
+        else {
+            return true;
+        };
+        if !release_notes::gh_available() {
+            self.set_status(
+                StatusLevel::Error,
+                "gh (GitHub CLI) not found: copy the notes or write RELEASE_NOTES.md instead.",
+            );
+            return true;
+        }
+
+        let label = format!("Waiting for the {} GitHub release…", tag);
+        let started = tasks.start(TaskKind::PublishReleaseNotes, label, move |_tx| {
+            release_notes::publish_with_gh(&tag, &notes)?;
+            Ok(TaskResult::OkMessage {
+                status: format!("Updated the GitHub release for {}.", tag),
+                log: Some(format!("gh release edit {} --notes-file -", tag)),
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Publish ignored: task runner was busy.");
+        }
+        started
+    }
+
+    /// Re-open the release confirmation after the missing remote was added.
+    pub fn resume_release_after_remote(&mut self, remote: &str) {
+        let Some(v) = self.pending_release_version.clone() else {
+            return;
+        };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Final confirmation".to_string(),
+            message: format!(
+                "Remote '{}' added. Continue: create and push tag v{}? This triggers CI release + crates publish.{}",
+                remote,
+                v,
+                changelog_preview(self.pending_changelog.as_deref())
+            ),
+            confirm_purpose: Some(ConfirmPurpose::ReleaseTrigger),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    /// Explain a post_generate_command rejection; Enter overrides, Esc goes back to editing.
+    pub fn open_hook_rejected_modal(&mut self, purpose: ConfirmPurpose, reason: &str) {
+        self.hook_rejected = true;
+        let action = match purpose {
+            ConfirmPurpose::HookRejectedCommit => "commit anyway",
+            _ => "keep it anyway",
+        };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Message rejected".to_string(),
+            message: format!(
+                "post_generate_command rejected the message:\n\n{}\n\nEnter: {}. Esc: edit it (re-checked on commit).",
+                reason.trim(),
+                action
+            ),
+            confirm_purpose: Some(purpose),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.set_status(
+            StatusLevel::Error,
+            "Message rejected by post_generate_command.",
+        );
+        self.log(format!("post_generate_command rejected: {}", reason.trim()));
+    }
+
+    /// The reply stopped at `limit` tokens: offer to regenerate with twice the limit.
+    pub fn open_output_limit_modal(&mut self, limit: u32) {
+        self.pending_output_limit = Some(limit * 2);
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Reply cut off".to_string(),
+            message: format!(
+                "The reply hit the {}-token output limit, so the message is probably cut off.\n\nEnter: regenerate with a limit of {} tokens (this session; max_output_tokens in the config raises it for good). Esc: keep it.",
+                limit,
+                limit * 2
+            ),
+            confirm_purpose: Some(ConfirmPurpose::RaiseOutputLimit),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.log(format!(
+            "Reply cut off at the {}-token output limit.",
+            limit
+        ));
+    }
+
+    /// The model's scope disagrees with the changed paths: offer the suggested one.
+    pub fn open_scope_fix_modal(&mut self, fix: ScopeFix) {
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Scope".to_string(),
+            message: format!(
+                "{}\n\nEnter: use `{}`. Esc: keep it.",
+                fix.question(),
+                fix.scope
+            ),
+            confirm_purpose: Some(ConfirmPurpose::UseSuggestedScope),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.log(fix.question());
+        self.pending_scope = Some(fix.scope);
+    }
+
+    /// The provider's host didn't answer: offer the mock generator for the session.
+    pub fn open_unreachable_modal(&mut self, reason: &str) {
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "No connection".to_string(),
+            message: format!(
+                "{}\n\nEnter: use the mock generator for the rest of this session (placeholder messages). Esc: cancel; generate again to retry.",
+                reason
+            ),
+            confirm_purpose: Some(ConfirmPurpose::UseMockGenerator),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.set_status(StatusLevel::Error, reason.to_string());
+        self.log(format!("Error: {}", reason));
+    }
+
+    /// Log everything a failed commit printed; when a hook may have rejected it, offer to
+    /// retry with `--no-verify`.
+    pub fn report_commit_failure(&mut self, failed: &git::CommitFailed) {
+        let summary = failed.summary();
+        self.log(format!("Error: {}", summary));
+        let output = failed.output();
+        for line in output.lines() {
+            self.log(format!("  {}", line));
+        }
+        self.set_status(StatusLevel::Error, summary.clone());
+        if !failed.hook_related() {
+            return;
+        }
+
+        let lines: Vec<&str> = output.lines().collect();
+        let shown = lines[lines.len().saturating_sub(COMMIT_OUTPUT_LINES)..].join("\n");
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Commit rejected".to_string(),
+            message: format!(
+                "{}\n\n{}\n\nEnter: retry with --no-verify, skipping {}. Esc: cancel; fix what the hook reported and commit again to retry.",
+                summary,
+                shown,
+                failed.hooks.join(" and ")
+            ),
+            confirm_purpose: Some(ConfirmPurpose::CommitWithoutHooks),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    /// Open the "Link issues" modal, prefilled with candidates from the branch and diff.
+    pub fn open_link_issues_modal(&mut self) {
+        if self.issue_candidates.is_empty() {
+            let branch = git::current_branch().ok();
+            let diff = git::get_diff_staged_allow_empty().unwrap_or_default();
+            self.issue_candidates = message::issue_candidates(branch.as_deref(), &diff);
+        }
+
+        let prefill = self
+            .issue_candidates
+            .iter()
+            .map(|n| format!("#{}", n))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "Link Issues".to_string(),
+            message: "Issue numbers to close (e.g. #12 34). Add 'refs' before numbers that should only be referenced.".to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::LinkIssues),
+            input_value: prefill,
+        };
+    }
+
+    /// Ask for the hint to generate with; it stays in effect for later generations.
+    fn open_hint_modal(&mut self) {
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "Generate with hint".to_string(),
+            message: "What the change is about, e.g. \"fixes the login timeout\". It is sent with every generation until you change it; leave it empty to clear it.".to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::GenerateHint),
+            input_value: self.generate_hint.clone().unwrap_or_default(),
+        };
+    }
+
+    /// Ask what to change about the editor's message; the reply replaces it.
+    fn open_refine_modal(&mut self) {
+        if self.commit_editor.lines().join("\n").trim().is_empty() {
+            self.set_status(
+                StatusLevel::Info,
+                "Nothing to refine: generate a message first.",
+            );
+            return;
+        }
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "Refine".to_string(),
+            message: "What should change? e.g. mention the config migration, drop the test details. The message, this note and the staged diff are sent to the model.".to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::Refine),
+            input_value: String::new(),
+        };
+    }
+
+    fn open_pr_base_modal(&mut self) {
+        let cfg = Config::load().ok().flatten();
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "PR Description".to_string(),
+            message: "Base to compare against (commit messages and the diff since it are sent to the model).".to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::PrBase),
+            input_value: pr::default_base(cfg.as_ref()).unwrap_or_default(),
+        };
+    }
+
+    /// Ask for the ref to compare HEAD with: the one on screen, else the remote's default
+    /// branch.
+    fn open_diff_ref_modal(&mut self) {
+        let current = match &self.diff_view_source {
+            DiffViewSource::AgainstRef(reference) => Some(reference.clone()),
+            _ => None,
+        };
+        self.modal = ModalState {
+            kind: ModalKind::TextInput,
+            title: "Diff against ref".to_string(),
+            message: "Branch, tag or commit; shows what HEAD changed since it forked from there."
+                .to_string(),
+            confirm_purpose: None,
+            input_purpose: Some(TextInputPurpose::DiffRef),
+            input_value: current.or_else(git::default_base_ref).unwrap_or_default(),
+        };
+    }
+
+    /// Collect the branch's commits and diff since `base` and show what sending them would
+    /// cost before anything goes to the model, as the CLI does.
+    fn open_pr_preflight_modal(&mut self, base: &str) {
+        let changes = match pr::collect(base) {
+            Ok(changes) => changes,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("PR description failed: {e:#}"));
+                return;
+            }
+        };
+        let estimate = generator::estimate_cost(self.mock_mode, &changes.prompt_text(), None);
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "PR Description".to_string(),
+            message: format!(
+                "{} commit(s) since {}, ~{} bytes of diff.\n\nEstimate: {}.\n\nEnter: send to the model. Esc: cancel.",
+                changes.commits.len(),
+                changes.base,
+                changes.diff.len(),
+                estimate
+            ),
+            confirm_purpose: Some(ConfirmPurpose::SendPrDescription),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.pending_pr = Some(changes);
+    }
+
+    fn start_generate_pr_description(
+        &mut self,
+        tasks: &TaskRunner,
+        changes: pr::BranchChanges,
+    ) -> bool {
+        let mock_mode = self.mock_mode;
+        let label = format!("Writing a PR description against {}…", changes.base);
+        let started = tasks.start(TaskKind::GeneratePrDescription, label, move |tx| {
+            let base = changes.base.clone();
+            let _ = tx.send(TaskEvent::Progress {
+                message: format!("{} commit(s); asking the model…", changes.commits.len()),
+            });
+            let (generator, _, _) = generator::build_generator(mock_mode)?;
+            let generation = runtime::tui_block_on(pr::generate(&generator, &changes))?;
+            let warning = generation.prompt_sizes.truncated().then(|| {
+                format!(
+                    "The diff was trimmed to fit the ~{} token prompt budget; the commit list was kept.",
+                    generation.prompt_sizes.budget_tokens
+                )
+            });
+            Ok(TaskResult::PrDescription {
+                base,
+                body: generation.message,
+                warning,
+                tokens: generation.tokens,
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("PR description ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn start_generate_from_staged(&mut self, tasks: &TaskRunner) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to start Generate while another task is running.");
+            return false;
+        }
+        if !git::is_repo() {
+            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
+            self.log("Generate failed: not a git repository.");
+            return true;
+        }
+        self.refinement = None;
+
+        if let Some(branch) = self.snapshot.as_ref().and_then(|s| s.branch.as_ref()) {
+            let protected = config::protected_branches(Config::load().ok().flatten().as_ref());
+            if protected.contains(branch) {
+                self.log(format!(
+                    "On {}: press b to create a branch before committing.",
+                    branch
+                ));
+            }
+        }
+
+        let repo_state = git::repo_state().unwrap_or_default();
+        let conflicted = repo_state.conflicted;
+        if !conflicted.is_empty() {
+            self.set_status(
+                StatusLevel::Error,
+                format!(
+                    "Generation blocked: {} file(s) have merge conflicts.",
+                    conflicted.len()
+                ),
+            );
+            self.modal = ModalState {
+                kind: ModalKind::Confirm,
+                title: "Merge conflicts".to_string(),
+                message: format!(
+                    "{} file(s) have unresolved conflicts:\n{}\n\nResolve them now? Each file opens in your editor.",
+                    conflicted.len(),
+                    conflicted.join("\n")
+                ),
+                confirm_purpose: Some(ConfirmPurpose::ResolveConflicts),
+                input_purpose: None,
+                input_value: String::new(),
+            };
+            return true;
+        }
+
+        // A merge is concluded by the commit; anything else would get a commit in the middle.
+        if let Some(operation) = repo_state
+            .operation
+            .filter(|operation| *operation != git::Operation::Merge)
+        {
+            self.modal = ModalState {
+                kind: ModalKind::Confirm,
+                title: format!("{} in progress", operation.command()),
+                message: format!(
+                    "A {} is in progress. Committing now adds a new commit in the middle of it; `{} --continue` is usually what finishes it.\n\nGenerate and commit anyway?",
+                    operation,
+                    operation.command()
+                ),
+                confirm_purpose: Some(ConfirmPurpose::GenerateDuringOperation),
+                input_purpose: None,
+                input_value: String::new(),
+            };
+            return true;
+        }
+
+        self.confirm_duplicate_then_generate(tasks)
+    }
+
+    /// Generate from the staged changes, asking first when they are what git-wiz committed
+    /// last.
+    fn confirm_duplicate_then_generate(&mut self, tasks: &TaskRunner) -> bool {
+        // Staged diff identical to the last git-wiz commit: ask before spending tokens.
+        let staged = git::get_diff_staged_allow_empty().unwrap_or_default();
+        if let Some(last) = history::matching_last_commit(&staged) {
+            self.modal = ModalState {
+                kind: ModalKind::Confirm,
+                title: "Confirm".to_string(),
+                message: format!(
+                    "These staged changes look identical to what you committed {} ({}). Generate anyway?",
+                    last.age(),
+                    last.commit
+                ),
+                confirm_purpose: Some(ConfirmPurpose::GenerateDuplicateStaged),
+                input_purpose: None,
+                input_value: String::new(),
+            };
+            return true;
+        }
+
+        self.spawn_generate_from_staged(tasks)
+    }
+
+    /// Generate a message for HEAD as amended: its changes plus the staged ones.
+    fn start_generate_for_amend(&mut self, tasks: &TaskRunner) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to start Generate while another task is running.");
+            return false;
+        }
+        self.refinement = None;
+        self.spawn_generate_from_staged(tasks)
+    }
+
+    /// Generate from the staged changes, or in amend mode from HEAD plus the staged changes.
+    fn spawn_generate_from_staged(&mut self, tasks: &TaskRunner) -> bool {
+        let mock_mode = self.mock_mode;
+        let amending = self.amending.is_some();
+        let output_limit = self.output_limit;
+        let refinement = self.refinement.clone();
+        let user_hint = self.generate_hint.clone();
+
+        let started = tasks.start(
+            TaskKind::GenerateCommitFromStaged,
+            if refinement.is_some() {
+                "Refining commit message (staged)…"
+            } else if amending {
+                "Generating commit message (HEAD + staged)…"
+            } else {
+                "Generating commit message (staged)…"
+            },
+            move |tx| {
+                let _ = tx.send(TaskEvent::Progress {
+                    message: "Collecting staged diff…".to_string(),
+                });
+                if let Err(e) = git::commit_template() {
+                    let _ = tx.send(TaskEvent::Progress {
+                        message: format!("{:#}", e),
+                    });
+                }
+
+                let (summary, diff) = if amending {
+                    let diff = git::FilteredDiff {
+                        text: prompt::amend_diff()?,
+                        excluded: Vec::new(),
+                    };
+                    (git::amend_diff_summary()?, diff)
+                } else {
+                    (
+                        git::diff_summary(git::DiffSource::Staged)?,
+                        prompt::diff_for(git::DiffSource::Staged)?,
+                    )
+                };
+                if let Some(largest) = largest_files_log(&summary) {
+                    let _ = tx.send(TaskEvent::Progress { message: largest });
+                }
+                let summary_text = match prompt::excluded_summary(&diff.excluded) {
+                    Some(note) => format!("{} ({})", summary_text(&summary), note),
+                    None => summary_text(&summary),
+                };
+                let filtered = !diff.excluded.is_empty();
+                let diff = diff.text;
+                if !mock_mode {
+                    if let Some(cfg) = Config::load()? {
+                        runtime::tui_block_on(offline::probe(&cfg))?;
+                    }
+                }
+                let (generator, provider, model) = match output_limit {
+                    Some(limit) => generator::build_generator_with_output_limit(mock_mode, limit)?,
+                    None => generator::build_generator(mock_mode)?,
+                };
+                // The user's hint wins over the one derived from the branch.
+                let (hint, summary_text) = match user_hint {
+                    Some(hint) => (Some(hint), summary_text),
+                    None => {
+                        let branch_hint =
+                            prompt::branch_hint(Config::load().ok().flatten().as_ref())?;
+                        let summary_text = match &branch_hint {
+                            Some(hint) => format!("{} (hint from branch: {})", summary_text, hint),
+                            None => summary_text,
+                        };
+                        (branch_hint, summary_text)
+                    }
+                };
+                // Concluding a merge: describe the merge; committing finishes it.
+                let merge = conflicts::merge_in_progress()?;
+                let hint = match &merge {
+                    Some(subject) => Some(conflicts::merge_hint(subject, hint.as_deref())),
+                    None => hint,
+                };
+
+                let _ = tx.send(TaskEvent::Progress {
+                    message: if refinement.is_some() {
+                        format!("Refining with {}…", provider)
+                    } else if merge.is_some() {
+                        format!("Generating merge commit message with {}…", provider)
+                    } else {
+                        format!("Generating with {}…", provider)
+                    },
+                });
+
+                // The editor fills in as the reply arrives.
+                let stream_tx = tx.clone();
+                let on_token = move |text: &str| {
+                    let _ = stream_tx.send(TaskEvent::Partial {
+                        text: text.to_string(),
+                    });
+                };
+                // A rate-limited request counts down in the footer.
+                let wait_tx = tx.clone();
+                let report = move |text: &str| {
+                    let _ = wait_tx.send(TaskEvent::Progress {
+                        message: text.to_string(),
+                    });
+                };
+                let streamed = http::with_wait_reporter(report, || {
+                    runtime::tui_block_on(async {
+                        match &refinement {
+                            Some(r) => generator.refine(&diff, hint, r, &on_token).await,
+                            None => generator.generate_streaming(&diff, hint, &on_token).await,
+                        }
+                    })
+                });
+                let generation = match streamed {
+                    Ok(generation) => generation,
+                    Err(e) => {
+                        let Some(unavailable) = e.downcast_ref::<ModelUnavailable>() else {
+                            return Err(e);
+                        };
+                        return Ok(TaskResult::Error {
+                            message: format!(
+                                "{} Pick another one in the Config tab (Change model).",
+                                unavailable
+                            ),
+                            detail: None,
+                        });
+                    }
+                };
+
+                let truncated_at = generation.truncated.then(|| generator.output_limit());
+                let cfg = Config::load().ok().flatten();
+                let mut generated = generation.message;
+                let mut fix_error = None;
+                let violations = conventional::check(&generated, cfg.as_ref());
+                if !violations.is_empty() && cfg.as_ref().is_some_and(|c| c.conventional_autofix) {
+                    let _ = tx.send(TaskEvent::Progress {
+                        message: "Fixing the commit format…".to_string(),
+                    });
+                    match runtime::tui_block_on(conventional::fix(
+                        &generator,
+                        &generated,
+                        &violations,
+                    )) {
+                        Ok(fixed) => generated = fixed,
+                        Err(e) => fix_error = Some(format!("Could not fix the format: {:#}", e)),
+                    }
+                }
+
+                let (message, hook_rejection) = match hook::post_generate_command() {
+                    Some(command) => {
+                        let _ = tx.send(TaskEvent::Progress {
+                            message: "Checking message…".to_string(),
+                        });
+                        match hook::run_post_generate(&command, &generated) {
+                            HookOutcome::Accepted(checked) => (checked, None),
+                            HookOutcome::Rejected(reason) => (generated, Some(reason)),
+                        }
+                    }
+                    None => (generated, None),
+                };
+
+                let branch = git::current_branch().ok();
+                let message = match &cfg {
+                    Some(cfg) => {
+                        message::append_trailers(&message, &cfg.trailers, branch.as_deref())
+                    }
+                    None => message,
+                };
+                let issue_candidates = message::issue_candidates(branch.as_deref(), &diff);
+                let scope_fix = scope::fix_for(&message, &diff, cfg.as_ref()).map(Box::new);
+                let prompt_link_issues = cfg.as_ref().is_some_and(|c| c.prompt_link_issues);
+                let warnings = message::subject_warning(
+                    &message,
+                    &message::FormatOptions::from_config(cfg.as_ref()),
+                )
+                .into_iter()
+                .chain(conventional::warning(&conventional::check(
+                    &message,
+                    cfg.as_ref(),
+                )))
+                .chain(breaking::warning(&breaking::for_config(
+                    &diff,
+                    cfg.as_ref(),
+                )))
+                .chain(git::encoding_warning(&diff))
+                .chain(fix_error)
+                .collect();
+
+                Ok(TaskResult::GeneratedCommitMessage {
+                    message,
+                    hook_rejection,
+                    source: git::DiffSource::Staged,
+                    filtered,
+                    trimmed: generation.prompt_sizes.truncated(),
+                    latency_ms: generation.latency.as_millis() as u64,
+                    prompt_sizes: Box::new(generation.prompt_sizes),
+                    tokens: generation.tokens,
+                    truncated_at,
+                    summary: summary_text,
+                    provider,
+                    model,
+                    issue_candidates,
+                    prompt_link_issues,
+                    warnings,
+                    scope_fix,
+                })
+            },
+        );
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Generate ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn start_commit_from_editor(&mut self, tasks: &TaskRunner) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to start Commit while another task is running.");
+            return false;
+        }
+        if !git::is_repo() {
+            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
+            self.log("Commit failed: not a git repository.");
+            return true;
+        }
+
+        let msg = self.commit_editor.lines().join("\n").trim().to_string();
+        if msg.is_empty() {
+            self.set_status(StatusLevel::Error, "Commit message is empty.");
+            self.log("Commit failed: empty message.");
+            return true;
+        }
+
+        // A rejected message is re-checked (after any edits) before committing.
+        if self.hook_rejected {
+            if let Some(command) = hook::post_generate_command() {
+                match hook::run_post_generate(&command, &msg) {
+                    HookOutcome::Accepted(checked) => {
+                        self.hook_rejected = false;
+                        if checked != msg {
+                            self.set_commit_message_text(&checked);
+                        }
+                        self.log("post_generate_command accepted the message.");
+                    }
+                    HookOutcome::Rejected(reason) => {
+                        self.open_hook_rejected_modal(ConfirmPurpose::HookRejectedCommit, &reason);
+                        return true;
+                    }
+                }
+            }
+        }
+
+        self.review_or_commit(tasks)
+    }
+
+    /// Show the pre-commit review if enabled, otherwise commit right away.
+    fn review_or_commit(&mut self, tasks: &TaskRunner) -> bool {
+        let msg = self.commit_editor.lines().join("\n").trim().to_string();
+        let confirm_commit = Config::load()
+            .ok()
+            .flatten()
+            .is_none_or(|c| c.confirm_commit);
+        if confirm_commit {
+            match review::staged_review(&msg) {
+                Ok(review) => {
+                    self.modal = ModalState {
+                        kind: ModalKind::Confirm,
+                        title: "Review Commit".to_string(),
+                        message: review.lines().join("\n"),
+                        confirm_purpose: Some(ConfirmPurpose::CommitReview),
+                        input_purpose: None,
+                        input_value: String::new(),
+                    };
+                    return true;
+                }
+                Err(e) => {
+                    self.set_status(StatusLevel::Error, format!("{:#}", e));
+                    self.log(format!("Commit review failed: {e:#}"));
+                    return true;
+                }
+            }
+        }
+
+        self.spawn_commit_from_editor(tasks, false)
+    }
+
+    /// Load HEAD into the editor and switch the Generate tab to its amend actions.
+    fn enter_amend_mode(&mut self) {
+        match git::head_commit() {
+            Ok(head) => {
+                self.set_commit_message_text(&head.message);
+                self.set_status(
+                    StatusLevel::Info,
+                    format!(
+                        "Amending {}: edit the message, stage extra changes, then Enter.",
+                        head.short
+                    ),
+                );
+                self.log(format!("Entered amend mode for {}.", head.short));
+                self.amending = Some(head);
+                self.hook_rejected = false;
+                self.action_index = 0;
+            }
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Amend failed: {e:#}"));
+            }
+        }
+    }
+
+    pub fn exit_amend_mode(&mut self) {
+        if self.amending.take().is_some() {
+            self.clear_editor();
+            self.action_index = 0;
+        }
+    }
+
+    /// Spell out what amending does (and whether HEAD was already pushed) before doing it.
+    fn confirm_amend(&mut self, tasks: &TaskRunner) {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            return;
+        }
+        let Some(head) = self.amending.clone() else {
+            return;
+        };
+        if self.commit_editor.lines().join("\n").trim().is_empty() {
+            self.set_status(StatusLevel::Error, "Commit message is empty.");
+            return;
+        }
+
+        let staged = git::diff_summary(git::DiffSource::Staged)
+            .map(|s| s.files_changed)
+            .unwrap_or(0);
+        let mut message = format!(
+            "Amend {}? This rewrites history: the commit gets a new hash.\n\n{}",
+            head.short,
+            if staged == 0 {
+                "Nothing new is staged: only the message changes.".to_string()
+            } else {
+                format!("{} staged file(s) will be added to it.", staged)
+            }
+        );
+        match git::remote_branches_containing_head() {
+            Ok(branches) if !branches.is_empty() => message.push_str(&format!(
+                "\n\n! {} is already pushed ({}). You will have to force-push.",
+                head.short,
+                branches.join(", ")
+            )),
+            Ok(_) => {}
+            Err(e) => message.push_str(&format!(
+                "\n\n! Could not check whether it was pushed: {:#}",
+                e
+            )),
+        }
+
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Rewrite history?".to_string(),
+            message,
+            confirm_purpose: Some(ConfirmPurpose::AmendCommit),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    fn spawn_amend_commit(&mut self, tasks: &TaskRunner, skip_hooks: bool) -> bool {
+        let Some(head) = self.amending.clone() else {
+            return false;
+        };
+        let msg = self.commit_editor.lines().join("\n").trim().to_string();
+        let label = if commit_hooks(skip_hooks).is_empty() {
+            "Amending commit…"
+        } else {
+            "Amending commit (running hooks)…"
+        };
+
+        let started = tasks.start(TaskKind::AmendCommit, label, move |tx| {
+            // Never amend a different commit than the one shown in amend mode.
+            let current = git::head_commit()?;
+            if current.hash != head.hash {
+                anyhow::bail!(
+                    "HEAD moved since amend mode started ({} -> {}). Exit amend mode and start again.",
+                    head.short,
+                    current.short
+                );
+            }
+            let mut options = repo_config::commit_options()?;
+            options.no_verify |= skip_hooks;
+            git::amend_commit(&msg, options, &mut |line| send_hook_output(&tx, line))?;
+            let amended = git::head_commit()?;
+            Ok(TaskResult::Amended {
+                status: format!("Amended {} -> {}.", head.short, amended.short),
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Amend ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn spawn_commit_from_editor(&mut self, tasks: &TaskRunner, skip_hooks: bool) -> bool {
+        let msg = self.commit_editor.lines().join("\n").trim().to_string();
+        let hooks = commit_hooks(skip_hooks);
+        let label = if hooks.is_empty() {
+            "Committing…"
+        } else {
+            "Committing (running hooks)…"
+        };
+
+        let started = tasks.start(TaskKind::CommitFromEditor, label, move |tx| {
+            // Instructions left over from "Load commit template" aren't part of the message.
+            let msg = match git::commit_template() {
+                Ok(Some(template)) => template.strip_comments(&msg).trim().to_string(),
+                _ => msg,
+            };
+            let mut options = repo_config::commit_options()?;
+            options.no_verify |= skip_hooks;
+            let started = Instant::now();
+            history::commit_and_record_with(&msg, options, &mut |line| {
+                send_hook_output(&tx, line)
+            })?;
+            let log = match repo_config::slow_hooks_warning(&hooks, started.elapsed()) {
+                Some(warning) => format!("Committed changes. {}", warning),
+                None => "Committed changes.".to_string(),
+            };
+            let committed = TaskResult::OkMessage {
+                status: "Committed successfully.".to_string(),
+                log: Some(log),
+            };
+            let auto_push = Config::load()
+                .ok()
+                .flatten()
+                .map(|c| c.auto_push)
+                .unwrap_or_default();
+            let then = match auto_push {
+                AutoPush::Never => return Ok(committed),
+                AutoPush::Ask => FollowUp::AskPushBranch,
+                AutoPush::Always => FollowUp::PushBranch,
+            };
+            Ok(TaskResult::Chained {
+                result: Box::new(committed),
+                then,
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Commit ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn start_stage_all(&mut self, tasks: &TaskRunner) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to start Stage All while another task is running.");
+            return false;
+        }
+        if !git::is_repo() {
+            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
+            self.log("Stage all failed: not a git repository.");
+            return true;
+        }
+
+        let started = tasks.start(TaskKind::StageAll, "Staging all changes…", move |_tx| {
+            git::stage_all()?;
+            Ok(TaskResult::OkMessage {
+                status: "Staged all changes.".to_string(),
+                log: Some("Staged all changes.".to_string()),
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Stage all ignored: task runner was busy.");
+        }
+        started
+    }
+
+    /// List the hunks of the unstaged diff, or with `unstage` the staged one, on the Stage
+    /// tab's right pane and focus it.
+    fn open_hunk_view(&mut self, unstage: bool) {
+        let files = match git::hunks(unstage) {
+            Ok(files) => files,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Listing hunks failed: {:#}", e));
+                return;
+            }
+        };
+        if files.is_empty() {
+            self.hunk_view = None;
+            let message = if unstage {
+                "Nothing staged."
+            } else {
+                "No changes to tracked files. Stage all (git add -A) adds new files."
+            };
+            self.set_status(StatusLevel::Info, message);
+            return;
+        }
+        self.hunk_view = Some(HunkView::new(unstage, files, Vec::new()));
+        self.focus = Focus::RightPane;
+        let verb = if unstage { "unstages" } else { "stages" };
+        self.set_status(
+            StatusLevel::Info,
+            format!("Space picks a hunk, Enter {} the picked ones.", verb),
+        );
+    }
+
+    /// List the hunks again, e.g. after coming back to the Stage tab; picks are dropped.
+    fn reload_hunk_view(&mut self) {
+        let Some(view) = &self.hunk_view else {
+            return;
+        };
+        let unstage = view.unstage;
+        match git::hunks(unstage) {
+            Ok(files) => self.hunk_view = Some(HunkView::new(unstage, files, Vec::new())),
+            Err(e) => {
+                self.hunk_view = None;
+                self.log(format!("Listing hunks failed: {:#}", e));
+            }
+        }
+    }
+
+    /// The hunks after an apply, with what it couldn't take.
+    pub fn set_hunk_view(
+        &mut self,
+        unstage: bool,
+        files: Vec<FileDiff>,
+        failures: Vec<git::HunkFailure>,
+    ) {
+        for failure in &failures {
+            self.log(format!(
+                "Not applied ({}): {}",
+                failure.path, failure.message
+            ));
+        }
+        self.hunk_view = Some(HunkView::new(unstage, files, failures));
+    }
+
+    /// Keys for the hunk list, while the Stage tab's right pane has focus.
+    pub fn handle_stage_key(&mut self, tasks: &TaskRunner, key: &KeyEvent) -> bool {
+        if self.focus != Focus::RightPane {
+            return false;
+        }
+        let Some(view) = self.hunk_view.as_mut() else {
+            return false;
+        };
+        match (key.code, key.modifiers) {
+            (KeyCode::Up, KeyModifiers::NONE) => {
+                view.index = view.index.saturating_sub(1);
+                true
+            }
+            (KeyCode::Down, KeyModifiers::NONE) => {
+                if view.index + 1 < view.rows.len() {
+                    view.index += 1;
+                }
+                true
+            }
+            (KeyCode::Char(' '), KeyModifiers::NONE) => {
+                if !view.rows.is_empty() && !view.picked.remove(&view.index) {
+                    view.picked.insert(view.index);
+                }
+                true
+            }
+            (KeyCode::Char('a'), KeyModifiers::NONE) => {
+                if view.picked.len() == view.rows.len() {
+                    view.picked.clear();
+                } else {
+                    view.picked = (0..view.rows.len()).collect();
+                }
+                true
+            }
+            (KeyCode::Char('c'), KeyModifiers::NONE) => {
+                self.hunk_view = None;
+                self.set_status(StatusLevel::Info, "Closed the hunk list.");
+                true
+            }
+            (KeyCode::Enter, KeyModifiers::NONE) => {
+                let _started = self.start_apply_hunks(tasks);
+                true
             }
+            _ => false,
         }
     }
 
-    fn start_generate_from_staged(&mut self, tasks: &TaskRunner) -> bool {
+    fn start_apply_hunks(&mut self, tasks: &TaskRunner) -> bool {
+        let Some(view) = &self.hunk_view else {
+            return false;
+        };
+        if view.picked.is_empty() {
+            self.set_status(StatusLevel::Info, "Pick hunks with Space first.");
+            return true;
+        }
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Generate while another task is running.");
+            self.log("Ignored: tried to apply hunks while another task is running.");
+            return false;
+        }
+
+        // The picked hunks, grouped by file.
+        let unstage = view.unstage;
+        let mut chosen: Vec<(FileDiff, Vec<usize>)> = Vec::new();
+        for &row in &view.picked {
+            let (f, hunk) = view.rows[row];
+            let file = &view.files[f];
+            match chosen.last_mut() {
+                Some((last, hunks)) if last.path == file.path => hunks.extend(hunk),
+                _ => chosen.push((file.clone(), hunk.into_iter().collect())),
+            }
+        }
+        let total = view.picked.len();
+        let (verb, label) = if unstage {
+            ("Unstaged", "Unstaging hunks…")
+        } else {
+            ("Staged", "Staging hunks…")
+        };
+
+        let started = tasks.start(TaskKind::ApplyHunks, label, move |_tx| {
+            let mut failures = Vec::new();
+            for (file, hunks) in &chosen {
+                failures.extend(git::apply_hunks(file, hunks, unstage)?);
+            }
+            let status = if failures.is_empty() {
+                format!("{} {} hunk(s).", verb, total)
+            } else {
+                format!(
+                    "{} {} of {} hunk(s); the rest no longer apply (the file changed?).",
+                    verb,
+                    total - failures.len(),
+                    total
+                )
+            };
+            Ok(TaskResult::Hunks {
+                unstage,
+                files: git::hunks(unstage)?,
+                failures,
+                status,
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Apply hunks ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn start_load_diff(&mut self, tasks: &TaskRunner, source: DiffViewSource) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to start Load Diff while another task is running.");
             return false;
         }
         if !git::is_repo() {
             self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Generate failed: not a git repository.");
+            self.log("Load diff failed: not a git repository.");
             return true;
         }
 
-        let mock_mode = self.mock_mode;
-
-        let started = tasks.start(
-            TaskKind::GenerateCommitFromStaged,
-            "Generating commit message (staged)…",
-            move |tx| {
-                let _ = tx.send(TaskEvent::Progress {
-                    message: "Collecting staged diff…".to_string(),
-                });
+        let name = match &source {
+            DiffViewSource::AgainstRef(_) => source.label(),
+            _ => source.label().to_lowercase(),
+        };
+        let label = format!("Loading {} diff…", name);
+        let status = format!("Loaded {} diff.", name);
+        let words = self.diff_words;
 
-                let summary = git::diff_summary(git::DiffSource::Staged)?;
-                let summary_text = format!(
-                    "{} files, +{} -{}, ~{} bytes",
-                    summary.files_changed, summary.insertions, summary.deletions, summary.bytes
-                );
+        let started = tasks.start(TaskKind::LoadDiff, label, move |_tx| {
+            let text = if words {
+                git::get_word_diff_allow_empty(source.to_git_source())?
+            } else {
+                git::get_diff_allow_empty(source.to_git_source(), &[])?
+            };
+            Ok(TaskResult::LoadedDiff {
+                source,
+                text,
+                status,
+            })
+        });
 
-                let diff = git::get_diff(git::DiffSource::Staged)?;
-                let (generator, provider, model) = build_generator_for_task(mock_mode)?;
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Load diff ignored: task runner was busy.");
+        }
+        started
+    }
 
-                let _ = tx.send(TaskEvent::Progress {
-                    message: format!("Generating with {}…", provider),
-                });
+    /// Switch the Diff tab between line and word diffs, and reload the diff shown.
+    pub fn toggle_word_diff(&mut self, tasks: &TaskRunner) {
+        self.diff_words = !self.diff_words;
+        let mode = if self.diff_words { "word" } else { "line" };
+        self.log(format!("Diff viewer: {} diff.", mode));
+        let _started = self.start_load_diff(tasks, self.diff_view_source.clone());
+    }
 
-                let msg = runtime::tui_block_on(generator.generate(&diff, None))?;
+    /// Start what a finished task chained (see `TaskResult::Chained`).
+    pub fn follow_up(&mut self, tasks: &TaskRunner, then: FollowUp) {
+        match then {
+            FollowUp::PushBranch => {
+                let _started = self.start_push_after_commit(tasks);
+            }
+            FollowUp::AskPushBranch => {
+                let destination = git::push_destination().unwrap_or_else(|_| "origin".to_string());
+                self.modal = ModalState {
+                    kind: ModalKind::Confirm,
+                    title: "Push".to_string(),
+                    message: format!(
+                        "Committed. Push to {} now?\n\nEnter: push. Esc: keep the commit local.",
+                        destination
+                    ),
+                    confirm_purpose: Some(ConfirmPurpose::PushAfterCommit),
+                    input_purpose: None,
+                    input_value: String::new(),
+                };
+            }
+        }
+    }
 
-                Ok(TaskResult::GeneratedCommitMessage {
-                    message: msg,
-                    summary: summary_text,
-                    provider,
-                    model,
-                })
-            },
-        );
+    /// Push after a commit (`auto_push`). A failed push leaves the commit in place.
+    fn start_push_after_commit(&mut self, tasks: &TaskRunner) -> bool {
+        let started = tasks.start(TaskKind::PushBranch, "Pushing branch…", move |_tx| {
+            Ok(match git::push_branch_with_policy(git::PushPolicy::FastForwardOnly) {
+                Ok(pushed) => TaskResult::OkMessage {
+                    status: format!("Committed. {}", pushed),
+                    log: Some(pushed.to_string()),
+                },
+                Err(e) if e.is::<git::RemoteBranchDiverged>() => push_stopped(e)?,
+                Err(e) => TaskResult::Error {
+                    message: format!(
+                        "Committed, but the push failed: {:#}. The commit is kept; push again from the Push tab.",
+                        e
+                    ),
+                    detail: None,
+                },
+            })
+        });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Generate ignored: task runner was busy.");
+            self.log("Push after commit ignored: task runner was busy.");
         }
         started
     }
 
-    fn start_commit_from_editor(&mut self, tasks: &TaskRunner) -> bool {
+    fn start_push_branch(&mut self, tasks: &TaskRunner) -> bool {
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Commit while another task is running.");
+            self.log("Ignored: tried to start Push Branch while another task is running.");
             return false;
         }
         if !git::is_repo() {
             self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Commit failed: not a git repository.");
+            self.log("Push branch failed: not a git repository.");
             return true;
         }
-
-        let msg = self.commit_editor.lines().join("\n").trim().to_string();
-        if msg.is_empty() {
-            self.set_status(StatusLevel::Error, "Commit message is empty.");
-            self.log("Commit failed: empty message.");
+        if let Ok(Some(commit)) = git::detached_head() {
+            self.set_status(
+                StatusLevel::Info,
+                format!("HEAD is detached at {}: no branch to push.", commit),
+            );
+            self.modal = ModalState {
+                kind: ModalKind::TextInput,
+                title: "Detached HEAD".to_string(),
+                message: format!(
+                    "HEAD is detached at {}, so there is no branch to push.\nName a branch to create here and push (Esc aborts):",
+                    commit
+                ),
+                confirm_purpose: None,
+                input_purpose: Some(TextInputPurpose::PushNewBranch),
+                input_value: String::new(),
+            };
             return true;
         }
 
-        let started = tasks.start(TaskKind::CommitFromEditor, "Committing…", move |_tx| {
-            git::commit_changes(&msg)?;
+        self.spawn_push_branch(tasks, false)
+    }
+
+    /// Push the current branch, after `git pull --ff-only` when `pull_first`. Otherwise
+    /// a branch behind its upstream (after a fetch with `fetch_before_push`) isn't pushed
+    /// but asks to pull first (`TaskResult::PushBehind`).
+    fn spawn_push_branch(&mut self, tasks: &TaskRunner, pull_first: bool) -> bool {
+        let fetch = Config::load()
+            .ok()
+            .flatten()
+            .is_some_and(|c| c.fetch_before_push);
+        let started = tasks.start(TaskKind::PushBranch, "Pushing branch…", move |tx| {
+            let mut note = String::new();
+            if pull_first {
+                let _ = tx.send(TaskEvent::Progress {
+                    message: "Pulling (fast-forward only)…".to_string(),
+                });
+                let pulled = git::pull_ff_only()?;
+                note = format!(" ({})", pulled);
+            } else {
+                if fetch {
+                    let _ = tx.send(TaskEvent::Progress {
+                        message: "Fetching…".to_string(),
+                    });
+                    if let Err(e) = git::fetch() {
+                        note = format!(" (could not fetch first: {:#})", e);
+                    }
+                }
+                if let Some(tracking) = git::ahead_behind()?.filter(|t| t.behind > 0) {
+                    return Ok(TaskResult::PushBehind { tracking });
+                }
+            }
+            let pushed = match git::push_branch_with_policy(git::PushPolicy::FastForwardOnly) {
+                Ok(pushed) => pushed,
+                Err(e) => return push_stopped(e),
+            };
             Ok(TaskResult::OkMessage {
-                status: "Committed successfully.".to_string(),
-                log: Some("Committed changes.".to_string()),
+                status: pushed.to_string(),
+                log: Some(format!("{}{}", pushed, note)),
             })
         });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Commit ignored: task runner was busy.");
+            self.log("Push branch ignored: task runner was busy.");
         }
         started
     }
 
-    fn start_stage_all(&mut self, tasks: &TaskRunner) -> bool {
+    /// Push the current branch, settling a same-named branch on origin with `policy`.
+    fn spawn_push_with_policy(&mut self, tasks: &TaskRunner, policy: git::PushPolicy) -> bool {
+        let label = match policy {
+            git::PushPolicy::Rebase => "Rebasing, then pushing…",
+            git::PushPolicy::ForceWithLease { .. } => "Force-pushing…",
+            git::PushPolicy::FastForwardOnly => "Pushing branch…",
+        };
+        let started = tasks.start(TaskKind::PushBranch, label, move |_tx| {
+            let pushed = git::push_branch_with_policy(policy)?;
+            Ok(TaskResult::OkMessage {
+                status: pushed.to_string(),
+                log: Some(pushed.to_string()),
+            })
+        });
+
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Push branch ignored: task runner was busy.");
+        }
+        started
+    }
+
+    fn is_protected(&self, branch: &str) -> bool {
+        config::protected_branches(Config::load().ok().flatten().as_ref())
+            .iter()
+            .any(|p| p.trim() == branch)
+    }
+
+    /// origin already has the branch and the push without upstream stopped: offer to
+    /// rebase onto it, overwrite it (`f`, unless protected) or leave it (Esc).
+    pub fn open_remote_branch_modal(&mut self, diverged: git::RemoteBranchDiverged) {
+        self.set_status(
+            StatusLevel::Info,
+            format!("Not pushed: origin/{} already exists.", diverged.branch),
+        );
+        self.log(format!("Push stopped: {}", diverged));
+        let force = if self.is_protected(&diverged.branch) {
+            String::new()
+        } else {
+            format!(
+                " f: overwrite origin/{} (--force-with-lease; its own commits are lost).",
+                diverged.branch
+            )
+        };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Branch exists on origin".to_string(),
+            message: format!(
+                "{}\n\nEnter: rebase onto origin/{}, then push.{} Esc: abort.",
+                diverged, diverged.branch, force
+            ),
+            confirm_purpose: Some(ConfirmPurpose::PushOverRemoteBranch),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+        self.pending_remote_branch = Some(diverged);
+    }
+
+    fn start_pull(&mut self, tasks: &TaskRunner) -> bool {
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Stage All while another task is running.");
+            self.log("Ignored: tried to start Pull while another task is running.");
             return false;
         }
         if !git::is_repo() {
-            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Stage all failed: not a git repository.");
+            self.set_status(
+                StatusLevel::Error,
+                "Not a git repository (or git is not installed).",
+            );
+            self.log("Pull failed: not a git repository.");
             return true;
         }
 
-        let started = tasks.start(TaskKind::StageAll, "Staging all changes…", move |_tx| {
-            git::stage_all()?;
+        let started = tasks.start(TaskKind::Pull, "Pulling…", move |_tx| {
+            let pulled = git::pull_ff_only()?;
             Ok(TaskResult::OkMessage {
-                status: "Staged all changes.".to_string(),
-                log: Some("Staged all changes.".to_string()),
+                status: pulled.to_string(),
+                log: Some(pulled.to_string()),
             })
         });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Stage all ignored: task runner was busy.");
+            self.log("Pull ignored: task runner was busy.");
         }
         started
     }
 
-    fn start_load_diff(&mut self, tasks: &TaskRunner, source: DiffViewSource) -> bool {
+    fn start_push_tag(&mut self, tasks: &TaskRunner, tag: String) -> bool {
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Load Diff while another task is running.");
+            self.log("Ignored: tried to start Push Tag while another task is running.");
             return false;
         }
         if !git::is_repo() {
             self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Load diff failed: not a git repository.");
+            self.log("Push tag failed: not a git repository.");
             return true;
         }
 
-        let label = format!("Loading {} diff…", source.label());
-        let status = format!("Loaded {} diff.", source.label().to_lowercase());
+        let t = tag.trim().to_string();
+        if t.is_empty() {
+            self.set_status(StatusLevel::Error, "Tag cannot be empty.");
+            self.log("Push tag failed: empty tag.");
+            return true;
+        }
 
-        let started = tasks.start(TaskKind::LoadDiff, label, move |_tx| {
-            let text = git::get_diff_allow_empty(source.to_git_source())?;
-            Ok(TaskResult::LoadedDiff {
-                source,
-                text,
-                status,
+        let label = format!("Pushing tag {}…", t);
+        // Unknown until `r` checks origin again.
+        if let Some(listed) = self.tags.iter_mut().find(|listed| listed.name == t) {
+            listed.on_remote = None;
+        }
+
+        let started = tasks.start(TaskKind::PushTag, label, move |_tx| {
+            git::push_tag(&t)?;
+            Ok(TaskResult::OkMessage {
+                status: format!("Tag pushed: {}", t),
+                log: Some(format!("Tag pushed: {}", t)),
             })
         });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Load diff ignored: task runner was busy.");
+            self.log("Push tag ignored: task runner was busy.");
         }
         started
     }
 
-    fn start_push_branch(&mut self, tasks: &TaskRunner) -> bool {
-        use std::process::Command;
-
-        if tasks.is_busy() {
-            self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Push Branch while another task is running.");
-            return false;
+    /// Read the preflight checks again; `.gitwiz.toml` may have changed.
+    pub fn refresh_preflight_checks(&mut self) {
+        match repo_config::preflight_config() {
+            Ok(preflight) => self.preflight_checks = preflight.checks(),
+            Err(e) => {
+                self.preflight_checks.clear();
+                self.log(format!("Reading the preflight checks failed: {:#}", e));
+            }
         }
-        if !git::is_repo() {
-            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Push branch failed: not a git repository.");
-            return true;
+    }
+
+    /// Reload the Tags list from the local repository.
+    pub fn refresh_tags(&mut self) {
+        match git::tags(None) {
+            Ok(tags) => self.set_tags(tags),
+            Err(e) => {
+                self.tags.clear();
+                self.log(format!("Listing tags failed: {:#}", e));
+            }
         }
+    }
 
-        let started = tasks.start(TaskKind::PushBranch, "Pushing branch…", move |_tx| {
-            // If upstream exists, `git push` is enough. Otherwise set upstream.
-            let has_upstream = Command::new("git")
-                .args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
-
-            if has_upstream {
-                let o = Command::new("git").args(["push"]).output()?;
-                if !o.status.success() {
-                    anyhow::bail!("git push failed: {}", String::from_utf8_lossy(&o.stderr));
-                }
-                return Ok(TaskResult::OkMessage {
-                    status: "Branch pushed.".to_string(),
-                    log: Some("Branch pushed.".to_string()),
-                });
+    /// Show `tags`, keeping what an earlier check said about origin for the ones this
+    /// listing didn't check.
+    pub fn set_tags(&mut self, mut tags: Vec<git::TagInfo>) {
+        for tag in &mut tags {
+            if tag.on_remote.is_none() {
+                tag.on_remote = self
+                    .tags
+                    .iter()
+                    .find(|old| old.name == tag.name)
+                    .and_then(|old| old.on_remote);
             }
+        }
+        self.tag_index = self.tag_index.min(tags.len().saturating_sub(1));
+        self.tags = tags;
+    }
 
-            let o = Command::new("git")
-                .args(["rev-parse", "--abbrev-ref", "HEAD"])
-                .output()?;
-            if !o.status.success() {
-                anyhow::bail!(
-                    "git rev-parse --abbrev-ref HEAD failed: {}",
-                    String::from_utf8_lossy(&o.stderr)
-                );
+    /// Keys for the Tags list, while the Push tab's right pane has focus.
+    pub fn handle_push_key(&mut self, tasks: &TaskRunner, key: &KeyEvent) -> bool {
+        if self.focus != Focus::RightPane {
+            return false;
+        }
+        match (key.code, key.modifiers) {
+            (KeyCode::Up, KeyModifiers::NONE) => {
+                self.tag_index = self.tag_index.saturating_sub(1);
+                true
+            }
+            (KeyCode::Down, KeyModifiers::NONE) => {
+                if self.tag_index + 1 < self.tags.len() {
+                    self.tag_index += 1;
+                }
+                true
+            }
+            (KeyCode::Char('p'), KeyModifiers::NONE) => {
+                if let Some(tag) = self.tags.get(self.tag_index) {
+                    let name = tag.name.clone();
+                    let _started = self.start_push_tag(tasks, name);
+                }
+                true
+            }
+            (KeyCode::Char('s'), KeyModifiers::NONE) => {
+                self.show_selected_tag();
+                true
+            }
+            (KeyCode::Char('d'), KeyModifiers::NONE) => {
+                self.open_delete_tag_modal(false);
+                true
+            }
+            (KeyCode::Char('D'), _) => {
+                self.open_delete_tag_modal(true);
+                true
             }
-            let branch = String::from_utf8_lossy(&o.stdout).trim().to_string();
+            (KeyCode::Char('r'), KeyModifiers::NONE) => {
+                let _started = self.start_check_remote_tags(tasks);
+                true
+            }
+            _ => false,
+        }
+    }
 
-            let o = Command::new("git")
-                .args(["push", "-u", "origin", &branch])
-                .output()?;
-            if !o.status.success() {
-                anyhow::bail!(
-                    "git push -u origin {} failed: {}",
-                    branch,
-                    String::from_utf8_lossy(&o.stderr)
+    /// Log `git show` of the selected tag: tagger, message and the commit it points at.
+    fn show_selected_tag(&mut self) {
+        let Some(tag) = self.tags.get(self.tag_index) else {
+            return;
+        };
+        let name = tag.name.clone();
+        match git::show_tag(&name) {
+            Ok(details) => {
+                self.log(format!("Tag {}:", name));
+                for line in details.lines() {
+                    self.log(format!("  {}", line));
+                }
+                self.set_status(
+                    StatusLevel::Info,
+                    format!("Details of {} are in the log.", name),
                 );
             }
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Show tag failed: {:#}", e));
+            }
+        }
+    }
+
+    /// Ask before deleting the selected tag. Removing a release tag (`v*`) from origin
+    /// takes typing its name, like the CLI.
+    fn open_delete_tag_modal(&mut self, also_remote: bool) {
+        let Some(tag) = self.tags.get(self.tag_index) else {
+            return;
+        };
+        let name = tag.name.clone();
+        self.pending_tag = Some(name.clone());
+        if also_remote && git::is_release_tag(&name) {
+            self.modal = ModalState {
+                kind: ModalKind::TextInput,
+                title: "Delete release tag".to_string(),
+                message: format!(
+                    "{} is a release tag: removing it from origin breaks anything that fetches \
+                     that version. Type {} to delete it here and on origin:",
+                    name, name
+                ),
+                confirm_purpose: None,
+                input_purpose: Some(TextInputPurpose::DeleteReleaseTag),
+                input_value: String::new(),
+            };
+            return;
+        }
+        let (purpose, message) = if also_remote {
+            (
+                ConfirmPurpose::DeleteRemoteTag,
+                format!("Delete {} here and on origin?", name),
+            )
+        } else {
+            (
+                ConfirmPurpose::DeleteTag,
+                format!("Delete {} locally? origin keeps its copy.", name),
+            )
+        };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Delete tag".to_string(),
+            message: format!("{}\n\nEnter: delete. Esc: keep it.", message),
+            confirm_purpose: Some(purpose),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    fn start_delete_tag(&mut self, tasks: &TaskRunner, tag: String, also_remote: bool) -> bool {
+        if tasks.is_busy() {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Ignored: tried to delete a tag while another task is running.");
+            return false;
+        }
 
-            Ok(TaskResult::OkMessage {
-                status: "Branch pushed.".to_string(),
-                log: Some("Branch pushed.".to_string()),
+        let label = format!("Deleting tag {}…", tag);
+        let started = tasks.start(TaskKind::Tags, label, move |_tx| {
+            git::delete_tag(&tag, also_remote)?;
+            let status = if also_remote {
+                format!("Deleted tag {} here and on origin.", tag)
+            } else {
+                format!("Deleted tag {} locally.", tag)
+            };
+            Ok(TaskResult::Tags {
+                tags: git::tags(None)?,
+                status,
             })
         });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Push branch ignored: task runner was busy.");
+            self.log("Delete tag ignored: task runner was busy.");
         }
         started
     }
 
-    fn start_push_tag(&mut self, tasks: &TaskRunner, tag: String) -> bool {
-        use std::process::Command;
-
+    /// Mark which listed tags origin has (`git ls-remote`, so it needs the network).
+    fn start_check_remote_tags(&mut self, tasks: &TaskRunner) -> bool {
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Ignored: tried to start Push Tag while another task is running.");
+            self.log("Ignored: tried to check tags while another task is running.");
             return false;
         }
-        if !git::is_repo() {
-            self.set_status(StatusLevel::Error, "Not a git repository (or git is not installed).");
-            self.log("Push tag failed: not a git repository.");
-            return true;
-        }
 
-        let t = tag.trim().to_string();
-        if t.is_empty() {
-            self.set_status(StatusLevel::Error, "Tag cannot be empty.");
-            self.log("Push tag failed: empty tag.");
-            return true;
-        }
+        let label = "Checking tags on origin…".to_string();
+        let started = tasks.start(TaskKind::Tags, label, |_tx| {
+            let tags = git::tags(Some("origin"))?;
+            if tags.iter().any(|t| t.on_remote.is_none()) {
+                bail!("Couldn't list the tags on origin (is it set up and reachable?).");
+            }
+            let local_only = tags.iter().filter(|t| t.on_remote == Some(false)).count();
+            Ok(TaskResult::Tags {
+                status: format!(
+                    "Checked {} tag(s) against origin: {} not pushed.",
+                    tags.len(),
+                    local_only
+                ),
+                tags,
+            })
+        });
 
-        let label = format!("Pushing tag {}…", t);
+        if !started {
+            self.set_status(StatusLevel::Info, "Busy: another task is running.");
+            self.log("Tag check ignored: task runner was busy.");
+        }
+        started
+    }
 
-        let started = tasks.start(TaskKind::PushTag, label, move |_tx| {
-            let o = Command::new("git").args(["push", "origin", &t]).output()?;
-            if !o.status.success() {
-                anyhow::bail!(
-                    "git push origin {} failed: {}",
-                    t,
-                    String::from_utf8_lossy(&o.stderr)
-                );
+    /// Confirm a force push, naming the branch and what it replaces. Protected branches,
+    /// a detached HEAD or a missing upstream are refused before asking.
+    fn open_force_push_modal(&mut self) {
+        let protected = config::protected_branches(Config::load().ok().flatten().as_ref());
+        let target = match git::force_push_target(&protected) {
+            Ok(target) => target,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Force push refused: {:#}", e));
+                return;
             }
+        };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Force push".to_string(),
+            message: format!(
+                "Force-push '{}' to {}?\n\nThis replaces {} with your local branch: commits only the remote has are lost. --force-with-lease refuses if anyone pushed since your last fetch.",
+                target.branch, target.upstream, target.upstream
+            ),
+            confirm_purpose: Some(ConfirmPurpose::ForcePushBranch),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    fn start_force_push(&mut self, tasks: &TaskRunner) -> bool {
+        let protected = config::protected_branches(Config::load().ok().flatten().as_ref());
+        let started = tasks.start(TaskKind::PushBranch, "Force-pushing…", move |_tx| {
+            let target = git::force_push_branch(&protected)?;
+            let status = format!("Force-pushed '{}' to {}.", target.branch, target.upstream);
             Ok(TaskResult::OkMessage {
-                status: format!("Tag pushed: {}", t),
-                log: Some(format!("Tag pushed: {}", t)),
+                status: status.clone(),
+                log: Some(status),
             })
         });
 
         if !started {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
-            self.log("Push tag ignored: task runner was busy.");
+            self.log("Force push ignored: task runner was busy.");
         }
         started
     }
 
     fn start_push_all_tags(&mut self, tasks: &TaskRunner) -> bool {
-        use std::process::Command;
-
         if tasks.is_busy() {
             self.set_status(StatusLevel::Info, "Busy: another task is running.");
             self.log("Ignored: tried to start Push All Tags while another task is running.");
@@ -1194,13 +3770,7 @@ impl App {
         }
 
         let started = tasks.start(TaskKind::PushAllTags, "Pushing all tags…", move |_tx| {
-            let o = Command::new("git").args(["push", "--tags"]).output()?;
-            if !o.status.success() {
-                anyhow::bail!(
-                    "git push --tags failed: {}",
-                    String::from_utf8_lossy(&o.stderr)
-                );
-            }
+            git::push_all_tags()?;
             Ok(TaskResult::OkMessage {
                 status: "All tags pushed.".to_string(),
                 log: Some("All tags pushed.".to_string()),
@@ -1220,22 +3790,19 @@ impl App {
             anyhow::bail!("Not a git repository (or git is not installed).");
         }
 
-        self.diff_source_label = "Staged (recommended)".to_string();
+        self.set_diff_source(git::DiffSource::Staged, false, false);
 
         let summary = git::diff_summary(git::DiffSource::Staged)?;
-        self.diff_summary = format!(
-            "{} files, +{} -{}, ~{} bytes",
-            summary.files_changed, summary.insertions, summary.deletions, summary.bytes
-        );
+        self.diff_summary = summary_text(&summary);
 
-        let diff = git::get_diff(git::DiffSource::Staged)?;
+        let diff = git::get_diff(git::DiffSource::Staged, &[])?;
         let generator = self.build_generator()?;
 
         self.set_status(StatusLevel::Info, "Generating commit message...");
         self.log("Generating commit message (staged)…");
 
         // NOTE: blocking; runtime module will provide non-blocking soon.
-        let msg = super::runtime::tui_block_on(generator.generate(&diff, None))?;
+        let msg = super::runtime::tui_block_on(generator.generate(&diff, None))?.message;
 
         self.set_commit_message_text(&msg);
         self.set_status(StatusLevel::Success, "Generated.");
@@ -1280,143 +3847,421 @@ impl App {
             anyhow::bail!("Not a git repository (or git is not installed).");
         }
 
+        let text = git::get_diff_allow_empty(source.to_git_source(), &[])?;
         self.diff_view_source = source;
         self.diff_scroll = 0;
 
-        let text = git::get_diff_allow_empty(source.to_git_source())?;
         self.diff_text = text;
 
         Ok(())
     }
 
-    #[allow(dead_code)]
-    fn push_current_branch_with_upstream(&mut self) -> Result<()> {
-        if !git::is_repo() {
-            anyhow::bail!("Not a git repository (or git is not installed).");
+    fn start_release_bump(&mut self, bump: &str) -> bool {
+        if self.release_blocked() {
+            return true;
         }
+        // Compute next version from Cargo.toml using the core release module, then ask for confirmation.
+        let bump_kind = match bump {
+            "patch" => release::BumpKind::Patch,
+            "minor" => release::BumpKind::Minor,
+            "major" => release::BumpKind::Major,
+            other => {
+                self.set_status(StatusLevel::Error, format!("Unknown bump kind: {}", other));
+                self.log(format!("Release failed: unknown bump kind {}", other));
+                return true;
+            }
+        };
 
-        // If upstream exists, `git push` is enough. Otherwise set upstream.
-        let has_upstream = std::process::Command::new("git")
-            .args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
+        self.pending_release_bump = Some(bump_kind);
+        self.pending_release_version = None;
+        self.preflight_skip.clear();
+        self.plan_release();
+        true
+    }
 
-        if has_upstream {
-            let o = std::process::Command::new("git").args(["push"]).output()?;
-            if !o.status.success() {
-                anyhow::bail!("git push failed: {}", String::from_utf8_lossy(&o.stderr));
+    /// Plan the pending bump (or custom version) for `release_scope` and ask for the final
+    /// confirmation, naming the file and table it changes.
+    fn plan_release(&mut self) {
+        let manifest = release::manifest_path();
+        let plan = match (
+            self.pending_release_bump,
+            self.pending_release_version.clone(),
+        ) {
+            (Some(kind), _) => {
+                manifest.and_then(|m| release::plan_bump(m, kind, self.release_scope))
             }
-            return Ok(());
+            (None, Some(version)) => {
+                manifest.and_then(|m| release::plan_custom(m, &version, self.release_scope))
+            }
+            (None, None) => return,
+        };
+        let plan = match plan {
+            Ok(p) => p,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Release failed: {e:#}"));
+                return;
+            }
+        };
+
+        self.pending_release_version = Some(plan.new_version.clone());
+        let changelog = self.prepare_changelog(&plan.new_version);
+        let switch = self
+            .other_version_source()
+            .map(|other| format!("\n\nw: bump {} instead.", other))
+            .unwrap_or_default();
+        self.refresh_preflight_checks();
+        let mut checks = String::new();
+        if !self.preflight_checks.is_empty() {
+            checks.push_str("\n\nPreflight (1-9: skip / run for this release):");
         }
+        for (i, check) in self.preflight_checks.iter().take(9).enumerate() {
+            let mark = if self.preflight_skip.contains(&check.name) {
+                " "
+            } else {
+                "x"
+            };
+            checks.push_str(&format!("\n[{}] {} {}", mark, i + 1, check));
+        }
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Final confirmation".to_string(),
+            message: format!(
+                "Bump {} -> {} in {} and push tag {}? This triggers CI release + crates publish.{}{}{}{}",
+                plan.old_version,
+                plan.new_version,
+                plan.source,
+                plan.tag,
+                release::upstream_note(&release::ReleaseGuardrailConfig::from_config(
+                    Config::load().ok().flatten().as_ref(),
+                ))
+                    .ok()
+                    .flatten()
+                    .map(|note| format!("\n\n! {}", note))
+                    .unwrap_or_default(),
+                switch,
+                checks,
+                changelog
+            ),
+            confirm_purpose: Some(ConfirmPurpose::ReleaseTrigger),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
 
-        let branch = self.current_branch()?;
-        let o = std::process::Command::new("git")
-            .args(["push", "-u", "origin", &branch])
-            .output()?;
-        if !o.status.success() {
-            anyhow::bail!(
-                "git push -u origin {} failed: {}",
-                branch,
-                String::from_utf8_lossy(&o.stderr)
-            );
+    /// The version the other `VersionScope` would bump, when the package has its own
+    /// version and its workspace has one too.
+    fn other_version_source(&self) -> Option<release::VersionSource> {
+        let manifest = release::manifest_path().ok()?;
+        let workspace = release::workspace_alternative(&manifest).ok()??;
+        match self.release_scope {
+            release::VersionScope::Package => Some(workspace),
+            release::VersionScope::Workspace => {
+                release::version_source(&manifest, release::VersionScope::Package).ok()
+            }
         }
+    }
 
-        Ok(())
+    /// The branch is behind its upstream, so a push would be rejected: offer to pull first.
+    pub fn open_pull_then_push_modal(&mut self, tracking: &git::AheadBehind) {
+        self.set_status(StatusLevel::Info, format!("Not pushed: {}.", tracking));
+        self.log(format!("Push stopped: {}.", tracking));
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Behind upstream".to_string(),
+            message: format!(
+                "{} has {} commit(s) this branch doesn't have; a push now would be rejected.\n\nEnter: pull (fast-forward only), then push. Esc: cancel.",
+                tracking.upstream, tracking.behind
+            ),
+            confirm_purpose: Some(ConfirmPurpose::PullThenPush),
+            input_purpose: None,
+            input_value: String::new(),
+        };
     }
 
-    #[allow(dead_code)]
-    fn push_tag(&mut self, tag: &str) -> Result<()> {
-        if !git::is_repo() {
-            anyhow::bail!("Not a git repository (or git is not installed).");
+    /// List the local branches, the current one selected.
+    pub fn open_branch_picker(&mut self) {
+        let branches = match git::branches() {
+            Ok(branches) => branches,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Listing branches failed: {:#}", e));
+                return;
+            }
+        };
+        self.branch_picker_index = branches.iter().position(|b| b.current).unwrap_or(0);
+        self.branch_picker = branches;
+        self.modal = ModalState {
+            kind: ModalKind::BranchPicker,
+            title: "Branches".to_string(),
+            message: "↑/↓ select   Enter: switch   n: new branch   Esc: close".to_string(),
+            confirm_purpose: None,
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    /// Switch to `branch`. When git refuses and the tree has changes, offer to stash them.
+    fn switch_to_branch(&mut self, branch: &git::Branch) {
+        if branch.current {
+            self.set_status(StatusLevel::Info, format!("Already on {}.", branch.name));
+            return;
         }
-        let t = tag.trim();
-        if t.is_empty() {
-            anyhow::bail!("Tag name cannot be empty.");
+        let Err(e) = git::switch_branch(&branch.name) else {
+            self.set_status(
+                StatusLevel::Success,
+                format!("Switched to {}.", branch.name),
+            );
+            self.log(format!("Switched to {}.", branch.name));
+            self.request_summary_refresh();
+            return;
+        };
+        self.set_status(StatusLevel::Error, format!("{:#}", e));
+        self.log(format!("Switch failed: {:#}", e));
+        if git::snapshot().is_ok_and(|s| s.is_clean()) {
+            return;
         }
+        self.pending_switch = Some(branch.name.clone());
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Local changes".to_string(),
+            message: format!(
+                "{:#}\n\nEnter: stash your changes (untracked files too) and switch to {}. Esc: cancel.",
+                e, branch.name
+            ),
+            confirm_purpose: Some(ConfirmPurpose::StashAndSwitch),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
 
-        let o = std::process::Command::new("git")
-            .args(["push", "origin", t])
-            .output()?;
-        if !o.status.success() {
-            anyhow::bail!(
-                "git push origin {} failed: {}",
-                t,
-                String::from_utf8_lossy(&o.stderr)
-            );
+    fn stash_and_switch(&mut self, branch: &str) {
+        let message = format!("git-wiz: before switching to {}", branch);
+        let stash = match git::stash_push(Some(&message)) {
+            Ok(entry) => entry.map(|entry| entry.name).unwrap_or_default(),
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Stash failed: {:#}", e));
+                return;
+            }
+        };
+        self.log(format!("Stashed your changes as {}.", stash));
+        self.request_summary_refresh();
+        if let Err(e) = git::switch_branch(branch) {
+            self.set_status(StatusLevel::Error, format!("{:#}", e));
+            self.log(format!(
+                "Switch failed: {:#}. Your changes are stashed as {}.",
+                e, stash
+            ));
+            return;
         }
-        Ok(())
+        self.set_status(
+            StatusLevel::Success,
+            format!(
+                "Switched to {}. Your changes are stashed as {}.",
+                branch, stash
+            ),
+        );
+        self.log(format!("Switched to {}.", branch));
     }
 
-    #[allow(dead_code)]
-    fn push_all_tags(&mut self) -> Result<()> {
-        if !git::is_repo() {
-            anyhow::bail!("Not a git repository (or git is not installed).");
+    /// Explain why the pull stopped and offer to log the commits on each side.
+    pub fn open_divergence_modal(&mut self, diverged: &git::NotFastForward) {
+        self.set_status(
+            StatusLevel::Error,
+            format!(
+                "Not pulled: the branch and {} have diverged.",
+                diverged.upstream
+            ),
+        );
+        self.log(format!("Pull stopped: {}", diverged));
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Diverged from upstream".to_string(),
+            message: format!(
+                "{}\n\nEnter: show the diverging commits in the log. Esc: close.",
+                diverged
+            ),
+            confirm_purpose: Some(ConfirmPurpose::ShowDivergence),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
+
+    /// Report it and return true when a merge, rebase or conflicts rule out a release now.
+    fn release_blocked(&mut self) -> bool {
+        match release::ensure_no_operation_in_progress() {
+            Ok(()) => false,
+            Err(e) => {
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Release failed: {e:#}"));
+                true
+            }
         }
+    }
 
-        let o = std::process::Command::new("git")
-            .args(["push", "--tags"])
-            .output()?;
-        if !o.status.success() {
-            anyhow::bail!(
-                "git push --tags failed: {}",
-                String::from_utf8_lossy(&o.stderr)
-            );
+    /// Draft the CHANGELOG.md section for `new_version` when `changelog` is enabled and
+    /// return its preview for the final confirmation.
+    fn prepare_changelog(&mut self, new_version: &str) -> String {
+        self.pending_changelog = None;
+        if !Config::load().ok().flatten().is_some_and(|c| c.changelog) {
+            return String::new();
+        }
+        let scope = self.release_scope;
+        match release::manifest_path()
+            .and_then(|manifest| release::plan_custom(manifest, new_version, scope))
+            .and_then(|plan| release::generate_changelog_section(&plan))
+        {
+            Ok(section) => {
+                self.pending_changelog = Some(section);
+                changelog_preview(self.pending_changelog.as_deref())
+            }
+            Err(e) => {
+                self.log(format!("Changelog skipped: {:#}", e));
+                format!("\n\n! No changelog section: {:#}", e)
+            }
         }
-        Ok(())
     }
 
-    #[allow(dead_code)]
-    fn current_branch(&self) -> Result<String> {
-        let o = std::process::Command::new("git")
-            .args(["rev-parse", "--abbrev-ref", "HEAD"])
-            .output()?;
-
-        if !o.status.success() {
-            anyhow::bail!(
-                "git rev-parse --abbrev-ref HEAD failed: {}",
-                String::from_utf8_lossy(&o.stderr)
-            );
+    /// Run the pending release with the TUI suspended. With `stash`, the working tree's
+    /// changes are stashed first and popped afterwards, even when the release fails.
+    fn trigger_release(&mut self, stash: bool) {
+        let Some(v) = self.pending_release_version.clone() else {
+            self.set_status(StatusLevel::Error, "No pending release version.");
+            self.log("Release failed: missing pending version.");
+            return;
+        };
+
+        // Suspend the TUI for the whole release execution so cargo/clippy/test output
+        // does not corrupt the terminal UI. The release pipeline intentionally streams
+        // output to stdout/stderr for transparency, and gpg may ask for a passphrase
+        // when signing the tag (`tag.sign`).
+        let started = std::time::Instant::now();
+        let mut stashed = None;
+        let result = runtime::with_tui_suspended(|| {
+            if stash {
+                stashed = git::stash_push(Some(&format!("git-wiz: before release v{}", v)))?;
+            }
+            self.perform_release(&v)
+        });
+        // Popped whether the release went through or not.
+        let restore_error = stashed
+            .as_ref()
+            .and_then(|entry| git::stash_pop(&entry.name).err());
+        notify::task_finished(
+            started.elapsed(),
+            if result.is_ok() {
+                "git-wiz: release pushed"
+            } else {
+                "git-wiz: release failed"
+            },
+        );
+
+        match result {
+            Ok(_) => {
+                let tag = format!("v{}", v);
+                self.set_status(
+                    StatusLevel::Success,
+                    format!("Release initiated: pushed tag {}", tag),
+                );
+                self.log(format!("Release initiated: {}", tag));
+
+                if let Some(repo_https) = origin_https_repo_url().ok().flatten() {
+                    self.log(format!(
+                        "Track progress (Actions): {}/actions?query=workflow%3ARelease",
+                        repo_https
+                    ));
+                    self.log(format!("Release page: {}/releases/tag/{}", repo_https, tag));
+                }
+                self.open_release_notes_modal(tag);
+            }
+            Err(e) => {
+                if let Some(missing) = e.downcast_ref::<release::MissingRemote>() {
+                    // Nothing was modified yet: offer to add the remote and resume.
+                    self.open_release_remote_modal(&missing.remote, String::new());
+                } else if !stash && e.is::<release::DirtyWorkingTree>() {
+                    // Nothing was modified yet either: offer to stash and resume.
+                    self.open_stash_release_modal(&v);
+                }
+                self.set_status(StatusLevel::Error, format!("{:#}", e));
+                self.log(format!("Release failed: {:#}", e));
+                if let Some(incomplete) = self.pending_rollback.clone() {
+                    self.open_rollback_modal(&incomplete);
+                }
+            }
         }
 
-        Ok(String::from_utf8_lossy(&o.stdout).trim().to_string())
+        if let Some(entry) = &stashed {
+            match restore_error {
+                Some(e) => {
+                    self.set_status(
+                        StatusLevel::Error,
+                        format!("Could not restore your stashed changes: {:#}", e),
+                    );
+                    self.log(format!(
+                        "Your changes are kept in {}. If the pop conflicted, resolve the files and `git stash drop`; otherwise `git stash pop` once the tree allows it.",
+                        entry.name
+                    ));
+                }
+                None => self.log(format!("Restored your stashed changes ({}).", entry.name)),
+            }
+        }
     }
 
-    fn start_release_bump(&mut self, bump: &str) -> bool {
-        // Compute next version from Cargo.toml using the core release module, then ask for confirmation.
-        let bump_kind = match bump {
-            "patch" => release::BumpKind::Patch,
-            "minor" => release::BumpKind::Minor,
-            "major" => release::BumpKind::Major,
-            other => {
-                self.set_status(StatusLevel::Error, format!("Unknown bump kind: {}", other));
-                self.log(format!("Release failed: unknown bump kind {}", other));
-                return true;
-            }
+    /// Enter rolls the release back with `git reset --soft`, `h` with `--hard`, Esc keeps it.
+    fn open_rollback_modal(&mut self, incomplete: &release::IncompleteRelease) {
+        let keep = match incomplete.resume_hint() {
+            Some(hint) => format!("Esc: keep it. {}", hint),
+            None => "Esc: keep it.".to_string(),
         };
+        self.modal = ModalState {
+            kind: ModalKind::Confirm,
+            title: "Roll the release back?".to_string(),
+            message: format!(
+                "{}\n\nEnter: delete the local tag, drop the release commit (git reset --soft) and restore the files it changed. h: the same with git reset --hard, which discards every uncommitted change. {}",
+                incomplete, keep
+            ),
+            confirm_purpose: Some(ConfirmPurpose::RollBackRelease),
+            input_purpose: None,
+            input_value: String::new(),
+        };
+    }
 
-        let plan = match release::plan_bump("Cargo.toml", bump_kind) {
-            Ok(p) => p,
+    /// Undo what a failed release did, one log line per step.
+    fn roll_back_release(
+        &mut self,
+        incomplete: &release::IncompleteRelease,
+        mode: release::ResetMode,
+    ) {
+        match release::rollback_release(incomplete, mode) {
+            Ok(undone) => {
+                for line in undone {
+                    self.log(format!("Rollback: {}", line));
+                }
+                self.set_status(
+                    StatusLevel::Success,
+                    format!("Release {} rolled back.", incomplete.tag),
+                );
+            }
             Err(e) => {
-                self.set_status(StatusLevel::Error, e.to_string());
-                self.log(format!("Release failed: {e}"));
-                return true;
+                self.set_status(StatusLevel::Error, format!("Rollback failed: {:#}", e));
+                self.log(format!("Rollback failed: {:#}", e));
             }
-        };
+        }
+    }
 
-        self.pending_release_version = Some(plan.new_version.clone());
+    fn open_stash_release_modal(&mut self, version: &str) {
         self.modal = ModalState {
             kind: ModalKind::Confirm,
-            title: "Final confirmation".to_string(),
+            title: "Working tree has changes".to_string(),
             message: format!(
-                "Bump {} -> {} and push tag {}? This triggers CI release + crates publish.",
-                plan.old_version, plan.new_version, plan.tag
+                "The release commit would pick up your uncommitted changes.\n\nEnter: stash them (untracked files too), release v{}, then pop them back, even if the release fails. Esc: cancel.",
+                version
             ),
-            confirm_purpose: Some(ConfirmPurpose::ReleaseTrigger),
+            confirm_purpose: Some(ConfirmPurpose::StashAndRelease),
             input_purpose: None,
             input_value: String::new(),
         };
-        true
     }
 
     fn perform_release(&mut self, new_version: &str) -> Result<()> {
@@ -1430,18 +4275,44 @@ impl App {
         // The tag push triggers GitHub Actions to build releases and publish to crates.io.
         self.pending_release_version = Some(new_version.to_string());
 
-        let plan = release::plan_custom("Cargo.toml", new_version)?;
+        let manifest = release::manifest_path()?;
+        let plan = release::plan_custom(&manifest, new_version, self.release_scope)?;
         let commit_message = self
             .generate_release_commit_message(&plan.new_version)
             .unwrap_or_else(|_| format!("chore(release): {}", plan.tag));
 
-        release::run_tag_release(
-            "Cargo.toml",
+        let changelog = self.pending_changelog.clone();
+        let mut preflight = repo_config::preflight_config()?;
+        preflight.skip_checks(&self.preflight_skip)?;
+        if let Some(note) = preflight.skipped_note() {
+            self.log(format!("Release commit notes: {}", note));
+        }
+        let result = release::run_tag_release(
             &plan,
             &commit_message,
-            &release::PreflightConfig::default(),
-            &release::ReleaseGuardrailConfig::default(),
-        )?;
+            changelog.as_deref(),
+            &preflight,
+            &release::ReleaseGuardrailConfig::from_config(Config::load().ok().flatten().as_ref()),
+            &repo_config::tag_config()?,
+        );
+        // Roll back here, before a stash made for the release is popped; otherwise ask.
+        self.pending_rollback = None;
+        if let Some(incomplete) = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<release::IncompleteRelease>())
+        {
+            let auto = Config::load()
+                .ok()
+                .flatten()
+                .is_some_and(|c| c.release_auto_rollback);
+            if auto {
+                self.roll_back_release(incomplete, release::ResetMode::Soft);
+            } else {
+                self.pending_rollback = Some(incomplete.clone());
+            }
+        }
+        result?;
 
         // Also surface helpful URLs in the status/log (best-effort)
         if let Some(repo_https) = origin_https_repo_url().ok().flatten() {
@@ -1468,9 +4339,9 @@ impl App {
     fn generate_release_commit_message(&mut self, new_version: &str) -> Result<String> {
         // Generate from staged diff; hint keeps the commit deterministic.
         let hint = Some(format!("release: bump version to v{}", new_version));
-        let diff = git::get_diff(git::DiffSource::Staged)?;
+        let diff = prompt::diff_for(git::DiffSource::Staged)?.text;
         let generator = self.build_generator()?;
-        super::runtime::tui_block_on(generator.generate(&diff, hint))
+        super::runtime::tui_block_on(generator.generate(&diff, hint)).map(|g| g.message)
     }
 
     pub fn set_commit_message_text(&mut self, msg: &str) {
@@ -1499,39 +4370,37 @@ impl App {
                 .borders(ratatui::widgets::Borders::ALL),
         );
     }
-
-    // NOTE: helper for background tasks (cannot borrow &mut self from worker thread)
-    // Returns (Generator, provider_label, model_label)
 }
 
-fn build_generator_for_task(mock_mode: bool) -> Result<(Generator, String, String)> {
-    if mock_mode {
-        return Ok((
-            Generator::Mock(MockGenerator::new()),
-            "Mock".to_string(),
-            "-".to_string(),
-        ));
+/// A push that stopped on origin's same-named branch, for the user to settle; any other
+/// push failure is the task's error.
+fn push_stopped(e: anyhow::Error) -> Result<TaskResult> {
+    match e.downcast::<git::RemoteBranchDiverged>() {
+        Ok(diverged) => Ok(TaskResult::RemoteBranchDiverged { diverged }),
+        Err(e) => Err(e),
     }
+}
 
-    match Config::load()? {
-        Some(cfg) => {
-            let provider_label = cfg.provider.to_string();
-            let model_label = cfg.model.clone();
-            let gen = match cfg.provider {
-                Provider::OpenAI => Generator::OpenAI(OpenAIGenerator::new(cfg.api_key, cfg.model)),
-                Provider::Anthropic => {
-                    Generator::Anthropic(AnthropicGenerator::new(cfg.api_key, cfg.model))
-                }
-                Provider::Gemini => Generator::Gemini(GeminiGenerator::new(cfg.api_key, cfg.model)),
-            };
-            Ok((gen, provider_label, model_label))
-        }
-        None => anyhow::bail!("No config found. Use the Config tab or run setup."),
+/// Hooks a commit from the editor runs, for the task label; none with `skip_hooks`.
+fn commit_hooks(skip_hooks: bool) -> Vec<&'static str> {
+    let Ok(mut options) = repo_config::commit_options() else {
+        return Vec::new();
+    };
+    options.no_verify |= skip_hooks;
+    git::commit_hooks(options)
+}
+
+/// A line git or a hook printed during a commit, into the Log panel.
+fn send_hook_output(tx: &Sender<TaskEvent>, line: &str) {
+    if !line.trim().is_empty() {
+        let _ = tx.send(TaskEvent::Progress {
+            message: line.to_string(),
+        });
     }
 }
 
 fn origin_https_repo_url() -> Result<Option<String>> {
-    let o = std::process::Command::new("git")
+    let o = git::command()
         .args(["remote", "get-url", "origin"])
         .output()?;
 
@@ -1632,3 +4501,79 @@ pub fn to_textarea_input(key: &KeyEvent) -> Option<Input> {
 
 // NOTE: local semver/version parsing helpers were removed.
 // Release planning is now handled by the core `release` module.
+
+/// Context panel label for a generation source. Only staged changes are committed,
+/// so the other sources say so.
+pub fn diff_source_label(source: &git::DiffSource) -> String {
+    match source {
+        git::DiffSource::Staged => "Staged (recommended)".to_string(),
+        git::DiffSource::Unstaged => "Unstaged (not what gets committed)".to_string(),
+        git::DiffSource::Both => "Staged + unstaged (commit has staged only)".to_string(),
+        git::DiffSource::AgainstRef(reference) => {
+            format!("Since {} (commit has staged only)", reference)
+        }
+    }
+}
+
+/// One-line diff summary for the Generate tab's Context panel.
+pub fn summary_text(summary: &git::DiffSummary) -> String {
+    let binary = if summary.binary_files > 0 {
+        format!(", {} binary", summary.binary_files)
+    } else {
+        String::new()
+    };
+    format!(
+        "{} files{}, +{} -{}, ~{} bytes",
+        summary.files_changed, binary, summary.insertions, summary.deletions, summary.bytes
+    )
+}
+
+/// The largest file for the Context panel, `-` for no diff.
+pub fn largest_text(summary: &git::DiffSummary) -> String {
+    match summary.largest_files(1).first() {
+        Some(file) => match summary.share(file) {
+            Some(share) => format!("{} ({}%)", file.display_path(), share),
+            None => format!("{} (binary)", file.display_path()),
+        },
+        None => "-".to_string(),
+    }
+}
+
+/// The files with the most changed lines, for the log before generating; none for a
+/// single file.
+fn largest_files_log(summary: &git::DiffSummary) -> Option<String> {
+    if summary.files_changed < 2 {
+        return None;
+    }
+    let files: Vec<String> = summary
+        .largest_files(git::LARGEST_FILES)
+        .iter()
+        .map(|f| match summary.share(f) {
+            Some(share) => format!("{} {} ({}%)", f.display_path(), f.counts(), share),
+            None => format!("{} (binary)", f.display_path()),
+        })
+        .collect();
+    Some(format!("Largest files: {}", files.join(", ")))
+}
+
+fn release_action(choice: ReleaseChoice) -> ActionItem {
+    match choice {
+        ReleaseChoice::Patch => ActionItem::ReleasePatch,
+        ReleaseChoice::Minor => ActionItem::ReleaseMinor,
+        ReleaseChoice::Major => ActionItem::ReleaseMajor,
+        ReleaseChoice::Custom => ActionItem::ReleaseCustom,
+    }
+}
+
+fn load_latency_stats() -> Vec<usage::LatencyStats> {
+    usage::load()
+        .map(|entries| usage::latency_stats(&entries, usage::DEFAULT_WINDOW))
+        .unwrap_or_default()
+}
+
+/// The changelog section as appended to the release confirmation, or nothing.
+fn changelog_preview(section: Option<&str>) -> String {
+    section
+        .map(|s| format!("\n\n{} gets:\n\n{}", release::CHANGELOG_FILE, s.trim_end()))
+        .unwrap_or_default()
+}
@@ -36,6 +36,7 @@ pub fn dispatch_key(app: &mut App, tasks: &TaskRunner, key: KeyEvent) -> bool {
         app.active_tab,
         Tab::Stage | Tab::Diff | Tab::Push | Tab::Release | Tab::Config
     ) && app.focus != Focus::LeftPane
+        && !(app.active_tab == Tab::Stage && app.hunk_view.is_some())
         && key.modifiers == KeyModifiers::NONE
         && key.code == KeyCode::Enter
     {
@@ -66,7 +67,10 @@ pub fn dispatch_key(app: &mut App, tasks: &TaskRunner, key: KeyEvent) -> bool {
                 if let Some(action) = app.selected_action() {
                     return match action {
                         ActionItem::RunSetupWizard
+                        | ActionItem::ChangeModel
+                        | ActionItem::ChangeCommitStyle
                         | ActionItem::StagePatch
+                        | ActionItem::OpenConflict
                         | ActionItem::UnstagePatch
                         | ActionItem::ReleasePatch
                         | ActionItem::ReleaseMinor
@@ -79,6 +83,7 @@ pub fn dispatch_key(app: &mut App, tasks: &TaskRunner, key: KeyEvent) -> bool {
                                 let _handled = app.activate_selected_action(tasks);
                                 Ok(())
                             });
+                            app.request_summary_refresh();
                             true
                         }
                         _ => app.activate_selected_action(tasks),
@@ -121,6 +126,10 @@ pub fn dispatch_key(app: &mut App, tasks: &TaskRunner, key: KeyEvent) -> bool {
                 app.diff_scroll = 0;
                 return true;
             }
+            (KeyCode::Char('w'), KeyModifiers::NONE) => {
+                app.toggle_word_diff(tasks);
+                return true;
+            }
             _ => {}
         }
     }
@@ -136,8 +145,14 @@ pub fn dispatch_key(app: &mut App, tasks: &TaskRunner, key: KeyEvent) -> bool {
         // Generate is special: it supports editor typing and shortcuts even when not focused on Actions.
         Tab::Generate => app.handle_generate_key(tasks, &key),
 
-        // Diff/Stage/Push/Release/Config: all interactions should come from Actions list (LeftPane)
+        // Stage: the hunk list takes its keys while it has focus.
+        Tab::Stage => app.handle_stage_key(tasks, &key),
+
+        // Push: the Tags list takes its keys while it has focus.
+        Tab::Push => app.handle_push_key(tasks, &key),
+
+        // Diff/Release/Config: all interactions should come from Actions list (LeftPane)
         // and/or modals, so we don't consume keys here.
-        Tab::Stage | Tab::Diff | Tab::Push | Tab::Release | Tab::Config => false,
+        Tab::Diff | Tab::Release | Tab::Config => false,
     }
 }
@@ -9,47 +9,133 @@
 //! - `view`: rendering/layout (ratatui)
 //! - `runtime`: async bridging helpers (blocking/suspend helpers)
 //! - `tasks`: single-task background runner for progress feedback (non-blocking UX)
+//! - `refresh`: low-frequency repo snapshot / staged-summary refresh, outside the task runner
+//!
+//! Terminals without an alternate screen (`TERM=dumb`, some IDE consoles, or when
+//! entering it fails) get an inline viewport of `INLINE_HEIGHT` rows below the prompt
+//! instead. Without raw mode or a terminal on stdout the TUI refuses to start and points
+//! to the classic menu; the terminal is restored whenever setup fails part way.
+//!
+//! Manual check of the fallback path:
+//! - `TERM=dumb git-wiz`: the TUI draws inline below the prompt and scrollback is kept.
+//! - In that mode, stage (`git add -p`) and Edit: the command runs below the viewport
+//!   and the TUI redraws underneath its output.
+//! - Quit: the viewport is cleared, the cursor is visible and typing echoes normally.
+//! - `git-wiz | cat`: refuses with the `--menu` hint; the terminal is left untouched.
+//! - Regular terminal: full-screen as before, and quitting restores the screen.
 
 pub mod app;
 pub mod input;
+pub mod refresh;
 pub mod runtime;
 pub mod tasks;
 pub mod view;
 
-use std::io;
+use std::io::{self, IsTerminal};
 use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use crossterm::{
+    cursor::Show,
     event::{self, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, Terminal, TerminalOptions, Viewport};
 
 use app::{App, RunningTaskSnapshot};
+use refresh::StatusRefresher;
 use tasks::TaskRunner;
 
+/// Rows of the inline viewport used when the alternate screen is unavailable.
+const INLINE_HEIGHT: u16 = 20;
+
+#[cfg(feature = "cli")]
+const NO_TUI_HINT: &str = "run `git-wiz --menu` for the classic menu instead";
+#[cfg(not(feature = "cli"))]
+const NO_TUI_HINT: &str = "this build has no classic menu (rebuild with the `cli` feature)";
+
+type Tui = Terminal<CrosstermBackend<io::Stdout>>;
+
 /// Run the full-screen TUI.
 ///
 /// Notes:
 /// - Synchronous crossterm event loop.
 /// - Long-running operations should not block rendering; use `TaskRunner` for background tasks.
 /// - Interactive/suspended operations should use `runtime::with_tui_suspended`.
-pub fn run_tui() -> Result<()> {
-    enable_raw_mode().context("Failed to enable raw mode")?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen).context("Failed to enter alternate screen")?;
+/// - `mock_mode` starts the session with the mock generator (no API calls).
+pub fn run_tui(mock_mode: bool) -> Result<()> {
+    let mut terminal = enter_terminal()?;
+    let result = run_loop(&mut terminal, mock_mode);
+    restore_terminal(&mut terminal);
+    result
+}
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).context("Failed to create terminal backend")?;
-    terminal.clear().ok();
+/// Raw mode plus the alternate screen, or an inline viewport when the alternate screen is
+/// unavailable. Whatever was already set up is undone if a later step fails.
+fn enter_terminal() -> Result<Tui> {
+    if !io::stdout().is_terminal() {
+        bail!("The TUI needs a terminal on stdout; {}.", NO_TUI_HINT);
+    }
+    enable_raw_mode()
+        .map_err(|e| anyhow!(e).context(format!("This terminal has no raw mode; {}", NO_TUI_HINT)))?;
 
+    let dumb = std::env::var("TERM").is_ok_and(|t| t == "dumb");
+    let alternate = !dumb && execute!(io::stdout(), EnterAlternateScreen).is_ok();
+    runtime::set_alternate_screen(alternate);
+
+    match new_terminal(alternate) {
+        Ok(mut terminal) => {
+            terminal.clear().ok();
+            Ok(terminal)
+        }
+        Err(e) => {
+            if alternate {
+                execute!(io::stdout(), LeaveAlternateScreen).ok();
+            }
+            disable_raw_mode().ok();
+            Err(e.context(format!("The TUI could not start; {}", NO_TUI_HINT)))
+        }
+    }
+}
+
+/// A terminal on stdout: fullscreen, or `INLINE_HEIGHT` rows below the cursor.
+fn new_terminal(alternate: bool) -> Result<Tui> {
+    let viewport = if alternate {
+        Viewport::Fullscreen
+    } else {
+        let rows = crossterm::terminal::size().map_or(INLINE_HEIGHT, |(_, rows)| rows);
+        Viewport::Inline(INLINE_HEIGHT.min(rows))
+    };
+    Terminal::with_options(CrosstermBackend::new(io::stdout()), TerminalOptions { viewport })
+        .context("Failed to create terminal backend")
+}
+
+/// Undo `enter_terminal`; best-effort so the shell gets a usable terminal back.
+fn restore_terminal(terminal: &mut Tui) {
+    if !runtime::alternate_screen() {
+        // Inline: wipe the viewport so the prompt returns where the TUI started.
+        terminal.clear().ok();
+    }
+    disable_raw_mode().ok();
+    if runtime::alternate_screen() {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
+    }
+    execute!(terminal.backend_mut(), Show).ok();
+}
+
+fn run_loop(terminal: &mut Tui, mock_mode: bool) -> Result<()> {
     let tick_rate = Duration::from_millis(33);
     let mut last_tick = Instant::now();
 
     let mut app = App::new();
+    app.mock_mode = mock_mode;
+    if !mock_mode {
+        // Best-effort: a broken config is reported when it is first used.
+        let _ = app.reload_config_labels();
+    }
     let tasks = TaskRunner::new();
+    let mut status_refresher = StatusRefresher::new();
 
     loop {
         // Drain task events and update spinner before rendering.
@@ -57,6 +143,7 @@ pub fn run_tui() -> Result<()> {
         if tasks.is_busy() {
             tasks.tick_spinner();
         }
+        status_refresher.tick(&mut app, &tasks);
 
         // Copy a snapshot of the running task into App so the view can render progress.
         app.running_task = tasks.running().map(|t| RunningTaskSnapshot {
@@ -65,9 +152,14 @@ pub fn run_tui() -> Result<()> {
             spinner_index: t.spinner_index,
         });
 
-        terminal
+        // After a suspension in inline mode, re-anchor the viewport below the output.
+        if runtime::take_inline_moved() {
+            *terminal = new_terminal(false)?;
+        }
+        let frame = terminal
             .draw(|f| view::draw(f, &mut app))
             .context("Failed to draw frame")?;
+        runtime::set_inline_last_row(frame.area.bottom().saturating_sub(1));
 
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
         if event::poll(timeout).context("Failed to poll events")? {
@@ -85,10 +177,5 @@ pub fn run_tui() -> Result<()> {
         }
     }
 
-    // Restore terminal state
-    disable_raw_mode().ok();
-    execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
-    terminal.show_cursor().ok();
-
     Ok(())
 }
@@ -0,0 +1,121 @@
+//! Low-frequency background refresh of the repository snapshot and staged summary.
+//!
+//! Runs on its own thread instead of through `TaskRunner`, so it never takes the single
+//! task slot and never touches the status line or log. Each refresh is one
+//! `git::snapshot()` call; the staged diff summary and cost estimate (which need
+//! `git diff`) are only recomputed when the staged content changed or a refresh was explicitly requested.
+//! At most one refresh is in flight; requests made meanwhile are coalesced. Refreshing
+//! pauses while a modal is open or a task is running, and on tabs that don't show it.
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::app::{self, App, ModalKind, Tab};
+use super::tasks::TaskRunner;
+use crate::{generator, git, prompt};
+
+/// Refresh period while idle.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Refresh {
+    snapshot: Option<git::RepoSnapshot>,
+    /// `None` when the staged content is unchanged (or the summary failed).
+    summary: Option<String>,
+    /// Recomputed together with `summary`.
+    largest: Option<String>,
+    estimate: Option<String>,
+}
+
+pub struct StatusRefresher {
+    tx: Sender<Refresh>,
+    rx: Receiver<Refresh>,
+    in_flight: bool,
+    last_started: Option<Instant>,
+    staged_fingerprint: Option<u64>,
+}
+
+impl StatusRefresher {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            in_flight: false,
+            last_started: None,
+            staged_fingerprint: None,
+        }
+    }
+
+    /// Apply a finished refresh and start the next one when due. Call once per UI tick.
+    pub fn tick(&mut self, app: &mut App, tasks: &TaskRunner) {
+        while let Ok(refresh) = self.rx.try_recv() {
+            self.in_flight = false;
+            // Failures (e.g. not a repository) keep the previous state; nothing is logged.
+            if let Some(snapshot) = refresh.snapshot {
+                self.staged_fingerprint = Some(snapshot.staged_fingerprint());
+                app.snapshot = Some(snapshot);
+            }
+            if let Some(summary) = refresh.summary {
+                app.diff_summary = summary;
+            }
+            if let Some(largest) = refresh.largest {
+                app.largest_file = largest;
+            }
+            if let Some(estimate) = refresh.estimate {
+                app.cost_estimate = estimate;
+            }
+        }
+
+        let paused = !matches!(app.active_tab, Tab::Generate | Tab::Stage | Tab::Push)
+            || app.modal.kind != ModalKind::None
+            || tasks.is_busy();
+        if self.in_flight || paused {
+            return;
+        }
+
+        let due = self
+            .last_started
+            .is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL);
+        let forced = app.summary_refresh_requested;
+        if !forced && !due {
+            return;
+        }
+
+        app.summary_refresh_requested = false;
+        self.in_flight = true;
+        self.last_started = Some(Instant::now());
+
+        let previous = self.staged_fingerprint;
+        let mock_mode = app.mock_mode;
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let snapshot = git::snapshot().ok();
+            let staged_changed = snapshot
+                .as_ref()
+                .is_some_and(|s| forced || previous != Some(s.staged_fingerprint()));
+            let summary = if staged_changed {
+                git::diff_summary(git::DiffSource::Staged).ok()
+            } else {
+                None
+            };
+            let largest = summary.as_ref().map(app::largest_text);
+            let summary = summary.as_ref().map(app::summary_text);
+            let estimate = if staged_changed {
+                prompt::diff_for(git::DiffSource::Staged)
+                    .ok()
+                    .map(|diff| generator::estimate_cost(mock_mode, &diff.text, None).short())
+            } else {
+                None
+            };
+            let _ = tx.send(Refresh {
+                snapshot,
+                summary,
+                largest,
+                estimate,
+            });
+        });
+    }
+}
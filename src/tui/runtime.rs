@@ -1,11 +1,38 @@
 use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use crossterm::{
+    cursor::MoveTo,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
+/// Whether the TUI is on the alternate screen (false: inline viewport fallback).
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+/// Set when a suspension ran in inline mode, so the viewport is re-anchored below the
+/// output of the interactive command.
+static INLINE_MOVED: AtomicBool = AtomicBool::new(false);
+/// Last row of the inline viewport, where suspended output starts below.
+static INLINE_LAST_ROW: AtomicU16 = AtomicU16::new(0);
+
+pub fn set_alternate_screen(active: bool) {
+    ALTERNATE_SCREEN.store(active, Ordering::SeqCst);
+}
+
+pub fn alternate_screen() -> bool {
+    ALTERNATE_SCREEN.load(Ordering::SeqCst)
+}
+
+pub fn set_inline_last_row(row: u16) {
+    INLINE_LAST_ROW.store(row, Ordering::SeqCst);
+}
+
+/// True once after an inline-mode suspension (see `with_tui_suspended`).
+pub fn take_inline_moved() -> bool {
+    INLINE_MOVED.swap(false, Ordering::SeqCst)
+}
+
 /// Minimal blocking adapter for the current synchronous TUI loop.
 ///
 /// This is a pragmatic bridge while the UI is still driven by a synchronous
@@ -20,15 +47,7 @@ pub fn tui_block_on<F, T>(fut: F) -> Result<T>
 where
     F: std::future::Future<Output = Result<T>>,
 {
-    // If we're already inside a tokio runtime (common in tests / other runtimes),
-    // use it. Otherwise create a small runtime for this one-off call.
-    match tokio::runtime::Handle::try_current() {
-        Ok(handle) => handle.block_on(fut),
-        Err(_) => {
-            let rt = tokio::runtime::Runtime::new().context("Failed to create tokio runtime")?;
-            rt.block_on(fut)
-        }
-    }
+    crate::runtime::block_on(fut)
 }
 
 /// Temporarily suspends the full-screen TUI so an interactive command can run safely.
@@ -43,6 +62,9 @@ where
 /// 2) Runs the provided closure
 /// 3) Re-enters alternate screen + re-enables raw mode (best-effort even if the closure errors)
 ///
+/// In the inline fallback (no alternate screen) only raw mode is toggled; the closure's
+/// output starts below the viewport and the event loop redraws the viewport below it.
+///
 /// Important:
 /// - The closure should do any interactive terminal I/O it needs.
 /// - After returning, the caller should redraw the UI (the event loop will do this naturally).
@@ -54,15 +76,26 @@ where
     // try to restore the TUI afterwards.
     let mut stdout = io::stdout();
 
+    let alternate = alternate_screen();
+
     // Leave TUI mode
     let _ = disable_raw_mode();
-    let _ = execute!(stdout, LeaveAlternateScreen);
+    if alternate {
+        let _ = execute!(stdout, LeaveAlternateScreen);
+    } else {
+        let _ = execute!(stdout, MoveTo(0, INLINE_LAST_ROW.load(Ordering::SeqCst)));
+        println!();
+    }
 
     // Run interactive work
     let result = f();
 
     // Restore TUI mode
-    let _ = execute!(io::stdout(), EnterAlternateScreen);
+    if alternate {
+        let _ = execute!(io::stdout(), EnterAlternateScreen);
+    } else {
+        INLINE_MOVED.store(true, Ordering::SeqCst);
+    }
     let _ = enable_raw_mode();
 
     result
@@ -9,7 +9,16 @@ use std::{
 
 use anyhow::Result;
 
-use super::app::{App, DiffViewSource, StatusLevel};
+use super::app::{App, ConfirmPurpose, DiffViewSource, StatusLevel};
+use crate::diff_parse::FileDiff;
+use crate::generator::{ApiError, TokenUsage};
+use crate::git::{self, CommitFailed, DiffSource, NotFastForward};
+use crate::http;
+use crate::notify;
+use crate::offline::{self, Unreachable};
+use crate::prompt::PromptSizes;
+use crate::scope::ScopeFix;
+use crate::usage;
 
 /// A single-task-at-a-time background runner for the TUI.
 ///
@@ -55,10 +64,18 @@ pub enum TaskKind {
     GenerateCommitFromStaged,
     CommitFromEditor,
     StageAll,
+    ApplyHunks,
     PushBranch,
+    Pull,
     PushTag,
     PushAllTags,
+    Tags,
     LoadDiff,
+    AddRemote,
+    AmendCommit,
+    GeneratePrDescription,
+    DraftReleaseNotes,
+    PublishReleaseNotes,
 }
 
 #[derive(Debug)]
@@ -71,31 +88,124 @@ pub enum TaskEvent {
     Progress {
         message: String,
     },
+    /// The reply so far, while a generation streams in; shown in the commit editor.
+    Partial {
+        text: String,
+    },
     Completed {
-        result: TaskResult,
+        result: Box<TaskResult>,
     },
 }
 
 /// High-level results that the UI can apply deterministically.
+/// What to start once a task succeeded (see `TaskResult::Chained`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowUp {
+    /// Push the current branch (`auto_push: always`).
+    PushBranch,
+    /// Ask before pushing the current branch (`auto_push: ask`).
+    AskPushBranch,
+}
+
 #[derive(Debug)]
 pub enum TaskResult {
     OkMessage {
         status: String,
         log: Option<String>,
     },
+    /// The branch is behind its upstream: nothing was pushed, the user picks what to do.
+    PushBehind {
+        tracking: git::AheadBehind,
+    },
+    /// The branch has no upstream and origin's branch of the same name has other commits:
+    /// nothing was pushed, the user picks what to do.
+    RemoteBranchDiverged {
+        diverged: git::RemoteBranchDiverged,
+    },
     GeneratedCommitMessage {
         message: String,
+        /// Why post_generate_command rejected `message`, if it did.
+        hook_rejection: Option<String>,
+        /// The diff the message describes (drives the Context panel's source label).
+        source: DiffSource,
+        /// Files were left out of the diff (built-in excludes, `prompt_exclude`).
+        filtered: bool,
+        /// The diff was cut to fit the prompt budget.
+        trimmed: bool,
+        latency_ms: u64,
+        /// What the prompt budget kept of each section (logged; cuts are flagged). Boxed
+        /// like `scope_fix`, to keep the variant from outgrowing the others.
+        prompt_sizes: Box<PromptSizes>,
+        /// Reported by the provider; added to the session total.
+        tokens: Option<TokenUsage>,
+        /// Output token limit the reply ran into, if it was cut off.
+        truncated_at: Option<u32>,
         summary: String,
         provider: String,
         model: String,
+        issue_candidates: Vec<u64>,
+        prompt_link_issues: bool,
+        /// Subject over the length limit, Conventional Commits violations.
+        warnings: Vec<String>,
+        /// The changed paths suggest another scope than the model used (boxed: rare, and
+        /// the variant is the largest already).
+        scope_fix: Option<Box<ScopeFix>>,
     },
     LoadedDiff {
         source: DiffViewSource,
         text: String,
         status: String,
     },
+    ReleaseRemoteAdded {
+        remote: String,
+    },
+    /// PR description for the current branch against `base`; `warning` notes a trimmed diff.
+    PrDescription {
+        base: String,
+        body: String,
+        warning: Option<String>,
+        tokens: Option<TokenUsage>,
+    },
+    /// Release notes for `tag`; `warning` explains a fallback to the plain draft.
+    ReleaseNotes {
+        tag: String,
+        notes: String,
+        warning: Option<String>,
+    },
+    /// Hunks were staged or unstaged: the fresh listing, and the ones that didn't apply.
+    Hunks {
+        unstage: bool,
+        files: Vec<FileDiff>,
+        failures: Vec<git::HunkFailure>,
+        status: String,
+    },
+    /// The reloaded tags list, after a deletion or a check against origin.
+    Tags {
+        tags: Vec<git::TagInfo>,
+        status: String,
+    },
+    /// HEAD was amended; leaves amend mode.
+    Amended {
+        status: String,
+    },
+    /// `result` is applied as usual, then `then` starts (e.g. commit, then push).
+    Chained {
+        result: Box<TaskResult>,
+        then: FollowUp,
+    },
+    /// `git commit` failed; its output goes to the log, and a hook failure offers
+    /// `--no-verify`.
+    CommitFailed(CommitFailed),
+    /// `git pull --ff-only` refused a diverged branch; offers to list the commits.
+    NotFastForward(NotFastForward),
+    /// The provider's host didn't answer; offers the mock generator.
+    Unreachable {
+        message: String,
+    },
     Error {
         message: String,
+        /// Raw provider response behind a classified API error; logged, not shown in the status.
+        detail: Option<String>,
     },
 }
 
@@ -170,14 +280,41 @@ impl TaskRunner {
             TaskEvent::Progress { message } => {
                 // Lightweight status updates. Keep logs too.
                 app.set_status(StatusLevel::Info, message.clone());
+                // A rate-limit countdown ticks every second; keep one log line for it.
+                if message.starts_with(http::RATE_LIMITED)
+                    && app
+                        .logs
+                        .last()
+                        .is_some_and(|l| l.starts_with(http::RATE_LIMITED))
+                {
+                    app.logs.pop();
+                }
                 app.log(message);
             }
+            TaskEvent::Partial { text } => {
+                app.streaming = true;
+                app.set_commit_message_text(&text);
+            }
             TaskEvent::Completed { result } => {
+                let streamed = std::mem::take(&mut app.streaming);
                 // Clear running task first.
-                if let Ok(mut s) = self.state.lock() {
-                    s.current = None;
+                let started_at = self
+                    .state
+                    .lock()
+                    .ok()
+                    .and_then(|mut s| s.current.take())
+                    .map(|t| t.started_at);
+                // We can't tell whether the terminal has focus, so long tasks notify.
+                if let Some(started_at) = started_at {
+                    notify::task_finished(started_at.elapsed(), completion_notice(&result));
                 }
+                // Tasks may stage, unstage or commit: re-read the staged summary.
+                app.request_summary_refresh();
 
+                let (result, then) = match *result {
+                    TaskResult::Chained { result, then } => (*result, Some(then)),
+                    result => (result, None),
+                };
                 match result {
                     TaskResult::OkMessage { status, log } => {
                         app.set_status(StatusLevel::Success, status.clone());
@@ -185,19 +322,111 @@ impl TaskRunner {
                             app.log(l);
                         }
                     }
+                    TaskResult::PushBehind { tracking } => {
+                        app.open_pull_then_push_modal(&tracking);
+                    }
+                    TaskResult::RemoteBranchDiverged { diverged } => {
+                        app.open_remote_branch_modal(diverged);
+                    }
+                    TaskResult::Hunks {
+                        unstage,
+                        files,
+                        failures,
+                        status,
+                    } => {
+                        let level = if failures.is_empty() {
+                            StatusLevel::Success
+                        } else {
+                            StatusLevel::Error
+                        };
+                        app.log(status.clone());
+                        app.set_status(level, status);
+                        app.set_hunk_view(unstage, files, failures);
+                    }
+                    TaskResult::Tags { tags, status } => {
+                        app.set_tags(tags);
+                        app.set_status(StatusLevel::Success, status.clone());
+                        app.log(status);
+                    }
                     TaskResult::GeneratedCommitMessage {
                         message,
+                        hook_rejection,
+                        source,
+                        filtered,
+                        trimmed,
+                        latency_ms,
+                        prompt_sizes,
+                        tokens,
+                        truncated_at,
                         summary,
                         provider,
                         model,
+                        issue_candidates,
+                        prompt_link_issues,
+                        warnings,
+                        scope_fix,
                     } => {
-                        app.diff_source_label = "Staged (recommended)".to_string();
+                        app.set_diff_source(source, filtered, trimmed);
                         app.diff_summary = summary;
                         app.provider_label = provider;
                         app.model_label = model;
+                        app.issue_candidates = issue_candidates;
                         app.set_commit_message_text(&message);
-                        app.set_status(StatusLevel::Success, "Generated.");
                         app.log("Generated commit message.");
+                        if let Some(tokens) = tokens {
+                            app.record_tokens(tokens);
+                        }
+                        if trimmed {
+                            app.set_status(
+                                StatusLevel::Info,
+                                format!(
+                                    "Generated in {}, but the diff was trimmed to fit the prompt budget; the message may be incomplete.",
+                                    usage::format_ms(latency_ms)
+                                ),
+                            );
+                            app.log(format!("Prompt cut to fit the budget: {}", prompt_sizes));
+                            if !prompt_sizes.omitted_files.is_empty() {
+                                app.log(format!(
+                                    "Headers only: {}",
+                                    prompt_sizes.omitted_files.join(", ")
+                                ));
+                            }
+                        } else {
+                            app.set_status(
+                                StatusLevel::Success,
+                                format!("Generated in {}.", usage::format_ms(latency_ms)),
+                            );
+                            app.log(format!("Prompt: {}", prompt_sizes));
+                        }
+                        if let Some(first) = warnings.first() {
+                            if !trimmed {
+                                app.set_status(StatusLevel::Info, first.clone());
+                            }
+                        }
+                        for warning in warnings {
+                            app.log(warning);
+                        }
+                        if offline::is_active() {
+                            app.set_status(StatusLevel::Info, offline::REMINDER);
+                            app.log(offline::REMINDER);
+                        }
+                        app.refresh_latency_stats();
+                        app.hook_rejected = false;
+
+                        if let Some(limit) = truncated_at {
+                            // A hook rejection still blocks the commit (re-checked there).
+                            app.hook_rejected = hook_rejection.is_some();
+                            app.open_output_limit_modal(limit);
+                        } else if let Some(reason) = hook_rejection {
+                            app.open_hook_rejected_modal(
+                                ConfirmPurpose::HookRejectedGenerated,
+                                &reason,
+                            );
+                        } else if let Some(fix) = scope_fix {
+                            app.open_scope_fix_modal(*fix);
+                        } else if prompt_link_issues && !app.issue_candidates.is_empty() {
+                            app.open_link_issues_modal();
+                        }
                     }
                     TaskResult::LoadedDiff {
                         source,
@@ -206,14 +435,104 @@ impl TaskRunner {
                     } => {
                         app.diff_view_source = source;
                         app.diff_scroll = 0;
+                        match git::encoding_warning(&text) {
+                            Some(warning) => {
+                                app.set_status(StatusLevel::Info, warning.clone());
+                                app.log(warning);
+                            }
+                            None => {
+                                app.set_status(StatusLevel::Success, status);
+                                app.log("Loaded diff.");
+                            }
+                        }
                         app.diff_text = text;
-                        app.set_status(StatusLevel::Success, status);
-                        app.log("Loaded diff.");
                     }
-                    TaskResult::Error { message } => {
+                    TaskResult::Amended { status } => {
+                        app.exit_amend_mode();
+                        app.set_status(StatusLevel::Success, status.clone());
+                        app.log(status);
+                    }
+                    TaskResult::ReleaseRemoteAdded { remote } => {
+                        app.set_status(
+                            StatusLevel::Success,
+                            format!("Added remote '{}' (reachable).", remote),
+                        );
+                        app.log(format!("Added remote '{}'.", remote));
+                        app.resume_release_after_remote(&remote);
+                    }
+                    TaskResult::PrDescription {
+                        base,
+                        body,
+                        warning,
+                        tokens,
+                    } => {
+                        match warning {
+                            Some(warning) => {
+                                app.set_status(StatusLevel::Info, warning.clone());
+                                app.log(warning);
+                            }
+                            None => app.set_status(
+                                StatusLevel::Success,
+                                format!("PR description against {} ready.", base),
+                            ),
+                        }
+                        app.log(format!("Generated a PR description against {}.", base));
+                        if let Some(tokens) = tokens {
+                            app.record_tokens(tokens);
+                        }
+                        app.pr_base = Some(base);
+                        app.pr_description = Some(body);
+                        app.action_index = 0;
+                    }
+                    TaskResult::ReleaseNotes {
+                        tag,
+                        notes,
+                        warning,
+                    } => {
+                        match warning {
+                            Some(warning) => {
+                                app.set_status(StatusLevel::Info, warning.clone());
+                                app.log(warning);
+                            }
+                            None => app.set_status(
+                                StatusLevel::Success,
+                                format!("Release notes for {} ready.", tag),
+                            ),
+                        }
+                        app.log(format!("Drafted release notes for {}.", tag));
+                        app.release_notes_tag = Some(tag);
+                        app.release_notes = Some(notes);
+                        app.action_index = 0;
+                    }
+                    TaskResult::CommitFailed(failed) => {
+                        app.report_commit_failure(&failed);
+                    }
+                    TaskResult::NotFastForward(diverged) => {
+                        app.open_divergence_modal(&diverged);
+                    }
+                    TaskResult::Unreachable { message } => {
+                        app.open_unreachable_modal(&message);
+                    }
+                    TaskResult::Error { message, detail } => {
+                        let message = if streamed {
+                            format!(
+                                "{} The editor keeps the part of the message that arrived.",
+                                message
+                            )
+                        } else {
+                            message
+                        };
                         app.set_status(StatusLevel::Error, message.clone());
                         app.log(format!("Error: {}", message));
+                        if let Some(detail) = detail {
+                            app.log(format!("Response: {}", detail));
+                        }
                     }
+                    // Unwrapped above; tasks don't nest chains.
+                    TaskResult::Chained { .. } => {}
+                }
+                if let Some(then) = then {
+                    app.follow_up(self, then);
                 }
             }
         }
@@ -253,16 +572,57 @@ impl TaskRunner {
         let tx = self.tx.clone();
         thread::spawn(move || {
             // Worker: run task, emit completion.
-            let result = f(tx.clone()).unwrap_or_else(|e| TaskResult::Error {
-                message: e.to_string(),
+            let result = f(tx.clone()).unwrap_or_else(error_result);
+            let _ = tx.send(TaskEvent::Completed {
+                result: Box::new(result),
             });
-            let _ = tx.send(TaskEvent::Completed { result });
         });
 
         true
     }
 }
 
+/// A failed task: an unreachable provider offers the mock generator, classified API errors
+/// show their guidance, with the raw response kept for the log, a failed commit logs
+/// git's output and a diverged pull offers the divergence.
+fn error_result(e: anyhow::Error) -> TaskResult {
+    if let Some(failed) = e.downcast_ref::<CommitFailed>() {
+        return TaskResult::CommitFailed(failed.clone());
+    }
+    if let Some(diverged) = e.downcast_ref::<NotFastForward>() {
+        return TaskResult::NotFastForward(diverged.clone());
+    }
+    if let Some(unreachable) = e.downcast_ref::<Unreachable>() {
+        return TaskResult::Unreachable {
+            message: unreachable.to_string(),
+        };
+    }
+    match e.downcast_ref::<ApiError>() {
+        Some(api) => TaskResult::Error {
+            message: api.to_string(),
+            detail: Some(api.response.to_string()),
+        },
+        None => TaskResult::Error {
+            message: format!("{:#}", e),
+            detail: None,
+        },
+    }
+}
+
+fn completion_notice(result: &TaskResult) -> &'static str {
+    match result {
+        TaskResult::GeneratedCommitMessage { .. } => "git-wiz: commit message ready",
+        TaskResult::PrDescription { .. } => "git-wiz: PR description ready",
+        TaskResult::ReleaseNotes { .. } => "git-wiz: release notes ready",
+        TaskResult::Chained { result, .. } => completion_notice(result),
+        TaskResult::Error { .. }
+        | TaskResult::CommitFailed(_)
+        | TaskResult::NotFastForward(_)
+        | TaskResult::Unreachable { .. } => "git-wiz: task failed",
+        _ => "git-wiz: task finished",
+    }
+}
+
 /// A simple unicode spinner sequence.
 ///
 /// You can render `frames[spinner_index % frames.len()]`.
@@ -279,3 +639,105 @@ pub fn format_elapsed(d: Duration) -> String {
         format!("{}m{}s", secs / 60, secs % 60)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::{Section, SectionSize};
+
+    fn generated(source: DiffSource, filtered: bool, trimmed: bool) -> TaskEvent {
+        let mut prompt_sizes = PromptSizes::default();
+        if trimmed {
+            prompt_sizes.sections.push(SectionSize {
+                section: Section::Diff,
+                original: 2000,
+                kept: 1000,
+            });
+        }
+        TaskEvent::Completed {
+            result: Box::new(TaskResult::GeneratedCommitMessage {
+                message: "feat: add retry".to_string(),
+                hook_rejection: None,
+                source,
+                filtered,
+                trimmed,
+                latency_ms: 10,
+                prompt_sizes: Box::new(prompt_sizes),
+                tokens: None,
+                truncated_at: None,
+                summary: "1 file".to_string(),
+                provider: "Mock".to_string(),
+                model: "mock".to_string(),
+                issue_candidates: Vec::new(),
+                prompt_link_issues: false,
+                warnings: Vec::new(),
+                scope_fix: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn generated_message_labels_its_diff_source() {
+        let cases = [
+            (DiffSource::Staged, false, false, "Staged (recommended)"),
+            (
+                DiffSource::Unstaged,
+                false,
+                false,
+                "Unstaged (not what gets committed)",
+            ),
+            (
+                DiffSource::Both,
+                true,
+                false,
+                "Staged + unstaged (commit has staged only), filtered",
+            ),
+            (
+                DiffSource::Staged,
+                true,
+                true,
+                "Staged (recommended), filtered, trimmed to fit",
+            ),
+            (
+                DiffSource::AgainstRef("main".to_string()),
+                false,
+                false,
+                "Since main (commit has staged only)",
+            ),
+        ];
+        let tasks = TaskRunner::new();
+        for (source, filtered, trimmed, label) in cases {
+            let mut app = App::new();
+            tasks.apply_event(&mut app, generated(source.clone(), filtered, trimmed));
+            assert_eq!(app.diff_source, source);
+            assert_eq!(app.diff_source_label, label);
+            assert_eq!(app.diff_trimmed, trimmed);
+            assert_eq!(app.diff_summary, "1 file");
+        }
+    }
+
+    #[test]
+    fn failed_task_logs_the_whole_error_chain() {
+        use anyhow::Context;
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "config.json is missing");
+        let err = Err::<(), _>(io)
+            .context("Failed to read the config")
+            .context("Setup failed")
+            .unwrap_err();
+        let mut app = App::new();
+        TaskRunner::new().apply_event(
+            &mut app,
+            TaskEvent::Completed {
+                result: Box::new(error_result(err)),
+            },
+        );
+
+        let expected = "Setup failed: Failed to read the config: config.json is missing";
+        assert_eq!(app.status.as_ref().unwrap().message, expected);
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some(format!("Error: {}", expected).as_str())
+        );
+    }
+}
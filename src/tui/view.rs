@@ -7,8 +7,12 @@ use ratatui::{
 };
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use super::app::{App, Focus, ModalKind, StatusLevel, Tab};
+use super::app::{App, Focus, HunkView, ModalKind, StatusLevel, Tab};
 use super::tasks::{format_elapsed, spinner_frames};
+use crate::diff_parse::FileChange;
+use crate::git;
+use crate::read_only;
+use crate::usage;
 
 pub fn draw(f: &mut Frame<'_>, app: &mut App) {
     let area = f.size();
@@ -58,10 +62,24 @@ fn draw_header(f: &mut Frame<'_>, app: &App, area: Rect) {
         Style::default().fg(Color::DarkGray)
     };
 
+    let mut title = vec![Span::raw(match &app.snapshot {
+        Some(snapshot) => format!(" Git Wiz · {} ", snapshot),
+        None => " Git Wiz ".to_string(),
+    })];
+    if read_only::is_active() {
+        title.push(Span::styled(
+            " READ-ONLY ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
     let tabs = Tabs::new(titles)
         .block(
             Block::default()
-                .title(" Git Wiz ")
+                .title(Line::from(title))
                 .borders(Borders::ALL)
                 .border_style(border),
         )
@@ -102,7 +120,7 @@ fn draw_generate_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     let left = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(8),
+            Constraint::Length(12),
             Constraint::Length(7),
             Constraint::Min(1),
         ])
@@ -123,10 +141,32 @@ fn draw_generate_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
             Span::styled("Model:       ", Style::default().fg(Color::DarkGray)),
             Span::styled(&app.model_label, Style::default().fg(Color::White)),
         ]),
-        Line::from(vec![
-            Span::styled("Diff Source: ", Style::default().fg(Color::DarkGray)),
-            Span::styled(&app.diff_source_label, Style::default().fg(Color::White)),
-        ]),
+        match &app.amending {
+            Some(head) => Line::from(vec![
+                Span::styled("Amending:    ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{} (HEAD)", head.short),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            None => Line::from(vec![
+                Span::styled("Diff Source: ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    &app.diff_source_label,
+                    // Non-staged sources describe content the commit will not include, a
+                    // trimmed diff leaves some of it out.
+                    Style::default().fg(
+                        if app.diff_source == git::DiffSource::Staged && !app.diff_trimmed {
+                            Color::White
+                        } else {
+                            Color::Yellow
+                        },
+                    ),
+                ),
+            ]),
+        },
         Line::from(vec![
             Span::styled("Summary:     ", Style::default().fg(Color::DarkGray)),
             Span::styled(
@@ -134,7 +174,66 @@ fn draw_generate_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
                 Style::default().fg(Color::White),
             ),
         ]),
-        Line::from(""),
+        Line::from(vec![
+            Span::styled("Largest:     ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                truncate_to_width(&app.largest_file, 28),
+                Style::default().fg(Color::White),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Hint:        ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                truncate_to_width(app.generate_hint.as_deref().unwrap_or("-"), 28),
+                Style::default().fg(Color::White),
+            ),
+        ]),
+        match &app.amending {
+            Some(head) => Line::from(vec![
+                Span::styled("HEAD stat:   ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!(
+                        "{} files, +{} -{}",
+                        head.files_changed, head.insertions, head.deletions
+                    ),
+                    Style::default().fg(Color::White),
+                ),
+            ]),
+            None if app.resolving_conflicts => Line::from(vec![
+                Span::styled("Conflicts:   ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!(
+                        "{} left",
+                        app.snapshot.as_ref().map_or(0, |s| s.unmerged().count())
+                    ),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            None => Line::from(vec![
+                Span::styled("Estimate:    ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    truncate_to_width(&app.cost_estimate, 28),
+                    Style::default().fg(Color::White),
+                ),
+            ]),
+        },
+        Line::from(vec![
+            Span::styled("Last call:   ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                app.last_tokens
+                    .map_or_else(|| "-".to_string(), |t| t.to_string()),
+                Style::default().fg(Color::White),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Session:     ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                app.session_tokens.to_string(),
+                Style::default().fg(Color::White),
+            ),
+        ]),
         Line::from(Span::styled(
             "Tip: ←/→ switches tabs (Alt+←/→ always). Tab cycles focus.",
             Style::default().fg(Color::DarkGray),
@@ -154,6 +253,25 @@ fn draw_generate_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     // Log panel
     render_log_panel(f, app, left[2]);
 
+    // PR description (replaces the editor until it is dismissed)
+    if let Some(body) = &app.pr_description {
+        let title = format!(
+            " PR description (vs {}) ",
+            app.pr_base.as_deref().unwrap_or_default()
+        );
+        let body_block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray));
+        f.render_widget(
+            Paragraph::new(body.as_str())
+                .block(body_block)
+                .wrap(Wrap { trim: false }),
+            cols[1],
+        );
+        return;
+    }
+
     // Editor
     let editor_border = if app.focus == Focus::CommitEditor {
         Style::default().fg(Color::White)
@@ -179,7 +297,11 @@ fn draw_stage_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
 
     let left = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Length(7), Constraint::Min(1)])
+        .constraints([
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Min(1),
+        ])
         .split(cols[0]);
 
     let info_block = Block::default()
@@ -194,7 +316,7 @@ fn draw_stage_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
         )),
         Line::from(""),
         Line::from(Span::styled(
-            "Patch actions open interactive git prompts.",
+            "Hunk actions: pick hunks on the right.",
             Style::default().fg(Color::DarkGray),
         )),
         Line::from(Span::styled(
@@ -213,34 +335,227 @@ fn draw_stage_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     render_actions_list(f, app, left[1]);
     render_log_panel(f, app, left[2]);
 
+    if let Some(hunks) = &app.hunk_view {
+        render_hunk_panel(f, hunks, app.focus == Focus::RightPane, cols[1]);
+        return;
+    }
+
     let details_block = Block::default()
-        .title(" Details ")
+        .title(" Files ")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::DarkGray));
 
-    let details = Paragraph::new(Text::from(vec![
-        Line::from(Span::styled(
-            "Stage patch: git add -p (interactive)",
-            Style::default().fg(Color::DarkGray),
-        )),
-        Line::from(Span::styled(
-            "Stage all:   git add -A",
-            Style::default().fg(Color::DarkGray),
-        )),
+    let details = Paragraph::new(Text::from(stage_file_lines(app.snapshot.as_ref())))
+        .block(details_block)
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(details, cols[1]);
+}
+
+/// Body lines shown under the selected hunk before the rest are summed up.
+const HUNK_PREVIEW_LINES: usize = 12;
+
+/// The Stage tab's hunk list: files with their hunks, the picked ones checked, and the
+/// selected one's body below it.
+fn render_hunk_panel(f: &mut Frame<'_>, view: &HunkView, focused: bool, area: Rect) {
+    let verb = if view.unstage { "unstage" } else { "stage" };
+    let border_style = if focused {
+        Style::default().fg(Color::White)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let block = Block::default()
+        .title(if view.unstage {
+            " Unstage hunks "
+        } else {
+            " Stage hunks "
+        })
+        .borders(Borders::ALL)
+        .border_style(border_style);
+
+    let hint = if focused {
+        format!(
+            "↑/↓ move · Space pick · a all/none · Enter {} picked · c close",
+            verb
+        )
+    } else {
+        "Tab here to pick hunks.".to_string()
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
         Line::from(""),
-        Line::from(Span::styled(
-            "Unstage patch: git restore --staged -p (fallback: git reset -p)",
+    ];
+    if view.rows.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("Nothing left to {}.", verb),
             Style::default().fg(Color::DarkGray),
-        )),
-        Line::from(Span::styled(
-            "Unstage all:   git restore --staged . (fallback: git reset)",
+        )));
+    }
+
+    let mut selected_line = 0;
+    let mut last_file = None;
+    for (row, &(f_index, hunk)) in view.rows.iter().enumerate() {
+        let file = &view.files[f_index];
+        if last_file != Some(f_index) {
+            last_file = Some(f_index);
+            let note = match (file.change, &file.mode_change) {
+                (FileChange::Added, _) => " (new file)".to_string(),
+                (FileChange::Deleted, _) => " (deleted)".to_string(),
+                (FileChange::Modified, Some((old, new))) => format!(" (mode {} → {})", old, new),
+                (FileChange::Modified, None) => String::new(),
+            };
+            let binary = if file.binary { " (binary)" } else { "" };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    file.path.clone(),
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("{}{}", note, binary),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+
+        let check = if view.picked.contains(&row) {
+            "[x] "
+        } else {
+            "[ ] "
+        };
+        let mut style = Style::default().fg(Color::White);
+        if focused && row == view.index {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        if row == view.index {
+            selected_line = lines.len();
+        }
+        let mut spans = vec![Span::raw("  ")];
+        match hunk.map(|h| &file.hunks[h]) {
+            Some(hunk) => {
+                let (added, removed) = hunk.counts();
+                spans.push(Span::styled(format!("{}{}", check, hunk.header()), style));
+                spans.push(Span::styled(
+                    format!("  +{} -{}", added, removed),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            None => spans.push(Span::styled(format!("{}whole file", check), style)),
+        }
+        lines.push(Line::from(spans));
+        if let Some(message) = view.failure(row) {
+            lines.push(Line::from(Span::styled(
+                format!("      ✗ {}", message),
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        if row == view.index {
+            if let Some(hunk) = hunk.map(|h| &file.hunks[h]) {
+                for body in hunk.lines.iter().take(HUNK_PREVIEW_LINES) {
+                    let color = match body.chars().next() {
+                        Some('+') => Color::Green,
+                        Some('-') => Color::Red,
+                        _ => Color::DarkGray,
+                    };
+                    lines.push(Line::from(Span::styled(
+                        format!("      {}", body.trim_end_matches('\r')),
+                        Style::default().fg(color),
+                    )));
+                }
+                if hunk.lines.len() > HUNK_PREVIEW_LINES {
+                    lines.push(Line::from(Span::styled(
+                        format!(
+                            "      … {} more lines",
+                            hunk.lines.len() - HUNK_PREVIEW_LINES
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+            }
+        }
+    }
+
+    // Keep the selected hunk in the top third once the list is taller than the pane.
+    let rows = (area.height as usize).saturating_sub(2);
+    let scroll = if lines.len() > rows {
+        selected_line
+            .saturating_sub(rows / 3)
+            .min(lines.len() - rows)
+    } else {
+        0
+    };
+    f.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .scroll((scroll as u16, 0)),
+        area,
+    );
+}
+
+/// The Stage tab's file list, grouped like `git status`.
+fn stage_file_lines(snapshot: Option<&git::RepoSnapshot>) -> Vec<Line<'static>> {
+    let Some(snapshot) = snapshot else {
+        return vec![Line::from(Span::styled(
+            "Reading repository status…",
             Style::default().fg(Color::DarkGray),
-        )),
-    ]))
-    .block(details_block)
-    .wrap(Wrap { trim: true });
+        ))];
+    };
+    if snapshot.is_clean() {
+        return vec![Line::from(Span::styled(
+            "Nothing to stage: working tree clean.",
+            Style::default().fg(Color::DarkGray),
+        ))];
+    }
 
-    f.render_widget(details, cols[1]);
+    let groups = [
+        (
+            "Conflicts",
+            Color::Red,
+            snapshot.unmerged().map(|e| ('U', e)).collect::<Vec<_>>(),
+        ),
+        (
+            "Staged",
+            Color::Green,
+            snapshot.staged().map(|e| (e.index, e)).collect(),
+        ),
+        (
+            "Unstaged",
+            Color::Yellow,
+            snapshot.unstaged().map(|e| (e.worktree, e)).collect(),
+        ),
+        (
+            "Untracked",
+            Color::DarkGray,
+            snapshot.untracked().map(|e| ('?', e)).collect(),
+        ),
+    ];
+
+    let mut lines = Vec::new();
+    for (title, color, entries) in groups {
+        if entries.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{} ({})", title, entries.len()),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )));
+        for (status, entry) in entries {
+            let path = match &entry.orig_path {
+                Some(orig) => format!("{} -> {}", orig, entry.path),
+                None => entry.path.clone(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {} ", status), Style::default().fg(color)),
+                Span::styled(path, Style::default().fg(Color::White)),
+            ]));
+        }
+    }
+    lines
 }
 
 fn draw_diff_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
@@ -264,7 +579,7 @@ fn draw_diff_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
         Line::from(vec![
             Span::styled("Source: ", Style::default().fg(Color::DarkGray)),
             Span::styled(
-                truncate_to_width(app.diff_view_source.label(), 28),
+                truncate_to_width(&app.diff_view_source.label(), 28),
                 Style::default().fg(Color::White),
             ),
         ]),
@@ -275,13 +590,20 @@ fn draw_diff_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
                 Style::default().fg(Color::White),
             ),
         ]),
-        Line::from(""),
+        Line::from(vec![
+            Span::styled("Mode:   ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                if app.diff_words { "words" } else { "lines" },
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(" (w toggles)", Style::default().fg(Color::DarkGray)),
+        ]),
         Line::from(Span::styled(
             "Tip: Tab to focus Actions, then ↑/↓ and Enter.",
             Style::default().fg(Color::DarkGray),
         )),
         Line::from(Span::styled(
-            "When not in Actions: ↑/↓ scroll, PgUp/PgDn faster, Home top.",
+            "When not in Actions: ↑/↓ scroll, PgUp/PgDn faster, Home top, w words.",
             Style::default().fg(Color::DarkGray),
         )),
     ]);
@@ -303,9 +625,15 @@ fn draw_diff_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::DarkGray));
 
-    // Basic scrolling by lines.
+    // Scrolling by screen rows: long lines (word diffs especially) are cut into rows of
+    // the viewer's width, so every row can be scrolled to.
     // Keep allocations proportional to the viewport rather than the whole diff.
-    let total = app.diff_text.lines().count();
+    let width = cols[1].width.saturating_sub(2) as usize;
+    let total: usize = app
+        .diff_text
+        .lines()
+        .map(|l| screen_rows(l, width).count())
+        .sum();
 
     let viewport_h = cols[1].height.saturating_sub(2) as usize; // account for borders
     let max_scroll = total.saturating_sub(viewport_h);
@@ -320,19 +648,64 @@ fn draw_diff_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     } else {
         app.diff_text
             .lines()
+            .flat_map(|l| screen_rows(l, width))
             .skip(scroll)
             .take(viewport_h)
             .map(|l| Line::from(Span::raw(l)))
             .collect()
     };
 
-    let p = Paragraph::new(visible)
-        .block(viewer_block)
-        .wrap(Wrap { trim: false });
+    let p = Paragraph::new(visible).block(viewer_block);
 
     f.render_widget(p, cols[1]);
 }
 
+/// `line` cut into rows at most `width` columns wide; an empty line is one empty row.
+fn screen_rows(line: &str, width: usize) -> impl Iterator<Item = &str> {
+    let width = width.max(1);
+    let mut rest = Some(line);
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let mut used = 0;
+        let mut end = text.len();
+        for (i, c) in text.char_indices() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width && i > 0 {
+                end = i;
+                break;
+            }
+            used += w;
+        }
+        let (row, tail) = text.split_at(end);
+        rest = (!tail.is_empty()).then_some(tail);
+        Some(row)
+    })
+}
+
+/// `2 ahead, 1 behind origin/master`, in yellow when a push would be rejected.
+fn tracking_line(snapshot: Option<&git::RepoSnapshot>) -> Line<'static> {
+    let Some(snapshot) = snapshot else {
+        return Line::from("");
+    };
+    match snapshot.ahead_behind() {
+        Some(tracking) => {
+            let color = if tracking.behind > 0 {
+                Color::Yellow
+            } else {
+                Color::Green
+            };
+            Line::from(Span::styled(
+                tracking.to_string(),
+                Style::default().fg(color),
+            ))
+        }
+        None => Line::from(Span::styled(
+            "No upstream yet: Push branch sets origin/<branch>.",
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+}
+
 fn draw_push_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     let cols = Layout::default()
         .direction(Direction::Horizontal)
@@ -341,7 +714,11 @@ fn draw_push_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
 
     let left = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Length(7), Constraint::Min(1)])
+        .constraints([
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Min(1),
+        ])
         .split(cols[0]);
 
     let info_block = Block::default()
@@ -354,6 +731,7 @@ fn draw_push_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
             "Push branch and/or tags to remote.",
             Style::default().fg(Color::White),
         )),
+        tracking_line(app.snapshot.as_ref()),
         Line::from(""),
         Line::from(Span::styled(
             "Tip: pushing v* tags triggers the Release workflow.",
@@ -375,38 +753,70 @@ fn draw_push_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     render_actions_list(f, app, left[1]);
     render_log_panel(f, app, left[2]);
 
-    let details_block = Block::default()
-        .title(" Notes ")
+    render_tags_panel(f, app, cols[1]);
+}
+
+/// The Push tab's Tags list: newest first, with origin's copy once `r` checked it.
+fn render_tags_panel(f: &mut Frame<'_>, app: &App, area: Rect) {
+    let focused = app.focus == Focus::RightPane;
+    let border_style = if focused {
+        Style::default().fg(Color::White)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let block = Block::default()
+        .title(" Tags ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
+        .border_style(border_style);
 
-    let details = Paragraph::new(Text::from(vec![
-        Line::from(Span::styled(
-            "Push branch:",
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(Span::styled(
-            "  - pushes current branch (sets upstream if missing)",
-            Style::default().fg(Color::DarkGray),
-        )),
+    let hint = if focused {
+        "↑/↓ select · p push · s show · d delete · D delete on origin too · r check origin"
+    } else {
+        "Tab here to push, show or delete tags."
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
         Line::from(""),
-        Line::from(Span::styled(
-            "Push all tags:",
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(Span::styled(
-            "  - runs git push --tags (may trigger releases)",
+    ];
+    if app.tags.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No tags yet.",
             Style::default().fg(Color::DarkGray),
-        )),
-    ]))
-    .block(details_block)
-    .wrap(Wrap { trim: true });
+        )));
+    }
+    // Keep the selection in view when there are more tags than rows.
+    let rows = (area.height as usize)
+        .saturating_sub(2 + lines.len())
+        .max(1);
+    let first = app.tag_index.saturating_sub(rows - 1);
+    for (i, tag) in app.tags.iter().enumerate().skip(first).take(rows) {
+        let mut style = Style::default().fg(Color::White);
+        if focused && i == app.tag_index {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let (origin, origin_color) = match tag.on_remote {
+            Some(true) => ("origin", Color::Green),
+            Some(false) => ("local ", Color::Yellow),
+            None => ("      ", Color::DarkGray),
+        };
+        let mut spans = vec![
+            Span::styled(format!("{:<16}", tag.name), style),
+            Span::styled(
+                format!(" {} ", tag.date),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(origin, Style::default().fg(origin_color)),
+        ];
+        if let Some(subject) = &tag.subject {
+            spans.push(Span::styled(
+                format!("  {}", subject),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
 
-    f.render_widget(details, cols[1]);
+    f.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
 }
 
 fn draw_release_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
@@ -458,16 +868,42 @@ fn draw_release_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     render_actions_list(f, app, left[1]);
     render_log_panel(f, app, left[2]);
 
+    if let Some(notes) = &app.release_notes {
+        let title = format!(
+            " Release notes {} ",
+            app.release_notes_tag.as_deref().unwrap_or_default()
+        );
+        let notes_block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray));
+        f.render_widget(
+            Paragraph::new(notes.as_str())
+                .block(notes_block)
+                .wrap(Wrap { trim: false }),
+            cols[1],
+        );
+        return;
+    }
+
     let details_block = Block::default()
         .title(" Flow ")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::DarkGray));
 
-    let details = Paragraph::new(Text::from(vec![
-        Line::from(Span::styled(
-            "1) Preflight: fmt/clippy/test (before bump)",
-            Style::default().fg(Color::DarkGray),
-        )),
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut flow = vec![Line::from(Span::styled(
+        if app.preflight_checks.is_empty() {
+            "1) Preflight: no checks"
+        } else {
+            "1) Preflight (before bump):"
+        },
+        dim,
+    ))];
+    for check in &app.preflight_checks {
+        flow.push(Line::from(Span::styled(format!("   {}", check), dim)));
+    }
+    flow.extend([
         Line::from(Span::styled(
             "2) Bump Cargo.toml + lockfile, stage + commit",
             Style::default().fg(Color::DarkGray),
@@ -480,9 +916,10 @@ fn draw_release_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
             "4) CI builds release assets + publishes to crates.io",
             Style::default().fg(Color::DarkGray),
         )),
-    ]))
-    .block(details_block)
-    .wrap(Wrap { trim: true });
+    ]);
+    let details = Paragraph::new(Text::from(flow))
+        .block(details_block)
+        .wrap(Wrap { trim: false });
 
     f.render_widget(details, cols[1]);
 }
@@ -495,7 +932,11 @@ fn draw_config_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
 
     let left = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(9), Constraint::Length(7), Constraint::Min(1)])
+        .constraints([
+            Constraint::Length(11),
+            Constraint::Length(8),
+            Constraint::Min(1),
+        ])
         .split(cols[0]);
 
     let info_block = Block::default()
@@ -512,6 +953,22 @@ fn draw_config_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
             Span::styled("Model:    ", Style::default().fg(Color::DarkGray)),
             Span::styled(&app.model_label, Style::default().fg(Color::White)),
         ]),
+        Line::from(vec![
+            Span::styled("Endpoint: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(&app.endpoint_label, Style::default().fg(Color::White)),
+        ]),
+        Line::from(vec![
+            Span::styled("Style:    ", Style::default().fg(Color::DarkGray)),
+            Span::styled(&app.commit_style_label, Style::default().fg(Color::White)),
+        ]),
+        Line::from(vec![
+            Span::styled("Language: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(&app.language_label, Style::default().fg(Color::White)),
+        ]),
+        Line::from(vec![
+            Span::styled("Prompt:   ", Style::default().fg(Color::DarkGray)),
+            Span::styled(&app.prompt_label, Style::default().fg(Color::White)),
+        ]),
         Line::from(""),
         Line::from(Span::styled(
             "Run setup wizard to configure provider + API key.",
@@ -562,7 +1019,49 @@ fn draw_config_tab(f: &mut Frame<'_>, app: &mut App, area: Rect) {
     .block(details_block)
     .wrap(Wrap { trim: true });
 
-    f.render_widget(details, cols[1]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(8), Constraint::Min(1)])
+        .split(cols[1]);
+
+    f.render_widget(details, right[0]);
+    render_performance(f, app, right[1]);
+}
+
+/// p50/p95 latency per model from the local usage ledger.
+fn render_performance(f: &mut Frame<'_>, app: &App, area: Rect) {
+    let block = Block::default()
+        .title(format!(
+            " Performance (last {} calls per model) ",
+            usage::DEFAULT_WINDOW
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+
+    let mut lines = vec![Line::from(Span::styled(
+        format!("{:<28} {:>5} {:>9} {:>9}", "model", "calls", "p50", "p95"),
+        Style::default().fg(Color::DarkGray),
+    ))];
+    if app.latency_stats.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No generations recorded yet.",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for s in &app.latency_stats {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{:<28} {:>5} {:>9} {:>9}",
+                truncate_to_width(&s.model, 28),
+                s.calls,
+                usage::format_ms(s.p50_ms),
+                usage::format_ms(s.p95_ms)
+            ),
+            Style::default().fg(Color::White),
+        )));
+    }
+
+    f.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
 }
 
 fn render_actions_list(f: &mut Frame<'_>, app: &App, area: Rect) {
@@ -592,8 +1091,14 @@ fn render_actions_list(f: &mut Frame<'_>, app: &App, area: Rect) {
                 Style::default().fg(Color::White)
             };
 
+            let last_used = if Some(*item) == app.last_release_action {
+                " (last used)"
+            } else {
+                ""
+            };
+
             ListItem::new(Line::from(Span::styled(
-                format!("{}{}", prefix, item.label()),
+                format!("{}{}{}", prefix, item.label(), last_used),
                 style,
             )))
         })
@@ -800,6 +1305,38 @@ fn draw_help_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
                         Style::default().fg(Color::DarkGray),
                     ),
                 ]),
+                Line::from(vec![
+                    Span::styled("Hint:     ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("h", Style::default().fg(Color::White)),
+                    Span::styled(
+                        " generate with a hint (kept for later generations)",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Refine:   ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("r", Style::default().fg(Color::White)),
+                    Span::styled(
+                        " rewrite the message with your feedback",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Issues:   ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("i", Style::default().fg(Color::White)),
+                    Span::styled(
+                        " link issues as Closes/Refs footers",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Branch:   ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("b", Style::default().fg(Color::White)),
+                    Span::styled(
+                        " switch branch, or n in the list for a new one",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]),
                 Line::from(vec![
                     Span::styled("Clear:    ", Style::default().fg(Color::DarkGray)),
                     Span::styled("c", Style::default().fg(Color::White)),
@@ -808,6 +1345,14 @@ fn draw_help_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
                         Style::default().fg(Color::DarkGray),
                     ),
                 ]),
+                Line::from(vec![
+                    Span::styled("Amend:    ", Style::default().fg(Color::DarkGray)),
+                    Span::styled("Actions", Style::default().fg(Color::White)),
+                    Span::styled(
+                        " → Amend last commit; Enter amends, Exit amend mode returns",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]),
                 Line::from(""),
                 Line::from(Span::styled(
                     "Tip: When the editor is focused, arrow keys move the cursor.",
@@ -843,7 +1388,14 @@ fn draw_help_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
 fn draw_app_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
     // Centered modal (slightly smaller than help)
     let width = (area.width as f32 * 0.55) as u16;
-    let height = (area.height as f32 * 0.35) as u16;
+    // Grow for multi-line messages (e.g. the commit review), within the screen.
+    let mut message_lines = app.modal.message.lines().count() as u16;
+    if app.modal.kind == ModalKind::BranchPicker {
+        message_lines += app.branch_picker.len() as u16 + 1;
+    }
+    let height = ((area.height as f32 * 0.35) as u16)
+        .max(message_lines + 5)
+        .min(area.height);
 
     let x = area.x + (area.width.saturating_sub(width)) / 2;
     let y = area.y + (area.height.saturating_sub(height)) / 2;
@@ -870,21 +1422,28 @@ fn draw_app_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
 
     match app.modal.kind {
         ModalKind::Confirm => {
-            let lines = vec![
-                Line::from(Span::styled(
-                    &app.modal.message,
-                    Style::default().fg(Color::White),
-                )),
-                Line::from(""),
-                Line::from(Span::styled(
-                    "Enter: confirm   Esc: cancel",
-                    Style::default().fg(Color::DarkGray),
-                )),
-            ];
+            let mut lines: Vec<Line> = app
+                .modal
+                .message
+                .lines()
+                .map(|l| {
+                    let color = if l.trim_start().starts_with("! ") {
+                        Color::Yellow
+                    } else {
+                        Color::White
+                    };
+                    Line::from(Span::styled(l, Style::default().fg(color)))
+                })
+                .collect();
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "Enter: confirm   Esc: cancel",
+                Style::default().fg(Color::DarkGray),
+            )));
 
             let p = Paragraph::new(lines)
                 .block(border)
-                .wrap(Wrap { trim: true })
+                .wrap(Wrap { trim: false })
                 .style(Style::default().fg(Color::White).bg(Color::Black));
 
             f.render_widget(p, modal);
@@ -915,6 +1474,44 @@ fn draw_app_modal(f: &mut Frame<'_>, app: &App, area: Rect) {
 
             f.render_widget(p, modal);
         }
+        ModalKind::BranchPicker => {
+            let mut lines = vec![
+                Line::from(Span::styled(
+                    &app.modal.message,
+                    Style::default().fg(Color::DarkGray),
+                )),
+                Line::from(""),
+            ];
+            // Keep the selection in view when there are more branches than rows.
+            let rows = (modal.height as usize)
+                .saturating_sub(2 + lines.len())
+                .max(1);
+            let first = app.branch_picker_index.saturating_sub(rows - 1);
+            for (i, branch) in app.branch_picker.iter().enumerate().skip(first).take(rows) {
+                let mut style = Style::default().fg(Color::White);
+                if branch.current {
+                    style = style.fg(Color::Green).add_modifier(Modifier::BOLD);
+                }
+                if i == app.branch_picker_index {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                let marker = if branch.current { "* " } else { "  " };
+                let mut spans = vec![Span::styled(format!("{}{}", marker, branch.name), style)];
+                if let Some(upstream) = &branch.upstream {
+                    spans.push(Span::styled(
+                        format!("  {}", upstream),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                lines.push(Line::from(spans));
+            }
+
+            let p = Paragraph::new(lines)
+                .block(border)
+                .style(Style::default().fg(Color::White).bg(Color::Black));
+
+            f.render_widget(p, modal);
+        }
         ModalKind::None => {}
     }
 }
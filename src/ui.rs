@@ -0,0 +1,414 @@
+//! Small presentation helpers for the classic (non-TUI) CLI.
+//!
+//! Everything here prints through `cliclack` so output lines up with the prompts.
+
+use anyhow::Result;
+use cliclack::{intro, log, spinner, ProgressBar};
+use colored::*;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::generator::OnToken;
+use crate::http;
+use crate::message;
+use crate::notify;
+use crate::read_only::ReadOnly;
+use crate::review::CommitReview;
+
+/// Characters of a streaming reply shown next to the spinner label.
+const STREAM_PREVIEW_CHARS: usize = 60;
+
+/// Narrowest terminal that still gets the boxed commit preview.
+const BOXED_MIN_WIDTH: usize = 60;
+
+/// Width assumed when stdout is not a terminal.
+const FALLBACK_WIDTH: usize = 80;
+
+/// Set by `--compact`: always use the compact preview.
+static FORCE_COMPACT: AtomicBool = AtomicBool::new(false);
+
+/// Exit code after Ctrl+C (128 + SIGINT), as shells report it.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// The spinner `with_spinner` is showing, so the Ctrl+C handler can stop it.
+static ACTIVE_SPINNER: Mutex<Option<(ProgressBar, String)>> = Mutex::new(None);
+
+/// How `print_commit_preview` lays out the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewStyle {
+    /// Light box with two-space indent (wide terminals).
+    Boxed,
+    /// `» subject`, indented body wrapped to the terminal, dimmed trailers.
+    Compact,
+}
+
+impl PreviewStyle {
+    pub fn for_width(width: usize, force_compact: bool) -> Self {
+        if force_compact || width < BOXED_MIN_WIDTH {
+            PreviewStyle::Compact
+        } else {
+            PreviewStyle::Boxed
+        }
+    }
+}
+
+pub fn set_compact(compact: bool) {
+    FORCE_COMPACT.store(compact, Ordering::Relaxed);
+}
+
+pub fn print_banner() {
+    let _ = intro(" git-wiz ".on_magenta().black().bold());
+}
+
+pub fn print_info(message: impl Display) {
+    let _ = log::info(message);
+}
+
+pub fn print_success(message: impl Display) {
+    let _ = log::success(message);
+}
+
+pub fn print_warning(message: impl Display) {
+    let _ = log::warning(message);
+}
+
+/// Print an error with its whole `.context(...)` chain: the outermost message first,
+/// then one dimmed `caused by:` line per underlying error.
+pub fn print_error(err: &anyhow::Error) {
+    // Blocked by --read-only: expected, not a failure.
+    if let Some(blocked) = err.downcast_ref::<ReadOnly>() {
+        print_warning(blocked);
+        return;
+    }
+    let _ = log::error(error_lines(err).join("\n"));
+}
+
+fn error_lines(err: &anyhow::Error) -> Vec<String> {
+    let mut lines = vec![err.to_string().red().bold().to_string()];
+    lines.extend(
+        err.chain()
+            .skip(1)
+            .map(|cause| format!("{} {}", "caused by:".dimmed(), cause.to_string().dimmed())),
+    );
+    lines
+}
+
+/// Print the commit message so it stands out from prompts.
+///
+/// Boxed on wide terminals; compact on narrow ones or with `--compact`.
+pub fn print_commit_preview(message: &str) {
+    let width = terminal_width();
+    let style = PreviewStyle::for_width(width, FORCE_COMPACT.load(Ordering::Relaxed));
+    for line in render_commit_preview(message, style, width) {
+        println!("{}", line);
+    }
+}
+
+fn terminal_width() -> usize {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, cols)| cols as usize)
+        .unwrap_or(FALLBACK_WIDTH)
+}
+
+/// Render the commit preview as output lines for a terminal `width` columns wide.
+pub fn render_commit_preview(message: &str, style: PreviewStyle, width: usize) -> Vec<String> {
+    match style {
+        PreviewStyle::Boxed => render_boxed(message),
+        PreviewStyle::Compact => render_compact(message, width),
+    }
+}
+
+fn render_boxed(message: &str) -> Vec<String> {
+    let width = message
+        .lines()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(20, 72);
+    let rule = "─".repeat(width + 2);
+
+    let lines: Vec<&str> = message.lines().collect();
+    let trailers_from = 1 + message::trailer_block_start(lines.get(1..).unwrap_or_default());
+
+    let mut out = vec![format!("  ┌{}", rule).dimmed().to_string()];
+    for (i, line) in lines.iter().enumerate() {
+        let text = if i == 0 {
+            line.bold().to_string()
+        } else if i >= trailers_from {
+            line.dimmed().to_string()
+        } else {
+            line.to_string()
+        };
+        out.push(format!("{} {}", "  │".dimmed(), text));
+    }
+    out.push(format!("  └{}", rule).dimmed().to_string());
+    out
+}
+
+fn render_compact(message: &str, width: usize) -> Vec<String> {
+    const INDENT: &str = "  ";
+    // Keep one spare column so terminals don't auto-wrap at the edge.
+    let wrap_at = width.saturating_sub(INDENT.len() + 1).max(20);
+
+    let mut lines = message.lines();
+    let mut out = vec![format!("» {}", lines.next().unwrap_or_default().bold())];
+
+    let body: Vec<&str> = lines.collect();
+    let trailers_from = message::trailer_block_start(&body);
+
+    for (i, line) in body.iter().enumerate() {
+        if i >= trailers_from {
+            out.push(format!("{}{}", INDENT, line).dimmed().to_string());
+        } else if line.trim().is_empty() {
+            out.push(String::new());
+        } else {
+            out.extend(
+                wrap(line, wrap_at)
+                    .into_iter()
+                    .map(|l| format!("{}{}", INDENT, l)),
+            );
+        }
+    }
+    out
+}
+
+/// Greedy word wrap; words longer than `width` are left on their own line.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let needed =
+            current.chars().count() + word.chars().count() + usize::from(!current.is_empty());
+        if !current.is_empty() && needed > width {
+            out.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || out.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+/// Print the pre-commit review; warning flags are highlighted.
+pub fn print_commit_review(review: &CommitReview) {
+    for line in review.lines() {
+        if line.trim_start().starts_with("! ") {
+            println!("  {}", line.yellow());
+        } else {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// Run `f` while showing a spinner with `label`; the spinner stops with success/error.
+///
+/// Long runs end with a notification when `notify` is enabled.
+pub fn with_spinner<T>(label: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    with_streaming_spinner(label, |_| f())
+}
+
+/// `with_spinner` for a streamed reply: `f` gets an `OnToken` callback that shows the end
+/// of the reply so far next to the label.
+pub fn with_streaming_spinner<T>(
+    label: &str,
+    f: impl FnOnce(OnToken<'_>) -> Result<T>,
+) -> Result<T> {
+    let sp = Spinner::start(label);
+    let bar = sp.bar.clone();
+    let on_token = |text: &str| {
+        let line = text
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or_default()
+            .trim();
+        let count = line.chars().count();
+        let tail = if count > STREAM_PREVIEW_CHARS {
+            let skip = count - STREAM_PREVIEW_CHARS + 1;
+            format!("…{}", line.chars().skip(skip).collect::<String>())
+        } else {
+            line.to_string()
+        };
+        bar.set_message(format!("{} {}", label, tail.dimmed()));
+    };
+    // A rate-limited request counts down in place of the label.
+    let wait_bar = sp.bar.clone();
+    let started = Instant::now();
+    let result = http::with_wait_reporter(move |text| wait_bar.set_message(text), || f(&on_token));
+    sp.finish(result.is_ok());
+    let task = label.trim_end_matches('…');
+    notify::task_finished(
+        started.elapsed(),
+        &match &result {
+            Ok(_) => format!("git-wiz: {} done", task),
+            Err(_) => format!("git-wiz: {} failed", task),
+        },
+    );
+    result
+}
+
+/// A running spinner, registered for the Ctrl+C handler while it spins.
+///
+/// Dropped without `finish` (a panic inside `with_spinner`), it stops as an error. Either
+/// way the cursor is shown again, since the spinner hides it while drawing.
+struct Spinner {
+    bar: ProgressBar,
+    label: String,
+    finished: bool,
+}
+
+impl Spinner {
+    fn start(label: &str) -> Self {
+        let bar = spinner();
+        bar.start(label);
+        if let Ok(mut active) = ACTIVE_SPINNER.lock() {
+            *active = Some((bar.clone(), label.to_string()));
+        }
+        Self {
+            bar,
+            label: label.to_string(),
+            finished: false,
+        }
+    }
+
+    fn finish(mut self, ok: bool) {
+        if ok {
+            self.bar.stop(&self.label);
+        } else {
+            self.bar.error(&self.label);
+        }
+        self.finished = true;
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_SPINNER.lock() {
+            active.take();
+        }
+        if !self.finished {
+            self.bar.error(&self.label);
+        }
+        show_cursor();
+    }
+}
+
+fn show_cursor() {
+    let _ = console::Term::stderr().show_cursor();
+    let _ = console::Term::stdout().show_cursor();
+}
+
+/// Handle Ctrl+C outside of prompts (which read keys themselves): stop the active
+/// spinner, show the cursor, end the line and exit with 130.
+pub fn install_interrupt_handler() {
+    std::thread::spawn(|| {
+        let Ok(rt) = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        else {
+            return;
+        };
+        rt.block_on(async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            if let Some((bar, label)) = ACTIVE_SPINNER.lock().ok().and_then(|mut a| a.take()) {
+                bar.cancel(label);
+            }
+            show_cursor();
+            println!();
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "feat(ui): add a compact preview\n\nNarrow tmux panes wrapped the boxed preview into something unreadable, so narrow terminals now get a plain one.\n\nRefs: #42\nSigned-off-by: Jane <jane@example.com>";
+
+    fn render(style: PreviewStyle, width: usize) -> Vec<String> {
+        colored::control::set_override(false);
+        render_commit_preview(MESSAGE, style, width)
+    }
+
+    #[test]
+    fn narrow_terminals_get_the_compact_preview() {
+        assert_eq!(PreviewStyle::for_width(120, false), PreviewStyle::Boxed);
+        assert_eq!(
+            PreviewStyle::for_width(BOXED_MIN_WIDTH, false),
+            PreviewStyle::Boxed
+        );
+        assert_eq!(
+            PreviewStyle::for_width(BOXED_MIN_WIDTH - 1, false),
+            PreviewStyle::Compact
+        );
+        assert_eq!(PreviewStyle::for_width(120, true), PreviewStyle::Compact);
+    }
+
+    #[test]
+    fn boxed_preview() {
+        let rule = "─".repeat(74);
+        let message_lines: Vec<String> = MESSAGE.lines().map(|l| format!("  │ {}", l)).collect();
+        let expected: Vec<String> = [format!("  ┌{}", rule)]
+            .into_iter()
+            .chain(message_lines)
+            .chain([format!("  └{}", rule)])
+            .collect();
+        assert_eq!(render(PreviewStyle::Boxed, 120), expected);
+    }
+
+    #[test]
+    fn compact_preview_wraps_the_body_only() {
+        assert_eq!(
+            render(PreviewStyle::Compact, 40),
+            [
+                "» feat(ui): add a compact preview",
+                "",
+                "  Narrow tmux panes wrapped the boxed",
+                "  preview into something unreadable, so",
+                "  narrow terminals now get a plain one.",
+                "",
+                "  Refs: #42",
+                "  Signed-off-by: Jane <jane@example.com>",
+            ]
+        );
+    }
+
+    #[test]
+    fn compact_preview_never_wraps_narrower_than_twenty_columns() {
+        let lines = render(PreviewStyle::Compact, 8);
+        assert!(lines[2..5].iter().all(|l| l.chars().count() <= 22));
+        assert_eq!(lines[2], "  Narrow tmux panes");
+    }
+
+    #[test]
+    fn error_chain_lists_every_level_once_in_order() {
+        use anyhow::Context;
+
+        colored::control::set_override(false);
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "config.json is missing");
+        let err = Err::<(), _>(io)
+            .context("Failed to read the config")
+            .context("Setup failed")
+            .unwrap_err();
+        assert_eq!(
+            error_lines(&err),
+            [
+                "Setup failed",
+                "caused by: Failed to read the config",
+                "caused by: config.json is missing",
+            ]
+        );
+        // A plain error has no causes to list.
+        assert_eq!(error_lines(&anyhow::anyhow!("just this")), ["just this"]);
+    }
+}
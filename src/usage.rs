@@ -0,0 +1,135 @@
+//! Local usage ledger: one JSON line per generation, stored next to `config.json`.
+//!
+//! Used for latency statistics (`git-wiz usage --latency`, TUI Config tab).
+//! Recording is best-effort and never fails a generation.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::read_only;
+
+/// Default number of recent calls per model used for statistics.
+pub const DEFAULT_WINDOW: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageEntry {
+    /// Unix timestamp (seconds).
+    pub timestamp: u64,
+    pub provider: String,
+    pub model: String,
+    /// Wall-clock time of the HTTP call only (not diff collection).
+    pub latency_ms: u64,
+    /// Size of the system + user prompt sent.
+    pub prompt_bytes: usize,
+}
+
+impl UsageEntry {
+    pub fn new(provider: String, model: String, latency_ms: u64, prompt_bytes: usize) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            provider,
+            model,
+            latency_ms,
+            prompt_bytes,
+        }
+    }
+}
+
+/// Latency percentiles for one provider/model pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub provider: String,
+    pub model: String,
+    pub calls: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+pub fn ledger_path() -> Result<PathBuf> {
+    let mut path = dirs::config_dir().context("Could not determine config directory")?;
+    path.push("git-wiz");
+    path.push("usage.jsonl");
+    Ok(path)
+}
+
+/// Append one entry to the ledger.
+pub fn record(entry: &UsageEntry) -> Result<()> {
+    read_only::check("Recording usage")?;
+    let path = ledger_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Load all entries (oldest first). Malformed lines are skipped.
+pub fn load() -> Result<Vec<UsageEntry>> {
+    let path = ledger_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// p50/p95 latency per provider/model over the last `window` calls of each,
+/// fastest median first.
+pub fn latency_stats(entries: &[UsageEntry], window: usize) -> Vec<LatencyStats> {
+    let mut groups: Vec<((&str, &str), Vec<u64>)> = Vec::new();
+    for e in entries.iter().rev() {
+        let key = (e.provider.as_str(), e.model.as_str());
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, latencies)) if latencies.len() < window => latencies.push(e.latency_ms),
+            Some(_) => {}
+            None => groups.push((key, vec![e.latency_ms])),
+        }
+    }
+
+    let mut stats: Vec<LatencyStats> = groups
+        .into_iter()
+        .map(|((provider, model), mut latencies)| {
+            latencies.sort_unstable();
+            LatencyStats {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                calls: latencies.len(),
+                p50_ms: percentile(&latencies, 50),
+                p95_ms: percentile(&latencies, 95),
+            }
+        })
+        .collect();
+    stats.sort_by_key(|s| s.p50_ms);
+    stats
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Human-friendly milliseconds, e.g. `840 ms` or `2.3 s`.
+pub fn format_ms(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    }
+}